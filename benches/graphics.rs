@@ -0,0 +1,55 @@
+#![feature(test)]
+extern crate crayon;
+extern crate test;
+
+use test::Bencher;
+use crayon::prelude::*;
+use crayon::graphics::MeshSetup;
+
+const NUM: usize = 10_000;
+
+fn create(video: &GraphicsSystemShared) -> Vec<MeshHandle> {
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 3;
+
+    (0..NUM)
+        .map(|_| {
+            video
+                .create_mesh(Location::unique(""), setup.clone(), empty, empty)
+                .unwrap()
+        })
+        .collect()
+}
+
+#[bench]
+fn bench_delete_meshes(b: &mut Bencher) {
+    let resource = ResourceSystem::new().unwrap();
+    let mut video = GraphicsSystem::headless(resource.shared(), (320, 240));
+    let shared = video.shared();
+
+    b.iter(|| {
+        let meshes = create(&shared);
+        for &v in &meshes {
+            shared.delete_mesh(v);
+        }
+
+        video.swap_frames();
+        video.advance().unwrap();
+    });
+}
+
+#[bench]
+fn bench_delete_meshes_batch(b: &mut Bencher) {
+    let resource = ResourceSystem::new().unwrap();
+    let mut video = GraphicsSystem::headless(resource.shared(), (320, 240));
+    let shared = video.shared();
+
+    b.iter(|| {
+        let meshes = create(&shared);
+        shared.delete_meshes(&meshes);
+
+        video.swap_frames();
+        video.advance().unwrap();
+    });
+}
@@ -0,0 +1,10 @@
+#version 100
+precision lowp float;
+
+varying vec2 v_Texcoord;
+
+uniform sampler2D renderedTexture;
+
+void main() {
+    gl_FragColor = texture2D(renderedTexture, v_Texcoord);
+}
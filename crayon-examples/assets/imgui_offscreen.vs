@@ -0,0 +1,12 @@
+#version 100
+precision lowp float;
+
+attribute vec2 Position;
+varying vec2 v_Texcoord;
+
+uniform mat4 u_MVPMatrix;
+
+void main(){
+    gl_Position = u_MVPMatrix * vec4(Position, 0.0, 1.0);
+    v_Texcoord = (Position + vec2(1.0, 1.0)) / 2.0;
+}
@@ -0,0 +1,167 @@
+use crayon::prelude::*;
+use crayon::math::Rotation3;
+use crayon_imgui::prelude::*;
+use utils::*;
+
+struct Window {
+    surface: SurfaceHandle,
+    scene: Scene,
+    console: ConsoleCanvas,
+
+    camera: Entity,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl Window {
+    fn new(engine: &mut Engine) -> errors::Result<Self> {
+        let ctx = engine.context();
+        let video = ctx.shared::<GraphicsSystem>().clone();
+
+        // Create the view state.
+        let mut setup = graphics::SurfaceSetup::default();
+        setup.set_clear(Color::gray(), 1.0, None);
+        let surface = video.create_surface(setup)?;
+
+        // Create scene.
+        let mut scene = Scene::new(&ctx)?;
+
+        let camera = {
+            let c = Camera::perspective(math::Deg(60.0), 6.4 / 4.8, 0.1, 1000.0);
+            scene.create_node(c)
+        };
+
+        Window::create_pillars(&mut scene, &video)?;
+
+        {
+            let tree = scene.arena::<Node>();
+            let mut transforms = scene.arena_mut::<Transform>();
+            Transform::set_world_position(&tree, &mut transforms, camera, [0.0, 10.0, -100.0])?;
+        }
+
+        // Captures the cursor, it will be released when the window lost focus.
+        ctx.shared::<InputSystem>().set_relative_mouse_mode(true);
+
+        Ok(Window {
+            console: ConsoleCanvas::new(1, ctx)?,
+            surface: surface,
+            scene: scene,
+            camera: camera,
+            yaw: 0.0,
+            pitch: 0.0,
+        })
+    }
+
+    fn create_pillars(scene: &mut Scene, video: &GraphicsSystemShared) -> errors::Result<()> {
+        let shader = scene::factory::shader::color(&video)?;
+        let mesh = scene::factory::mesh::cube(&video)?;
+        let colors = [Color::red(), Color::blue(), Color::green(), Color::cyan()];
+
+        for i in 0..8 {
+            for j in 0..8 {
+                let mat = scene.create_material(shader)?;
+                let color: [f32; 4] = colors[(i + j) % colors.len()].into();
+                scene.update_material_uniform(mat, "u_Color", color)?;
+
+                let pillar = scene.create_node(MeshRenderer {
+                    mesh: mesh,
+                    index: MeshIndex::All,
+                    materials: vec![mat],
+                });
+
+                let position = [(i as f32 - 3.5) * 40.0, 0.0, (j as f32 - 3.5) * 40.0];
+                unsafe {
+                    let mut transforms = scene.arena_mut::<Transform>();
+                    transforms.get_unchecked_mut(pillar).set_scale(10.0);
+                    transforms.get_unchecked_mut(pillar).set_position(position);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Application for Window {
+    fn on_update(&mut self, ctx: &Context) -> errors::Result<()> {
+        {
+            let canvas = self.console.render(&ctx)?;
+            canvas
+                .window(im_str!("Controls"))
+                .movable(false)
+                .resizable(false)
+                .title_bar(false)
+                .position((0.0, 70.0), ImGuiCond::FirstUseEver)
+                .size((250.0, 50.0), ImGuiCond::FirstUseEver)
+                .build(|| {
+                    canvas.text(im_str!("WASD to move, mouse to look around."));
+                    canvas.text(im_str!("Esc to release, left click to capture."));
+                });
+        }
+
+        let input = ctx.shared::<InputSystem>();
+
+        if input.is_key_press(event::KeyboardButton::Escape) {
+            input.set_relative_mouse_mode(false);
+        }
+
+        if input.is_mouse_press(event::MouseButton::Left) {
+            input.set_relative_mouse_mode(true);
+        }
+
+        let duration = ctx.shared::<TimeSystem>().frame_delta();
+        let dt = duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0;
+
+        if input.is_relative_mouse_mode() {
+            let movement = input.mouse_movement();
+            self.yaw += movement.x * 0.2;
+            self.pitch = (self.pitch + movement.y * 0.2).max(-89.0).min(89.0);
+        }
+
+        let rotation = math::Quaternion::from_angle_y(math::Deg(self.yaw))
+            * math::Quaternion::from_angle_x(math::Deg(self.pitch));
+
+        let mut disp = math::Vector3::<f32>::new(0.0, 0.0, 0.0);
+        if input.is_key_down(event::KeyboardButton::W) {
+            disp.z += 1.0;
+        }
+
+        if input.is_key_down(event::KeyboardButton::S) {
+            disp.z -= 1.0;
+        }
+
+        if input.is_key_down(event::KeyboardButton::D) {
+            disp.x += 1.0;
+        }
+
+        if input.is_key_down(event::KeyboardButton::A) {
+            disp.x -= 1.0;
+        }
+
+        unsafe {
+            let mut transforms = self.scene.arena_mut::<Transform>();
+            let transform = transforms.get_unchecked_mut(self.camera);
+            transform.set_rotation(rotation);
+            transform.translate(rotation * disp * 100.0 * dt);
+        }
+
+        self.scene.render(self.surface, self.camera)?;
+        Ok(())
+    }
+
+    fn on_post_update(&mut self, _: &Context, info: &FrameInfo) -> errors::Result<()> {
+        self.console.update(info);
+        Ok(())
+    }
+}
+
+pub fn main(title: String, _: &[String]) {
+    let mut settings = Settings::default();
+    settings.window.width = 640;
+    settings.window.height = 480;
+    settings.window.title = title;
+
+    let mut engine = Engine::new_with(settings).unwrap();
+    let window = Window::new(&mut engine).unwrap();
+    engine.run(window).unwrap();
+}
@@ -12,9 +12,15 @@ struct Window {
 impl Window {
     fn new(engine: &mut Engine) -> errors::Result<Self> {
         let ctx = engine.context();
-        let canvas = Canvas::new(&ctx).unwrap();
+        // Smooths the edges of UI with 4x MSAA, the internal surface of canvas is
+        // drawn before the presenting surface.
+        let mut setup = CanvasSetup::default();
+        setup.samples = 4;
+        setup.order = 0;
+        let canvas = Canvas::new(&ctx, setup).unwrap();
 
         let mut setup = graphics::SurfaceSetup::default();
+        setup.set_order(1);
         setup.set_clear(Color::white(), None, None);
         setup.set_sequence(true);
         let surface = ctx.shared::<GraphicsSystem>().create_surface(setup)?;
@@ -29,7 +35,7 @@ impl Window {
 
 impl Application for Window {
     fn on_update(&mut self, ctx: &Context) -> errors::Result<()> {
-        let ui = self.canvas.frame(self.surface, &ctx);
+        let ui = self.canvas.frame(self.surface, &ctx)?;
         let info = self.info;
         ui.window(im_str!("ImGui & Crayon"))
             .movable(false)
@@ -0,0 +1,142 @@
+use crayon::prelude::*;
+use crayon_imgui::prelude::*;
+
+impl_vertex!{
+    Vertex {
+        position => [Position; Float; 2; false],
+    }
+}
+
+const DIMENSIONS: (u32, u32) = (512, 512);
+
+struct Window {
+    _label: graphics::RAIIGuard,
+    canvas: Canvas,
+    offscreen: graphics::SurfaceHandle,
+    surface: graphics::SurfaceHandle,
+    shader: graphics::ShaderHandle,
+    mesh: graphics::MeshHandle,
+    texture: graphics::TextureHandle,
+    time: f32,
+}
+
+impl Window {
+    pub fn new(engine: &mut Engine) -> errors::Result<Self> {
+        let ctx = engine.context();
+        let video = ctx.shared::<GraphicsSystem>().clone();
+        let mut label = graphics::RAIIGuard::new(video);
+
+        // Create the render texture that ImGui is drawn into.
+        let mut setup = graphics::RenderTextureSetup::default();
+        setup.format = graphics::RenderTextureFormat::RGBA8;
+        setup.dimensions = DIMENSIONS;
+        let texture = label.create_render_texture(setup)?;
+
+        let mut setup = graphics::FrameBufferSetup::default();
+        setup.set_attachment(texture, 0)?;
+        let fbo = label.create_framebuffer(setup)?;
+
+        let mut setup = graphics::SurfaceSetup::default();
+        setup.set_order(0);
+        setup.set_sequence(true);
+        setup.set_framebuffer(fbo);
+        setup.set_clear(Color::gray(), None, None);
+        let offscreen = label.create_surface(setup)?;
+
+        // Create the quad that samples the render texture.
+        let verts: [Vertex; 4] = [
+            Vertex::new([-1.0, -1.0]),
+            Vertex::new([1.0, -1.0]),
+            Vertex::new([1.0, 1.0]),
+            Vertex::new([-1.0, 1.0]),
+        ];
+        let idxes: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+        let mut setup = graphics::MeshSetup::default();
+        setup.num_verts = 4;
+        setup.num_idxes = 6;
+        setup.layout = Vertex::layout();
+
+        let mesh = label.create_mesh(
+            Location::unique(""),
+            setup,
+            Vertex::as_bytes(&verts[..]),
+            graphics::IndexFormat::as_bytes(&idxes),
+        )?;
+
+        let mut setup = graphics::SurfaceSetup::default();
+        setup.set_order(1);
+        setup.set_clear(Color::white(), 1.0, None);
+        let surface = label.create_surface(setup)?;
+
+        let mut setup = graphics::ShaderSetup::default();
+        setup.layout = graphics::AttributeLayoutBuilder::new()
+            .with(graphics::Attribute::Position, 2)
+            .finish();
+        setup.vs = include_str!("../../assets/imgui_offscreen.vs").to_owned();
+        setup.fs = include_str!("../../assets/imgui_offscreen.fs").to_owned();
+        let tt = graphics::UniformVariableType::Texture;
+        setup.uniform_variables.insert("renderedTexture".into(), tt);
+        let tt = graphics::UniformVariableType::Matrix4f;
+        setup.uniform_variables.insert("u_MVPMatrix".into(), tt);
+        let shader = label.create_shader(Location::unique(""), setup)?;
+
+        Ok(Window {
+            _label: label,
+            canvas: Canvas::new(&ctx, CanvasSetup::default())?,
+            offscreen: offscreen,
+            surface: surface,
+            shader: shader,
+            mesh: mesh,
+            texture: texture,
+            time: 0.0,
+        })
+    }
+}
+
+impl Application for Window {
+    fn on_update(&mut self, ctx: &Context) -> errors::Result<()> {
+        {
+            // The UI is laid out and clipped with the dimensions of render texture.
+            let ui = self.canvas.frame_to(self.offscreen, DIMENSIONS, &ctx)?;
+            let time = self.time;
+            ui.window(im_str!("Offscreen ImGui"))
+                .position((32.0, 32.0), ImGuiCond::FirstUseEver)
+                .size((448.0, 448.0), ImGuiCond::FirstUseEver)
+                .build(|| {
+                    ui.text(im_str!("Rendered into a texture."));
+                    ui.text(im_str!("Time: {:.2}s", time));
+                    ui.separator();
+                    ui.text(im_str!("And mapped onto a spinning quad."));
+                });
+        }
+
+        let video = ctx.shared::<GraphicsSystem>();
+        let (w, h) = video.dimensions();
+
+        let projection = math::perspective(math::Deg(60.0), w as f32 / h as f32, 0.1, 100.0);
+        let view = math::Matrix4::from_translation(math::Vector3::new(0.0, 0.0, -3.0));
+        let model = math::Matrix4::from_angle_y(math::Rad(self.time * 0.5));
+
+        let mut dc = graphics::DrawCall::new(self.shader, self.mesh);
+        dc.set_uniform_variable("renderedTexture", self.texture);
+        dc.set_uniform_variable("u_MVPMatrix", projection * view * model);
+        let cmd = dc.build(graphics::MeshIndex::All)?;
+        video.submit(self.surface, 0u64, cmd)?;
+
+        let duration = ctx.shared::<TimeSystem>().frame_delta();
+        self.time += duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0;
+        Ok(())
+    }
+}
+
+pub fn main(title: String, _: &[String]) {
+    let mut settings = Settings::default();
+    settings.window.width = 1024;
+    settings.window.height = 768;
+    settings.window.title = title;
+
+    let mut engine = Engine::new_with(settings).unwrap();
+    let window = Window::new(&mut engine).unwrap();
+    engine.run(window).unwrap();
+}
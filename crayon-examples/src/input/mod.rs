@@ -17,7 +17,7 @@ struct Window {
 impl Window {
     fn new(engine: &mut Engine) -> errors::Result<Self> {
         let ctx = engine.context();
-        let canvas = Canvas::new(&ctx).unwrap();
+        let canvas = Canvas::new(&ctx, CanvasSetup::default()).unwrap();
 
         let mut setup = graphics::SurfaceSetup::default();
         setup.set_clear(Color::white(), None, None);
@@ -54,7 +54,7 @@ impl Application for Window {
             self.double_click_count += 1;
         }
 
-        let ui = self.canvas.frame(self.surface, &ctx);
+        let ui = self.canvas.frame(self.surface, &ctx)?;
         let info = self.info;
         let text = &self.text;
         let rc = self.repeat_count;
@@ -14,8 +14,10 @@ mod utils;
 mod texture;
 mod render_target;
 mod imgui;
+mod imgui_offscreen;
 mod input;
 mod mesh;
+mod fps_camera;
 
 const USAGE: &'static str = "";
 
@@ -36,8 +38,10 @@ fn main() {
         "texture" => texture::main(name, &args[1..]),
         "render_target" => render_target::main(name, &args[1..]),
         "imgui" => imgui::main(name, &args[1..]),
+        "imgui_offscreen" => imgui_offscreen::main(name, &args[1..]),
         "input" => input::main(name, &args[1..]),
         "mesh" => mesh::main(name, &args[1..]),
+        "fps_camera" => fps_camera::main(name, &args[1..]),
         _ => usage(),
     }
 }
@@ -9,6 +9,8 @@ struct Window {
     console: ConsoleCanvas,
 
     material: MaterialHandle,
+    render_state: graphics::RenderState,
+    wireframe: bool,
     camera: Entity,
     room: Entity,
     rotation: math::Vector3<f32>,
@@ -38,6 +40,11 @@ impl Window {
         };
 
         let (room, mat_block) = Window::create_room(&mut scene, &video)?;
+        let render_state = {
+            let shader = scene::factory::shader::phong(&video)?;
+            video.shader_state(shader).unwrap().render_state
+        };
+
         Window::create_lits(&mut scene, &video)?;
 
         let light = scene.create_node(Light::default());
@@ -63,6 +70,8 @@ impl Window {
             room: room,
             rotation: math::Vector3::new(0.0, 0.0, 0.0),
             material: mat_block,
+            render_state: render_state,
+            wireframe: false,
             ambient: [1.0, 1.0, 1.0],
             diffuse: [1.0, 1.0, 1.0],
             specular: [1.0, 1.0, 1.0],
@@ -103,7 +112,7 @@ impl Window {
             let cube = scene.create_node(MeshRenderer {
                 mesh: mesh,
                 index: MeshIndex::All,
-                material: mat,
+                materials: vec![mat],
             });
 
             unsafe {
@@ -151,7 +160,7 @@ impl Window {
             let wall = scene.create_node(MeshRenderer {
                 mesh: mesh,
                 index: MeshIndex::SubMesh(i),
-                material: mat_wall,
+                materials: vec![mat_wall],
             });
 
             let mut tree = scene.arena_mut::<Node>();
@@ -164,7 +173,7 @@ impl Window {
             let block = scene.create_node(MeshRenderer {
                 mesh: mesh,
                 index: MeshIndex::SubMesh(i),
-                material: mat_block,
+                materials: vec![mat_block],
             });
 
             let mut tree = scene.arena_mut::<Node>();
@@ -187,7 +196,7 @@ impl Application for Window {
         let specular = &mut self.specular;
 
         let capture = {
-            let canvas = self.console.render(&ctx);
+            let canvas = self.console.render(&ctx)?;
             canvas
                 .window(im_str!("Materials"))
                 .movable(false)
@@ -211,6 +220,21 @@ impl Application for Window {
 
         if !capture {
             let input = ctx.shared::<InputSystem>();
+
+            // Press `W` to switch the blocks between fill and wireframe.
+            if input.is_key_press(event::KeyboardButton::W) {
+                self.wireframe = !self.wireframe;
+
+                let mut state = self.render_state;
+                if self.wireframe {
+                    state.polygon_mode = graphics::PolygonMode::Line;
+                    state.cull_face = graphics::CullFace::Nothing;
+                }
+
+                self.scene
+                    .update_material_render_state(self.material, state)?;
+            }
+
             match input.finger_pan() {
                 input::GesturePan::Move {
                     start_position: _,
@@ -22,6 +22,7 @@ impl graphics::TextureParser for TextureParser {
         Ok(graphics::TextureData {
             format: graphics::TextureFormat::U8U8U8U8,
             dimensions: dynamic.dimensions(),
+            levels: 1,
             data: dynamic.to_rgba().into_raw(),
         })
     }
@@ -0,0 +1,10 @@
+#version 100
+precision lowp float;
+
+uniform sampler2D texture;
+
+varying vec2 v_Texcoord0;
+
+void main() {
+    gl_FragColor = texture2D(texture, v_Texcoord0);
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+#version 100
+precision lowp float;
+
+attribute vec2 Position;
+
+varying vec2 v_Texcoord0;
+
+void main()
+{
+    v_Texcoord0 = Position * 0.5 + 0.5;
+    gl_Position = vec4(Position.xy, 0.0, 1.0);
+}
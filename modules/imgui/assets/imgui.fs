@@ -7,5 +7,8 @@ varying vec4 v_Color0;
 varying vec2 v_Texcoord0;
 
 void main() {
-    gl_FragColor = v_Color0 * texture2D(texture, v_Texcoord0.xy);
+    vec4 color = v_Color0 * texture2D(texture, v_Texcoord0.xy);
+    // Outputs premultiplied color, so the alpha of render target stays meaningful
+    // when the canvas is drawn offscreen and composited later.
+    gl_FragColor = vec4(color.rgb * color.a, color.a);
 }
\ No newline at end of file
@@ -33,47 +33,139 @@ impl<'a> Drop for FrameGuard<'a> {
     }
 }
 
+/// The setup parameters of `Canvas`.
+#[derive(Debug, Copy, Clone)]
+pub struct CanvasSetup {
+    /// The number of samples of the internal surface that UI is drawn into, which
+    /// smooths the edges of text and window borders. It will be clamped to the
+    /// maximum supported samples, and zero or one means no anti-aliasing.
+    ///
+    /// With anti-aliasing, UI is drawn into an offscreen multisampled surface, and
+    /// then blended onto the surface passed to `frame` after its other drawcalls.
+    pub samples: u32,
+    /// The order of the internal multisampled surface, which should be lower than
+    /// the orders of surfaces that present the canvas.
+    pub order: u64,
+}
+
+impl Default for CanvasSetup {
+    fn default() -> Self {
+        CanvasSetup {
+            samples: 0,
+            order: 0,
+        }
+    }
+}
+
+/// The size in points of the default font.
+const FONT_SIZE: f32 = 13.0;
+
 pub struct Canvas {
     ctx: imgui::ImGui,
     renderer: Renderer,
+    scale: f32,
 }
 
 impl Canvas {
-    pub fn new(ctx: &application::Context) -> Result<Self> {
+    pub fn new(ctx: &application::Context, setup: CanvasSetup) -> Result<Self> {
         let mut imgui = imgui::ImGui::init();
         imgui.set_ini_filename(None);
 
-        let renderer = Renderer::new(ctx, &mut imgui)?;
+        let scale = ctx.content_scale();
+        Self::rasterize_fonts(&mut imgui, scale);
+        let renderer = Renderer::new(ctx, &mut imgui, setup.samples, setup.order)?;
 
         Self::bind_keycode(&mut imgui);
         Ok(Canvas {
             ctx: imgui,
             renderer: renderer,
+            scale: scale,
         })
     }
 
+    /// Starts a new frame, which will be rendered into `surface` when the returned
+    /// guard is dropped. The UI is laid out with the dimensions of window.
+    ///
+    /// The font atlas is re-rasterized if the content scale of window has changed,
+    /// and the error of uploading it is returned.
     pub fn frame<'a>(
         &'a mut self,
         surface: graphics::SurfaceHandle,
         ctx: &application::Context,
-    ) -> FrameGuard<'a> {
+    ) -> Result<FrameGuard<'a>> {
+        let v = ctx.shared::<graphics::GraphicsSystem>();
+        let (dp, d) = (v.dimensions_in_pixels(), v.dimensions());
+        self.begin(surface, d, dp, true, ctx)
+    }
+
+    /// Starts a new frame, which will be rendered into an offscreen `surface` when the
+    /// returned guard is dropped. The surface should be backed by a framebuffer whose
+    /// color attachment has `dimensions` in pixels, e.g. a render texture that will be
+    /// sampled by other shaders.
+    ///
+    /// The UI is laid out, and clipped with the dimensions of render target instead of
+    /// window. Since the mapping from window to render target is unknown, e.g. an
+    /// in-world panel, the mouse position is not forwarded to the offscreen frame.
+    pub fn frame_to<'a>(
+        &'a mut self,
+        surface: graphics::SurfaceHandle,
+        dimensions: (u32, u32),
+        ctx: &application::Context,
+    ) -> Result<FrameGuard<'a>> {
+        self.begin(surface, dimensions, dimensions, false, ctx)
+    }
+
+    fn begin<'a>(
+        &'a mut self,
+        surface: graphics::SurfaceHandle,
+        d: (u32, u32),
+        dp: (u32, u32),
+        mouse: bool,
+        ctx: &application::Context,
+    ) -> Result<FrameGuard<'a>> {
+        self.set_scale(ctx.content_scale())?;
+
         // Update input device states.
         let input = ctx.shared::<input::InputSystem>();
-        Self::update_mouse_state(&mut self.ctx, &input);
+        Self::update_mouse_state(&mut self.ctx, &input, mouse);
         Self::update_keycode_state(&mut self.ctx, &input);
+        Self::update_text_input(&mut self.ctx, &input);
 
         // Generates frame builder.
-        let v = ctx.shared::<graphics::GraphicsSystem>();
         let duration = ctx.shared::<application::TimeSystem>().frame_delta();
         let ts = duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0;
 
-        let (dp, d) = (v.dimensions_in_pixels(), v.dimensions());
-
-        FrameGuard {
+        Ok(FrameGuard {
             renderer: &mut self.renderer,
             frame: Some(self.ctx.frame(d, dp, ts)),
             surface: surface,
+        })
+    }
+
+    /// Re-rasterizes and uploads the font atlas if the content scale has changed.
+    fn set_scale(&mut self, scale: f32) -> Result<()> {
+        if scale != self.scale {
+            Self::rasterize_fonts(&mut self.ctx, scale);
+            self.renderer.reload_font_texture(&mut self.ctx)?;
+            self.scale = scale;
         }
+
+        Ok(())
+    }
+
+    /// Rasterizes the default font with the content `scale`, so the glyphs are mapped
+    /// onto physical pixels one by one. They are scaled back when laying out, so the
+    /// size of text in points never changes.
+    fn rasterize_fonts(imgui: &mut imgui::ImGui, scale: f32) {
+        {
+            let mut fonts = imgui.fonts();
+            fonts.clear();
+
+            let config = imgui::ImFontConfig::new().size_pixels(FONT_SIZE * scale);
+            fonts.add_default_font_with_config(config);
+        }
+
+        imgui.set_font_global_scale(1.0 / scale);
     }
 
     pub fn render(&mut self) {}
@@ -142,14 +234,24 @@ impl Canvas {
         imgui.set_key_super(lwin || rwin);
     }
 
-    fn update_mouse_state(imgui: &mut imgui::ImGui, input: &input::InputSystemShared) {
-        use self::application::event::MouseButton;
+    fn update_text_input(imgui: &mut imgui::ImGui, input: &input::InputSystemShared) {
+        for c in input.text_input() {
+            imgui.add_input_character(c);
+        }
+    }
 
-        let scale = imgui.display_framebuffer_scale();
+    fn update_mouse_state(imgui: &mut imgui::ImGui, input: &input::InputSystemShared, pos: bool) {
+        use self::application::event::MouseButton;
 
-        let pos = input.mouse_position();
-        let pos = (pos.x / scale.0, pos.y / scale.1);
-        imgui.set_mouse_pos(pos.0, pos.1);
+        if pos {
+            let scale = imgui.display_framebuffer_scale();
+            let pos = input.mouse_position();
+            let pos = (pos.x / scale.0, pos.y / scale.1);
+            imgui.set_mouse_pos(pos.0, pos.1);
+        } else {
+            // ImGui treats `-FLT_MAX` as the mouse being unavailable.
+            imgui.set_mouse_pos(-::std::f32::MAX, -::std::f32::MAX);
+        }
 
         let l = input.is_mouse_down(MouseButton::Left);
         let r = input.is_mouse_down(MouseButton::Right);
@@ -159,3 +261,27 @@ impl Canvas {
         imgui.set_mouse_wheel(input.mouse_scroll().y);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::prelude::*;
+
+    #[test]
+    fn scale() {
+        let engine = Engine::new_headless(Settings::default()).unwrap();
+        let ctx = engine.context();
+        let video = ctx.shared::<graphics::GraphicsSystem>();
+        let surface = video.create_surface(graphics::SurfaceSetup::default()).unwrap();
+
+        let mut canvas = Canvas::new(ctx, CanvasSetup::default()).unwrap();
+        assert_eq!(canvas.scale, 1.0);
+
+        canvas.set_scale(2.0).unwrap();
+        assert_eq!(canvas.scale, 2.0);
+
+        // The content scale of headless context is 1.0.
+        assert!(canvas.frame(surface, ctx).is_ok());
+        assert_eq!(canvas.scale, 1.0);
+    }
+}
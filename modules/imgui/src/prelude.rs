@@ -1,2 +1,2 @@
-pub use canvas::Canvas;
+pub use canvas::{Canvas, CanvasSetup};
 pub use imgui::*;
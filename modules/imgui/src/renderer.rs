@@ -13,6 +13,27 @@ impl_vertex!{
     }
 }
 
+impl_vertex!{
+    CompositeVertex {
+        position => [Position; Float; 2; false],
+    }
+}
+
+/// The multisampled surface that UI is drawn into before compositing, which is
+/// backed by a render texture with the dimensions of frame in pixels.
+struct Offscreen {
+    dimensions: (u32, u32),
+    texture: graphics::TextureHandle,
+    framebuffer: graphics::FrameBufferHandle,
+    surface: graphics::SurfaceHandle,
+}
+
+/// The full-screen quad that blends the offscreen UI onto the presenting surface.
+struct Composite {
+    shader: graphics::ShaderHandle,
+    mesh: graphics::MeshHandle,
+}
+
 pub struct Renderer {
     video: Arc<graphics::GraphicsSystemShared>,
 
@@ -20,12 +41,25 @@ pub struct Renderer {
     texture: graphics::TextureHandle,
 
     mesh: Option<(usize, usize, graphics::MeshHandle)>,
+
+    samples: u32,
+    order: u64,
+    composite: Option<Composite>,
+    offscreen: Option<Offscreen>,
 }
 
 impl Renderer {
     /// Creates a new `CanvasRenderer`. This will allocates essential video
     /// resources in background.
-    pub fn new(ctx: &application::Context, imgui: &mut ImGui) -> Result<Self> {
+    ///
+    /// If `samples` is greater than one, UI is drawn into an internal multisampled
+    /// surface with `order`, and composited onto the presenting surface.
+    pub fn new(
+        ctx: &application::Context,
+        imgui: &mut ImGui,
+        samples: u32,
+        order: u64,
+    ) -> Result<Self> {
         let video = ctx.shared::<graphics::GraphicsSystem>();
 
         let layout = graphics::AttributeLayoutBuilder::new()
@@ -38,11 +72,7 @@ impl Renderer {
         setup.layout = layout;
         setup.render_state.cull_face = graphics::CullFace::Back;
         setup.render_state.front_face_order = graphics::FrontFaceOrder::Clockwise;
-        setup.render_state.color_blend = Some((
-            graphics::Equation::Add,
-            graphics::BlendFactor::Value(graphics::BlendValue::SourceAlpha),
-            graphics::BlendFactor::OneMinusValue(graphics::BlendValue::SourceAlpha),
-        ));
+        setup.render_state.color_blend = Some(Self::premultiplied_blend());
 
         setup.vs = include_str!("../assets/imgui.vs").to_owned();
         setup.fs = include_str!("../assets/imgui.fs").to_owned();
@@ -55,26 +85,182 @@ impl Renderer {
 
         let shader = video.create_shader(resource::Location::unique(""), setup)?;
 
+        let texture = Self::create_font_texture(&video, imgui)?;
+
+        let composite = if samples > 1 {
+            Some(Self::create_composite(&video)?)
+        } else {
+            None
+        };
+
+        Ok(Renderer {
+            video: video.clone(),
+            shader: shader,
+            texture: texture,
+            mesh: None,
+            samples: samples,
+            order: order,
+            composite: composite,
+            offscreen: None,
+        })
+    }
+
+    /// The shader outputs premultiplied color, which blends the same as the
+    /// conventional alpha blending, but also accumulates correct coverage in the
+    /// alpha channel of offscreen render target.
+    fn premultiplied_blend() -> (
+        graphics::Equation,
+        graphics::BlendFactor,
+        graphics::BlendFactor,
+    ) {
+        (
+            graphics::Equation::Add,
+            graphics::BlendFactor::One,
+            graphics::BlendFactor::OneMinusValue(graphics::BlendValue::SourceAlpha),
+        )
+    }
+
+    fn create_composite(video: &graphics::GraphicsSystemShared) -> Result<Composite> {
+        let mut setup = graphics::ShaderSetup::default();
+        setup.layout = graphics::AttributeLayoutBuilder::new()
+            .with(graphics::Attribute::Position, 2)
+            .finish();
+        setup.render_state.color_blend = Some(Self::premultiplied_blend());
+        setup.vs = include_str!("../assets/composite.vs").to_owned();
+        setup.fs = include_str!("../assets/composite.fs").to_owned();
+
+        let tt = graphics::UniformVariableType::Texture;
+        setup.uniform_variables.insert("texture".into(), tt);
+        let shader = video.create_shader(resource::Location::unique(""), setup)?;
+
+        let verts: [CompositeVertex; 4] = [
+            CompositeVertex::new([-1.0, -1.0]),
+            CompositeVertex::new([1.0, -1.0]),
+            CompositeVertex::new([1.0, 1.0]),
+            CompositeVertex::new([-1.0, 1.0]),
+        ];
+        let idxes: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+        let mut setup = graphics::MeshSetup::default();
+        setup.layout = CompositeVertex::layout();
+        setup.num_verts = 4;
+        setup.num_idxes = 6;
+
+        let mesh = video.create_mesh(
+            resource::Location::unique(""),
+            setup,
+            CompositeVertex::as_bytes(&verts[..]),
+            graphics::IndexFormat::as_bytes(&idxes),
+        )?;
+
+        Ok(Composite {
+            shader: shader,
+            mesh: mesh,
+        })
+    }
+
+    /// Gets the multisampled surface that UI is drawn into, which will be (re)created
+    /// if the dimensions of frame changes.
+    fn offscreen(&mut self, dimensions: (u32, u32)) -> Result<&Offscreen> {
+        let valid = self.offscreen
+            .as_ref()
+            .map(|v| v.dimensions == dimensions)
+            .unwrap_or(false);
+
+        if !valid {
+            if let Some(v) = self.offscreen.take() {
+                Self::delete_offscreen(&self.video, v);
+            }
+
+            let mut setup = graphics::RenderTextureSetup::default();
+            setup.format = graphics::RenderTextureFormat::RGBA8;
+            setup.dimensions = dimensions;
+            let texture = self.video.create_render_texture(setup)?;
+
+            let mut setup = graphics::FrameBufferSetup::default();
+            setup.set_attachment(texture, 0)?;
+            let framebuffer = self.video.create_framebuffer(setup)?;
+
+            let mut setup = graphics::SurfaceSetup::default();
+            setup.set_framebuffer(framebuffer);
+            setup.set_clear(utils::Color::transparent(), None, None);
+            setup.set_sequence(true);
+            setup.set_samples(self.samples);
+            setup.set_order(self.order);
+            let surface = self.video.create_surface(setup)?;
+
+            self.offscreen = Some(Offscreen {
+                dimensions: dimensions,
+                texture: texture,
+                framebuffer: framebuffer,
+                surface: surface,
+            });
+        }
+
+        Ok(self.offscreen.as_ref().unwrap())
+    }
+
+    fn delete_offscreen(video: &graphics::GraphicsSystemShared, v: Offscreen) {
+        video.delete_surface(v.surface);
+        video.delete_framebuffer(v.framebuffer);
+        video.delete_texture(v.texture);
+    }
+
+    /// Re-rasterizes and uploads the font atlas, this should be called when the
+    /// content scale of window has changed.
+    pub fn reload_font_texture(&mut self, imgui: &mut ImGui) -> Result<()> {
+        let texture = Self::create_font_texture(&self.video, imgui)?;
+        self.video.delete_texture(self.texture);
+        self.texture = texture;
+        Ok(())
+    }
+
+    fn create_font_texture(
+        video: &graphics::GraphicsSystemShared,
+        imgui: &mut ImGui,
+    ) -> Result<graphics::TextureHandle> {
         let texture = imgui.prepare_texture(|v| {
             let mut setup = graphics::TextureSetup::default();
             setup.dimensions = (v.width, v.height);
+            // The glyphs are mapped onto pixels one by one, so the atlas is sampled with
+            // nearest filter to keep text crisp. Multisampling only smooths the edges of
+            // geometry, it does not touch how the atlas is sampled.
             setup.filter = graphics::TextureFilter::Nearest;
             setup.format = graphics::TextureFormat::U8U8U8U8;
             video.create_texture(resource::Location::unique(""), setup, Some(v.pixels))
         })?;
 
         imgui.set_texture_id(**texture as usize);
-
-        Ok(Renderer {
-            video: video.clone(),
-            shader: shader,
-            texture: texture,
-            mesh: None,
-        })
+        Ok(texture)
     }
 
     pub fn render<'a>(&mut self, surface: graphics::SurfaceHandle, ui: Ui<'a>) -> Result<()> {
-        ui.render(|ui, dcs| self.render_draw_list(surface, ui, &dcs))?;
+        if self.composite.is_none() {
+            ui.render(|ui, dcs| self.render_draw_list(surface, ui, &dcs))?;
+            return Ok(());
+        }
+
+        let (width, height) = ui.imgui().display_size();
+        let (scale_width, scale_height) = ui.imgui().display_framebuffer_scale();
+        let dimensions = ((width * scale_width) as u32, (height * scale_height) as u32);
+
+        if dimensions.0 == 0 || dimensions.1 == 0 {
+            return Ok(());
+        }
+
+        let (offscreen, texture) = {
+            let v = self.offscreen(dimensions)?;
+            (v.surface, v.texture)
+        };
+
+        ui.render(|ui, dcs| self.render_draw_list(offscreen, ui, &dcs))?;
+
+        // Blends the resolved UI onto the presenting surface after everything else.
+        let composite = self.composite.as_ref().unwrap();
+        let mut dc = graphics::DrawCall::new(composite.shader, composite.mesh);
+        dc.set_uniform_variable("texture", texture);
+        let cmd = dc.build_from(0, 6)?;
+        self.video.submit(surface, ::std::u64::MAX, cmd)?;
         Ok(())
     }
 
@@ -97,44 +283,40 @@ impl Renderer {
 
         let mesh = self.update_mesh(surface, &verts, &tasks.idx_buffer)?;
         let (width, height) = ui.imgui().display_size();
-        let (scale_width, scale_height) = ui.imgui().display_framebuffer_scale();
 
         if width == 0.0 || height == 0.0 {
             return Ok(());
         }
 
-        let matrix = graphics::UniformVariable::Matrix4f(
-            [
-                [2.0 / width as f32, 0.0, 0.0, 0.0],
-                [0.0, 2.0 / -(height as f32), 0.0, 0.0],
-                [0.0, 0.0, -1.0, 0.0],
-                [-1.0, 1.0, 0.0, 1.0],
-            ],
-            false,
-        );
+        let matrix = [
+            [2.0 / width as f32, 0.0, 0.0, 0.0],
+            [0.0, 2.0 / -(height as f32), 0.0, 0.0],
+            [0.0, 0.0, -1.0, 0.0],
+            [-1.0, 1.0, 0.0, 1.0],
+        ];
 
         let font_texture_id = **self.texture as usize;
         let mut idx_start = 0;
         for cmd in tasks.cmd_buffer {
             assert!(font_texture_id == cmd.texture_id as usize);
 
-            let scissor_pos = (
-                (cmd.clip_rect.x * scale_width) as u16,
-                ((height - cmd.clip_rect.w) * scale_height) as u16,
-            );
-            let scissor_size = (
-                ((cmd.clip_rect.z - cmd.clip_rect.x) * scale_width) as u16,
-                ((cmd.clip_rect.w - cmd.clip_rect.y) * scale_height) as u16,
-            );
+            // Clamps the clip rect into the render target, which is the window or the
+            // framebuffer of offscreen surface.
+            let x = cmd.clip_rect.x.max(0.0).min(width);
+            let y = cmd.clip_rect.y.max(0.0).min(height);
+            let z = cmd.clip_rect.z.max(x).min(width);
+            let w = cmd.clip_rect.w.max(y).min(height);
 
-            {
-                let scissor = graphics::Scissor::Enable(scissor_pos, scissor_size);
-                let cmd = graphics::Command::set_scissor(scissor);
-                self.video.submit(surface, 0u64, cmd)?;
-            }
+            // The clip rect is normalized with bottom-left origin, which is converted into
+            // pixels of the render target when the frame is dispatched. So it works with
+            // the hidpi window and the offscreen framebuffers alike.
+            let scissor_pos = (x / width, (height - w) / height);
+            let scissor_size = ((z - x) / width, (w - y) / height);
 
             {
+                // Every draw call clips its contents with its own scissor.
                 let mut dc = graphics::DrawCall::new(self.shader, mesh);
+                dc.set_scissor(graphics::Scissor::Normalized(scissor_pos, scissor_size));
                 dc.set_uniform_variable("matrix", matrix);
                 dc.set_uniform_variable("texture", self.texture);
                 let cmd = dc.build_from(idx_start, cmd.elem_count as usize)?;
@@ -144,9 +326,6 @@ impl Renderer {
             idx_start += cmd.elem_count as usize;
         }
 
-        let scissor = graphics::Scissor::Disable;
-        let cmd = graphics::Command::set_scissor(scissor);
-        self.video.submit(surface, 0u64, cmd)?;
         Ok(())
     }
 
@@ -211,5 +390,14 @@ impl Drop for Renderer {
         if let Some((_, _, mesh)) = self.mesh.take() {
             self.video.delete_mesh(mesh);
         }
+
+        if let Some(v) = self.composite.take() {
+            self.video.delete_shader(v.shader);
+            self.video.delete_mesh(v.mesh);
+        }
+
+        if let Some(v) = self.offscreen.take() {
+            Self::delete_offscreen(&self.video, v);
+        }
     }
 }
@@ -1,8 +1,12 @@
 //! The context of systems that could be accessed from multi-thread environments.
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::mpsc::Sender;
 use std::any::{Any, TypeId};
 
+use ecs::Events;
+use super::event::Event;
+
 pub trait ContextSystem {
     type Shared: Send + Sync + 'static;
 }
@@ -11,6 +15,9 @@ pub trait ContextSystem {
 pub struct Context {
     shareds: HashMap<TypeId, Box<Any + Send + Sync>>,
     shutdown: RwLock<bool>,
+    content_scale: RwLock<f32>,
+    events: Events,
+    event_sender: Mutex<Sender<Event>>,
 }
 
 impl Context {
@@ -23,6 +30,28 @@ impl Context {
         Self::cast::<T>(self.shareds.get(&tid).unwrap().as_ref())
     }
 
+    /// Gets the current content scale (aka. DPI factor) of the window. This could
+    /// be changed at runtime, `ApplicationEvent::ScaleFactorChanged` will be sent
+    /// when it happens.
+    #[inline]
+    pub fn content_scale(&self) -> f32 {
+        *self.content_scale.read().unwrap()
+    }
+
+    /// Gets the event bus shared by all the systems. The events sent during a frame
+    /// are readable in the next frame.
+    #[inline]
+    pub fn events(&self) -> &Events {
+        &self.events
+    }
+
+    /// Gets a `Sender` that feeds input and application events into the engine from
+    /// any thread, e.g. the gamepads polled by a platform backend. The events are
+    /// dispatched at the next frame, just like the ones from the windowing system.
+    pub fn event_sender(&self) -> Sender<Event> {
+        self.event_sender.lock().unwrap().clone()
+    }
+
     /// Shutdown the whole application.
     pub fn shutdown(&self) {
         *self.shutdown.write().unwrap() = true;
@@ -36,10 +65,13 @@ impl Context {
         any.downcast_ref::<Arc<T::Shared>>().unwrap()
     }
 
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(event_sender: Sender<Event>) -> Self {
         Context {
             shareds: HashMap::new(),
             shutdown: RwLock::new(false),
+            content_scale: RwLock::new(1.0),
+            events: Events::new(),
+            event_sender: Mutex::new(event_sender),
         }
     }
 
@@ -51,6 +83,10 @@ impl Context {
         self.shareds.insert(tid, Box::new(v));
     }
 
+    pub(crate) fn set_content_scale(&self, scale: f32) {
+        *self.content_scale.write().unwrap() = scale;
+    }
+
     pub(crate) fn is_shutdown(&self) -> bool {
         *self.shutdown.read().unwrap()
     }
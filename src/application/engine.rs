@@ -4,6 +4,7 @@ use std::sync::mpsc;
 use std::thread;
 
 use super::*;
+use math;
 use graphics;
 use resource;
 use input;
@@ -31,12 +32,16 @@ impl ContextSystem for time::TimeSystem {
 pub struct Engine {
     pub events_loop: event::EventsLoop,
     pub input: input::InputSystem,
-    pub window: Arc<graphics::Window>,
+    /// The window of application, which is `None` in headless mode.
+    pub window: Option<Arc<graphics::Window>>,
     pub graphics: graphics::GraphicsSystem,
     pub resource: resource::ResourceSystem,
     pub time: time::TimeSystem,
 
     context: Arc<Context>,
+    window_state: event::WindowState,
+    cursor_captured: bool,
+    cursor_grabbed: bool,
 }
 
 impl Engine {
@@ -47,26 +52,53 @@ impl Engine {
 
     /// Setup engine with specified settings.
     pub fn new_with(settings: Settings) -> Result<Self> {
-        let mut wb = graphics::WindowBuilder::new();
-        wb.with_title(settings.window.title.clone())
-            .with_dimensions(settings.window.width, settings.window.height);
-
         let input = input::InputSystem::new(settings.input);
         let input_shared = input.shared();
 
-        let events_loop = event::EventsLoop::new();
-        let window = Arc::new(wb.build(&events_loop.underlaying())?);
-
         let resource = resource::ResourceSystem::new()?;
         let resource_shared = resource.shared();
 
-        let graphics = graphics::GraphicsSystem::new(window.clone(), resource_shared.clone())?;
+        let (events_loop, window, graphics) = if settings.headless {
+            let dimensions = (settings.window.width, settings.window.height);
+            let graphics = graphics::GraphicsSystem::headless_with(
+                resource_shared.clone(),
+                dimensions,
+                settings.graphics,
+            );
+            (event::EventsLoop::headless(), None, graphics)
+        } else {
+            let mut wb = graphics::WindowBuilder::new();
+            wb.with_title(settings.window.title.clone())
+                .with_dimensions(settings.window.width, settings.window.height)
+                .with_vsync(settings.window.vsync)
+                .with_srgb(settings.window.srgb);
+
+            let events_loop = event::EventsLoop::new();
+            let window = Arc::new(wb.build(events_loop.underlaying().unwrap())?);
+            let graphics = graphics::GraphicsSystem::new(
+                window.clone(),
+                resource_shared.clone(),
+                settings.graphics,
+            )?;
+            (events_loop, Some(window), graphics)
+        };
+
         let graphics_shared = graphics.shared();
 
-        let time = time::TimeSystem::new(settings.engine)?;
+        let mut time = time::TimeSystem::new(settings.engine)?;
+        // The frame rate is limited by the refresh rate of screen if vsync is enabled.
+        time.set_vsync(window.as_ref().map(|v| v.is_vsync()).unwrap_or(false));
         let time_shared = time.shared();
 
-        let mut context = Context::new();
+        let dimensions = (settings.window.width, settings.window.height);
+        let dimensions = window
+            .as_ref()
+            .and_then(|v| v.dimensions())
+            .unwrap_or(dimensions);
+
+        let hidpi = window.as_ref().map(|v| v.hidpi_factor()).unwrap_or(1.0);
+        let mut context = Context::new(events_loop.sender());
+        context.set_content_scale(hidpi);
         context.insert::<resource::ResourceSystem>(resource_shared);
         context.insert::<graphics::GraphicsSystem>(graphics_shared);
         context.insert::<input::InputSystem>(input_shared);
@@ -81,9 +113,46 @@ impl Engine {
             time: time,
 
             context: Arc::new(context),
+            window_state: event::WindowState::new(dimensions, hidpi),
+            cursor_captured: false,
+            cursor_grabbed: false,
         })
     }
 
+    /// Setup engine without window, which is a shortcut of `new_with` with the
+    /// `headless` flag of settings.
+    pub fn new_headless(mut settings: Settings) -> Result<Self> {
+        settings.headless = true;
+        Engine::new_with(settings)
+    }
+
+    /// Creates a secondary window, whose OpenGL context shares textures, meshes and
+    /// shaders with the primary window. Surfaces present to it with
+    /// `SurfaceSetup::set_window`.
+    pub fn create_window(&mut self, builder: graphics::WindowBuilder) -> Result<graphics::WindowHandle> {
+        let window = match self.window {
+            Some(ref window) => {
+                let events = self.events_loop.underlaying().unwrap();
+                Arc::new(builder.build_shared(events, window)?)
+            }
+            None => {
+                let err = graphics::errors::ErrorKind::Headless;
+                return Err(graphics::errors::Error::from(err).into());
+            }
+        };
+
+        let handle = self.graphics.add_window(window.clone())?;
+        self.events_loop.add_window(window.id(), handle);
+        Ok(handle)
+    }
+
+    /// Removes the secondary window, which is closed once all the references to it are
+    /// dropped.
+    pub fn remove_window(&mut self, handle: graphics::WindowHandle) {
+        self.graphics.remove_window(handle);
+        self.events_loop.remove_window(handle);
+    }
+
     pub fn context(&self) -> &Context {
         &self.context
     }
@@ -110,15 +179,44 @@ impl Engine {
 
         let mut alive = true;
         while alive {
-            self.input.advance(self.window.hidpi_factor());
+            let hidpi = self.window
+                .as_ref()
+                .map(|v| v.hidpi_factor())
+                .unwrap_or(1.0);
+            for value in self.input.advance(hidpi) {
+                let mut application = application.write().unwrap();
+                application.on_receive_event(&self.context, value)?;
+            }
+
+            // Notifies application if the DPI factor of window has changed, e.g. the window
+            // has been dragged into a monitor with different pixel densities.
+            if let Some(hidpi) = self.window_state.rescale(hidpi) {
+                self.context.set_content_scale(hidpi);
+
+                let mut application = application.write().unwrap();
+                let event = event::ApplicationEvent::ScaleFactorChanged(hidpi);
+                application.on_receive_event(&self.context, event)?;
+            }
 
             // Poll any possible events first.
-            for v in self.events_loop.advance() {
-                match *v {
+            let mut closed_windows = Vec::new();
+            for &(window, v) in self.events_loop.advance() {
+                match v {
+                    // The secondary windows only notify the application when closed.
+                    event::Event::Application(value) if window.is_some() => {
+                        if let event::ApplicationEvent::Closed = value {
+                            closed_windows.push(window.unwrap());
+                        }
+                    }
+
                     event::Event::Application(value) => {
                         {
                             let mut application = application.write().unwrap();
                             application.on_receive_event(&self.context, value)?;
+
+                            if let Some(v) = self.window_state.update(value) {
+                                application.on_window_event(&self.context, v)?;
+                            }
                         }
 
                         match value {
@@ -129,10 +227,39 @@ impl Engine {
                         };
                     }
 
-                    event::Event::InputDevice(value) => self.input.update_with(value),
+                    event::Event::InputDevice(value) => {
+                        self.input.set_window(window);
+                        if let Some(value) = self.input.update_with(value) {
+                            let mut application = application.write().unwrap();
+                            application.on_receive_event(&self.context, value)?;
+                        }
+                    }
+                }
+            }
+
+            for handle in closed_windows {
+                self.remove_window(handle);
+
+                let mut application = application.write().unwrap();
+                let event = event::ApplicationEvent::WindowClosed(handle);
+                application.on_receive_event(&self.context, event)?;
+            }
+
+            // Not all the platforms report resizing and minimization by events, so the
+            // dimensions of window are polled as well.
+            if let Some((width, height)) = self.window.as_ref().and_then(|v| v.dimensions()) {
+                let value = event::ApplicationEvent::Resized(width, height);
+                if let Some(v) = self.window_state.update(value) {
+                    let mut application = application.write().unwrap();
+                    application.on_window_event(&self.context, v)?;
                 }
             }
 
+            if let Some(v) = self.update_cursor() {
+                let mut application = application.write().unwrap();
+                application.on_receive_event(&self.context, v)?;
+            }
+
             alive = alive && !self.context.is_shutdown();
             if !alive {
                 break;
@@ -140,6 +267,7 @@ impl Engine {
 
             self.time.advance();
             self.graphics.swap_frames();
+            self.context.events().advance();
 
             let (video_info, duration) = {
                 // Perform update and render submitting for frame [x], and drawing
@@ -154,10 +282,13 @@ impl Engine {
             };
 
             {
+                let time = self.time.shared();
                 let info = FrameInfo {
                     video: video_info,
                     duration: duration,
-                    fps: self.time.shared().get_fps(),
+                    delta: time.frame_delta(),
+                    unscaled_delta: time.unscaled_frame_delta(),
+                    fps: time.get_fps(),
                 };
 
                 let mut application = application.write().unwrap();
@@ -176,6 +307,55 @@ impl Engine {
         Ok(self)
     }
 
+    /// Grabs, hides and re-centers the cursor in relative mouse mode. The cursor is
+    /// released when the window lost focus, so users could switch to other applications.
+    ///
+    /// Returns `ApplicationEvent::CursorGrabFailed` if the cursor could not be grabbed.
+    fn update_cursor(&mut self) -> Option<event::ApplicationEvent> {
+        let window = match self.window {
+            Some(ref window) => window.clone(),
+            None => return None,
+        };
+
+        let input = self.input.shared();
+        let captured = input.is_relative_mouse_mode() && self.window_state.is_focused();
+
+        if captured != self.cursor_captured {
+            // The state is updated even if it fails, so the grab is not retried every
+            // frame, and the cursor stays free and visible.
+            self.cursor_captured = captured;
+
+            let grabbed = window
+                .set_cursor_grab(captured)
+                .and_then(|_| window.set_cursor_visible(!captured))
+                .is_ok();
+
+            self.cursor_grabbed = captured && grabbed;
+            if captured && !grabbed {
+                let _ = window.set_cursor_grab(false);
+                let _ = window.set_cursor_visible(true);
+                return Some(event::ApplicationEvent::CursorGrabFailed);
+            }
+        }
+
+        if !self.cursor_grabbed {
+            return None;
+        }
+
+        if let Some(dimensions) = window.dimensions_in_pixels() {
+            // The center is aligned to pixels, so the synthetic event of warping could
+            // be recognized exactly.
+            let center = ((dimensions.0 / 2) as f32, (dimensions.1 / 2) as f32);
+            if input.mouse_position() != math::Vector2::from(center) {
+                if window.set_cursor_position(center).is_ok() {
+                    self.input.on_cursor_warped(center);
+                }
+            }
+        }
+
+        None
+    }
+
     fn main_thread<T>(
         receiver: mpsc::Receiver<bool>,
         sender: mpsc::Sender<Result<Duration>>,
@@ -1,4 +1,7 @@
+use serde_json;
+
 use graphics;
+use input;
 use resource;
 use scene;
 
@@ -9,10 +12,12 @@ error_chain!{
 
     foreign_links {
         IO(::std::io::Error);
+        Json(serde_json::Error);
     }
 
     links {
         Graphics(graphics::errors::Error, graphics::errors::ErrorKind);
+        Input(input::errors::Error, input::errors::ErrorKind);
         Resource(resource::errors::Error, resource::errors::ErrorKind);
         Scene(scene::errors::Error, scene::errors::ErrorKind);
     }
@@ -1,9 +1,13 @@
 //! Responsible for converting window messages to input state and internal events.
 
+use std::collections::HashMap;
 use std::slice::Iter;
+use std::sync::mpsc::{self, Receiver, Sender};
 use glutin;
 use math;
 
+use graphics::WindowHandle;
+
 pub use glutin::VirtualKeyCode as KeyboardButton;
 pub use glutin::MouseButton;
 
@@ -26,9 +30,114 @@ pub enum ApplicationEvent {
     Resized(u32, u32),
     /// The position of window has changed.
     Moved(u32, u32),
+    /// The DPI factor of window has changed, which usually happens when the window
+    /// is moved between monitors with different pixel densities. The parameter is
+    /// the new content scale factor.
+    ScaleFactorChanged(f32),
+    /// A gamepad has been connected, the parameter is the stable id assigned by
+    /// `InputSystem`.
+    GamepadConnected(usize),
+    /// A gamepad has been disconnected.
+    GamepadDisconnected(usize),
+    /// The playback of `InputRecord` has reached its end.
+    InputPlaybackFinished,
+    /// A secondary window has been closed, it's removed from `GraphicsSystem` already.
+    WindowClosed(WindowHandle),
+    /// The cursor could not be grabbed in relative mouse mode, which is refused by
+    /// some platforms or window managers. The application keeps running with a free
+    /// cursor until the relative mouse mode is enabled again.
+    CursorGrabFailed,
+}
+
+/// The state changes of window, which are delivered to `Application::on_window_event`.
+/// Games could pause themselves when the window goes into background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// The window gained (`true`) or lost (`false`) focus of user input.
+    WindowFocused(bool),
+    /// The window has been resized, or restored from minimization. The parameters
+    /// are the new dimensions in points.
+    WindowResized(u32, u32),
+    /// The window has been minimized. Nothing will be drawn until it's restored.
+    WindowMinimized,
+}
+
+/// Tracks the state of window, and converts the raw application events into
+/// `WindowEvent`s without duplications.
+pub(crate) struct WindowState {
+    focused: bool,
+    minimized: bool,
+    dimensions: (u32, u32),
+    hidpi: f32,
 }
 
-/// Input device event, supports mouse and keyboard only.
+impl WindowState {
+    pub fn new(dimensions: (u32, u32), hidpi: f32) -> Self {
+        WindowState {
+            focused: true,
+            minimized: dimensions.0 == 0 || dimensions.1 == 0,
+            dimensions: dimensions,
+            hidpi: hidpi,
+        }
+    }
+
+    pub fn update(&mut self, v: ApplicationEvent) -> Option<WindowEvent> {
+        match v {
+            ApplicationEvent::GainFocus => self.focus(true),
+            ApplicationEvent::LostFocus => self.focus(false),
+            ApplicationEvent::Resized(width, height) => self.resize((width, height)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the window has focus of user input.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Updates the DPI factor of window, which is polled every frame. Returns the new
+    /// factor if it has changed, so `ApplicationEvent::ScaleFactorChanged` should be
+    /// sent.
+    pub fn rescale(&mut self, hidpi: f32) -> Option<f32> {
+        if self.hidpi == hidpi {
+            return None;
+        }
+
+        self.hidpi = hidpi;
+        Some(hidpi)
+    }
+
+    fn focus(&mut self, focused: bool) -> Option<WindowEvent> {
+        if self.focused == focused {
+            return None;
+        }
+
+        self.focused = focused;
+        Some(WindowEvent::WindowFocused(focused))
+    }
+
+    fn resize(&mut self, dimensions: (u32, u32)) -> Option<WindowEvent> {
+        // Some platforms report minimization as resizing to zero dimensions.
+        if dimensions.0 == 0 || dimensions.1 == 0 {
+            if self.minimized {
+                return None;
+            }
+
+            self.minimized = true;
+            return Some(WindowEvent::WindowMinimized);
+        }
+
+        if !self.minimized && self.dimensions == dimensions {
+            return None;
+        }
+
+        self.minimized = false;
+        self.dimensions = dimensions;
+        Some(WindowEvent::WindowResized(dimensions.0, dimensions.1))
+    }
+}
+
+/// Input device event.
 #[derive(Debug, Clone, Copy)]
 pub enum InputDeviceEvent {
     /// The cursor has moved on the window.
@@ -39,7 +148,8 @@ pub enum InputDeviceEvent {
     MousePressed { button: MouseButton },
     /// Released event from mouse has been received.
     MouseReleased { button: MouseButton },
-    /// A mouse wheel movement or touchpad scroll occurred.
+    /// A mouse wheel movement or touchpad scroll occurred. The parameters are the
+    /// horizontal and vertical scroll amount.
     MouseWheel { delta: (f32, f32) },
 
     /// Pressed event on keyboard has been received.
@@ -59,8 +169,59 @@ pub enum InputDeviceEvent {
     ///
     /// Depending on platform implementation id may or may not be reused by system after End event.
     Touch(TouchEvent),
+
+    /// A game controller has been plugged in. The `device` is the identifier of
+    /// controller that provided by the platform backend.
+    GamepadConnected { device: u64 },
+    /// A game controller has been unplugged.
+    GamepadDisconnected { device: u64 },
+    /// Pressed event on game controller has been received.
+    GamepadPressed { device: u64, button: GamepadButton },
+    /// Released event from game controller has been received.
+    GamepadReleased { device: u64, button: GamepadButton },
+    /// The analog axis of game controller has moved. The value is in range of
+    /// [-1.0, 1.0] for sticks, and [0.0, 1.0] for triggers.
+    GamepadAxisMoved {
+        device: u64,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// The buttons of game controller, named after the layout of Xbox controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftShoulder,
+    RightShoulder,
+    LeftThumb,
+    RightThumb,
+    Back,
+    Start,
+    Guide,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// The analog axes of game controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX = 0,
+    LeftStickY = 1,
+    RightStickX = 2,
+    RightStickY = 3,
+    LeftTrigger = 4,
+    RightTrigger = 5,
 }
 
+/// The number of analog axes of game controller.
+pub const MAX_GAMEPAD_AXES: usize = 6;
+
 /// The enumerations of all events that come from various kinds of user input.
 #[derive(Debug, Clone, Copy)]
 pub enum Event {
@@ -70,33 +231,90 @@ pub enum Event {
 
 /// A `EventsLoop` is responsible for converting window messages to input state
 /// and internal events.
+///
+/// Every event is tagged with the secondary window it comes from, or `None` for the
+/// primary window.
+///
+/// Notes that glutin does not report game controllers, the platform backends of
+/// gamepads could feed their events from any thread with the `Sender` returned by
+/// `sender` (or `Context::event_sender` while the engine is running).
 pub struct EventsLoop {
-    ctx: glutin::EventsLoop,
-    frame_events: Vec<Event>,
+    ctx: Option<glutin::EventsLoop>,
+    windows: HashMap<glutin::WindowId, WindowHandle>,
+    frame_events: Vec<(Option<WindowHandle>, Event)>,
+    pending_events: Vec<(Option<WindowHandle>, Event)>,
+    sender: Sender<Event>,
+    receiver: Receiver<Event>,
 }
 
 impl EventsLoop {
     /// Creates a new `EventsLoop`.
     pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        EventsLoop {
+            ctx: Some(glutin::EventsLoop::new()),
+            windows: HashMap::new(),
+            frame_events: Vec::new(),
+            pending_events: Vec::new(),
+            sender: sender,
+            receiver: receiver,
+        }
+    }
+
+    /// Creates a new `EventsLoop` without connecting to the windowing system, only
+    /// the events pushed manually will be dispatched.
+    pub fn headless() -> Self {
+        let (sender, receiver) = mpsc::channel();
         EventsLoop {
-            ctx: glutin::EventsLoop::new(),
+            ctx: None,
+            windows: HashMap::new(),
             frame_events: Vec::new(),
+            pending_events: Vec::new(),
+            sender: sender,
+            receiver: receiver,
         }
     }
 
     /// Gets the iterator over the events collected during last frame.
-    pub fn iter(&self) -> Iter<Event> {
-        self.frame_events.iter()
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a Event> + 'a {
+        self.frame_events.iter().map(|v| &v.1)
+    }
+
+    /// Pushes an event which will be dispatched at next frame.
+    pub fn push(&mut self, v: Event) {
+        self.pending_events.push((None, v));
+    }
+
+    /// Pushes an event of secondary window which will be dispatched at next frame.
+    pub fn push_from(&mut self, window: WindowHandle, v: Event) {
+        self.pending_events.push((Some(window), v));
+    }
+
+    /// Creates a `Sender` that pushes events from other threads, the events received
+    /// before the start of a frame are dispatched in that frame.
+    pub fn sender(&self) -> Sender<Event> {
+        self.sender.clone()
     }
 
-    pub(crate) fn advance(&mut self) -> Iter<Event> {
+    pub(crate) fn advance(&mut self) -> Iter<(Option<WindowHandle>, Event)> {
         self.frame_events.clear();
+        self.frame_events.extend(self.pending_events.drain(..));
+        self.frame_events
+            .extend(self.receiver.try_iter().map(|v| (None, v)));
 
-        {
+        if let Some(ref mut ctx) = self.ctx {
             let frame = &mut self.frame_events;
-            self.ctx.poll_events(|event| {
+            let windows = &self.windows;
+            ctx.poll_events(|event| {
+                let window = match event {
+                    glutin::Event::WindowEvent { window_id, .. } => {
+                        windows.get(&window_id).cloned()
+                    }
+                    _ => None,
+                };
+
                 if let Some(v) = from_event(event) {
-                    frame.push(v);
+                    frame.push((window, v));
                 }
             });
         }
@@ -104,9 +322,17 @@ impl EventsLoop {
         self.frame_events.iter()
     }
 
+    /// Routes the events of platform window `id` to the secondary window `handle`.
+    pub(crate) fn add_window(&mut self, id: glutin::WindowId, handle: WindowHandle) {
+        self.windows.insert(id, handle);
+    }
+
+    pub(crate) fn remove_window(&mut self, handle: WindowHandle) {
+        self.windows.retain(|_, v| *v != handle);
+    }
 
-    pub(crate) fn underlaying(&self) -> &glutin::EventsLoop {
-        &self.ctx
+    pub(crate) fn underlaying(&self) -> Option<&glutin::EventsLoop> {
+        self.ctx.as_ref()
     }
 }
 
@@ -136,6 +362,10 @@ fn from_window_event(source: glutin::WindowEvent) -> Option<Event> {
     match source {
         glutin::WindowEvent::Closed => Some(Event::Application(ApplicationEvent::Closed)),
 
+        glutin::WindowEvent::Resized(width, height) => {
+            Some(Event::Application(ApplicationEvent::Resized(width, height)))
+        }
+
         glutin::WindowEvent::Focused(v) => if v {
             Some(Event::Application(ApplicationEvent::GainFocus))
         } else {
@@ -218,7 +448,7 @@ fn from_window_event(source: glutin::WindowEvent) -> Option<Event> {
 
         glutin::WindowEvent::Touch(touch) => {
             let evt = TouchEvent {
-                id: touch.id as u8,
+                id: touch.id,
                 state: from_touch_state(touch.phase),
                 position: (touch.location.0 as f32, touch.location.1 as f32).into(),
             };
@@ -250,7 +480,9 @@ pub enum TouchState {
 
 #[derive(Debug, Clone, Copy)]
 pub struct TouchEvent {
-    pub id: u8,
+    /// The identifier of finger assigned by platform, which might be reused once the
+    /// finger is lifted.
+    pub id: u64,
     pub state: TouchState,
     pub position: math::Vector2<f32>,
 }
@@ -264,3 +496,50 @@ impl Default for TouchEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn window_state() {
+        let mut state = WindowState::new((320, 240), 1.0);
+        assert_eq!(state.update(ApplicationEvent::GainFocus), None);
+
+        let v = state.update(ApplicationEvent::LostFocus);
+        assert_eq!(v, Some(WindowEvent::WindowFocused(false)));
+        assert_eq!(state.update(ApplicationEvent::LostFocus), None);
+
+        assert_eq!(state.update(ApplicationEvent::Resized(320, 240)), None);
+        let v = state.update(ApplicationEvent::Resized(640, 480));
+        assert_eq!(v, Some(WindowEvent::WindowResized(640, 480)));
+
+        let v = state.update(ApplicationEvent::Resized(0, 0));
+        assert_eq!(v, Some(WindowEvent::WindowMinimized));
+        assert_eq!(state.update(ApplicationEvent::Resized(0, 0)), None);
+
+        // Restoring to the same dimensions is reported as well.
+        let v = state.update(ApplicationEvent::Resized(640, 480));
+        assert_eq!(v, Some(WindowEvent::WindowResized(640, 480)));
+
+        assert_eq!(state.update(ApplicationEvent::Awakened), None);
+    }
+
+    #[test]
+    fn scale_factor() {
+        let mut state = WindowState::new((320, 240), 1.0);
+        assert_eq!(state.rescale(1.0), None);
+
+        // Moved into a monitor with higher pixel densities, and back.
+        assert_eq!(state.rescale(2.0), Some(2.0));
+        assert_eq!(state.rescale(2.0), None);
+        assert_eq!(state.rescale(2.0), None);
+        assert_eq!(state.rescale(1.0), Some(1.0));
+        assert_eq!(state.rescale(1.0), None);
+
+        // The scale factor is independent of other states.
+        state.update(ApplicationEvent::Resized(0, 0));
+        assert_eq!(state.rescale(1.0), None);
+        assert_eq!(state.rescale(1.5), Some(1.5));
+    }
+}
@@ -24,6 +24,13 @@
 //! `Engine` mentioned above is the most fundamental module in crayon. It binds various
 //! essential systems in a central place, and responsible for running the main loop.
 //!
+//! # Headless
+//!
+//! The engine could also run without window, e.g. on servers or CI machines, by setting
+//! `Settings::headless` or creating it with `Engine::new_headless`. A no-op graphics
+//! backend is installed in that case, so the game logic runs as usual but nothing will
+//! be drawn, and `Engine::window` is `None`.
+//!
 
 pub mod errors;
 pub mod settings;
@@ -47,7 +54,12 @@ use std::time::Duration;
 #[derive(Debug, Copy, Clone, Default)]
 pub struct FrameInfo {
     pub video: GraphicsFrameInfo,
+    /// The time spent on `on_update` and `on_render`.
     pub duration: Duration,
+    /// The duration of game time, which is scaled and could be zero if paused.
+    pub delta: Duration,
+    /// The duration of wall-clock.
+    pub unscaled_delta: Duration,
     pub fps: u32,
 }
 
@@ -75,6 +87,12 @@ pub trait Application {
         Ok(())
     }
 
+    /// `Application::on_window_event` is called when the window gained or lost focus,
+    /// has been resized, minimized or restored.
+    fn on_window_event(&mut self, _: &Context, _: event::WindowEvent) -> Result<()> {
+        Ok(())
+    }
+
     /// `Application::on_exit` is called when exiting.
     fn on_exit(&mut self, _: &Context) -> Result<()> {
         Ok(())
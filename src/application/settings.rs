@@ -1,20 +1,74 @@
 //! Functions for loading game settings.
+//!
+//! The settings could be persisted into a human-editable JSON file, e.g. a default
+//! config shipped alongside the binary, or the resolution chosen by players:
+//!
+//! ```json
+//! {
+//!   "window": { "width": 1280, "height": 720, "vsync": true },
+//!   "engine": { "max_fps": 60 }
+//! }
+//! ```
+//!
+//! The fields missing from the file fall back to their defaults, and the unknown ones
+//! are ignored, so config files written by older versions could still be loaded.
+
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use serde_json;
 
 use input;
+use graphics;
+
+use super::errors::*;
 
 /// A structure containing configuration data for the game engine, which are
 /// used to specify hardware setup stuff to create the window and other
 /// context information.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub engine: EngineSettings,
     pub window: WindowSettings,
     pub input: InputSettings,
+    pub graphics: GraphicsSettings,
+    /// Runs the engine without window, e.g. on servers or CI machines without display.
+    /// A no-op graphics backend will be installed, which validates handles and accounts
+    /// for resources but draws nothing.
+    pub headless: bool,
+}
+
+impl Settings {
+    /// Loads settings from the JSON file at `path`.
+    pub fn load_from<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = fs::File::open(path)?;
+        let settings = serde_json::from_reader(BufReader::new(file))?;
+        Ok(settings)
+    }
+
+    /// Saves settings into the JSON file at `path`, which will be created if it does
+    /// not exist.
+    pub fn save_to<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let file = fs::File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EngineSettings {
     pub min_fps: u32,
+    /// The target frame rate cap, which is only performed when vsync is disabled.
+    /// Zero means uncapped.
     pub max_fps: u32,
     pub max_inactive_fps: u32,
     pub time_smooth_step: u32,
@@ -31,11 +85,18 @@ impl Default for EngineSettings {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WindowSettings {
     pub title: String,
     pub width: u32,
     pub height: u32,
+    /// Synchronizes the swapping of buffers with the vertical refresh of screen.
+    pub vsync: bool,
+    /// Requests a default framebuffer in sRGB space. The encoding is enabled per
+    /// surface with `SurfaceSetup::set_srgb`, which makes everything drawn into it
+    /// look brighter. It's disabled by default.
+    pub srgb: bool,
 }
 
 impl Default for WindowSettings {
@@ -44,8 +105,62 @@ impl Default for WindowSettings {
             title: "Window".to_owned(),
             width: 640,
             height: 320,
+            vsync: false,
+            srgb: false,
         }
     }
 }
 
 pub type InputSettings = input::InputSetup;
+
+pub type GraphicsSettings = graphics::GraphicsSetup;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let text = r#"{
+            "window": { "width": 1280, "vsync": true, "fullscreen": true },
+            "engine": { "max_fps": 60 },
+            "unknown": 1
+        }"#;
+
+        let settings: Settings = serde_json::from_str(text).unwrap();
+        assert_eq!(settings.window.width, 1280);
+        assert_eq!(settings.window.height, 320);
+        assert_eq!(settings.window.title, "Window");
+        assert!(settings.window.vsync);
+        assert_eq!(settings.engine.max_fps, 60);
+        assert_eq!(settings.graphics.frame_capacity, 64 * 1024);
+        assert!(!settings.headless);
+
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.engine.max_fps, 30);
+        assert!(serde_json::from_str::<Settings>(r#"{ "window": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn file() {
+        let path = ::std::env::temp_dir().join("crayon-settings-test.json");
+
+        let mut settings = Settings::default();
+        settings.window.title = "Test".into();
+        settings.window.srgb = true;
+        settings.input.keyboard.max_chars = 16;
+        settings.save_to(&path).unwrap();
+
+        let loaded = Settings::load_from(&path).unwrap();
+        assert_eq!(loaded.window.title, "Test");
+        assert!(loaded.window.srgb);
+        assert_eq!(loaded.input.keyboard.max_chars, 16);
+        assert_eq!(
+            loaded.input.keyboard.repeat_timeout,
+            settings.input.keyboard.repeat_timeout
+        );
+
+        fs::remove_file(&path).unwrap();
+        assert!(Settings::load_from(&path).is_err());
+    }
+}
@@ -8,12 +8,17 @@ use std::sync::{Arc, RwLock};
 use super::settings::EngineSettings;
 use super::errors::*;
 
+/// The waiting loop sleeps until the remaining time of frame is shorter than this,
+/// and then spins, since `thread::sleep` could oversleep a few milliseconds.
+const SPIN_THRESHOLD: u64 = 2;
+
 /// `TimeSystem`
 pub struct TimeSystem {
     min_fps: u32,
     max_fps: u32,
     max_inactive_fps: u32,
     smoothing_step: usize,
+    vsync: bool,
 
     timestep: Duration,
     previous_timesteps: VecDeque<Duration>,
@@ -30,6 +35,7 @@ impl TimeSystem {
             max_fps: setup.max_fps,
             max_inactive_fps: setup.max_inactive_fps,
             smoothing_step: setup.time_smooth_step as usize,
+            vsync: false,
             previous_timesteps: VecDeque::new(),
             timestep: Duration::new(0, 0),
             last_frame_timepoint: Instant::now(),
@@ -42,6 +48,12 @@ impl TimeSystem {
         self.shared.clone()
     }
 
+    /// Sets whether vsync is enabled, the frame rate cap will be skipped in that case
+    /// since swapping buffers blocks until the next refresh of screen.
+    pub(crate) fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
     pub(crate) fn advance(&mut self) -> Duration {
         // Synchonize with configurations.
         self.min_fps = *self.shared.min_fps.read().unwrap();
@@ -49,17 +61,9 @@ impl TimeSystem {
         self.max_inactive_fps = *self.shared.max_inactive_fps.read().unwrap();
         self.smoothing_step = *self.shared.smoothing_step.read().unwrap();
 
-        // Perform waiting loop if maximum fps set, cooperatively gives up
-        // a timeslice to the OS scheduler.
-        if self.max_fps > 0 {
-            let td = Duration::from_millis((1000 / self.max_fps) as u64);
-            while self.last_frame_timepoint.elapsed() <= td {
-                if (self.last_frame_timepoint.elapsed() + Duration::from_millis(2)) < td {
-                    std::thread::sleep(Duration::from_millis(1));
-                } else {
-                    std::thread::yield_now();
-                }
-            }
+        // Perform waiting loop if maximum fps set and vsync is disabled.
+        if let Some(td) = self.frame_duration() {
+            TimeSystem::wait(self.last_frame_timepoint, td);
         }
 
         let mut elapsed = self.last_frame_timepoint.elapsed();
@@ -91,6 +95,44 @@ impl TimeSystem {
         *self.shared.timestep.write().unwrap() = self.timestep;
         self.timestep
     }
+
+    /// Gets the minimum duration of frame that capped by the maximum fps. Returns
+    /// `None` if it's uncapped, or vsync is enabled.
+    fn frame_duration(&self) -> Option<Duration> {
+        if self.max_fps > 0 && !self.vsync {
+            Some(Duration::new(0, 1_000_000_000 / self.max_fps))
+        } else {
+            None
+        }
+    }
+
+    /// Blocks until `td` has elapsed since `from`. It sleeps most of the time, and spins
+    /// the last few milliseconds to be accurate.
+    fn wait(from: Instant, td: Duration) {
+        while let Some(duration) = TimeSystem::sleep_duration(from.elapsed(), td) {
+            if duration > Duration::new(0, 0) {
+                std::thread::sleep(duration);
+            } else {
+                std::thread::yield_now();
+            }
+        }
+    }
+
+    /// Gets how long the waiting loop should sleep after `elapsed` of `td`. It spins
+    /// if the duration is zero, and stops waiting if `None` is returned.
+    fn sleep_duration(elapsed: Duration, td: Duration) -> Option<Duration> {
+        if elapsed >= td {
+            return None;
+        }
+
+        let threshold = Duration::from_millis(SPIN_THRESHOLD);
+        let remains = td - elapsed;
+        if remains > threshold {
+            Some(remains - threshold)
+        } else {
+            Some(Duration::new(0, 0))
+        }
+    }
 }
 
 /// The multi-thread friendly parts of `TimeSystem`.
@@ -100,6 +142,8 @@ pub struct TimeSystemShared {
     max_inactive_fps: RwLock<u32>,
     smoothing_step: RwLock<usize>,
     timestep: RwLock<Duration>,
+    time_scale: RwLock<f32>,
+    paused: RwLock<bool>,
 }
 
 impl TimeSystemShared {
@@ -110,6 +154,8 @@ impl TimeSystemShared {
             max_inactive_fps: RwLock::new(setup.max_inactive_fps),
             smoothing_step: RwLock::new(setup.time_smooth_step as usize),
             timestep: RwLock::new(Duration::new(0, 0)),
+            time_scale: RwLock::new(1.0),
+            paused: RwLock::new(false),
         }
     }
 
@@ -122,7 +168,8 @@ impl TimeSystemShared {
     }
 
     /// Set maximum frames per second. The engine will sleep if fps is higher
-    /// than this for less resource(e.g. power) consumptions.
+    /// than this for less resource(e.g. power) consumptions. Zero means uncapped,
+    /// and the cap is ignored if vsync is enabled.
     #[inline]
     pub fn set_max_fps(&self, fps: u32) {
         *self.max_fps.write().unwrap() = fps;
@@ -141,6 +188,39 @@ impl TimeSystemShared {
         *self.smoothing_step.write().unwrap() = step as usize;
     }
 
+    /// Sets the speed of game time, e.g. 0.5 for slow-motion. The wall-clock, and
+    /// the loop of `Engine`, are not affected. Negative scales are clamped to zero.
+    #[inline]
+    pub fn set_time_scale(&self, scale: f32) {
+        *self.time_scale.write().unwrap() = scale.max(0.0);
+    }
+
+    /// Gets the speed of game time.
+    #[inline]
+    pub fn time_scale(&self) -> f32 {
+        *self.time_scale.read().unwrap()
+    }
+
+    /// Pauses the game time, `frame_delta` will be zero until `resume` is called. The
+    /// `Engine` keeps running its loop, so inputs are still polled and frames are
+    /// still rendered.
+    #[inline]
+    pub fn pause(&self) {
+        *self.paused.write().unwrap() = true;
+    }
+
+    /// Resumes the game time.
+    #[inline]
+    pub fn resume(&self) {
+        *self.paused.write().unwrap() = false;
+    }
+
+    /// Returns true if the game time is paused.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        *self.paused.read().unwrap()
+    }
+
     /// Gets current fps.
     #[inline]
     pub fn get_fps(&self) -> u32 {
@@ -152,9 +232,91 @@ impl TimeSystemShared {
         }
     }
 
-    /// Gets the duration duraing last frame.
+    /// Gets the duration of game time during last frame, which is scaled by the time
+    /// scale, or zero if paused.
     #[inline]
     pub fn frame_delta(&self) -> Duration {
+        if self.is_paused() {
+            return Duration::new(0, 0);
+        }
+
+        scale(self.unscaled_frame_delta(), self.time_scale())
+    }
+
+    /// Gets the duration of wall-clock during last frame, it keeps going even if the
+    /// game time is paused. This is useful for things like the animations of UI.
+    #[inline]
+    pub fn unscaled_frame_delta(&self) -> Duration {
         *self.timestep.read().unwrap()
     }
 }
+
+fn scale(duration: Duration, scale: f32) -> Duration {
+    let nanos = duration.as_secs() as f64 * 1e9 + duration.subsec_nanos() as f64;
+    let nanos = (nanos * scale as f64) as u64;
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cap() {
+        let mut setup = EngineSettings::default();
+        setup.max_fps = 100;
+
+        let mut time = TimeSystem::new(setup).unwrap();
+        let td = Duration::from_millis(10);
+        assert_eq!(time.frame_duration(), Some(td));
+
+        time.set_vsync(true);
+        assert_eq!(time.frame_duration(), None);
+
+        time.set_vsync(false);
+        time.max_fps = 0;
+        assert_eq!(time.frame_duration(), None);
+
+        // Sleeps until the last few milliseconds, and then spins.
+        let ms = Duration::from_millis;
+        assert_eq!(TimeSystem::sleep_duration(ms(0), td), Some(ms(8)));
+        assert_eq!(TimeSystem::sleep_duration(ms(7), td), Some(ms(1)));
+        assert_eq!(TimeSystem::sleep_duration(ms(9), td), Some(ms(0)));
+        assert_eq!(TimeSystem::sleep_duration(ms(10), td), None);
+        assert_eq!(TimeSystem::sleep_duration(ms(15), td), None);
+    }
+
+    #[test]
+    fn scaled() {
+        let mut setup = EngineSettings::default();
+        setup.max_fps = 100;
+        setup.time_smooth_step = 0;
+
+        let mut time = TimeSystem::new(setup).unwrap();
+        let shared = time.shared();
+        time.advance();
+        time.advance();
+
+        let delta = shared.unscaled_frame_delta();
+        assert!(delta >= Duration::from_millis(10));
+        assert_eq!(shared.frame_delta(), delta);
+
+        shared.set_time_scale(0.5);
+        assert_eq!(shared.frame_delta(), scale(delta, 0.5));
+        assert!(shared.frame_delta() < delta);
+
+        shared.set_time_scale(-1.0);
+        assert_eq!(shared.time_scale(), 0.0);
+        shared.set_time_scale(2.0);
+
+        shared.pause();
+        time.advance();
+        assert!(shared.is_paused());
+        assert_eq!(shared.frame_delta(), Duration::new(0, 0));
+        assert!(shared.unscaled_frame_delta() >= Duration::from_millis(10));
+
+        shared.resume();
+        assert_eq!(shared.frame_delta(), scale(shared.unscaled_frame_delta(), 2.0));
+        assert_eq!(scale(Duration::new(1, 500_000_000), 2.0), Duration::new(3, 0));
+    }
+}
@@ -0,0 +1,20 @@
+use std::io;
+use serde_json;
+
+error_chain!{
+    types {
+        Error, ErrorKind, ResultExt, Result;
+    }
+
+    foreign_links {
+        IO(io::Error);
+        Json(serde_json::Error);
+    }
+
+    errors {
+        MalformedComponent(name: String, reason: String) {
+            description("malformed component")
+            display("Failed to deserialize component '{}'. {}", name, reason)
+        }
+    }
+}
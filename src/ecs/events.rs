@@ -0,0 +1,166 @@
+//! A typed event bus for one-shot messages between systems.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Mutex, RwLock};
+
+/// `Events` is a typed event bus, which could be used to send one-shot messages, e.g.
+/// collision happened or item picked up, between decoupled systems.
+///
+/// The events are double-buffered: everything sent during a frame becomes readable
+/// after `Events::advance`, and is cleared at the next advancing. Reading does not
+/// consume the events, so multiple readers of the same event type each see the full
+/// set exactly once per frame.
+///
+/// ```rust,ignore
+/// // Sends event in one system.
+/// ctx.events().send(CollisionEvent { lhs, rhs });
+///
+/// // And receives the events sent during last frame in other systems.
+/// for v in ctx.events().read::<CollisionEvent>() { ... }
+/// ```
+///
+/// The `Events` of `Context` is advanced by `Engine` at the start of every frame.
+#[derive(Default)]
+pub struct Events {
+    queues: RwLock<HashMap<TypeId, Box<AnyQueue>>>,
+}
+
+impl Events {
+    /// Creates a new and empty event bus.
+    pub fn new() -> Self {
+        Events {
+            queues: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Sends a event, which will be readable after next advancing.
+    pub fn send<T>(&self, v: T)
+    where
+        T: Any + Send + Sync,
+    {
+        let tid = TypeId::of::<T>();
+
+        {
+            let queues = self.queues.read().unwrap();
+            if let Some(queue) = queues.get(&tid) {
+                Self::cast::<T>(queue.as_ref()).current.lock().unwrap().push(v);
+                return;
+            }
+        }
+
+        let mut queues = self.queues.write().unwrap();
+        let queue = queues
+            .entry(tid)
+            .or_insert_with(|| Box::new(Queue::<T>::new()));
+
+        Self::cast::<T>(queue.as_ref()).current.lock().unwrap().push(v);
+    }
+
+    /// Gets the events with type `T` that have been sent before last advancing.
+    pub fn read<T>(&self) -> Vec<T>
+    where
+        T: Any + Send + Sync + Clone,
+    {
+        let queues = self.queues.read().unwrap();
+        match queues.get(&TypeId::of::<T>()) {
+            Some(queue) => Self::cast::<T>(queue.as_ref()).last.read().unwrap().clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the number of readable events with type `T`.
+    pub fn len<T>(&self) -> usize
+    where
+        T: Any + Send + Sync,
+    {
+        let queues = self.queues.read().unwrap();
+        match queues.get(&TypeId::of::<T>()) {
+            Some(queue) => Self::cast::<T>(queue.as_ref()).last.read().unwrap().len(),
+            None => 0,
+        }
+    }
+
+    /// Makes the events sent during this frame readable, and clears the ones of
+    /// last frame.
+    pub fn advance(&self) {
+        let queues = self.queues.read().unwrap();
+        for v in queues.values() {
+            v.advance();
+        }
+    }
+
+    #[inline]
+    fn cast<T>(queue: &AnyQueue) -> &Queue<T>
+    where
+        T: Any + Send + Sync,
+    {
+        queue.as_any().downcast_ref::<Queue<T>>().unwrap()
+    }
+}
+
+trait AnyQueue: Send + Sync {
+    fn advance(&self);
+    fn as_any(&self) -> &Any;
+}
+
+struct Queue<T> {
+    current: Mutex<Vec<T>>,
+    last: RwLock<Vec<T>>,
+}
+
+impl<T> Queue<T> {
+    fn new() -> Self {
+        Queue {
+            current: Mutex::new(Vec::new()),
+            last: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl<T> AnyQueue for Queue<T>
+where
+    T: Any + Send + Sync,
+{
+    fn advance(&self) {
+        let mut current = self.current.lock().unwrap();
+        let mut last = self.last.write().unwrap();
+        last.clear();
+        mem::swap(&mut *current, &mut *last);
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn double_buffered() {
+        let events = Events::new();
+        assert!(events.read::<u32>().is_empty());
+
+        events.send(1u32);
+        events.send(2u32);
+        events.send("str");
+        assert!(events.read::<u32>().is_empty());
+
+        events.advance();
+        assert_eq!(events.read::<u32>(), [1, 2]);
+        assert_eq!(events.read::<u32>(), [1, 2]);
+        assert_eq!(events.len::<&str>(), 1);
+        assert_eq!(events.len::<u64>(), 0);
+
+        events.send(3u32);
+        events.advance();
+        assert_eq!(events.read::<u32>(), [3]);
+        assert_eq!(events.len::<&str>(), 0);
+
+        events.advance();
+        assert!(events.read::<u32>().is_empty());
+    }
+}
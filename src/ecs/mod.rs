@@ -51,7 +51,49 @@
 //! ## System and Dispatcher
 //!
 //! __TODO__
+//!
+//! ## Change Detection
+//!
+//! The `World` tracks the ticks of global frame counter when components are added,
+//! or borrowed mutably, so systems could only process the changed ones:
+//!
+//! ```rust,ignore
+//! for ent in world.changed::<Transform>() { ... }
+//!
+//! // Starts a new frame, the changes above will not be reported any more.
+//! world.advance();
+//! ```
+//!
+//! ## Events
+//!
+//! Besides sharing components, systems could also communicate with one-shot events,
+//! e.g. collision happened, through the double-buffered `Events` bus. The events sent
+//! during a frame are readable by every reader in the next frame.
+//!
+//! ## Serialization
+//!
+//! The entities and their components could be saved into a persistent format, e.g.
+//! for save games. Components opt in by implementing `SerializableComponent` with
+//! a stable name, and being registered with `World::register_serializable`:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Position(f32, f32, f32);
+//!
+//! impl SerializableComponent for Position {
+//!     const NAME: &'static str = "Position";
+//! }
+//!
+//! world.register_serializable::<Position>();
+//! world.save(File::create("save.json")?)?;
+//!
+//! // The entities are re-created with new handles, and the references between
+//! // components are fixed with `SerializableComponent::remap`.
+//! let report = world.load(File::open("save.json")?)?;
+//! let player = report.entities.get(saved_player);
+//! ```
 
+pub mod errors;
 pub mod bitset;
 pub mod cell;
 
@@ -59,10 +101,14 @@ pub mod cell;
 pub mod component;
 pub mod world;
 pub mod system;
+pub mod serialize;
+pub mod events;
 
 pub use self::component::{Component, ComponentArena, HashMapArena, VecArena};
 pub use self::world::{Arena, ArenaMut, Fetch, FetchMut, View, World};
 pub use self::system::System;
+pub use self::serialize::{EntityMap, LoadReport, SerializableComponent};
+pub use self::events::Events;
 
 /// `Entity` type, as seen by the user, its a alias to `Handle` internally.
 pub type Entity = ::utils::handle::Handle;
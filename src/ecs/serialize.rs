@@ -0,0 +1,378 @@
+//! Serializes entities and their components into a persistent format, e.g. save games.
+//!
+//! Components opt in by implementing `SerializableComponent`, and being registered with
+//! `World::register_serializable`. The world is written as JSON document:
+//!
+//! ```json
+//! {
+//!   "entities": [
+//!     { "id": { "index": 1, "version": 1 }, "components": { "Transform": { ... } } }
+//!   ]
+//! }
+//! ```
+//!
+//! The entities will be re-created with different handles when loading, the references
+//! between components should be fixed with `SerializableComponent::remap`.
+
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use super::{Arena, ArenaMut, Component, Entity, World};
+use super::errors::*;
+
+/// Components that could be saved and loaded with `World::save` and `World::load`.
+pub trait SerializableComponent: Component + Serialize + DeserializeOwned {
+    /// The name that identifies this component type in serialized data, which should be
+    /// stable between versions of game.
+    const NAME: &'static str;
+
+    /// Remaps the entities referenced by this component after loading, since the saved
+    /// entities have been re-created with different handles.
+    fn remap(&mut self, _: &EntityMap) {}
+}
+
+/// The mappings from saved entities to the re-created ones.
+#[derive(Debug, Clone, Default)]
+pub struct EntityMap {
+    entities: HashMap<Entity, Entity>,
+}
+
+impl EntityMap {
+    /// Gets the re-created entity of saved one, `None` is returned if the entity is not
+    /// in the saved data.
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<Entity> {
+        self.entities.get(&ent).cloned()
+    }
+
+    /// Returns the number of loaded entities.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns true if there is no entity loaded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, saved: Entity, ent: Entity) {
+        self.entities.insert(saved, ent);
+    }
+
+    /// Gets the iterator over the pairs of saved and re-created entities.
+    #[inline]
+    pub fn iter(&self) -> ::std::collections::hash_map::Iter<Entity, Entity> {
+        self.entities.iter()
+    }
+}
+
+/// The result of `World::load`.
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// The mappings from saved entities to the re-created ones.
+    pub entities: EntityMap,
+    /// The components that have been skipped since their types are not registered as
+    /// serializable, with the re-created entity and the name of component type.
+    pub skipped: Vec<(Entity, String)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WorldData {
+    entities: Vec<EntityData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntityData {
+    id: Entity,
+    components: BTreeMap<String, serde_json::Value>,
+}
+
+/// The type-erased serialization functions of component.
+#[derive(Clone, Copy)]
+pub(crate) struct Serializer {
+    id: TypeId,
+    name: &'static str,
+    save: fn(&World, Entity) -> Option<serde_json::Result<serde_json::Value>>,
+    load: fn(&mut World, Entity, serde_json::Value) -> serde_json::Result<()>,
+    remap: fn(&World, Entity, &EntityMap),
+}
+
+impl Serializer {
+    pub fn new<T>() -> Self
+    where
+        T: SerializableComponent,
+    {
+        Serializer {
+            id: TypeId::of::<T>(),
+            name: T::NAME,
+            save: save::<T>,
+            load: load::<T>,
+            remap: remap::<T>,
+        }
+    }
+}
+
+fn save<T>(world: &World, ent: Entity) -> Option<serde_json::Result<serde_json::Value>>
+where
+    T: SerializableComponent,
+{
+    if world.has::<T>(ent) {
+        let arena = world.arena::<T>();
+        let v = unsafe { arena.get_unchecked(ent) };
+        Some(serde_json::to_value(v))
+    } else {
+        None
+    }
+}
+
+fn load<T>(world: &mut World, ent: Entity, value: serde_json::Value) -> serde_json::Result<()>
+where
+    T: SerializableComponent,
+{
+    let v: T = serde_json::from_value(value)?;
+    world.add(ent, v);
+    Ok(())
+}
+
+fn remap<T>(world: &World, ent: Entity, entities: &EntityMap)
+where
+    T: SerializableComponent,
+{
+    let mut arena = world.arena_mut::<T>();
+    if let Some(v) = arena.get_mut(ent) {
+        v.remap(entities);
+    }
+}
+
+impl World {
+    /// Registers a new component type which could be saved and loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another component type has been registered with the same name.
+    pub fn register_serializable<T>(&mut self)
+    where
+        T: SerializableComponent,
+    {
+        self.register::<T>();
+
+        if let Some(v) = self.serializers.get(T::NAME) {
+            if v.id != TypeId::of::<T>() {
+                panic!("Serializable component {} has been registered.", T::NAME);
+            }
+
+            return;
+        }
+
+        self.serializers.insert(T::NAME, Serializer::new::<T>());
+    }
+
+    /// Writes all the alive entities, with their serializable components into `writer`.
+    /// The components which are not registered as serializable are ignored.
+    pub fn save<W>(&self, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut serializers: Vec<_> = self.serializers.values().collect();
+        serializers.sort_by_key(|v| v.name);
+
+        let mut data = WorldData {
+            entities: Vec::with_capacity(self.len()),
+        };
+
+        for ent in self.iter() {
+            let mut components = BTreeMap::new();
+            for v in &serializers {
+                if let Some(value) = (v.save)(self, ent) {
+                    components.insert(v.name.to_owned(), value?);
+                }
+            }
+
+            data.entities.push(EntityData {
+                id: ent,
+                components: components,
+            });
+        }
+
+        serde_json::to_writer_pretty(writer, &data)?;
+        Ok(())
+    }
+
+    /// Re-creates the entities from `reader`, which was written by `World::save`. The
+    /// loaded entities are added besides the existing ones.
+    ///
+    /// The components whose type is not registered as serializable, e.g. a component
+    /// that has been removed from game, are skipped and reported in `LoadReport` instead
+    /// of failing the whole loading. Nothing will be created if an error is returned.
+    pub fn load<R>(&mut self, reader: R) -> Result<LoadReport>
+    where
+        R: Read,
+    {
+        let data: WorldData = serde_json::from_reader(reader)?;
+
+        let mut report = LoadReport::default();
+        for v in &data.entities {
+            let ent = self.create();
+            report.entities.insert(v.id, ent);
+        }
+
+        let mut loaded = Vec::new();
+        for v in data.entities {
+            let ent = report.entities.get(v.id).unwrap();
+
+            for (name, value) in v.components {
+                let serializer = match self.serializers.get(name.as_str()) {
+                    Some(serializer) => *serializer,
+                    None => {
+                        report.skipped.push((ent, name));
+                        continue;
+                    }
+                };
+
+                if let Err(err) = (serializer.load)(self, ent, value) {
+                    for (_, v) in report.entities.iter() {
+                        self.free(*v);
+                    }
+
+                    bail!(ErrorKind::MalformedComponent(name, err.to_string()));
+                }
+
+                loaded.push((ent, serializer));
+            }
+        }
+
+        for (ent, serializer) in loaded {
+            (serializer.remap)(self, ent, &report.entities);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ecs::{HashMapArena, VecArena};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Position(f32, f32);
+
+    impl Component for Position {
+        type Arena = VecArena<Position>;
+    }
+
+    impl SerializableComponent for Position {
+        const NAME: &'static str = "Position";
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Target(Option<Entity>);
+
+    impl Component for Target {
+        type Arena = HashMapArena<Target>;
+    }
+
+    impl SerializableComponent for Target {
+        const NAME: &'static str = "Target";
+
+        fn remap(&mut self, entities: &EntityMap) {
+            self.0 = self.0.and_then(|v| entities.get(v));
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct Velocity;
+
+    impl Component for Velocity {
+        type Arena = HashMapArena<Velocity>;
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut world = World::new();
+        world.register_serializable::<Position>();
+        world.register_serializable::<Target>();
+        world.register::<Velocity>();
+
+        // Makes sure that the entities are re-created with different handles.
+        let dummy = world.create();
+        world.free(dummy);
+
+        let e1 = world.build().with(Position(1.0, 2.0)).finish();
+        let e2 = world
+            .build()
+            .with(Position(3.0, 4.0))
+            .with(Target(Some(e1)))
+            .with(Velocity)
+            .finish();
+
+        let mut bytes = Vec::new();
+        world.save(&mut bytes).unwrap();
+
+        let mut loaded = World::new();
+        loaded.register_serializable::<Position>();
+        loaded.register_serializable::<Target>();
+        loaded.create();
+
+        let report = loaded.load(&bytes[..]).unwrap();
+        assert_eq!(report.entities.len(), 2);
+        assert!(report.skipped.is_empty());
+        assert_eq!(loaded.len(), 3);
+
+        let n1 = report.entities.get(e1).unwrap();
+        let n2 = report.entities.get(e2).unwrap();
+        assert!(n1 != e1 && n2 != e2);
+
+        assert_eq!(loaded.get::<Position>(n1), Some(Position(1.0, 2.0)));
+        assert_eq!(loaded.get::<Position>(n2), Some(Position(3.0, 4.0)));
+        assert!(!loaded.has::<Target>(n1));
+        assert_eq!(loaded.arena::<Target>().get(n2), Some(&Target(Some(n1))));
+    }
+
+    #[test]
+    fn unregistered() {
+        let mut world = World::new();
+        world.register_serializable::<Position>();
+        world.register_serializable::<Target>();
+
+        let e1 = world.create();
+        let e2 = world.build().with(Target(Some(e1))).finish();
+        world.add(e1, Position(1.0, 2.0));
+
+        let mut bytes = Vec::new();
+        world.save(&mut bytes).unwrap();
+
+        // The `Target` component is no longer registered.
+        let mut loaded = World::new();
+        loaded.register_serializable::<Position>();
+
+        let report = loaded.load(&bytes[..]).unwrap();
+        let n2 = report.entities.get(e2).unwrap();
+        assert_eq!(report.skipped, [(n2, "Target".to_owned())]);
+        assert_eq!(loaded.len(), 2);
+
+        let n1 = report.entities.get(e1).unwrap();
+        assert_eq!(loaded.get::<Position>(n1), Some(Position(1.0, 2.0)));
+    }
+
+    #[test]
+    fn malformed() {
+        let mut world = World::new();
+        world.register_serializable::<Position>();
+
+        let json = r#"{ "entities": [
+            { "id": { "index": 0, "version": 1 }, "components": { "Position": [1.0, 2.0] } },
+            { "id": { "index": 1, "version": 1 }, "components": { "Position": "none" } }
+        ] }"#;
+
+        assert!(world.load(json.as_bytes()).is_err());
+        assert_eq!(world.len(), 0);
+    }
+}
@@ -7,6 +7,7 @@ use utils::{HandleIndex, HandleIter, HandlePool};
 use super::*;
 use super::bitset::BitSet;
 use super::cell::{Ref, RefCell, RefMut};
+use super::serialize::Serializer;
 
 /// The `World` struct are used to manage the whole entity-component system, It keeps
 /// tracks of the state of every created `Entity`s. All memthods are supposed to be
@@ -17,6 +18,8 @@ pub struct World {
 
     registry: HashMap<TypeId, usize>,
     arenas: Vec<Entry>,
+    tick: u32,
+    pub(crate) serializers: HashMap<&'static str, Serializer>,
 }
 
 /// Make sure that `World` can be used on multi-threads.
@@ -31,6 +34,8 @@ impl World {
             masks: Vec::new(),
             registry: HashMap::new(),
             arenas: Vec::new(),
+            tick: 1,
+            serializers: HashMap::new(),
         }
     }
 
@@ -50,6 +55,26 @@ impl World {
         self.arenas.push(Entry::new::<T>());
     }
 
+    /// Returns the current tick of the global frame counter, which is used to detect
+    /// the changes of components.
+    #[inline]
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Advances the global frame counter. The changes made before advancing will not
+    /// be reported by `World::changed` any more.
+    ///
+    /// It should be called once per frame by the owner of world, e.g. `Scene` advances
+    /// its world when the first camera of a frame is rendered.
+    pub fn advance(&mut self) {
+        self.tick += 1;
+
+        for v in &self.arenas {
+            v.ticks.borrow_mut().removed.clear();
+        }
+    }
+
     /// Creates and returns a unused Entity handle.
     pub fn create(&mut self) -> Entity {
         let ent = self.entities.create();
@@ -91,6 +116,7 @@ impl World {
                 let arena = &v.arena;
                 let eraser = &mut v.eraser;
                 eraser(arena.as_ref(), ent.index());
+                v.ticks.borrow_mut().removed.push(ent);
             }
 
             self.masks[ent.index() as usize].clear();
@@ -109,6 +135,12 @@ impl World {
 
         if self.is_alive(ent) {
             self.masks[ent.index() as usize].insert(index);
+
+            self.arenas[index]
+                .ticks
+                .borrow_mut()
+                .on_added(ent.index(), self.tick);
+
             self.cell::<T>().borrow_mut().insert(ent.index(), value)
         } else {
             None
@@ -134,6 +166,7 @@ impl World {
 
         if self.masks[ent.index() as usize].contains(index) {
             self.masks[ent.index() as usize].remove(index);
+            self.arenas[index].ticks.borrow_mut().removed.push(ent);
             self.cell::<T>().borrow_mut().remove(ent.index())
         } else {
             None
@@ -175,8 +208,11 @@ impl World {
     where
         T: Component,
     {
+        let index = self.index::<T>();
         FetchMut {
             arena: self.cell::<T>().borrow_mut(),
+            ticks: self.arenas[index].ticks.borrow_mut(),
+            tick: self.tick,
         }
     }
 
@@ -197,6 +233,75 @@ impl World {
         }
     }
 
+    /// Returns the entities whose component `T` has been added or modified since last
+    /// `World::advance`. A component is regarded as modified once it has been borrowed
+    /// mutably with `ArenaMut::get_mut` or `ArenaMut::get_unchecked_mut`.
+    ///
+    /// The iterator works on a snapshot, so the world could be modified during iterating.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena with type `T` is currently borrowed mutably.
+    #[inline]
+    pub fn changed<T>(&self) -> ::std::vec::IntoIter<Entity>
+    where
+        T: Component,
+    {
+        self.changed_since::<T>(self.tick)
+    }
+
+    /// Returns the entities whose component `T` has been added or modified at or after
+    /// `tick`, which is usually recorded with `World::tick` at the last run of a system.
+    pub fn changed_since<T>(&self, tick: u32) -> ::std::vec::IntoIter<Entity>
+    where
+        T: Component,
+    {
+        let index = self.index::<T>();
+        let ticks = self.arenas[index].ticks.borrow();
+        self.filter(index, |v| ticks.modified(v) >= tick)
+    }
+
+    /// Returns the tick when component `T` of `ent` has been added or modified lastly,
+    /// or `None` if `ent` does not have component `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena with type `T` is currently borrowed mutably.
+    pub fn modified<T>(&self, ent: Entity) -> Option<u32>
+    where
+        T: Component,
+    {
+        let index = self.index::<T>();
+        if !self.is_alive(ent) || !self.masks[ent.index() as usize].contains(index) {
+            return None;
+        }
+
+        let ticks = self.arenas[index].ticks.borrow();
+        Some(ticks.modified(ent.index()))
+    }
+
+    /// Returns the entities which component `T` has been added to since last
+    /// `World::advance`.
+    pub fn added<T>(&self) -> ::std::vec::IntoIter<Entity>
+    where
+        T: Component,
+    {
+        let index = self.index::<T>();
+        let ticks = self.arenas[index].ticks.borrow();
+        self.filter(index, |v| ticks.added(v) >= self.tick)
+    }
+
+    /// Returns the entities which component `T` has been removed from since last
+    /// `World::advance`, including the freed entities.
+    pub fn removed<T>(&self) -> ::std::vec::IntoIter<Entity>
+    where
+        T: Component,
+    {
+        let index = self.index::<T>();
+        let ticks = self.arenas[index].ticks.borrow();
+        ticks.removed.clone().into_iter()
+    }
+
     /// Gets immutable `World` iterator into all of the `Entity`s.
     #[inline]
     pub fn iter(&self) -> HandleIter {
@@ -212,6 +317,17 @@ impl World {
             .expect("Component has NOT been registered.")
     }
 
+    fn filter<F>(&self, index: usize, func: F) -> ::std::vec::IntoIter<Entity>
+    where
+        F: Fn(HandleIndex) -> bool,
+    {
+        let entities: Vec<_> = self.iter()
+            .filter(|v| self.masks[v.index() as usize].contains(index) && func(v.index()))
+            .collect();
+
+        entities.into_iter()
+    }
+
     pub(crate) fn view(&self, mask: BitSet) -> View {
         View {
             world: self,
@@ -239,6 +355,7 @@ impl World {
 struct Entry {
     arena: Box<Any + Send + Sync>,
     eraser: Box<FnMut(&Any, HandleIndex) -> () + Send + Sync>,
+    ticks: RefCell<ChangeTicks>,
 }
 
 impl Entry {
@@ -255,7 +372,48 @@ impl Entry {
         Entry {
             arena: Box::new(RefCell::new(T::Arena::new())),
             eraser: eraser,
+            ticks: RefCell::new(ChangeTicks::default()),
+        }
+    }
+}
+
+/// The ticks when components have been added or modified, indexed by entity, and the
+/// components removed during current frame.
+#[derive(Default)]
+pub(crate) struct ChangeTicks {
+    added: Vec<u32>,
+    modified: Vec<u32>,
+    removed: Vec<Entity>,
+}
+
+impl ChangeTicks {
+    fn on_added(&mut self, index: HandleIndex, tick: u32) {
+        Self::set(&mut self.added, index, tick);
+        Self::set(&mut self.modified, index, tick);
+    }
+
+    #[inline]
+    fn on_modified(&mut self, index: HandleIndex, tick: u32) {
+        Self::set(&mut self.modified, index, tick);
+    }
+
+    #[inline]
+    fn added(&self, index: HandleIndex) -> u32 {
+        self.added.get(index as usize).cloned().unwrap_or(0)
+    }
+
+    #[inline]
+    fn modified(&self, index: HandleIndex) -> u32 {
+        self.modified.get(index as usize).cloned().unwrap_or(0)
+    }
+
+    #[inline]
+    fn set(ticks: &mut Vec<u32>, index: HandleIndex, tick: u32) {
+        if ticks.len() <= index as usize {
+            ticks.resize(index as usize + 1, 0);
         }
+
+        ticks[index as usize] = tick;
     }
 }
 
@@ -302,6 +460,8 @@ where
     T: Component,
 {
     arena: RefMut<'a, T::Arena>,
+    ticks: RefMut<'a, ChangeTicks>,
+    tick: u32,
 }
 
 impl<'a, T> Arena<T> for FetchMut<'a, T>
@@ -325,11 +485,17 @@ where
 {
     #[inline]
     fn get_mut(&mut self, ent: Entity) -> Option<&mut T> {
-        self.arena.get_mut(ent.index())
+        let v = self.arena.get_mut(ent.index());
+        if v.is_some() {
+            self.ticks.on_modified(ent.index(), self.tick);
+        }
+
+        v
     }
 
     #[inline]
     unsafe fn get_unchecked_mut(&mut self, ent: Entity) -> &mut T {
+        self.ticks.on_modified(ent.index(), self.tick);
         self.arena.get_unchecked_mut(ent.index())
     }
 }
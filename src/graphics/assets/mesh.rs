@@ -1,16 +1,19 @@
-//! Immutable or dynamic vertex and index data.
+//! Static or dynamic vertex and index data.
 
 use graphics::MAX_VERTEX_ATTRIBUTES;
 use graphics::assets::shader::Attribute;
 use graphics::errors::*;
+use utils::Aabb3;
 
 impl_handle!(MeshHandle);
 
 /// The setup parameters of mesh object.
 #[derive(Debug, Clone)]
 pub struct MeshSetup {
-    /// Usage hints.
+    /// Usage hint of the vertex buffer.
     pub hint: BufferHint,
+    /// Usage hint of the index buffer, it's the same as `hint` if not specified.
+    pub index_hint: Option<BufferHint>,
     /// How a single vertex structure looks like.
     pub layout: VertexLayout,
     /// Index format
@@ -23,23 +26,34 @@ pub struct MeshSetup {
     pub num_idxes: usize,
     /// The start indices of sub-meshes.
     pub sub_mesh_offsets: Vec<usize>,
+    /// The bounding box of vertices in local space. It will be calculated from the
+    /// positions of initial vertices if not specified.
+    pub aabb: Option<Aabb3>,
 }
 
 impl Default for MeshSetup {
     fn default() -> Self {
         MeshSetup {
-            hint: BufferHint::Immutable,
+            hint: BufferHint::Static,
+            index_hint: None,
             layout: VertexLayout::default(),
             index_format: IndexFormat::U16,
             primitive: Primitive::Triangles,
             num_verts: 0,
             num_idxes: 0,
             sub_mesh_offsets: Vec::new(),
+            aabb: None,
         }
     }
 }
 
 impl MeshSetup {
+    /// Gets the usage hint of the index buffer.
+    #[inline(always)]
+    pub fn index_buffer_hint(&self) -> BufferHint {
+        self.index_hint.unwrap_or(self.hint)
+    }
+
     #[inline(always)]
     pub fn vertex_buffer_len(&self) -> usize {
         self.num_verts * self.layout.stride() as usize
@@ -50,6 +64,30 @@ impl MeshSetup {
         self.num_idxes * self.index_format.len() as usize
     }
 
+    /// Calculates the bounding box from the float `Position` attribute of `verts`.
+    pub fn calculate_aabb(&self, verts: &[u8]) -> Option<Aabb3> {
+        let element = self.layout.element(Attribute::Position)?;
+        if element.format != VertexFormat::Float || element.size < 2 {
+            return None;
+        }
+
+        let offset = self.layout.offset(Attribute::Position)? as usize;
+        let stride = self.layout.stride() as usize;
+        let num = ::std::cmp::min(self.num_verts, verts.len() / stride);
+
+        let read = |pos: usize| {
+            let v = &verts[pos..pos + 4];
+            let bits = v[0] as u32 | (v[1] as u32) << 8 | (v[2] as u32) << 16 | (v[3] as u32) << 24;
+            f32::from_bits(bits)
+        };
+
+        Aabb3::from_points((0..num).map(|i| {
+            let pos = i * stride + offset;
+            let z = if element.size > 2 { read(pos + 8) } else { 0.0 };
+            [read(pos), read(pos + 4), z]
+        }))
+    }
+
     pub fn validate(&self) -> Result<()> {
         for v in &self.sub_mesh_offsets {
             if *v >= self.num_idxes {
@@ -69,13 +107,63 @@ pub enum MeshIndex {
     All,
 }
 
+impl MeshIndex {
+    /// Gets the range `(from, len)` of indices selected in the index buffer with
+    /// `num_idxes` indices and sub-meshes starting at `sub_mesh_offsets`. An error is
+    /// returned if the range is out of bounds.
+    pub(crate) fn range(
+        &self,
+        num_idxes: usize,
+        sub_mesh_offsets: &[usize],
+    ) -> Result<(usize, usize)> {
+        match *self {
+            MeshIndex::Ptr(from, len) => {
+                if from.checked_add(len).map(|v| v > num_idxes).unwrap_or(true) {
+                    bail!(format!(
+                        "The {} indices from {} are out of bounds of {} indices.",
+                        len, from, num_idxes
+                    ));
+                }
+
+                Ok((from, len))
+            }
+            MeshIndex::SubMesh(index) => {
+                let num = sub_mesh_offsets.len();
+                if index >= num {
+                    bail!(format!(
+                        "The sub-mesh {} is out of bounds of {} sub-meshes.",
+                        index, num
+                    ));
+                }
+
+                let from = sub_mesh_offsets[index];
+                let to = if index == (num - 1) {
+                    num_idxes
+                } else {
+                    sub_mesh_offsets[index + 1]
+                };
+
+                if from > to || to > num_idxes {
+                    bail!(format!(
+                        "The sub-mesh {} selects indices [{}, {}) out of {} indices.",
+                        index, from, to, num_idxes
+                    ));
+                }
+
+                Ok((from, to - from))
+            }
+            MeshIndex::All => Ok((0, num_idxes)),
+        }
+    }
+}
+
 /// Hint abouts the intended update strategy of the data.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BufferHint {
     /// The resource is initialized with data and cannot be changed later, this
     /// is the most common and most efficient usage. Optimal for render targets
-    /// and resourced memory.
-    Immutable,
+    /// and resourced memory. Updates of static buffers are rejected.
+    Static,
     /// The resource is initialized without data, but will be be updated by the
     /// CPU in each frame.
     Stream,
@@ -85,7 +173,10 @@ pub enum BufferHint {
 }
 
 /// Defines how the input vertex data is used to assemble primitives.
-#[derive(Debug, Clone, Copy)]
+///
+/// Notes that `RenderState::polygon_mode` only changes the rasterization of triangles,
+/// `Points`, `Lines` and `LineStrip` are always drawn as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Primitive {
     /// Separate points.
     Points,
@@ -100,23 +191,26 @@ pub enum Primitive {
 }
 
 impl Primitive {
+    /// Gets the number of primitives assembled with `indices`.
     pub fn assemble(&self, indices: u32) -> u32 {
         match *self {
             Primitive::Points => indices,
             Primitive::Lines => indices / 2,
-            Primitive::LineStrip => indices - 1,
+            Primitive::LineStrip => indices.saturating_sub(1),
             Primitive::Triangles => indices / 3,
-            Primitive::TriangleStrip => indices - 2,
+            Primitive::TriangleStrip => indices.saturating_sub(2),
         }
     }
 
+    /// Gets the number of triangles assembled with `indices`, it's zero for points and
+    /// lines.
     pub fn assemble_triangles(&self, indices: u32) -> u32 {
         match *self {
             Primitive::Points => 0,
             Primitive::Lines => 0,
             Primitive::LineStrip => 0,
             Primitive::Triangles => indices / 3,
-            Primitive::TriangleStrip => indices - 2,
+            Primitive::TriangleStrip => indices.saturating_sub(2),
         }
     }
 }
@@ -302,6 +396,24 @@ fn size_of_vertex(format: VertexFormat) -> u8 {
 mod test {
     use super::*;
 
+    #[test]
+    fn index_range() {
+        let offsets = [0, 6, 12];
+        assert_eq!(MeshIndex::All.range(18, &offsets).unwrap(), (0, 18));
+        assert_eq!(MeshIndex::Ptr(3, 15).range(18, &offsets).unwrap(), (3, 15));
+        assert_eq!(MeshIndex::SubMesh(1).range(18, &offsets).unwrap(), (6, 6));
+        assert_eq!(MeshIndex::SubMesh(2).range(18, &offsets).unwrap(), (12, 6));
+
+        // Off-by-one ranges are rejected.
+        assert!(MeshIndex::Ptr(3, 16).range(18, &offsets).is_err());
+        assert!(MeshIndex::Ptr(::std::usize::MAX, 2).range(18, &offsets).is_err());
+        assert!(MeshIndex::SubMesh(3).range(18, &offsets).is_err());
+        assert!(MeshIndex::SubMesh(0).range(18, &[]).is_err());
+
+        // Sub-meshes should be sorted by their offsets.
+        assert!(MeshIndex::SubMesh(0).range(18, &[6, 0]).is_err());
+    }
+
     #[test]
     fn basic() {
         let layout = VertexLayout::build()
@@ -321,6 +433,19 @@ mod test {
         assert_eq!(layout.element(Attribute::Normal), None);
     }
 
+    #[test]
+    fn primitives() {
+        assert_eq!(Primitive::Points.assemble(6), 6);
+        assert_eq!(Primitive::Lines.assemble(6), 3);
+        assert_eq!(Primitive::LineStrip.assemble(6), 5);
+        assert_eq!(Primitive::LineStrip.assemble(0), 0);
+        assert_eq!(Primitive::TriangleStrip.assemble(1), 0);
+
+        assert_eq!(Primitive::Lines.assemble_triangles(6), 0);
+        assert_eq!(Primitive::Triangles.assemble_triangles(6), 2);
+        assert_eq!(Primitive::TriangleStrip.assemble_triangles(6), 4);
+    }
+
     #[test]
     fn rewrite() {
         let layout = VertexLayout::build()
@@ -534,5 +659,29 @@ pub mod macros {
             let _b = Vertex2::as_bytes(&[]);
             assert_eq!(layout.stride() as usize, ::std::mem::size_of::<Vertex2>());
         }
+
+        #[test]
+        fn aabb() {
+            let verts = [
+                Vertex::new([1.0, -2.0, 0.5], [0.0, 0.0]),
+                Vertex::new([-1.0, 3.0, 0.0], [0.0, 0.0]),
+            ];
+
+            let mut setup = MeshSetup::default();
+            setup.layout = Vertex::layout();
+            setup.num_verts = 2;
+
+            let aabb = setup.calculate_aabb(Vertex::as_bytes(&verts)).unwrap();
+            assert_eq!(aabb.min, [-1.0, -2.0, 0.0].into());
+            assert_eq!(aabb.max, [1.0, 3.0, 0.5].into());
+            assert_eq!(setup.calculate_aabb(&[]), None);
+
+            let verts = [Vertex2::new([1.0, -2.0], [0, 0, 0, 0], [0, 0])];
+            setup.layout = Vertex2::layout();
+            setup.num_verts = 1;
+
+            let aabb = setup.calculate_aabb(Vertex2::as_bytes(&verts)).unwrap();
+            assert_eq!(aabb.min, [1.0, -2.0, 0.0].into());
+        }
     }
 }
@@ -4,8 +4,9 @@ use std::sync::{Arc, RwLock};
 use std::marker::PhantomData;
 
 use resource;
+use utils::Aabb3;
 use graphics::assets::mesh::*;
-use graphics::backend::frame::{DoubleFrame, PreFrameTask};
+use graphics::backend::frame::{MultiFrame, PreFrameTask};
 
 /// Parsed mesh from `MeshParser`.
 pub struct MeshData {
@@ -27,10 +28,12 @@ pub trait MeshParser {
 }
 
 #[doc(hidden)]
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq)]
 pub(crate) enum MeshState {
     NotReady,
-    Ready,
+    /// The bounding box, the vertex layout, the number of indices and the start
+    /// indices of sub-meshes.
+    Ready(Option<Aabb3>, VertexLayout, usize, Vec<usize>),
     Err(String),
 }
 
@@ -42,7 +45,8 @@ where
     handle: MeshHandle,
     setup: MeshSetup,
     state: Arc<RwLock<MeshState>>,
-    frames: Arc<DoubleFrame>,
+    frames: Arc<MultiFrame>,
+    promise: resource::ResourcePromise,
     _phantom: PhantomData<T>,
 }
 
@@ -54,18 +58,34 @@ where
         handle: MeshHandle,
         state: Arc<RwLock<MeshState>>,
         setup: MeshSetup,
-        frames: Arc<DoubleFrame>,
+        frames: Arc<MultiFrame>,
+        promise: resource::ResourcePromise,
     ) -> Self {
         MeshLoader {
             handle: handle,
             setup: setup,
             state: state,
             frames: frames,
+            promise: promise,
             _phantom: PhantomData,
         }
     }
 }
 
+impl<T> Drop for MeshLoader<T>
+where
+    T: MeshParser,
+{
+    fn drop(&mut self) {
+        // The loader might be dropped without finishing, e.g. the parser panics.
+        if let Ok(mut state) = self.state.write() {
+            if *state == MeshState::NotReady {
+                *state = MeshState::Err("The loading of mesh was interrupted.".into());
+            }
+        }
+    }
+}
+
 impl<T> resource::ResourceAsyncLoader for MeshLoader<T>
 where
     T: MeshParser + Send + Sync + 'static,
@@ -81,13 +101,23 @@ where
                     self.setup.num_idxes = mesh.num_idxes;
                     self.setup.sub_mesh_offsets = mesh.sub_mesh_offsets;
 
+                    if self.setup.aabb.is_none() {
+                        self.setup.aabb = self.setup.calculate_aabb(&mesh.verts);
+                    }
+
+                    let aabb = self.setup.aabb;
+                    let layout = self.setup.layout;
+                    let num_idxes = self.setup.num_idxes;
+                    let sub_meshes = self.setup.sub_mesh_offsets.clone();
+
                     let mut frame = self.frames.front();
                     let vptr = Some(frame.buf.extend_from_slice(&mesh.verts));
                     let iptr = Some(frame.buf.extend_from_slice(&mesh.idxes));
-                    let task = PreFrameTask::CreateMesh(self.handle, self.setup, vptr, iptr);
+                    let setup = self.setup.clone();
+                    let task = PreFrameTask::CreateMesh(self.handle, setup, vptr, iptr);
                     frame.pre.push(task);
 
-                    MeshState::Ready
+                    MeshState::Ready(aabb, layout, num_idxes, sub_meshes)
                 }
                 Err(error) => {
                     let error = format!("Failed to load mesh at {:?}.\n{:?}", path, error);
@@ -100,6 +130,12 @@ where
             }
         };
 
+        let result = match state {
+            MeshState::Err(ref error) => Err(error.clone()),
+            _ => Ok(()),
+        };
+
         *self.state.write().unwrap() = state;
+        self.promise.resolve(result);
     }
 }
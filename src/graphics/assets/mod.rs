@@ -1,7 +1,12 @@
 pub mod surface;
 pub mod shader;
+pub mod shader_loader;
+pub mod shader_preprocessor;
 pub mod texture;
 pub mod texture_loader;
+pub mod texture_container;
 #[macro_use]
 pub mod mesh;
 pub mod mesh_loader;
+pub mod uniform_buffer;
+pub mod storage_buffer;
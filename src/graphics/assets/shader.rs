@@ -4,7 +4,8 @@ use std::collections::HashMap;
 
 use math;
 use graphics::{TextureHandle, MAX_VERTEX_ATTRIBUTES};
-use utils::HashValue;
+use graphics::errors::*;
+use utils::{Color, HashValue};
 
 use super::mesh::VertexLayout;
 
@@ -12,21 +13,181 @@ impl_handle!(ShaderHandle);
 
 /// A `ShaderObject` encapusulate all the informations we need to configurate
 /// OpenGL before real drawing, like shaders, render states, etc.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct ShaderSetup {
     pub render_state: RenderState,
     pub uniform_variables: HashMap<String, UniformVariableType>,
+    /// The uniform blocks declared by shader, with the size of block in bytes. The size
+    /// follows the `std140` layout, and it's validated against the linked program when
+    /// the shader is created. The contents of blocks are sourced from uniform buffers
+    /// that bound with `DrawCall::set_uniform_buffer`.
+    pub uniform_blocks: HashMap<String, usize>,
+    /// The names of shader storage blocks declared by shader, which are sourced from
+    /// storage buffers that bound with `DrawCall::set_storage_buffer`. Storage blocks
+    /// require the `compute_shader` capability.
+    pub storage_blocks: Vec<String>,
     pub layout: AttributeLayout,
+    /// The number of color outputs written by fragment shader. Shaders with multiple
+    /// outputs write `gl_FragData[i]`, which goes into the color attachment at slot `i`
+    /// of framebuffer. It's one by default.
+    pub outputs: usize,
+    /// The macros which are injected as `#define NAME VALUE` after the `#version`
+    /// directive of sources, e.g. to toggle features of a shader permutation.
+    ///
+    /// The `#include "path"` directives in sources are resolved against the resource
+    /// filesystem as well, see `GraphicsSystemShared::preprocess_shader`.
+    pub defines: Vec<(String, String)>,
     pub vs: String,
     pub fs: String,
 }
 
+impl Default for ShaderSetup {
+    fn default() -> Self {
+        ShaderSetup {
+            render_state: RenderState::default(),
+            uniform_variables: HashMap::new(),
+            uniform_blocks: HashMap::new(),
+            storage_blocks: Vec::new(),
+            layout: AttributeLayout::default(),
+            outputs: 1,
+            defines: Vec::new(),
+            vs: String::new(),
+            fs: String::new(),
+        }
+    }
+}
+
+/// The setup of compute shader, which runs outside of the render pipeline and is
+/// launched with `Command::Dispatch`. The results are usually written into storage
+/// buffers, which could be read by the following draw calls or dispatches.
+#[derive(Debug, Clone, Default)]
+pub struct ComputeShaderSetup {
+    pub uniform_variables: HashMap<String, UniformVariableType>,
+    /// The names of shader storage blocks declared by compute shader, which are sourced
+    /// from storage buffers that bound with `Dispatch::set_storage_buffer`.
+    pub storage_blocks: Vec<String>,
+    /// The macros which are injected after the `#version` directive of source.
+    pub defines: Vec<(String, String)>,
+    pub cs: String,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ShaderState {
     pub render_state: RenderState,
     pub uniform_variables: HashMap<HashValue<str>, UniformVariableType>,
     pub uniform_variable_names: HashMap<HashValue<str>, String>,
+    pub uniform_blocks: HashMap<HashValue<str>, usize>,
+    pub uniform_block_names: HashMap<HashValue<str>, String>,
+    pub storage_block_names: HashMap<HashValue<str>, String>,
     pub layout: AttributeLayout,
+    /// The shader is a compute shader, which could only be used by `Command::Dispatch`.
+    pub compute: bool,
+    /// The shader failed to compile or link in backend, which is known after the
+    /// frame it's created in has been dispatched.
+    pub failed: bool,
+}
+
+impl ShaderState {
+    pub(crate) fn new(setup: &ShaderSetup) -> Self {
+        let mut uniform_variable_names = HashMap::new();
+        let mut uniform_variables = HashMap::new();
+        for (name, v) in &setup.uniform_variables {
+            let k: HashValue<str> = name.into();
+            uniform_variables.insert(k, *v);
+            uniform_variable_names.insert(k, name.clone());
+        }
+
+        let mut uniform_block_names = HashMap::new();
+        let mut uniform_blocks = HashMap::new();
+        for (name, &size) in &setup.uniform_blocks {
+            let k: HashValue<str> = name.into();
+            uniform_blocks.insert(k, size);
+            uniform_block_names.insert(k, name.clone());
+        }
+
+        ShaderState {
+            render_state: setup.render_state,
+            layout: setup.layout,
+            uniform_variables: uniform_variables,
+            uniform_variable_names: uniform_variable_names,
+            uniform_blocks: uniform_blocks,
+            uniform_block_names: uniform_block_names,
+            storage_block_names: Self::storage_block_names(&setup.storage_blocks),
+            compute: false,
+            failed: false,
+        }
+    }
+
+    pub(crate) fn compute(setup: &ComputeShaderSetup) -> Self {
+        let mut state = ShaderState::default();
+        for (name, v) in &setup.uniform_variables {
+            let k: HashValue<str> = name.into();
+            state.uniform_variables.insert(k, *v);
+            state.uniform_variable_names.insert(k, name.clone());
+        }
+
+        state.storage_block_names = Self::storage_block_names(&setup.storage_blocks);
+        state.compute = true;
+        state
+    }
+
+    fn storage_block_names(names: &[String]) -> HashMap<HashValue<str>, String> {
+        names.iter().map(|v| (v.into(), v.clone())).collect()
+    }
+
+    /// Checks if a storage buffer could be bound to the storage block `field` declared
+    /// by shader.
+    pub fn validate_storage_block(&self, field: HashValue<str>) -> Result<()> {
+        if self.storage_block_names.contains_key(&field) {
+            Ok(())
+        } else {
+            bail!(format!("Undefined storage block: {:?}.", field));
+        }
+    }
+
+    /// Checks if an uniform buffer with `size` bytes could be bound to the uniform
+    /// block `field` declared by shader.
+    pub fn validate_uniform_block(&self, field: HashValue<str>, size: usize) -> Result<()> {
+        let name = match self.uniform_block_names.get(&field) {
+            Some(name) => name.clone(),
+            None => format!("{:?}", field),
+        };
+
+        match self.uniform_blocks.get(&field) {
+            Some(&v) if v == size => Ok(()),
+            Some(&v) => bail!(format!(
+                "Uniform block {:?} requires {} bytes, but the uniform buffer has {} bytes.",
+                name, v, size
+            )),
+            None => bail!(format!("Undefined uniform block: {:?}.", name)),
+        }
+    }
+
+    /// Checks if `variable` could be bound to the uniform `field` declared by shader.
+    pub fn validate_uniform_variable(
+        &self,
+        field: HashValue<str>,
+        variable: &UniformVariable,
+    ) -> Result<()> {
+        let name = match self.uniform_variable_names.get(&field) {
+            Some(name) => name.clone(),
+            None => format!("{:?}", field),
+        };
+
+        match self.uniform_variables.get(&field) {
+            Some(&tt) if tt.accepts(variable.variable_type()) => Ok(()),
+            Some(&UniformVariableType::Vector3fArray(len)) => bail!(format!(
+                "Uniform array {:?} accepts at most {} elements of `vec3`.",
+                name, len
+            )),
+            Some(&UniformVariableType::Matrix4fArray(len)) => bail!(format!(
+                "Uniform array {:?} accepts at most {} elements of `mat4`.",
+                name, len
+            )),
+            Some(_) => bail!(format!("Unmatched uniform variable: {:?}.", name)),
+            None => bail!(format!("Undefined uniform variable: {:?}.", name)),
+        }
+    }
 }
 
 /// The possible pre-defined and named attributes in the vertex component, describing
@@ -122,6 +283,11 @@ impl AttributeLayout {
         }
     }
 
+    /// Returns true if the layout contains specified attribute.
+    pub fn contains(&self, attribute: Attribute) -> bool {
+        self.iter().any(|(name, _)| name == attribute)
+    }
+
     pub fn is_match(&self, layout: &VertexLayout) -> bool {
         for (name, size) in self.iter() {
             if let Some(element) = layout.element(name) {
@@ -202,6 +368,15 @@ pub enum FrontFaceOrder {
     CounterClockwise,
 }
 
+/// Specify how polygons are rasterized, it's useful to inspect the topology of
+/// meshes with `Line` or `Point` mode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
 /// A pixel-wise comparison function.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Comparison {
@@ -215,6 +390,21 @@ pub enum Comparison {
     Always,
 }
 
+impl Comparison {
+    /// Gets the comparison with operands swapped, e.g. `Less` becomes `Greater`. It's
+    /// the depth test that pairs with the reverse-Z projection, where the near plane
+    /// maps to depth 1.0 and the far plane to 0.0.
+    pub fn reversed(&self) -> Comparison {
+        match *self {
+            Comparison::Less => Comparison::Greater,
+            Comparison::LessOrEqual => Comparison::GreaterOrEqual,
+            Comparison::Greater => Comparison::Less,
+            Comparison::GreaterOrEqual => Comparison::LessOrEqual,
+            v => v,
+        }
+    }
+}
+
 /// Specifies how incoming RGBA values (source) and the RGBA in framebuffer (destination)
 /// are combined.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -228,6 +418,12 @@ pub enum Equation {
     /// Subtracts source from destination. Source and destination are
     /// multiplied by blending parameters before subtraction.
     ReverseSubtract,
+    /// Takes the component-wise minimum of source and destination, the blending
+    /// parameters are ignored.
+    Min,
+    /// Takes the component-wise maximum of source and destination, the blending
+    /// parameters are ignored.
+    Max,
 }
 
 /// Blend values.
@@ -248,15 +444,81 @@ pub enum BlendFactor {
     OneMinusValue(BlendValue),
 }
 
+/// The action to take on the stencil buffer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StencilOp {
+    /// Keeps the current value.
+    Keep,
+    /// Sets the value to 0.
+    Zero,
+    /// Sets the value to the reference value.
+    Replace,
+    /// Increments the current value, clamps to the maximum representable value.
+    Increment,
+    /// Increments the current value, wraps to zero when overflowed.
+    IncrementWrap,
+    /// Decrements the current value, clamps to 0.
+    Decrement,
+    /// Decrements the current value, wraps to the maximum representable value
+    /// when underflowed.
+    DecrementWrap,
+    /// Bitwise inverts the current value.
+    Invert,
+}
+
+/// Specifies the stencil test and the actions on stencil buffer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StencilTest {
+    /// The comparison between the masked reference value and the masked value in
+    /// stencil buffer.
+    pub comparison: Comparison,
+    /// The reference value for the stencil test.
+    pub reference: i32,
+    /// The mask that is ANDed with both the reference value and the stored value
+    /// when the test is done.
+    pub read_mask: u32,
+    /// The mask that enables and disables writing of individual bits in the stencil
+    /// buffer.
+    pub write_mask: u32,
+    /// The action to take when the stencil test fails.
+    pub fail: StencilOp,
+    /// The action to take when the stencil test passes, but the depth test fails.
+    pub depth_fail: StencilOp,
+    /// The action to take when both the stencil test and depth test pass.
+    pub pass: StencilOp,
+}
+
+impl Default for StencilTest {
+    fn default() -> Self {
+        StencilTest {
+            comparison: Comparison::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
 /// A struct that encapsulate all the necessary render states.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct RenderState {
     pub cull_face: CullFace,
     pub front_face_order: FrontFaceOrder,
+    /// Rasterization mode of polygons. Modes other than `Fill` are only available
+    /// on desktop OpenGL, and it has no effects on meshes of lines or points.
+    pub polygon_mode: PolygonMode,
     pub depth_test: Comparison,
     pub depth_write: bool,
     pub depth_write_offset: Option<(f32, f32)>,
+    pub stencil_test: Option<StencilTest>,
     pub color_blend: Option<(Equation, BlendFactor, BlendFactor)>,
+    /// Optional blend function of alpha channel. It only takes effect when the
+    /// `color_blend` is enabled, and the alpha channel will be blended in the same
+    /// way as RGB channels if it's `None`.
+    pub alpha_blend: Option<(Equation, BlendFactor, BlendFactor)>,
     pub color_write: (bool, bool, bool, bool),
 }
 
@@ -265,19 +527,151 @@ impl Default for RenderState {
         RenderState {
             cull_face: CullFace::Nothing,
             front_face_order: FrontFaceOrder::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
             depth_test: Comparison::Always, // no depth test,
             depth_write: false,             // no depth write,
             depth_write_offset: None,
+            stencil_test: None,
             color_blend: None,
+            alpha_blend: None,
             color_write: (true, true, true, true),
         }
     }
 }
 
+impl RenderState {
+    /// Creates a `RenderState` that blends source into destination with the
+    /// alpha of source. The depth writing is disabled since translucent objects
+    /// should not occlude the others.
+    pub fn translucent() -> Self {
+        let mut state = RenderState::default();
+        state.color_blend = Some((
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        ));
+        state
+    }
+}
+
+/// A partial `RenderState` which overrides some fields of the shader's state for a
+/// single draw call, e.g. drawing the same geometry with depth writing disabled in a
+/// transparent pre-pass. Fields that are `None` keep the states of shader.
+///
+/// A whole `RenderState` converts into an override with every field set.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct RenderStateOverride {
+    pub cull_face: Option<CullFace>,
+    pub front_face_order: Option<FrontFaceOrder>,
+    /// Only available on desktop OpenGL, the draw call is rejected otherwise.
+    pub polygon_mode: Option<PolygonMode>,
+    pub depth_test: Option<Comparison>,
+    pub depth_write: Option<bool>,
+    pub depth_write_offset: Option<Option<(f32, f32)>>,
+    pub stencil_test: Option<Option<StencilTest>>,
+    /// Overrides the blend function of color, and the alpha channel will be blended in
+    /// the same way as RGB channels unless `alpha_blend` is overridden too.
+    /// `Some(None)` disables the blending.
+    pub color_blend: Option<Option<(Equation, BlendFactor, BlendFactor)>>,
+    pub alpha_blend: Option<Option<(Equation, BlendFactor, BlendFactor)>>,
+    pub color_write: Option<(bool, bool, bool, bool)>,
+}
+
+impl From<RenderState> for RenderStateOverride {
+    fn from(state: RenderState) -> Self {
+        RenderStateOverride {
+            cull_face: Some(state.cull_face),
+            front_face_order: Some(state.front_face_order),
+            polygon_mode: Some(state.polygon_mode),
+            depth_test: Some(state.depth_test),
+            depth_write: Some(state.depth_write),
+            depth_write_offset: Some(state.depth_write_offset),
+            stencil_test: Some(state.stencil_test),
+            color_blend: Some(state.color_blend),
+            alpha_blend: Some(state.alpha_blend),
+            color_write: Some(state.color_write),
+        }
+    }
+}
+
+impl RenderStateOverride {
+    /// Applies the overridden fields on top of `state`.
+    pub fn apply(&self, state: &RenderState) -> RenderState {
+        let mut state = *state;
+
+        if let Some(v) = self.cull_face {
+            state.cull_face = v;
+        }
+
+        if let Some(v) = self.front_face_order {
+            state.front_face_order = v;
+        }
+
+        if let Some(v) = self.polygon_mode {
+            state.polygon_mode = v;
+        }
+
+        if let Some(v) = self.depth_test {
+            state.depth_test = v;
+        }
+
+        if let Some(v) = self.depth_write {
+            state.depth_write = v;
+        }
+
+        if let Some(v) = self.depth_write_offset {
+            state.depth_write_offset = v;
+        }
+
+        if let Some(v) = self.stencil_test {
+            state.stencil_test = v;
+        }
+
+        if let Some(v) = self.color_blend {
+            state.color_blend = v;
+            state.alpha_blend = None;
+        }
+
+        if let Some(v) = self.alpha_blend {
+            state.alpha_blend = v;
+        }
+
+        if let Some(v) = self.color_write {
+            state.color_write = v;
+        }
+
+        state
+    }
+
+    /// Returns an override with the fields of `v` on top of this one.
+    pub fn merge(&self, v: &RenderStateOverride) -> RenderStateOverride {
+        let mut alpha_blend = v.alpha_blend.or(self.alpha_blend);
+        if v.color_blend.is_some() && v.alpha_blend.is_none() {
+            alpha_blend = Some(None);
+        }
+
+        RenderStateOverride {
+            cull_face: v.cull_face.or(self.cull_face),
+            front_face_order: v.front_face_order.or(self.front_face_order),
+            polygon_mode: v.polygon_mode.or(self.polygon_mode),
+            depth_test: v.depth_test.or(self.depth_test),
+            depth_write: v.depth_write.or(self.depth_write),
+            depth_write_offset: v.depth_write_offset.or(self.depth_write_offset),
+            stencil_test: v.stencil_test.or(self.stencil_test),
+            color_blend: v.color_blend.or(self.color_blend),
+            alpha_blend: alpha_blend,
+            color_write: v.color_write.or(self.color_write),
+        }
+    }
+}
+
 /// Uniform variable type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum UniformVariableType {
     Texture,
+    TextureCube,
+    /// Sampler of 2D texture array, which is declared as `sampler2DArray`.
+    Texture2DArray,
     I32,
     F32,
     Vector2f,
@@ -286,13 +680,38 @@ pub enum UniformVariableType {
     Matrix2f,
     Matrix3f,
     Matrix4f,
+    /// Array of `vec3` with the declared maximum number of elements.
+    Vector3fArray(usize),
+    /// Array of `mat4` with the declared maximum number of elements.
+    Matrix4fArray(usize),
+}
+
+impl UniformVariableType {
+    /// Returns true if a variable of type `tt` could be bound to the field declared
+    /// with this type. Arrays accept any number of elements up to the declared size.
+    pub fn accepts(&self, tt: UniformVariableType) -> bool {
+        match (*self, tt) {
+            (UniformVariableType::Vector3fArray(n), UniformVariableType::Vector3fArray(len)) => {
+                len <= n
+            }
+            (UniformVariableType::Matrix4fArray(n), UniformVariableType::Matrix4fArray(len)) => {
+                len <= n
+            }
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
 }
 
 /// Uniform variable for graphics program object. Each matrix based `UniformVariable`
 /// is assumed to be supplied in row major order with a optional transpose.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UniformVariable {
     Texture(TextureHandle),
+    /// Cube texture should be bound explicitly with this variant.
+    TextureCube(TextureHandle),
+    /// Texture array should be bound explicitly with this variant, the layer is
+    /// chosen by the third texture coordinate in shaders.
+    Texture2DArray(TextureHandle),
     I32(i32),
     F32(f32),
     Vector2f([f32; 2]),
@@ -301,12 +720,16 @@ pub enum UniformVariable {
     Matrix2f([[f32; 2]; 2], bool),
     Matrix3f([[f32; 3]; 3], bool),
     Matrix4f([[f32; 4]; 4], bool),
+    Vector3fArray(Vec<[f32; 3]>),
+    Matrix4fArray(Vec<[[f32; 4]; 4]>),
 }
 
 impl UniformVariable {
     pub fn variable_type(&self) -> UniformVariableType {
         match self {
             &UniformVariable::Texture(_) => UniformVariableType::Texture,
+            &UniformVariable::TextureCube(_) => UniformVariableType::TextureCube,
+            &UniformVariable::Texture2DArray(_) => UniformVariableType::Texture2DArray,
             &UniformVariable::I32(_) => UniformVariableType::I32,
             &UniformVariable::F32(_) => UniformVariableType::F32,
             &UniformVariable::Vector2f(_) => UniformVariableType::Vector2f,
@@ -315,6 +738,8 @@ impl UniformVariable {
             &UniformVariable::Matrix2f(_, _) => UniformVariableType::Matrix2f,
             &UniformVariable::Matrix3f(_, _) => UniformVariableType::Matrix3f,
             &UniformVariable::Matrix4f(_, _) => UniformVariableType::Matrix4f,
+            &UniformVariable::Vector3fArray(ref v) => UniformVariableType::Vector3fArray(v.len()),
+            &UniformVariable::Matrix4fArray(ref v) => UniformVariableType::Matrix4fArray(v.len()),
         }
     }
 }
@@ -397,6 +822,30 @@ impl Into<UniformVariable> for [f32; 3] {
     }
 }
 
+impl Into<UniformVariable> for Vec<[f32; 3]> {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Vector3fArray(self)
+    }
+}
+
+impl<'a> Into<UniformVariable> for &'a [math::Vector3<f32>] {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Vector3fArray(self.iter().map(|v| (*v).into()).collect())
+    }
+}
+
+impl Into<UniformVariable> for Vec<[[f32; 4]; 4]> {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Matrix4fArray(self)
+    }
+}
+
+impl<'a> Into<UniformVariable> for &'a [math::Matrix4<f32>] {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Matrix4fArray(self.iter().map(|v| *v.as_ref()).collect())
+    }
+}
+
 impl Into<UniformVariable> for math::Vector4<f32> {
     fn into(self) -> UniformVariable {
         UniformVariable::Vector4f(*self.as_ref())
@@ -408,3 +857,99 @@ impl Into<UniformVariable> for [f32; 4] {
         UniformVariable::Vector4f(self)
     }
 }
+
+impl Into<UniformVariable> for Color {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Vector4f(self.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stencil_test() {
+        let mut stencil = StencilTest::default();
+        stencil.comparison = Comparison::Equal;
+        stencil.reference = 0x7;
+        stencil.write_mask = 0;
+
+        let mut setup = ShaderSetup::default();
+        setup.render_state.stencil_test = Some(stencil);
+        setup
+            .uniform_variables
+            .insert("u_Color".into(), UniformVariableType::Vector4f);
+
+        let state = ShaderState::new(&setup);
+        let v = state.render_state.stencil_test.unwrap();
+        assert_eq!(v.reference, 0x7);
+        assert_eq!(v.comparison, Comparison::Equal);
+        assert_eq!(v.read_mask, !0);
+        assert_eq!(v.write_mask, 0);
+        assert_eq!(v.pass, StencilOp::Keep);
+        assert_eq!(state.render_state, setup.render_state);
+
+        let k: HashValue<str> = "u_Color".into();
+        assert_eq!(state.uniform_variable_names[&k], "u_Color");
+        assert_eq!(RenderState::default().stencil_test, None);
+    }
+
+    #[test]
+    fn render_state_override() {
+        let mut state = RenderState::translucent();
+        state.depth_write = true;
+        state.alpha_blend = Some((Equation::Add, BlendFactor::One, BlendFactor::Zero));
+
+        assert_eq!(RenderStateOverride::default().apply(&state), state);
+
+        let mut v = RenderStateOverride::default();
+        v.depth_write = Some(false);
+        v.cull_face = Some(CullFace::Back);
+        let rs = v.apply(&state);
+        assert_eq!(rs.depth_write, false);
+        assert_eq!(rs.cull_face, CullFace::Back);
+        assert_eq!(rs.color_blend, state.color_blend);
+        assert_eq!(rs.alpha_blend, state.alpha_blend);
+
+        v.color_blend = Some(None);
+        let rs = v.apply(&state);
+        assert_eq!(rs.color_blend, None);
+        assert_eq!(rs.alpha_blend, None);
+        assert_eq!(rs.depth_test, state.depth_test);
+
+        // A whole state replaces every field.
+        let base = RenderStateOverride::from(state);
+        assert_eq!(base.apply(&RenderState::default()), state);
+
+        let rs = base.merge(&v).apply(&RenderState::default());
+        assert_eq!(rs, v.apply(&state));
+    }
+
+    #[test]
+    fn uniform_array() {
+        let tt = UniformVariableType::Vector3fArray(4);
+        let v: UniformVariable = vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].into();
+        assert_eq!(v.variable_type(), UniformVariableType::Vector3fArray(2));
+        assert!(tt.accepts(v.variable_type()));
+        assert!(tt.accepts(UniformVariableType::Vector3fArray(4)));
+        assert!(!tt.accepts(UniformVariableType::Vector3fArray(5)));
+        assert!(!tt.accepts(UniformVariableType::Vector3f));
+
+        let points = [math::Vector3::new(1.0, 0.0, 0.0); 3];
+        let v: UniformVariable = (&points[..]).into();
+        assert_eq!(v.variable_type(), UniformVariableType::Vector3fArray(3));
+
+        let tt = UniformVariableType::Matrix4fArray(2);
+        let matrices = [math::Matrix4::from_scale(2.0); 2];
+        let v: UniformVariable = (&matrices[..]).into();
+        assert_eq!(v.variable_type(), UniformVariableType::Matrix4fArray(2));
+        assert!(tt.accepts(v.variable_type()));
+        assert!(!tt.accepts(UniformVariableType::Matrix4fArray(3)));
+        assert!(!tt.accepts(UniformVariableType::Vector3fArray(2)));
+
+        let tt = UniformVariableType::F32;
+        assert!(tt.accepts(UniformVariableType::F32));
+        assert!(!tt.accepts(UniformVariableType::I32));
+    }
+}
@@ -0,0 +1,77 @@
+use std::str;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+
+use resource::{self, ResourceSystemShared};
+use graphics::assets::shader::*;
+use graphics::assets::shader_preprocessor::preprocess_shader;
+use graphics::backend::frame::{MultiFrame, PreFrameTask};
+
+/// The stages of shader which could be reloaded separately.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+#[doc(hidden)]
+pub(crate) struct ShaderReloader {
+    handle: ShaderHandle,
+    stage: ShaderStage,
+    setup: Weak<Mutex<ShaderSetup>>,
+    resource: Arc<ResourceSystemShared>,
+    frames: Arc<MultiFrame>,
+}
+
+impl ShaderReloader {
+    pub fn new(
+        handle: ShaderHandle,
+        stage: ShaderStage,
+        setup: &Arc<Mutex<ShaderSetup>>,
+        resource: Arc<ResourceSystemShared>,
+        frames: Arc<MultiFrame>,
+    ) -> Self {
+        ShaderReloader {
+            handle: handle,
+            stage: stage,
+            setup: Arc::downgrade(setup),
+            resource: resource,
+            frames: frames,
+        }
+    }
+}
+
+impl resource::ResourceHotReloader for ShaderReloader {
+    fn is_alive(&self) -> bool {
+        self.setup.upgrade().is_some()
+    }
+
+    fn on_reload(&self, path: &Path, bytes: &[u8]) -> resource::errors::Result<()> {
+        let setup = match self.setup.upgrade() {
+            Some(setup) => setup,
+            None => return Ok(()),
+        };
+
+        let source = match str::from_utf8(bytes) {
+            Ok(source) if !source.is_empty() => source.to_owned(),
+            _ => bail!(format!("Failed to reload shader at {:?}.", path)),
+        };
+
+        let mut setup = setup.lock().unwrap();
+        match self.stage {
+            ShaderStage::Vertex => setup.vs = source,
+            ShaderStage::Fragment => setup.fs = source,
+        }
+
+        let expanded = match preprocess_shader(&self.resource, &setup) {
+            Ok(expanded) => expanded,
+            Err(err) => bail!(format!("Failed to preprocess shader at {:?}: {}", path, err)),
+        };
+
+        // The compiling errors will be reported after the task is dispatched.
+        let task = PreFrameTask::UpdatePipeline(self.handle, expanded, path.to_owned());
+        self.frames.front().pre.push(task);
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! A minimal preprocessor of shader sources, which resolves `#include` directives and
+//! injects macros before the sources are handed to the driver.
+
+use std::path::{Path, PathBuf};
+
+use graphics::errors::*;
+use resource::ResourceSystemShared;
+
+use super::shader::{ComputeShaderSetup, ShaderSetup};
+
+/// Preprocesses the sources of `setup`, with included files loaded from `resource`. The
+/// `defines` of returned setup are cleared since they have been injected already.
+pub(crate) fn preprocess_shader(
+    resource: &ResourceSystemShared,
+    setup: &ShaderSetup,
+) -> Result<ShaderSetup> {
+    let mut expanded = setup.clone();
+    expanded.vs = preprocess(&setup.vs, &setup.defines, |v| load(resource, v))?;
+    expanded.fs = preprocess(&setup.fs, &setup.defines, |v| load(resource, v))?;
+    expanded.defines.clear();
+    Ok(expanded)
+}
+
+/// Preprocesses the source of compute shader `setup`, see `preprocess_shader`.
+pub(crate) fn preprocess_compute_shader(
+    resource: &ResourceSystemShared,
+    setup: &ComputeShaderSetup,
+) -> Result<ComputeShaderSetup> {
+    let mut expanded = setup.clone();
+    expanded.cs = preprocess(&setup.cs, &setup.defines, |v| load(resource, v))?;
+    expanded.defines.clear();
+    Ok(expanded)
+}
+
+fn load(resource: &ResourceSystemShared, path: &Path) -> Result<String> {
+    match String::from_utf8(resource.load(path)?) {
+        Ok(source) => Ok(source),
+        Err(_) => bail!(format!("Shader source at {:?} is not valid UTF-8.", path)),
+    }
+}
+
+/// Expands the `#include "path"` directives of `source` recursively with the sources
+/// returned by `load`, and injects `defines` as `#define NAME VALUE` right after the
+/// `#version` directive.
+///
+/// The paths starting with `/` are absolute paths of the virtual filesystem, and the
+/// others are relative to the file that includes them. Circular includes are reported
+/// as errors with the include chain.
+pub(crate) fn preprocess<F>(
+    source: &str,
+    defines: &[(String, String)],
+    mut load: F,
+) -> Result<String>
+where
+    F: FnMut(&Path) -> Result<String>,
+{
+    let mut expanded = String::with_capacity(source.len());
+    let mut chain = Vec::new();
+    expand(source, None, &mut chain, &mut load, &mut expanded)?;
+
+    if defines.is_empty() {
+        return Ok(expanded);
+    }
+
+    let mut macros = String::new();
+    for &(ref name, ref value) in defines {
+        let mut chars = name.chars();
+        let valid = chars.next().map(|v| v.is_ascii_alphabetic() || v == '_');
+        if valid != Some(true) || !chars.all(|v| v.is_ascii_alphanumeric() || v == '_') {
+            bail!(format!("Invalid macro name {:?}.", name));
+        }
+
+        macros.push_str(&format!("#define {} {}\n", name, value.trim()));
+    }
+
+    // The `#version` directive must be the first statement of shader.
+    let pos = version_end(&expanded);
+    expanded.insert_str(pos, &macros);
+    Ok(expanded)
+}
+
+fn expand<F>(
+    source: &str,
+    path: Option<&Path>,
+    chain: &mut Vec<PathBuf>,
+    load: &mut F,
+    expanded: &mut String,
+) -> Result<()>
+where
+    F: FnMut(&Path) -> Result<String>,
+{
+    for line in source.lines() {
+        let include = match parse_include(line) {
+            Some(Ok(include)) => include,
+            Some(Err(_)) => bail!(format!("Malformed directive `{}`.", line.trim())),
+            None => {
+                expanded.push_str(line);
+                expanded.push('\n');
+                continue;
+            }
+        };
+
+        let include = match path.and_then(|v| v.parent()) {
+            Some(parent) if !include.starts_with('/') => parent.join(include),
+            _ => PathBuf::from(include),
+        };
+
+        if chain.contains(&include) {
+            let chain: Vec<_> = chain
+                .iter()
+                .chain(Some(&include))
+                .map(|v| format!("{:?}", v))
+                .collect();
+            bail!(format!("Circular #include: {}.", chain.join(" -> ")));
+        }
+
+        let source = match load(&include) {
+            Ok(source) => source,
+            Err(err) => bail!(format!("Failed to #include {:?}: {}", include, err)),
+        };
+
+        chain.push(include.clone());
+        expand(&source, Some(&include), chain, load, expanded)?;
+        chain.pop();
+    }
+
+    Ok(())
+}
+
+/// Parses the path of `#include "path"` directive, the malformed ones are errors.
+fn parse_include(line: &str) -> Option<::std::result::Result<&str, ()>> {
+    let line = line.trim();
+    if !line.starts_with("#include") {
+        return None;
+    }
+
+    let v = line["#include".len()..].trim();
+    if v.len() > 2 && v.starts_with('"') && v.ends_with('"') {
+        Some(Ok(&v[1..v.len() - 1]))
+    } else {
+        Some(Err(()))
+    }
+}
+
+/// Gets the position right after the `#version` directive, or zero if there is none.
+fn version_end(source: &str) -> usize {
+    let mut pos = 0;
+    for line in source.lines() {
+        pos += line.len() + 1;
+        if line.trim().starts_with("#version") {
+            return ::std::cmp::min(pos, source.len());
+        }
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn files<'a>(files: &'a HashMap<&str, &str>) -> impl FnMut(&Path) -> Result<String> + 'a {
+        move |path| match files.get(path.to_str().unwrap()) {
+            Some(v) => Ok(v.to_string()),
+            None => bail!("Not found."),
+        }
+    }
+
+    #[test]
+    fn include() {
+        let mut files = HashMap::new();
+        files.insert("/std/shaders/lighting.glsl", "#include \"math.glsl\"\nlighting");
+        files.insert("/std/shaders/math.glsl", "math");
+        files.insert("/std/common.glsl", "common");
+
+        let source = "#version 330 core\n#include \"/std/shaders/lighting.glsl\"\n  \
+                      #include \"/std/common.glsl\"\nvoid main() {}";
+        let defines = [
+            ("SHADOW".to_owned(), "1".to_owned()),
+            ("NUM_LIGHTS".to_owned(), "4".to_owned()),
+        ];
+
+        let expanded = preprocess(source, &defines, files(&files)).unwrap();
+        assert_eq!(
+            expanded,
+            "#version 330 core\n#define SHADOW 1\n#define NUM_LIGHTS 4\nmath\nlighting\ncommon\n\
+             void main() {}\n"
+        );
+
+        // The same file could be included more than once.
+        let source = "#include \"/std/shaders/math.glsl\"\n#include \"/std/shaders/math.glsl\"";
+        assert_eq!(preprocess(source, &[], files(&files)).unwrap(), "math\nmath\n");
+
+        let defines = [("A".to_owned(), String::new())];
+        let expanded = preprocess("void main() {}", &defines, files(&files)).unwrap();
+        assert_eq!(expanded, "#define A \nvoid main() {}\n");
+
+        let defines = [("1A".to_owned(), String::new())];
+        assert!(preprocess("", &defines, files(&files)).is_err());
+        assert!(preprocess("#include \"/std/none.glsl\"", &[], files(&files)).is_err());
+        assert!(preprocess("#include <math.glsl>", &[], files(&files)).is_err());
+    }
+
+    #[test]
+    fn circular() {
+        let mut files = HashMap::new();
+        files.insert("/std/a.glsl", "#include \"b.glsl\"");
+        files.insert("/std/b.glsl", "#include \"/std/a.glsl\"");
+
+        let err = preprocess("#include \"/std/a.glsl\"", &[], files(&files)).unwrap_err();
+        let chain = "\"/std/a.glsl\" -> \"/std/b.glsl\" -> \"/std/a.glsl\"";
+        assert!(err.to_string().contains(chain), "{}", err);
+    }
+}
@@ -0,0 +1,16 @@
+#version 100
+precision lowp float;
+attribute vec2 Position;
+attribute vec2 Texcoord0;
+attribute vec4 Color0;
+
+uniform mat4 u_MVPMatrix;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+
+void main() {
+    v_Texcoord = Texcoord0;
+    v_Color = Color0;
+    gl_Position = u_MVPMatrix * vec4(Position, 0.0, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+//! Shader storage buffer object, which could be read and written by both compute and
+//! render shaders.
+
+use graphics::BufferHint;
+
+impl_handle!(StorageBufferHandle);
+
+/// The setup of shader storage buffer object. The contents of buffer are laid out in
+/// `std430`, and they are bound to the storage blocks declared by shaders in
+/// `ShaderSetup::storage_blocks` or `ComputeShaderSetup::storage_blocks`.
+#[derive(Debug, Copy, Clone)]
+pub struct StorageBufferSetup {
+    /// The size of buffer in bytes.
+    pub size: usize,
+    /// Storage buffers written by compute shaders every frame, e.g. the states of GPU
+    /// particles, are usually `BufferHint::Dynamic`.
+    pub hint: BufferHint,
+}
+
+impl Default for StorageBufferSetup {
+    fn default() -> Self {
+        StorageBufferSetup {
+            size: 0,
+            hint: BufferHint::Dynamic,
+        }
+    }
+}
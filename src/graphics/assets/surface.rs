@@ -4,6 +4,7 @@
 use utils::Color;
 use graphics::MAX_FRAMEBUFFER_ATTACHMENTS;
 use graphics::assets::texture::{RenderBufferHandle, TextureHandle};
+use graphics::window::WindowHandle;
 use graphics::errors::*;
 
 /// SurfaceObject wraps rendering operations to a render-target. Likes clearing, MSAA
@@ -14,27 +15,38 @@ use graphics::errors::*;
 /// preserved (for example in rendering GUIs), view can be set to be in sequential order.
 /// Sequential order is less efficient, because it doesn't allow state change optimization,
 /// and should be avoided when possible.
+///
+/// Surfaces are executed by their `order`, and the ones with the same order are grouped
+/// by their handles, so their commands never interleave.
 #[derive(Debug, Copy, Clone)]
 pub struct SurfaceSetup {
     pub(crate) framebuffer: Option<FrameBufferHandle>,
+    pub(crate) window: Option<WindowHandle>,
     pub(crate) clear_color: Option<Color>,
     pub(crate) clear_depth: Option<f32>,
     pub(crate) clear_stencil: Option<i32>,
     pub(crate) order: u64,
     pub(crate) sequence: bool,
     pub(crate) viewport: ((f32, f32), (f32, f32)),
+    pub(crate) samples: u32,
+    pub(crate) srgb: bool,
+    pub(crate) reversed_z: bool,
 }
 
 impl Default for SurfaceSetup {
     fn default() -> Self {
         SurfaceSetup {
             framebuffer: None,
+            window: None,
             clear_color: Some(Color::black()),
             clear_depth: Some(1.0),
             clear_stencil: None,
             sequence: false,
             order: 0,
             viewport: ((0.0, 0.0), (1.0, 1.0)),
+            samples: 0,
+            srgb: false,
+            reversed_z: false,
         }
     }
 }
@@ -60,9 +72,15 @@ impl SurfaceSetup {
         self.order = order;
     }
 
-    /// Sets the clear flags for this surface.A
+    /// Sets the clear values of color, depth and stencil buffers for this surface.
+    /// `Some(v)` clears the corresponding buffer to `v` before drawing, and `None`
+    /// preserves its contents, e.g. a later pass that draws over the depth buffer of
+    /// previous pass could be set with `set_clear(Color::black(), None, None)`.
+    ///
+    /// By default, the color is cleared to black and the depth is cleared to 1.0,
+    /// while the stencil is preserved.
     #[inline(always)]
-    pub fn set_clear<C, D, S>(&mut self, color: C, depth: D, stentil: S)
+    pub fn set_clear<C, D, S>(&mut self, color: C, depth: D, stencil: S)
     where
         C: Into<Option<Color>>,
         D: Into<Option<f32>>,
@@ -70,7 +88,40 @@ impl SurfaceSetup {
     {
         self.clear_color = color.into();
         self.clear_depth = depth.into();
-        self.clear_stencil = stentil.into();
+        self.clear_stencil = stencil.into();
+    }
+
+    /// Sets the clear value of color buffer, `None` preserves its contents.
+    #[inline(always)]
+    pub fn set_clear_color<T>(&mut self, color: T)
+    where
+        T: Into<Option<Color>>,
+    {
+        self.clear_color = color.into();
+    }
+
+    /// Sets the clear value of depth buffer, `None` preserves its contents.
+    #[inline(always)]
+    pub fn set_clear_depth<T>(&mut self, depth: T)
+    where
+        T: Into<Option<f32>>,
+    {
+        self.clear_depth = depth.into();
+    }
+
+    /// Sets the clear value of stencil buffer, `None` preserves its contents.
+    #[inline(always)]
+    pub fn set_clear_stencil<T>(&mut self, stencil: T)
+    where
+        T: Into<Option<i32>>,
+    {
+        self.clear_stencil = stencil.into();
+    }
+
+    /// Gets the clear values of color, depth and stencil buffers.
+    #[inline(always)]
+    pub fn clear(&self) -> (Option<Color>, Option<f32>, Option<i32>) {
+        (self.clear_color, self.clear_depth, self.clear_stencil)
     }
 
     /// Sets the viewport of view. This specifies the affine transformation of (x, y) from
@@ -92,6 +143,61 @@ impl SurfaceSetup {
     pub fn set_sequence(&mut self, sequence: bool) {
         self.sequence = sequence;
     }
+
+    /// Sets the number of samples for multisample anti-aliasing.
+    ///
+    /// Drawcalls inside a multisampled `Surface` are rendered into a internal
+    /// multisampled framebuffer, which will be resolved into the render target of
+    /// `Surface` after all the drawcalls are submitted. The `samples` is clamped to
+    /// the maximum supported samples, and zero or one disables multisampling.
+    ///
+    /// The contents of render target are not loaded before drawing, so multisampled
+    /// surfaces should clear the color and depth buffers, and the stencil buffer is
+    /// cleared to zero if it has no clear value. The depth and stencil buffers are
+    /// resolved along with colors if the render target has them.
+    #[inline(always)]
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+    }
+
+    /// Enables `GL_FRAMEBUFFER_SRGB` when drawing into this `Surface`, so the linear
+    /// colors written by shaders are encoded into sRGB space by GPU. It only takes
+    /// effect on render targets in sRGB space, e.g. the default framebuffer of window
+    /// created with `WindowSettings::srgb`.
+    ///
+    /// Notes that this changes the apparent brightness of everything drawn into this
+    /// `Surface`, the colors which were gamma-corrected by hand will look washed out.
+    /// So it's disabled by default.
+    #[inline(always)]
+    pub fn set_srgb(&mut self, srgb: bool) {
+        self.srgb = srgb;
+    }
+
+    /// Enables the reverse-Z depth of this `Surface`, which should be used along with
+    /// cameras with reverse-Z projection (see `Camera::set_reversed_z`). The depth of
+    /// clip space is mapped into `[0, 1]` with `glClipControl` instead of `[-1, 1]`,
+    /// and the depth buffer is cleared to `1.0 - depth`, e.g. to 0.0 by default.
+    ///
+    /// It requires OpenGL 4.5 or `GL_ARB_clip_control`, the creation of `Surface` fails
+    /// if it's not supported.
+    #[inline(always)]
+    pub fn set_reversed_z(&mut self, enable: bool) {
+        self.reversed_z = enable;
+    }
+
+    /// Sets the window this `Surface` presents to. If `window` is none, the primary
+    /// window will be used.
+    ///
+    /// Surfaces of secondary windows always draw into the default framebuffer of window,
+    /// since framebuffers could not be shared between OpenGL contexts. Render into a
+    /// texture with surfaces of primary window instead, and draw it on secondary windows.
+    #[inline(always)]
+    pub fn set_window<T>(&mut self, window: T)
+    where
+        T: Into<Option<WindowHandle>>,
+    {
+        self.window = window.into();
+    }
 }
 
 /// `FrameBuffer` is a collection of 2D arrays or storages, including
@@ -149,8 +255,25 @@ impl_handle!(FrameBufferHandle);
 /// Defines a rectangle, called the scissor box, in window coordinates. The test is
 /// initially disabled. While the test is enabled, only pixels that lie within the
 /// scissor box can be modified by drawing commands.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// `Enable` takes the position and size of box in framebuffer pixels. Since the
+/// dimensions of window in points does not match its pixels on HiDPI displays, the
+/// box could also be specified with `Points` or `Normalized`, both are relative to
+/// the bottom-left corner of the viewport of surface, and converted into pixels when
+/// the frame is dispatched:
+///
+/// * `Points` is scaled by the hidpi factor of window, if the surface renders into the
+/// window or a framebuffer that follows the dimensions of window. Framebuffers with
+/// fixed dimensions are addressed in pixels directly.
+/// * `Normalized` is in range [0, 1] of the viewport of surface.
+///
+/// The edges of box, instead of its position and size, are rounded to the nearest
+/// pixels. So adjacent boxes always share their boundaries without leaving a gap or
+/// overlapping, even with fractional hidpi factors like 1.5.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Scissor {
     Enable((u16, u16), (u16, u16)),
+    Points((f32, f32), (f32, f32)),
+    Normalized((f32, f32), (f32, f32)),
     Disable,
 }
@@ -1,27 +1,253 @@
-//! Immutable or dynamic 2D texture.
+//! Immutable or dynamic 2D texture, cube texture and 2D texture array.
+
+use graphics::errors::*;
+use utils::Rect;
+
+/// The kind of a texture object.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TextureKind {
+    /// A two-dimensional texture.
+    Texture2D,
+    /// A cube texture with six square faces, which are ordered as +X, -X, +Y, -Y,
+    /// +Z and -Z.
+    Cube,
+    /// An array of two-dimensional textures with the same dimensions and format,
+    /// which are sampled with a layer index in shaders. The number of layers is
+    /// attached.
+    Texture2DArray(u32),
+}
 
 /// The public attributes of a texture object.
 #[derive(Debug, Copy, Clone)]
 pub struct TextureSetup {
+    pub kind: TextureKind,
     pub format: TextureFormat,
     pub address: TextureAddress,
+    /// The filter used when the texture is minified or magnified, unless it's
+    /// overridden by `min_filter` or `mag_filter`. Filters that sample mipmaps
+    /// require `mipmap` to be enabled, or more than one `levels`.
     pub filter: TextureFilter,
+    /// Overrides `filter` when the texture is minified.
+    pub min_filter: Option<TextureFilter>,
+    /// Overrides `filter` when the texture is magnified, only `Nearest` and `Linear`
+    /// are allowed. Mipmaps are never sampled when magnified, so a mipmap `filter`
+    /// falls back to its `Nearest` or `Linear` counterpart.
+    pub mag_filter: Option<TextureFilter>,
+    /// Generates mipmaps automatically after the texture is created.
     pub mipmap: bool,
+    /// The number of mip levels in the data, which are stored contiguously from the
+    /// largest one. Mipmaps of compressed textures could not be generated by GPU, so
+    /// they should be provided with levels instead. It's one by default.
+    pub levels: u32,
+    /// The color channels are encoded in sRGB space, e.g. the albedo and diffuse
+    /// textures authored by artists. They are converted into linear space by GPU
+    /// when sampled. Normal maps and other data textures should stay linear.
+    ///
+    /// Only 8-bits RGB(A) and compressed formats except `ETC1` could be sRGB, it's
+    /// disabled by default.
+    pub srgb: bool,
     pub dimensions: (u32, u32),
 }
 
 impl Default for TextureSetup {
     fn default() -> Self {
         TextureSetup {
+            kind: TextureKind::Texture2D,
             format: TextureFormat::U8U8U8U8,
             address: TextureAddress::Clamp,
             filter: TextureFilter::Linear,
+            min_filter: None,
+            mag_filter: None,
             mipmap: false,
+            levels: 1,
+            srgb: false,
             dimensions: (0, 0),
         }
     }
 }
 
+impl TextureSetup {
+    /// Returns true if both width and height of texture are power of two.
+    pub fn is_power_of_two(&self) -> bool {
+        self.dimensions.0.is_power_of_two() && self.dimensions.1.is_power_of_two()
+    }
+
+    /// Returns true if the mipmaps of this texture could be generated. Mipmaps of
+    /// NPOT textures are only available with `npot_mipmap` capability.
+    pub fn is_mipmap_supported(&self, npot_mipmap: bool) -> bool {
+        (!self.mipmap && self.levels <= 1) || npot_mipmap || self.is_power_of_two()
+    }
+
+    /// Gets the filters used when the texture is minified and magnified.
+    pub fn filters(&self) -> (TextureFilter, TextureFilter) {
+        let min = self.min_filter.unwrap_or(self.filter);
+        let mag = self.mag_filter.unwrap_or(match self.filter {
+            TextureFilter::NearestMipmapNearest | TextureFilter::NearestMipmapLinear => {
+                TextureFilter::Nearest
+            }
+            TextureFilter::LinearMipmapNearest | TextureFilter::LinearMipmapLinear => {
+                TextureFilter::Linear
+            }
+            v => v,
+        });
+
+        (min, mag)
+    }
+
+    /// Gets the dimensions of mip `level`.
+    pub fn level_dimensions(&self, level: u32) -> (u32, u32) {
+        let w = (self.dimensions.0 >> level).max(1);
+        let h = (self.dimensions.1 >> level).max(1);
+        (w, h)
+    }
+
+    /// Gets the size in bytes of a face, including all its mip levels.
+    pub fn len(&self) -> usize {
+        (0..self.levels)
+            .map(|i| self.format.len(self.level_dimensions(i)))
+            .sum()
+    }
+
+    /// Validates the data of faces with this setup. Every face should be filled
+    /// with the pixels of `dimensions` and mip levels, and the faces of cube texture
+    /// should be square. Each layer of texture array is treated as a face. The `faces`
+    /// could be empty if the texture is not initialized with data.
+    pub fn validate(&self, faces: &[&[u8]]) -> Result<()> {
+        let (w, h) = self.dimensions;
+        let len = self.len();
+
+        let (min_filter, mag_filter) = self.filters();
+        if mag_filter.is_mipmap() {
+            bail!("Mipmaps are not used when texture is magnified.");
+        }
+
+        if min_filter.is_mipmap() && !self.mipmap && self.levels <= 1 {
+            bail!(format!("{:?} requires mipmaps.", min_filter));
+        }
+
+        let max_levels = 32 - w.max(h).max(1).leading_zeros();
+        if self.levels == 0 || self.levels > max_levels {
+            bail!(format!(
+                "The number of mip levels should be in range [1, {}].",
+                max_levels
+            ));
+        }
+
+        if self.mipmap && self.levels > 1 {
+            bail!("Mipmaps could not be generated if they are provided with levels.");
+        }
+
+        if self.mipmap && self.format.is_compressed() {
+            bail!("Mipmaps of compressed texture should be provided with levels.");
+        }
+
+        if self.srgb && !self.format.is_srgb_compatible() {
+            bail!(format!("{:?} could not be encoded in sRGB space.", self.format));
+        }
+
+        match self.kind {
+            TextureKind::Texture2D => if faces.len() > 1 {
+                bail!("2D texture could only have one face.");
+            },
+            TextureKind::Cube => {
+                if self.format.is_compressed() || self.levels > 1 {
+                    bail!("Cube texture with compressed format or mip levels is not supported.");
+                }
+
+                if !faces.is_empty() && faces.len() != 6 {
+                    bail!("Cube texture requires six faces.");
+                }
+
+                if w != h {
+                    bail!("The faces of cube texture should be square.");
+                }
+            }
+            TextureKind::Texture2DArray(layers) => {
+                if layers == 0 {
+                    bail!("Texture array requires at least one layer.");
+                }
+
+                if self.format.is_compressed() || self.levels > 1 {
+                    bail!("Texture array with compressed format or mip levels is not supported.");
+                }
+
+                if !faces.is_empty() && faces.len() != layers as usize {
+                    bail!(format!("Texture array requires {} layers.", layers));
+                }
+            }
+        }
+
+        for (i, face) in faces.iter().enumerate() {
+            if face.len() != len {
+                bail!(format!(
+                    "Mismatched dimensions of face {}, expects {} bytes but got {}.",
+                    i,
+                    len,
+                    face.len()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the update of `rect` in the largest mip level of `layer` with `data`.
+    /// The `layer` should be zero except for texture arrays. The rect of compressed
+    /// texture should be aligned to the blocks of format, which are usually 4x4
+    /// pixels, since the partial updates of block are illegal.
+    pub fn validate_update(&self, rect: Rect, layer: u32, data: &[u8]) -> Result<()> {
+        let layers = match self.kind {
+            TextureKind::Texture2D => 1,
+            TextureKind::Texture2DArray(layers) => layers,
+            TextureKind::Cube => bail!("Can not update cube texture."),
+        };
+
+        if layer >= layers {
+            bail!(ErrorKind::OutOfBounds);
+        }
+
+        let (w, h) = (self.dimensions.0 as i32, self.dimensions.1 as i32);
+        if rect.min.x < 0 || rect.min.y < 0 || rect.max.x > w || rect.max.y > h
+            || rect.width() <= 0 || rect.height() <= 0
+        {
+            bail!(ErrorKind::OutOfBounds);
+        }
+
+        let (bw, bh) = self.format.block_dimensions();
+        let (bw, bh) = (bw as i32, bh as i32);
+        let aligned = |min: i32, max: i32, size: i32, block: i32| {
+            min % block == 0 && (max % block == 0 || max == size)
+        };
+
+        if !aligned(rect.min.x, rect.max.x, w, bw) || !aligned(rect.min.y, rect.max.y, h, bh) {
+            bail!(format!(
+                "The update {:?} of {:?} texture should be aligned to {}x{} blocks.",
+                rect, self.format, bw, bh
+            ));
+        }
+
+        let len = self.format
+            .len((rect.width() as u32, rect.height() as u32));
+        if data.len() < len {
+            bail!(format!(
+                "The update {:?} expects {} bytes but got {}.",
+                rect,
+                len,
+                data.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The setup of render texture, which could be attached to framebuffer and sampled
+/// later as a normal texture.
+///
+/// Render textures with depth formats are sampled with `UniformVariableType::Texture`
+/// as `sampler2D`, the depth values in range [0, 1] are stored in the red channel. They
+/// are always sampled with `Nearest` filter and `Clamp` address, which is required
+/// by OpenGL ES.
 #[derive(Debug, Copy, Clone)]
 pub struct RenderTextureSetup {
     pub format: RenderTextureFormat,
@@ -43,6 +269,19 @@ impl_handle!(TextureHandle);
 pub struct RenderBufferSetup {
     pub format: RenderTextureFormat,
     pub dimensions: (u32, u32),
+    /// The number of samples of multisampled render buffer. It will be clamped to
+    /// the maximum supported samples, and zero or one means no multisampling.
+    pub samples: u32,
+}
+
+impl Default for RenderBufferSetup {
+    fn default() -> Self {
+        RenderBufferSetup {
+            format: RenderTextureFormat::RGB8,
+            dimensions: (0, 0),
+            samples: 0,
+        }
+    }
 }
 
 impl_handle!(RenderBufferHandle);
@@ -56,6 +295,29 @@ pub enum TextureFilter {
     /// Returns the weighted average of the four texture elements that are closest to the
     /// center of the pixel being textured.
     Linear,
+    /// Chooses the mipmap that most closely matches the size of the pixel being textured,
+    /// and samples it with `Nearest`.
+    NearestMipmapNearest,
+    /// Chooses the mipmap that most closely matches the size of the pixel being textured,
+    /// and samples it with `Linear`.
+    LinearMipmapNearest,
+    /// Chooses the two mipmaps that most closely match the size of the pixel being
+    /// textured, samples each of them with `Nearest` and blends the results.
+    NearestMipmapLinear,
+    /// Chooses the two mipmaps that most closely match the size of the pixel being
+    /// textured, samples each of them with `Linear` and blends the results. This is
+    /// also known as trilinear filtering.
+    LinearMipmapLinear,
+}
+
+impl TextureFilter {
+    /// Returns true if this filter samples from mipmaps.
+    pub fn is_mipmap(&self) -> bool {
+        match *self {
+            TextureFilter::Nearest | TextureFilter::Linear => false,
+            _ => true,
+        }
+    }
 }
 
 /// Sets the wrap parameter for texture.
@@ -81,9 +343,30 @@ pub enum RenderTextureFormat {
     Depth16,
     Depth24,
     Depth32,
+    /// 32-bits floating-point depth, which keeps the precision of far distances with
+    /// the reverse-Z projection.
+    Depth32F,
     Depth24Stencil8,
 }
 
+impl RenderTextureFormat {
+    /// Returns true if this format is attached to the depth attachment point of
+    /// framebuffer, including the packed depth-stencil format.
+    pub fn is_depth(&self) -> bool {
+        match *self {
+            RenderTextureFormat::RGB8 | RenderTextureFormat::RGBA4 | RenderTextureFormat::RGBA8 => {
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns true if this format has a stencil buffer.
+    pub fn is_stencil(&self) -> bool {
+        *self == RenderTextureFormat::Depth24Stencil8
+    }
+}
+
 /// List of all the possible formats of input data when uploading to texture.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TextureFormat {
@@ -103,6 +386,22 @@ pub enum TextureFormat {
     F32F32,
     F32F32F32,
     F32F32F32F32,
+    /// S3TC compressed RGB, 8 bytes per 4x4 block. It's also known as BC1.
+    DXT1,
+    /// S3TC compressed RGBA with explicit alpha, 16 bytes per 4x4 block. It's also
+    /// known as BC2.
+    DXT3,
+    /// S3TC compressed RGBA with interpolated alpha, 16 bytes per 4x4 block. It's
+    /// also known as BC3.
+    DXT5,
+    /// ETC1 compressed RGB, 8 bytes per 4x4 block.
+    ETC1,
+    /// ETC2 compressed RGB, 8 bytes per 4x4 block.
+    ETC2RGB8,
+    /// ETC2 compressed RGBA, 16 bytes per 4x4 block.
+    ETC2RGBA8,
+    /// ASTC compressed RGBA with 4x4 blocks, 16 bytes per block.
+    ASTC4x4,
 }
 
 impl TextureFormat {
@@ -125,10 +424,18 @@ impl TextureFormat {
             TextureFormat::F32F32 => 2,
             TextureFormat::F32F32F32 => 3,
             TextureFormat::F32F32F32F32 => 4,
+            TextureFormat::DXT1 => 3,
+            TextureFormat::DXT3 => 4,
+            TextureFormat::DXT5 => 4,
+            TextureFormat::ETC1 => 3,
+            TextureFormat::ETC2RGB8 => 3,
+            TextureFormat::ETC2RGBA8 => 4,
+            TextureFormat::ASTC4x4 => 4,
         }
     }
 
-    /// Returns the size in bytes of a pixel of this type.
+    /// Returns the size in bytes of a pixel of this type, or a block of compressed
+    /// formats.
     pub fn size(&self) -> u8 {
         match *self {
             TextureFormat::U8 => 1,
@@ -147,6 +454,192 @@ impl TextureFormat {
             TextureFormat::F32F32 => 8,
             TextureFormat::F32F32F32 => 12,
             TextureFormat::F32F32F32F32 => 16,
+            TextureFormat::DXT1 => 8,
+            TextureFormat::DXT3 => 16,
+            TextureFormat::DXT5 => 16,
+            TextureFormat::ETC1 => 8,
+            TextureFormat::ETC2RGB8 => 8,
+            TextureFormat::ETC2RGBA8 => 16,
+            TextureFormat::ASTC4x4 => 16,
+        }
+    }
+
+    /// Returns true if this is a GPU compressed format.
+    pub fn is_compressed(&self) -> bool {
+        self.block_dimensions() != (1, 1)
+    }
+
+    /// Gets the dimensions in pixels of a block, it's `(1, 1)` for uncompressed
+    /// formats.
+    pub fn block_dimensions(&self) -> (u32, u32) {
+        match *self {
+            TextureFormat::DXT1
+            | TextureFormat::DXT3
+            | TextureFormat::DXT5
+            | TextureFormat::ETC1
+            | TextureFormat::ETC2RGB8
+            | TextureFormat::ETC2RGBA8
+            | TextureFormat::ASTC4x4 => (4, 4),
+            _ => (1, 1),
+        }
+    }
+
+    /// Gets the size in bytes of a image with `dimensions`. The partial blocks at
+    /// the edges of compressed image take the same room as the complete ones.
+    pub fn len(&self, dimensions: (u32, u32)) -> usize {
+        let (bw, bh) = self.block_dimensions();
+        let blocks = ((dimensions.0 + bw - 1) / bw) * ((dimensions.1 + bh - 1) / bh);
+        blocks as usize * self.size() as usize
+    }
+
+    /// Returns true if this format could be encoded in sRGB space.
+    pub fn is_srgb_compatible(&self) -> bool {
+        match *self {
+            TextureFormat::U8U8U8 | TextureFormat::U8U8U8U8 => true,
+            TextureFormat::ETC1 => false,
+            _ => self.is_compressed(),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math;
+
+    #[test]
+    fn validate() {
+        let mut setup = TextureSetup::default();
+        setup.kind = TextureKind::Cube;
+        setup.dimensions = (2, 2);
+
+        let face = [0u8; 16];
+        let faces = [&face[..]; 6];
+        assert!(setup.validate(&faces).is_ok());
+        assert!(setup.validate(&faces[0..5]).is_err());
+
+        let small = [0u8; 4];
+        let mut mismatched = faces;
+        mismatched[3] = &small[..];
+        assert!(setup.validate(&mismatched).is_err());
+
+        setup.dimensions = (2, 1);
+        let face = [0u8; 8];
+        assert!(setup.validate(&[&face[..]; 6]).is_err());
+
+        setup.kind = TextureKind::Texture2D;
+        assert!(setup.validate(&[&face[..]]).is_ok());
+
+        setup.kind = TextureKind::Texture2DArray(3);
+        assert!(setup.validate(&[&face[..]; 3]).is_ok());
+        assert!(setup.validate(&[&face[..]; 2]).is_err());
+        assert!(setup.validate(&[&face[..], &face[..], &small[..]]).is_err());
+
+        setup.kind = TextureKind::Texture2DArray(0);
+        assert!(setup.validate(&[]).is_err());
+    }
+
+    #[test]
+    fn filters() {
+        let mut setup = TextureSetup::default();
+        assert!(setup.validate(&[]).is_ok());
+
+        setup.filter = TextureFilter::LinearMipmapLinear;
+        assert!(setup.validate(&[]).is_err());
+
+        setup.mipmap = true;
+        assert!(setup.validate(&[]).is_ok());
+        assert_eq!(
+            setup.filters(),
+            (TextureFilter::LinearMipmapLinear, TextureFilter::Linear)
+        );
+
+        setup.mag_filter = Some(TextureFilter::Nearest);
+        setup.min_filter = Some(TextureFilter::NearestMipmapNearest);
+        assert_eq!(
+            setup.filters(),
+            (TextureFilter::NearestMipmapNearest, TextureFilter::Nearest)
+        );
+
+        setup.mag_filter = Some(TextureFilter::LinearMipmapNearest);
+        assert!(setup.validate(&[]).is_err());
+
+        setup.dimensions = (256, 128);
+        assert!(setup.is_power_of_two());
+
+        setup.dimensions = (256, 100);
+        assert!(!setup.is_power_of_two());
+    }
+
+    #[test]
+    fn srgb() {
+        let mut setup = TextureSetup::default();
+        setup.srgb = true;
+        assert!(setup.validate(&[]).is_ok());
+
+        setup.format = TextureFormat::U8U8U8;
+        assert!(setup.validate(&[]).is_ok());
+
+        // Data textures like the floating-point ones should stay linear.
+        setup.format = TextureFormat::F16F16F16F16;
+        assert!(setup.validate(&[]).is_err());
+
+        setup.srgb = false;
+        assert!(setup.validate(&[]).is_ok());
+    }
+
+    #[test]
+    fn compressed() {
+        assert_eq!(TextureFormat::DXT1.len((4, 4)), 8);
+        assert_eq!(TextureFormat::DXT5.len((6, 5)), 64);
+        assert_eq!(TextureFormat::U8U8U8U8.len((6, 5)), 120);
+
+        let mut setup = TextureSetup::default();
+        setup.format = TextureFormat::DXT1;
+        setup.dimensions = (8, 8);
+        setup.levels = 4;
+        setup.filter = TextureFilter::LinearMipmapLinear;
+
+        // 8x8, 4x4, 2x2 and 1x1 levels, the smaller ones take a whole block.
+        assert_eq!(setup.len(), 32 + 8 + 8 + 8);
+        assert!(setup.validate(&[&[0u8; 56][..]]).is_ok());
+        assert!(setup.validate(&[&[0u8; 48][..]]).is_err());
+
+        setup.levels = 5;
+        assert!(setup.validate(&[]).is_err());
+
+        setup.levels = 1;
+        setup.mipmap = true;
+        assert!(setup.validate(&[]).is_err());
+    }
+
+    #[test]
+    fn update() {
+        let rect = |x0, y0, x1, y1| {
+            Rect::new(math::Point2::new(x0, y0), math::Point2::new(x1, y1))
+        };
+
+        let mut setup = TextureSetup::default();
+        setup.dimensions = (6, 6);
+        assert!(setup.validate_update(rect(1, 1, 3, 3), 0, &[0; 16]).is_ok());
+        assert!(setup.validate_update(rect(1, 1, 3, 3), 0, &[0; 8]).is_err());
+        assert!(setup.validate_update(rect(4, 4, 8, 8), 0, &[0; 64]).is_err());
+
+        // Updates of compressed texture should be aligned to blocks, except at the edges.
+        setup.format = TextureFormat::DXT1;
+        assert!(setup.validate_update(rect(0, 0, 4, 4), 0, &[0; 8]).is_ok());
+        assert!(setup.validate_update(rect(4, 0, 6, 6), 0, &[0; 16]).is_ok());
+        assert!(setup.validate_update(rect(2, 0, 6, 4), 0, &[0; 8]).is_err());
+        assert!(setup.validate_update(rect(0, 0, 3, 4), 0, &[0; 8]).is_err());
+
+        setup.format = TextureFormat::U8U8U8U8;
+        assert!(setup.validate_update(rect(0, 0, 1, 1), 1, &[0; 4]).is_err());
+
+        setup.kind = TextureKind::Texture2DArray(2);
+        assert!(setup.validate_update(rect(0, 0, 1, 1), 1, &[0; 4]).is_ok());
+        assert!(setup.validate_update(rect(0, 0, 1, 1), 2, &[0; 4]).is_err());
+
+        setup.kind = TextureKind::Cube;
+        assert!(setup.validate_update(rect(0, 0, 1, 1), 0, &[0; 4]).is_err());
+    }
+}
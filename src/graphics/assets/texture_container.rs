@@ -0,0 +1,263 @@
+//! Parses the `.dds` and `.ktx` containers of GPU textures.
+//!
+//! ```rust,ignore
+//! let location = Location::shared(0, "/std/stone.ktx");
+//! let texture = video.create_texture_from::<TextureContainerParser>(location, setup)?;
+//! ```
+
+use graphics::errors::*;
+use graphics::assets::texture::*;
+use graphics::assets::texture_loader::{TextureData, TextureParser};
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x0020_0000;
+
+const KTX_MAGIC: &[u8] = &[
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A
+];
+const KTX_HEADER_SIZE: usize = 64;
+const KTX_ENDIANNESS: u32 = 0x0403_0201;
+
+/// Parses the `.dds` and `.ktx` (version 1) containers into `TextureData`, which could
+/// be used with `create_texture_from::<TextureContainerParser>`. The container is
+/// recognized by its magic bytes.
+///
+/// All the mip levels in container are fed into texture directly, and the compressed
+/// blocks are passed through unchanged. Only 2D textures with the compressed formats of
+/// `TextureFormat` or 8-bits RGBA are supported. Notes that the rows are uploaded in the
+/// order they are stored, e.g. the `.dds` files are usually upside down.
+pub struct TextureContainerParser {}
+
+impl TextureParser for TextureContainerParser {
+    type Error = Error;
+
+    fn parse(bytes: &[u8]) -> Result<TextureData> {
+        if bytes.starts_with(DDS_MAGIC) {
+            TextureContainerParser::parse_dds(bytes)
+        } else if bytes.starts_with(KTX_MAGIC) {
+            TextureContainerParser::parse_ktx(bytes)
+        } else {
+            bail!("Unknown texture container, only DDS and KTX are supported.");
+        }
+    }
+}
+
+impl TextureContainerParser {
+    fn parse_dds(bytes: &[u8]) -> Result<TextureData> {
+        if bytes.len() < DDS_HEADER_SIZE || read_u32(bytes, 4) != 124 {
+            bail!("Truncated DDS header.");
+        }
+
+        if read_u32(bytes, 112) & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+            bail!("Cube and volume textures in DDS are not supported.");
+        }
+
+        let dimensions = (read_u32(bytes, 16), read_u32(bytes, 12));
+        let levels = read_u32(bytes, 28).max(1);
+
+        let flags = read_u32(bytes, 80);
+        let (format, offset) = if flags & DDPF_FOURCC != 0 {
+            match &bytes[84..88] {
+                b"DXT1" => (TextureFormat::DXT1, DDS_HEADER_SIZE),
+                b"DXT3" => (TextureFormat::DXT3, DDS_HEADER_SIZE),
+                b"DXT5" => (TextureFormat::DXT5, DDS_HEADER_SIZE),
+                b"DX10" => {
+                    let offset = DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE;
+                    if bytes.len() < offset {
+                        bail!("Truncated DX10 header of DDS.");
+                    }
+
+                    // The dimension of resource should be TEXTURE2D, and the array
+                    // size should be one.
+                    if read_u32(bytes, 132) != 3 || read_u32(bytes, 140) > 1 {
+                        bail!("Only 2D textures in DDS are supported.");
+                    }
+
+                    match read_u32(bytes, 128) {
+                        71 | 72 => (TextureFormat::DXT1, offset),
+                        74 | 75 => (TextureFormat::DXT3, offset),
+                        77 | 78 => (TextureFormat::DXT5, offset),
+                        28 | 29 => (TextureFormat::U8U8U8U8, offset),
+                        v => bail!(format!("Unsupported DXGI format {} of DDS.", v)),
+                    }
+                }
+                v => bail!(format!("Unsupported FourCC {:?} of DDS.", v)),
+            }
+        } else if flags & DDPF_RGB != 0 && read_u32(bytes, 88) == 32
+            && read_u32(bytes, 92) == 0x0000_00FF
+            && read_u32(bytes, 96) == 0x0000_FF00
+            && read_u32(bytes, 100) == 0x00FF_0000
+        {
+            (TextureFormat::U8U8U8U8, DDS_HEADER_SIZE)
+        } else {
+            bail!("Unsupported pixel format of DDS.");
+        };
+
+        let len = TextureContainerParser::len(format, dimensions, levels);
+        let data = bytes.get(offset..offset + len);
+        let data = data.ok_or("Truncated mip levels of DDS.")?;
+
+        Ok(TextureData {
+            format: format,
+            dimensions: dimensions,
+            levels: levels,
+            data: data.to_vec(),
+        })
+    }
+
+    fn parse_ktx(bytes: &[u8]) -> Result<TextureData> {
+        if bytes.len() < KTX_HEADER_SIZE {
+            bail!("Truncated KTX header.");
+        }
+
+        if read_u32(bytes, 12) != KTX_ENDIANNESS {
+            bail!("Only KTX in little endian is supported.");
+        }
+
+        if read_u32(bytes, 44) > 0 || read_u32(bytes, 48) > 0 || read_u32(bytes, 52) != 1 {
+            bail!("Only 2D textures in KTX are supported.");
+        }
+
+        let format = match read_u32(bytes, 28) {
+            0x83F0 | 0x8C4C => TextureFormat::DXT1,
+            0x83F2 | 0x8C4E => TextureFormat::DXT3,
+            0x83F3 | 0x8C4F => TextureFormat::DXT5,
+            0x8D64 => TextureFormat::ETC1,
+            0x9274 | 0x9275 => TextureFormat::ETC2RGB8,
+            0x9278 | 0x9279 => TextureFormat::ETC2RGBA8,
+            0x93B0 | 0x93D0 => TextureFormat::ASTC4x4,
+            // GL_RGBA8 and GL_SRGB8_ALPHA8 with GL_UNSIGNED_BYTE.
+            0x8058 | 0x8C43 if read_u32(bytes, 16) == 0x1401 => TextureFormat::U8U8U8U8,
+            v => bail!(format!("Unsupported internal format {:#X} of KTX.", v)),
+        };
+
+        let dimensions = (read_u32(bytes, 36), read_u32(bytes, 40).max(1));
+        let levels = read_u32(bytes, 56).max(1);
+
+        let mut setup = TextureSetup::default();
+        setup.format = format;
+        setup.dimensions = dimensions;
+
+        // Skips the key-value pairs, and every level is prefixed with its size.
+        let mut pos = KTX_HEADER_SIZE + read_u32(bytes, 60) as usize;
+        let mut data = Vec::with_capacity(TextureContainerParser::len(format, dimensions, levels));
+        for i in 0..levels {
+            let len = format.len(setup.level_dimensions(i));
+            let size = bytes.get(pos..pos + 4).ok_or("Truncated mip levels of KTX.")?;
+            let size = read_u32(size, 0) as usize;
+            if size != len {
+                bail!(format!(
+                    "Mismatched size of mip level {} in KTX, expects {} bytes but got {}.",
+                    i, len, size
+                ));
+            }
+
+            let level = bytes.get(pos + 4..pos + 4 + len);
+            data.extend_from_slice(level.ok_or("Truncated mip levels of KTX.")?);
+
+            // Levels are padded to 4 bytes.
+            pos += 4 + ((len + 3) & !3);
+        }
+
+        Ok(TextureData {
+            format: format,
+            dimensions: dimensions,
+            levels: levels,
+            data: data,
+        })
+    }
+
+    fn len(format: TextureFormat, dimensions: (u32, u32), levels: u32) -> usize {
+        let mut setup = TextureSetup::default();
+        setup.format = format;
+        setup.dimensions = dimensions;
+        setup.levels = levels;
+        setup.len()
+    }
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    let v = &bytes[pos..pos + 4];
+    u32::from(v[0]) | u32::from(v[1]) << 8 | u32::from(v[2]) << 16 | u32::from(v[3]) << 24
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_u32(bytes: &mut [u8], pos: usize, v: u32) {
+        for i in 0..4 {
+            bytes[pos + i] = (v >> (i * 8)) as u8;
+        }
+    }
+
+    #[test]
+    fn dds() {
+        let mut bytes = vec![0; DDS_HEADER_SIZE];
+        bytes[0..4].copy_from_slice(DDS_MAGIC);
+        write_u32(&mut bytes, 4, 124);
+        write_u32(&mut bytes, 12, 8);
+        write_u32(&mut bytes, 16, 8);
+        write_u32(&mut bytes, 28, 4);
+        write_u32(&mut bytes, 80, DDPF_FOURCC);
+        bytes[84..88].copy_from_slice(b"DXT5");
+
+        // Blocks of 8x8, 4x4, 2x2 and 1x1 levels.
+        bytes.extend_from_slice(&[1; 64]);
+        bytes.extend_from_slice(&[2; 16 * 3]);
+
+        let texture = TextureContainerParser::parse(&bytes).unwrap();
+        assert_eq!(texture.format, TextureFormat::DXT5);
+        assert_eq!(texture.dimensions, (8, 8));
+        assert_eq!(texture.levels, 4);
+        assert_eq!(texture.data.len(), 112);
+
+        assert!(TextureContainerParser::parse(&bytes[..120]).is_err());
+
+        bytes.truncate(DDS_HEADER_SIZE + 64);
+        assert!(TextureContainerParser::parse(&bytes).is_err());
+
+        write_u32(&mut bytes, 28, 1);
+        bytes[84..88].copy_from_slice(b"ATI2");
+        assert!(TextureContainerParser::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn ktx() {
+        let mut bytes = vec![0; KTX_HEADER_SIZE];
+        bytes[0..12].copy_from_slice(KTX_MAGIC);
+        write_u32(&mut bytes, 12, KTX_ENDIANNESS);
+        write_u32(&mut bytes, 28, 0x8D64);
+        write_u32(&mut bytes, 36, 6);
+        write_u32(&mut bytes, 40, 4);
+        write_u32(&mut bytes, 52, 1);
+        write_u32(&mut bytes, 56, 2);
+        write_u32(&mut bytes, 60, 4);
+        bytes.extend_from_slice(&[0; 4]);
+
+        // The 6x4 level takes two blocks, and the 3x2 one takes one.
+        for &(len, v) in &[(16, 1), (8, 2)] {
+            let mut size = [0; 4];
+            write_u32(&mut size, 0, len as u32);
+            bytes.extend_from_slice(&size);
+            bytes.extend_from_slice(&vec![v; len]);
+        }
+
+        let texture = TextureContainerParser::parse(&bytes).unwrap();
+        assert_eq!(texture.format, TextureFormat::ETC1);
+        assert_eq!(texture.dimensions, (6, 4));
+        assert_eq!(texture.levels, 2);
+        assert_eq!(&texture.data[..16], &[1; 16]);
+        assert_eq!(&texture.data[16..], &[2; 8]);
+
+        write_u32(&mut bytes, 52, 6);
+        assert!(TextureContainerParser::parse(&bytes).is_err());
+
+        assert!(TextureContainerParser::parse(b"PNG").is_err());
+    }
+}
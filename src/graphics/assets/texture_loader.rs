@@ -1,16 +1,19 @@
 use std;
 use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Weak};
 use std::marker::PhantomData;
 
 use resource;
 use graphics::assets::texture::*;
-use graphics::backend::frame::{DoubleFrame, PreFrameTask};
+use graphics::backend::frame::{MultiFrame, PreFrameTask};
 
 /// Parsed texture from `TextureParser`.
 pub struct TextureData {
     pub format: TextureFormat,
     pub dimensions: (u32, u32),
+    /// The number of mip levels, which are stored contiguously in `data` from the
+    /// largest one.
+    pub levels: u32,
     pub data: Vec<u8>,
 }
 
@@ -37,7 +40,9 @@ where
     handle: TextureHandle,
     setup: TextureSetup,
     state: Arc<RwLock<TextureState>>,
-    frames: Arc<DoubleFrame>,
+    frames: Arc<MultiFrame>,
+    npot_mipmap: bool,
+    promise: resource::ResourcePromise,
     _phantom: PhantomData<T>,
 }
 
@@ -49,47 +54,162 @@ where
         handle: TextureHandle,
         state: Arc<RwLock<TextureState>>,
         setup: TextureSetup,
-        frames: Arc<DoubleFrame>,
+        frames: Arc<MultiFrame>,
+        npot_mipmap: bool,
+        promise: resource::ResourcePromise,
     ) -> Self {
         TextureLoader {
             handle: handle,
             setup: setup,
             state: state,
             frames: frames,
+            npot_mipmap: npot_mipmap,
+            promise: promise,
             _phantom: PhantomData,
         }
     }
 }
 
+impl<T> Drop for TextureLoader<T>
+where
+    T: TextureParser,
+{
+    fn drop(&mut self) {
+        // The loader might be dropped without finishing, e.g. the parser panics.
+        if let Ok(mut state) = self.state.write() {
+            if *state == TextureState::NotReady {
+                *state = TextureState::Err("The loading of texture was interrupted.".into());
+            }
+        }
+    }
+}
+
 impl<T> resource::ResourceAsyncLoader for TextureLoader<T>
 where
     T: TextureParser + Send + Sync + 'static,
 {
-    fn on_finished(mut self, path: &Path, result: resource::errors::Result<&[u8]>) {
+    fn on_finished(self, path: &Path, result: resource::errors::Result<&[u8]>) {
+        let result = result
+            .map_err(|err| format!("{:?}", err))
+            .and_then(|bytes| parse::<T>(self.setup, self.npot_mipmap, bytes));
+
         let state = match result {
-            Ok(bytes) => match T::parse(bytes) {
-                Ok(texture) => {
-                    self.setup.dimensions = texture.dimensions;
-                    self.setup.format = texture.format;
-
-                    let mut frame = self.frames.front();
-                    let ptr = frame.buf.extend_from_slice(&texture.data);
-                    let task = PreFrameTask::CreateTexture(self.handle, self.setup, Some(ptr));
-                    frame.pre.push(task);
-
-                    TextureState::Ready
-                }
-                Err(error) => {
-                    let error = format!("Failed to load texture at {:?}.\n{:?}", path, error);
-                    TextureState::Err(error)
-                }
-            },
+            Ok((setup, texture)) => {
+                let mut frame = self.frames.front();
+                let ptr = frame.buf.extend_from_slice(&texture.data);
+                let task = PreFrameTask::CreateTexture(self.handle, setup, Some(ptr));
+                frame.pre.push(task);
+
+                TextureState::Ready
+            }
             Err(error) => {
-                let error = format!("Failed to load texture at {:?}.\n{:?}", path, error);
+                let error = format!("Failed to load texture at {:?}.\n{}", path, error);
                 TextureState::Err(error)
             }
         };
 
+        let result = match state {
+            TextureState::Err(ref error) => Err(error.clone()),
+            _ => Ok(()),
+        };
+
         *self.state.write().unwrap() = state;
+        self.promise.resolve(result);
+    }
+}
+
+#[doc(hidden)]
+pub(crate) struct TextureReloader<T>
+where
+    T: TextureParser,
+{
+    handle: TextureHandle,
+    setup: TextureSetup,
+    state: Weak<RwLock<TextureState>>,
+    frames: Arc<MultiFrame>,
+    npot_mipmap: bool,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> TextureReloader<T>
+where
+    T: TextureParser,
+{
+    pub fn new(
+        handle: TextureHandle,
+        state: &Arc<RwLock<TextureState>>,
+        setup: TextureSetup,
+        frames: Arc<MultiFrame>,
+        npot_mipmap: bool,
+    ) -> Self {
+        TextureReloader {
+            handle: handle,
+            setup: setup,
+            state: Arc::downgrade(state),
+            frames: frames,
+            npot_mipmap: npot_mipmap,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> resource::ResourceHotReloader for TextureReloader<T>
+where
+    T: TextureParser + Send + Sync + 'static,
+{
+    fn is_alive(&self) -> bool {
+        self.state.upgrade().is_some()
+    }
+
+    fn on_reload(&self, path: &Path, bytes: &[u8]) -> resource::errors::Result<()> {
+        let state = match self.state.upgrade() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+
+        match parse::<T>(self.setup, self.npot_mipmap, bytes) {
+            Ok((setup, texture)) => {
+                let mut frame = self.frames.front();
+                let ptr = frame.buf.extend_from_slice(&texture.data);
+                let task = PreFrameTask::ReloadTexture(self.handle, setup, ptr, path.to_owned());
+                frame.pre.push(task);
+
+                *state.write().unwrap() = TextureState::Ready;
+                Ok(())
+            }
+            Err(error) => {
+                let error = format!("Failed to reload texture at {:?}.\n{}", path, error);
+                Err(error.into())
+            }
+        }
+    }
+}
+
+fn parse<T>(
+    mut setup: TextureSetup,
+    npot_mipmap: bool,
+    bytes: &[u8],
+) -> ::std::result::Result<(TextureSetup, TextureData), String>
+where
+    T: TextureParser,
+{
+    let texture = T::parse(bytes).map_err(|err| format!("{:?}", err))?;
+    setup.dimensions = texture.dimensions;
+    setup.format = texture.format;
+    setup.levels = texture.levels;
+
+    // The mip levels from parser are used directly instead of being generated.
+    if texture.levels > 1 || texture.format.is_compressed() {
+        setup.mipmap = false;
+    }
+
+    if let Err(error) = setup.validate(&[&texture.data]) {
+        return Err(format!("{:?}", error));
+    }
+
+    if !setup.is_mipmap_supported(npot_mipmap) {
+        return Err("Mipmaps of NPOT texture are not supported by the device.".into());
     }
+
+    Ok((setup, texture))
 }
@@ -0,0 +1,27 @@
+//! Uniform buffer object, which shares a block of uniform variables between draw calls
+//! and shaders.
+
+use graphics::BufferHint;
+
+impl_handle!(UniformBufferHandle);
+
+/// The setup of uniform buffer object. The contents of buffer are laid out in `std140`,
+/// e.g. `vec3` and `mat4` columns are aligned to 16 bytes, so it could be shared by any
+/// shader that declares the same uniform block.
+#[derive(Debug, Copy, Clone)]
+pub struct UniformBufferSetup {
+    /// The size of buffer in bytes, which should match the size of uniform block
+    /// declared by shaders in `ShaderSetup::uniform_blocks`.
+    pub size: usize,
+    /// Usually, uniform buffers are updated once per frame with `BufferHint::Stream`.
+    pub hint: BufferHint,
+}
+
+impl Default for UniformBufferSetup {
+    fn default() -> Self {
+        UniformBufferSetup {
+            size: 0,
+            hint: BufferHint::Stream,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Packs many small images into one texture, e.g. the sprites or glyphs of fonts.
+
+use std::sync::Arc;
+
+use math;
+use resource::Location;
+use utils::Rect;
+
+use super::*;
+use super::errors::*;
+
+/// The empty pixels between regions, which keeps the linear filtering from sampling the
+/// neighbours. It's not required at the edges of texture.
+const PADDING: u32 = 1;
+
+/// A region allocated in `TextureAtlas`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    /// The rect in pixels.
+    pub rect: Rect,
+    /// The normalized texture coordinates of the min and max corners.
+    pub uv: ((f32, f32), (f32, f32)),
+}
+
+/// `TextureAtlas` owns a 2D texture, and packs the images inserted into it with a
+/// shelf bin-packer. The pixels of images are uploaded with `update_texture` into their
+/// regions, and the regions never overlap.
+///
+/// An error is returned once there is no room for the image, the existing regions are
+/// never overwritten. The texture is deleted when the atlas is dropped.
+///
+/// ```rust,ignore
+/// let mut setup = TextureSetup::default();
+/// setup.dimensions = (512, 512);
+///
+/// let mut atlas = TextureAtlas::new(video.clone(), setup)?;
+/// let region = atlas.insert((16, 16), &pixels)?;
+/// dc.set_uniform_variable("u_MainTex", atlas.texture());
+/// ```
+pub struct TextureAtlas {
+    video: Arc<GraphicsSystemShared>,
+    texture: TextureHandle,
+    setup: TextureSetup,
+    packer: ShelfPacker,
+}
+
+impl TextureAtlas {
+    /// Creates an atlas with an empty texture. Images are uploaded into the texture
+    /// after it's created, so neither mipmaps nor compressed formats are supported.
+    pub fn new(video: Arc<GraphicsSystemShared>, setup: TextureSetup) -> Result<Self> {
+        if setup.format.is_compressed() {
+            bail!(format!("Atlas of compressed format {:?} is not supported.", setup.format));
+        }
+
+        if setup.mipmap || setup.levels > 1 {
+            bail!("Atlas with mipmaps is not supported.");
+        }
+
+        let empty: Option<&[u8]> = None;
+        let texture = video.create_texture(Location::unique(""), setup, empty)?;
+
+        Ok(TextureAtlas {
+            video: video,
+            texture: texture,
+            setup: setup,
+            packer: ShelfPacker::new(setup.dimensions),
+        })
+    }
+
+    /// Gets the texture of this atlas.
+    #[inline]
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Gets the dimensions of texture in pixels.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.setup.dimensions
+    }
+
+    /// Allocates a region of `dimensions`, and uploads the tightly packed pixels of
+    /// image into it. An error is returned if the atlas is full.
+    pub fn insert(&mut self, dimensions: (u32, u32), data: &[u8]) -> Result<AtlasRegion> {
+        let len = self.setup.format.len(dimensions);
+        if data.len() != len {
+            bail!(format!(
+                "The image of {:?} should have {} bytes, but {} bytes are provided.",
+                dimensions,
+                len,
+                data.len()
+            ));
+        }
+
+        let rect = match self.packer.allocate(dimensions) {
+            Some(rect) => rect,
+            None => bail!(format!(
+                "There is no room for the image of {:?} in atlas of {:?}.",
+                dimensions, self.setup.dimensions
+            )),
+        };
+
+        self.video.update_texture(self.texture, rect, data)?;
+
+        let (w, h) = (self.setup.dimensions.0 as f32, self.setup.dimensions.1 as f32);
+        let uv = (
+            (rect.min.x as f32 / w, rect.min.y as f32 / h),
+            (rect.max.x as f32 / w, rect.max.y as f32 / h),
+        );
+
+        Ok(AtlasRegion { rect: rect, uv: uv })
+    }
+
+    /// Forgets all the regions, so the texture could be filled from scratch. The
+    /// pixels of texture are left as they are.
+    pub fn clear(&mut self) {
+        self.packer = ShelfPacker::new(self.setup.dimensions);
+    }
+}
+
+impl Drop for TextureAtlas {
+    fn drop(&mut self) {
+        self.video.delete_texture(self.texture);
+    }
+}
+
+/// A row of regions with the same height.
+#[derive(Debug, Clone, Copy)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// The left edge of free space.
+    x: u32,
+}
+
+/// Packs rects into rows from top to bottom. Every rect is placed into the shelf
+/// that wastes the least height, or a new shelf if none of them fits.
+struct ShelfPacker {
+    dimensions: (u32, u32),
+    shelves: Vec<Shelf>,
+    /// The top edge of free space below the shelves.
+    bottom: u32,
+}
+
+impl ShelfPacker {
+    fn new(dimensions: (u32, u32)) -> Self {
+        ShelfPacker {
+            dimensions: dimensions,
+            shelves: Vec::new(),
+            bottom: 0,
+        }
+    }
+
+    fn allocate(&mut self, dimensions: (u32, u32)) -> Option<Rect> {
+        if dimensions.0 == 0 || dimensions.1 == 0 {
+            return None;
+        }
+
+        let (w, h) = (dimensions.0 + PADDING, dimensions.1 + PADDING);
+        let width = self.dimensions.0;
+
+        let mut best: Option<usize> = None;
+        for (i, v) in self.shelves.iter().enumerate() {
+            if v.height >= dimensions.1 && v.x + dimensions.0 <= width {
+                let better = match best {
+                    Some(b) => v.height < self.shelves[b].height,
+                    None => true,
+                };
+
+                if better {
+                    best = Some(i);
+                }
+            }
+        }
+
+        let index = match best {
+            Some(index) => index,
+            None => {
+                if dimensions.0 > width || self.bottom + dimensions.1 > self.dimensions.1 {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y: self.bottom,
+                    height: dimensions.1,
+                    x: 0,
+                });
+
+                self.bottom += h;
+                self.shelves.len() - 1
+            }
+        };
+
+        let shelf = &mut self.shelves[index];
+        let min = math::Point2::new(shelf.x as i32, shelf.y as i32);
+        let max = math::Point2::new(min.x + dimensions.0 as i32, min.y + dimensions.1 as i32);
+        shelf.x += w;
+        Some(Rect::new(min, max))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack() {
+        let bounds = Rect::new(math::Point2::new(0, 0), math::Point2::new(64, 64));
+        let mut packer = ShelfPacker::new((64, 64));
+
+        let mut rects: Vec<Rect> = Vec::new();
+        for i in 0..256 {
+            let dimensions = (4 + i % 7, 3 + i % 5);
+            let rect = match packer.allocate(dimensions) {
+                Some(rect) => rect,
+                None => break,
+            };
+
+            assert_eq!(rect.width(), dimensions.0 as i32);
+            assert_eq!(rect.height(), dimensions.1 as i32);
+            assert!(bounds.contains_rect(rect));
+
+            // The padding is kept between regions.
+            for v in &rects {
+                assert!(!v.expand(PADDING as i32).intersects(rect));
+            }
+
+            rects.push(rect);
+        }
+
+        assert!(rects.len() > 32);
+        assert!(packer.allocate((65, 1)).is_none());
+        assert!(packer.allocate((0, 0)).is_none());
+    }
+
+    #[test]
+    fn full() {
+        let mut packer = ShelfPacker::new((16, 16));
+        for _ in 0..4 {
+            assert!(packer.allocate((7, 7)).is_some());
+        }
+
+        assert!(packer.allocate((7, 7)).is_none());
+        assert!(packer.allocate((1, 1)).is_none());
+
+        // Rects fill the whole texture without padding at the edges, and smaller ones
+        // are placed into the remaining space of shelves.
+        let mut packer = ShelfPacker::new((16, 16));
+        assert!(packer.allocate((7, 16)).is_some());
+        assert!(packer.allocate((3, 3)).is_some());
+        assert!(packer.allocate((4, 4)).is_some());
+        assert!(packer.allocate((1, 1)).is_none());
+    }
+}
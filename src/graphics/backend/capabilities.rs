@@ -4,6 +4,7 @@ use std::mem;
 
 use gl;
 use gl::types::*;
+use graphics::{RenderTextureFormat, TextureFormat};
 use super::errors::*;
 
 /// Describes the OpenGL context profile.
@@ -155,6 +156,15 @@ macro_rules! extensions {
 
                 Ok(extensions)
             }
+
+            /// Returns the list of extensions with nothing supported.
+            pub fn none() -> Extensions {
+                Extensions {
+                    $(
+                        $field: false,
+                    )+
+                }
+            }
         }
     }
 }
@@ -174,17 +184,38 @@ extensions! {
     "GL_EXT_framebuffer_blit" => gl_ext_framebuffer_blit,
     "GL_NV_fbo_color_attachments" => gl_nv_fbo_color_attachments,
     "GL_OES_vertex_array_object" => gl_oes_vertex_array_object,
+    "GL_OES_texture_npot" => gl_oes_texture_npot,
+    "GL_ARB_texture_non_power_of_two" => gl_arb_texture_non_power_of_two,
+    "GL_ARB_draw_instanced" => gl_arb_draw_instanced,
+    "GL_ARB_instanced_arrays" => gl_arb_instanced_arrays,
+    "GL_EXT_framebuffer_multisample" => gl_ext_framebuffer_multisample,
+    "GL_ARB_ES2_compatibility" => gl_arb_es2_compatibility,
+    "GL_EXT_texture_sRGB" => gl_ext_texture_srgb,
+    "GL_EXT_sRGB" => gl_ext_srgb,
+    "GL_ARB_framebuffer_sRGB" => gl_arb_framebuffer_srgb,
+    "GL_EXT_framebuffer_sRGB" => gl_ext_framebuffer_srgb,
+    "GL_EXT_texture_compression_s3tc" => gl_ext_texture_compression_s3tc,
+    "GL_OES_compressed_ETC1_RGB8_texture" => gl_oes_compressed_etc1_rgb8_texture,
+    "GL_ARB_ES3_compatibility" => gl_arb_es3_compatibility,
+    "GL_KHR_texture_compression_astc_ldr" => gl_khr_texture_compression_astc_ldr,
+    "GL_OES_depth_texture" => gl_oes_depth_texture,
+    "GL_OES_packed_depth_stencil" => gl_oes_packed_depth_stencil,
+    "GL_ARB_clip_control" => gl_arb_clip_control,
+    "GL_ARB_sync" => gl_arb_sync,
 }
 
 /// Represents the capabilities of the context.
 ///
 /// Contrary to the state, these values never change.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Capabilities {
     /// Returns a version or release number. Vendor-specific information may follow the version
     /// number.
     pub version: Version,
 
+    /// The raw version string returned by `glGetString(GL_VERSION)`.
+    pub version_string: String,
+
     /// The company responsible for this GL implementation.
     pub vendor: String,
 
@@ -221,6 +252,32 @@ pub struct Capabilities {
 
     /// Maximum number of color attachment bind points.
     pub max_color_attachments: u32,
+
+    /// Maximum width and height of textures.
+    pub max_texture_size: u32,
+
+    /// Maximum number of vertex attributes that could be enabled in a program.
+    pub max_vertex_attributes: u32,
+
+    /// Maximum number of four-element vectors that could be held in uniform variables
+    /// of vertex shader.
+    pub max_vertex_uniform_vectors: u32,
+
+    /// Maximum number of four-element vectors that could be held in uniform variables
+    /// of fragment shader.
+    pub max_fragment_uniform_vectors: u32,
+
+    /// Maximum number of samples of multisampled render buffers. `0` if multisampling
+    /// is not supported.
+    pub max_samples: u32,
+
+    /// Maximum number of layers of 2D texture arrays. `0` if texture arrays are not
+    /// supported.
+    pub max_array_texture_layers: u32,
+
+    /// Number of available buffer bind points for `GL_SHADER_STORAGE_BUFFER`. `0` if
+    /// compute shaders are not supported.
+    pub max_shader_storage_buffer_bindings: u32,
 }
 
 impl Capabilities {
@@ -240,8 +297,12 @@ impl Capabilities {
             (false, false)
         };
 
+        let (max_vertex_uniform_vectors, max_fragment_uniform_vectors) =
+            Capabilities::parse_uniform_vectors(version, &extensions);
+
         Ok(Capabilities {
             version: version,
+            version_string: Capabilities::parse_str(gl::VERSION)?,
             extensions: extensions,
             vendor: Capabilities::parse_str(gl::VENDOR)?,
             renderer: Capabilities::parse_str(gl::RENDERER)?,
@@ -252,12 +313,160 @@ impl Capabilities {
             max_combined_texture_image_units: Capabilities::parse_texture_image_units(),
             max_indexed_uniform_buffer: Capabilities::parse_uniform_buffers(version, &extensions),
             max_color_attachments: Capabilities::parse_color_attachments(version, &extensions),
+            max_texture_size: Capabilities::parse_integer(gl::MAX_TEXTURE_SIZE, 64),
+            max_vertex_attributes: Capabilities::parse_integer(gl::MAX_VERTEX_ATTRIBS, 8),
+            max_vertex_uniform_vectors: max_vertex_uniform_vectors,
+            max_fragment_uniform_vectors: max_fragment_uniform_vectors,
+            max_samples: Capabilities::parse_samples(version, &extensions),
+            max_array_texture_layers: Capabilities::parse_array_texture_layers(version),
+            max_shader_storage_buffer_bindings: Capabilities::parse_storage_buffers(version),
         })
     }
 
+    /// Returns the capabilities of the no-op backend in headless mode, which behaves
+    /// like a minimal OpenGL 3.3 core profile implementation.
+    pub fn headless() -> Capabilities {
+        Capabilities {
+            version: Version::GL(3, 3),
+            version_string: "3.3 (headless)".to_owned(),
+            extensions: Extensions::none(),
+            vendor: "crayon".to_owned(),
+            renderer: "headless".to_owned(),
+            profile: Some(Profile::Core),
+            debug: false,
+            forward_compatible: false,
+            max_viewport_dims: (4096, 4096),
+            max_combined_texture_image_units: 16,
+            max_indexed_uniform_buffer: 36,
+            max_color_attachments: 8,
+            max_texture_size: 4096,
+            max_vertex_attributes: 16,
+            max_vertex_uniform_vectors: 256,
+            max_fragment_uniform_vectors: 224,
+            max_samples: 4,
+            max_array_texture_layers: 256,
+            max_shader_storage_buffer_bindings: 0,
+        }
+    }
+
+    /// Returns true if instanced draw calls are supported.
+    pub fn support_instancing(&self) -> bool {
+        self.version >= Version::GL(3, 3) || self.version >= Version::ES(3, 0)
+            || (self.extensions.gl_arb_draw_instanced && self.extensions.gl_arb_instanced_arrays)
+    }
+
+    /// Returns true if polygon modes other than `Fill` are supported, which is not
+    /// available in OpenGL ES.
+    pub fn support_polygon_mode(&self) -> bool {
+        match self.version {
+            Version::GL(_, _) => true,
+            Version::ES(_, _) => false,
+        }
+    }
+
+    /// Returns true if uniform buffer objects are supported.
+    pub fn support_uniform_buffer(&self) -> bool {
+        self.max_indexed_uniform_buffer > 0
+    }
+
+    /// Returns true if multisampled render buffers are supported.
+    pub fn support_multisample(&self) -> bool {
+        self.max_samples > 0
+    }
+
+    /// Returns true if textures in sRGB space are supported.
+    pub fn support_srgb_texture(&self) -> bool {
+        self.version >= Version::GL(2, 1) || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_ext_texture_srgb || self.extensions.gl_ext_srgb
+    }
+
+    /// Returns true if the sRGB encoding of framebuffer could be toggled with
+    /// `GL_FRAMEBUFFER_SRGB`, which is not available in OpenGL ES.
+    pub fn support_framebuffer_srgb(&self) -> bool {
+        self.version >= Version::GL(3, 0) || self.extensions.gl_arb_framebuffer_srgb
+            || self.extensions.gl_ext_framebuffer_srgb
+    }
+
+    /// Returns true if textures with `format` are supported. Compressed formats are
+    /// usually available on some of the platforms only, e.g. DXT on desktop and ETC on
+    /// mobile devices.
+    pub fn support_texture_format(&self, format: TextureFormat) -> bool {
+        match format {
+            TextureFormat::DXT1 | TextureFormat::DXT3 | TextureFormat::DXT5 => {
+                self.extensions.gl_ext_texture_compression_s3tc
+            }
+            TextureFormat::ETC1 => self.extensions.gl_oes_compressed_etc1_rgb8_texture,
+            TextureFormat::ETC2RGB8 | TextureFormat::ETC2RGBA8 => {
+                self.version >= Version::GL(4, 3) || self.version >= Version::ES(3, 0)
+                    || self.extensions.gl_arb_es3_compatibility
+            }
+            TextureFormat::ASTC4x4 => self.extensions.gl_khr_texture_compression_astc_ldr,
+            _ => true,
+        }
+    }
+
+    /// Returns true if render textures with `format` could be created. Depth textures
+    /// require `GL_OES_depth_texture` on OpenGL ES 2.0, 32-bits depth textures are
+    /// only available on desktop, and floating-point ones require GL 3.0 or ES 3.0.
+    pub fn support_render_texture_format(&self, format: RenderTextureFormat) -> bool {
+        match format {
+            RenderTextureFormat::Depth16 | RenderTextureFormat::Depth24 => {
+                self.support_draw_buffers() || self.extensions.gl_oes_depth_texture
+            }
+            RenderTextureFormat::Depth32 => match self.version {
+                Version::GL(_, _) => true,
+                Version::ES(_, _) => false,
+            },
+            RenderTextureFormat::Depth32F => {
+                self.version >= Version::GL(3, 0) || self.version >= Version::ES(3, 0)
+            }
+            RenderTextureFormat::Depth24Stencil8 => {
+                self.support_draw_buffers()
+                    || (self.extensions.gl_oes_depth_texture
+                        && self.extensions.gl_oes_packed_depth_stencil)
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns true if the fragment outputs could be redirected with `glDrawBuffers`,
+    /// which is not available in OpenGL ES 2.0.
+    pub fn support_draw_buffers(&self) -> bool {
+        self.version >= Version::GL(2, 0) || self.version >= Version::ES(3, 0)
+    }
+
+    /// Returns true if 2D texture arrays are supported.
+    pub fn support_texture_array(&self) -> bool {
+        self.max_array_texture_layers > 0
+    }
+
+    /// Returns true if compute shaders and shader storage buffers are supported.
+    pub fn support_compute_shader(&self) -> bool {
+        self.version >= Version::GL(4, 3) || self.version >= Version::ES(3, 1)
+    }
+
+    /// Returns true if the clip space depth could be mapped into `[0, 1]` with
+    /// `glClipControl`, which is required by surfaces with reverse-Z.
+    pub fn support_clip_control(&self) -> bool {
+        self.version >= Version::GL(4, 5) || self.extensions.gl_arb_clip_control
+    }
+
+    /// Returns true if fences could be inserted with `glFenceSync`.
+    pub fn support_sync(&self) -> bool {
+        self.version >= Version::GL(3, 2) || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_sync
+    }
+
+    /// Returns true if mipmaps of non-power-of-two textures are supported.
+    pub fn support_npot_mipmap(&self) -> bool {
+        self.version >= Version::GL(2, 0) || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_oes_texture_npot
+            || self.extensions.gl_arb_texture_non_power_of_two
+    }
+
     #[inline]
     unsafe fn parse_str(id: GLenum) -> Result<String> {
-        let s = gl::GetString(gl::RENDERER);
+        let s = gl::GetString(id);
         if s.is_null() {
             bail!(format!("glGetString({}) returned a invalid string.", id));
         }
@@ -267,6 +476,60 @@ impl Capabilities {
         })
     }
 
+    #[inline]
+    unsafe fn parse_integer(id: GLenum, fallback: GLint) -> u32 {
+        let mut val = fallback;
+        gl::GetIntegerv(id, &mut val);
+        val.max(0) as u32
+    }
+
+    #[inline]
+    unsafe fn parse_uniform_vectors(version: Version, exts: &Extensions) -> (u32, u32) {
+        if version >= Version::GL(4, 1) || version >= Version::ES(2, 0)
+            || exts.gl_arb_es2_compatibility
+        {
+            (
+                Capabilities::parse_integer(gl::MAX_VERTEX_UNIFORM_VECTORS, 128),
+                Capabilities::parse_integer(gl::MAX_FRAGMENT_UNIFORM_VECTORS, 16),
+            )
+        } else {
+            (
+                Capabilities::parse_integer(gl::MAX_VERTEX_UNIFORM_COMPONENTS, 512) / 4,
+                Capabilities::parse_integer(gl::MAX_FRAGMENT_UNIFORM_COMPONENTS, 64) / 4,
+            )
+        }
+    }
+
+    #[inline]
+    unsafe fn parse_samples(version: Version, exts: &Extensions) -> u32 {
+        // Multisampled render buffer is not supported in OpenGL ES 2.0.
+        if version >= Version::GL(3, 0) || version >= Version::ES(3, 0)
+            || exts.gl_ext_framebuffer_multisample
+        {
+            Capabilities::parse_integer(gl::MAX_SAMPLES, 0)
+        } else {
+            0
+        }
+    }
+
+    #[inline]
+    unsafe fn parse_array_texture_layers(version: Version) -> u32 {
+        if version >= Version::GL(3, 0) || version >= Version::ES(3, 0) {
+            Capabilities::parse_integer(gl::MAX_ARRAY_TEXTURE_LAYERS, 0)
+        } else {
+            0
+        }
+    }
+
+    #[inline]
+    unsafe fn parse_storage_buffers(version: Version) -> u32 {
+        if version >= Version::GL(4, 3) || version >= Version::ES(3, 1) {
+            Capabilities::parse_integer(gl::MAX_SHADER_STORAGE_BUFFER_BINDINGS, 0)
+        } else {
+            0
+        }
+    }
+
     #[inline]
     unsafe fn parse_viewport_dims() -> (u32, u32) {
         let mut val: [gl::types::GLint; 2] = [0, 0];
@@ -301,7 +564,9 @@ impl Capabilities {
 
     #[inline]
     unsafe fn parse_uniform_buffers(version: Version, exts: &Extensions) -> u32 {
-        if version >= Version::GL(3, 1) || exts.gl_arb_uniform_buffer_object {
+        if version >= Version::GL(3, 1) || version >= Version::ES(3, 0)
+            || exts.gl_arb_uniform_buffer_object
+        {
             let mut val = mem::uninitialized();
             gl::GetIntegerv(gl::MAX_UNIFORM_BUFFER_BINDINGS, &mut val);
             val as u32
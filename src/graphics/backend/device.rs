@@ -2,17 +2,21 @@ use std::str;
 use std::cell::{Cell, RefCell};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use std::sync::mpsc::Sender;
 
 use gl;
 use gl::types::*;
 
-use utils::{Color, DataBuffer, Handle, HashValue, Rect};
+use math;
+use utils::{Color, DataBuffer, DataBufferPtr, Handle, HashValue, Rect};
 use graphics::*;
 
 use super::errors::*;
 use super::visitor::*;
-use super::frame::{FrameDrawCall, FrameTask};
+use super::capabilities::Capabilities;
+use super::frame::{FrameDispatch, FrameDrawCall, FrameTask, FrameUniformVariable};
 
 type ResourceID = GLuint;
 type UniformID = GLint;
@@ -29,13 +33,43 @@ struct ShaderObject {
     id: ResourceID,
     render_state: RenderState,
     layout: AttributeLayout,
+    outputs: usize,
     uniform_locations: HashMap<HashValue<str>, UniformID>,
-    uniforms: HashMap<String, UniformVariable>,
+    /// The binding points of uniform blocks.
+    uniform_blocks: HashMap<HashValue<str>, GLuint>,
+    /// The binding points of shader storage blocks.
+    storage_blocks: HashMap<HashValue<str>, GLuint>,
+    uniforms: HashMap<String, FrameUniformVariable>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct UniformBufferObject {
+    id: ResourceID,
+    setup: UniformBufferSetup,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct StorageBufferObject {
+    id: ResourceID,
+    setup: StorageBufferSetup,
 }
 
 #[derive(Debug, Clone)]
 struct SurfaceObject {
     setup: SurfaceSetup,
+    multisample: Cell<Option<MultisampleObject>>,
+}
+
+/// The internal multisampled framebuffer of surface.
+#[derive(Debug, Copy, Clone)]
+struct MultisampleObject {
+    fbo: ResourceID,
+    color: ResourceID,
+    depth: ResourceID,
+    /// The format of depth buffer, which is the same as the render target of surface.
+    depth_format: RenderTextureFormat,
+    dimensions: (u16, u16),
+    samples: u32,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -50,16 +84,58 @@ struct TextureObject {
     setup: GenericTextureSetup,
 }
 
+impl TextureObject {
+    fn target(&self) -> GLenum {
+        match self.setup {
+            GenericTextureSetup::Normal(setup) => match setup.kind {
+                TextureKind::Texture2D => gl::TEXTURE_2D,
+                TextureKind::Cube => gl::TEXTURE_CUBE_MAP,
+                TextureKind::Texture2DArray(_) => gl::TEXTURE_2D_ARRAY,
+            },
+            GenericTextureSetup::Render(_) => gl::TEXTURE_2D,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 struct RenderBufferObject {
     id: ResourceID,
     setup: RenderBufferSetup,
+    samples: u32,
 }
 
 #[derive(Debug, Copy, Clone)]
 struct FrameBufferObject {
     id: ResourceID,
     dimensions: Option<(u16, u16)>,
+    samples: Option<u32>,
+    /// The bit mask of slots with color attachment.
+    colors: u32,
+    /// The format of depth attachment.
+    depth: Option<RenderTextureFormat>,
+}
+
+impl FrameBufferObject {
+    /// Gets the draw buffers that the fragment outputs are written into, slots without
+    /// color attachment in between are filled with `GL_NONE`.
+    fn draw_buffers(&self) -> Vec<GLenum> {
+        let len = 32 - self.colors.leading_zeros();
+        (0..len)
+            .map(|i| {
+                if self.colors & (1 << i) != 0 {
+                    gl::COLOR_ATTACHMENT0 + i
+                } else {
+                    gl::NONE
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct FenceObject {
+    handle: FenceHandle,
+    sync: GLsync,
 }
 
 #[derive(Debug, Copy, Clone, Default)]
@@ -68,6 +144,15 @@ pub struct FrameInfo {
     pub triangles: u32,
 }
 
+/// The window that surfaces present to, and its dimensions of current frame.
+pub(crate) struct WindowTarget<'a> {
+    /// The handle of secondary window, `None` for the primary one.
+    pub handle: Option<WindowHandle>,
+    pub window: &'a Window,
+    pub dimensions: (u32, u32),
+    pub hidpi: f32,
+}
+
 pub(crate) struct Device {
     visitor: OpenGLVisitor,
 
@@ -77,8 +162,26 @@ pub(crate) struct Device {
     textures: DataVec<TextureObject>,
     render_buffers: DataVec<RenderBufferObject>,
     framebuffers: DataVec<FrameBufferObject>,
+    uniform_buffers: DataVec<UniformBufferObject>,
+    storage_buffers: DataVec<StorageBufferObject>,
+    fences: DataVec<FenceObject>,
+
+    /// The shaders that failed to compile or link, the draw calls with them are
+    /// skipped until they are recompiled successfully.
+    failed_shaders: HashSet<ShaderHandle>,
 
     active_shader: Cell<Option<ShaderHandle>>,
+    /// The viewport of bound surface in pixels, and the scale from points to pixels,
+    /// which are used to convert the scissors relative to viewport.
+    active_viewport: Cell<((u16, u16), (u16, u16), f32)>,
+    /// The dimensions in pixels of the default framebuffer of primary window.
+    framebuffer_dimensions: Cell<(u32, u32)>,
+    max_samples: u32,
+    polygon_mode: bool,
+    framebuffer_srgb: bool,
+    clip_control: bool,
+    draw_buffers: bool,
+    instance_buffer: Cell<Option<(ResourceID, usize)>>,
     frame_info: RefCell<FrameInfo>,
 }
 
@@ -86,7 +189,7 @@ unsafe impl Send for Device {}
 unsafe impl Sync for Device {}
 
 impl Device {
-    pub unsafe fn new() -> Self {
+    pub unsafe fn new(capabilities: &Capabilities) -> Self {
         Device {
             visitor: OpenGLVisitor::new(),
             meshes: DataVec::new(),
@@ -95,7 +198,19 @@ impl Device {
             textures: DataVec::new(),
             render_buffers: DataVec::new(),
             framebuffers: DataVec::new(),
+            uniform_buffers: DataVec::new(),
+            storage_buffers: DataVec::new(),
+            fences: DataVec::new(),
+            failed_shaders: HashSet::new(),
             active_shader: Cell::new(None),
+            active_viewport: Cell::new(((0, 0), (0, 0), 1.0)),
+            framebuffer_dimensions: Cell::new((0, 0)),
+            max_samples: capabilities.max_samples,
+            polygon_mode: capabilities.support_polygon_mode(),
+            framebuffer_srgb: capabilities.support_framebuffer_srgb(),
+            clip_control: capabilities.support_clip_control(),
+            draw_buffers: capabilities.support_draw_buffers(),
+            instance_buffer: Cell::new(None),
             frame_info: RefCell::new(FrameInfo::default()),
         }
     }
@@ -108,6 +223,10 @@ impl Device {
         self.visitor.clear(Color::black(), None, None)?;
         self.visitor.set_scissor(Scissor::Disable)?;
 
+        if self.polygon_mode {
+            self.visitor.set_polygon_mode(PolygonMode::Fill)?;
+        }
+
         *self.frame_info.borrow_mut() = FrameInfo::default();
         Ok(())
     }
@@ -116,86 +235,310 @@ impl Device {
         *self.frame_info.borrow()
     }
 
+    /// Executes the frame tasks in order. A failing task is recorded into `failures`
+    /// and skipped, so the rest of tasks still run. The draw calls with shaders that
+    /// failed to compile are skipped silently.
+    ///
+    /// The tasks are grouped by the windows of their surfaces, the context of every
+    /// window in `windows` is made current before drawing, and the first one, which is
+    /// the primary window, is restored at last.
     pub fn flush(
         &mut self,
         tasks: &mut [(SurfaceHandle, u64, FrameTask)],
         buf: &DataBuffer,
-        dimensions: (u32, u32),
-        hidpi: f32,
+        windows: &[WindowTarget],
+        failures: &mut Vec<String>,
     ) -> Result<()> {
-        // Sort frame tasks by user defined priorities. Notes that Slice::sort_by
-        // is stable, which means it does not reorder equal elements, so it will
-        // not change the execution order in one specific surface.
+        if let Some(v) = windows.first() {
+            let w = (v.dimensions.0 as f32 * v.hidpi) as u32;
+            let h = (v.dimensions.1 as f32 * v.hidpi) as u32;
+            self.framebuffer_dimensions.set((w, h));
+        }
+
+        Self::sort_tasks(tasks, |handle| {
+            let surface = self.surfaces.get(handle).unwrap();
+            let window = Self::window_index(surface, windows);
+            (window, surface.setup.order, surface.setup.sequence)
+        });
+
+        unsafe {
+            let mut from = 0;
+            for (i, target) in windows.iter().enumerate() {
+                let len = tasks[from..]
+                    .iter()
+                    .take_while(|v| {
+                        let surface = self.surfaces.get(v.0).unwrap();
+                        Self::window_index(surface, windows) == i
+                    })
+                    .count();
+
+                if i > 0 {
+                    self.switch_window(target)?;
+                }
+
+                let tasks = &mut tasks[from..(from + len)];
+                self.flush_window(tasks, buf, target, failures)?;
+                from += len;
+            }
+
+            // The rest tasks belong to surfaces of removed windows, which are dropped.
+
+            if windows.len() > 1 {
+                self.switch_window(&windows[0])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn flush_window(
+        &mut self,
+        tasks: &mut [(SurfaceHandle, u64, FrameTask)],
+        buf: &DataBuffer,
+        target: &WindowTarget,
+        failures: &mut Vec<String>,
+    ) -> Result<()> {
+        let dimensions = (target.dimensions.0 as u16, target.dimensions.1 as u16);
+        let hidpi = target.hidpi;
+
+        // Submit real OpenGL drawcall in order.
+        let mut surface = None;
+        for v in tasks {
+            if surface != Some(v.0) {
+                if let Some(last) = surface {
+                    self.resolve_surface(last, dimensions, hidpi)?;
+                }
+
+                surface = Some(v.0);
+                self.rebind_surface(v.0, dimensions, hidpi)?;
+            }
+
+            if let Err(err) = self.execute(v.0, v.2, buf) {
+                failures.push(format!("{} failed: {}", v.2.describe(), err));
+            }
+        }
+
+        if let Some(last) = surface {
+            self.resolve_surface(last, dimensions, hidpi)?;
+        }
+
+        self.visitor.flush()
+    }
+
+    /// Sorts frame tasks by the windows and orders of their surfaces, then the orders
+    /// of tasks unless the surface is sequential. Tasks of surfaces with the same
+    /// order are grouped by the handles of surfaces, so they never interleave.
+    ///
+    /// Notes that `sort_by` is stable, tasks with equal keys are executed in the order
+    /// they are submitted, which is deterministic in every frame.
+    fn sort_tasks<F>(tasks: &mut [(SurfaceHandle, u64, FrameTask)], surface: F)
+    where
+        F: Fn(SurfaceHandle) -> (usize, u64, bool),
+    {
         tasks.sort_by(|lhs, rhs| {
-            let lv = self.surfaces.get(lhs.0).unwrap();
-            let rv = self.surfaces.get(rhs.0).unwrap();
-            let mut ord = lv.setup.order.cmp(&rv.setup.order);
+            let (lw, lo, sequence) = surface(lhs.0);
+            let (rw, ro, _) = surface(rhs.0);
+            let mut ord = (lw, lo, lhs.0).cmp(&(rw, ro, rhs.0));
 
-            if ord == Ordering::Equal && !lv.setup.sequence {
+            if ord == Ordering::Equal && !sequence {
                 ord = lhs.1.cmp(&rhs.1);
             }
 
             ord
         });
+    }
 
-        let dimensions = (dimensions.0 as u16, dimensions.1 as u16);
-        unsafe {
-            // Submit real OpenGL drawcall in order.
-            let mut surface = None;
-            for v in tasks {
-                if surface != Some(v.0) {
-                    surface = Some(v.0);
-                    self.rebind_surface(v.0, dimensions, hidpi)?;
-                }
+    /// Gets the index of the window that surface presents to, surfaces of removed
+    /// windows are placed at the end.
+    fn window_index(surface: &SurfaceObject, windows: &[WindowTarget]) -> usize {
+        windows
+            .iter()
+            .position(|v| v.handle == surface.setup.window)
+            .unwrap_or(windows.len())
+    }
 
-                match v.2 {
-                    FrameTask::DrawCall(dc) => self.draw(dc, buf)?,
+    /// Makes the context of window current, and clears its default framebuffer.
+    unsafe fn switch_window(&self, target: &WindowTarget) -> Result<()> {
+        // The states below are not reset along with context, so they are restored
+        // before leaving.
+        if self.polygon_mode {
+            self.visitor.set_polygon_mode(PolygonMode::Fill)?;
+        }
 
-                    FrameTask::UpdateSurface(scissor) => self.visitor.set_scissor(scissor)?,
+        if self.framebuffer_srgb {
+            self.visitor.set_framebuffer_srgb(false)?;
+        }
 
-                    FrameTask::UpdateVertexBuffer(vbo, offset, ptr) => {
-                        let data = buf.as_slice(ptr);
-                        self.update_vertex_buffer(vbo, offset, data)?;
-                    }
+        if self.clip_control {
+            self.visitor.set_clip_control(false)?;
+        }
 
-                    FrameTask::UpdateIndexBuffer(ibo, offset, ptr) => {
-                        let data = buf.as_slice(ptr);
-                        self.update_index_buffer(ibo, offset, data)?;
-                    }
+        target.window.make_current().map_err(|err| err.to_string())?;
+        self.visitor.switch_context(target.handle)?;
+        self.active_shader.set(None);
 
-                    FrameTask::UpdateTexture(texture, rect, ptr) => {
-                        let data = buf.as_slice(ptr);
-                        self.update_texture(texture, rect, data)?;
-                    }
+        if target.handle.is_some() {
+            self.visitor.clear(Color::black(), None, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the per-context objects of the removed window.
+    pub fn remove_window(&self, handle: WindowHandle) {
+        self.visitor.remove_context(handle);
+    }
+
+    unsafe fn execute(
+        &mut self,
+        surface: SurfaceHandle,
+        task: FrameTask,
+        buf: &DataBuffer,
+    ) -> Result<()> {
+        match task {
+            FrameTask::DrawCall(dc) => {
+                if self.failed_shaders.contains(&dc.shader) {
+                    return Ok(());
+                }
+
+                self.check_draw_buffers(surface, dc.shader)?;
+                let scissor = self.bind_drawcall_scissor(&dc)?;
+                let result = self.draw(dc, buf);
+                self.restore_scissor(scissor)?;
+                result
+            }
+
+            FrameTask::InstancedDrawCall(dc, layout, ptr, count) => {
+                if self.failed_shaders.contains(&dc.shader) {
+                    return Ok(());
+                }
+
+                let data = buf.as_slice(ptr);
+                self.check_draw_buffers(surface, dc.shader)?;
+                let scissor = self.bind_drawcall_scissor(&dc)?;
+                let result = self.draw_instanced(dc, buf, layout, data, count);
+                self.restore_scissor(scissor)?;
+                result
+            }
+
+            FrameTask::Dispatch(dc) => {
+                if self.failed_shaders.contains(&dc.shader) {
+                    return Ok(());
                 }
+
+                self.dispatch(dc, buf)
+            }
+
+            FrameTask::UpdateSurface(scissor) => {
+                self.visitor
+                    .set_scissor(Self::scissor_in_pixels(scissor, self.active_viewport.get()))
+            }
+
+            FrameTask::UpdateVertexBuffer(vbo, offset, ptr) => {
+                let data = buf.as_slice(ptr);
+                self.update_vertex_buffer(vbo, offset, data)
+            }
+
+            FrameTask::UpdateIndexBuffer(ibo, offset, ptr) => {
+                let data = buf.as_slice(ptr);
+                self.update_index_buffer(ibo, offset, data)
+            }
+
+            FrameTask::UpdateTexture(texture, layer, rect, ptr) => {
+                let data = buf.as_slice(ptr);
+                self.update_texture(texture, layer, rect, data)
+            }
+        }
+    }
+
+    /// Makes sure that the fragment outputs of shader match the color attachments of
+    /// the render target of surface. Shaders with a single output could be drawn into
+    /// any render target.
+    fn check_draw_buffers(&self, handle: SurfaceHandle, shader: ShaderHandle) -> Result<()> {
+        let shader = self.shaders.get(shader).ok_or(ErrorKind::InvalidHandle)?;
+        if shader.outputs <= 1 {
+            return Ok(());
+        }
+
+        let surface = self.surfaces.get(handle).ok_or(ErrorKind::InvalidHandle)?;
+        if self.clamp_samples(surface.setup.samples) > 0 {
+            bail!(format!(
+                "Multiple fragment outputs are not supported by multisampled surface {:?}.",
+                handle
+            ));
+        }
+
+        let colors = match surface.setup.framebuffer {
+            Some(fbo) => {
+                let fbo = self.framebuffers.get(fbo).ok_or(ErrorKind::InvalidHandle)?;
+                fbo.draw_buffers().len()
             }
+            None => 1,
+        };
 
-            self.visitor.flush()?;
+        if shader.outputs != colors {
+            bail!(format!(
+                "Mismatched fragment outputs ({}) and color attachments ({}) of surface {:?}.",
+                shader.outputs, colors, handle
+            ));
         }
 
         Ok(())
     }
 
-    unsafe fn draw(&self, dc: FrameDrawCall, buf: &DataBuffer) -> Result<()> {
-        // Bind program and associated uniforms and textures.
-        let shader = self.bind_shader(dc.shader)?;
-
-        let texture_idx = 0;
-        for &(field, ptr) in buf.as_slice(dc.uniforms) {
-            let variable = buf.as_ref(ptr);
-            let location = shader.uniform_locations[&field];
+    /// Applies the scissor of draw call if it has one, and returns the scissor of
+    /// surface that should be restored after drawing.
+    unsafe fn bind_drawcall_scissor(&self, dc: &FrameDrawCall) -> Result<Option<Scissor>> {
+        if let Some(scissor) = dc.scissor {
+            let surface = self.visitor.scissor();
+            let scissor = Self::scissor_in_pixels(scissor, self.active_viewport.get());
+            self.visitor.set_scissor(scissor)?;
+            Ok(Some(surface))
+        } else {
+            Ok(None)
+        }
+    }
 
-            if let &UniformVariable::Texture(handle) = variable {
-                if let Some(texture) = self.textures.get(handle) {
-                    let v = UniformVariable::I32(texture_idx);
-                    self.visitor.bind_uniform(location, &v)?;
-                    self.visitor.bind_texture(texture_idx as u32, texture.id)?;
-                }
-            } else {
-                self.visitor.bind_uniform(location, &variable)?;
+    /// Converts the scissor relative to the viewport of bound surface into pixels.
+    /// The edges of box are rounded to the nearest pixels, so adjacent boxes never
+    /// leave gaps between them.
+    fn scissor_in_pixels(
+        scissor: Scissor,
+        viewport: ((u16, u16), (u16, u16), f32),
+    ) -> Scissor {
+        let (position, size, scale) = viewport;
+        let (min, max) = match scissor {
+            Scissor::Points(p, s) => (
+                (p.0 * scale, p.1 * scale),
+                ((p.0 + s.0) * scale, (p.1 + s.1) * scale),
+            ),
+            Scissor::Normalized(p, s) => {
+                let (w, h) = (size.0 as f32, size.1 as f32);
+                ((p.0 * w, p.1 * h), ((p.0 + s.0) * w, (p.1 + s.1) * h))
             }
+            _ => return scissor,
+        };
+
+        let min = (min.0.round().max(0.0), min.1.round().max(0.0));
+        let max = (max.0.round().max(min.0), max.1.round().max(min.1));
+
+        Scissor::Enable(
+            (position.0 + min.0 as u16, position.1 + min.1 as u16),
+            ((max.0 - min.0) as u16, (max.1 - min.1) as u16),
+        )
+    }
+
+    unsafe fn restore_scissor(&self, scissor: Option<Scissor>) -> Result<()> {
+        if let Some(scissor) = scissor {
+            self.visitor.set_scissor(scissor)?;
         }
 
+        Ok(())
+    }
+
+    unsafe fn draw(&self, dc: FrameDrawCall, buf: &DataBuffer) -> Result<()> {
+        let shader = self.bind_drawcall(&dc, buf)?;
+
         // Bind vertex buffer and vertex array object.
         let mesh = self.meshes.get(dc.mesh).ok_or(ErrorKind::InvalidHandle)?;
         self.visitor.bind_buffer(gl::ARRAY_BUFFER, mesh.vbo)?;
@@ -206,7 +549,193 @@ impl Device {
         self.visitor
             .bind_buffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo)?;
 
-        let (from, len) = match dc.index {
+        let (from, len) = Self::index_range(mesh, dc.index)?;
+
+        gl::DrawElements(
+            mesh.setup.primitive.into(),
+            len,
+            mesh.setup.index_format.into(),
+            from as *const u32 as *const ::std::os::raw::c_void,
+        );
+
+        {
+            let mut info = self.frame_info.borrow_mut();
+            info.drawcall += 1;
+            info.triangles += mesh.setup.primitive.assemble_triangles(len as u32);
+        }
+
+        check()
+    }
+
+    unsafe fn draw_instanced(
+        &self,
+        dc: FrameDrawCall,
+        buf: &DataBuffer,
+        layout: AttributeLayout,
+        data: &[u8],
+        count: usize,
+    ) -> Result<()> {
+        let mesh = self.meshes.get(dc.mesh).ok_or(ErrorKind::InvalidHandle)?;
+        let shader = self.bind_drawcall(&dc, buf)?;
+
+        // Bind vertex buffer, instance buffer and vertex array object.
+        let ivbo = self.update_instance_buffer(data)?;
+        self.visitor.bind_instanced_attribute_layout(
+            &shader.layout,
+            &mesh.setup.layout,
+            mesh.vbo,
+            &layout,
+            ivbo,
+        )?;
+
+        // Bind index buffer object if available.
+        self.visitor
+            .bind_buffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo)?;
+
+        let (from, len) = Self::index_range(mesh, dc.index)?;
+
+        gl::DrawElementsInstanced(
+            mesh.setup.primitive.into(),
+            len,
+            mesh.setup.index_format.into(),
+            from as *const u32 as *const ::std::os::raw::c_void,
+            count as GLsizei,
+        );
+
+        {
+            let mut info = self.frame_info.borrow_mut();
+            info.drawcall += 1;
+            info.triangles += mesh.setup.primitive.assemble_triangles(len as u32) * count as u32;
+        }
+
+        check()
+    }
+
+    /// Binds program and associated uniforms and textures of draw call.
+    unsafe fn bind_drawcall(&self, dc: &FrameDrawCall, buf: &DataBuffer) -> Result<&ShaderObject> {
+        let shader = self.bind_shader(dc.shader)?;
+
+        // We reset the active shader here to make sure the default state will be
+        // restored for the following draw calls.
+        if let Some(state) = Self::drawcall_render_state(&shader.render_state, dc.render_state) {
+            self.bind_render_state(&state)?;
+            self.active_shader.set(None);
+        }
+
+        self.bind_uniforms(shader, buf.as_slice(dc.uniforms), buf)?;
+
+        for &(field, handle) in buf.as_slice(dc.uniform_buffers) {
+            let binding = shader.uniform_blocks[&field];
+            let ubo = self.uniform_buffers
+                .get(handle)
+                .ok_or(ErrorKind::InvalidHandle)?;
+            self.visitor.bind_uniform_buffer(binding, ubo.id)?;
+        }
+
+        self.bind_storage_buffers(shader, buf.as_slice(dc.storage_buffers))?;
+        Ok(shader)
+    }
+
+    /// Launches the work groups of compute shader.
+    unsafe fn dispatch(&self, dc: FrameDispatch, buf: &DataBuffer) -> Result<()> {
+        let shader = self.shaders.get(dc.shader).ok_or(ErrorKind::InvalidHandle)?;
+
+        // Compute programs have no render state, we reset the active shader here to
+        // make sure the following draw calls rebind their programs and states.
+        self.visitor.bind_program(shader.id)?;
+        self.active_shader.set(None);
+
+        self.bind_uniforms(shader, buf.as_slice(dc.uniforms), buf)?;
+        self.bind_storage_buffers(shader, buf.as_slice(dc.storage_buffers))?;
+        self.visitor.dispatch_compute(dc.groups)
+    }
+
+    unsafe fn bind_uniforms(
+        &self,
+        shader: &ShaderObject,
+        uniforms: &[(HashValue<str>, DataBufferPtr<FrameUniformVariable>)],
+        buf: &DataBuffer,
+    ) -> Result<()> {
+        let mut texture_idx = 0;
+        for &(field, ptr) in uniforms {
+            let variable = buf.as_ref(ptr);
+            let location = shader.uniform_locations[&field];
+
+            match *variable {
+                FrameUniformVariable::Texture(handle)
+                | FrameUniformVariable::TextureCube(handle)
+                | FrameUniformVariable::Texture2DArray(handle) => {
+                    // Every sampler takes its own texture unit, since samplers of
+                    // different types can not share the same one.
+                    if let Some(texture) = self.textures.get(handle) {
+                        let v = FrameUniformVariable::I32(texture_idx);
+                        self.visitor.bind_uniform(location, &v)?;
+                        self.visitor
+                            .bind_texture(texture_idx as u32, texture.target(), texture.id)?;
+                        texture_idx += 1;
+                    }
+                }
+                FrameUniformVariable::Vector3fArray(ptr) => {
+                    let v = buf.as_slice(ptr);
+                    self.visitor.bind_uniform_vector3f_array(location, v)?;
+                }
+                FrameUniformVariable::Matrix4fArray(ptr) => {
+                    let v = buf.as_slice(ptr);
+                    self.visitor.bind_uniform_matrix4f_array(location, v)?;
+                }
+                _ => self.visitor.bind_uniform(location, &variable)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn bind_storage_buffers(
+        &self,
+        shader: &ShaderObject,
+        buffers: &[(HashValue<str>, StorageBufferHandle)],
+    ) -> Result<()> {
+        for &(field, handle) in buffers {
+            let binding = shader.storage_blocks[&field];
+            let ssbo = self.storage_buffers
+                .get(handle)
+                .ok_or(ErrorKind::InvalidHandle)?;
+            self.visitor.bind_storage_buffer(binding, ssbo.id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Uploads per-instance attributes into the streaming instance buffer, which
+    /// will be re-allocated if its capacity is not enough.
+    unsafe fn update_instance_buffer(&self, data: &[u8]) -> Result<ResourceID> {
+        if let Some((id, capacity)) = self.instance_buffer.get() {
+            if capacity >= data.len() {
+                self.visitor
+                    .update_buffer(id, OpenGLBuffer::Vertex, 0, data)?;
+                return Ok(id);
+            }
+
+            self.instance_buffer.set(None);
+            self.visitor.delete_buffer(id)?;
+        }
+
+        let capacity = data.len().next_power_of_two();
+        let id = self.visitor.create_buffer(
+            OpenGLBuffer::Vertex,
+            BufferHint::Stream,
+            capacity as u32,
+            None,
+        )?;
+
+        self.visitor
+            .update_buffer(id, OpenGLBuffer::Vertex, 0, data)?;
+        self.instance_buffer.set(Some((id, capacity)));
+        Ok(id)
+    }
+
+    fn index_range(mesh: &MeshObject, index: MeshIndex) -> Result<(u32, GLsizei)> {
+        let range = match index {
             MeshIndex::Ptr(from, len) => {
                 if (from + len) > mesh.setup.num_idxes {
                     bail!("Invalid index of sub-mesh!");
@@ -238,47 +767,156 @@ impl Device {
             MeshIndex::All => (0, mesh.setup.num_idxes as i32),
         };
 
-        gl::DrawElements(
-            mesh.setup.primitive.into(),
-            len,
-            mesh.setup.index_format.into(),
-            from as *const u32 as *const ::std::os::raw::c_void,
-        );
+        Ok(range)
+    }
 
-        {
-            let mut info = self.frame_info.borrow_mut();
-            info.drawcall += 1;
-            info.triangles += mesh.setup.primitive.assemble_triangles(len as u32);
+    unsafe fn rebind_surface(
+        &self,
+        handle: SurfaceHandle,
+        dimensions: (u16, u16),
+        hidpi: f32,
+    ) -> Result<()> {
+        let surface = self.surfaces.get(handle).ok_or(ErrorKind::InvalidHandle)?;
+        let (target, dimensions) = self.surface_target(surface, dimensions, hidpi)?;
+
+        // Bind frame buffer.
+        if let Some(msaa) = self.multisample_target(surface, dimensions)? {
+            self.visitor.bind_framebuffer(msaa.fbo, true)?;
+        } else {
+            self.visitor.bind_framebuffer(target, target != 0)?;
         }
 
-        check()
+        let (position, dimensions) = Self::surface_viewport(surface, dimensions);
+        let scale = self.surface_scale(surface, hidpi);
+        self.active_viewport.set((position, dimensions, scale));
+
+        // Binds the viewport and scissor box.
+        self.visitor.set_viewport(position, dimensions)?;
+        self.visitor.set_scissor(Scissor::Disable)?;
+
+        if self.framebuffer_srgb {
+            self.visitor.set_framebuffer_srgb(surface.setup.srgb)?;
+        }
+
+        // Surfaces with reverse-Z are rejected if clip control is not supported.
+        if self.clip_control {
+            self.visitor.set_clip_control(surface.setup.reversed_z)?;
+        }
+
+        // Sets depth write enable to make sure that we can clear depth buffer properly.
+        self.visitor.set_depth_write(true, None)?;
+        // Disables stencil test to make sure that we can clear stencil buffer properly.
+        self.visitor.set_stencil_test(None)?;
+
+        // The far plane is placed at depth 0.0 with reverse-Z.
+        let clear_depth = if surface.setup.reversed_z {
+            surface.setup.clear_depth.map(|v| 1.0 - v)
+        } else {
+            surface.setup.clear_depth
+        };
+
+        // The contents of render target are not loaded into the multisampled framebuffer,
+        // so its stencil buffer is always cleared. The color and depth buffers are
+        // required to be cleared when the surface is created.
+        let clear_stencil = if surface.multisample.get().is_some() {
+            surface.setup.clear_stencil.or(Some(0))
+        } else {
+            surface.setup.clear_stencil
+        };
+
+        // Clears frame buffer.
+        self.visitor
+            .clear(surface.setup.clear_color, clear_depth, clear_stencil)?;
+
+        // Makes sure that the render state of shader will be restored.
+        self.active_shader.set(None);
+        Ok(())
     }
 
-    unsafe fn rebind_surface(
+    /// Resolves the multisampled framebuffer of surface into its render target. The
+    /// depth and stencil buffers are resolved too if the render target has them, so
+    /// the following surfaces could draw over them.
+    unsafe fn resolve_surface(
         &self,
         handle: SurfaceHandle,
         dimensions: (u16, u16),
         hidpi: f32,
     ) -> Result<()> {
         let surface = self.surfaces.get(handle).ok_or(ErrorKind::InvalidHandle)?;
+        if let Some(msaa) = surface.multisample.get() {
+            let (target, dimensions) = self.surface_target(surface, dimensions, hidpi)?;
+            let (position, dimensions) = Self::surface_viewport(surface, dimensions);
+
+            let mut mask = gl::COLOR_BUFFER_BIT;
+            if self.surface_depth_format(surface).is_some() {
+                mask |= gl::DEPTH_BUFFER_BIT;
+                if msaa.depth_format.is_stencil() {
+                    mask |= gl::STENCIL_BUFFER_BIT;
+                }
+            }
+
+            // Scissor test affects the blitting too.
+            self.visitor.set_scissor(Scissor::Disable)?;
+            self.visitor
+                .resolve_framebuffer(msaa.fbo, target, position, dimensions, mask)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the render target and its dimensions of surface.
+    fn surface_target(
+        &self,
+        surface: &SurfaceObject,
+        dimensions: (u16, u16),
+        hidpi: f32,
+    ) -> Result<(ResourceID, (u16, u16))> {
         let dimensions = (
             (dimensions.0 as f32 * hidpi) as u16,
             (dimensions.1 as f32 * hidpi) as u16,
         );
 
-        // Bind frame buffer.
-        let dimensions = if let Some(fbo) = surface.setup.framebuffer {
+        if let Some(fbo) = surface.setup.framebuffer {
             if let Some(fbo) = self.framebuffers.get(fbo) {
-                self.visitor.bind_framebuffer(fbo.id, true)?;
-                fbo.dimensions.unwrap_or(dimensions)
+                Ok((fbo.id, fbo.dimensions.unwrap_or(dimensions)))
             } else {
                 bail!(ErrorKind::InvalidHandle);
             }
         } else {
-            self.visitor.bind_framebuffer(0, false)?;
-            dimensions
-        };
+            Ok((0, dimensions))
+        }
+    }
+
+    /// Gets the format of the depth buffer of surface's render target. The default
+    /// framebuffer of window is created with 24-bits depth and 8-bits stencil.
+    fn surface_depth_format(&self, surface: &SurfaceObject) -> Option<RenderTextureFormat> {
+        match surface.setup.framebuffer {
+            Some(fbo) => self.framebuffers.get(fbo).and_then(|v| v.depth),
+            None => Some(RenderTextureFormat::Depth24Stencil8),
+        }
+    }
 
+    /// Gets the scale from points to pixels of surface, which is the hidpi factor if
+    /// its render target follows the dimensions of window.
+    fn surface_scale(&self, surface: &SurfaceObject, hidpi: f32) -> f32 {
+        let fixed = surface
+            .setup
+            .framebuffer
+            .and_then(|v| self.framebuffers.get(v))
+            .map(|v| v.dimensions.is_some())
+            .unwrap_or(false);
+
+        if fixed {
+            1.0
+        } else {
+            hidpi
+        }
+    }
+
+    fn surface_viewport(
+        surface: &SurfaceObject,
+        dimensions: (u16, u16),
+    ) -> ((u16, u16), (u16, u16)) {
         let vp = surface.setup.viewport;
         let position = (
             ((vp.0).0 * dimensions.0 as f32) as u16,
@@ -289,20 +927,75 @@ impl Device {
             ((vp.1).1 * dimensions.1 as f32) as u16,
         );
 
-        // Binds the viewport and scissor box.
-        self.visitor.set_viewport(position, dimensions)?;
-        self.visitor.set_scissor(Scissor::Disable)?;
-        // Sets depth write enable to make sure that we can clear depth buffer properly.
-        self.visitor.set_depth_write(true, None)?;
+        (position, dimensions)
+    }
 
-        // Clears frame buffer.
-        self.visitor.clear(
-            surface.setup.clear_color,
-            surface.setup.clear_depth,
-            surface.setup.clear_stencil,
-        )?;
+    /// Gets the multisampled framebuffer of surface, which will be (re)created if
+    /// the dimensions or the depth format of render target changes.
+    unsafe fn multisample_target(
+        &self,
+        surface: &SurfaceObject,
+        dimensions: (u16, u16),
+    ) -> Result<Option<MultisampleObject>> {
+        let samples = self.clamp_samples(surface.setup.samples);
+        if samples == 0 {
+            return Ok(None);
+        }
 
-        Ok(())
+        // The depth buffer shares the format of render target, so it could be resolved.
+        let depth_format = self.surface_depth_format(surface)
+            .unwrap_or(RenderTextureFormat::Depth24Stencil8);
+
+        if let Some(msaa) = surface.multisample.get() {
+            if msaa.dimensions == dimensions && msaa.samples == samples
+                && msaa.depth_format == depth_format
+            {
+                return Ok(Some(msaa));
+            }
+
+            surface.multisample.set(None);
+            self.delete_multisample_object(msaa)?;
+        }
+
+        let (w, h) = (dimensions.0 as u32, dimensions.1 as u32);
+        let (internal_format, _, _) = depth_format.into();
+        let color = self.visitor.create_render_buffer(gl::RGBA8, w, h, samples)?;
+        let depth = self.visitor.create_render_buffer(internal_format, w, h, samples)?;
+
+        let fbo = self.visitor.create_framebuffer()?;
+        self.visitor
+            .bind_framebuffer_with_renderbuffer(gl::COLOR_ATTACHMENT0, color)?;
+        let location = Self::framebuffer_attachment_point(depth_format, 0);
+        self.visitor.bind_framebuffer_with_renderbuffer(location, depth)?;
+
+        let msaa = MultisampleObject {
+            fbo: fbo,
+            color: color,
+            depth: depth,
+            depth_format: depth_format,
+            dimensions: dimensions,
+            samples: samples,
+        };
+
+        surface.multisample.set(Some(msaa));
+        Ok(Some(msaa))
+    }
+
+    unsafe fn delete_multisample_object(&self, msaa: MultisampleObject) -> Result<()> {
+        self.visitor.delete_framebuffer(msaa.fbo)?;
+        self.visitor.delete_render_buffer(msaa.color)?;
+        self.visitor.delete_render_buffer(msaa.depth)
+    }
+
+    /// Clamps the number of samples to the maximum supported samples, returns zero if
+    /// multisampling is disabled.
+    fn clamp_samples(&self, samples: u32) -> u32 {
+        let samples = samples.min(self.max_samples);
+        if samples > 1 {
+            samples
+        } else {
+            0
+        }
     }
 
     unsafe fn bind_shader(&self, handle: ShaderHandle) -> Result<&ShaderObject> {
@@ -316,16 +1009,7 @@ impl Device {
 
         self.visitor.bind_program(shader.id)?;
 
-        let state = &shader.render_state;
-        self.visitor.set_cull_face(state.cull_face)?;
-        self.visitor.set_front_face_order(state.front_face_order)?;
-        self.visitor.set_depth_test(state.depth_test)?;
-        self.visitor
-            .set_depth_write(state.depth_write, state.depth_write_offset)?;
-        self.visitor.set_color_blend(state.color_blend)?;
-
-        let c = &state.color_write;
-        self.visitor.set_color_write(c.0, c.1, c.2, c.3)?;
+        self.bind_render_state(&shader.render_state)?;
 
         for (name, variable) in &shader.uniforms {
             let location = self.visitor.get_uniform_location(shader.id, &name)?;
@@ -337,6 +1021,41 @@ impl Device {
         self.active_shader.set(Some(handle));
         Ok(&shader)
     }
+
+    /// Resolves the render state of a draw call with its override. Returns `None` if
+    /// it's the same as the state of shader, which has been bound with the program.
+    fn drawcall_render_state(
+        state: &RenderState,
+        v: Option<RenderStateOverride>,
+    ) -> Option<RenderState> {
+        let resolved = v?.apply(state);
+        if resolved != *state {
+            Some(resolved)
+        } else {
+            None
+        }
+    }
+
+    unsafe fn bind_render_state(&self, state: &RenderState) -> Result<()> {
+        self.visitor.set_cull_face(state.cull_face)?;
+        self.visitor.set_front_face_order(state.front_face_order)?;
+
+        // Polygon mode is applied with the render state of every draw, so it will be
+        // reset to `Fill` once a draw call with default state is submitted.
+        if self.polygon_mode {
+            self.visitor.set_polygon_mode(state.polygon_mode)?;
+        }
+
+        self.visitor.set_depth_test(state.depth_test)?;
+        self.visitor
+            .set_depth_write(state.depth_write, state.depth_write_offset)?;
+        self.visitor.set_stencil_test(state.stencil_test)?;
+        self.visitor
+            .set_color_blend(state.color_blend, state.alpha_blend)?;
+
+        let c = &state.color_write;
+        self.visitor.set_color_write(c.0, c.1, c.2, c.3)
+    }
 }
 
 impl Device {
@@ -360,7 +1079,7 @@ impl Device {
 
         let ibo = self.visitor.create_buffer(
             OpenGLBuffer::Index,
-            setup.hint,
+            setup.index_buffer_hint(),
             setup.index_buffer_len() as u32,
             idxes,
         )?;
@@ -382,48 +1101,172 @@ impl Device {
         data: &[u8],
     ) -> Result<()> {
         if let Some(mesh) = self.meshes.get(handle) {
-            if mesh.setup.hint == BufferHint::Immutable {
+            if mesh.setup.hint == BufferHint::Static {
                 bail!(ErrorKind::InvalidUpdateStaticResource);
             }
 
-            if data.len() + offset > mesh.setup.vertex_buffer_len() {
+            if data.len() + offset > mesh.setup.vertex_buffer_len() {
+                bail!(ErrorKind::OutOfBounds);
+            }
+
+            self.visitor
+                .update_buffer(mesh.vbo, OpenGLBuffer::Vertex, offset as u32, data)
+        } else {
+            bail!(ErrorKind::InvalidHandle);
+        }
+    }
+
+    pub unsafe fn update_index_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        if let Some(mesh) = self.meshes.get(handle) {
+            if mesh.setup.index_buffer_hint() == BufferHint::Static {
+                bail!(ErrorKind::InvalidUpdateStaticResource);
+            }
+
+            if data.len() + offset > mesh.setup.index_buffer_len() {
+                bail!(ErrorKind::OutOfBounds);
+            }
+
+            self.visitor
+                .update_buffer(mesh.ibo, OpenGLBuffer::Index, offset as u32, data)
+        } else {
+            bail!(ErrorKind::InvalidHandle);
+        }
+    }
+
+    pub unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
+        if let Some(mesh) = self.meshes.remove(handle) {
+            self.visitor.delete_buffer(mesh.vbo)?;
+            self.visitor.delete_buffer(mesh.ibo)?;
+            Ok(())
+        } else {
+            bail!(ErrorKind::InvalidHandle);
+        }
+    }
+
+    pub unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        setup: UniformBufferSetup,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        if self.uniform_buffers.get(handle).is_some() {
+            bail!(ErrorKind::DuplicatedHandle)
+        }
+
+        if let Some(v) = data {
+            if v.len() != setup.size {
+                bail!(ErrorKind::OutOfBounds);
+            }
+        }
+
+        let id = self.visitor.create_buffer(
+            OpenGLBuffer::Uniform,
+            setup.hint,
+            setup.size as u32,
+            data,
+        )?;
+
+        self.uniform_buffers
+            .set(handle, UniformBufferObject { id: id, setup: setup });
+        check()
+    }
+
+    pub unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        if let Some(ubo) = self.uniform_buffers.get(handle) {
+            if ubo.setup.hint == BufferHint::Static {
+                bail!(ErrorKind::InvalidUpdateStaticResource);
+            }
+
+            if data.len() + offset > ubo.setup.size {
+                bail!(ErrorKind::OutOfBounds);
+            }
+
+            if data.is_empty() {
+                return Ok(());
+            }
+
+            self.visitor
+                .update_buffer(ubo.id, OpenGLBuffer::Uniform, offset as u32, data)
+        } else {
+            bail!(ErrorKind::InvalidHandle);
+        }
+    }
+
+    pub unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()> {
+        if let Some(ubo) = self.uniform_buffers.remove(handle) {
+            self.visitor.delete_buffer(ubo.id)
+        } else {
+            bail!(ErrorKind::InvalidHandle);
+        }
+    }
+
+    pub unsafe fn create_storage_buffer(
+        &mut self,
+        handle: StorageBufferHandle,
+        setup: StorageBufferSetup,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        if self.storage_buffers.get(handle).is_some() {
+            bail!(ErrorKind::DuplicatedHandle)
+        }
+
+        if let Some(v) = data {
+            if v.len() != setup.size {
                 bail!(ErrorKind::OutOfBounds);
             }
-
-            self.visitor
-                .update_buffer(mesh.vbo, OpenGLBuffer::Vertex, offset as u32, data)
-        } else {
-            bail!(ErrorKind::InvalidHandle);
         }
+
+        let id = self.visitor.create_buffer(
+            OpenGLBuffer::Storage,
+            setup.hint,
+            setup.size as u32,
+            data,
+        )?;
+
+        self.storage_buffers
+            .set(handle, StorageBufferObject { id: id, setup: setup });
+        check()
     }
 
-    pub unsafe fn update_index_buffer(
+    pub unsafe fn update_storage_buffer(
         &mut self,
-        handle: MeshHandle,
+        handle: StorageBufferHandle,
         offset: usize,
         data: &[u8],
     ) -> Result<()> {
-        if let Some(mesh) = self.meshes.get(handle) {
-            if mesh.setup.hint == BufferHint::Immutable {
+        if let Some(ssbo) = self.storage_buffers.get(handle) {
+            if ssbo.setup.hint == BufferHint::Static {
                 bail!(ErrorKind::InvalidUpdateStaticResource);
             }
 
-            if data.len() + offset > mesh.setup.index_buffer_len() {
+            if data.len() + offset > ssbo.setup.size {
                 bail!(ErrorKind::OutOfBounds);
             }
 
+            if data.is_empty() {
+                return Ok(());
+            }
+
             self.visitor
-                .update_buffer(mesh.ibo, OpenGLBuffer::Index, offset as u32, data)
+                .update_buffer(ssbo.id, OpenGLBuffer::Storage, offset as u32, data)
         } else {
             bail!(ErrorKind::InvalidHandle);
         }
     }
 
-    pub unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
-        if let Some(mesh) = self.meshes.remove(handle) {
-            self.visitor.delete_buffer(mesh.vbo)?;
-            self.visitor.delete_buffer(mesh.ibo)?;
-            Ok(())
+    pub unsafe fn delete_storage_buffer(&mut self, handle: StorageBufferHandle) -> Result<()> {
+        if let Some(ssbo) = self.storage_buffers.remove(handle) {
+            self.visitor.delete_buffer(ssbo.id)
         } else {
             bail!(ErrorKind::InvalidHandle);
         }
@@ -435,10 +1278,12 @@ impl Device {
         setup: RenderBufferSetup,
     ) -> Result<()> {
         let (internal_format, _, _) = setup.format.into();
+        let samples = self.clamp_samples(setup.samples);
         let id = self.visitor.create_render_buffer(
             internal_format,
             setup.dimensions.0,
             setup.dimensions.1,
+            samples,
         )?;
 
         self.render_buffers.set(
@@ -446,6 +1291,7 @@ impl Device {
             RenderBufferObject {
                 id: id,
                 setup: setup,
+                samples: samples,
             },
         );
         Ok(())
@@ -459,7 +1305,11 @@ impl Device {
         }
     }
 
-    pub unsafe fn create_framebuffer(&mut self, handle: FrameBufferHandle) -> Result<()> {
+    pub unsafe fn create_framebuffer(
+        &mut self,
+        handle: FrameBufferHandle,
+        setup: FrameBufferSetup,
+    ) -> Result<()> {
         if self.framebuffers.get(handle).is_some() {
             bail!(ErrorKind::DuplicatedHandle)
         }
@@ -467,9 +1317,47 @@ impl Device {
         let fbo = FrameBufferObject {
             id: self.visitor.create_framebuffer()?,
             dimensions: None,
+            samples: None,
+            colors: 0,
+            depth: None,
         };
 
         self.framebuffers.set(handle, fbo);
+
+        for (i, attachment) in setup.attachments().iter().enumerate() {
+            if let Some(v) = *attachment {
+                let i = i as u32;
+                match v {
+                    FrameBufferAttachment::RenderBuffer(rb) => {
+                        self.update_framebuffer_with_renderbuffer(handle, rb, i)?;
+                    }
+                    FrameBufferAttachment::Texture(texture) => {
+                        self.update_framebuffer_with_texture(handle, texture, i)?;
+                    }
+                };
+            }
+        }
+
+        // Fans out the fragment outputs into color attachments. A single attachment
+        // at the first slot is the default of OpenGL, which saves a call on platforms
+        // without `glDrawBuffers`.
+        let fbo = *self.framebuffers
+            .get(handle)
+            .ok_or(ErrorKind::InvalidHandle)?;
+
+        if fbo.colors > 1 {
+            self.visitor.bind_framebuffer(fbo.id, false)?;
+            self.visitor.set_draw_buffers(&fbo.draw_buffers())?;
+        }
+
+        // Depth-only framebuffers, e.g. the ones of shadow maps, are incomplete on
+        // desktop OpenGL unless both the draw and read buffers are disabled.
+        if fbo.colors == 0 && self.draw_buffers {
+            self.visitor.bind_framebuffer(fbo.id, false)?;
+            self.visitor.set_draw_buffers(&[gl::NONE])?;
+            self.visitor.set_read_buffer(gl::NONE)?;
+        }
+
         Ok(())
     }
 
@@ -499,21 +1387,17 @@ impl Device {
                 fbo.dimensions = Some(attached_dimensions);
             }
 
-            match setup.format {
-                RenderTextureFormat::RGB8
-                | RenderTextureFormat::RGBA4
-                | RenderTextureFormat::RGBA8 => {
-                    let location = gl::COLOR_ATTACHMENT0 + slot;
-                    self.visitor
-                        .bind_framebuffer_with_texture(location, texture.id)
-                }
-                RenderTextureFormat::Depth16
-                | RenderTextureFormat::Depth24
-                | RenderTextureFormat::Depth32 => self.visitor
-                    .bind_framebuffer_with_texture(gl::DEPTH_ATTACHMENT, texture.id),
-                RenderTextureFormat::Depth24Stencil8 => self.visitor
-                    .bind_framebuffer_with_texture(gl::DEPTH_STENCIL_ATTACHMENT, texture.id),
+            Self::check_framebuffer_samples(handle, fbo, 0)?;
+
+            let location = Self::framebuffer_attachment_point(setup.format, slot);
+            if setup.format.is_depth() {
+                fbo.depth = Some(setup.format);
+            } else {
+                fbo.colors |= 1 << slot;
             }
+
+            self.visitor
+                .bind_framebuffer_with_texture(location, texture.id)
         } else {
             bail!("can't attach normal texture to framebuffer.");
         }
@@ -526,26 +1410,55 @@ impl Device {
         slot: u32,
     ) -> Result<()> {
         let fbo = self.framebuffers
-            .get(handle)
+            .get_mut(handle)
             .ok_or(ErrorKind::InvalidHandle)?;
         let buf = self.render_buffers
             .get(buf)
             .ok_or(ErrorKind::InvalidHandle)?;
 
+        Self::check_framebuffer_samples(handle, fbo, buf.samples)?;
+
         self.visitor.bind_framebuffer(fbo.id, false)?;
-        match buf.setup.format {
-            RenderTextureFormat::RGB8 | RenderTextureFormat::RGBA4 | RenderTextureFormat::RGBA8 => {
-                let location = gl::COLOR_ATTACHMENT0 + slot;
-                self.visitor
-                    .bind_framebuffer_with_renderbuffer(location, buf.id)
+
+        let location = Self::framebuffer_attachment_point(buf.setup.format, slot);
+        if buf.setup.format.is_depth() {
+            fbo.depth = Some(buf.setup.format);
+        } else {
+            fbo.colors |= 1 << slot;
+        }
+
+        self.visitor
+            .bind_framebuffer_with_renderbuffer(location, buf.id)
+    }
+
+    /// Gets the attachment point of `format`, the `slot` is only used by colors.
+    fn framebuffer_attachment_point(format: RenderTextureFormat, slot: u32) -> GLenum {
+        if format.is_stencil() {
+            gl::DEPTH_STENCIL_ATTACHMENT
+        } else if format.is_depth() {
+            gl::DEPTH_ATTACHMENT
+        } else {
+            gl::COLOR_ATTACHMENT0 + slot
+        }
+    }
+
+    fn check_framebuffer_samples(
+        handle: FrameBufferHandle,
+        fbo: &mut FrameBufferObject,
+        samples: u32,
+    ) -> Result<()> {
+        if let Some(v) = fbo.samples {
+            if v != samples {
+                bail!(
+                    "Incompitable(mismatch samples) attachment of frame-buffer {:?}",
+                    handle
+                );
             }
-            RenderTextureFormat::Depth16
-            | RenderTextureFormat::Depth24
-            | RenderTextureFormat::Depth32 => self.visitor
-                .bind_framebuffer_with_renderbuffer(gl::DEPTH_ATTACHMENT, buf.id),
-            RenderTextureFormat::Depth24Stencil8 => self.visitor
-                .bind_framebuffer_with_renderbuffer(gl::DEPTH_STENCIL_ATTACHMENT, buf.id),
+        } else {
+            fbo.samples = Some(samples);
         }
+
+        Ok(())
     }
 
     pub unsafe fn delete_framebuffer(&mut self, handle: FrameBufferHandle) -> Result<()> {
@@ -561,17 +1474,24 @@ impl Device {
         handle: TextureHandle,
         setup: RenderTextureSetup,
     ) -> Result<()> {
+        // Depth textures could not be filtered linearly on OpenGL ES.
+        let (address, filter) = if setup.format.is_depth() {
+            (TextureAddress::Clamp, TextureFilter::Nearest)
+        } else {
+            (TextureAddress::Repeat, TextureFilter::Linear)
+        };
+
         let (internal_format, in_format, pixel_type) = setup.format.into();
         let id = self.visitor.create_texture(
             internal_format,
             in_format,
             pixel_type,
-            TextureAddress::Repeat,
-            TextureFilter::Linear,
             false,
-            setup.dimensions.0,
-            setup.dimensions.1,
-            None,
+            address,
+            filter,
+            filter,
+            false,
+            &[(setup.dimensions, None)],
         )?;
 
         self.textures.set(
@@ -590,16 +1510,105 @@ impl Device {
         setup: TextureSetup,
         data: Option<&[u8]>,
     ) -> Result<()> {
-        let (internal_format, in_format, pixel_type) = setup.format.into();
+        let (internal_format, in_format, pixel_type) = setup.into();
+        let compressed = setup.format.is_compressed();
+
+        // The storage of compressed texture could not be allocated without data.
+        let zeros;
+        let data = match data {
+            None if compressed => {
+                zeros = vec![0; setup.len()];
+                Some(&zeros[..])
+            }
+            v => v,
+        };
+
+        // Splits the data into mip levels, from the largest to the smallest one.
+        let mut levels = Vec::with_capacity(setup.levels as usize);
+        let mut offset = 0;
+        for i in 0..setup.levels {
+            let dimensions = setup.level_dimensions(i);
+            let len = setup.format.len(dimensions);
+            levels.push((dimensions, data.and_then(|v| v.get(offset..offset + len))));
+            offset += len;
+        }
+
+        let (min_filter, mag_filter) = setup.filters();
         let id = self.visitor.create_texture(
+            internal_format,
+            in_format,
+            pixel_type,
+            compressed,
+            setup.address,
+            min_filter,
+            mag_filter,
+            setup.mipmap,
+            &levels,
+        )?;
+
+        self.textures.set(
+            handle,
+            TextureObject {
+                id: id,
+                setup: GenericTextureSetup::Normal(setup),
+            },
+        );
+        Ok(())
+    }
+
+    pub unsafe fn create_cube_texture(
+        &mut self,
+        handle: TextureHandle,
+        setup: TextureSetup,
+        faces: Option<[&[u8]; 6]>,
+    ) -> Result<()> {
+        let (internal_format, in_format, pixel_type) = setup.into();
+        let (min_filter, mag_filter) = setup.filters();
+        let id = self.visitor.create_cube_texture(
             internal_format,
             in_format,
             pixel_type,
             setup.address,
-            setup.filter,
+            min_filter,
+            mag_filter,
             setup.mipmap,
             setup.dimensions.0,
-            setup.dimensions.1,
+            faces,
+        )?;
+
+        self.textures.set(
+            handle,
+            TextureObject {
+                id: id,
+                setup: GenericTextureSetup::Normal(setup),
+            },
+        );
+        Ok(())
+    }
+
+    pub unsafe fn create_texture_array(
+        &mut self,
+        handle: TextureHandle,
+        setup: TextureSetup,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        let layers = match setup.kind {
+            TextureKind::Texture2DArray(layers) => layers,
+            _ => bail!("Texture array requires the kind of `Texture2DArray`."),
+        };
+
+        let (internal_format, in_format, pixel_type) = setup.into();
+        let (min_filter, mag_filter) = setup.filters();
+        let id = self.visitor.create_texture_array(
+            internal_format,
+            in_format,
+            pixel_type,
+            setup.address,
+            min_filter,
+            mag_filter,
+            setup.mipmap,
+            setup.dimensions,
+            layers,
             data,
         )?;
 
@@ -616,21 +1625,29 @@ impl Device {
     pub unsafe fn update_texture(
         &mut self,
         handle: TextureHandle,
+        layer: u32,
         rect: Rect,
         data: &[u8],
     ) -> Result<()> {
         if let Some(texture) = self.textures.get(handle) {
             if let GenericTextureSetup::Normal(setup) = texture.setup {
-                if data.len() > rect.size() as usize || rect.min.x as u32 >= setup.dimensions.0
-                    || rect.min.y as u32 >= setup.dimensions.1 || rect.max.x < 0
-                    || rect.max.y < 0
-                {
-                    bail!(ErrorKind::OutOfBounds);
+                if let Err(err) = setup.validate_update(rect, layer, data) {
+                    bail!(err.to_string());
+                }
+
+                let (internal_format, format, tt) = setup.into();
+                if setup.format.is_compressed() {
+                    let len = setup
+                        .format
+                        .len((rect.width() as u32, rect.height() as u32));
+                    self.visitor
+                        .update_compressed_texture(texture.id, internal_format, rect, &data[..len])?;
+                } else {
+                    let tp = texture.target();
+                    self.visitor
+                        .update_texture(texture.id, tp, layer, format, tt, rect, data)?;
                 }
 
-                let (_, format, tt) = setup.format.into();
-                self.visitor
-                    .update_texture(texture.id, format, tt, rect, data)?;
                 Ok(())
             } else {
                 bail!("Can not update render texture.");
@@ -640,6 +1657,31 @@ impl Device {
         }
     }
 
+    /// Recreates the texture with new setup and data, the old texture object will be
+    /// freed if exists.
+    pub unsafe fn reload_texture(
+        &mut self,
+        handle: TextureHandle,
+        setup: TextureSetup,
+        data: &[u8],
+    ) -> Result<()> {
+        // The old texture is kept if the new one could not be created.
+        let old = self.textures.remove(handle);
+        if let Err(err) = self.create_texture(handle, setup, Some(data)) {
+            if let Some(texture) = old {
+                self.textures.set(handle, texture);
+            }
+
+            return Err(err);
+        }
+
+        if let Some(texture) = old {
+            self.visitor.delete_texture(texture.id)?;
+        }
+
+        Ok(())
+    }
+
     pub unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
         if let Some(texture) = self.textures.remove(handle) {
             self.visitor.delete_texture(texture.id)?;
@@ -649,14 +1691,66 @@ impl Device {
         }
     }
 
+    /// Reads the pixels of render texture, and sends them through `sender`. The format
+    /// and rect are validated by `GraphicsSystemShared::read_render_texture` already.
+    pub unsafe fn read_texture(
+        &mut self,
+        handle: TextureHandle,
+        rect: Rect,
+        sender: Sender<Vec<u8>>,
+    ) -> Result<()> {
+        let texture = self.textures.get(handle).ok_or(ErrorKind::InvalidHandle)?;
+
+        if let GenericTextureSetup::Render(setup) = texture.setup {
+            if setup.format.is_depth() {
+                return Ok(());
+            }
+
+            let dimensions = math::Point2::new(setup.dimensions.0 as i32, setup.dimensions.1 as i32);
+            let rect = match rect.intersection(Rect::new(math::Point2::new(0, 0), dimensions)) {
+                Some(rect) => rect,
+                None => return Ok(()),
+            };
+
+            let pixels = self.visitor.read_texture_pixels(texture.id, rect)?;
+            // The receiver might be dropped, it's fine to ignore the pixels.
+            let _ = sender.send(pixels);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the pixels of the default framebuffer of primary window back, with its
+    /// dimensions in pixels.
+    pub unsafe fn read_framebuffer(&mut self, sender: Sender<(Vec<u8>, (u32, u32))>) -> Result<()> {
+        let dimensions = self.framebuffer_dimensions.get();
+        if dimensions.0 == 0 || dimensions.1 == 0 {
+            return Ok(());
+        }
+
+        let max = math::Point2::new(dimensions.0 as i32, dimensions.1 as i32);
+        let rect = Rect::new(math::Point2::new(0, 0), max);
+        let pixels = self.visitor.read_framebuffer_pixels(rect)?;
+        // The receiver might be dropped, it's fine to ignore the pixels.
+        let _ = sender.send((pixels, dimensions));
+        Ok(())
+    }
+
     pub fn create_surface(&mut self, handle: SurfaceHandle, setup: SurfaceSetup) -> Result<()> {
-        let view = SurfaceObject { setup: setup };
+        let view = SurfaceObject {
+            setup: setup,
+            multisample: Cell::new(None),
+        };
         self.surfaces.set(handle, view);
         Ok(())
     }
 
-    pub fn delete_surface(&mut self, handle: SurfaceHandle) -> Result<()> {
-        if let Some(_) = self.surfaces.remove(handle) {
+    pub unsafe fn delete_surface(&mut self, handle: SurfaceHandle) -> Result<()> {
+        if let Some(surface) = self.surfaces.remove(handle) {
+            if let Some(msaa) = surface.multisample.get() {
+                self.delete_multisample_object(msaa)?;
+            }
+
             Ok(())
         } else {
             bail!(ErrorKind::InvalidHandle);
@@ -666,38 +1760,176 @@ impl Device {
     /// Initializes named program object. A program object is an object to
     /// which shader objects can be attached. Vertex and fragment shader
     /// are minimal requirement to build a proper program.
+    ///
+    /// Compiles and links the shader. The shader is marked as failed if anything goes
+    /// wrong, so the draw calls with it could be skipped cheaply.
     pub unsafe fn create_shader(&mut self, handle: ShaderHandle, setup: ShaderSetup) -> Result<()> {
+        match self.compile_shader(setup) {
+            Ok(shader) => {
+                self.shaders.set(handle, shader);
+                check()
+            }
+            Err(err) => {
+                self.failed_shaders.insert(handle);
+                Err(err)
+            }
+        }
+    }
+
+    /// Compiles and links the compute shader, which is marked as failed if anything
+    /// goes wrong as well.
+    pub unsafe fn create_compute_shader(
+        &mut self,
+        handle: ShaderHandle,
+        setup: ComputeShaderSetup,
+    ) -> Result<()> {
+        match self.compile_compute_shader(setup) {
+            Ok(shader) => {
+                self.shaders.set(handle, shader);
+                check()
+            }
+            Err(err) => {
+                self.failed_shaders.insert(handle);
+                Err(err)
+            }
+        }
+    }
+
+    /// Recompiles the shader with new sources. The old program is kept if anything
+    /// goes wrong, and a failed shader is recovered if succeed.
+    pub unsafe fn update_shader(&mut self, handle: ShaderHandle, setup: ShaderSetup) -> Result<()> {
+        if self.shaders.get(handle).is_none() && !self.failed_shaders.contains(&handle) {
+            bail!(ErrorKind::InvalidHandle);
+        }
+
+        let shader = self.compile_shader(setup)?;
+        if let Some(old) = self.shaders.remove(handle) {
+            self.visitor.delete_program(old.id)?;
+        }
+
+        self.failed_shaders.remove(&handle);
+        self.shaders.set(handle, shader);
+        check()
+    }
+
+    unsafe fn compile_shader(&mut self, setup: ShaderSetup) -> Result<ShaderObject> {
         let pid = self.visitor.create_program(&setup.vs, &setup.fs)?;
 
         for (name, _) in setup.layout.iter() {
             let name: &'static str = name.into();
             let location = self.visitor.get_attribute_location(pid, name)?;
             if location == -1 {
+                self.visitor.delete_program(pid)?;
                 bail!(format!("failed to locate attribute {:?}", name));
             }
         }
 
+        let uniform_locations = self.locate_uniforms(pid, setup.uniform_variables.keys())?;
+
+        // Every uniform block of program takes its own binding point, and the uniform
+        // buffers are bound to them before drawing.
+        let mut uniform_blocks = HashMap::new();
+        for (binding, (name, size)) in setup.uniform_blocks.into_iter().enumerate() {
+            let index = match self.visitor.get_uniform_block_index(pid, &name)? {
+                Some(index) => index,
+                None => {
+                    self.visitor.delete_program(pid)?;
+                    bail!(format!("failed to locate uniform block {:?}", name));
+                }
+            };
+
+            let expected = self.visitor.get_uniform_block_size(pid, index)?;
+            if expected != size {
+                self.visitor.delete_program(pid)?;
+                bail!(format!(
+                    "uniform block {:?} is declared with {} bytes, but {} bytes are required by shader",
+                    name, size, expected
+                ));
+            }
+
+            let binding = binding as GLuint;
+            self.visitor.set_uniform_block_binding(pid, index, binding)?;
+            uniform_blocks.insert(name.into(), binding);
+        }
+
+        let storage_blocks = self.bind_storage_blocks(pid, &setup.storage_blocks)?;
+
+        Ok(ShaderObject {
+            id: pid,
+            render_state: setup.render_state,
+            layout: setup.layout,
+            outputs: setup.outputs,
+            uniform_locations: uniform_locations,
+            uniform_blocks: uniform_blocks,
+            storage_blocks: storage_blocks,
+            uniforms: HashMap::new(),
+        })
+    }
+
+    unsafe fn compile_compute_shader(&mut self, setup: ComputeShaderSetup) -> Result<ShaderObject> {
+        let pid = self.visitor.create_compute_program(&setup.cs)?;
+        let uniform_locations = self.locate_uniforms(pid, setup.uniform_variables.keys())?;
+        let storage_blocks = self.bind_storage_blocks(pid, &setup.storage_blocks)?;
+
+        Ok(ShaderObject {
+            id: pid,
+            render_state: RenderState::default(),
+            layout: AttributeLayout::default(),
+            outputs: 0,
+            uniform_locations: uniform_locations,
+            uniform_blocks: HashMap::new(),
+            storage_blocks: storage_blocks,
+            uniforms: HashMap::new(),
+        })
+    }
+
+    /// Gets the locations of uniform variables, the program is deleted if any of them
+    /// could not be found.
+    unsafe fn locate_uniforms<'a, T>(
+        &self,
+        pid: ResourceID,
+        names: T,
+    ) -> Result<HashMap<HashValue<str>, UniformID>>
+    where
+        T: Iterator<Item = &'a String>,
+    {
         let mut uniform_locations = HashMap::new();
-        for (name, _) in setup.uniform_variables {
-            let location = self.visitor.get_uniform_location(pid, &name)?;
+        for name in names {
+            let location = self.visitor.get_uniform_location(pid, name)?;
             if location == -1 {
+                self.visitor.delete_program(pid)?;
                 bail!(format!("failed to locate uniform {:?}", name));
             }
 
             uniform_locations.insert(name.into(), location);
         }
 
-        self.shaders.set(
-            handle,
-            ShaderObject {
-                id: pid,
-                render_state: setup.render_state,
-                layout: setup.layout,
-                uniform_locations: uniform_locations,
-                uniforms: HashMap::new(),
-            },
-        );
-        check()
+        Ok(uniform_locations)
+    }
+
+    /// Assigns every shader storage block of program its own binding point, the storage
+    /// buffers are bound to them before drawing or dispatching.
+    unsafe fn bind_storage_blocks(
+        &self,
+        pid: ResourceID,
+        names: &[String],
+    ) -> Result<HashMap<HashValue<str>, GLuint>> {
+        let mut storage_blocks = HashMap::new();
+        for (binding, name) in names.iter().enumerate() {
+            let index = match self.visitor.get_storage_block_index(pid, name)? {
+                Some(index) => index,
+                None => {
+                    self.visitor.delete_program(pid)?;
+                    bail!(format!("failed to locate storage block {:?}", name));
+                }
+            };
+
+            let binding = binding as GLuint;
+            self.visitor.set_storage_block_binding(pid, index, binding)?;
+            storage_blocks.insert(name.into(), binding);
+        }
+
+        Ok(storage_blocks)
     }
 
     // pub fn update_shader_uniform(&mut self,
@@ -717,12 +1949,66 @@ impl Device {
     pub unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
         if let Some(shader) = self.shaders.remove(handle) {
             self.visitor.delete_program(shader.id)
+        } else if self.failed_shaders.remove(&handle) {
+            Ok(())
         } else {
             bail!(ErrorKind::InvalidHandle);
         }
     }
 }
 
+impl Device {
+    pub unsafe fn create_fence(&mut self, handle: FenceHandle) -> Result<()> {
+        if self.fences.get(handle).is_some() {
+            bail!(ErrorKind::DuplicatedHandle)
+        }
+
+        let fence = FenceObject {
+            handle: handle,
+            sync: self.visitor.create_fence()?,
+        };
+
+        self.fences.set(handle, fence);
+        Ok(())
+    }
+
+    /// Blocks until the fence has been signaled or `timeout` elapsed. Signaled fence
+    /// will be deleted automatically. Returns true if the fence has been signaled.
+    pub unsafe fn wait_fence(&mut self, handle: FenceHandle, timeout: Duration) -> Result<bool> {
+        let sync = self.fences
+            .get(handle)
+            .ok_or(ErrorKind::InvalidHandle)?
+            .sync;
+
+        let ns = timeout.as_secs() * 1_000_000_000 + timeout.subsec_nanos() as u64;
+        if self.visitor.client_wait_fence(sync, ns)? {
+            self.fences.remove(handle);
+            self.visitor.delete_fence(sync)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Deletes all the signaled fences, and returns their handles.
+    pub unsafe fn poll_fences(&mut self) -> Result<Vec<FenceHandle>> {
+        let mut signaled = Vec::new();
+        for v in self.fences.buf.iter().filter_map(|v| *v) {
+            if self.visitor.client_wait_fence(v.sync, 0)? {
+                signaled.push(v.handle);
+            }
+        }
+
+        for &handle in &signaled {
+            if let Some(fence) = self.fences.remove(handle) {
+                self.visitor.delete_fence(fence.sync)?;
+            }
+        }
+
+        Ok(signaled)
+    }
+}
+
 struct DataVec<T>
 where
     T: Sized,
@@ -782,3 +2068,100 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scissor_in_pixels() {
+        let viewport = ((10, 20), (300, 200), 1.5);
+
+        let scissor = Scissor::Points((1.0, 1.0), (2.0, 2.0));
+        let v = Device::scissor_in_pixels(scissor, viewport);
+        assert_eq!(v, Scissor::Enable((12, 22), (3, 3)));
+
+        // Adjacent boxes share their boundaries with fractional hidpi factor.
+        let lhs = Scissor::Points((0.0, 0.0), (3.0, 1.0));
+        let rhs = Scissor::Points((3.0, 0.0), (3.0, 1.0));
+        match (
+            Device::scissor_in_pixels(lhs, viewport),
+            Device::scissor_in_pixels(rhs, viewport),
+        ) {
+            (Scissor::Enable(p0, s0), Scissor::Enable(p1, _)) => assert_eq!(p0.0 + s0.0, p1.0),
+            _ => unreachable!(),
+        }
+
+        let scissor = Scissor::Normalized((0.5, 0.0), (0.5, 1.0));
+        let v = Device::scissor_in_pixels(scissor, viewport);
+        assert_eq!(v, Scissor::Enable((160, 20), (150, 200)));
+
+        let scissor = Scissor::Enable((1, 2), (3, 4));
+        assert_eq!(Device::scissor_in_pixels(scissor, viewport), scissor);
+    }
+
+    #[test]
+    fn drawcall_render_state() {
+        let state = RenderState::translucent();
+
+        // Draw calls without override bind nothing but the state of shader.
+        assert_eq!(Device::drawcall_render_state(&state, None), None);
+
+        let v = RenderStateOverride::default();
+        assert_eq!(Device::drawcall_render_state(&state, Some(v)), None);
+        assert_eq!(Device::drawcall_render_state(&state, Some(state.into())), None);
+
+        let mut v = RenderStateOverride::default();
+        v.depth_write = Some(true);
+        let rs = Device::drawcall_render_state(&state, Some(v)).unwrap();
+        assert_eq!(rs, v.apply(&state));
+        assert_eq!(rs.depth_write, true);
+    }
+
+    #[test]
+    fn sort_tasks() {
+        let s1: SurfaceHandle = Handle::new(1, 1).into();
+        let s2: SurfaceHandle = Handle::new(2, 1).into();
+        let s3: SurfaceHandle = Handle::new(3, 1).into();
+        let s4: SurfaceHandle = Handle::new(4, 1).into();
+
+        // The tasks are tagged with the indices of submission.
+        let submissions = [
+            (s1, 1),
+            (s2, 0),
+            (s1, 0),
+            (s3, 5),
+            (s2, 0),
+            (s1, 1),
+            (s4, 0),
+            (s3, 2),
+        ];
+
+        let mut tasks: Vec<_> = submissions
+            .iter()
+            .enumerate()
+            .map(|(i, &(s, o))| {
+                let tag = Scissor::Enable((i as u16, 0), (0, 0));
+                (s, o, FrameTask::UpdateSurface(tag))
+            })
+            .collect();
+
+        // `s1` and `s2` share the same order, `s3` is sequential, and `s4` presents to
+        // the secondary window.
+        Device::sort_tasks(&mut tasks, |handle| match handle.index() {
+            1 | 2 => (0, 1, false),
+            3 => (0, 0, true),
+            _ => (1, 0, false),
+        });
+
+        let order: Vec<_> = tasks
+            .iter()
+            .map(|v| match v.2 {
+                FrameTask::UpdateSurface(Scissor::Enable((i, _), _)) => i,
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(order, [3, 7, 2, 0, 5, 1, 4, 6]);
+    }
+}
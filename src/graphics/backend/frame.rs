@@ -1,8 +1,13 @@
-use std::sync::{Mutex, MutexGuard, RwLock};
+use std::mem;
+use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::sync::mpsc::Sender;
 
 use super::super::*;
 use super::errors::*;
-use super::device::Device;
+use super::device::{Device, WindowTarget};
 
 use utils::{DataBuffer, DataBufferPtr, HashValue, Rect};
 
@@ -10,9 +15,14 @@ use utils::{DataBuffer, DataBufferPtr, HashValue, Rect};
 pub(crate) enum PreFrameTask {
     CreateSurface(SurfaceHandle, SurfaceSetup),
     CreatePipeline(ShaderHandle, ShaderSetup),
+    CreateComputePipeline(ShaderHandle, ComputeShaderSetup),
+    UpdatePipeline(ShaderHandle, ShaderSetup, PathBuf),
     CreateFrameBuffer(FrameBufferHandle, FrameBufferSetup),
     CreateTexture(TextureHandle, TextureSetup, Option<DataBufferPtr<[u8]>>),
-    UpdateTexture(TextureHandle, Rect, DataBufferPtr<[u8]>),
+    CreateCubeTexture(TextureHandle, TextureSetup, Option<[DataBufferPtr<[u8]>; 6]>),
+    CreateTextureArray(TextureHandle, TextureSetup, Option<DataBufferPtr<[u8]>>),
+    UpdateTexture(TextureHandle, u32, Rect, DataBufferPtr<[u8]>),
+    ReloadTexture(TextureHandle, TextureSetup, DataBufferPtr<[u8]>, PathBuf),
     CreateRenderTexture(TextureHandle, RenderTextureSetup),
     CreateRenderBuffer(RenderBufferHandle, RenderBufferSetup),
     CreateMesh(
@@ -23,26 +33,200 @@ pub(crate) enum PreFrameTask {
     ),
     UpdateVertexBuffer(MeshHandle, usize, DataBufferPtr<[u8]>),
     UpdateIndexBuffer(MeshHandle, usize, DataBufferPtr<[u8]>),
+    CreateUniformBuffer(
+        UniformBufferHandle,
+        UniformBufferSetup,
+        Option<DataBufferPtr<[u8]>>,
+    ),
+    UpdateUniformBuffer(UniformBufferHandle, usize, DataBufferPtr<[u8]>),
+    CreateStorageBuffer(
+        StorageBufferHandle,
+        StorageBufferSetup,
+        Option<DataBufferPtr<[u8]>>,
+    ),
+    UpdateStorageBuffer(StorageBufferHandle, usize, DataBufferPtr<[u8]>),
+}
+
+impl PreFrameTask {
+    /// Describes the task with the handle it operates on.
+    pub fn describe(&self) -> String {
+        match *self {
+            PreFrameTask::CreateSurface(handle, _) => format!("CreateSurface({:?})", handle),
+            PreFrameTask::CreatePipeline(handle, _) => format!("CreatePipeline({:?})", handle),
+            PreFrameTask::CreateComputePipeline(handle, _) => {
+                format!("CreateComputePipeline({:?})", handle)
+            }
+            PreFrameTask::UpdatePipeline(handle, _, _) => {
+                format!("UpdatePipeline({:?})", handle)
+            }
+            PreFrameTask::CreateFrameBuffer(handle, _) => {
+                format!("CreateFrameBuffer({:?})", handle)
+            }
+            PreFrameTask::CreateTexture(handle, _, _) => format!("CreateTexture({:?})", handle),
+            PreFrameTask::CreateCubeTexture(handle, _, _) => {
+                format!("CreateCubeTexture({:?})", handle)
+            }
+            PreFrameTask::CreateTextureArray(handle, _, _) => {
+                format!("CreateTextureArray({:?})", handle)
+            }
+            PreFrameTask::UpdateTexture(handle, _, _, _) => {
+                format!("UpdateTexture({:?})", handle)
+            }
+            PreFrameTask::ReloadTexture(handle, _, _, _) => format!("ReloadTexture({:?})", handle),
+            PreFrameTask::CreateRenderTexture(handle, _) => {
+                format!("CreateRenderTexture({:?})", handle)
+            }
+            PreFrameTask::CreateRenderBuffer(handle, _) => {
+                format!("CreateRenderBuffer({:?})", handle)
+            }
+            PreFrameTask::CreateMesh(handle, _, _, _) => format!("CreateMesh({:?})", handle),
+            PreFrameTask::UpdateVertexBuffer(handle, _, _) => {
+                format!("UpdateVertexBuffer({:?})", handle)
+            }
+            PreFrameTask::UpdateIndexBuffer(handle, _, _) => {
+                format!("UpdateIndexBuffer({:?})", handle)
+            }
+            PreFrameTask::CreateUniformBuffer(handle, _, _) => {
+                format!("CreateUniformBuffer({:?})", handle)
+            }
+            PreFrameTask::UpdateUniformBuffer(handle, _, _) => {
+                format!("UpdateUniformBuffer({:?})", handle)
+            }
+            PreFrameTask::CreateStorageBuffer(handle, _, _) => {
+                format!("CreateStorageBuffer({:?})", handle)
+            }
+            PreFrameTask::UpdateStorageBuffer(handle, _, _) => {
+                format!("UpdateStorageBuffer({:?})", handle)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum FrameTask {
     DrawCall(FrameDrawCall),
+    InstancedDrawCall(FrameDrawCall, AttributeLayout, DataBufferPtr<[u8]>, usize),
     UpdateSurface(Scissor),
     UpdateVertexBuffer(MeshHandle, usize, DataBufferPtr<[u8]>),
     UpdateIndexBuffer(MeshHandle, usize, DataBufferPtr<[u8]>),
-    UpdateTexture(TextureHandle, Rect, DataBufferPtr<[u8]>),
+    UpdateTexture(TextureHandle, u32, Rect, DataBufferPtr<[u8]>),
+    Dispatch(FrameDispatch),
+}
+
+impl FrameTask {
+    /// Describes the task with the handle it operates on.
+    pub fn describe(&self) -> String {
+        match *self {
+            FrameTask::DrawCall(dc) => {
+                format!("DrawCall(shader: {:?}, mesh: {:?})", dc.shader, dc.mesh)
+            }
+            FrameTask::InstancedDrawCall(dc, _, _, count) => format!(
+                "InstancedDrawCall(shader: {:?}, mesh: {:?}, count: {})",
+                dc.shader, dc.mesh, count
+            ),
+            FrameTask::UpdateSurface(_) => "UpdateSurface".to_owned(),
+            FrameTask::UpdateVertexBuffer(handle, _, _) => {
+                format!("UpdateVertexBuffer({:?})", handle)
+            }
+            FrameTask::UpdateIndexBuffer(handle, _, _) => {
+                format!("UpdateIndexBuffer({:?})", handle)
+            }
+            FrameTask::UpdateTexture(handle, _, _, _) => format!("UpdateTexture({:?})", handle),
+            FrameTask::Dispatch(dc) => {
+                format!("Dispatch(shader: {:?}, groups: {:?})", dc.shader, dc.groups)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct FrameDrawCall {
     pub shader: ShaderHandle,
-    pub uniforms: DataBufferPtr<[(HashValue<str>, DataBufferPtr<UniformVariable>)]>,
+    pub uniforms: DataBufferPtr<[(HashValue<str>, DataBufferPtr<FrameUniformVariable>)]>,
+    pub uniform_buffers: DataBufferPtr<[(HashValue<str>, UniformBufferHandle)]>,
+    pub storage_buffers: DataBufferPtr<[(HashValue<str>, StorageBufferHandle)]>,
     pub mesh: MeshHandle,
     pub index: MeshIndex,
+    pub render_state: Option<RenderStateOverride>,
+    pub scissor: Option<Scissor>,
 }
 
 #[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameDispatch {
+    pub shader: ShaderHandle,
+    pub uniforms: DataBufferPtr<[(HashValue<str>, DataBufferPtr<FrameUniformVariable>)]>,
+    pub storage_buffers: DataBufferPtr<[(HashValue<str>, StorageBufferHandle)]>,
+    pub groups: (u32, u32, u32),
+}
+
+/// The packed `UniformVariable`, the elements of arrays are stored contiguously
+/// in the data buffer of frame.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FrameUniformVariable {
+    Texture(TextureHandle),
+    TextureCube(TextureHandle),
+    Texture2DArray(TextureHandle),
+    I32(i32),
+    F32(f32),
+    Vector2f([f32; 2]),
+    Vector3f([f32; 3]),
+    Vector4f([f32; 4]),
+    Matrix2f([[f32; 2]; 2], bool),
+    Matrix3f([[f32; 3]; 3], bool),
+    Matrix4f([[f32; 4]; 4], bool),
+    Vector3fArray(DataBufferPtr<[[f32; 3]]>),
+    Matrix4fArray(DataBufferPtr<[[[f32; 4]; 4]]>),
+}
+
+impl FrameUniformVariable {
+    pub fn new(variable: &UniformVariable, buf: &mut DataBuffer) -> Self {
+        match *variable {
+            UniformVariable::Texture(v) => FrameUniformVariable::Texture(v),
+            UniformVariable::TextureCube(v) => FrameUniformVariable::TextureCube(v),
+            UniformVariable::Texture2DArray(v) => FrameUniformVariable::Texture2DArray(v),
+            UniformVariable::I32(v) => FrameUniformVariable::I32(v),
+            UniformVariable::F32(v) => FrameUniformVariable::F32(v),
+            UniformVariable::Vector2f(v) => FrameUniformVariable::Vector2f(v),
+            UniformVariable::Vector3f(v) => FrameUniformVariable::Vector3f(v),
+            UniformVariable::Vector4f(v) => FrameUniformVariable::Vector4f(v),
+            UniformVariable::Matrix2f(v, t) => FrameUniformVariable::Matrix2f(v, t),
+            UniformVariable::Matrix3f(v, t) => FrameUniformVariable::Matrix3f(v, t),
+            UniformVariable::Matrix4f(v, t) => FrameUniformVariable::Matrix4f(v, t),
+            UniformVariable::Vector3fArray(ref v) => {
+                FrameUniformVariable::Vector3fArray(buf.extend_from_slice_aligned(v, 16))
+            }
+            UniformVariable::Matrix4fArray(ref v) => {
+                FrameUniformVariable::Matrix4fArray(buf.extend_from_slice_aligned(v, 16))
+            }
+        }
+    }
+
+    /// Resolves the packed variable back into `UniformVariable`, with the elements of
+    /// arrays copied out of the data buffer.
+    pub fn resolve(&self, buf: &DataBuffer) -> UniformVariable {
+        match *self {
+            FrameUniformVariable::Texture(v) => UniformVariable::Texture(v),
+            FrameUniformVariable::TextureCube(v) => UniformVariable::TextureCube(v),
+            FrameUniformVariable::Texture2DArray(v) => UniformVariable::Texture2DArray(v),
+            FrameUniformVariable::I32(v) => UniformVariable::I32(v),
+            FrameUniformVariable::F32(v) => UniformVariable::F32(v),
+            FrameUniformVariable::Vector2f(v) => UniformVariable::Vector2f(v),
+            FrameUniformVariable::Vector3f(v) => UniformVariable::Vector3f(v),
+            FrameUniformVariable::Vector4f(v) => UniformVariable::Vector4f(v),
+            FrameUniformVariable::Matrix2f(v, t) => UniformVariable::Matrix2f(v, t),
+            FrameUniformVariable::Matrix3f(v, t) => UniformVariable::Matrix3f(v, t),
+            FrameUniformVariable::Matrix4f(v, t) => UniformVariable::Matrix4f(v, t),
+            FrameUniformVariable::Vector3fArray(ptr) => {
+                UniformVariable::Vector3fArray(buf.as_slice(ptr).to_vec())
+            }
+            FrameUniformVariable::Matrix4fArray(ptr) => {
+                UniformVariable::Matrix4fArray(buf.as_slice(ptr).to_vec())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) enum PostFrameTask {
     DeleteSurface(SurfaceHandle),
     DeletePipeline(ShaderHandle),
@@ -50,6 +234,50 @@ pub(crate) enum PostFrameTask {
     DeleteTexture(TextureHandle),
     DeleteRenderBuffer(RenderBufferHandle),
     DeleteFrameBuffer(FrameBufferHandle),
+    DeleteUniformBuffer(UniformBufferHandle),
+    DeleteStorageBuffer(StorageBufferHandle),
+    DeleteSurfaceBatch(Vec<SurfaceHandle>),
+    DeleteMeshBatch(Vec<MeshHandle>),
+    DeleteTextureBatch(Vec<TextureHandle>),
+    CreateFence(FenceHandle),
+    ReadTexture(TextureHandle, Rect, Sender<Vec<u8>>),
+    ReadFrameBuffer(Sender<(Vec<u8>, (u32, u32))>),
+}
+
+impl PostFrameTask {
+    /// Describes the task with the handle it operates on.
+    pub fn describe(&self) -> String {
+        match *self {
+            PostFrameTask::DeleteSurface(handle) => format!("DeleteSurface({:?})", handle),
+            PostFrameTask::DeletePipeline(handle) => format!("DeletePipeline({:?})", handle),
+            PostFrameTask::DeleteMesh(handle) => format!("DeleteMesh({:?})", handle),
+            PostFrameTask::DeleteTexture(handle) => format!("DeleteTexture({:?})", handle),
+            PostFrameTask::DeleteRenderBuffer(handle) => {
+                format!("DeleteRenderBuffer({:?})", handle)
+            }
+            PostFrameTask::DeleteFrameBuffer(handle) => {
+                format!("DeleteFrameBuffer({:?})", handle)
+            }
+            PostFrameTask::DeleteUniformBuffer(handle) => {
+                format!("DeleteUniformBuffer({:?})", handle)
+            }
+            PostFrameTask::DeleteStorageBuffer(handle) => {
+                format!("DeleteStorageBuffer({:?})", handle)
+            }
+            PostFrameTask::DeleteSurfaceBatch(ref handles) => {
+                format!("DeleteSurfaceBatch({} handles)", handles.len())
+            }
+            PostFrameTask::DeleteMeshBatch(ref handles) => {
+                format!("DeleteMeshBatch({} handles)", handles.len())
+            }
+            PostFrameTask::DeleteTextureBatch(ref handles) => {
+                format!("DeleteTextureBatch({} handles)", handles.len())
+            }
+            PostFrameTask::CreateFence(handle) => format!("CreateFence({:?})", handle),
+            PostFrameTask::ReadTexture(handle, _, _) => format!("ReadTexture({:?})", handle),
+            PostFrameTask::ReadFrameBuffer(_) => "ReadFrameBuffer".to_owned(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +286,15 @@ pub(crate) struct Frame {
     pub tasks: Vec<(SurfaceHandle, u64, FrameTask)>,
     pub post: Vec<PostFrameTask>,
     pub buf: DataBuffer,
+    /// The failures of hot reloading, which are reported after dispatching.
+    pub reload_failures: Vec<(PathBuf, String)>,
+    /// The failures of tasks during dispatching, which are described with the task
+    /// and handle.
+    pub failures: Vec<String>,
+    /// The shaders that failed to compile, or recovered by hot reloading.
+    pub shader_failures: Vec<(ShaderHandle, bool)>,
+    /// The requests of capturing this frame, with the index of frame they are made in.
+    pub captures: Vec<(u64, Sender<FrameCapture>)>,
 }
 
 unsafe impl Send for Frame {}
@@ -71,6 +308,10 @@ impl Frame {
             post: Vec::new(),
             tasks: Vec::new(),
             buf: DataBuffer::with_capacity(capacity),
+            reload_failures: Vec::new(),
+            failures: Vec::new(),
+            shader_failures: Vec::new(),
+            captures: Vec::new(),
         }
     }
 
@@ -80,133 +321,521 @@ impl Frame {
         self.tasks.clear();
         self.post.clear();
         self.buf.clear();
+        self.reload_failures.clear();
+        self.failures.clear();
+        self.shader_failures.clear();
+        self.captures.clear();
     }
 
-    /// Dispatch frame tasks and draw calls to the backend context.
-    pub unsafe fn dispatch(
-        &mut self,
-        device: &mut Device,
-        dimensions: (u32, u32),
-        hidpi: f32,
-    ) -> Result<()> {
-        for v in self.pre.drain(..) {
-            match v {
-                PreFrameTask::CreateSurface(handle, setup) => {
-                    device.create_surface(handle, setup)?;
-                }
-                PreFrameTask::CreatePipeline(handle, setup) => {
-                    device.create_shader(handle, setup)?;
-                }
-                PreFrameTask::CreateMesh(handle, setup, verts, idxes) => {
-                    let field = &self.buf;
-                    let verts = verts.map(|v| field.as_slice(v));
-                    let idxes = idxes.map(|v| field.as_slice(v));
-                    device.create_mesh(handle, setup, verts, idxes)?;
-                }
-                PreFrameTask::UpdateVertexBuffer(handle, offset, data) => {
-                    let data = self.buf.as_slice(data);
-                    device.update_vertex_buffer(handle, offset, data)?;
-                }
-                PreFrameTask::UpdateIndexBuffer(handle, offset, data) => {
-                    let data = self.buf.as_slice(data);
-                    device.update_index_buffer(handle, offset, data)?;
-                }
-                PreFrameTask::CreateTexture(handle, setup, data) => {
-                    let field = &self.buf;
-                    let buf = data.map(|v| field.as_slice(v));
-                    device.create_texture(handle, setup, buf)?;
-                }
-                PreFrameTask::UpdateTexture(handle, rect, data) => {
-                    let data = self.buf.as_slice(data);
-                    device.update_texture(handle, rect, data)?;
+    /// Skips the frame without dispatching anything to the backend, which is used by
+    /// the no-op backend in headless mode. Returns the number of drawcalls and the fences
+    /// that should be signaled.
+    pub fn skip(&mut self) -> (u32, Vec<FenceHandle>) {
+        let drawcalls = self.tasks
+            .iter()
+            .filter(|v| match v.2 {
+                FrameTask::DrawCall(_) | FrameTask::InstancedDrawCall(..) => true,
+                _ => false,
+            })
+            .count();
+
+        let mut fences = Vec::new();
+        for v in self.post.drain(..) {
+            if let PostFrameTask::CreateFence(handle) = v {
+                fences.push(handle);
+            }
+        }
+
+        (drawcalls as u32, fences)
+    }
+
+    /// Dispatch frame tasks and draw calls to the backend context. A failing task is
+    /// recorded into `failures` with the task and handle it operates on, and skipped,
+    /// so the rest of the frame still presents.
+    pub unsafe fn dispatch(&mut self, device: &mut Device, windows: &[WindowTarget]) -> Result<()> {
+        self.execute_pre(|v, buf| Self::dispatch_pre(device, buf, v));
+        device.flush(&mut self.tasks, &self.buf, windows, &mut self.failures)?;
+
+        self.execute_post(|v| Self::dispatch_post(device, v));
+        Ok(())
+    }
+
+    /// Executes the pre-frame tasks with `f` in order. The failures of shader
+    /// compilation are also tracked, so the draw calls with broken shaders could be
+    /// skipped cheaply.
+    fn execute_pre<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&PreFrameTask, &DataBuffer) -> Result<()>,
+    {
+        let mut tasks = mem::replace(&mut self.pre, Vec::new());
+        for v in tasks.drain(..) {
+            match (&v, f(&v, &self.buf)) {
+                // A broken edit of shader or texture should never crash the
+                // application, the old one will be kept if anything goes wrong.
+                (&PreFrameTask::UpdatePipeline(_, _, ref path), Err(err))
+                | (&PreFrameTask::ReloadTexture(_, _, _, ref path), Err(err)) => {
+                    self.reload_failures.push((path.clone(), format!("{:?}", err)));
                 }
-                PreFrameTask::CreateRenderTexture(handle, setup) => {
-                    device.create_render_texture(handle, setup)?;
+                (&PreFrameTask::UpdatePipeline(handle, _, _), Ok(_)) => {
+                    self.shader_failures.push((handle, false));
                 }
-                PreFrameTask::CreateRenderBuffer(handle, setup) => {
-                    device.create_render_buffer(handle, setup)?;
+                (&PreFrameTask::CreatePipeline(handle, _), Err(err))
+                | (&PreFrameTask::CreateComputePipeline(handle, _), Err(err)) => {
+                    self.shader_failures.push((handle, true));
+                    self.failures.push(format!("{} failed: {}", v.describe(), err));
                 }
-                PreFrameTask::CreateFrameBuffer(handle, setup) => {
-                    device.create_framebuffer(handle)?;
-
-                    // Update framebuffer's attachments.
-                    for (i, attachment) in setup.attachments().iter().enumerate() {
-                        if let &Some(v) = attachment {
-                            let i = i as u32;
-                            match v {
-                                FrameBufferAttachment::RenderBuffer(rb) => {
-                                    device.update_framebuffer_with_renderbuffer(handle, rb, i)?;
-                                }
-                                FrameBufferAttachment::Texture(texture) => {
-                                    device.update_framebuffer_with_texture(handle, texture, i)?;
-                                }
-                            };
-                        }
-                    }
+                (_, Err(err)) => {
+                    self.failures.push(format!("{} failed: {}", v.describe(), err));
                 }
+                _ => {}
             }
         }
 
-        device.flush(&mut self.tasks, &self.buf, dimensions, hidpi)?;
+        self.pre = tasks;
+    }
 
+    /// Executes the post-frame tasks with `f` in order.
+    fn execute_post<F>(&mut self, mut f: F)
+    where
+        F: FnMut(PostFrameTask) -> Result<()>,
+    {
         for v in self.post.drain(..) {
-            match v {
-                PostFrameTask::DeleteSurface(handle) => {
-                    device.delete_surface(handle)?;
-                }
-                PostFrameTask::DeletePipeline(handle) => {
-                    device.delete_shader(handle)?;
-                }
-                PostFrameTask::DeleteMesh(handle) => {
-                    device.delete_mesh(handle)?;
-                }
-                PostFrameTask::DeleteTexture(handle) => {
-                    device.delete_texture(handle)?;
-                }
-                PostFrameTask::DeleteRenderBuffer(handle) => {
-                    device.delete_render_buffer(handle)?;
-                }
-                PostFrameTask::DeleteFrameBuffer(handle) => {
-                    device.delete_framebuffer(handle)?;
+            let desc = v.describe();
+            if let Err(err) = f(v) {
+                self.failures.push(format!("{} failed: {}", desc, err));
+            }
+        }
+    }
+
+    unsafe fn dispatch_pre(device: &mut Device, buf: &DataBuffer, v: &PreFrameTask) -> Result<()> {
+        match *v {
+            PreFrameTask::CreateSurface(handle, setup) => device.create_surface(handle, setup),
+            PreFrameTask::CreatePipeline(handle, ref setup) => {
+                device.create_shader(handle, setup.clone())
+            }
+            PreFrameTask::CreateComputePipeline(handle, ref setup) => {
+                device.create_compute_shader(handle, setup.clone())
+            }
+            PreFrameTask::UpdatePipeline(handle, ref setup, _) => {
+                device.update_shader(handle, setup.clone())
+            }
+            PreFrameTask::CreateMesh(handle, ref setup, verts, idxes) => {
+                let verts = verts.map(|v| buf.as_slice(v));
+                let idxes = idxes.map(|v| buf.as_slice(v));
+                device.create_mesh(handle, setup.clone(), verts, idxes)
+            }
+            PreFrameTask::UpdateVertexBuffer(handle, offset, data) => {
+                device.update_vertex_buffer(handle, offset, buf.as_slice(data))
+            }
+            PreFrameTask::UpdateIndexBuffer(handle, offset, data) => {
+                device.update_index_buffer(handle, offset, buf.as_slice(data))
+            }
+            PreFrameTask::CreateTexture(handle, setup, data) => {
+                let data = data.map(|v| buf.as_slice(v));
+                device.create_texture(handle, setup, data)
+            }
+            PreFrameTask::CreateCubeTexture(handle, setup, faces) => {
+                let faces = faces.map(|v| {
+                    [
+                        buf.as_slice(v[0]),
+                        buf.as_slice(v[1]),
+                        buf.as_slice(v[2]),
+                        buf.as_slice(v[3]),
+                        buf.as_slice(v[4]),
+                        buf.as_slice(v[5]),
+                    ]
+                });
+                device.create_cube_texture(handle, setup, faces)
+            }
+            PreFrameTask::CreateTextureArray(handle, setup, data) => {
+                let data = data.map(|v| buf.as_slice(v));
+                device.create_texture_array(handle, setup, data)
+            }
+            PreFrameTask::UpdateTexture(handle, layer, rect, data) => {
+                device.update_texture(handle, layer, rect, buf.as_slice(data))
+            }
+            PreFrameTask::ReloadTexture(handle, setup, data, _) => {
+                device.reload_texture(handle, setup, buf.as_slice(data))
+            }
+            PreFrameTask::CreateRenderTexture(handle, setup) => {
+                device.create_render_texture(handle, setup)
+            }
+            PreFrameTask::CreateRenderBuffer(handle, setup) => {
+                device.create_render_buffer(handle, setup)
+            }
+            PreFrameTask::CreateFrameBuffer(handle, setup) => {
+                device.create_framebuffer(handle, setup)
+            }
+            PreFrameTask::CreateUniformBuffer(handle, setup, data) => {
+                let data = data.map(|v| buf.as_slice(v));
+                device.create_uniform_buffer(handle, setup, data)
+            }
+            PreFrameTask::UpdateUniformBuffer(handle, offset, data) => {
+                device.update_uniform_buffer(handle, offset, buf.as_slice(data))
+            }
+            PreFrameTask::CreateStorageBuffer(handle, setup, data) => {
+                let data = data.map(|v| buf.as_slice(v));
+                device.create_storage_buffer(handle, setup, data)
+            }
+            PreFrameTask::UpdateStorageBuffer(handle, offset, data) => {
+                device.update_storage_buffer(handle, offset, buf.as_slice(data))
+            }
+        }
+    }
+
+    /// Executes `func` on every handle, a failure does not stop the rest of them from
+    /// being processed. The first error is returned.
+    unsafe fn dispatch_batch<T, F>(handles: &[T], mut func: F) -> Result<()>
+    where
+        T: Copy,
+        F: FnMut(T) -> Result<()>,
+    {
+        let mut result = Ok(());
+        for &v in handles {
+            if let Err(err) = func(v) {
+                if result.is_ok() {
+                    result = Err(err);
                 }
             }
         }
 
-        Ok(())
+        result
     }
+
+    unsafe fn dispatch_post(device: &mut Device, v: PostFrameTask) -> Result<()> {
+        match v {
+            PostFrameTask::DeleteSurface(handle) => device.delete_surface(handle),
+            PostFrameTask::DeletePipeline(handle) => device.delete_shader(handle),
+            PostFrameTask::DeleteMesh(handle) => device.delete_mesh(handle),
+            PostFrameTask::DeleteTexture(handle) => device.delete_texture(handle),
+            PostFrameTask::DeleteRenderBuffer(handle) => device.delete_render_buffer(handle),
+            PostFrameTask::DeleteFrameBuffer(handle) => device.delete_framebuffer(handle),
+            PostFrameTask::DeleteUniformBuffer(handle) => device.delete_uniform_buffer(handle),
+            PostFrameTask::DeleteStorageBuffer(handle) => device.delete_storage_buffer(handle),
+            PostFrameTask::DeleteSurfaceBatch(handles) => {
+                Frame::dispatch_batch(&handles, |v| device.delete_surface(v))
+            }
+            PostFrameTask::DeleteMeshBatch(handles) => {
+                Frame::dispatch_batch(&handles, |v| device.delete_mesh(v))
+            }
+            PostFrameTask::DeleteTextureBatch(handles) => {
+                Frame::dispatch_batch(&handles, |v| device.delete_texture(v))
+            }
+            PostFrameTask::CreateFence(handle) => device.create_fence(handle),
+            PostFrameTask::ReadTexture(handle, rect, sender) => {
+                device.read_texture(handle, rect, sender)
+            }
+            PostFrameTask::ReadFrameBuffer(sender) => device.read_framebuffer(sender),
+        }
+    }
+}
+
+/// The number of frames in `MultiFrame` by default.
+pub(crate) const DEFAULT_FRAMES: usize = 3;
+
+struct FrameRing {
+    /// The frame that is being written by producers.
+    front: usize,
+    /// The frames that have been submitted, but not dispatched yet.
+    pending: VecDeque<usize>,
+    /// The frames that have been dispatched and could be written again.
+    free: Vec<usize>,
+    /// The high-water mark of bytes stored in the buffer of any frame.
+    peak: usize,
 }
 
-pub(crate) struct DoubleFrame {
-    idx: RwLock<usize>,
-    frames: [Mutex<Frame>; 2],
+/// A ring of N frames. Producers always write into the front frame, and `swap_frames`
+/// submits it and rotates to a free one, so the producers never wait on the frame
+/// in-flight being dispatched.
+pub(crate) struct MultiFrame {
+    ring: Mutex<FrameRing>,
+    available: Condvar,
+    frames: Vec<Mutex<Frame>>,
+    /// The empty frame that will be dispatched if nothing has been submitted.
+    idle: Mutex<Frame>,
 }
 
-impl DoubleFrame {
+impl MultiFrame {
+    /// Creates a ring of `DEFAULT_FRAMES` frames with specified capacity each.
     pub fn with_capacity(capacity: usize) -> Self {
-        DoubleFrame {
-            idx: RwLock::new(0),
-            frames: [
-                Mutex::new(Frame::with_capacity(capacity)),
-                Mutex::new(Frame::with_capacity(capacity)),
-            ],
+        MultiFrame::new(DEFAULT_FRAMES, capacity)
+    }
+
+    /// Creates a ring of `num` frames with specified capacity each. There are at least
+    /// two frames.
+    pub fn new(num: usize, capacity: usize) -> Self {
+        let num = ::std::cmp::max(num, 2);
+
+        MultiFrame {
+            ring: Mutex::new(FrameRing {
+                front: 0,
+                pending: VecDeque::new(),
+                free: (1..num).rev().collect(),
+                peak: 0,
+            }),
+            available: Condvar::new(),
+            frames: (0..num)
+                .map(|_| Mutex::new(Frame::with_capacity(capacity)))
+                .collect(),
+            idle: Mutex::new(Frame::with_capacity(0)),
         }
     }
 
+    /// Gets the frame that is being written.
     #[inline]
     pub fn front(&self) -> MutexGuard<Frame> {
-        self.frames[*self.idx.read().unwrap()].lock().unwrap()
+        let ring = self.ring.lock().unwrap();
+        self.frames[ring.front].lock().unwrap()
     }
 
+    /// Gets the oldest submitted frame for dispatching, it will be recycled when the
+    /// returned guard is dropped. An empty frame is returned if nothing is submitted.
     #[inline]
-    pub fn back(&self) -> MutexGuard<Frame> {
-        self.frames[(*self.idx.read().unwrap() + 1) % 2]
-            .lock()
-            .unwrap()
+    pub fn back(&self) -> FrameGuard {
+        let idx = self.ring.lock().unwrap().pending.pop_front();
+        let frame = match idx {
+            Some(idx) => self.frames[idx].lock().unwrap(),
+            None => self.idle.lock().unwrap(),
+        };
+
+        FrameGuard {
+            frames: self,
+            idx: idx,
+            frame: frame,
+        }
     }
 
+    /// Submits the front frame and rotates to a free one. This blocks only if all the
+    /// other frames are still waiting for dispatching.
+    ///
+    /// The buffer of new front frame is pre-grown to the peak usage of previous frames,
+    /// so it will not be reallocated incrementally during submitting.
     #[inline]
     pub fn swap_frames(&self) {
-        let mut idx = self.idx.write().unwrap();
-        *idx = (*idx + 1) % 2;
+        let mut ring = self.ring.lock().unwrap();
+        while ring.free.is_empty() {
+            ring = self.available.wait(ring).unwrap();
+        }
+
+        let front = ring.front;
+        ring.pending.push_back(front);
+        ring.front = ring.free.pop().unwrap();
+
+        let mut frame = self.frames[ring.front].lock().unwrap();
+        frame.buf.reserve(ring.peak);
+    }
+
+    /// Gets the high-water mark of bytes stored in the buffer of dispatched frames.
+    #[inline]
+    pub fn peak(&self) -> usize {
+        self.ring.lock().unwrap().peak
+    }
+}
+
+/// The frame being dispatched, which will be recycled when dropped.
+pub(crate) struct FrameGuard<'a> {
+    frames: &'a MultiFrame,
+    idx: Option<usize>,
+    frame: MutexGuard<'a, Frame>,
+}
+
+impl<'a> Deref for FrameGuard<'a> {
+    type Target = Frame;
+
+    fn deref(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+impl<'a> DerefMut for FrameGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Frame {
+        &mut self.frame
+    }
+}
+
+impl<'a> Drop for FrameGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(idx) = self.idx {
+            let mut ring = self.frames.ring.lock().unwrap();
+            ring.peak = ::std::cmp::max(ring.peak, self.frame.buf.peak());
+            ring.free.push(idx);
+            self.frames.available.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use utils::Handle;
+
+    #[test]
+    fn ring() {
+        let frames = MultiFrame::new(3, 64);
+        frames.front().post.push(PostFrameTask::DeleteSurface(Handle::new(1, 1).into()));
+        assert!(frames.back().post.is_empty());
+
+        frames.swap_frames();
+        frames.front().post.push(PostFrameTask::DeleteSurface(Handle::new(2, 1).into()));
+        frames.swap_frames();
+
+        // The frames are dispatched in the order of submitting.
+        for i in 1..3 {
+            let mut frame = frames.back();
+            match frame.post[0] {
+                PostFrameTask::DeleteSurface(handle) => assert_eq!(handle.index(), i),
+                _ => unreachable!(),
+            }
+
+            unsafe { frame.clear() };
+        }
+
+        assert!(frames.back().post.is_empty());
+    }
+
+    #[test]
+    fn peak() {
+        let frames = MultiFrame::new(3, 0);
+        frames.front().buf.extend_from_slice(&[0u8; 1024]);
+        frames.swap_frames();
+        assert_eq!(frames.peak(), 0);
+
+        {
+            let mut frame = frames.back();
+            unsafe { frame.clear() };
+        }
+
+        assert_eq!(frames.peak(), 1024);
+
+        // The frames that have never been written are pre-grown to the peak as well.
+        frames.swap_frames();
+        frames.swap_frames();
+        assert!(frames.front().buf.capacity() >= 1024);
+    }
+
+    #[test]
+    fn stress() {
+        const SWAPS: u32 = 2000;
+
+        let frames = Arc::new(MultiFrame::new(3, 64));
+
+        let producer = {
+            let frames = frames.clone();
+            thread::spawn(move || {
+                for i in 1..(SWAPS + 1) {
+                    for j in 0..(i % 4) {
+                        let handle = Handle::new(i, j + 1).into();
+                        frames.front().post.push(PostFrameTask::DeleteSurface(handle));
+                    }
+
+                    frames.swap_frames();
+                }
+            })
+        };
+
+        let consumer = {
+            let frames = frames.clone();
+            thread::spawn(move || {
+                let mut dispatched = Vec::new();
+                while dispatched.len() < (SWAPS / 4 * 6) as usize {
+                    let mut frame = frames.back();
+                    for v in frame.post.drain(..) {
+                        if let PostFrameTask::DeleteSurface(handle) = v {
+                            dispatched.push((handle.index(), handle.version()));
+                        }
+                    }
+
+                    unsafe { frame.clear() };
+                }
+                dispatched
+            })
+        };
+
+        producer.join().unwrap();
+        let dispatched = consumer.join().unwrap();
+
+        let mut expected = Vec::new();
+        for i in 1..(SWAPS + 1) {
+            for j in 0..(i % 4) {
+                expected.push((i, j + 1));
+            }
+        }
+
+        assert_eq!(dispatched, expected);
+        assert!(frames.back().post.is_empty());
+    }
+
+    #[test]
+    fn isolate() {
+        let surface: SurfaceHandle = Handle::new(1, 1).into();
+        let shader: ShaderHandle = Handle::new(2, 1).into();
+        let texture: TextureHandle = Handle::new(3, 1).into();
+
+        let mut frame = Frame::with_capacity(64);
+        let setup = TextureSetup::default();
+        frame.pre.push(PreFrameTask::CreateSurface(surface, SurfaceSetup::default()));
+        frame.pre.push(PreFrameTask::CreatePipeline(shader, ShaderSetup::default()));
+        frame.pre.push(PreFrameTask::CreateTexture(texture, setup, None));
+
+        // The failing task is skipped, and the later ones still run.
+        let mut executed = Vec::new();
+        frame.execute_pre(|v, _| {
+            executed.push(v.describe());
+            match *v {
+                PreFrameTask::CreatePipeline(..) => bail!("failed to link program."),
+                _ => Ok(()),
+            }
+        });
+
+        assert_eq!(executed.len(), 3);
+        assert!(frame.pre.is_empty());
+        assert_eq!(frame.failures.len(), 1);
+        assert!(frame.failures[0].starts_with("CreatePipeline("));
+        assert_eq!(frame.shader_failures, vec![(shader, true)]);
+
+        frame.post.push(PostFrameTask::DeleteTexture(texture));
+        frame.post.push(PostFrameTask::DeleteSurface(surface));
+
+        let mut executed = Vec::new();
+        frame.execute_post(|v| {
+            executed.push(v.describe());
+            match v {
+                PostFrameTask::DeleteTexture(_) => bail!(ErrorKind::InvalidHandle),
+                _ => Ok(()),
+            }
+        });
+
+        assert_eq!(executed.len(), 2);
+        assert_eq!(frame.failures.len(), 2);
+        assert!(frame.failures[1].starts_with("DeleteTexture("));
+
+        unsafe { frame.clear() };
+        assert!(frame.failures.is_empty() && frame.shader_failures.is_empty());
+    }
+
+    #[test]
+    fn batch() {
+        let handles: Vec<_> = (0..8).collect();
+
+        // The rest of batch are still processed after a failure.
+        let mut executed = Vec::new();
+        let result = unsafe {
+            Frame::dispatch_batch(&handles, |v| {
+                executed.push(v);
+                if v % 3 == 1 {
+                    bail!(format!("failed at {}.", v));
+                }
+                Ok(())
+            })
+        };
+
+        assert_eq!(executed, handles);
+        assert_eq!(result.unwrap_err().to_string(), "failed at 1.");
+
+        let task = PostFrameTask::DeleteMeshBatch(vec![Handle::new(1, 1).into(); 4]);
+        assert_eq!(task.describe(), "DeleteMeshBatch(4 handles)");
     }
 }
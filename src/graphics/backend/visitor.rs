@@ -9,6 +9,7 @@ use utils::{Color, Rect};
 use graphics::*;
 
 use super::errors::*;
+use super::frame::FrameUniformVariable;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpenGLBuffer {
@@ -16,23 +17,36 @@ pub enum OpenGLBuffer {
     Vertex,
     /// Vertex array indices.
     Index,
+    /// Uniform block storage.
+    Uniform,
+    /// Shader storage block.
+    Storage,
 }
 
+/// Vertex array objects are containers which are not shared between contexts, so they
+/// are cached with the window of context, `None` for the primary one.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct VAOPair(GLuint, GLuint);
+struct VAOPair(Option<WindowHandle>, GLuint, GLuint);
+
+type BlendState = (Equation, BlendFactor, BlendFactor);
 
 pub(crate) struct OpenGLVisitor {
     cull_face: Cell<CullFace>,
     front_face_order: Cell<FrontFaceOrder>,
+    polygon_mode: Cell<PolygonMode>,
     depth_test: Cell<Comparison>,
     depth_write: Cell<bool>,
     depth_write_offset: Cell<Option<(f32, f32)>>,
-    color_blend: Cell<Option<(Equation, BlendFactor, BlendFactor)>>,
+    stencil_test: Cell<Option<StencilTest>>,
+    color_blend: Cell<Option<(BlendState, BlendState)>>,
     color_write: Cell<(bool, bool, bool, bool)>,
     viewport: Cell<((u16, u16), (u16, u16))>,
     scissor: Cell<Scissor>,
+    framebuffer_srgb: Cell<bool>,
+    clip_zero_to_one: Cell<bool>,
 
     active_bufs: RefCell<HashMap<GLenum, GLuint>>,
+    active_uniform_bufs: RefCell<HashMap<GLuint, GLuint>>,
     active_program: Cell<Option<GLuint>>,
     active_vao: Cell<Option<GLuint>>,
     active_textures: RefCell<[GLuint; MAX_UNIFORM_TEXTURE_SLOTS]>,
@@ -41,34 +55,37 @@ pub(crate) struct OpenGLVisitor {
     program_attribute_locations: RefCell<HashMap<GLuint, HashMap<String, GLint>>>,
     program_uniform_locations: RefCell<HashMap<GLuint, HashMap<String, GLint>>>,
     vertex_array_objects: RefCell<HashMap<VAOPair, GLuint>>,
+    instanced_vertex_array_objects: RefCell<HashMap<Option<WindowHandle>, GLuint>>,
+    instanced_attribute_locations: RefCell<HashMap<Option<WindowHandle>, Vec<GLuint>>>,
+
+    /// The window of current context, `None` for the primary one.
+    context: Cell<Option<WindowHandle>>,
+    /// The vertex array objects that should be deleted once their contexts become
+    /// current.
+    orphan_vertex_array_objects: RefCell<Vec<(Option<WindowHandle>, GLuint)>>,
 }
 
 impl OpenGLVisitor {
     pub unsafe fn new() -> OpenGLVisitor {
-        // Reset all states to default.
-        gl::Disable(gl::CULL_FACE);
-        gl::FrontFace(gl::CCW);
-        gl::Disable(gl::DEPTH_TEST);
-        gl::DepthMask(gl::FALSE);
-        gl::Disable(gl::POLYGON_OFFSET_FILL);
-        gl::Disable(gl::BLEND);
-        gl::Disable(gl::SCISSOR_TEST);
-        gl::ColorMask(1, 1, 1, 1);
-        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        Self::reset_states();
 
         OpenGLVisitor {
             cull_face: Cell::new(CullFace::Nothing),
             front_face_order: Cell::new(FrontFaceOrder::CounterClockwise),
+            polygon_mode: Cell::new(PolygonMode::Fill),
             depth_test: Cell::new(Comparison::Always),
             depth_write: Cell::new(false),
             depth_write_offset: Cell::new(None),
+            stencil_test: Cell::new(None),
             color_blend: Cell::new(None),
             color_write: Cell::new((true, true, true, true)),
             viewport: Cell::new(((0, 0), (128, 128))),
             scissor: Cell::new(Scissor::Disable),
+            framebuffer_srgb: Cell::new(false),
+            clip_zero_to_one: Cell::new(false),
 
             active_bufs: RefCell::new(HashMap::new()),
+            active_uniform_bufs: RefCell::new(HashMap::new()),
             active_program: Cell::new(None),
             active_vao: Cell::new(None),
             active_textures: RefCell::new([0; MAX_UNIFORM_TEXTURE_SLOTS]),
@@ -77,9 +94,92 @@ impl OpenGLVisitor {
             program_attribute_locations: RefCell::new(HashMap::new()),
             program_uniform_locations: RefCell::new(HashMap::new()),
             vertex_array_objects: RefCell::new(HashMap::new()),
+            instanced_vertex_array_objects: RefCell::new(HashMap::new()),
+            instanced_attribute_locations: RefCell::new(HashMap::new()),
+            context: Cell::new(None),
+            orphan_vertex_array_objects: RefCell::new(Vec::new()),
         }
     }
 
+    /// Notifies that the context of `window` has been made current. The cached states
+    /// belong to the previous context, so they are reset to defaults here.
+    pub unsafe fn switch_context(&self, window: Option<WindowHandle>) -> Result<()> {
+        Self::reset_states();
+
+        self.cull_face.set(CullFace::Nothing);
+        self.front_face_order.set(FrontFaceOrder::CounterClockwise);
+        self.polygon_mode.set(PolygonMode::Fill);
+        self.depth_test.set(Comparison::Always);
+        self.depth_write.set(false);
+        self.depth_write_offset.set(None);
+        self.stencil_test.set(None);
+        self.color_blend.set(None);
+        self.color_write.set((true, true, true, true));
+        self.viewport.set(((0, 0), (128, 128)));
+        self.scissor.set(Scissor::Disable);
+        self.framebuffer_srgb.set(false);
+        self.clip_zero_to_one.set(false);
+
+        self.active_bufs.borrow_mut().clear();
+        self.active_uniform_bufs.borrow_mut().clear();
+        self.active_program.set(None);
+        self.active_vao.set(None);
+        *self.active_textures.borrow_mut() = [0; MAX_UNIFORM_TEXTURE_SLOTS];
+        self.active_framebuffer.set(0);
+        self.active_renderbuffer.set(None);
+        self.context.set(window);
+
+        // Frees the vertex array objects of deleted programs and buffers, which could
+        // only be done in their own context.
+        self.orphan_vertex_array_objects
+            .borrow_mut()
+            .retain(|&(context, vao)| {
+                if context == window {
+                    gl::DeleteVertexArrays(1, &vao);
+                    false
+                } else {
+                    true
+                }
+            });
+
+        check()
+    }
+
+    /// Forgets the container objects of the context of `window`, which are destroyed
+    /// along with the window.
+    pub fn remove_context(&self, window: WindowHandle) {
+        let context = Some(window);
+        self.vertex_array_objects
+            .borrow_mut()
+            .retain(|k, _| k.0 != context);
+        self.instanced_vertex_array_objects
+            .borrow_mut()
+            .remove(&context);
+        self.instanced_attribute_locations
+            .borrow_mut()
+            .remove(&context);
+        self.orphan_vertex_array_objects
+            .borrow_mut()
+            .retain(|v| v.0 != context);
+    }
+
+    unsafe fn reset_states() {
+        // Reset all states to default.
+        gl::Disable(gl::CULL_FACE);
+        gl::FrontFace(gl::CCW);
+        gl::Disable(gl::DEPTH_TEST);
+        gl::DepthMask(gl::FALSE);
+        gl::Disable(gl::POLYGON_OFFSET_FILL);
+        gl::Disable(gl::STENCIL_TEST);
+        gl::StencilMask(!0);
+        gl::Disable(gl::BLEND);
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::ColorMask(1, 1, 1, 1);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::UseProgram(0);
+    }
+
     /// `flush` does not return until the effects of all previously called GL commands are
     /// complete. Such effects include all changes to GL state, all changes to connection
     /// state, and all changes to the frame buffer contents.
@@ -89,7 +189,10 @@ impl OpenGLVisitor {
     }
 
     pub unsafe fn bind_buffer(&self, tp: GLenum, id: GLuint) -> Result<()> {
-        assert!(tp == gl::ARRAY_BUFFER || tp == gl::ELEMENT_ARRAY_BUFFER);
+        assert!(
+            tp == gl::ARRAY_BUFFER || tp == gl::ELEMENT_ARRAY_BUFFER || tp == gl::UNIFORM_BUFFER
+                || tp == gl::SHADER_STORAGE_BUFFER
+        );
 
         // if let Some(record) = self.active_bufs.borrow().get(&tp) {
         //     if *record == id {
@@ -102,6 +205,44 @@ impl OpenGLVisitor {
         check()
     }
 
+    /// Binds the uniform buffer to the indexed binding point of `GL_UNIFORM_BUFFER`.
+    pub unsafe fn bind_uniform_buffer(&self, binding: GLuint, id: GLuint) -> Result<()> {
+        if let Some(record) = self.active_uniform_bufs.borrow().get(&binding) {
+            if *record == id {
+                return Ok(());
+            }
+        }
+
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, id);
+        self.active_uniform_bufs.borrow_mut().insert(binding, id);
+        // `glBindBufferBase` binds the generic binding point as well.
+        self.active_bufs.borrow_mut().insert(gl::UNIFORM_BUFFER, id);
+        check()
+    }
+
+    /// Binds the storage buffer to the indexed binding point of `GL_SHADER_STORAGE_BUFFER`.
+    pub unsafe fn bind_storage_buffer(&self, binding: GLuint, id: GLuint) -> Result<()> {
+        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, id);
+        // `glBindBufferBase` binds the generic binding point as well.
+        self.active_bufs
+            .borrow_mut()
+            .insert(gl::SHADER_STORAGE_BUFFER, id);
+        check()
+    }
+
+    /// Launches `groups` of work groups with the active compute program. A memory
+    /// barrier is issued after dispatching, so the writes into storage buffers are
+    /// visible to the following draw calls, dispatches and buffer reads.
+    pub unsafe fn dispatch_compute(&self, groups: (u32, u32, u32)) -> Result<()> {
+        gl::DispatchCompute(groups.0, groups.1, groups.2);
+        gl::MemoryBarrier(
+            gl::SHADER_STORAGE_BARRIER_BIT | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT
+                | gl::ELEMENT_ARRAY_BARRIER_BIT | gl::UNIFORM_BARRIER_BIT
+                | gl::BUFFER_UPDATE_BARRIER_BIT,
+        );
+        check()
+    }
+
     pub unsafe fn bind_program(&self, id: GLuint) -> Result<()> {
         if let Some(record) = self.active_program.get() {
             if record == id {
@@ -125,7 +266,8 @@ impl OpenGLVisitor {
             .get(&gl::ARRAY_BUFFER)
             .ok_or(ErrorKind::InvalidHandle)?;
 
-        if let Some(vao) = self.vertex_array_objects.borrow().get(&VAOPair(pid, vid)) {
+        let pair = VAOPair(self.context.get(), pid, vid);
+        if let Some(vao) = self.vertex_array_objects.borrow().get(&pair) {
             if let Some(v) = self.active_vao.get() {
                 if *vao == v {
                     return Ok(());
@@ -176,27 +318,131 @@ impl OpenGLVisitor {
         check()?;
         self.vertex_array_objects
             .borrow_mut()
-            .insert(VAOPair(pid, vid), vao);
+            .insert(pair, vao);
         Ok(())
     }
 
-    pub unsafe fn bind_uniform(&self, location: GLint, variable: &UniformVariable) -> Result<()> {
+    /// Binds the attributes of shader with vertex buffer and per-instance buffer. A
+    /// dedicated vertex array object is used here since the instance stream changes
+    /// between draw calls.
+    pub unsafe fn bind_instanced_attribute_layout(
+        &self,
+        attributes: &AttributeLayout,
+        layout: &VertexLayout,
+        vbo: GLuint,
+        instances: &AttributeLayout,
+        ivbo: GLuint,
+    ) -> Result<()> {
+        let pid = self.active_program.get().ok_or(ErrorKind::InvalidHandle)?;
+
+        let context = self.context.get();
+        let vao = *self.instanced_vertex_array_objects
+            .borrow_mut()
+            .entry(context)
+            .or_insert_with(|| {
+                let mut vao = 0;
+                gl::GenVertexArrays(1, &mut vao);
+                vao
+            });
+
+        gl::BindVertexArray(vao);
+        self.active_vao.set(Some(vao));
+
+        let mut locations = self.instanced_attribute_locations.borrow_mut();
+        let locations = locations.entry(context).or_insert_with(Vec::new);
+        for location in locations.drain(..) {
+            gl::VertexAttribDivisor(location, 0);
+            gl::DisableVertexAttribArray(location);
+        }
+
+        let instance_stride = instances.iter().map(|(_, size)| size as usize).sum::<usize>()
+            * ::std::mem::size_of::<f32>();
+
+        for (name, size) in attributes.iter() {
+            let location = self.get_attribute_location(pid, name.into())? as GLuint;
+
+            if instances.contains(name) {
+                let mut offset = 0;
+                for (v, n) in instances.iter() {
+                    if v == name {
+                        break;
+                    }
+
+                    offset += n as usize * ::std::mem::size_of::<f32>();
+                }
+
+                self.bind_buffer(gl::ARRAY_BUFFER, ivbo)?;
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(
+                    location,
+                    size as GLsizei,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    instance_stride as GLsizei,
+                    offset as *const u8 as *const c_void,
+                );
+                gl::VertexAttribDivisor(location, 1);
+            } else if let Some(element) = layout.element(name) {
+                if element.size < size {
+                    bail!(format!(
+                        "vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
+                        name,
+                        element.size,
+                        size
+                    ));
+                }
+
+                let offset = layout.offset(name).unwrap() as *const u8 as *const c_void;
+
+                self.bind_buffer(gl::ARRAY_BUFFER, vbo)?;
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(
+                    location,
+                    element.size as GLsizei,
+                    element.format.into(),
+                    element.normalized as u8,
+                    layout.stride() as GLsizei,
+                    offset,
+                );
+            } else {
+                bail!(format!(
+                    "can't find attribute {:?} description in vertex or instance buffer.",
+                    name
+                ));
+            }
+
+            locations.push(location);
+        }
+
+        check()
+    }
+
+    pub unsafe fn bind_uniform(
+        &self,
+        location: GLint,
+        variable: &FrameUniformVariable,
+    ) -> Result<()> {
         match *variable {
-            UniformVariable::Texture(_) => unreachable!(),
-            UniformVariable::I32(v) => gl::Uniform1i(location, v),
-            UniformVariable::F32(v) => gl::Uniform1f(location, v),
-            UniformVariable::Vector2f(v) => gl::Uniform2f(location, v[0], v[1]),
-            UniformVariable::Vector3f(v) => gl::Uniform3f(location, v[0], v[1], v[2]),
-            UniformVariable::Vector4f(v) => gl::Uniform4f(location, v[0], v[1], v[2], v[3]),
-            UniformVariable::Matrix2f(v, transpose) => {
+            FrameUniformVariable::Texture(_)
+            | FrameUniformVariable::TextureCube(_)
+            | FrameUniformVariable::Texture2DArray(_) => unreachable!(),
+            FrameUniformVariable::Vector3fArray(_) | FrameUniformVariable::Matrix4fArray(_) => {
+                unreachable!()
+            }
+            FrameUniformVariable::I32(v) => gl::Uniform1i(location, v),
+            FrameUniformVariable::F32(v) => gl::Uniform1f(location, v),
+            FrameUniformVariable::Vector2f(v) => gl::Uniform2f(location, v[0], v[1]),
+            FrameUniformVariable::Vector3f(v) => gl::Uniform3f(location, v[0], v[1], v[2]),
+            FrameUniformVariable::Vector4f(v) => gl::Uniform4f(location, v[0], v[1], v[2], v[3]),
+            FrameUniformVariable::Matrix2f(v, transpose) => {
                 let transpose = if transpose { gl::TRUE } else { gl::FALSE };
                 gl::UniformMatrix2fv(location, 1, transpose, v[0].as_ptr())
             }
-            UniformVariable::Matrix3f(v, transpose) => {
+            FrameUniformVariable::Matrix3f(v, transpose) => {
                 let transpose = if transpose { gl::TRUE } else { gl::FALSE };
                 gl::UniformMatrix3fv(location, 1, transpose, v[0].as_ptr())
             }
-            UniformVariable::Matrix4f(v, transpose) => {
+            FrameUniformVariable::Matrix4f(v, transpose) => {
                 let transpose = if transpose { gl::TRUE } else { gl::FALSE };
                 gl::UniformMatrix4fv(location, 1, transpose, v[0].as_ptr())
             }
@@ -205,6 +451,31 @@ impl OpenGLVisitor {
         check()
     }
 
+    pub unsafe fn bind_uniform_vector3f_array(
+        &self,
+        location: GLint,
+        variable: &[[f32; 3]],
+    ) -> Result<()> {
+        if !variable.is_empty() {
+            gl::Uniform3fv(location, variable.len() as GLsizei, variable[0].as_ptr());
+        }
+
+        check()
+    }
+
+    pub unsafe fn bind_uniform_matrix4f_array(
+        &self,
+        location: GLint,
+        variable: &[[[f32; 4]; 4]],
+    ) -> Result<()> {
+        if !variable.is_empty() {
+            let len = variable.len() as GLsizei;
+            gl::UniformMatrix4fv(location, len, gl::FALSE, variable[0][0].as_ptr());
+        }
+
+        check()
+    }
+
     pub unsafe fn get_uniform_location(&self, id: GLuint, name: &str) -> Result<GLint> {
         let mut cache = self.program_uniform_locations.borrow_mut();
         if let Some(uniforms) = cache.get_mut(&id) {
@@ -224,6 +495,64 @@ impl OpenGLVisitor {
         }
     }
 
+    /// Gets the index of named uniform block, `None` is returned if the block is not
+    /// active in program.
+    pub unsafe fn get_uniform_block_index(&self, id: GLuint, name: &str) -> Result<Option<GLuint>> {
+        let c_name = ::std::ffi::CString::new(name.as_bytes()).unwrap();
+        let index = gl::GetUniformBlockIndex(id, c_name.as_ptr());
+        check()?;
+
+        if index == gl::INVALID_INDEX {
+            Ok(None)
+        } else {
+            Ok(Some(index))
+        }
+    }
+
+    /// Gets the minimum size of buffer that backs the uniform block, in bytes.
+    pub unsafe fn get_uniform_block_size(&self, id: GLuint, index: GLuint) -> Result<usize> {
+        let mut size = 0;
+        gl::GetActiveUniformBlockiv(id, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+        check()?;
+        Ok(size as usize)
+    }
+
+    /// Gets the index of named shader storage block, `None` is returned if the block is
+    /// not active in program.
+    pub unsafe fn get_storage_block_index(&self, id: GLuint, name: &str) -> Result<Option<GLuint>> {
+        let c_name = ::std::ffi::CString::new(name.as_bytes()).unwrap();
+        let index = gl::GetProgramResourceIndex(id, gl::SHADER_STORAGE_BLOCK, c_name.as_ptr());
+        check()?;
+
+        if index == gl::INVALID_INDEX {
+            Ok(None)
+        } else {
+            Ok(Some(index))
+        }
+    }
+
+    /// Assigns the shader storage block of program to the indexed binding point.
+    pub unsafe fn set_storage_block_binding(
+        &self,
+        id: GLuint,
+        index: GLuint,
+        binding: GLuint,
+    ) -> Result<()> {
+        gl::ShaderStorageBlockBinding(id, index, binding);
+        check()
+    }
+
+    /// Assigns the uniform block of program to the indexed binding point.
+    pub unsafe fn set_uniform_block_binding(
+        &self,
+        id: GLuint,
+        index: GLuint,
+        binding: GLuint,
+    ) -> Result<()> {
+        gl::UniformBlockBinding(id, index, binding);
+        check()
+    }
+
     pub unsafe fn get_attribute_location(&self, id: GLuint, name: &str) -> Result<GLint> {
         let mut cache = self.program_uniform_locations.borrow_mut();
         if let Some(attributes) = cache.get_mut(&id) {
@@ -253,19 +582,20 @@ impl OpenGLVisitor {
         let depth = depth.into();
         let stencil = stencil.into();
 
-        let mut bits = 0;
+        let bits = clear_mask(color, depth, stencil);
+        if bits == 0 {
+            return Ok(());
+        }
+
         if let Some(v) = color {
-            bits |= gl::COLOR_BUFFER_BIT;
             gl::ClearColor(v.0, v.1, v.2, v.3);
         }
 
         if let Some(v) = depth {
-            bits |= gl::DEPTH_BUFFER_BIT;
             gl::ClearDepth(v as f64);
         }
 
         if let Some(v) = stencil {
-            bits |= gl::STENCIL_BUFFER_BIT;
             gl::ClearStencil(v);
         }
 
@@ -289,6 +619,12 @@ impl OpenGLVisitor {
         }
     }
 
+    /// Gets the current scissor box.
+    #[inline]
+    pub fn scissor(&self) -> Scissor {
+        self.scissor.get()
+    }
+
     /// Set the scissor box relative to the top-lef corner of th window, in pixels.
     pub unsafe fn set_scissor(&self, scissor: Scissor) -> Result<()> {
         match scissor {
@@ -307,12 +643,50 @@ impl OpenGLVisitor {
                     size.1 as GLsizei,
                 );
             }
+            _ => bail!(format!(
+                "Scissor {:?} should be converted into pixels before binding.",
+                scissor
+            )),
         }
 
         self.scissor.set(scissor);
         check()
     }
 
+    /// Enables or disables the sRGB encoding of colors written into framebuffer.
+    /// Notes that `GL_FRAMEBUFFER_SRGB` is not available in OpenGL ES.
+    pub unsafe fn set_framebuffer_srgb(&self, enable: bool) -> Result<()> {
+        if self.framebuffer_srgb.get() != enable {
+            if enable {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            } else {
+                gl::Disable(gl::FRAMEBUFFER_SRGB);
+            }
+
+            self.framebuffer_srgb.set(enable);
+        }
+
+        check()
+    }
+
+    /// Sets the depth range of clip space to `[0, 1]` if `zero_to_one` is true, or the
+    /// default `[-1, 1]` otherwise. Notes that `glClipControl` is only available with
+    /// OpenGL 4.5 or `GL_ARB_clip_control`.
+    pub unsafe fn set_clip_control(&self, zero_to_one: bool) -> Result<()> {
+        if self.clip_zero_to_one.get() != zero_to_one {
+            let depth = if zero_to_one {
+                gl::ZERO_TO_ONE
+            } else {
+                gl::NEGATIVE_ONE_TO_ONE
+            };
+
+            gl::ClipControl(gl::LOWER_LEFT, depth);
+            self.clip_zero_to_one.set(zero_to_one);
+        }
+
+        check()
+    }
+
     /// Specify whether front- or back-facing polygons can be culled.
     pub unsafe fn set_cull_face(&self, face: CullFace) -> Result<()> {
         if self.cull_face.get() != face {
@@ -348,6 +722,25 @@ impl OpenGLVisitor {
         }
     }
 
+    /// Select how polygons are rasterized. Notes that `glPolygonMode` is not
+    /// available in OpenGL ES, so this should only be called on desktop OpenGL.
+    pub unsafe fn set_polygon_mode(&self, mode: PolygonMode) -> Result<()> {
+        if self.polygon_mode.get() != mode {
+            gl::PolygonMode(
+                gl::FRONT_AND_BACK,
+                match mode {
+                    PolygonMode::Fill => gl::FILL,
+                    PolygonMode::Line => gl::LINE,
+                    PolygonMode::Point => gl::POINT,
+                },
+            );
+            self.polygon_mode.set(mode);
+            check()
+        } else {
+            Ok(())
+        }
+    }
+
     /// Specify the value used for depth buffer comparisons.
     pub unsafe fn set_depth_test(&self, comparsion: Comparison) -> Result<()> {
         if self.depth_test.get() != comparsion {
@@ -393,19 +786,52 @@ impl OpenGLVisitor {
         check()
     }
 
-    // Specifies how source and destination are combined.
+    /// Enable or disable stencil test, the write mask of stencil buffer will be reset
+    /// if stencil test is disabled to make sure that we can clear it properly.
+    pub unsafe fn set_stencil_test(&self, stencil: Option<StencilTest>) -> Result<()> {
+        if self.stencil_test.get() != stencil {
+            if let Some(v) = stencil {
+                if self.stencil_test.get() == None {
+                    gl::Enable(gl::STENCIL_TEST);
+                }
+
+                gl::StencilFunc(v.comparison.into(), v.reference, v.read_mask);
+                gl::StencilOp(v.fail.into(), v.depth_fail.into(), v.pass.into());
+                gl::StencilMask(v.write_mask);
+            } else {
+                gl::Disable(gl::STENCIL_TEST);
+                gl::StencilMask(!0);
+            }
+
+            self.stencil_test.set(stencil);
+            check()
+        } else {
+            Ok(())
+        }
+    }
+
+    // Specifies how source and destination are combined. The alpha channel will be
+    // blended in the same way as RGB channels if `alpha` is `None`.
     pub unsafe fn set_color_blend(
         &self,
-        blend: Option<(Equation, BlendFactor, BlendFactor)>,
+        color: Option<BlendState>,
+        alpha: Option<BlendState>,
     ) -> Result<()> {
+        let blend = color.map(|v| (v, alpha.unwrap_or(v)));
+
         if self.color_blend.get() != blend {
-            if let Some((equation, src, dst)) = blend {
+            if let Some((color, alpha)) = blend {
                 if self.color_blend.get() == None {
                     gl::Enable(gl::BLEND);
                 }
 
-                gl::BlendFunc(src.into(), dst.into());
-                gl::BlendEquation(equation.into());
+                gl::BlendFuncSeparate(
+                    color.1.into(),
+                    color.2.into(),
+                    alpha.1.into(),
+                    alpha.2.into(),
+                );
+                gl::BlendEquationSeparate(color.0.into(), alpha.0.into());
             } else {
                 if self.color_blend.get() != None {
                     gl::Disable(gl::BLEND);
@@ -440,13 +866,26 @@ impl OpenGLVisitor {
     pub unsafe fn create_program(&self, vs: &str, fs: &str) -> Result<GLuint> {
         let vs = self.compile(gl::VERTEX_SHADER, vs)?;
         let fs = self.compile(gl::FRAGMENT_SHADER, fs)?;
-        let id = self.link(vs, fs)?;
+        self.create_program_with(&[vs, fs])
+    }
 
-        gl::DetachShader(id, vs);
-        gl::DeleteShader(vs);
-        gl::DetachShader(id, fs);
-        gl::DeleteShader(fs);
+    /// Creates a program with a single compute shader.
+    pub unsafe fn create_compute_program(&self, cs: &str) -> Result<GLuint> {
+        let cs = self.compile(gl::COMPUTE_SHADER, cs)?;
+        self.create_program_with(&[cs])
+    }
 
+    unsafe fn create_program_with(&self, shaders: &[GLuint]) -> Result<GLuint> {
+        let result = self.link(shaders);
+        for &v in shaders {
+            if let Ok(id) = result {
+                gl::DetachShader(id, v);
+            }
+
+            gl::DeleteShader(v);
+        }
+
+        let id = result?;
         check()?;
 
         let mut cache = self.program_uniform_locations.borrow_mut();
@@ -471,19 +910,14 @@ impl OpenGLVisitor {
         let mut removes = vec![];
 
         for pair in vao_cache.keys() {
-            if pair.0 == id {
+            if pair.1 == id {
                 removes.push(*pair);
             }
         }
 
         for pair in removes {
             if let Some(v) = vao_cache.remove(&pair) {
-                gl::DeleteVertexArrays(1, &v);
-                if let Some(vao) = self.active_vao.get() {
-                    if v == vao {
-                        self.active_vao.set(None);
-                    }
-                }
+                self.delete_vertex_array_object(pair.0, v);
             }
         }
 
@@ -515,13 +949,24 @@ impl OpenGLVisitor {
         format: GLenum,
         width: u32,
         height: u32,
+        samples: u32,
     ) -> Result<GLuint> {
         let mut id = 0;
         gl::GenRenderbuffers(1, &mut id);
         assert!(id != 0);
 
         self.bind_render_buffer(id)?;
-        gl::RenderbufferStorage(gl::RENDERBUFFER, format, width as GLint, height as GLint);
+        if samples > 1 {
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples as GLsizei,
+                format,
+                width as GLint,
+                height as GLint,
+            );
+        } else {
+            gl::RenderbufferStorage(gl::RENDERBUFFER, format, width as GLint, height as GLint);
+        }
         check()?;
         Ok(id)
     }
@@ -531,7 +976,7 @@ impl OpenGLVisitor {
         check()
     }
 
-    pub unsafe fn bind_texture(&self, slot: GLuint, id: GLuint) -> Result<()> {
+    pub unsafe fn bind_texture(&self, slot: GLuint, tp: GLenum, id: GLuint) -> Result<()> {
         if id == 0 {
             bail!("failed to bind texture with 0.");
         }
@@ -543,7 +988,7 @@ impl OpenGLVisitor {
         let cache = &mut self.active_textures.borrow_mut();
         if cache[slot as usize] != id {
             gl::ActiveTexture(gl::TEXTURE0 + slot);
-            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::BindTexture(tp, id);
             cache[slot as usize] = id;
             check()?;
         }
@@ -551,69 +996,246 @@ impl OpenGLVisitor {
         Ok(())
     }
 
+    /// Creates a 2D texture with mip `levels` in the form of `(dimensions, data)`. The
+    /// `data` of compressed texture is passed through unchanged, and it's required.
     pub unsafe fn create_texture(
         &self,
         internal_format: GLuint,
         format: GLenum,
         pixel_type: GLenum,
+        compressed: bool,
         address: TextureAddress,
-        filter: TextureFilter,
+        min_filter: TextureFilter,
+        mag_filter: TextureFilter,
         mipmap: bool,
-        width: u32,
-        height: u32,
-        data: Option<&[u8]>,
+        levels: &[((u32, u32), Option<&[u8]>)],
     ) -> Result<(GLuint)> {
         let mut id = 0;
         gl::GenTextures(1, &mut id);
         assert!(id != 0);
 
-        self.bind_texture(0, id)?;
-        self.update_texture_parameters(address, filter, mipmap)?;
+        self.bind_texture(0, gl::TEXTURE_2D, id)?;
+        self.update_texture_parameters(gl::TEXTURE_2D, address, min_filter, mag_filter)?;
+
+        for (i, &((width, height), data)) in levels.iter().enumerate() {
+            let value = match data {
+                Some(v) if v.len() > 0 => ::std::mem::transmute(&v[0]),
+                _ => ::std::ptr::null(),
+            };
+
+            if compressed {
+                gl::CompressedTexImage2D(
+                    gl::TEXTURE_2D,
+                    i as GLint,
+                    internal_format,
+                    width as GLsizei,
+                    height as GLsizei,
+                    0,
+                    data.map(|v| v.len()).unwrap_or(0) as GLsizei,
+                    value,
+                );
+            } else {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    i as GLint,
+                    internal_format as GLint,
+                    width as GLsizei,
+                    height as GLsizei,
+                    0,
+                    format,
+                    pixel_type,
+                    value,
+                );
+            }
+        }
 
-        let value = match data {
-            Some(v) if v.len() > 0 => ::std::mem::transmute(&v[0]),
-            _ => ::std::ptr::null(),
-        };
+        if mipmap {
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
 
-        gl::TexImage2D(
-            gl::TEXTURE_2D,
+        check()?;
+        Ok(id)
+    }
+
+    /// Creates a cube texture with six faces, which are ordered as +X, -X, +Y, -Y, +Z
+    /// and -Z.
+    pub unsafe fn create_cube_texture(
+        &self,
+        internal_format: GLuint,
+        format: GLenum,
+        pixel_type: GLenum,
+        address: TextureAddress,
+        min_filter: TextureFilter,
+        mag_filter: TextureFilter,
+        mipmap: bool,
+        size: u32,
+        faces: Option<[&[u8]; 6]>,
+    ) -> Result<(GLuint)> {
+        let mut id = 0;
+        gl::GenTextures(1, &mut id);
+        assert!(id != 0);
+
+        let tp = gl::TEXTURE_CUBE_MAP;
+        self.bind_texture(0, tp, id)?;
+        self.update_texture_parameters(tp, address, min_filter, mag_filter)?;
+
+        for i in 0..6 {
+            let value = match faces {
+                Some(v) if v[i].len() > 0 => ::std::mem::transmute(&v[i][0]),
+                _ => ::std::ptr::null(),
+            };
+
+            gl::TexImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as GLenum,
+                0,
+                internal_format as GLint,
+                size as GLsizei,
+                size as GLsizei,
+                0,
+                format,
+                pixel_type,
+                value,
+            );
+        }
+
+        if mipmap {
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+        }
+
+        check()?;
+        Ok(id)
+    }
+
+    /// Creates a 2D texture array with `layers`, the `data` of layers are stored
+    /// contiguously.
+    pub unsafe fn create_texture_array(
+        &self,
+        internal_format: GLuint,
+        format: GLenum,
+        pixel_type: GLenum,
+        address: TextureAddress,
+        min_filter: TextureFilter,
+        mag_filter: TextureFilter,
+        mipmap: bool,
+        dimensions: (u32, u32),
+        layers: u32,
+        data: Option<&[u8]>,
+    ) -> Result<(GLuint)> {
+        let mut id = 0;
+        gl::GenTextures(1, &mut id);
+        assert!(id != 0);
+
+        let tp = gl::TEXTURE_2D_ARRAY;
+        self.bind_texture(0, tp, id)?;
+        self.update_texture_parameters(tp, address, min_filter, mag_filter)?;
+
+        // Allocates the storage of all layers first, and then fills them one by one.
+        gl::TexImage3D(
+            tp,
             0,
             internal_format as GLint,
-            width as GLsizei,
-            height as GLsizei,
+            dimensions.0 as GLsizei,
+            dimensions.1 as GLsizei,
+            layers as GLsizei,
             0,
             format,
             pixel_type,
-            value,
+            ::std::ptr::null(),
         );
 
+        if let Some(data) = data {
+            let len = data.len() / layers.max(1) as usize;
+            for i in 0..layers {
+                let offset = i as usize * len;
+                gl::TexSubImage3D(
+                    tp,
+                    0,
+                    0,
+                    0,
+                    i as GLint,
+                    dimensions.0 as GLsizei,
+                    dimensions.1 as GLsizei,
+                    1,
+                    format,
+                    pixel_type,
+                    ::std::mem::transmute(&data[offset]),
+                );
+            }
+        }
+
         if mipmap {
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::GenerateMipmap(tp);
         }
 
         check()?;
         Ok(id)
     }
 
+    /// Updates the `rect` of texture with `data`. The `layer` is only used by texture
+    /// arrays.
     pub unsafe fn update_texture(
         &self,
         id: GLuint,
+        tp: GLenum,
+        layer: u32,
         format: GLenum,
         tt: GLenum,
         rect: Rect,
         data: &[u8],
     ) -> Result<()> {
-        self.bind_texture(0, id)?;
+        self.bind_texture(0, tp, id)?;
+
+        if tp == gl::TEXTURE_2D_ARRAY {
+            gl::TexSubImage3D(
+                tp,
+                0,
+                rect.min.x,
+                rect.min.y,
+                layer as GLint,
+                rect.width(),
+                rect.height(),
+                1,
+                format,
+                tt,
+                ::std::mem::transmute(&data[0]),
+            );
+        } else {
+            gl::TexSubImage2D(
+                tp,
+                0,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                format,
+                tt,
+                ::std::mem::transmute(&data[0]),
+            );
+        }
+
+        check()
+    }
+
+    /// Updates a block-aligned region of compressed texture, the `data` is passed
+    /// through unchanged.
+    pub unsafe fn update_compressed_texture(
+        &self,
+        id: GLuint,
+        internal_format: GLenum,
+        rect: Rect,
+        data: &[u8],
+    ) -> Result<()> {
+        self.bind_texture(0, gl::TEXTURE_2D, id)?;
 
-        gl::TexSubImage2D(
+        gl::CompressedTexSubImage2D(
             gl::TEXTURE_2D,
             0,
             rect.min.x,
             rect.min.y,
             rect.width(),
             rect.height(),
-            format,
-            tt,
+            internal_format,
+            data.len() as GLsizei,
             ::std::mem::transmute(&data[0]),
         );
 
@@ -622,33 +1244,23 @@ impl OpenGLVisitor {
 
     pub unsafe fn update_texture_parameters(
         &self,
+        tp: GLenum,
         address: TextureAddress,
-        filter: TextureFilter,
-        mipmap: bool,
+        min_filter: TextureFilter,
+        mag_filter: TextureFilter,
     ) -> Result<()> {
         let address: GLenum = address.into();
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, address as GLint);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, address as GLint);
+        gl::TexParameteri(tp, gl::TEXTURE_WRAP_S, address as GLint);
+        gl::TexParameteri(tp, gl::TEXTURE_WRAP_T, address as GLint);
 
-        match filter {
-            TextureFilter::Nearest => {
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
-            }
-            TextureFilter::Linear => {
-                if mipmap {
-                    gl::TexParameteri(
-                        gl::TEXTURE_2D,
-                        gl::TEXTURE_MIN_FILTER,
-                        gl::LINEAR_MIPMAP_NEAREST as GLint,
-                    );
-                } else {
-                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-                }
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-            }
+        if tp == gl::TEXTURE_CUBE_MAP {
+            gl::TexParameteri(tp, gl::TEXTURE_WRAP_R, address as GLint);
         }
 
+        let min_filter: GLenum = min_filter.into();
+        let mag_filter: GLenum = mag_filter.into();
+        gl::TexParameteri(tp, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+        gl::TexParameteri(tp, gl::TEXTURE_MAG_FILTER, mag_filter as GLint);
         check()
     }
 
@@ -664,6 +1276,30 @@ impl OpenGLVisitor {
         check()
     }
 
+    pub unsafe fn create_fence(&self) -> Result<GLsync> {
+        let sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+        check()?;
+        Ok(sync)
+    }
+
+    /// Waits for the sync object to be signaled in `timeout` nanoseconds, returns
+    /// true if it has been signaled.
+    pub unsafe fn client_wait_fence(&self, sync: GLsync, timeout: u64) -> Result<bool> {
+        match gl::ClientWaitSync(sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout) {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => Ok(true),
+            gl::TIMEOUT_EXPIRED => Ok(false),
+            _ => {
+                check()?;
+                bail!("failed to wait for sync object.");
+            }
+        }
+    }
+
+    pub unsafe fn delete_fence(&self, sync: GLsync) -> Result<()> {
+        gl::DeleteSync(sync);
+        check()
+    }
+
     pub unsafe fn bind_framebuffer(&self, id: GLuint, check_status: bool) -> Result<()> {
         if self.active_framebuffer.get() == id {
             return Ok(());
@@ -700,6 +1336,62 @@ impl OpenGLVisitor {
         check()
     }
 
+    /// Resolves the buffers in `mask` of multisampled framebuffer `src` into `dst`
+    /// inside the specified region. The depth and stencil buffers of both should have
+    /// the same format. The `dst` will be bound as current framebuffer.
+    pub unsafe fn resolve_framebuffer(
+        &self,
+        src: GLuint,
+        dst: GLuint,
+        position: (u16, u16),
+        size: (u16, u16),
+        mask: GLbitfield,
+    ) -> Result<()> {
+        let (x0, y0) = (position.0 as GLint, position.1 as GLint);
+        let (x1, y1) = (x0 + size.0 as GLint, y0 + size.1 as GLint);
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, src);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst);
+        gl::BlitFramebuffer(
+            x0,
+            y0,
+            x1,
+            y1,
+            x0,
+            y0,
+            x1,
+            y1,
+            mask,
+            gl::NEAREST,
+        );
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, dst);
+        self.active_framebuffer.set(dst);
+        check()
+    }
+
+    /// Specifies the color attachments of current framebuffer that the fragment
+    /// outputs are written into.
+    pub unsafe fn set_draw_buffers(&self, buffers: &[GLenum]) -> Result<()> {
+        if self.active_framebuffer.get() == 0 {
+            bail!("cann't set draw buffers of default framebuffer.");
+        }
+
+        gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+        check()
+    }
+
+    /// Specifies the color attachment of current framebuffer that pixels are read
+    /// from, it should be `GL_NONE` for depth-only framebuffers.
+    pub unsafe fn set_read_buffer(&self, buffer: GLenum) -> Result<()> {
+        if self.active_framebuffer.get() == 0 {
+            bail!("cann't set read buffer of default framebuffer.");
+        }
+
+        gl::ReadBuffer(buffer);
+        check()
+    }
+
     pub unsafe fn create_framebuffer(&self) -> Result<GLuint> {
         let mut id = 0;
         gl::GenFramebuffers(1, &mut id);
@@ -723,6 +1415,43 @@ impl OpenGLVisitor {
         check()
     }
 
+    /// Reads the pixels of texture in RGBA8 format by attaching it to a temporary
+    /// framebuffer.
+    pub unsafe fn read_texture_pixels(&self, id: GLuint, rect: Rect) -> Result<Vec<u8>> {
+        let fbo = self.create_framebuffer()?;
+        self.bind_framebuffer_with_texture(gl::COLOR_ATTACHMENT0, id)?;
+
+        let result = self.read_pixels(rect);
+        self.delete_framebuffer(fbo)?;
+        result
+    }
+
+    /// Reads the pixels of the default framebuffer in RGBA8 format.
+    pub unsafe fn read_framebuffer_pixels(&self, rect: Rect) -> Result<Vec<u8>> {
+        self.bind_framebuffer(0, false)?;
+        self.read_pixels(rect)
+    }
+
+    /// Reads the pixels of bound framebuffer in RGBA8 format, row by row from bottom
+    /// to top. The values are copied as they are stored, no color space conversion
+    /// is applied even if the framebuffer is sRGB.
+    unsafe fn read_pixels(&self, rect: Rect) -> Result<Vec<u8>> {
+        let mut pixels = vec![0u8; (rect.width() * rect.height() * 4) as usize];
+        // Rows are tightly packed, so the buffer is large enough with any width.
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+
+        check().map(|_| pixels)
+    }
+
     pub unsafe fn create_buffer(
         &self,
         buf: OpenGLBuffer,
@@ -770,23 +1499,22 @@ impl OpenGLVisitor {
             }
         }
 
+        self.active_uniform_bufs
+            .borrow_mut()
+            .retain(|_, v| *v != id);
+
         let vao_cache = &mut self.vertex_array_objects.borrow_mut();
         let mut removes = vec![];
 
         for pair in vao_cache.keys() {
-            if pair.1 == id {
+            if pair.2 == id {
                 removes.push(*pair);
             }
         }
 
         for pair in removes {
             if let Some(v) = vao_cache.remove(&pair) {
-                gl::DeleteVertexArrays(1, &v);
-                if let Some(vao) = self.active_vao.get() {
-                    if v == vao {
-                        self.active_vao.set(None);
-                    }
-                }
+                self.delete_vertex_array_object(pair.0, v);
             }
         }
 
@@ -794,6 +1522,22 @@ impl OpenGLVisitor {
         check()
     }
 
+    unsafe fn delete_vertex_array_object(&self, context: Option<WindowHandle>, vao: GLuint) {
+        if context != self.context.get() {
+            self.orphan_vertex_array_objects
+                .borrow_mut()
+                .push((context, vao));
+            return;
+        }
+
+        gl::DeleteVertexArrays(1, &vao);
+        if let Some(v) = self.active_vao.get() {
+            if v == vao {
+                self.active_vao.set(None);
+            }
+        }
+    }
+
     pub unsafe fn compile(&self, shader: GLenum, src: &str) -> Result<GLuint> {
         let shader = gl::CreateShader(shader);
         // Attempt to compile the shader
@@ -824,10 +1568,11 @@ impl OpenGLVisitor {
         Ok(shader)
     }
 
-    pub unsafe fn link(&self, vs: GLuint, fs: GLuint) -> Result<GLuint> {
+    pub unsafe fn link(&self, shaders: &[GLuint]) -> Result<GLuint> {
         let program = gl::CreateProgram();
-        gl::AttachShader(program, vs);
-        gl::AttachShader(program, fs);
+        for &v in shaders {
+            gl::AttachShader(program, v);
+        }
 
         gl::LinkProgram(program);
         // Get the link status
@@ -866,10 +1611,29 @@ pub unsafe fn check() -> Result<()> {
     }
 }
 
+/// Assembles the mask of `glClear`, only the buffers with clear values are cleared.
+pub fn clear_mask(color: Option<Color>, depth: Option<f32>, stencil: Option<i32>) -> GLbitfield {
+    let mut bits = 0;
+
+    if color.is_some() {
+        bits |= gl::COLOR_BUFFER_BIT;
+    }
+
+    if depth.is_some() {
+        bits |= gl::DEPTH_BUFFER_BIT;
+    }
+
+    if stencil.is_some() {
+        bits |= gl::STENCIL_BUFFER_BIT;
+    }
+
+    bits
+}
+
 impl From<BufferHint> for GLenum {
     fn from(hint: BufferHint) -> Self {
         match hint {
-            BufferHint::Immutable => gl::STATIC_DRAW,
+            BufferHint::Static => gl::STATIC_DRAW,
             BufferHint::Stream => gl::STREAM_DRAW,
             BufferHint::Dynamic => gl::DYNAMIC_DRAW,
         }
@@ -881,6 +1645,8 @@ impl From<OpenGLBuffer> for GLuint {
         match res {
             OpenGLBuffer::Vertex => gl::ARRAY_BUFFER,
             OpenGLBuffer::Index => gl::ELEMENT_ARRAY_BUFFER,
+            OpenGLBuffer::Uniform => gl::UNIFORM_BUFFER,
+            OpenGLBuffer::Storage => gl::SHADER_STORAGE_BUFFER,
         }
     }
 }
@@ -906,6 +1672,36 @@ impl From<Equation> for GLenum {
             Equation::Add => gl::FUNC_ADD,
             Equation::Subtract => gl::FUNC_SUBTRACT,
             Equation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            Equation::Min => gl::MIN,
+            Equation::Max => gl::MAX,
+        }
+    }
+}
+
+impl From<TextureFilter> for GLenum {
+    fn from(filter: TextureFilter) -> Self {
+        match filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+            TextureFilter::NearestMipmapNearest => gl::NEAREST_MIPMAP_NEAREST,
+            TextureFilter::LinearMipmapNearest => gl::LINEAR_MIPMAP_NEAREST,
+            TextureFilter::NearestMipmapLinear => gl::NEAREST_MIPMAP_LINEAR,
+            TextureFilter::LinearMipmapLinear => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+
+impl From<StencilOp> for GLenum {
+    fn from(op: StencilOp) -> Self {
+        match op {
+            StencilOp::Keep => gl::KEEP,
+            StencilOp::Zero => gl::ZERO,
+            StencilOp::Replace => gl::REPLACE,
+            StencilOp::Increment => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert => gl::INVERT,
         }
     }
 }
@@ -979,10 +1775,53 @@ impl From<TextureFormat> for (GLenum, GLenum, GLenum) {
             TextureFormat::F32F32 => (gl::RG32F, gl::RG, gl::FLOAT),
             TextureFormat::F32F32F32 => (gl::RGB32F, gl::RGB, gl::FLOAT),
             TextureFormat::F32F32F32F32 => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+            // The client format and pixel type are not used by compressed textures.
+            TextureFormat::DXT1 => (COMPRESSED_RGB_S3TC_DXT1, gl::NONE, gl::NONE),
+            TextureFormat::DXT3 => (COMPRESSED_RGBA_S3TC_DXT3, gl::NONE, gl::NONE),
+            TextureFormat::DXT5 => (COMPRESSED_RGBA_S3TC_DXT5, gl::NONE, gl::NONE),
+            TextureFormat::ETC1 => (COMPRESSED_ETC1_RGB8, gl::NONE, gl::NONE),
+            TextureFormat::ETC2RGB8 => (COMPRESSED_RGB8_ETC2, gl::NONE, gl::NONE),
+            TextureFormat::ETC2RGBA8 => (COMPRESSED_RGBA8_ETC2_EAC, gl::NONE, gl::NONE),
+            TextureFormat::ASTC4x4 => (COMPRESSED_RGBA_ASTC_4X4, gl::NONE, gl::NONE),
         }
     }
 }
 
+// The internal formats of compressed textures, most of them are defined by extensions
+// which are not included in the bindings of core profile.
+const COMPRESSED_RGB_S3TC_DXT1: GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+const COMPRESSED_ETC1_RGB8: GLenum = 0x8D64;
+const COMPRESSED_RGB8_ETC2: GLenum = 0x9274;
+const COMPRESSED_SRGB8_ETC2: GLenum = 0x9275;
+const COMPRESSED_RGBA8_ETC2_EAC: GLenum = 0x9278;
+const COMPRESSED_SRGB8_ALPHA8_ETC2_EAC: GLenum = 0x9279;
+const COMPRESSED_RGBA_ASTC_4X4: GLenum = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: GLenum = 0x93D0;
+
+impl From<TextureSetup> for (GLenum, GLenum, GLenum) {
+    fn from(setup: TextureSetup) -> Self {
+        let (internal_format, format, pixel_type) = setup.format.into();
+        let internal_format = match (setup.srgb, setup.format) {
+            (true, TextureFormat::U8U8U8) => gl::SRGB8,
+            (true, TextureFormat::U8U8U8U8) => gl::SRGB8_ALPHA8,
+            (true, TextureFormat::DXT1) => COMPRESSED_SRGB_S3TC_DXT1,
+            (true, TextureFormat::DXT3) => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            (true, TextureFormat::DXT5) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            (true, TextureFormat::ETC2RGB8) => COMPRESSED_SRGB8_ETC2,
+            (true, TextureFormat::ETC2RGBA8) => COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            (true, TextureFormat::ASTC4x4) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4,
+            _ => internal_format,
+        };
+
+        (internal_format, format, pixel_type)
+    }
+}
+
 impl From<TextureAddress> for GLenum {
     fn from(address: TextureAddress) -> Self {
         match address {
@@ -1003,21 +1842,56 @@ impl From<RenderTextureFormat> for (GLenum, GLenum, GLenum) {
             RenderTextureFormat::Depth16 => (
                 gl::DEPTH_COMPONENT16,
                 gl::DEPTH_COMPONENT,
-                gl::UNSIGNED_BYTE,
+                gl::UNSIGNED_SHORT,
             ),
             RenderTextureFormat::Depth24 => (
                 gl::DEPTH_COMPONENT24,
                 gl::DEPTH_COMPONENT,
-                gl::UNSIGNED_BYTE,
+                gl::UNSIGNED_INT,
             ),
             RenderTextureFormat::Depth32 => (
                 gl::DEPTH_COMPONENT32,
                 gl::DEPTH_COMPONENT,
-                gl::UNSIGNED_BYTE,
+                gl::UNSIGNED_INT,
+            ),
+            RenderTextureFormat::Depth32F => (
+                gl::DEPTH_COMPONENT32F,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+            ),
+            RenderTextureFormat::Depth24Stencil8 => (
+                gl::DEPTH24_STENCIL8,
+                gl::DEPTH_STENCIL,
+                gl::UNSIGNED_INT_24_8,
             ),
-            RenderTextureFormat::Depth24Stencil8 => {
-                (gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL, gl::UNSIGNED_BYTE)
-            }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clear() {
+        let mut setup = SurfaceSetup::default();
+        let (color, depth, stencil) = setup.clear();
+        let bits = clear_mask(color, depth, stencil);
+        assert_eq!(bits, gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+        setup.set_clear(Color::white(), None, None);
+        let (color, depth, stencil) = setup.clear();
+        let bits = clear_mask(color, depth, stencil);
+        assert_eq!(bits, gl::COLOR_BUFFER_BIT);
+        assert_eq!(bits & gl::DEPTH_BUFFER_BIT, 0);
+
+        setup.set_clear_color(None);
+        setup.set_clear_stencil(0);
+        let (color, depth, stencil) = setup.clear();
+        assert_eq!(clear_mask(color, depth, stencil), gl::STENCIL_BUFFER_BIT);
+
+        setup.set_clear(None, None, None);
+        let (color, depth, stencil) = setup.clear();
+        assert_eq!(clear_mask(color, depth, stencil), 0);
+    }
+}
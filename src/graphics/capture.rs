@@ -0,0 +1,545 @@
+//! Captures the commands of a frame into a human-readable dump, which is useful to
+//! inspect exactly what was submitted when a draw looks wrong.
+//!
+//! The command stream stores the uniform variables as offsets into the data buffer of
+//! frame, so they are resolved back into concrete `UniformVariable`s, and the hashes
+//! of fields are resolved into the names declared by shaders.
+
+use std::fmt;
+
+use utils::{DataBuffer, Handle, HashValue};
+
+use super::*;
+use super::backend::frame::*;
+use super::leaks::ResourceKind;
+
+/// A captured task of frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedTask {
+    pub surface: SurfaceHandle,
+    pub order: u64,
+    /// The description of task, with the resources it operates on.
+    pub description: String,
+    /// The uniform variables of draw calls and dispatches sorted by names, with the
+    /// values resolved from the data buffer of frame.
+    pub uniforms: Vec<(String, UniformVariable)>,
+}
+
+/// The dump of a frame, which is taken right before it's dispatched to the backend.
+///
+/// It implements `Display`, so it could be printed or written into files and diffed
+/// between frames.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameCapture {
+    /// The index of frame the capture is requested in.
+    pub frame_index: u64,
+    /// The descriptions of tasks executed before the draw calls, e.g. the creations
+    /// and updates of resources.
+    pub pre: Vec<String>,
+    /// The tasks in the order they are submitted. Notes that they will be sorted by
+    /// surfaces and orders when executing.
+    pub tasks: Vec<CapturedTask>,
+    /// The descriptions of tasks executed after the draw calls, e.g. the deletions of
+    /// resources.
+    pub post: Vec<String>,
+}
+
+impl fmt::Display for FrameCapture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Frame {}", self.frame_index)?;
+
+        writeln!(f, "Pre ({}):", self.pre.len())?;
+        for v in &self.pre {
+            writeln!(f, "    {}", v)?;
+        }
+
+        writeln!(f, "Tasks ({}):", self.tasks.len())?;
+        for v in &self.tasks {
+            writeln!(
+                f,
+                "    [{}:{} #{}] {}",
+                v.surface.index(),
+                v.surface.version(),
+                v.order,
+                v.description
+            )?;
+
+            for &(ref name, ref variable) in &v.uniforms {
+                writeln!(f, "        {} = {:?}", name, variable)?;
+            }
+        }
+
+        writeln!(f, "Post ({}):", self.post.len())?;
+        for v in &self.post {
+            writeln!(f, "    {}", v)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves the labels of resources and the names of fields, which are only known by
+/// `GraphicsSystemShared`.
+pub(crate) trait CaptureContext {
+    /// Gets the label of resource recorded when it's created.
+    fn label(&self, kind: ResourceKind, handle: Handle) -> Option<String>;
+
+    /// Gets the name of uniform variable, uniform block or storage block declared by
+    /// shader.
+    fn field(&self, shader: ShaderHandle, field: HashValue<str>) -> Option<String>;
+}
+
+/// Captures `frame` with the labels and names resolved by `ctx`.
+pub(crate) fn capture<C>(ctx: &C, frame: &Frame, frame_index: u64) -> FrameCapture
+where
+    C: CaptureContext,
+{
+    let capturer = Capturer {
+        ctx: ctx,
+        buf: &frame.buf,
+    };
+
+    FrameCapture {
+        frame_index: frame_index,
+        pre: frame.pre.iter().map(|v| capturer.pre(v)).collect(),
+        tasks: frame
+            .tasks
+            .iter()
+            .map(|&(surface, order, ref task)| capturer.task(surface, order, task))
+            .collect(),
+        post: frame.post.iter().map(|v| capturer.post(v)).collect(),
+    }
+}
+
+struct Capturer<'a, C: 'a> {
+    ctx: &'a C,
+    buf: &'a DataBuffer,
+}
+
+impl<'a, C> Capturer<'a, C>
+where
+    C: CaptureContext,
+{
+    /// Names the resource with its handle, and the label if there is one.
+    fn name<H>(&self, kind: ResourceKind, handle: H) -> String
+    where
+        H: Into<Handle>,
+    {
+        let handle = handle.into();
+        match self.ctx.label(kind, handle) {
+            Some(label) => format!(
+                "{:?}({}:{} {:?})",
+                kind,
+                handle.index(),
+                handle.version(),
+                label
+            ),
+            None => format!("{:?}({}:{})", kind, handle.index(), handle.version()),
+        }
+    }
+
+    fn field(&self, shader: ShaderHandle, field: HashValue<str>) -> String {
+        self.ctx
+            .field(shader, field)
+            .unwrap_or_else(|| format!("{:?}", field))
+    }
+
+    fn bytes(ptr: Option<DataBufferPtr<[u8]>>) -> usize {
+        ptr.map(|v| v.len()).unwrap_or(0)
+    }
+
+    fn pre(&self, task: &PreFrameTask) -> String {
+        use self::ResourceKind::*;
+
+        match *task {
+            PreFrameTask::CreateSurface(handle, _) => {
+                format!("CreateSurface({})", self.name(Surface, handle))
+            }
+            PreFrameTask::CreatePipeline(handle, _) => {
+                format!("CreatePipeline({})", self.name(Shader, handle))
+            }
+            PreFrameTask::CreateComputePipeline(handle, _) => {
+                format!("CreateComputePipeline({})", self.name(Shader, handle))
+            }
+            PreFrameTask::UpdatePipeline(handle, _, ref path) => format!(
+                "UpdatePipeline({}, path: {:?})",
+                self.name(Shader, handle),
+                path
+            ),
+            PreFrameTask::CreateFrameBuffer(handle, _) => {
+                format!("CreateFrameBuffer({})", self.name(FrameBuffer, handle))
+            }
+            PreFrameTask::CreateTexture(handle, setup, data) => format!(
+                "CreateTexture({}, dimensions: {:?}, format: {:?}, bytes: {})",
+                self.name(Texture, handle),
+                setup.dimensions,
+                setup.format,
+                Self::bytes(data)
+            ),
+            PreFrameTask::CreateTextureArray(handle, setup, data) => format!(
+                "CreateTextureArray({}, dimensions: {:?}, format: {:?}, bytes: {})",
+                self.name(Texture, handle),
+                setup.dimensions,
+                setup.format,
+                Self::bytes(data)
+            ),
+            PreFrameTask::CreateCubeTexture(handle, setup, faces) => format!(
+                "CreateCubeTexture({}, dimensions: {:?}, format: {:?}, bytes: {})",
+                self.name(Texture, handle),
+                setup.dimensions,
+                setup.format,
+                faces.map(|v| v.iter().map(|v| v.len()).sum::<usize>()).unwrap_or(0)
+            ),
+            PreFrameTask::UpdateTexture(handle, level, rect, data) => format!(
+                "UpdateTexture({}, level: {}, rect: {:?}, bytes: {})",
+                self.name(Texture, handle),
+                level,
+                rect,
+                data.len()
+            ),
+            PreFrameTask::ReloadTexture(handle, setup, data, _) => format!(
+                "ReloadTexture({}, dimensions: {:?}, bytes: {})",
+                self.name(Texture, handle),
+                setup.dimensions,
+                data.len()
+            ),
+            PreFrameTask::CreateRenderTexture(handle, setup) => format!(
+                "CreateRenderTexture({}, dimensions: {:?}, format: {:?})",
+                self.name(Texture, handle),
+                setup.dimensions,
+                setup.format
+            ),
+            PreFrameTask::CreateRenderBuffer(handle, _) => {
+                format!("CreateRenderBuffer({})", self.name(RenderBuffer, handle))
+            }
+            PreFrameTask::CreateMesh(handle, ref setup, verts, idxes) => format!(
+                "CreateMesh({}, vertices: {}, indices: {}, bytes: {})",
+                self.name(Mesh, handle),
+                setup.num_verts,
+                setup.num_idxes,
+                Self::bytes(verts) + Self::bytes(idxes)
+            ),
+            PreFrameTask::UpdateVertexBuffer(handle, offset, data) => format!(
+                "UpdateVertexBuffer({}, offset: {}, bytes: {})",
+                self.name(Mesh, handle),
+                offset,
+                data.len()
+            ),
+            PreFrameTask::UpdateIndexBuffer(handle, offset, data) => format!(
+                "UpdateIndexBuffer({}, offset: {}, bytes: {})",
+                self.name(Mesh, handle),
+                offset,
+                data.len()
+            ),
+            PreFrameTask::CreateUniformBuffer(handle, setup, data) => format!(
+                "CreateUniformBuffer({}, size: {}, bytes: {})",
+                self.name(UniformBuffer, handle),
+                setup.size,
+                Self::bytes(data)
+            ),
+            PreFrameTask::UpdateUniformBuffer(handle, offset, data) => format!(
+                "UpdateUniformBuffer({}, offset: {}, bytes: {})",
+                self.name(UniformBuffer, handle),
+                offset,
+                data.len()
+            ),
+            PreFrameTask::CreateStorageBuffer(handle, setup, data) => format!(
+                "CreateStorageBuffer({}, size: {}, bytes: {})",
+                self.name(StorageBuffer, handle),
+                setup.size,
+                Self::bytes(data)
+            ),
+            PreFrameTask::UpdateStorageBuffer(handle, offset, data) => format!(
+                "UpdateStorageBuffer({}, offset: {}, bytes: {})",
+                self.name(StorageBuffer, handle),
+                offset,
+                data.len()
+            ),
+        }
+    }
+
+    fn task(&self, surface: SurfaceHandle, order: u64, task: &FrameTask) -> CapturedTask {
+        use self::ResourceKind::*;
+
+        let (description, uniforms) = match *task {
+            FrameTask::DrawCall(ref dc) => (
+                format!("DrawCall({})", self.drawcall(dc)),
+                self.uniforms(dc.shader, dc.uniforms),
+            ),
+            FrameTask::InstancedDrawCall(ref dc, _, data, count) => (
+                format!(
+                    "InstancedDrawCall({}, instances: {}, bytes: {})",
+                    self.drawcall(dc),
+                    count,
+                    data.len()
+                ),
+                self.uniforms(dc.shader, dc.uniforms),
+            ),
+            FrameTask::Dispatch(ref dc) => {
+                let mut description = format!(
+                    "Dispatch(shader: {}, groups: {:?}",
+                    self.name(Shader, dc.shader),
+                    dc.groups
+                );
+
+                self.storage_buffers(&mut description, dc.shader, dc.storage_buffers);
+                description.push(')');
+                (description, self.uniforms(dc.shader, dc.uniforms))
+            }
+            FrameTask::UpdateSurface(scissor) => {
+                (format!("UpdateSurface({:?})", scissor), Vec::new())
+            }
+            FrameTask::UpdateVertexBuffer(handle, offset, data) => (
+                format!(
+                    "UpdateVertexBuffer({}, offset: {}, bytes: {})",
+                    self.name(Mesh, handle),
+                    offset,
+                    data.len()
+                ),
+                Vec::new(),
+            ),
+            FrameTask::UpdateIndexBuffer(handle, offset, data) => (
+                format!(
+                    "UpdateIndexBuffer({}, offset: {}, bytes: {})",
+                    self.name(Mesh, handle),
+                    offset,
+                    data.len()
+                ),
+                Vec::new(),
+            ),
+            FrameTask::UpdateTexture(handle, level, rect, data) => (
+                format!(
+                    "UpdateTexture({}, level: {}, rect: {:?}, bytes: {})",
+                    self.name(Texture, handle),
+                    level,
+                    rect,
+                    data.len()
+                ),
+                Vec::new(),
+            ),
+        };
+
+        CapturedTask {
+            surface: surface,
+            order: order,
+            description: description,
+            uniforms: uniforms,
+        }
+    }
+
+    fn drawcall(&self, dc: &FrameDrawCall) -> String {
+        use self::ResourceKind::*;
+
+        let mut description = format!(
+            "shader: {}, mesh: {}, index: {:?}",
+            self.name(Shader, dc.shader),
+            self.name(Mesh, dc.mesh),
+            dc.index
+        );
+
+        if let Some(state) = dc.render_state {
+            description.push_str(&format!(", render_state: {:?}", state));
+        }
+
+        if let Some(v) = dc.render_state_override {
+            description.push_str(&format!(", render_state_override: {:?}", v));
+        }
+
+        if let Some(scissor) = dc.scissor {
+            description.push_str(&format!(", scissor: {:?}", scissor));
+        }
+
+        for &(field, handle) in self.buf.as_slice(dc.uniform_buffers) {
+            description.push_str(&format!(
+                ", {}: {}",
+                self.field(dc.shader, field),
+                self.name(UniformBuffer, handle)
+            ));
+        }
+
+        self.storage_buffers(&mut description, dc.shader, dc.storage_buffers);
+        description
+    }
+
+    fn storage_buffers(
+        &self,
+        description: &mut String,
+        shader: ShaderHandle,
+        buffers: DataBufferPtr<[(HashValue<str>, StorageBufferHandle)]>,
+    ) {
+        for &(field, handle) in self.buf.as_slice(buffers) {
+            description.push_str(&format!(
+                ", {}: {}",
+                self.field(shader, field),
+                self.name(ResourceKind::StorageBuffer, handle)
+            ));
+        }
+    }
+
+    fn uniforms(
+        &self,
+        shader: ShaderHandle,
+        uniforms: DataBufferPtr<[(HashValue<str>, DataBufferPtr<FrameUniformVariable>)]>,
+    ) -> Vec<(String, UniformVariable)> {
+        let mut resolved: Vec<_> = self.buf
+            .as_slice(uniforms)
+            .iter()
+            .map(|&(field, ptr)| {
+                let variable = self.buf.as_ref(ptr).resolve(self.buf);
+                (self.field(shader, field), variable)
+            })
+            .collect();
+
+        resolved.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+        resolved
+    }
+
+    fn post(&self, task: &PostFrameTask) -> String {
+        use self::ResourceKind::*;
+
+        match *task {
+            PostFrameTask::DeleteSurface(handle) => {
+                format!("DeleteSurface({})", self.name(Surface, handle))
+            }
+            PostFrameTask::DeletePipeline(handle) => {
+                format!("DeletePipeline({})", self.name(Shader, handle))
+            }
+            PostFrameTask::DeleteMesh(handle) => format!("DeleteMesh({})", self.name(Mesh, handle)),
+            PostFrameTask::DeleteTexture(handle) => {
+                format!("DeleteTexture({})", self.name(Texture, handle))
+            }
+            PostFrameTask::DeleteRenderBuffer(handle) => {
+                format!("DeleteRenderBuffer({})", self.name(RenderBuffer, handle))
+            }
+            PostFrameTask::DeleteFrameBuffer(handle) => {
+                format!("DeleteFrameBuffer({})", self.name(FrameBuffer, handle))
+            }
+            PostFrameTask::DeleteUniformBuffer(handle) => {
+                format!("DeleteUniformBuffer({})", self.name(UniformBuffer, handle))
+            }
+            PostFrameTask::DeleteStorageBuffer(handle) => {
+                format!("DeleteStorageBuffer({})", self.name(StorageBuffer, handle))
+            }
+            PostFrameTask::DeleteSurfaceBatch(ref handles) => {
+                self.batch("DeleteSurfaceBatch", Surface, handles)
+            }
+            PostFrameTask::DeleteMeshBatch(ref handles) => {
+                self.batch("DeleteMeshBatch", Mesh, handles)
+            }
+            PostFrameTask::DeleteTextureBatch(ref handles) => {
+                self.batch("DeleteTextureBatch", Texture, handles)
+            }
+            _ => task.describe(),
+        }
+    }
+
+    fn batch<H>(&self, name: &str, kind: ResourceKind, handles: &[H]) -> String
+    where
+        H: Into<Handle> + Copy,
+    {
+        let names: Vec<_> = handles.iter().map(|&v| self.name(kind, v)).collect();
+        format!("{}({})", name, names.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::mem;
+
+    use utils::Rect;
+    use math;
+
+    use super::*;
+
+    struct Context(HashMap<HashValue<str>, String>);
+
+    impl CaptureContext for Context {
+        fn label(&self, kind: ResourceKind, handle: Handle) -> Option<String> {
+            if kind == ResourceKind::Mesh && handle.index() == 2 {
+                Some("/cube.obj".to_owned())
+            } else {
+                None
+            }
+        }
+
+        fn field(&self, _: ShaderHandle, field: HashValue<str>) -> Option<String> {
+            self.0.get(&field).cloned()
+        }
+    }
+
+    #[test]
+    fn capture() {
+        let names = ["u_MVPMatrix", "u_Joints", "u_Color"];
+        let names: HashMap<HashValue<str>, String> =
+            names.iter().map(|&v| (v.into(), v.to_owned())).collect();
+        let ctx = Context(names);
+
+        let shader: ShaderHandle = Handle::new(1, 1).into();
+        let mesh: MeshHandle = Handle::new(2, 1).into();
+        let surface: SurfaceHandle = Handle::new(3, 1).into();
+        let texture: TextureHandle = Handle::new(4, 1).into();
+
+        let mvp = [[0.0; 4]; 4];
+        let joints = vec![[[1.0, 0.0, 0.0, 0.0]; 4], [[2.0, 0.0, 0.0, 0.0]; 4]];
+        let uniforms = [
+            ("u_MVPMatrix", UniformVariable::Matrix4f(mvp, false)),
+            ("u_Joints", UniformVariable::Matrix4fArray(joints.clone())),
+            ("u_Color", UniformVariable::Vector4f([1.0, 0.5, 0.0, 1.0])),
+        ];
+
+        let mut frame = Frame::with_capacity(1024);
+        let mut pack: Vec<(HashValue<str>, _)> = Vec::new();
+        for &(n, ref v) in &uniforms {
+            let v = FrameUniformVariable::new(v, &mut frame.buf);
+            let align = mem::align_of::<FrameUniformVariable>();
+            pack.push((n.into(), frame.buf.extend_aligned(&v, align)));
+        }
+
+        let align = mem::align_of::<(HashValue<str>, DataBufferPtr<FrameUniformVariable>)>();
+        let empty: &[(HashValue<str>, UniformBufferHandle)] = &[];
+        let empty_storages: &[(HashValue<str>, StorageBufferHandle)] = &[];
+
+        let dc = FrameDrawCall {
+            shader: shader,
+            uniforms: frame.buf.extend_from_slice_aligned(&pack, align),
+            uniform_buffers: frame.buf.extend_from_slice(empty),
+            storage_buffers: frame.buf.extend_from_slice(empty_storages),
+            mesh: mesh,
+            index: MeshIndex::All,
+            render_state: None,
+            render_state_override: None,
+            scissor: None,
+        };
+
+        let pixels = frame.buf.extend_from_slice(&[0u8; 16]);
+        let rect = Rect::new(math::Point2::new(0, 0), math::Point2::new(2, 2));
+        frame.pre.push(PreFrameTask::UpdateTexture(texture, 0, rect, pixels));
+        frame.tasks.push((surface, 42, FrameTask::DrawCall(dc)));
+        frame.post.push(PostFrameTask::DeleteMesh(mesh));
+
+        let v = super::capture(&ctx, &frame, 7);
+        assert_eq!(v.frame_index, 7);
+        assert_eq!(v.pre.len(), 1);
+        assert!(v.pre[0].contains("bytes: 16"));
+        assert_eq!(v.post, ["DeleteMesh(Mesh(2:1 \"/cube.obj\"))"]);
+
+        assert_eq!(v.tasks.len(), 1);
+        assert_eq!(v.tasks[0].surface, surface);
+        assert_eq!(v.tasks[0].order, 42);
+        assert!(v.tasks[0].description.contains("Shader(1:1)"));
+        assert!(v.tasks[0].description.contains("Mesh(2:1 \"/cube.obj\")"));
+
+        // The variables are resolved from data buffer, and sorted by names.
+        let expected = vec![
+            ("u_Color".to_owned(), uniforms[2].1.clone()),
+            ("u_Joints".to_owned(), UniformVariable::Matrix4fArray(joints)),
+            ("u_MVPMatrix".to_owned(), UniformVariable::Matrix4f(mvp, false)),
+        ];
+        assert_eq!(v.tasks[0].uniforms, expected);
+
+        let dump = format!("{}", v);
+        assert!(dump.contains("[3:1 #42] DrawCall("));
+        assert!(dump.contains("u_MVPMatrix = Matrix4f("));
+    }
+}
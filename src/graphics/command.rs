@@ -3,14 +3,23 @@ use super::errors::*;
 
 use utils::{HashValue, Rect};
 
+/// The number of high bits of `order` that store the layer in `Command::order`.
+pub const ORDER_LAYER_BITS: u32 = 16;
+/// The number of low bits of `order` that store the quantized depth in `Command::order`.
+pub const ORDER_DEPTH_BITS: u32 = 64 - ORDER_LAYER_BITS;
+/// The mask of the bits that store the quantized depth.
+pub const ORDER_DEPTH_MASK: u64 = (1 << ORDER_DEPTH_BITS) - 1;
+
 /// `Command` will be executed in sequential order.
 pub enum Command<'a> {
     DrawCall(SliceDrawCall<'a>),
+    InstancedDrawCall(SliceInstancedDrawCall<'a>),
     // ShaderUniformVariableUpdate(),
     VertexBufferUpdate(VertexBufferUpdate<'a>),
     IndexBufferUpdate(IndexBufferUpdate<'a>),
     TextureUpdate(TextureUpdate<'a>),
     SetScissor(ScissorUpdate),
+    Dispatch(SliceDispatch<'a>),
 }
 
 impl<'a> Command<'a> {
@@ -35,8 +44,19 @@ impl<'a> Command<'a> {
     }
 
     pub fn update_texture(texture: TextureHandle, rect: Rect, data: &[u8]) -> Command {
+        Command::update_texture_layer(texture, 0, rect, data)
+    }
+
+    /// Updates the `rect` of a single `layer` in texture array.
+    pub fn update_texture_layer(
+        texture: TextureHandle,
+        layer: u32,
+        rect: Rect,
+        data: &[u8],
+    ) -> Command {
         let task = TextureUpdate {
             texture: texture,
+            layer: layer,
             rect: rect,
             data: data,
         };
@@ -47,14 +67,31 @@ impl<'a> Command<'a> {
     pub fn set_scissor(scissor: Scissor) -> Command<'a> {
         Command::SetScissor(ScissorUpdate { scissor: scissor })
     }
+
+    /// Composes the `order` of command from `layer` and `depth`, which are sorted by
+    /// layers first, then depth in ascending order. Layers could be used to draw the
+    /// opaque objects before translucent ones, and UI at last.
+    ///
+    /// The `depth` is clamped into `[0, 1]` and quantized into the low
+    /// `ORDER_DEPTH_BITS` bits, so opaque objects are drawn front-to-back with the
+    /// normalized view depth, and translucent objects back-to-front with `1 - depth`.
+    pub fn order(layer: u16, depth: f32) -> u64 {
+        let depth = f64::from(depth.max(0.0).min(1.0));
+        let depth = (depth * ORDER_DEPTH_MASK as f64) as u64;
+        (u64::from(layer) << ORDER_DEPTH_BITS) | (depth & ORDER_DEPTH_MASK)
+    }
 }
 
 /// Draw.
 pub struct SliceDrawCall<'a> {
     pub(crate) shader: ShaderHandle,
     pub(crate) uniforms: &'a [(HashValue<str>, UniformVariable)],
+    pub(crate) uniform_buffers: &'a [(HashValue<str>, UniformBufferHandle)],
+    pub(crate) storage_buffers: &'a [(HashValue<str>, StorageBufferHandle)],
     pub(crate) mesh: MeshHandle,
     pub(crate) index: MeshIndex,
+    pub(crate) render_state: Option<RenderStateOverride>,
+    pub(crate) scissor: Option<Scissor>,
 }
 
 impl<'a> Into<Command<'a>> for SliceDrawCall<'a> {
@@ -63,6 +100,20 @@ impl<'a> Into<Command<'a>> for SliceDrawCall<'a> {
     }
 }
 
+/// Instanced draw.
+pub struct SliceInstancedDrawCall<'a> {
+    pub(crate) drawcall: SliceDrawCall<'a>,
+    pub(crate) layout: AttributeLayout,
+    pub(crate) data: &'a [u8],
+    pub(crate) count: usize,
+}
+
+impl<'a> Into<Command<'a>> for SliceInstancedDrawCall<'a> {
+    fn into(self) -> Command<'a> {
+        Command::InstancedDrawCall(self)
+    }
+}
+
 /// Vertex buffer object update.
 pub struct VertexBufferUpdate<'a> {
     pub(crate) mesh: MeshHandle,
@@ -80,6 +131,7 @@ pub struct IndexBufferUpdate<'a> {
 /// Texture object update.
 pub struct TextureUpdate<'a> {
     pub(crate) texture: TextureHandle,
+    pub(crate) layer: u32,
     pub(crate) rect: Rect,
     pub(crate) data: &'a [u8],
 }
@@ -96,12 +148,54 @@ pub struct ScissorUpdate {
 }
 
 /// A draw call.
-#[derive(Debug, Copy, Clone)]
+///
+/// # Render State
+///
+/// By default, a draw call uses the `RenderState` that its shader was created with.
+/// Some of the fields, e.g. depth writing or blending, could be overridden per-draw
+/// with `DrawCall::set_render_state_override`, which saves creating near-identical
+/// shaders for passes that differ only in these states. `DrawCall::set_render_state`
+/// is a shortcut that overrides all of them.
+///
+/// Overrides are merged in the order they are set, so the latter one wins on the
+/// fields that both of them set. The merged override is applied on top of the state
+/// of shader for this draw only, and the state of shader will be restored when the
+/// next draw call without override is submitted.
+///
+/// # Scissor
+///
+/// By default, a draw call inherits the scissor of surface that set with
+/// `Command::set_scissor`. It could be overridden per-draw with `DrawCall::set_scissor`,
+/// e.g. widgets of UI which clip their own contents. `Scissor::Disable` turns clipping
+/// off for this draw only, and the scissor of surface is restored after drawing.
+///
+/// # Uniform Buffers
+///
+/// Uniforms shared by many draw calls, e.g. the view and projection matrices of camera,
+/// could be uploaded once per frame into an uniform buffer, and bound to the uniform
+/// block declared in `ShaderSetup::uniform_blocks` with `DrawCall::set_uniform_buffer`.
+///
+/// # Storage Buffers
+///
+/// The results of compute shaders, e.g. the positions of GPU particles, could be read
+/// by binding the storage buffer to the storage block declared in
+/// `ShaderSetup::storage_blocks` with `DrawCall::set_storage_buffer`.
+///
+/// # Instancing
+///
+/// `DrawCall::build_instanced` draws the mesh `count` times in one call. The
+/// per-instance attributes are described by an `AttributeLayout`, and sourced from
+/// `data` which should contains tightly packed `f32` components of every instance.
+/// Attributes of the instance stream must not exist in the vertex layout of mesh.
+#[derive(Debug, Clone)]
 pub struct DrawCall {
     shader: ShaderHandle,
-    uniforms: [(HashValue<str>, UniformVariable); MAX_UNIFORM_VARIABLES],
-    uniforms_len: usize,
+    uniforms: Vec<(HashValue<str>, UniformVariable)>,
+    uniform_buffers: Vec<(HashValue<str>, UniformBufferHandle)>,
+    storage_buffers: Vec<(HashValue<str>, StorageBufferHandle)>,
     mesh: MeshHandle,
+    render_state: Option<RenderStateOverride>,
+    scissor: Option<Scissor>,
 }
 
 impl DrawCall {
@@ -109,40 +203,92 @@ impl DrawCall {
     pub fn new(shader: ShaderHandle, mesh: MeshHandle) -> Self {
         DrawCall {
             shader: shader,
-            uniforms: [(HashValue::zero(), UniformVariable::I32(0)); MAX_UNIFORM_VARIABLES],
-            uniforms_len: 0,
+            uniforms: Vec::new(),
+            uniform_buffers: Vec::new(),
+            storage_buffers: Vec::new(),
             mesh: mesh,
+            render_state: None,
+            scissor: None,
         }
     }
 
+    /// Overrides the whole `RenderState` of shader for this draw call only.
+    pub fn set_render_state(&mut self, state: RenderState) {
+        self.set_render_state_override(state.into());
+    }
+
+    /// Overrides some fields of the `RenderState` for this draw call only. It's merged
+    /// on top of the overrides that have been set before.
+    pub fn set_render_state_override(&mut self, v: RenderStateOverride) {
+        let merged = match self.render_state {
+            Some(ref base) => base.merge(&v),
+            None => v,
+        };
+
+        self.render_state = Some(merged);
+    }
+
+    /// Overrides the scissor of surface for this draw call only.
+    pub fn set_scissor(&mut self, scissor: Scissor) {
+        self.scissor = Some(scissor);
+    }
+
     /// Bind the named field with `UniformVariable`.
     pub fn set_uniform_variable<F, T>(&mut self, field: F, variable: T)
     where
         F: Into<HashValue<str>>,
         T: Into<UniformVariable>,
     {
-        assert!(self.uniforms_len < MAX_UNIFORM_VARIABLES);
+        assert!(self.uniforms.len() < MAX_UNIFORM_VARIABLES);
 
         let field = field.into();
         let variable = variable.into();
 
-        for i in 0..self.uniforms_len {
-            if self.uniforms[i].0 == field {
-                self.uniforms[i] = (field, variable);
+        for v in &mut self.uniforms {
+            if v.0 == field {
+                v.1 = variable;
                 return;
             }
         }
 
-        self.uniforms[self.uniforms_len] = (field, variable);
-        self.uniforms_len += 1;
+        self.uniforms.push((field, variable));
+    }
+
+    /// Bind the named uniform block with `UniformBufferHandle`.
+    pub fn set_uniform_buffer<F>(&mut self, field: F, buffer: UniformBufferHandle)
+    where
+        F: Into<HashValue<str>>,
+    {
+        let field = field.into();
+
+        for v in &mut self.uniform_buffers {
+            if v.0 == field {
+                v.1 = buffer;
+                return;
+            }
+        }
+
+        self.uniform_buffers.push((field, buffer));
+    }
+
+    /// Bind the named storage block with `StorageBufferHandle`.
+    pub fn set_storage_buffer<F>(&mut self, field: F, buffer: StorageBufferHandle)
+    where
+        F: Into<HashValue<str>>,
+    {
+        set_storage_buffer(&mut self.storage_buffers, field.into(), buffer);
     }
 
     pub fn build(&mut self, index: MeshIndex) -> Result<SliceDrawCall> {
         let task = SliceDrawCall {
             shader: self.shader,
-            uniforms: &self.uniforms[0..self.uniforms_len],
+            uniforms: &self.uniforms,
+            uniform_buffers: &self.uniform_buffers,
+            storage_buffers: &self.storage_buffers,
             mesh: self.mesh,
             index: index,
+            render_state: self.render_state,
+            scissor: self.scissor,
         };
 
         Ok(task)
@@ -151,9 +297,13 @@ impl DrawCall {
     pub fn build_from(&mut self, from: usize, len: usize) -> Result<SliceDrawCall> {
         let task = SliceDrawCall {
             shader: self.shader,
-            uniforms: &self.uniforms[0..self.uniforms_len],
+            uniforms: &self.uniforms,
+            uniform_buffers: &self.uniform_buffers,
+            storage_buffers: &self.storage_buffers,
             mesh: self.mesh,
             index: MeshIndex::Ptr(from, len),
+            render_state: self.render_state,
+            scissor: self.scissor,
         };
 
         Ok(task)
@@ -162,11 +312,148 @@ impl DrawCall {
     pub fn build_sub_mesh(&mut self, index: usize) -> Result<SliceDrawCall> {
         let task = SliceDrawCall {
             shader: self.shader,
-            uniforms: &self.uniforms[0..self.uniforms_len],
+            uniforms: &self.uniforms,
+            uniform_buffers: &self.uniform_buffers,
+            storage_buffers: &self.storage_buffers,
             mesh: self.mesh,
             index: MeshIndex::SubMesh(index),
+            render_state: self.render_state,
+            scissor: self.scissor,
+        };
+
+        Ok(task)
+    }
+
+    pub fn build_instanced<'a>(
+        &'a mut self,
+        index: MeshIndex,
+        layout: AttributeLayout,
+        data: &'a [u8],
+        count: usize,
+    ) -> Result<SliceInstancedDrawCall<'a>> {
+        let task = SliceInstancedDrawCall {
+            drawcall: self.build(index)?,
+            layout: layout,
+            data: data,
+            count: count,
         };
 
         Ok(task)
     }
 }
+
+/// A dispatch of compute shader, which launches `groups` of work groups. The storage
+/// buffers written by compute shader are visible to the following draw calls and
+/// dispatches, since a memory barrier is issued after dispatching.
+///
+/// Compute shaders require the `compute_shader` capability, the dispatches are rejected
+/// on devices without it.
+#[derive(Debug, Clone)]
+pub struct Dispatch {
+    shader: ShaderHandle,
+    uniforms: Vec<(HashValue<str>, UniformVariable)>,
+    storage_buffers: Vec<(HashValue<str>, StorageBufferHandle)>,
+}
+
+impl Dispatch {
+    /// Create a new and empty dispatch of compute shader.
+    pub fn new(shader: ShaderHandle) -> Self {
+        Dispatch {
+            shader: shader,
+            uniforms: Vec::new(),
+            storage_buffers: Vec::new(),
+        }
+    }
+
+    /// Bind the named field with `UniformVariable`.
+    pub fn set_uniform_variable<F, T>(&mut self, field: F, variable: T)
+    where
+        F: Into<HashValue<str>>,
+        T: Into<UniformVariable>,
+    {
+        assert!(self.uniforms.len() < MAX_UNIFORM_VARIABLES);
+
+        let field = field.into();
+        let variable = variable.into();
+
+        for v in &mut self.uniforms {
+            if v.0 == field {
+                v.1 = variable;
+                return;
+            }
+        }
+
+        self.uniforms.push((field, variable));
+    }
+
+    /// Bind the named storage block with `StorageBufferHandle`.
+    pub fn set_storage_buffer<F>(&mut self, field: F, buffer: StorageBufferHandle)
+    where
+        F: Into<HashValue<str>>,
+    {
+        set_storage_buffer(&mut self.storage_buffers, field.into(), buffer);
+    }
+
+    pub fn build(&mut self, groups: (u32, u32, u32)) -> Result<SliceDispatch> {
+        if groups.0 == 0 || groups.1 == 0 || groups.2 == 0 {
+            bail!(format!("The work groups {:?} should not be empty.", groups));
+        }
+
+        let task = SliceDispatch {
+            shader: self.shader,
+            uniforms: &self.uniforms,
+            storage_buffers: &self.storage_buffers,
+            groups: groups,
+        };
+
+        Ok(task)
+    }
+}
+
+fn set_storage_buffer(
+    buffers: &mut Vec<(HashValue<str>, StorageBufferHandle)>,
+    field: HashValue<str>,
+    buffer: StorageBufferHandle,
+) {
+    for v in buffers.iter_mut() {
+        if v.0 == field {
+            v.1 = buffer;
+            return;
+        }
+    }
+
+    buffers.push((field, buffer));
+}
+
+/// Dispatch of compute shader.
+pub struct SliceDispatch<'a> {
+    pub(crate) shader: ShaderHandle,
+    pub(crate) uniforms: &'a [(HashValue<str>, UniformVariable)],
+    pub(crate) storage_buffers: &'a [(HashValue<str>, StorageBufferHandle)],
+    pub(crate) groups: (u32, u32, u32),
+}
+
+impl<'a> Into<Command<'a>> for SliceDispatch<'a> {
+    fn into(self) -> Command<'a> {
+        Command::Dispatch(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn order() {
+        assert_eq!(Command::order(0, 0.0), 0);
+        assert_eq!(Command::order(0, 1.0), ORDER_DEPTH_MASK);
+        assert_eq!(Command::order(1, 0.0), 1 << ORDER_DEPTH_BITS);
+        assert_eq!(Command::order(1, -1.0), Command::order(1, 0.0));
+        assert_eq!(Command::order(1, 2.0), Command::order(1, 1.0));
+
+        // Sorted by layers first, then depth.
+        assert!(Command::order(0, 1.0) < Command::order(1, 0.0));
+        assert!(Command::order(1, 0.25) < Command::order(1, 0.5));
+        assert_eq!(Command::order(!0, 1.0), !0);
+    }
+}
@@ -1,4 +1,5 @@
 use glutin;
+use resource;
 
 error_chain!{
     types {
@@ -7,6 +8,7 @@ error_chain!{
 
     links {
         Backend(super::backend::errors::Error, super::backend::errors::ErrorKind);
+        Resource(resource::errors::Error, resource::errors::ErrorKind);
     }
 
     foreign_links {
@@ -16,9 +18,15 @@ error_chain!{
 
     errors {
         InvalidHandle
+        InvalidUpdateStaticResource
+        OutOfBounds
         WindowNotExist
         CanNotDrawWithoutView
         CanNotDrawWithoutShaderState
         CanNotDrawWihtoutVertexBuffer
+        Headless {
+            description("not supported in headless mode")
+            display("The operation requires a window, which is not available in headless mode.")
+        }
     }
 }
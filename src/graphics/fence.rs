@@ -0,0 +1,17 @@
+//! Synchronization primitives between the GPU and the CPU.
+//!
+//! A fence is inserted into the command stream after all the commands of the frame
+//! it belongs to, and becomes signaled when all of them have been finished by GPU.
+//!
+//! # Threading
+//!
+//! The underlying sync objects are created, polled and deleted on the render thread
+//! only. `GraphicsSystemShared::insert_fence` could be called from any thread, but
+//! the fence will NOT be placed until the frame it belongs to is dispatched. So waiting
+//! on a fence from the logic thread in the same frame it was inserted will always time
+//! out, since the render thread is blocked by the logic thread at that moment.
+//!
+//! Fences are cleaned up automatically after they have been signaled, and querying a
+//! cleaned-up fence will always report it as signaled.
+
+impl_handle!(FenceHandle);
@@ -1,38 +1,95 @@
 //! The centralized management of video sub-system.
 
-use std::sync::{Arc, RwLock};
 use std::collections::HashMap;
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use std::thread;
 
-use utils::{HashValue, Rect};
-use resource::{Location, Registery, ResourceSystemShared};
+use math;
+use utils::{Aabb3, DataBufferPtr, Handle, HandleObjectPool, HashValue, Rect};
+use resource::{Location, Registery, ResourceFuture, ResourcePromise, ResourceSystemShared};
 
 use super::*;
 use super::errors::*;
 use super::backend::frame::*;
-use super::backend::device::Device;
+use super::backend::device::{Device, WindowTarget};
 use super::command::Command;
-use super::window::Window;
+use super::window::{Window, WindowHandle};
 
-use super::assets::texture_loader::{TextureLoader, TextureParser, TextureState};
+use super::assets::texture_loader::{TextureLoader, TextureParser, TextureReloader, TextureState};
 use super::assets::mesh_loader::{MeshLoader, MeshParser, MeshState};
 use super::assets::shader::ShaderState;
+use super::assets::shader_loader::{ShaderReloader, ShaderStage};
+use super::assets::shader_preprocessor;
+use super::screenshot;
+use super::leaks::{LeakTracker, ResourceKind, ResourceLeak};
+use super::capture::{self, CaptureContext, FrameCapture};
+
+/// The setup parameters of `GraphicsSystem`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSetup {
+    /// The initial capacity in bytes of the buffer that stores the data submitted
+    /// during a frame, e.g. uniform variables and the updates of vertices and textures.
+    /// It will be pre-grown to the peak usage of previous frames at the start of each
+    /// frame, which is reported by `GraphicsFrameInfo::frame_buffer_peak`.
+    pub frame_capacity: usize,
+}
+
+impl Default for GraphicsSetup {
+    fn default() -> Self {
+        GraphicsSetup {
+            frame_capacity: 64 * 1024,
+        }
+    }
+}
 
 /// The centralized management of video sub-system.
 pub struct GraphicsSystem {
-    window: Arc<Window>,
-    device: Device,
-    frames: Arc<DoubleFrame>,
+    backend: Option<Backend>,
+    frames: Arc<MultiFrame>,
     shared: Arc<GraphicsSystemShared>,
 
     last_dimensions: (u32, u32),
     last_hidpi: f32,
 }
 
+#[cfg(feature = "leak-detection")]
+impl Drop for GraphicsSystem {
+    fn drop(&mut self) {
+        for v in self.shared.report_leaks() {
+            eprintln!(
+                "GraphicsSystem: {:?} {:?} is leaked, created by {}.",
+                v.kind, v.handle, v.label
+            );
+        }
+    }
+}
+
+struct Backend {
+    window: Arc<Window>,
+    windows: Vec<SecondaryWindow>,
+    device: Device,
+}
+
+struct SecondaryWindow {
+    handle: WindowHandle,
+    window: Arc<Window>,
+    last_dimensions: (u32, u32),
+}
+
 impl GraphicsSystem {
     /// Create a new `GraphicsSystem` with one `Window` context.
-    pub fn new(window: Arc<window::Window>, resource: Arc<ResourceSystemShared>) -> Result<Self> {
-        let device = unsafe { Device::new() };
-        let frames = Arc::new(DoubleFrame::with_capacity(64 * 1024));
+    pub fn new(
+        window: Arc<window::Window>,
+        resource: Arc<ResourceSystemShared>,
+        setup: GraphicsSetup,
+    ) -> Result<Self> {
+        let device = unsafe { Device::new(window.capabilities()) };
+        let frames = Arc::new(MultiFrame::with_capacity(setup.frame_capacity));
 
         let err = ErrorKind::WindowNotExist;
         let dimensions = window.dimensions().ok_or(err)?;
@@ -40,29 +97,138 @@ impl GraphicsSystem {
         let err = ErrorKind::WindowNotExist;
         let dimensions_in_pixels = window.dimensions_in_pixels().ok_or(err)?;
 
-        let shared =
-            GraphicsSystemShared::new(resource, frames.clone(), dimensions, dimensions_in_pixels);
+        let shared = GraphicsSystemShared::new(
+            resource,
+            frames.clone(),
+            dimensions,
+            dimensions_in_pixels,
+            window.capabilities().clone(),
+            false,
+        );
 
         Ok(GraphicsSystem {
             last_dimensions: dimensions,
             last_hidpi: window.hidpi_factor(),
 
-            window: window,
-            device: device,
+            backend: Some(Backend {
+                window: window,
+                windows: Vec::new(),
+                device: device,
+            }),
             frames: frames,
             shared: Arc::new(shared),
         })
     }
 
+    /// Create a new `GraphicsSystem` without window, which installs a no-op backend
+    /// that validates handles and accounts for resources but issues no OpenGL calls.
+    /// This is useful to run the simulation on servers and CI machines without display.
+    pub fn headless(resource: Arc<ResourceSystemShared>, dimensions: (u32, u32)) -> Self {
+        Self::headless_with(resource, dimensions, GraphicsSetup::default())
+    }
+
+    /// Create a new headless `GraphicsSystem` with specified setup.
+    pub fn headless_with(
+        resource: Arc<ResourceSystemShared>,
+        dimensions: (u32, u32),
+        setup: GraphicsSetup,
+    ) -> Self {
+        let frames = Arc::new(MultiFrame::with_capacity(setup.frame_capacity));
+
+        let shared = GraphicsSystemShared::new(
+            resource,
+            frames.clone(),
+            dimensions,
+            dimensions,
+            Capabilities::headless(),
+            true,
+        );
+
+        GraphicsSystem {
+            last_dimensions: dimensions,
+            last_hidpi: 1.0,
+
+            backend: None,
+            frames: frames,
+            shared: Arc::new(shared),
+        }
+    }
+
     /// Returns the multi-thread friendly parts of `GraphicsSystem`.
     pub fn shared(&self) -> Arc<GraphicsSystemShared> {
         self.shared.clone()
     }
 
+    /// Returns true if the `GraphicsSystem` runs without window.
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.backend.is_none()
+    }
+
+    /// Adds a secondary window, e.g. an inspector of tools. Surfaces present to it with
+    /// `SurfaceSetup::set_window`, and its buffers are swapped in `advance` along with
+    /// the primary window.
+    ///
+    /// The window should be built with `WindowBuilder::build_shared` from the primary
+    /// window, so the textures, meshes and shaders could be drawn in both of them.
+    ///
+    /// Notes that this method MUST be called at main thread.
+    pub fn add_window(&mut self, window: Arc<Window>) -> Result<WindowHandle> {
+        let backend = match self.backend {
+            Some(ref mut backend) => backend,
+            None => bail!(ErrorKind::Headless),
+        };
+
+        let err = ErrorKind::WindowNotExist;
+        let dimensions = window.dimensions().ok_or(err)?;
+
+        let err = ErrorKind::WindowNotExist;
+        let dimensions_in_pixels = window.dimensions_in_pixels().ok_or(err)?;
+
+        let handle = self.shared
+            .windows
+            .write()
+            .unwrap()
+            .create((dimensions, dimensions_in_pixels))
+            .into();
+
+        backend.windows.push(SecondaryWindow {
+            handle: handle,
+            window: window,
+            last_dimensions: dimensions,
+        });
+
+        Ok(handle)
+    }
+
+    /// Removes the secondary window, and returns it if exists. The surfaces presenting
+    /// to it draw nothing until they are deleted.
+    ///
+    /// Notes that this method MUST be called at main thread.
+    pub fn remove_window(&mut self, handle: WindowHandle) -> Option<Arc<Window>> {
+        self.shared.windows.write().unwrap().free(handle);
+
+        let backend = self.backend.as_mut()?;
+        let index = backend.windows.iter().position(|v| v.handle == handle)?;
+        backend.device.remove_window(handle);
+        Some(backend.windows.remove(index).window)
+    }
+
+    /// Gets the secondary window.
+    pub fn window(&self, handle: WindowHandle) -> Option<Arc<Window>> {
+        let backend = self.backend.as_ref()?;
+        backend
+            .windows
+            .iter()
+            .find(|v| v.handle == handle)
+            .map(|v| v.window.clone())
+    }
+
     /// Swap internal commands frame.
     #[inline]
     pub fn swap_frames(&self) {
         self.frames.swap_frames();
+        *self.shared.frame_index.write().unwrap() += 1;
     }
 
     /// Advance to next frame.
@@ -72,57 +238,181 @@ impl GraphicsSystem {
     pub fn advance(&mut self) -> Result<GraphicsFrameInfo> {
         use std::time;
 
-        unsafe {
-            let ts = time::Instant::now();
+        let ts = time::Instant::now();
+        let mut info = GraphicsFrameInfo::default();
 
-            let err = ErrorKind::WindowNotExist;
-            let dimensions = self.window.dimensions().ok_or(err)?;
+        if let Some(ref mut backend) = self.backend {
+            unsafe {
+                let err = ErrorKind::WindowNotExist;
+                let dimensions = backend.window.dimensions().ok_or(err)?;
 
-            let err = ErrorKind::WindowNotExist;
-            let dimensions_in_pixels = self.window.dimensions_in_pixels().ok_or(err)?;
+                let err = ErrorKind::WindowNotExist;
+                let dimensions_in_pixels = backend.window.dimensions_in_pixels().ok_or(err)?;
 
-            let hidpi = self.window.hidpi_factor();
+                let hidpi = backend.window.hidpi_factor();
 
-            // Resize the window, which would recreate the underlying framebuffer.
-            if dimensions != self.last_dimensions || self.last_hidpi != hidpi {
-                self.last_dimensions = dimensions;
-                self.last_hidpi = hidpi;
-                self.window.resize(dimensions);
-            }
+                // The framebuffer with zero dimensions is invalid, so nothing will be drawn
+                // when the window is minimized, until it has been restored.
+                let minimized = dimensions.0 == 0 || dimensions.1 == 0;
 
-            *self.shared.dimensions.write().unwrap() = (dimensions, dimensions_in_pixels);
+                if !minimized {
+                    // Resize the window, which would recreate the underlying framebuffer.
+                    if dimensions != self.last_dimensions || self.last_hidpi != hidpi {
+                        self.last_dimensions = dimensions;
+                        self.last_hidpi = hidpi;
+                        backend.window.resize(dimensions);
+                    }
+
+                    *self.shared.dimensions.write().unwrap() = (dimensions, dimensions_in_pixels);
+                }
+
+                let mut targets = vec![WindowTarget {
+                    handle: None,
+                    window: &backend.window,
+                    dimensions: dimensions,
+                    hidpi: hidpi,
+                }];
+
+                // The secondary windows which are minimized or closed are skipped.
+                for v in &mut backend.windows {
+                    let dimensions = match v.window.dimensions() {
+                        Some(dimensions) if dimensions.0 > 0 && dimensions.1 > 0 => dimensions,
+                        _ => continue,
+                    };
+
+                    if dimensions != v.last_dimensions {
+                        v.last_dimensions = dimensions;
+                        v.window.resize(dimensions);
+                    }
+
+                    if let Some(pixels) = v.window.dimensions_in_pixels() {
+                        let mut windows = self.shared.windows.write().unwrap();
+                        if let Some(value) = windows.get_mut(v.handle) {
+                            *value = (dimensions, pixels);
+                        }
+                    }
 
-            {
-                self.device.run_one_frame()?;
+                    targets.push(WindowTarget {
+                        handle: Some(v.handle),
+                        window: &v.window,
+                        dimensions: dimensions,
+                        hidpi: v.window.hidpi_factor(),
+                    });
+                }
 
                 {
-                    let mut frame = self.frames.back();
-                    frame.dispatch(&mut self.device, dimensions, hidpi)?;
-                    frame.clear();
+                    backend.device.run_one_frame()?;
+
+                    {
+                        let mut frame = self.frames.back();
+
+                        // Resources are still created, updated and deleted as usual,
+                        // but the drawcalls and scissor changes are discarded.
+                        if minimized {
+                            frame.tasks.retain(|v| match v.2 {
+                                FrameTask::DrawCall(_)
+                                | FrameTask::InstancedDrawCall(..)
+                                | FrameTask::UpdateSurface(_) => false,
+                                _ => true,
+                            });
+                        }
+
+                        Self::capture(&self.shared, &mut frame);
+                        frame.dispatch(&mut backend.device, &targets)?;
+
+                        for (path, err) in frame.reload_failures.drain(..) {
+                            let err = err.into();
+                            self.shared.resource.notify_reload_failure(&path, &err);
+                        }
+
+                        // Failed tasks are skipped, so the rest of frame still presents.
+                        info.errors = frame.failures.len() as u32;
+                        *self.shared.failures.lock().unwrap() = frame.failures.drain(..).collect();
+
+                        {
+                            let mut shaders = self.shared.shaders.write().unwrap();
+                            for (handle, failed) in frame.shader_failures.drain(..) {
+                                if let Some(state) = shaders.get_mut(handle.into()) {
+                                    state.failed = failed;
+                                }
+                            }
+                        }
+
+                        frame.clear();
+                    }
+
+                    // Recycles the signaled fences.
+                    let fences = backend.device.poll_fences()?;
+                    self.shared.recycle_fences(&fences);
                 }
-            }
 
-            self.window.swap_buffers()?;
-            let mut info = GraphicsFrameInfo::default();
-            {
-                let v = self.device.frame_info();
+                if !minimized {
+                    // Swapping buffers requires the context of window to be current on
+                    // some platforms.
+                    for v in &targets[1..] {
+                        v.window.make_current()?;
+                        v.window.swap_buffers()?;
+                    }
+
+                    if targets.len() > 1 {
+                        backend.window.make_current()?;
+                    }
+
+                    backend.window.swap_buffers()?;
+                }
+
+                let v = backend.device.frame_info();
                 info.drawcall = v.drawcall;
                 info.triangles = v.triangles;
             }
+        } else {
+            let mut frame = self.frames.back();
+            Self::capture(&self.shared, &mut frame);
+            let (drawcall, fences) = frame.skip();
+            unsafe { frame.clear() };
 
-            {
-                let s = &self.shared;
-                info.alive_surfaces = Self::clear(&mut s.surfaces.write().unwrap());
-                info.alive_shaders = Self::clear(&mut s.shaders.write().unwrap());
-                info.alive_frame_buffers = Self::clear(&mut s.framebuffers.write().unwrap());
-                info.alive_meshes = Self::clear(&mut s.meshes.write().unwrap());
-                info.alive_textures = Self::clear(&mut s.textures.write().unwrap());
-                info.alive_render_buffers = Self::clear(&mut s.render_buffers.write().unwrap());
-            }
+            // There is nothing to wait in headless mode.
+            self.shared.recycle_fences(&fences);
+
+            info.drawcall = drawcall;
+        }
+
+        {
+            let s = &self.shared;
+            info.alive_surfaces = Self::clear(&mut s.surfaces.write().unwrap());
+            info.alive_shaders = Self::clear(&mut s.shaders.write().unwrap());
+            info.alive_frame_buffers = Self::clear(&mut s.framebuffers.write().unwrap());
+            info.alive_meshes = Self::clear(&mut s.meshes.write().unwrap());
+            info.alive_textures = Self::clear(&mut s.textures.write().unwrap());
+            info.alive_render_buffers = Self::clear(&mut s.render_buffers.write().unwrap());
+            info.alive_uniform_buffers = Self::clear(&mut s.uniform_buffers.write().unwrap());
+            info.alive_storage_buffers = Self::clear(&mut s.storage_buffers.write().unwrap());
+        }
+
+        info.frame_buffer_peak = self.frames.peak();
+        info.duration = time::Instant::now() - ts;
+        Ok(info)
+    }
+
+    /// Blocks the render thread until the fence has been signaled or `timeout`
+    /// elapsed. Returns true if the fence has been signaled.
+    ///
+    /// Notes that this method MUST be called at main thread.
+    pub fn wait_fence(&mut self, handle: FenceHandle, timeout: Duration) -> Result<bool> {
+        if !self.shared.fences.read().unwrap().is_alive(handle.into()) {
+            return Ok(true);
+        }
+
+        let signaled = match self.backend {
+            Some(ref mut backend) => unsafe { backend.device.wait_fence(handle, timeout)? },
+            None => true,
+        };
 
-            info.duration = time::Instant::now() - ts;
-            Ok(info)
+        if signaled {
+            self.shared.recycle_fences(&[handle]);
         }
+
+        Ok(signaled)
     }
 
     fn clear<T>(v: &mut Registery<T>) -> u32
@@ -132,41 +422,117 @@ impl GraphicsSystem {
         v.clear();
         v.len() as u32
     }
+
+    /// Sends the dump of frame to the requests of capturing, before it's dispatched.
+    fn capture(shared: &GraphicsSystemShared, frame: &mut Frame) {
+        let captures = mem::replace(&mut frame.captures, Vec::new());
+        for (index, tx) in captures {
+            // The receiver might be dropped, it's fine to ignore the result.
+            let _ = tx.send(capture::capture(shared, frame, index));
+        }
+    }
+}
+
+/// The loading state of mesh, and the usage hints of its vertex and index buffers
+/// which are known once the mesh is created.
+struct MeshEntry {
+    state: Arc<RwLock<MeshState>>,
+    future: ResourceFuture,
+    hints: (BufferHint, BufferHint),
+    /// The vertex layout and the number of vertices, which are used to keep the
+    /// bounding box up to date when the vertex buffer is updated. It's `None` if the
+    /// bounding box is specified by user, or the layout is unknown until loaded.
+    bounds: Option<(VertexLayout, usize)>,
+}
+
+impl MeshEntry {
+    fn new(state: Arc<RwLock<MeshState>>, future: ResourceFuture, setup: &MeshSetup) -> Self {
+        MeshEntry {
+            state: state,
+            future: future,
+            hints: (setup.hint, setup.index_buffer_hint()),
+            bounds: None,
+        }
+    }
+}
+
+/// The loading state of texture.
+struct TextureEntry {
+    state: Arc<RwLock<TextureState>>,
+    future: ResourceFuture,
+    /// The setup of render texture, which is required to validate the reading.
+    render: Option<RenderTextureSetup>,
+}
+
+impl TextureEntry {
+    fn ready() -> Self {
+        TextureEntry {
+            state: Arc::new(RwLock::new(TextureState::Ready)),
+            future: ResourceFuture::ready(),
+            render: None,
+        }
+    }
 }
 
 /// The multi-thread friendly parts of `GraphicsSystem`.
 pub struct GraphicsSystemShared {
     resource: Arc<ResourceSystemShared>,
-    frames: Arc<DoubleFrame>,
+    frames: Arc<MultiFrame>,
     dimensions: RwLock<((u32, u32), (u32, u32))>,
+    windows: RwLock<HandleObjectPool<((u32, u32), (u32, u32))>>,
+    frame_index: RwLock<u64>,
+    capabilities: Capabilities,
+    headless: bool,
 
     surfaces: RwLock<Registery<()>>,
     shaders: RwLock<Registery<ShaderState>>,
+    shader_sources: RwLock<HashMap<ShaderHandle, Arc<Mutex<ShaderSetup>>>>,
     framebuffers: RwLock<Registery<()>>,
     render_buffers: RwLock<Registery<()>>,
-    meshes: RwLock<Registery<Arc<RwLock<MeshState>>>>,
-    textures: RwLock<Registery<Arc<RwLock<TextureState>>>>,
+    meshes: RwLock<Registery<MeshEntry>>,
+    textures: RwLock<Registery<TextureEntry>>,
+    uniform_buffers: RwLock<Registery<UniformBufferSetup>>,
+    storage_buffers: RwLock<Registery<StorageBufferSetup>>,
+    fences: RwLock<Registery<()>>,
+    fences_signaled: (Mutex<()>, Condvar),
+    failures: Mutex<Vec<String>>,
+
+    leaks: LeakTracker,
 }
 
 impl GraphicsSystemShared {
     /// Create a new `GraphicsSystem` with one `Window` context.
     fn new(
         resource: Arc<ResourceSystemShared>,
-        frames: Arc<DoubleFrame>,
+        frames: Arc<MultiFrame>,
         dimensions: (u32, u32),
         dimensions_in_pixels: (u32, u32),
+        capabilities: Capabilities,
+        headless: bool,
     ) -> Self {
         GraphicsSystemShared {
             resource: resource,
             frames: frames,
             dimensions: RwLock::new((dimensions, dimensions_in_pixels)),
+            windows: RwLock::new(HandleObjectPool::new()),
+            frame_index: RwLock::new(0),
+            capabilities: capabilities,
+            headless: headless,
 
             surfaces: RwLock::new(Registery::new()),
             shaders: RwLock::new(Registery::new()),
+            shader_sources: RwLock::new(HashMap::new()),
             framebuffers: RwLock::new(Registery::new()),
             render_buffers: RwLock::new(Registery::new()),
             meshes: RwLock::new(Registery::new()),
             textures: RwLock::new(Registery::new()),
+            uniform_buffers: RwLock::new(Registery::new()),
+            storage_buffers: RwLock::new(Registery::new()),
+            fences: RwLock::new(Registery::new()),
+            fences_signaled: (Mutex::new(()), Condvar::new()),
+            failures: Mutex::new(Vec::new()),
+
+            leaks: LeakTracker::default(),
         }
     }
 
@@ -187,9 +553,55 @@ impl GraphicsSystemShared {
         self.dimensions.read().unwrap().1
     }
 
+    /// Returns the size in points of the client area of the secondary window, which is
+    /// updated once per frame. `None` is returned if the window has been removed.
+    #[inline]
+    pub fn window_dimensions(&self, handle: WindowHandle) -> Option<(u32, u32)> {
+        self.windows.read().unwrap().get(handle).map(|v| v.0)
+    }
+
+    /// Returns the size in pixels of the client area of the secondary window.
+    #[inline]
+    pub fn window_dimensions_in_pixels(&self, handle: WindowHandle) -> Option<(u32, u32)> {
+        self.windows.read().unwrap().get(handle).map(|v| v.1)
+    }
+
+    /// Returns the capabilities and limits of the underlying OpenGL implementation,
+    /// which are captured once when the `GraphicsSystem` is created.
+    #[inline]
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Returns true if the graphics system runs without window, nothing will be drawn
+    /// in this mode.
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.headless
+    }
+
+    /// Returns the index of frame that commands are submitted into currently.
+    #[inline]
+    pub fn frame_index(&self) -> u64 {
+        *self.frame_index.read().unwrap()
+    }
+
+    /// Returns the failures of backend tasks during last frame, which are described
+    /// with the tasks. They are counted by `GraphicsFrameInfo::errors`.
+    pub fn failures(&self) -> Vec<String> {
+        self.failures.lock().unwrap().clone()
+    }
+
     /// Submit a task into named bucket.
     ///
-    /// Tasks inside bucket will be executed in sequential order.
+    /// Tasks inside bucket are sorted by `o` in ascending order, unless the surface is
+    /// sequential. The sorting is stable, so tasks with the same order are executed in
+    /// the order they are submitted. `Command::order` composes the order from a layer
+    /// and depth.
+    ///
+    /// The `MeshIndex` of draw calls is validated against the index buffer of mesh. The
+    /// draw calls of meshes that are still loading, or that failed to load, are skipped
+    /// silently; the failures of loading are reported by the futures of meshes.
     pub fn submit<'a, T1, T2>(&self, s: SurfaceHandle, o: T1, task: T2) -> Result<()>
     where
         T1: Into<u64>,
@@ -202,10 +614,12 @@ impl GraphicsSystemShared {
         let o = o.into();
         match task.into() {
             Command::DrawCall(dc) => self.submit_drawcall(s, o, dc),
+            Command::InstancedDrawCall(idc) => self.submit_instanced_drawcall(s, o, idc),
             Command::VertexBufferUpdate(vbu) => self.submit_update_vertex_buffer(s, o, vbu),
             Command::IndexBufferUpdate(ibu) => self.submit_update_index_buffer(s, o, ibu),
             Command::TextureUpdate(tu) => self.submit_update_texture(s, o, tu),
             Command::SetScissor(sc) => self.submit_set_scissor(s, o, sc),
+            Command::Dispatch(dc) => self.submit_dispatch(s, o, dc),
         }
     }
 
@@ -215,42 +629,271 @@ impl GraphicsSystemShared {
         order: u64,
         dc: command::SliceDrawCall<'a>,
     ) -> Result<()> {
-        if !self.meshes.read().unwrap().is_alive(dc.mesh.into()) {
-            bail!("Undefined mesh handle.");
+        if !self.check_mesh_index(dc.mesh, dc.index)? || self.is_shader_failed(dc.shader) {
+            return Ok(());
+        }
+
+        let mut frame = self.frames.front();
+        let dc = self.pack_drawcall(&mut frame, &dc)?;
+        frame.tasks.push((surface, order, FrameTask::DrawCall(dc)));
+        Ok(())
+    }
+
+    fn submit_instanced_drawcall<'a>(
+        &self,
+        surface: SurfaceHandle,
+        order: u64,
+        idc: command::SliceInstancedDrawCall<'a>,
+    ) -> Result<()> {
+        if !self.capabilities.support_instancing() {
+            bail!(
+                "Instanced draw call is not supported by the device, which requires OpenGL \
+                 3.3, OpenGL ES 3.0 or GL_ARB_instanced_arrays."
+            );
+        }
+
+        if !self.check_mesh_index(idc.drawcall.mesh, idc.drawcall.index)? {
+            return Ok(());
+        }
+
+        if let Some(shader) = self.shaders.read().unwrap().get(idc.drawcall.shader.into()) {
+            for (name, _) in idc.layout.iter() {
+                if !shader.layout.contains(name) {
+                    bail!(format!("Undefined instance attribute: {:?}.", name));
+                }
+            }
+        } else {
+            bail!("Undefined shader state handle.");
+        }
+
+        if let Some(layout) = self.mesh_layout(idc.drawcall.mesh) {
+            for (name, _) in idc.layout.iter() {
+                if layout.element(name).is_some() {
+                    bail!(format!(
+                        "Instance attribute {:?} collides with the vertex layout of mesh.",
+                        name
+                    ));
+                }
+            }
+        }
+
+        let components: usize = idc.layout.iter().map(|(_, size)| size as usize).sum();
+        if components == 0 {
+            bail!("Empty instance attribute layout.");
+        }
+
+        let len = components * ::std::mem::size_of::<f32>() * idc.count;
+        if len > idc.data.len() {
+            bail!(format!(
+                "Instance buffer is too small: {} bytes for {} instances.",
+                idc.data.len(),
+                idc.count
+            ));
+        }
+
+        if idc.count == 0 || self.is_shader_failed(idc.drawcall.shader) {
+            return Ok(());
         }
 
         let mut frame = self.frames.front();
-        let uniforms = {
-            let mut pack = Vec::new();
+        let dc = self.pack_drawcall(&mut frame, &idc.drawcall)?;
+        let ptr = frame.buf.extend_from_slice(&idc.data[0..len]);
+        let task = FrameTask::InstancedDrawCall(dc, idc.layout, ptr, idc.count);
+        frame.tasks.push((surface, order, task));
+        Ok(())
+    }
+
+    fn pack_drawcall<'a>(
+        &self,
+        frame: &mut Frame,
+        dc: &command::SliceDrawCall<'a>,
+    ) -> Result<FrameDrawCall> {
+        if let Some(ref v) = dc.render_state {
+            self.check_render_state_override(v)?;
+        }
+
+        let uniforms = self.pack_uniforms(frame, dc.shader, false, dc.uniforms)?;
+
+        let uniform_buffers = {
             if let Some(shader) = self.shaders.read().unwrap().get(dc.shader.into()) {
-                for &(n, v) in dc.uniforms {
-                    if let Some(&tt) = shader.uniform_variables.get(&n) {
-                        if tt == v.variable_type() {
-                            pack.push((n, frame.buf.extend(&v)));
-                        } else {
-                            let name = &shader.uniform_variable_names[&n];
-                            bail!(format!("Unmatched uniform variable: {:?}.", name));
-                        }
-                    } else {
-                        let name = &shader.uniform_variable_names[&n];
-                        bail!(format!("Undefined uniform variable: {:?}.", name));
+                let buffers = self.uniform_buffers.read().unwrap();
+                for &(n, handle) in dc.uniform_buffers {
+                    match buffers.get(handle.into()) {
+                        Some(setup) => shader.validate_uniform_block(n, setup.size)?,
+                        None => bail!("Undefined uniform buffer handle."),
+                    }
+                }
+
+                // The binding points of uniform blocks are shared by all the programs,
+                // so a block without uniform buffer might read the one of other shaders.
+                for (n, name) in &shader.uniform_block_names {
+                    if !dc.uniform_buffers.iter().any(|v| v.0 == *n) {
+                        bail!(format!("Uniform block {:?} is not bound.", name));
                     }
                 }
-            } else {
-                bail!("Undefined shader state handle.");
             }
 
-            frame.buf.extend_from_slice(&pack)
+            let align = mem::align_of::<(HashValue<str>, UniformBufferHandle)>();
+            frame.buf.extend_from_slice_aligned(dc.uniform_buffers, align)
         };
 
-        let dc = FrameDrawCall {
+        let storage_buffers = self.pack_storage_buffers(frame, dc.shader, dc.storage_buffers)?;
+
+        Ok(FrameDrawCall {
             shader: dc.shader,
             uniforms: uniforms,
+            uniform_buffers: uniform_buffers,
+            storage_buffers: storage_buffers,
             mesh: dc.mesh,
             index: dc.index,
+            render_state: dc.render_state,
+            scissor: dc.scissor,
+        })
+    }
+
+    /// Validates and packs the uniform variables into frame. Render shaders could only
+    /// be used by draw calls, and compute shaders by dispatches.
+    fn pack_uniforms(
+        &self,
+        frame: &mut Frame,
+        shader: ShaderHandle,
+        compute: bool,
+        uniforms: &[(HashValue<str>, UniformVariable)],
+    ) -> Result<DataBufferPtr<[(HashValue<str>, DataBufferPtr<FrameUniformVariable>)]>> {
+        let mut pack = Vec::new();
+        if let Some(state) = self.shaders.read().unwrap().get(shader.into()) {
+            if state.compute && !compute {
+                bail!("Compute shader could only be used with `Command::Dispatch`.");
+            }
+
+            if !state.compute && compute {
+                bail!("Render shader could not be dispatched.");
+            }
+
+            for &(n, ref v) in uniforms {
+                state.validate_uniform_variable(n, v)?;
+                let v = FrameUniformVariable::new(v, &mut frame.buf);
+                let align = mem::align_of::<FrameUniformVariable>();
+                pack.push((n, frame.buf.extend_aligned(&v, align)));
+            }
+        } else {
+            bail!("Undefined shader state handle.");
+        }
+
+        let align = mem::align_of::<(HashValue<str>, DataBufferPtr<FrameUniformVariable>)>();
+        Ok(frame.buf.extend_from_slice_aligned(&pack, align))
+    }
+
+    fn pack_storage_buffers(
+        &self,
+        frame: &mut Frame,
+        shader: ShaderHandle,
+        storage_buffers: &[(HashValue<str>, StorageBufferHandle)],
+    ) -> Result<DataBufferPtr<[(HashValue<str>, StorageBufferHandle)]>> {
+        if let Some(state) = self.shaders.read().unwrap().get(shader.into()) {
+            let buffers = self.storage_buffers.read().unwrap();
+            for &(n, handle) in storage_buffers {
+                if !buffers.is_alive(handle.into()) {
+                    bail!("Undefined storage buffer handle.");
+                }
+
+                state.validate_storage_block(n)?;
+            }
+
+            for (n, name) in &state.storage_block_names {
+                if !storage_buffers.iter().any(|v| v.0 == *n) {
+                    bail!(format!("Storage block {:?} is not bound.", name));
+                }
+            }
+        }
+
+        let align = mem::align_of::<(HashValue<str>, StorageBufferHandle)>();
+        Ok(frame.buf.extend_from_slice_aligned(storage_buffers, align))
+    }
+
+    fn submit_dispatch<'a>(
+        &self,
+        surface: SurfaceHandle,
+        order: u64,
+        dc: command::SliceDispatch<'a>,
+    ) -> Result<()> {
+        if !self.capabilities.support_compute_shader() {
+            bail!("Compute shader is not supported by the device.");
+        }
+
+        if self.is_shader_failed(dc.shader) {
+            return Ok(());
+        }
+
+        let mut frame = self.frames.front();
+        let uniforms = self.pack_uniforms(&mut frame, dc.shader, true, dc.uniforms)?;
+        let storage_buffers =
+            self.pack_storage_buffers(&mut frame, dc.shader, dc.storage_buffers)?;
+
+        let task = FrameDispatch {
+            shader: dc.shader,
+            uniforms: uniforms,
+            storage_buffers: storage_buffers,
+            groups: dc.groups,
         };
 
-        frame.tasks.push((surface, order, FrameTask::DrawCall(dc)));
+        frame.tasks.push((surface, order, FrameTask::Dispatch(task)));
+        Ok(())
+    }
+
+    /// Validates that the range of `index` lies within the index buffer of mesh.
+    /// Returns false if the mesh is not ready or failed to load, so the draw call should
+    /// be skipped.
+    fn check_mesh_index(&self, mesh: MeshHandle, index: MeshIndex) -> Result<bool> {
+        let meshes = self.meshes.read().unwrap();
+        let entry = match meshes.get(mesh.into()) {
+            Some(entry) => entry,
+            None => bail!("Undefined mesh handle."),
+        };
+
+        let state = entry.state.read().unwrap();
+        match *state {
+            MeshState::Ready(_, _, num_idxes, ref sub_mesh_offsets) => {
+                if let Err(err) = index.range(num_idxes, sub_mesh_offsets) {
+                    bail!(format!("Invalid {:?} of mesh {:?}. {}", index, mesh, err));
+                }
+
+                Ok(true)
+            }
+            // The failures of loading are reported by the futures of meshes.
+            MeshState::NotReady | MeshState::Err(_) => Ok(false),
+        }
+    }
+
+    /// Gets the vertex layout of mesh. Returns `None` if the mesh is not ready.
+    fn mesh_layout(&self, mesh: MeshHandle) -> Option<VertexLayout> {
+        let meshes = self.meshes.read().unwrap();
+        let entry = meshes.get(mesh.into())?;
+        let state = entry.state.read().unwrap();
+
+        match *state {
+            MeshState::Ready(_, layout, _, _) => Some(layout),
+            _ => None,
+        }
+    }
+
+    fn check_render_state(&self, state: &RenderState) -> Result<()> {
+        self.check_polygon_mode(state.polygon_mode)
+    }
+
+    fn check_render_state_override(&self, v: &RenderStateOverride) -> Result<()> {
+        if let Some(mode) = v.polygon_mode {
+            self.check_polygon_mode(mode)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_polygon_mode(&self, mode: PolygonMode) -> Result<()> {
+        if mode != PolygonMode::Fill && !self.capabilities.support_polygon_mode() {
+            bail!(format!("{:?} is not supported by the device.", mode));
+        }
+
         Ok(())
     }
 
@@ -280,15 +923,13 @@ impl GraphicsSystemShared {
             bail!("Undefined surface handle.");
         }
 
-        if self.meshes.read().unwrap().is_alive(vbu.mesh.into()) {
-            let mut frame = self.frames.front();
-            let ptr = frame.buf.extend_from_slice(vbu.data);
-            let task = FrameTask::UpdateVertexBuffer(vbu.mesh, vbu.offset, ptr);
-            frame.tasks.push((surface, order, task));
-            Ok(())
-        } else {
-            bail!(ErrorKind::InvalidHandle);
-        }
+        self.check_mesh_update(vbu.mesh, false)?;
+
+        let mut frame = self.frames.front();
+        let ptr = frame.buf.extend_from_slice(vbu.data);
+        let task = FrameTask::UpdateVertexBuffer(vbu.mesh, vbu.offset, ptr);
+        frame.tasks.push((surface, order, task));
+        Ok(())
     }
 
     fn submit_update_index_buffer(
@@ -301,15 +942,13 @@ impl GraphicsSystemShared {
             bail!("Undefined surface handle.");
         }
 
-        if self.meshes.read().unwrap().is_alive(ibu.mesh.into()) {
-            let mut frame = self.frames.front();
-            let ptr = frame.buf.extend_from_slice(ibu.data);
-            let task = FrameTask::UpdateIndexBuffer(ibu.mesh, ibu.offset, ptr);
-            frame.tasks.push((surface, order, task));
-            Ok(())
-        } else {
-            bail!(ErrorKind::InvalidHandle);
-        }
+        self.check_mesh_update(ibu.mesh, true)?;
+
+        let mut frame = self.frames.front();
+        let ptr = frame.buf.extend_from_slice(ibu.data);
+        let task = FrameTask::UpdateIndexBuffer(ibu.mesh, ibu.offset, ptr);
+        frame.tasks.push((surface, order, task));
+        Ok(())
     }
 
     fn submit_update_texture(
@@ -322,11 +961,11 @@ impl GraphicsSystemShared {
             bail!("Undefined surface handle.");
         }
 
-        if let Some(state) = self.textures.read().unwrap().get(tu.texture.into()) {
-            if TextureState::Ready == *state.read().unwrap() {
+        if let Some(entry) = self.textures.read().unwrap().get(tu.texture.into()) {
+            if TextureState::Ready == *entry.state.read().unwrap() {
                 let mut frame = self.frames.front();
                 let ptr = frame.buf.extend_from_slice(tu.data);
-                let task = FrameTask::UpdateTexture(tu.texture, tu.rect, ptr);
+                let task = FrameTask::UpdateTexture(tu.texture, tu.layer, tu.rect, ptr);
                 frame.tasks.push((surface, order, task));
             }
 
@@ -340,6 +979,27 @@ impl GraphicsSystemShared {
 impl GraphicsSystemShared {
     /// Creates an view with `SurfaceSetup`.
     pub fn create_surface(&self, setup: SurfaceSetup) -> Result<SurfaceHandle> {
+        if let Some(window) = setup.window {
+            if !self.windows.read().unwrap().is_alive(window) {
+                bail!("Undefined window handle.");
+            }
+
+            // Framebuffers are containers that could not be shared between contexts.
+            if setup.framebuffer.is_some() || setup.samples > 1 {
+                bail!("Surface of secondary window could only draw into its default framebuffer.");
+            }
+        }
+
+        if setup.reversed_z && !self.capabilities.support_clip_control() {
+            bail!("Reverse-Z requires glClipControl (OpenGL 4.5 or GL_ARB_clip_control).");
+        }
+
+        // The contents of render target are not loaded into the multisampled framebuffer,
+        // which would be drawn over with undefined values otherwise.
+        if setup.samples > 1 && (setup.clear_color.is_none() || setup.clear_depth.is_none()) {
+            bail!("Multisampled surface should clear its color and depth buffers.");
+        }
+
         let location = Location::unique("");
         let handle = self.surfaces.write().unwrap().create(location, ()).into();
 
@@ -348,6 +1008,7 @@ impl GraphicsSystemShared {
             self.frames.front().pre.push(task);
         }
 
+        self.leaks.track(ResourceKind::Surface, handle, location, "create_surface");
         Ok(handle)
     }
 
@@ -364,6 +1025,16 @@ impl GraphicsSystemShared {
         }
     }
 
+    /// Deletes surface objects in batch. It's equivalent to calling `delete_surface`
+    /// on each of them, but the lock is only acquired once.
+    pub fn delete_surfaces(&self, handles: &[SurfaceHandle]) {
+        let handles = Self::dec_rcs(&mut self.surfaces.write().unwrap(), handles);
+        if !handles.is_empty() {
+            let task = PostFrameTask::DeleteSurfaceBatch(handles);
+            self.frames.front().post.push(task);
+        }
+    }
+
     /// Lookup shader object from location.
     pub fn lookup_shader_from(&self, location: Location) -> Option<ShaderHandle> {
         self.shaders
@@ -383,6 +1054,53 @@ impl GraphicsSystemShared {
             );
         }
 
+        for (name, &tt) in &setup.uniform_variables {
+            match tt {
+                UniformVariableType::Vector3fArray(0) | UniformVariableType::Matrix4fArray(0) => {
+                    bail!(format!("Uniform array {:?} should have at least one element.", name));
+                }
+                _ => {}
+            }
+        }
+
+        if !setup.uniform_blocks.is_empty() {
+            if !self.capabilities.support_uniform_buffer() {
+                bail!("Uniform buffer is not supported by the device.");
+            }
+
+            let max_blocks = self.capabilities.max_indexed_uniform_buffer as usize;
+            if setup.uniform_blocks.len() > max_blocks {
+                bail!(format!("Too many uniform blocks (> {:?}).", max_blocks));
+            }
+
+            for (name, &size) in &setup.uniform_blocks {
+                if size == 0 {
+                    bail!(format!("Uniform block {:?} should have at least one byte.", name));
+                }
+            }
+        }
+
+        if !setup.storage_blocks.is_empty() {
+            if !self.capabilities.support_compute_shader() {
+                bail!("Storage buffer is not supported by the device.");
+            }
+
+            let max_blocks = self.capabilities.max_shader_storage_buffer_bindings as usize;
+            if setup.storage_blocks.len() > max_blocks {
+                bail!(format!("Too many storage blocks (> {:?}).", max_blocks));
+            }
+        }
+
+        self.check_render_state(&setup.render_state)?;
+
+        let max_outputs = self.capabilities.max_color_attachments as usize;
+        if setup.outputs == 0 || setup.outputs > max_outputs {
+            bail!(format!(
+                "The number of fragment outputs {} should be in range [1, {}].",
+                setup.outputs, max_outputs
+            ));
+        }
+
         if setup.vs.len() == 0 {
             bail!("Vertex shader is required to describe a proper render pipeline.");
         }
@@ -391,6 +1109,8 @@ impl GraphicsSystemShared {
             bail!("Fragment shader is required to describe a proper render pipeline.");
         }
 
+        let setup = self.preprocess_shader(&setup)?;
+
         let handle = {
             let mut shaders = self.shaders.write().unwrap();
             if let Some(handle) = shaders.lookup(location) {
@@ -398,40 +1118,163 @@ impl GraphicsSystemShared {
                 return Ok(handle.into());
             }
 
-            let mut uniform_variable_names = HashMap::new();
-            let mut uniform_variables = HashMap::new();
-            for (name, v) in &setup.uniform_variables {
-                let k: HashValue<str> = name.into();
-                uniform_variables.insert(k, *v);
-                uniform_variable_names.insert(k, name.clone());
-            }
-
-            let shader_state = ShaderState {
-                render_state: setup.render_state,
-                layout: setup.layout,
-                uniform_variables: uniform_variables,
-                uniform_variable_names: uniform_variable_names,
-            };
-
+            let shader_state = ShaderState::new(&setup);
             let handle = shaders.create(location, shader_state).into();
             handle
         };
 
         let task = PreFrameTask::CreatePipeline(handle, setup);
         self.frames.front().pre.push(task);
+        self.leaks.track(ResourceKind::Shader, handle, location, "create_shader");
         Ok(handle)
     }
 
+    /// Gets a copy of `setup` with preprocessed sources, which are the ones handed to
+    /// the driver by `create_shader`. It's useful for debugging compile failures.
+    ///
+    /// The `#include "path"` directives are replaced with the sources of files in the
+    /// resource filesystem recursively, where the relative paths in included files are
+    /// resolved against the including file. And the `defines` are injected right after the `#version`
+    /// directive.
+    pub fn preprocess_shader(&self, setup: &ShaderSetup) -> Result<ShaderSetup> {
+        shader_preprocessor::preprocess_shader(&self.resource, setup)
+    }
+
+    /// Create a compute shader, which is launched with `Command::Dispatch` outside of
+    /// the render pipeline. It's deleted with `delete_shader` as well.
+    pub fn create_compute_shader(
+        &self,
+        location: Location,
+        setup: ComputeShaderSetup,
+    ) -> Result<ShaderHandle> {
+        if !self.capabilities.support_compute_shader() {
+            bail!("Compute shader is not supported by the device.");
+        }
+
+        if setup.uniform_variables.len() > MAX_UNIFORM_VARIABLES {
+            bail!(
+                "Too many uniform variables (>= {:?}).",
+                MAX_UNIFORM_VARIABLES
+            );
+        }
+
+        let max_blocks = self.capabilities.max_shader_storage_buffer_bindings as usize;
+        if setup.storage_blocks.len() > max_blocks {
+            bail!(format!("Too many storage blocks (> {:?}).", max_blocks));
+        }
+
+        if setup.cs.len() == 0 {
+            bail!("Compute shader requires source.");
+        }
+
+        let setup = shader_preprocessor::preprocess_compute_shader(&self.resource, &setup)?;
+
+        let handle = {
+            let mut shaders = self.shaders.write().unwrap();
+            if let Some(handle) = shaders.lookup(location) {
+                shaders.inc_rc(handle);
+                return Ok(handle.into());
+            }
+
+            let shader_state = ShaderState::compute(&setup);
+            shaders.create(location, shader_state).into()
+        };
+
+        let task = PreFrameTask::CreateComputePipeline(handle, setup);
+        self.frames.front().pre.push(task);
+        self.leaks.track(ResourceKind::Shader, handle, location, "create_compute_shader");
+        Ok(handle)
+    }
+
+    /// Create a shader with sources loaded from `vs` and `fs` of the virtual file system,
+    /// the `vs` and `fs` fields of `setup` are ignored.
+    ///
+    /// The shader will be recompiled when its source files change if hot reloading is
+    /// enabled, the old program is kept if the new sources fail to compile.
+    pub fn create_shader_from<P1, P2>(
+        &self,
+        location: Location,
+        mut setup: ShaderSetup,
+        vs: P1,
+        fs: P2,
+    ) -> Result<ShaderHandle>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        if let Some(handle) = self.lookup_shader_from(location) {
+            self.shaders.write().unwrap().inc_rc(handle.into());
+            return Ok(handle);
+        }
+
+        let (vs, fs) = (vs.as_ref(), fs.as_ref());
+        setup.vs = Self::load_shader_source(&self.resource, vs)?;
+        setup.fs = Self::load_shader_source(&self.resource, fs)?;
+
+        let handle = self.create_shader(location, setup.clone())?;
+
+        if self.resource.is_hot_reload_enabled() {
+            let setup = Arc::new(Mutex::new(setup));
+
+            // Failures are reported through the hot-reload failure callback.
+            for &(stage, path) in &[(ShaderStage::Vertex, vs), (ShaderStage::Fragment, fs)] {
+                let reloader = ShaderReloader::new(
+                    handle,
+                    stage,
+                    &setup,
+                    self.resource.clone(),
+                    self.frames.clone(),
+                );
+                let _ = self.resource.watch(reloader, path);
+            }
+
+            self.shader_sources.write().unwrap().insert(handle, setup);
+        }
+
+        Ok(handle)
+    }
+
+    fn load_shader_source(resource: &ResourceSystemShared, path: &Path) -> Result<String> {
+        let bytes = resource.load(path)?;
+        match String::from_utf8(bytes) {
+            Ok(source) => Ok(source),
+            Err(_) => bail!(format!("Shader source at {:?} is not valid UTF-8.", path)),
+        }
+    }
+
     /// Gets the shader state if exists.
     pub fn shader_state(&self, handle: ShaderHandle) -> Option<ShaderState> {
         self.shaders.read().unwrap().get(*handle).map(|v| v.clone())
     }
 
+    /// Calls `func` with the shader state if exists, without cloning it.
+    pub(crate) fn with_shader_state<F, R>(&self, handle: ShaderHandle, func: F) -> Option<R>
+    where
+        F: FnOnce(&ShaderState) -> R,
+    {
+        self.shaders.read().unwrap().get(*handle).map(func)
+    }
+
     /// Returns true if shader is exists.
     pub fn is_shader_alive(&self, handle: ShaderHandle) -> bool {
         self.shaders.read().unwrap().is_alive(handle.into())
     }
 
+    /// Returns true if the shader failed to compile or link in backend. Draw calls
+    /// with failed shader are skipped silently, until it's recompiled successfully
+    /// by hot reloading.
+    pub fn is_shader_failed(&self, handle: ShaderHandle) -> bool {
+        match self.shaders.read().unwrap().get(handle.into()) {
+            Some(state) => state.failed,
+            None => false,
+        }
+    }
+
+    /// Increases the reference count of shader, returns false if it has been deleted.
+    pub(crate) fn retain_shader(&self, handle: ShaderHandle) -> bool {
+        Self::retain(&mut self.shaders.write().unwrap(), handle)
+    }
+
     /// Delete shader state object.
     pub fn delete_shader(&self, handle: ShaderHandle) {
         if self.shaders
@@ -440,6 +1283,8 @@ impl GraphicsSystemShared {
             .dec_rc(handle.into(), true)
             .is_some()
         {
+            self.shader_sources.write().unwrap().remove(&handle);
+
             let task = PostFrameTask::DeletePipeline(handle);
             self.frames.front().post.push(task);
         }
@@ -449,6 +1294,10 @@ impl GraphicsSystemShared {
     /// which can then be used in other rendering operations.
     ///
     /// At least one color attachment has been attached before you can use it.
+    ///
+    /// Shaders with multiple outputs (see `ShaderSetup::outputs`) write into several
+    /// color attachments at once, the number of outputs should match the slots of
+    /// color attachments.
     pub fn create_framebuffer(&self, setup: FrameBufferSetup) -> Result<FrameBufferHandle> {
         let location = Location::unique("");
         let handle = self.framebuffers
@@ -462,6 +1311,7 @@ impl GraphicsSystemShared {
             self.frames.front().pre.push(task);
         }
 
+        self.leaks.track(ResourceKind::FrameBuffer, handle, location, "create_framebuffer");
         Ok(handle)
     }
 
@@ -487,25 +1337,274 @@ impl GraphicsSystemShared {
             .create(location, ())
             .into();
 
-        {
-            let task = PreFrameTask::CreateRenderBuffer(handle, setup);
-            self.frames.front().pre.push(task);
+        {
+            let task = PreFrameTask::CreateRenderBuffer(handle, setup);
+            self.frames.front().pre.push(task);
+        }
+
+        self.leaks.track(ResourceKind::RenderBuffer, handle, location, "create_render_buffer");
+        Ok(handle)
+    }
+
+    /// Delete frame buffer object.
+    pub fn delete_render_buffer(&self, handle: RenderBufferHandle) {
+        if self.render_buffers
+            .write()
+            .unwrap()
+            .dec_rc(handle.into(), true)
+            .is_some()
+        {
+            let task = PostFrameTask::DeleteRenderBuffer(handle);
+            self.frames.front().post.push(task);
+        }
+    }
+}
+
+impl GraphicsSystemShared {
+    /// Create an uniform buffer object, which could be bound to the uniform blocks of
+    /// shaders with `DrawCall::set_uniform_buffer`. The initial `data`, if provided,
+    /// should fill the whole buffer.
+    pub fn create_uniform_buffer<'a, T>(
+        &self,
+        setup: UniformBufferSetup,
+        data: T,
+    ) -> Result<UniformBufferHandle>
+    where
+        T: Into<Option<&'a [u8]>>,
+    {
+        if !self.capabilities.support_uniform_buffer() {
+            bail!("Uniform buffer is not supported by the device.");
+        }
+
+        if setup.size == 0 {
+            bail!("Uniform buffer should have at least one byte.");
+        }
+
+        let data = data.into();
+        if let Some(v) = data {
+            if v.len() != setup.size {
+                bail!(ErrorKind::OutOfBounds);
+            }
+        }
+
+        let location = Location::unique("");
+        let handle = self.uniform_buffers
+            .write()
+            .unwrap()
+            .create(location, setup)
+            .into();
+
+        {
+            let mut frame = self.frames.front();
+            let ptr = data.map(|v| frame.buf.extend_from_slice_aligned(v, 16));
+            let task = PreFrameTask::CreateUniformBuffer(handle, setup, ptr);
+            frame.pre.push(task);
+        }
+
+        self.leaks.track(ResourceKind::UniformBuffer, handle, location, "create_uniform_buffer");
+        Ok(handle)
+    }
+
+    /// Update a subset of uniform buffer. Use `offset` specifies the offset into the
+    /// buffer where data replacement will begin, measured in bytes. The `data` should
+    /// be laid out in `std140`, which is what the uniform blocks of shaders expect.
+    ///
+    /// The update takes effect before all the draw calls of current frame, so it's
+    /// usually done once per frame, e.g. with the matrices of camera.
+    ///
+    /// Returns `InvalidUpdateStaticResource` if the buffer is `BufferHint::Static`.
+    pub fn update_uniform_buffer(
+        &self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let setup = match self.uniform_buffers.read().unwrap().get(handle.into()) {
+            Some(setup) => *setup,
+            None => bail!(ErrorKind::InvalidHandle),
+        };
+
+        if setup.hint == BufferHint::Static {
+            bail!(ErrorKind::InvalidUpdateStaticResource);
+        }
+
+        if offset + data.len() > setup.size {
+            bail!(ErrorKind::OutOfBounds);
+        }
+
+        let mut frame = self.frames.front();
+        let ptr = frame.buf.extend_from_slice_aligned(data, 16);
+        let task = PreFrameTask::UpdateUniformBuffer(handle, offset, ptr);
+        frame.pre.push(task);
+        Ok(())
+    }
+
+    /// Delete uniform buffer object.
+    pub fn delete_uniform_buffer(&self, handle: UniformBufferHandle) {
+        if self.uniform_buffers
+            .write()
+            .unwrap()
+            .dec_rc(handle.into(), true)
+            .is_some()
+        {
+            let task = PostFrameTask::DeleteUniformBuffer(handle);
+            self.frames.front().post.push(task);
+        }
+    }
+
+    /// Create shader storage buffer object, which could be bound to the storage blocks
+    /// of compute shaders with `Dispatch::set_storage_buffer`, and render shaders with
+    /// `DrawCall::set_storage_buffer`. The initial `data`, if provided, should have
+    /// exactly `setup.size` bytes.
+    pub fn create_storage_buffer<'a, T>(
+        &self,
+        setup: StorageBufferSetup,
+        data: T,
+    ) -> Result<StorageBufferHandle>
+    where
+        T: Into<Option<&'a [u8]>>,
+    {
+        if !self.capabilities.support_compute_shader() {
+            bail!("Storage buffer is not supported by the device.");
+        }
+
+        if setup.size == 0 {
+            bail!("Storage buffer should have at least one byte.");
+        }
+
+        let data = data.into();
+        if let Some(v) = data {
+            if v.len() != setup.size {
+                bail!(ErrorKind::OutOfBounds);
+            }
+        }
+
+        let location = Location::unique("");
+        let handle = self.storage_buffers
+            .write()
+            .unwrap()
+            .create(location, setup)
+            .into();
+
+        {
+            let mut frame = self.frames.front();
+            let ptr = data.map(|v| frame.buf.extend_from_slice_aligned(v, 16));
+            let task = PreFrameTask::CreateStorageBuffer(handle, setup, ptr);
+            frame.pre.push(task);
+        }
+
+        self.leaks.track(ResourceKind::StorageBuffer, handle, location, "create_storage_buffer");
+        Ok(handle)
+    }
+
+    /// Update a subset of storage buffer. Use `offset` specifies the offset into the
+    /// buffer where data replacement will begin, measured in bytes. The `data` should
+    /// be laid out in `std430`.
+    ///
+    /// Returns `InvalidUpdateStaticResource` if the buffer is `BufferHint::Static`.
+    pub fn update_storage_buffer(
+        &self,
+        handle: StorageBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let setup = match self.storage_buffers.read().unwrap().get(handle.into()) {
+            Some(setup) => *setup,
+            None => bail!(ErrorKind::InvalidHandle),
+        };
+
+        if setup.hint == BufferHint::Static {
+            bail!(ErrorKind::InvalidUpdateStaticResource);
+        }
+
+        if offset + data.len() > setup.size {
+            bail!(ErrorKind::OutOfBounds);
+        }
+
+        let mut frame = self.frames.front();
+        let ptr = frame.buf.extend_from_slice_aligned(data, 16);
+        let task = PreFrameTask::UpdateStorageBuffer(handle, offset, ptr);
+        frame.pre.push(task);
+        Ok(())
+    }
+
+    /// Delete storage buffer object.
+    pub fn delete_storage_buffer(&self, handle: StorageBufferHandle) {
+        if self.storage_buffers
+            .write()
+            .unwrap()
+            .dec_rc(handle.into(), true)
+            .is_some()
+        {
+            let task = PostFrameTask::DeleteStorageBuffer(handle);
+            self.frames.front().post.push(task);
+        }
+    }
+}
+
+impl GraphicsSystemShared {
+    /// Inserts a fence after all the commands of current frame. Please read the
+    /// documents of `graphics::fence` for the threading constraints.
+    pub fn insert_fence(&self) -> Result<FenceHandle> {
+        if !self.capabilities.support_sync() {
+            bail!("Fence requires glFenceSync (OpenGL 3.2, OpenGL ES 3.0 or GL_ARB_sync).");
+        }
+
+        let location = Location::unique("");
+        let handle = self.fences.write().unwrap().create(location, ()).into();
+
+        {
+            let task = PostFrameTask::CreateFence(handle);
+            self.frames.front().post.push(task);
+        }
+
+        Ok(handle)
+    }
+
+    /// Returns true if the fence has been signaled and cleaned up.
+    #[inline]
+    pub fn is_fence_signaled(&self, handle: FenceHandle) -> bool {
+        !self.fences.read().unwrap().is_alive(handle.into())
+    }
+
+    /// Blocks the current thread until the fence has been signaled or `timeout`
+    /// elapsed. Returns true if the fence has been signaled.
+    ///
+    /// The status of fences are polled by the render thread once per frame, so the
+    /// precision of waiting is limited to the frame rate. Use `GraphicsSystem::wait_fence`
+    /// on the render thread for precise synchronization.
+    pub fn wait_fence(&self, handle: FenceHandle, timeout: Duration) -> bool {
+        let ts = Instant::now();
+        let (ref lock, ref cvar) = self.fences_signaled;
+
+        let mut guard = lock.lock().unwrap();
+        while !self.is_fence_signaled(handle) {
+            let elapsed = ts.elapsed();
+            if elapsed >= timeout {
+                return false;
+            }
+
+            guard = cvar.wait_timeout(guard, timeout - elapsed).unwrap().0;
         }
 
-        Ok(handle)
+        true
     }
 
-    /// Delete frame buffer object.
-    pub fn delete_render_buffer(&self, handle: RenderBufferHandle) {
-        if self.render_buffers
-            .write()
-            .unwrap()
-            .dec_rc(handle.into(), true)
-            .is_some()
+    /// Cleans up the signaled fences, and wakes up the threads waiting on them.
+    fn recycle_fences(&self, handles: &[FenceHandle]) {
+        if handles.is_empty() {
+            return;
+        }
+
         {
-            let task = PostFrameTask::DeleteRenderBuffer(handle);
-            self.frames.front().post.push(task);
+            let mut fences = self.fences.write().unwrap();
+            for &handle in handles {
+                fences.dec_rc(handle.into(), false);
+            }
         }
+
+        let _guard = self.fences_signaled.0.lock().unwrap();
+        self.fences_signaled.1.notify_all();
     }
 }
 
@@ -524,28 +1623,48 @@ impl GraphicsSystemShared {
     where
         T: MeshParser + Send + Sync + 'static,
     {
+        self.create_mesh_from_async::<T>(location, setup).map(|v| v.0)
+    }
+
+    /// Create a new mesh object from location, and returns a `ResourceFuture` that
+    /// resolves when the mesh is ready or fails to load. The future of a shared mesh
+    /// which has been created before is returned if the location matches.
+    pub fn create_mesh_from_async<T>(
+        &self,
+        location: Location,
+        setup: MeshSetup,
+    ) -> Result<(MeshHandle, ResourceFuture)>
+    where
+        T: MeshParser + Send + Sync + 'static,
+    {
+        let promise = ResourcePromise::new();
+        let future = promise.future();
+
         let (handle, state) = {
             let mut meshes = self.meshes.write().unwrap();
             if let Some(handle) = meshes.lookup(location) {
                 meshes.inc_rc(handle);
-                return Ok(handle.into());
+                let future = meshes.get(handle).unwrap().future.clone();
+                return Ok((handle.into(), future));
             }
 
             let state = Arc::new(RwLock::new(MeshState::NotReady));
-            let handle = meshes.create(location, state.clone()).into();
+            let entry = MeshEntry::new(state.clone(), future.clone(), &setup);
+            let handle = meshes.create(location, entry).into();
             (handle, state)
         };
 
-        let loader = MeshLoader::<T>::new(handle, state, setup, self.frames.clone());
+        let loader = MeshLoader::<T>::new(handle, state, setup, self.frames.clone(), promise);
         self.resource.load_async(loader, location.uri());
-        Ok(handle)
+        self.leaks.track(ResourceKind::Mesh, handle, location, "create_mesh_from_async");
+        Ok((handle, future))
     }
 
     /// Create a new mesh object.
     pub fn create_mesh<'a, 'b, T1, T2>(
         &self,
         location: Location,
-        setup: MeshSetup,
+        mut setup: MeshSetup,
         verts: T1,
         idxes: T2,
     ) -> Result<MeshHandle>
@@ -569,7 +1688,14 @@ impl GraphicsSystemShared {
         }
 
         setup.validate()?;
-        
+
+        let bounds = if setup.aabb.is_none() {
+            setup.aabb = verts.and_then(|v| setup.calculate_aabb(v));
+            Some((setup.layout, setup.num_verts))
+        } else {
+            None
+        };
+
         let handle = {
             let mut meshes = self.meshes.write().unwrap();
             if let Some(handle) = meshes.lookup(location) {
@@ -577,8 +1703,12 @@ impl GraphicsSystemShared {
                 return Ok(handle.into());
             }
 
-            let state = Arc::new(RwLock::new(MeshState::Ready));
-            let handle = meshes.create(location, state).into();
+            let sub_meshes = setup.sub_mesh_offsets.clone();
+            let state = MeshState::Ready(setup.aabb, setup.layout, setup.num_idxes, sub_meshes);
+            let state = Arc::new(RwLock::new(state));
+            let mut entry = MeshEntry::new(state, ResourceFuture::ready(), &setup);
+            entry.bounds = bounds;
+            let handle = meshes.create(location, entry).into();
             handle
         };
 
@@ -587,37 +1717,74 @@ impl GraphicsSystemShared {
         let idxes_ptr = idxes.map(|v| frame.buf.extend_from_slice(v));
         let task = PreFrameTask::CreateMesh(handle, setup, verts_ptr, idxes_ptr);
         frame.pre.push(task);
+        self.leaks.track(ResourceKind::Mesh, handle, location, "create_mesh");
         Ok(handle)
     }
 
+    /// Gets the bounding box of mesh in local space. Returns `None` if the mesh is
+    /// not ready, or its bounding box is unknown.
+    pub fn mesh_aabb(&self, mesh: MeshHandle) -> Option<Aabb3> {
+        let meshes = self.meshes.read().unwrap();
+        let entry = meshes.get(mesh.into())?;
+        let state = entry.state.read().unwrap();
+
+        match *state {
+            MeshState::Ready(aabb, _, _, _) => aabb,
+            _ => None,
+        }
+    }
+
+    /// Gets the number of sub-meshes that declared by `MeshSetup::sub_mesh_offsets`.
+    /// Returns `None` if the mesh is not ready.
+    pub fn mesh_sub_meshes(&self, mesh: MeshHandle) -> Option<usize> {
+        let meshes = self.meshes.read().unwrap();
+        let entry = meshes.get(mesh.into())?;
+        let state = entry.state.read().unwrap();
+
+        match *state {
+            MeshState::Ready(_, _, _, ref sub_meshes) => Some(sub_meshes.len()),
+            _ => None,
+        }
+    }
+
     /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
     /// into the buffer object's data store where data replacement will begin, measured
     /// in bytes.
+    ///
+    /// Returns `InvalidUpdateStaticResource` if the vertex buffer is `BufferHint::Static`.
+    ///
+    /// The bounding box of mesh is recalculated if the whole vertex buffer is replaced,
+    /// and grown to contain the updated vertices otherwise. It's left untouched if it
+    /// was specified by `MeshSetup::aabb`.
     pub fn update_vertex_buffer(&self, mesh: MeshHandle, offset: usize, data: &[u8]) -> Result<()> {
-        if self.meshes.read().unwrap().is_alive(mesh.into()) {
-            let mut frame = self.frames.front();
-            let ptr = frame.buf.extend_from_slice(data);
-            let task = PreFrameTask::UpdateVertexBuffer(mesh, offset, ptr);
-            frame.pre.push(task);
-            Ok(())
-        } else {
-            bail!(ErrorKind::InvalidHandle);
-        }
+        self.check_mesh_update(mesh, false)?;
+        self.update_mesh_aabb(mesh, offset, data);
+
+        let mut frame = self.frames.front();
+        let ptr = frame.buf.extend_from_slice(data);
+        let task = PreFrameTask::UpdateVertexBuffer(mesh, offset, ptr);
+        frame.pre.push(task);
+        Ok(())
     }
 
     /// Update a subset of dynamic index buffer. Use `offset` specifies the offset
     /// into the buffer object's data store where data replacement will begin, measured
     /// in bytes.
+    ///
+    /// Returns `InvalidUpdateStaticResource` if the index buffer is `BufferHint::Static`.
     pub fn update_index_buffer(&self, mesh: MeshHandle, offset: usize, data: &[u8]) -> Result<()> {
-        if self.meshes.read().unwrap().is_alive(mesh.into()) {
-            let mut frame = self.frames.front();
-            let ptr = frame.buf.extend_from_slice(data);
-            let task = PreFrameTask::UpdateIndexBuffer(mesh, offset, ptr);
-            frame.pre.push(task);
-            Ok(())
-        } else {
-            bail!(ErrorKind::InvalidHandle);
-        }
+        self.check_mesh_update(mesh, true)?;
+
+        let mut frame = self.frames.front();
+        let ptr = frame.buf.extend_from_slice(data);
+        let task = PreFrameTask::UpdateIndexBuffer(mesh, offset, ptr);
+        frame.pre.push(task);
+        Ok(())
+    }
+
+    /// Increases the reference count of mesh, returns false if it has been deleted.
+    pub(crate) fn retain_mesh(&self, handle: MeshHandle) -> bool {
+        Self::retain(&mut self.meshes.write().unwrap(), handle)
     }
 
     /// Delete mesh object.
@@ -632,6 +1799,68 @@ impl GraphicsSystemShared {
             self.frames.front().post.push(task);
         }
     }
+
+    /// Deletes mesh objects in batch. It's equivalent to calling `delete_mesh` on
+    /// each of them, but the lock is only acquired once.
+    pub fn delete_meshes(&self, meshes: &[MeshHandle]) {
+        let handles = Self::dec_rcs(&mut self.meshes.write().unwrap(), meshes);
+        if !handles.is_empty() {
+            let task = PostFrameTask::DeleteMeshBatch(handles);
+            self.frames.front().post.push(task);
+        }
+    }
+
+    fn update_mesh_aabb(&self, mesh: MeshHandle, offset: usize, data: &[u8]) {
+        let meshes = self.meshes.read().unwrap();
+        let entry = match meshes.get(mesh.into()) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let (layout, num_verts) = match entry.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let mut setup = MeshSetup::default();
+        setup.layout = layout;
+        setup.num_verts = num_verts;
+
+        let stride = layout.stride() as usize;
+        let replaced = offset == 0 && data.len() >= setup.vertex_buffer_len();
+
+        let mut state = entry.state.write().unwrap();
+        if let MeshState::Ready(ref mut aabb, _, _, _) = *state {
+            *aabb = if stride == 0 || offset % stride != 0 {
+                // The updated vertices could not be decoded, so the box is unknown.
+                None
+            } else if replaced {
+                setup.calculate_aabb(data)
+            } else {
+                match (*aabb, setup.calculate_aabb(data)) {
+                    (Some(v), Some(updated)) => Some(v.grow(updated.min).grow(updated.max)),
+                    (v, _) => v,
+                }
+            };
+        }
+    }
+
+    /// Makes sure that the vertex (or index) buffer of mesh could be updated, so
+    /// misuses of static meshes are caught before the frame is executed.
+    fn check_mesh_update(&self, mesh: MeshHandle, index: bool) -> Result<()> {
+        let meshes = self.meshes.read().unwrap();
+        let entry = match meshes.get(mesh.into()) {
+            Some(entry) => entry,
+            None => bail!(ErrorKind::InvalidHandle),
+        };
+
+        let hint = if index { entry.hints.1 } else { entry.hints.0 };
+        if hint == BufferHint::Static {
+            bail!(ErrorKind::InvalidUpdateStaticResource);
+        }
+
+        Ok(())
+    }
 }
 
 impl GraphicsSystemShared {
@@ -644,7 +1873,8 @@ impl GraphicsSystemShared {
             .map(|v| v.into())
     }
 
-    /// Create texture object from location.
+    /// Create texture object from location. The texture will be reloaded when its file
+    /// changes if hot reloading is enabled.
     pub fn create_texture_from<T>(
         &self,
         location: Location,
@@ -653,21 +1883,75 @@ impl GraphicsSystemShared {
     where
         T: TextureParser + Send + Sync + 'static,
     {
+        self.create_texture_from_async::<T>(location, setup).map(|v| v.0)
+    }
+
+    /// Create texture object from location, and returns a `ResourceFuture` that
+    /// resolves when the texture is ready or fails to load. The future of a shared
+    /// texture which has been created before is returned if the location matches.
+    pub fn create_texture_from_async<T>(
+        &self,
+        location: Location,
+        setup: TextureSetup,
+    ) -> Result<(TextureHandle, ResourceFuture)>
+    where
+        T: TextureParser + Send + Sync + 'static,
+    {
+        if setup.kind != TextureKind::Texture2D {
+            bail!(format!(
+                "Texture of {:?} should be created with `create_cube_texture` or \
+                 `create_texture_array`.",
+                setup.kind
+            ));
+        }
+
+        setup.validate(&[])?;
+
+        let promise = ResourcePromise::new();
+        let future = promise.future();
+
         let (handle, state) = {
             let mut textures = self.textures.write().unwrap();
             if let Some(handle) = textures.lookup(location) {
                 textures.inc_rc(handle);
-                return Ok(handle.into());
+                let future = textures.get(handle).unwrap().future.clone();
+                return Ok((handle.into(), future));
             }
 
             let state = Arc::new(RwLock::new(TextureState::NotReady));
-            let handle = textures.create(location, state.clone()).into();
-            (handle, state)
+            let entry = TextureEntry {
+                state: state.clone(),
+                future: future.clone(),
+                render: None,
+            };
+
+            (textures.create(location, entry).into(), state)
         };
-    
-        let loader = TextureLoader::<T>::new(handle, state, setup, self.frames.clone());
+
+        if self.resource.is_hot_reload_enabled() {
+            let reloader = TextureReloader::<T>::new(
+                handle,
+                &state,
+                setup,
+                self.frames.clone(),
+                self.capabilities.support_npot_mipmap(),
+            );
+
+            // Failures are reported through the hot-reload failure callback.
+            let _ = self.resource.watch(reloader, location.uri());
+        }
+
+        let loader = TextureLoader::<T>::new(
+            handle,
+            state,
+            setup,
+            self.frames.clone(),
+            self.capabilities.support_npot_mipmap(),
+            promise,
+        );
         self.resource.load_async(loader, location.uri());
-        Ok(handle)
+        self.leaks.track(ResourceKind::Texture, handle, location, "create_texture_from_async");
+        Ok((handle, future))
     }
 
     /// Create texture object. A texture is an image loaded in video memory,
@@ -681,6 +1965,23 @@ impl GraphicsSystemShared {
     where
         T: Into<Option<&'a [u8]>>,
     {
+        if setup.kind != TextureKind::Texture2D {
+            bail!(format!(
+                "Texture of {:?} should be created with `create_cube_texture` or \
+                 `create_texture_array`.",
+                setup.kind
+            ));
+        }
+
+        let data = data.into();
+        if let Some(v) = data {
+            setup.validate(&[v])?;
+        } else {
+            setup.validate(&[])?;
+        }
+
+        self.check_texture_setup(&setup)?;
+
         let handle = {
             let mut textures = self.textures.write().unwrap();
             if let Some(handle) = textures.lookup(location) {
@@ -688,35 +1989,185 @@ impl GraphicsSystemShared {
                 return Ok(handle.into());
             }
 
-            let state = Arc::new(RwLock::new(TextureState::Ready));
-            textures.create(location, state).into()
+            textures.create(location, TextureEntry::ready()).into()
         };
     
         let mut frame = self.frames.front();
-        let ptr = data.into().map(|v| frame.buf.extend_from_slice(v));
+        let ptr = data.map(|v| frame.buf.extend_from_slice(v));
         let task = PreFrameTask::CreateTexture(handle, setup, ptr);
         frame.pre.push(task);
+        self.leaks.track(ResourceKind::Texture, handle, location, "create_texture");
+        Ok(handle)
+    }
+
+    /// Create cube texture object with six faces, which are ordered as +X, -X, +Y, -Y,
+    /// +Z and -Z. A optional data of faces could be provided to fill the texture.
+    pub fn create_cube_texture<'a, T>(
+        &self,
+        location: Location,
+        mut setup: TextureSetup,
+        faces: T,
+    ) -> Result<TextureHandle>
+    where
+        T: Into<Option<[&'a [u8]; 6]>>,
+    {
+        setup.kind = TextureKind::Cube;
+
+        let faces = faces.into();
+        if let Some(ref v) = faces {
+            setup.validate(&v[..])?;
+        } else {
+            setup.validate(&[])?;
+        }
+
+        self.check_texture_setup(&setup)?;
+
+        let handle = {
+            let mut textures = self.textures.write().unwrap();
+            if let Some(handle) = textures.lookup(location) {
+                textures.inc_rc(handle);
+                return Ok(handle.into());
+            }
+
+            textures.create(location, TextureEntry::ready()).into()
+        };
+
+        let mut frame = self.frames.front();
+        let ptr = faces.map(|v| {
+            [
+                frame.buf.extend_from_slice(v[0]),
+                frame.buf.extend_from_slice(v[1]),
+                frame.buf.extend_from_slice(v[2]),
+                frame.buf.extend_from_slice(v[3]),
+                frame.buf.extend_from_slice(v[4]),
+                frame.buf.extend_from_slice(v[5]),
+            ]
+        });
+
+        let task = PreFrameTask::CreateCubeTexture(handle, setup, ptr);
+        frame.pre.push(task);
+        self.leaks.track(ResourceKind::Texture, handle, location, "create_cube_texture");
+        Ok(handle)
+    }
+
+    /// Create 2D texture array object with `layers` of the same dimensions and format.
+    /// A optional data of layers, which are stored contiguously, could be provided
+    /// to fill the texture.
+    pub fn create_texture_array<'a, T>(
+        &self,
+        location: Location,
+        mut setup: TextureSetup,
+        layers: u32,
+        data: T,
+    ) -> Result<TextureHandle>
+    where
+        T: Into<Option<&'a [u8]>>,
+    {
+        setup.kind = TextureKind::Texture2DArray(layers);
+
+        let data = data.into();
+        if let Some(v) = data {
+            let len = setup.len();
+            if v.len() != len * layers as usize {
+                bail!(format!(
+                    "Mismatched size of texture array, expects {} layers of {} bytes but got {}.",
+                    layers,
+                    len,
+                    v.len()
+                ));
+            }
+
+            let faces: Vec<_> = v.chunks(len.max(1)).collect();
+            setup.validate(&faces)?;
+        } else {
+            setup.validate(&[])?;
+        }
+
+        self.check_texture_setup(&setup)?;
+
+        if !self.capabilities.support_texture_array() {
+            bail!("Texture arrays are not supported by the device.");
+        }
+
+        let max = self.capabilities.max_array_texture_layers;
+        if layers > max {
+            bail!(format!(
+                "The number of layers {} exceeds the maximum {} of texture array.",
+                layers, max
+            ));
+        }
+
+        let handle = {
+            let mut textures = self.textures.write().unwrap();
+            if let Some(handle) = textures.lookup(location) {
+                textures.inc_rc(handle);
+                return Ok(handle.into());
+            }
+
+            textures.create(location, TextureEntry::ready()).into()
+        };
+
+        let mut frame = self.frames.front();
+        let ptr = data.map(|v| frame.buf.extend_from_slice(v));
+        let task = PreFrameTask::CreateTextureArray(handle, setup, ptr);
+        frame.pre.push(task);
+        self.leaks.track(ResourceKind::Texture, handle, location, "create_texture_array");
         Ok(handle)
     }
 
+    fn check_texture_setup(&self, setup: &TextureSetup) -> Result<()> {
+        let max = self.capabilities.max_texture_size;
+        if setup.dimensions.0 > max || setup.dimensions.1 > max {
+            bail!(format!(
+                "Texture dimensions {:?} exceed the maximum size {}.",
+                setup.dimensions, max
+            ));
+        }
+
+        if !setup.is_mipmap_supported(self.capabilities.support_npot_mipmap()) {
+            bail!("Mipmaps of NPOT texture are not supported by the device.");
+        }
+
+        if setup.srgb && !self.capabilities.support_srgb_texture() {
+            bail!("Textures in sRGB space are not supported by the device.");
+        }
+
+        if !self.capabilities.support_texture_format(setup.format) {
+            bail!(format!(
+                "Texture format {:?} is not supported by the device.",
+                setup.format
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Create render texture object, which could be attached with a framebuffer.
     pub fn create_render_texture(
         &self,
         setup: RenderTextureSetup,
     ) -> Result<TextureHandle> {
+        if !self.capabilities.support_render_texture_format(setup.format) {
+            bail!(format!(
+                "Render texture with {:?} format is not supported.",
+                setup.format
+            ));
+        }
+
         let location = Location::unique("");
-        let state = Arc::new(RwLock::new(TextureState::Ready));
-        let handle = self.textures
-            .write()
-            .unwrap()
-            .create(location, state)
-            .into();
+        let entry = TextureEntry {
+            render: Some(setup),
+            ..TextureEntry::ready()
+        };
+
+        let handle = self.textures.write().unwrap().create(location, entry).into();
 
         {
             let task = PreFrameTask::CreateRenderTexture(handle, setup);
             self.frames.front().pre.push(task);
         }
 
+        self.leaks.track(ResourceKind::Texture, handle, location, "create_render_texture");
         Ok(handle)
     }
 
@@ -725,11 +2176,26 @@ impl GraphicsSystemShared {
     /// Notes that this method might fails without any error when the texture is not
     /// ready for operating.
     pub fn update_texture(&self, texture: TextureHandle, rect: Rect, data: &[u8]) -> Result<()> {
-        if let Some(state) = self.textures.read().unwrap().get(texture.into()) {
-            if TextureState::Ready == *state.read().unwrap() {
+        self.update_texture_layer(texture, 0, rect, data)
+    }
+
+    /// Update a single `layer` of the texture array object, the `layer` of other
+    /// textures should be zero.
+    ///
+    /// Notes that this method might fails without any error when the texture is not
+    /// ready for operating.
+    pub fn update_texture_layer(
+        &self,
+        texture: TextureHandle,
+        layer: u32,
+        rect: Rect,
+        data: &[u8],
+    ) -> Result<()> {
+        if let Some(entry) = self.textures.read().unwrap().get(texture.into()) {
+            if TextureState::Ready == *entry.state.read().unwrap() {
                 let mut frame = self.frames.front();
                 let ptr = frame.buf.extend_from_slice(data);
-                let task = PreFrameTask::UpdateTexture(texture, rect, ptr);
+                let task = PreFrameTask::UpdateTexture(texture, layer, rect, ptr);
                 frame.pre.push(task);
             }
 
@@ -739,6 +2205,39 @@ impl GraphicsSystemShared {
         }
     }
 
+    /// Increases the reference count of texture, returns false if it has been deleted.
+    pub(crate) fn retain_texture(&self, handle: TextureHandle) -> bool {
+        Self::retain(&mut self.textures.write().unwrap(), handle)
+    }
+
+    fn retain<T, H>(registery: &mut Registery<T>, handle: H) -> bool
+    where
+        H: Into<Handle>,
+    {
+        // The handles of deleted resources are recycled at the end of frame, so checks
+        // the entry instead of the handle.
+        let handle = handle.into();
+        if registery.get(handle).is_some() {
+            registery.inc_rc(handle);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Decreases the reference counts of `handles`, returns the ones that should be
+    /// deleted by the backend.
+    fn dec_rcs<T, H>(registery: &mut Registery<T>, handles: &[H]) -> Vec<H>
+    where
+        H: Into<Handle> + Copy,
+    {
+        handles
+            .iter()
+            .filter(|v| registery.dec_rc((**v).into(), true).is_some())
+            .cloned()
+            .collect()
+    }
+
     /// Delete the texture object.
     pub fn delete_texture(&self, handle: TextureHandle) {
         if self.textures
@@ -751,4 +2250,158 @@ impl GraphicsSystemShared {
             self.frames.front().post.push(task);
         }
     }
+
+    /// Deletes texture objects in batch. It's equivalent to calling `delete_texture`
+    /// on each of them, but the lock is only acquired once.
+    pub fn delete_textures(&self, handles: &[TextureHandle]) {
+        let handles = Self::dec_rcs(&mut self.textures.write().unwrap(), handles);
+        if !handles.is_empty() {
+            let task = PostFrameTask::DeleteTextureBatch(handles);
+            self.frames.front().post.push(task);
+        }
+    }
+
+    /// Reads the pixels inside `rect` of render texture back to CPU. The pixels are
+    /// encoded in RGBA8 format, row by row from bottom to top.
+    ///
+    /// Since the backend runs one frame later on the main thread, the pixels will be
+    /// sent through the returned `Receiver` after the rendering of this frame. Only
+    /// color render textures could be read, and the `rect` is clipped by the bounds
+    /// of texture, which should not be empty after clipping. A `Headless` error is
+    /// returned if there is no window.
+    pub fn read_render_texture(
+        &self,
+        texture: TextureHandle,
+        rect: Rect,
+    ) -> Result<Receiver<Vec<u8>>> {
+        let setup = if let Some(entry) = self.textures.read().unwrap().get(texture.into()) {
+            if TextureState::Ready != *entry.state.read().unwrap() {
+                bail!("Texture is not ready for reading.");
+            }
+
+            match entry.render {
+                Some(setup) => setup,
+                None => bail!("Only render textures could be read back."),
+            }
+        } else {
+            bail!(ErrorKind::InvalidHandle);
+        };
+
+        if setup.format.is_depth() {
+            bail!(format!(
+                "Render texture with {:?} format could not be read back, only color \
+                 formats are supported.",
+                setup.format
+            ));
+        }
+
+        let dimensions = math::Point2::new(setup.dimensions.0 as i32, setup.dimensions.1 as i32);
+        let bounds = Rect::new(math::Point2::new(0, 0), dimensions);
+        if rect.intersection(bounds).is_none() {
+            bail!(format!(
+                "Rect {:?} for reading is empty or out of the bounds of texture {:?}.",
+                rect, setup.dimensions
+            ));
+        }
+
+        if self.headless {
+            bail!(ErrorKind::Headless);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let task = PostFrameTask::ReadTexture(texture, rect, tx);
+        self.frames.front().post.push(task);
+        Ok(rx)
+    }
+
+    /// Captures the default framebuffer of primary window after the rendering of this
+    /// frame, and writes it into `path` as PNG image.
+    ///
+    /// The pixels are read back on the main thread when this frame is dispatched, then
+    /// flipped to top-left origin and encoded on a worker thread, so the main loop is
+    /// never blocked by the encoding and file IO. The result is sent through the
+    /// returned `Receiver` once the file is written.
+    ///
+    /// The pixels are written as they are stored in framebuffer, so a sRGB framebuffer
+    /// is captured as displayed without being gamma corrected again. A `Headless` error
+    /// is returned if there is no window.
+    pub fn capture_frame<P>(&self, path: P) -> Result<Receiver<Result<()>>>
+    where
+        P: AsRef<Path>,
+    {
+        if self.headless {
+            bail!(ErrorKind::Headless);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let (pixels_tx, pixels_rx) = mpsc::channel();
+        let path = path.as_ref().to_owned();
+
+        thread::spawn(move || {
+            let result = match pixels_rx.recv() {
+                Ok((pixels, dimensions)) => screenshot::write_png(&path, dimensions, pixels),
+                Err(_) => Err("The frame is discarded before capturing.".into()),
+            };
+
+            // The receiver might be dropped, it's fine to ignore the result.
+            let _ = tx.send(result);
+        });
+
+        let task = PostFrameTask::ReadFrameBuffer(pixels_tx);
+        self.frames.front().post.push(task);
+        Ok(rx)
+    }
+
+    /// Captures the commands of the frame that is being submitted into a human-readable
+    /// `FrameCapture`, which is sent through the returned `Receiver` right before the
+    /// frame is dispatched to the backend. It includes the commands submitted before
+    /// this call in the same frame.
+    ///
+    /// The uniform variables are resolved into their values and the names declared by
+    /// shaders, so it's easy to find out that a matrix was zeroed or a handle was stale.
+    /// Resources are named with their handles, and also the labels recorded at creation
+    /// if the `leak-detection` feature is enabled. It works in headless mode too.
+    pub fn capture_next_frame(&self) -> Receiver<FrameCapture> {
+        let (tx, rx) = mpsc::channel();
+        let index = self.frame_index();
+        self.frames.front().captures.push((index, tx));
+        rx
+    }
+
+    /// Reports the video resources that are still alive, sorted by kind and handle,
+    /// with the labels recorded at creation.
+    ///
+    /// The leaks are logged to stderr when `GraphicsSystem` is dropped. The resources
+    /// are only tracked with the `leak-detection` feature, otherwise the report is
+    /// always empty.
+    pub fn report_leaks(&self) -> Vec<ResourceLeak> {
+        self.leaks.leaks(|kind, handle| match kind {
+            ResourceKind::Surface => self.surfaces.read().unwrap().is_alive(handle),
+            ResourceKind::Shader => self.shaders.read().unwrap().is_alive(handle),
+            ResourceKind::FrameBuffer => self.framebuffers.read().unwrap().is_alive(handle),
+            ResourceKind::RenderBuffer => self.render_buffers.read().unwrap().is_alive(handle),
+            ResourceKind::Mesh => self.meshes.read().unwrap().is_alive(handle),
+            ResourceKind::Texture => self.textures.read().unwrap().is_alive(handle),
+            ResourceKind::UniformBuffer => self.uniform_buffers.read().unwrap().is_alive(handle),
+            ResourceKind::StorageBuffer => self.storage_buffers.read().unwrap().is_alive(handle),
+        })
+    }
+}
+
+impl CaptureContext for GraphicsSystemShared {
+    fn label(&self, kind: ResourceKind, handle: Handle) -> Option<String> {
+        self.leaks.label(kind, handle)
+    }
+
+    fn field(&self, shader: ShaderHandle, field: HashValue<str>) -> Option<String> {
+        let shaders = self.shaders.read().unwrap();
+        let state = shaders.get(shader.into())?;
+
+        state
+            .uniform_variable_names
+            .get(&field)
+            .or_else(|| state.uniform_block_names.get(&field))
+            .or_else(|| state.storage_block_names.get(&field))
+            .cloned()
+    }
 }
@@ -1,3 +1,6 @@
+//! Utilities that delete resources automatically when they are no longer used.
+
+use std::fmt;
 use std::sync::Arc;
 
 use resource::Location;
@@ -5,6 +8,7 @@ use super::*;
 use super::errors::*;
 use super::assets::texture_loader::TextureParser;
 
+/// Deletes all the resources created through it when dropped.
 pub struct RAIIGuard {
     stack: Vec<Resource>,
     video: Arc<GraphicsSystemShared>,
@@ -108,6 +112,20 @@ impl RAIIGuard {
         Ok(self.push(v))
     }
 
+    #[inline(always)]
+    pub fn create_cube_texture<'a, T>(
+        &mut self,
+        location: Location,
+        setup: TextureSetup,
+        faces: T,
+    ) -> Result<TextureHandle>
+    where
+        T: Into<Option<[&'a [u8]; 6]>>,
+    {
+        let v = self.video.create_cube_texture(location, setup, faces)?;
+        Ok(self.push(v))
+    }
+
     pub fn clear(&mut self) {
         for v in self.stack.drain(..) {
             match v {
@@ -180,3 +198,116 @@ impl From<RenderBufferHandle> for Resource {
         Resource::RenderBuffer(handle)
     }
 }
+
+/// Resources that could be shared with reference counting, which is the same count
+/// that `create_*` increases for shared locations and `delete_*` decreases.
+pub trait OwnedResource: Copy + fmt::Debug + Send + Sync + 'static {
+    /// Increases the reference count, returns false if the resource has been deleted.
+    fn retain(self, video: &GraphicsSystemShared) -> bool;
+    /// Decreases the reference count, the resource will be deleted after the drawing
+    /// of this frame if nothing references it anymore.
+    fn release(self, video: &GraphicsSystemShared);
+}
+
+impl OwnedResource for TextureHandle {
+    fn retain(self, video: &GraphicsSystemShared) -> bool {
+        video.retain_texture(self)
+    }
+
+    fn release(self, video: &GraphicsSystemShared) {
+        video.delete_texture(self);
+    }
+}
+
+impl OwnedResource for MeshHandle {
+    fn retain(self, video: &GraphicsSystemShared) -> bool {
+        video.retain_mesh(self)
+    }
+
+    fn release(self, video: &GraphicsSystemShared) {
+        video.delete_mesh(self);
+    }
+}
+
+impl OwnedResource for ShaderHandle {
+    fn retain(self, video: &GraphicsSystemShared) -> bool {
+        video.retain_shader(self)
+    }
+
+    fn release(self, video: &GraphicsSystemShared) {
+        video.delete_shader(self);
+    }
+}
+
+/// A reference counted handle that owns one reference of resource, and releases it
+/// when the last clone is dropped. It's an alternative of the explicit `delete_*`.
+///
+/// ```rust,ignore
+/// let texture = video.create_texture_from::<Parser>(location, setup)?;
+/// let texture = OwnedHandle::new(video.clone(), texture);
+///
+/// // The handles of shared locations are reference counted too, so the texture is
+/// // only deleted after both of them are dropped.
+/// let other = video.lookup_texture_from(location).unwrap();
+/// let other = OwnedHandle::retain(video.clone(), other).unwrap();
+/// ```
+///
+/// Notes that the raw handle returned by `handle` is not counted, it should not be
+/// used after all the `OwnedHandle`s are dropped.
+pub struct OwnedHandle<T: OwnedResource> {
+    inner: Arc<OwnedInner<T>>,
+}
+
+struct OwnedInner<T: OwnedResource> {
+    handle: T,
+    video: Arc<GraphicsSystemShared>,
+}
+
+impl<T: OwnedResource> Drop for OwnedInner<T> {
+    fn drop(&mut self) {
+        self.handle.release(&self.video);
+    }
+}
+
+impl<T: OwnedResource> OwnedHandle<T> {
+    /// Takes over the reference of `handle` which is returned by `create_*`, so it
+    /// should not be deleted manually anymore.
+    pub fn new(video: Arc<GraphicsSystemShared>, handle: T) -> Self {
+        OwnedHandle {
+            inner: Arc::new(OwnedInner {
+                handle: handle,
+                video: video,
+            }),
+        }
+    }
+
+    /// Adds a new reference to `handle`, e.g. the one returned by `lookup_*_from`.
+    /// Returns `None` if the resource has been deleted.
+    pub fn retain(video: Arc<GraphicsSystemShared>, handle: T) -> Option<Self> {
+        if handle.retain(&video) {
+            Some(OwnedHandle::new(video, handle))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the underlying handle.
+    #[inline]
+    pub fn handle(&self) -> T {
+        self.inner.handle
+    }
+}
+
+impl<T: OwnedResource> Clone for OwnedHandle<T> {
+    fn clone(&self) -> Self {
+        OwnedHandle {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: OwnedResource> fmt::Debug for OwnedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OwnedHandle({:?})", self.inner.handle)
+    }
+}
@@ -0,0 +1,149 @@
+//! Tracks the creations of video resources to find the leaked ones, which is only
+//! enabled with the `leak-detection` feature.
+//!
+//! Every resource records a label when it's created, and the ones that are still
+//! alive could be reported with `GraphicsSystemShared::report_leaks`. Without the
+//! feature, nothing is recorded and the report is always empty.
+
+#[cfg(feature = "leak-detection")]
+use std::collections::BTreeMap;
+#[cfg(feature = "leak-detection")]
+use std::sync::Mutex;
+
+use resource::Location;
+use utils::Handle;
+
+/// The kinds of video resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceKind {
+    Surface,
+    Shader,
+    FrameBuffer,
+    RenderBuffer,
+    Mesh,
+    Texture,
+    UniformBuffer,
+    StorageBuffer,
+}
+
+/// A video resource which is still alive, with the label recorded when it's created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceLeak {
+    pub kind: ResourceKind,
+    pub handle: Handle,
+    /// The `Location` of resource, or the name of creation function if the location
+    /// is empty, e.g. `create_render_texture`.
+    pub label: String,
+}
+
+#[derive(Default)]
+pub(crate) struct LeakTracker {
+    #[cfg(feature = "leak-detection")]
+    labels: Mutex<BTreeMap<(ResourceKind, Handle), String>>,
+}
+
+impl LeakTracker {
+    /// Records the label of resource created with `location` by function `from`.
+    #[cfg(feature = "leak-detection")]
+    pub fn track<H>(&self, kind: ResourceKind, handle: H, location: Location, from: &str)
+    where
+        H: Into<Handle>,
+    {
+        let label = if location.uri().as_os_str().is_empty() {
+            from.to_owned()
+        } else {
+            format!("{} ({})", location.uri().display(), from)
+        };
+
+        let mut labels = self.labels.lock().unwrap();
+        labels.insert((kind, handle.into()), label);
+    }
+
+    #[cfg(not(feature = "leak-detection"))]
+    #[inline(always)]
+    pub fn track<H>(&self, _: ResourceKind, _: H, _: Location, _: &str)
+    where
+        H: Into<Handle>,
+    {
+    }
+
+    /// Gets the label of resource recorded when it's created.
+    #[cfg(feature = "leak-detection")]
+    pub fn label(&self, kind: ResourceKind, handle: Handle) -> Option<String> {
+        let labels = self.labels.lock().unwrap();
+        labels.get(&(kind, handle)).cloned()
+    }
+
+    #[cfg(not(feature = "leak-detection"))]
+    #[inline(always)]
+    pub fn label(&self, _: ResourceKind, _: Handle) -> Option<String> {
+        None
+    }
+
+    /// Gets the tracked resources that are still alive, sorted by kinds and handles.
+    /// The labels of deleted resources are dropped.
+    #[cfg(feature = "leak-detection")]
+    pub fn leaks<F>(&self, is_alive: F) -> Vec<ResourceLeak>
+    where
+        F: Fn(ResourceKind, Handle) -> bool,
+    {
+        let mut labels = self.labels.lock().unwrap();
+
+        let dead: Vec<_> = labels
+            .keys()
+            .filter(|&&(kind, handle)| !is_alive(kind, handle))
+            .cloned()
+            .collect();
+
+        for v in dead {
+            labels.remove(&v);
+        }
+
+        labels
+            .iter()
+            .map(|(&(kind, handle), label)| ResourceLeak {
+                kind: kind,
+                handle: handle,
+                label: label.clone(),
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "leak-detection"))]
+    #[inline(always)]
+    pub fn leaks<F>(&self, _: F) -> Vec<ResourceLeak>
+    where
+        F: Fn(ResourceKind, Handle) -> bool,
+    {
+        Vec::new()
+    }
+}
+
+#[cfg(all(test, feature = "leak-detection"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leaks() {
+        let tracker = LeakTracker::default();
+        let h1 = Handle::new(1, 1);
+        let h2 = Handle::new(0, 1);
+        let h3 = Handle::new(2, 1);
+
+        tracker.track(ResourceKind::Texture, h1, Location::unique(""), "create_texture");
+        tracker.track(ResourceKind::Texture, h2, Location::unique("/a.png"), "create_texture");
+        tracker.track(ResourceKind::Mesh, h3, Location::unique(""), "create_mesh");
+
+        let leaks = tracker.leaks(|_, v| v != h1);
+        let labels: Vec<_> = leaks.iter().map(|v| (v.kind, v.handle)).collect();
+        assert_eq!(
+            labels,
+            [(ResourceKind::Mesh, h3), (ResourceKind::Texture, h2)]
+        );
+        assert_eq!(leaks[1].label, "/a.png (create_texture)");
+
+        // The labels of deleted resources are dropped.
+        let leaks = tracker.leaks(|_, _| true);
+        assert_eq!(leaks.len(), 2);
+    }
+}
@@ -44,6 +44,10 @@
 //! with the OpenGL API are performed. The frontend thread that runs the game logic
 //! communicates with the backend renderer via a command double-buffer.
 //!
+//! Crayon goes one step further with a ring of three command buffers by default. The
+//! frontend always writes into a free buffer, so it never waits on the one that is
+//! being dispatched by the backend.
+//!
 //! ### Layered Rendering
 //!
 //! Its important to sort video commands (generated by different threads) before submiting
@@ -121,20 +125,114 @@
 //! graphics.delete_shader(setup);
 //! ```
 //!
+//! The sources are preprocessed before compiling, so common helpers could be shared
+//! with `#include "/res/shaders/lighting.glsl"`, and permutations of one source could be
+//! made with the `defines` of setup, e.g. `("NUM_LIGHTS".into(), "4".into())`. The
+//! expanded sources could be retrieved with `preprocess_shader` for debugging.
+//!
 //! _TODO_: SPIRV based shader compiling and information generations.
 //!
 //! ### Texture Object
 //!
-//! _TODO_: Compressed texture.
-//! _TODO_: Cube texture.
+//! Compressed formats like `DXT`, `ETC` and `ASTC` are uploaded as they are, and
+//! their mip levels should be provided with `TextureSetup::levels`. The updates of
+//! compressed texture should be aligned to the blocks of format. `.dds` and `.ktx`
+//! containers could be loaded with `TextureContainerParser`, and the availability of
+//! formats could be checked with `Capabilities::support_texture_format`.
+//!
+//! Render textures could be attached to framebuffers, and sampled later like a normal
+//! texture. Depth formats are attached to the depth attachment point regardless of
+//! their slot, and a framebuffer with only a depth texture is fine, which is what
+//! shadow maps require.
+//!
+//! Layers of the same dimensions and format, like terrain splat maps and sprite sheets,
+//! could be packed into a 2D texture array with `create_texture_array`. It's bound with
+//! `UniformVariable::Texture2DArray` and sampled as `sampler2DArray`, and a single layer
+//! could be updated with `update_texture_layer`. Texture arrays require OpenGL 3.0 or
+//! OpenGL ES 3.0.
+//!
 //! _TODO_: 3D texture.
 //!
 //! ### Mesh Object
 //!
+//! The vertex and index buffers of mesh are created with usage hints of `MeshSetup`.
+//! `BufferHint::Static` is the default one, and updates of static buffers will be
+//! rejected with `InvalidUpdateStaticResource`. Use `Stream` for data that changes
+//! every frame, or `Dynamic` for the infrequent updates.
+//!
 //! _TODO_: Mesh abstraction.
 //! _TODO_: Mesh loader.
 //! _TODO_: Mesh builder.
 //!
+//! ### Uniform Buffer Object
+//!
+//! Uniforms shared by many draw calls, like the matrices of camera, could be uploaded
+//! once per frame into an uniform buffer instead of being packed into every draw call.
+//! The contents are laid out in `std140`, and the size of block declared in
+//! `ShaderSetup::uniform_blocks` is validated against the linked program.
+//!
+//! ```rust,ignore
+//! let mut setup = UniformBufferSetup::default();
+//! setup.size = 128;
+//! let ubo = graphics.create_uniform_buffer(setup, None)?;
+//!
+//! // Updates the buffer once per frame.
+//! graphics.update_uniform_buffer(ubo, 0, bytes)?;
+//!
+//! // Binds the buffer to the uniform block `Camera` of shader.
+//! dc.set_uniform_buffer("Camera", ubo);
+//! ```
+//!
+//! ### Compute Shader
+//!
+//! Compute shaders run outside of the render pipeline on devices with the
+//! `compute_shader` capability, and usually write their results into storage buffers,
+//! which could be bound to the storage blocks of the following draw calls.
+//!
+//! ```rust,ignore
+//! let mut setup = StorageBufferSetup::default();
+//! setup.size = 16 * 1024;
+//! let ssbo = graphics.create_storage_buffer(setup, None)?;
+//!
+//! let mut setup = ComputeShaderSetup::default();
+//! setup.storage_blocks.push("Particles".into());
+//! setup.cs = cs.into();
+//! let shader = graphics.create_compute_shader(location, setup)?;
+//!
+//! // Launches 64 work groups, a memory barrier is issued after dispatching.
+//! let mut dispatch = Dispatch::new(shader);
+//! dispatch.set_storage_buffer("Particles", ssbo);
+//! graphics.submit(surface, 0, dispatch.build((64, 1, 1))?)?;
+//! ```
+//!
+//! ### Lifetime
+//!
+//! Textures, meshes and shaders of the same shared `Location` are reference counted,
+//! every `create_*` increases the count and `delete_*` decreases it. The resource is
+//! deleted after the drawing of the frame in which the count reaches zero. Instead of
+//! deleting explicitly, a handle could be wrapped into `OwnedHandle`, which releases
+//! its reference automatically when the last clone of it is dropped.
+//!
+//! ```rust,ignore
+//! let texture = OwnedHandle::new(video.clone(), video.create_texture_from::<Parser>(location, setup)?);
+//! ```
+//!
+//! ### Secondary Windows
+//!
+//! Tools like inspectors could open secondary windows with `Engine::create_window`.
+//! Their OpenGL contexts share textures, meshes and shaders with the primary window,
+//! and surfaces present to them with `SurfaceSetup::set_window`. Framebuffers are never
+//! shared between contexts, so render into a texture with surfaces of primary window
+//! and draw it on secondary windows instead.
+//!
+//! ```rust,ignore
+//! let window = engine.create_window(WindowBuilder::new())?;
+//!
+//! let mut setup = SurfaceSetup::default();
+//! setup.set_window(window);
+//! let surface = graphics.create_surface(setup)?;
+//! ```
+//!
 //! # Commands
 //!
 //! There are two kinds of commands that could be submitted into `Surface` object, the
@@ -146,6 +244,16 @@
 //! can apply different sorting criteria for the same array of draw calls, as long as
 //! u know how the keys were built.
 //!
+//! The sorting is stable, commands with the same key in one surface are executed in
+//! the order they are submitted. `Command::order` composes a key from a layer index in
+//! the high `ORDER_LAYER_BITS` bits and a normalized depth in the low bits, e.g. for
+//! drawing translucent objects back-to-front after the opaque ones.
+//!
+//! ```rust,ignore
+//! graphics.submit(surface, Command::order(0, depth), opaque).unwrap();
+//! graphics.submit(surface, Command::order(1, 1.0 - depth), translucent).unwrap();
+//! ```
+//!
 //! ```rust,ignore
 //! // Updates the vertex buffer object.
 //! let slice = Vertex::as_bytes(vertices);
@@ -180,6 +288,28 @@
 //! let cmd = dc.build(from, len)?;
 //! self.video.submit(self.surface, 0, cmd).unwrap();
 //! ```
+//!
+//! Meshes could also be drawn many times with one draw call, the per-instance attributes
+//! are tightly packed `f32` components described by an `AttributeLayout`:
+//!
+//! ```rust,ignore
+//! let layout = AttributeLayout::build().with(Attribute::Texcoord3, 4).finish();
+//! let cmd = dc.build_instanced(MeshIndex::All, layout, offsets, 128)?;
+//! self.video.submit(self.surface, 0, cmd).unwrap();
+//! ```
+//!
+//! ### Frame Capture
+//!
+//! When a draw looks wrong, the commands of a frame could be captured right before they
+//! are dispatched with `GraphicsSystemShared::capture_next_frame`. The `FrameCapture`
+//! lists every task with the uniform variables resolved into their names and values,
+//! and could be printed or diffed between frames.
+//!
+//! ```rust,ignore
+//! let rx = video.capture_next_frame();
+//! // ... submits the commands and advances the frame.
+//! println!("{}", rx.recv()?);
+//! ```
 
 mod backend;
 #[macro_use]
@@ -190,6 +320,12 @@ pub mod graphics;
 pub mod window;
 pub mod guard;
 pub mod command;
+pub mod fence;
+pub mod atlas;
+pub mod sprite;
+mod screenshot;
+pub mod leaks;
+pub mod capture;
 
 pub use self::assets::surface::*;
 pub use self::assets::shader::*;
@@ -199,12 +335,23 @@ pub use self::assets::mesh_loader::{MeshData, MeshParser};
 
 pub use self::assets::texture::*;
 pub use self::assets::texture_loader::{TextureData, TextureParser};
+pub use self::assets::texture_container::TextureContainerParser;
+
+pub use self::assets::uniform_buffer::*;
+pub use self::assets::storage_buffer::*;
 
-pub use self::graphics::{GraphicsSystem, GraphicsSystemShared};
-pub use self::window::{Window, WindowBuilder};
+pub use self::graphics::{GraphicsSetup, GraphicsSystem, GraphicsSystemShared};
+pub use self::backend::{Capabilities, Profile, Version};
+pub use self::window::{Window, WindowBuilder, WindowHandle};
 
-pub use self::guard::RAIIGuard;
-pub use self::command::{Command, DrawCall};
+pub use self::guard::{OwnedHandle, OwnedResource, RAIIGuard};
+pub use self::command::{Command, Dispatch, DrawCall, ORDER_DEPTH_BITS, ORDER_DEPTH_MASK,
+                        ORDER_LAYER_BITS};
+pub use self::fence::FenceHandle;
+pub use self::atlas::{AtlasRegion, TextureAtlas};
+pub use self::sprite::SpriteBatch;
+pub use self::leaks::{ResourceKind, ResourceLeak};
+pub use self::capture::{CapturedTask, FrameCapture};
 
 /// Maximum number of attributes in vertex layout.
 pub const MAX_VERTEX_ATTRIBUTES: usize = 12;
@@ -229,4 +376,12 @@ pub struct GraphicsFrameInfo {
     pub alive_meshes: u32,
     pub alive_textures: u32,
     pub alive_render_buffers: u32,
+    pub alive_uniform_buffers: u32,
+    pub alive_storage_buffers: u32,
+    /// The high-water mark of bytes submitted during a frame, which could be used to
+    /// tune `GraphicsSetup::frame_capacity`.
+    pub frame_buffer_peak: usize,
+    /// The number of tasks that failed in backend, which are skipped so the rest of
+    /// frame still presents. Use `GraphicsSystemShared::failures` for the details.
+    pub errors: u32,
 }
@@ -0,0 +1,93 @@
+//! Encodes the pixels read back from framebuffer into PNG images.
+
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+
+use png;
+use png::HasParameters;
+
+use super::errors::*;
+
+/// Flips the rows of RGBA8 `pixels` with `width`, which converts the bottom-left
+/// origin of OpenGL into the top-left origin of images.
+pub(crate) fn flip_rows(pixels: &mut [u8], width: u32) {
+    let stride = width as usize * 4;
+    if stride == 0 {
+        return;
+    }
+
+    let rows = pixels.len() / stride;
+    let mut row = vec![0u8; stride];
+    for i in 0..rows / 2 {
+        let (top, bottom) = (i * stride, (rows - i - 1) * stride);
+        row.copy_from_slice(&pixels[top..top + stride]);
+
+        {
+            let (head, tail) = pixels.split_at_mut(bottom);
+            head[top..top + stride].copy_from_slice(&tail[..stride]);
+        }
+
+        pixels[bottom..bottom + stride].copy_from_slice(&row);
+    }
+}
+
+/// Writes RGBA8 `pixels` that read back from framebuffer into `path` as PNG image.
+/// The values are written as they are, so the image looks the same as displayed.
+pub(crate) fn write_png(path: &Path, dimensions: (u32, u32), mut pixels: Vec<u8>) -> Result<()> {
+    let len = dimensions.0 as usize * dimensions.1 as usize * 4;
+    if dimensions.0 == 0 || dimensions.1 == 0 || pixels.len() != len {
+        bail!(format!(
+            "Invalid pixels ({} bytes) of {:?} screenshot.",
+            pixels.len(),
+            dimensions
+        ));
+    }
+
+    flip_rows(&mut pixels, dimensions.0);
+
+    let file = match fs::File::create(path) {
+        Ok(file) => file,
+        Err(err) => bail!(format!("Failed to create {:?}. {}", path, err)),
+    };
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), dimensions.0, dimensions.1);
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+
+    let result = encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels));
+
+    if let Err(err) = result {
+        bail!(format!("Failed to encode {:?}. {}", path, err));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flip() {
+        // Rows of 3 pixels are not aligned to 4 pixels.
+        let mut pixels: Vec<u8> = (0..36).collect();
+        flip_rows(&mut pixels, 3);
+        assert_eq!(&pixels[0..12], &(24..36).collect::<Vec<u8>>()[..]);
+        assert_eq!(&pixels[12..24], &(12..24).collect::<Vec<u8>>()[..]);
+        assert_eq!(&pixels[24..36], &(0..12).collect::<Vec<u8>>()[..]);
+
+        let mut pixels: Vec<u8> = (0..8).collect();
+        flip_rows(&mut pixels, 1);
+        assert_eq!(pixels, [4, 5, 6, 7, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn invalid() {
+        let path = Path::new("invalid.png");
+        assert!(write_png(path, (0, 0), Vec::new()).is_err());
+        assert!(write_png(path, (2, 2), vec![0; 15]).is_err());
+        assert!(!path.exists());
+    }
+}
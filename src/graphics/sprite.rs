@@ -0,0 +1,351 @@
+//! Batches textured quads of 2D rendering into a minimal number of draw calls.
+
+use std::sync::Arc;
+
+use math;
+use math::SquareMatrix;
+use resource::Location;
+use utils::Color;
+
+use super::*;
+use super::errors::*;
+
+impl_vertex! {
+    SpriteVertex {
+        position => [Position; Float; 2; false],
+        texcoord => [Texcoord0; Float; 2; false],
+        color => [Color0; UByte; 4; true],
+    }
+}
+
+/// The maximum number of sprites that could be drawn with one `SpriteBatch` during a
+/// frame, which is limited by the 16-bits indices.
+pub const MAX_SPRITES: usize = 16384;
+
+/// The shared location of the sprite shader. It's also known as
+/// `scene::factory::shader::SPRITE`, and kept unchanged for the saved scenes.
+pub const SHADER: &str = "__Core/Scene/Shader/SPRITE";
+
+/// Creates the shader of sprites, which modulates the texture with vertex color and
+/// blends with the alpha channel.
+pub fn shader(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+    let location = Location::shared(0, SHADER);
+    let attributes = AttributeLayout::build()
+        .with(Attribute::Position, 2)
+        .with(Attribute::Texcoord0, 2)
+        .with(Attribute::Color0, 4)
+        .finish();
+
+    let mut setup = ShaderSetup::default();
+    setup.render_state = RenderState::translucent();
+    setup.layout = attributes;
+    setup.vs = include_str!("assets/sprite.vs").to_owned();
+    setup.fs = include_str!("assets/sprite.fs").to_owned();
+
+    let uvs = [
+        ("u_MVPMatrix", UniformVariableType::Matrix4f),
+        ("u_Texture", UniformVariableType::Texture),
+    ];
+
+    for &(field, tt) in &uvs {
+        setup.uniform_variables.insert(field.into(), tt);
+    }
+
+    video.create_shader(location, setup)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sprite {
+    shader: ShaderHandle,
+    texture: TextureHandle,
+    verts: [SpriteVertex; 4],
+}
+
+/// A `SpriteBatch` accumulates textured quads, and flushes them as a minimal number of
+/// draw calls with one dynamic mesh.
+///
+/// ```rust,ignore
+/// let mut batch = SpriteBatch::new(video.clone())?;
+/// batch.set_matrix(Camera::ortho_2d(width, height).projection_matrix());
+///
+/// batch.draw(texture, ((0.0, 0.0), (32.0, 32.0)), ((0.0, 0.0), (1.0, 1.0)), Color::white());
+/// batch.flush(surface, 0)?;
+/// ```
+///
+/// The sprites are sorted by shader and texture before flushing, the ones with the same
+/// texture keep the order they were drawn, which is essential for alpha blending. The
+/// sorting could be disabled if the order between different textures matters too,
+/// draw calls will be broken whenever the texture changes in that case.
+pub struct SpriteBatch {
+    video: Arc<GraphicsSystemShared>,
+    shader: ShaderHandle,
+    default_shader: ShaderHandle,
+    matrix: math::Matrix4<f32>,
+    sorting: bool,
+    sprites: Vec<Sprite>,
+
+    mesh: Option<(usize, MeshHandle)>,
+    /// The frame index and the number of sprites that have been uploaded during it.
+    cursor: (u64, usize),
+    verts: Vec<SpriteVertex>,
+}
+
+impl SpriteBatch {
+    /// Creates a new `SpriteBatch` with the built-in sprite shader.
+    pub fn new(video: Arc<GraphicsSystemShared>) -> Result<Self> {
+        let shader = self::shader(&video)?;
+
+        Ok(SpriteBatch {
+            video: video,
+            shader: shader,
+            default_shader: shader,
+            matrix: math::Matrix4::identity(),
+            sorting: true,
+            sprites: Vec::new(),
+            mesh: None,
+            cursor: (0, 0),
+            verts: Vec::new(),
+        })
+    }
+
+    /// Sets the view-projection matrix of sprites, e.g. the projection matrix of a 2D
+    /// camera. It's the identity matrix by default.
+    #[inline]
+    pub fn set_matrix(&mut self, matrix: math::Matrix4<f32>) {
+        self.matrix = matrix;
+    }
+
+    /// Sets the shader of sprites drawn after this, `None` restores the default one.
+    /// The shader should have the same vertex layout as the default one, and uniforms
+    /// `u_MVPMatrix` and `u_Texture`.
+    #[inline]
+    pub fn set_shader<T>(&mut self, shader: T)
+    where
+        T: Into<Option<ShaderHandle>>,
+    {
+        self.shader = shader.into().unwrap_or(self.default_shader);
+    }
+
+    /// Enables or disables the sorting by shader and texture. It's enabled by default.
+    #[inline]
+    pub fn set_sorting(&mut self, enable: bool) {
+        self.sorting = enable;
+    }
+
+    /// Gets the number of sprites that waiting for flushing.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    /// Returns true if there is no sprite waiting for flushing.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Draws a quad of `texture`. The `dst` and `uv` rects are in the form of
+    /// `(position, size)`, with `position` at the bottom-left corner.
+    pub fn draw(
+        &mut self,
+        texture: TextureHandle,
+        dst: ((f32, f32), (f32, f32)),
+        uv: ((f32, f32), (f32, f32)),
+        color: Color,
+    ) {
+        let ((x, y), (w, h)) = dst;
+        let ((u, v), (uw, vh)) = uv;
+        let color: [u8; 4] = color.into();
+
+        let verts = [
+            SpriteVertex::new([x, y], [u, v], color),
+            SpriteVertex::new([x + w, y], [u + uw, v], color),
+            SpriteVertex::new([x + w, y + h], [u + uw, v + vh], color),
+            SpriteVertex::new([x, y + h], [u, v + vh], color),
+        ];
+
+        self.sprites.push(Sprite {
+            shader: self.shader,
+            texture: texture,
+            verts: verts,
+        });
+    }
+
+    /// Submits all the sprites into `surface` with `order`, and clears the batch. The
+    /// batch could be flushed several times during a frame.
+    pub fn flush<T>(&mut self, surface: SurfaceHandle, order: T) -> Result<()>
+    where
+        T: Into<u64>,
+    {
+        if self.sprites.is_empty() {
+            return Ok(());
+        }
+
+        let order = order.into();
+        if self.sorting {
+            // The sort is stable, so sprites with the same texture keep their order.
+            self.sprites.sort_by_key(|v| (v.shader, v.texture));
+        }
+
+        let mut sprites = Vec::new();
+        ::std::mem::swap(&mut sprites, &mut self.sprites);
+
+        let result = self.submit(surface, order, &sprites);
+        sprites.clear();
+        self.sprites = sprites;
+        result
+    }
+
+    fn submit(&mut self, surface: SurfaceHandle, order: u64, sprites: &[Sprite]) -> Result<()> {
+        let (mesh, from) = self.reserve(sprites.len())?;
+
+        self.verts.clear();
+        for v in sprites {
+            self.verts.extend_from_slice(&v.verts);
+        }
+
+        let offset = from * 4 * ::std::mem::size_of::<SpriteVertex>();
+        let slice = SpriteVertex::as_bytes(&self.verts);
+        let cmd = Command::update_vertex_buffer(mesh, offset, slice);
+        self.video.submit(surface, order, cmd)?;
+
+        for (shader, texture, start, len) in batches(sprites) {
+            let mut dc = DrawCall::new(shader, mesh);
+            dc.set_uniform_variable("u_MVPMatrix", self.matrix);
+            dc.set_uniform_variable("u_Texture", texture);
+
+            let cmd = dc.build_from((from + start) * 6, len * 6)?;
+            self.video.submit(surface, order, cmd)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reserves room for `len` sprites in the dynamic mesh. The sprites of different
+    /// flushes during a frame are placed after each other, so they will not override
+    /// the others before being drawn.
+    fn reserve(&mut self, len: usize) -> Result<(MeshHandle, usize)> {
+        let frame_index = self.video.frame_index();
+        if self.cursor.0 != frame_index {
+            self.cursor = (frame_index, 0);
+        }
+
+        // The total number of sprites during this frame, which is used to make sure that
+        // the mesh will not be recreated again next frame.
+        let required = self.cursor.1 + len;
+
+        if let Some((capacity, mesh)) = self.mesh {
+            if required <= capacity {
+                let from = self.cursor.1;
+                self.cursor.1 = required;
+                return Ok((mesh, from));
+            }
+
+            // The old mesh will be deleted after the drawing of this frame.
+            self.video.delete_mesh(mesh);
+            self.mesh = None;
+        }
+
+        if len > MAX_SPRITES {
+            bail!("Too many sprites to draw with one SpriteBatch during a frame.");
+        }
+
+        let mut capacity = 64;
+        while capacity < required && capacity < MAX_SPRITES {
+            capacity *= 2;
+        }
+
+        let mut idxes = Vec::with_capacity(capacity * 6);
+        for i in 0..capacity {
+            let v = (i * 4) as u16;
+            idxes.extend_from_slice(&[v, v + 1, v + 2, v + 2, v + 3, v]);
+        }
+
+        let mut setup = MeshSetup::default();
+        setup.hint = BufferHint::Stream;
+        // The quads always share the same indices.
+        setup.index_hint = Some(BufferHint::Static);
+        setup.layout = SpriteVertex::layout();
+        setup.index_format = IndexFormat::U16;
+        setup.primitive = Primitive::Triangles;
+        setup.num_verts = capacity * 4;
+        setup.num_idxes = capacity * 6;
+
+        let verts: Option<&[u8]> = None;
+        let ibytes = IndexFormat::as_bytes::<u16>(&idxes);
+        let mesh = self.video
+            .create_mesh(Location::unique(""), setup, verts, ibytes)?;
+
+        self.mesh = Some((capacity, mesh));
+        self.cursor.1 = len;
+        Ok((mesh, 0))
+    }
+}
+
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        if let Some((_, mesh)) = self.mesh.take() {
+            self.video.delete_mesh(mesh);
+        }
+
+        self.video.delete_shader(self.default_shader);
+    }
+}
+
+/// Splits sprites into batches with the same shader and texture, each is in the form of
+/// `(shader, texture, start, len)`.
+fn batches(sprites: &[Sprite]) -> Vec<(ShaderHandle, TextureHandle, usize, usize)> {
+    let mut batches: Vec<(ShaderHandle, TextureHandle, usize, usize)> = Vec::new();
+
+    for (i, v) in sprites.iter().enumerate() {
+        if let Some(last) = batches.last_mut() {
+            if last.0 == v.shader && last.1 == v.texture {
+                last.3 += 1;
+                continue;
+            }
+        }
+
+        batches.push((v.shader, v.texture, i, 1));
+    }
+
+    batches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::Handle;
+
+    fn sprite(texture: u32, x: f32) -> Sprite {
+        let v = SpriteVertex::new([x, 0.0], [0.0, 0.0], [255, 255, 255, 255]);
+        Sprite {
+            shader: Handle::new(1, 1).into(),
+            texture: Handle::new(texture, 1).into(),
+            verts: [v; 4],
+        }
+    }
+
+    #[test]
+    fn batching() {
+        let mut sprites = vec![
+            sprite(2, 0.0),
+            sprite(1, 1.0),
+            sprite(2, 2.0),
+            sprite(1, 3.0),
+            sprite(2, 4.0),
+        ];
+
+        let v = batches(&sprites);
+        assert_eq!(v.len(), 5);
+
+        sprites.sort_by_key(|v| (v.shader, v.texture));
+        let v = batches(&sprites);
+        assert_eq!(v.len(), 2);
+        assert_eq!((v[0].2, v[0].3), (0, 2));
+        assert_eq!((v[1].2, v[1].3), (2, 3));
+
+        // Sprites with the same texture are kept in the order of drawing.
+        let xs: Vec<_> = sprites.iter().map(|v| v.verts[0].position[0]).collect();
+        assert_eq!(xs, vec![1.0, 3.0, 0.0, 2.0, 4.0]);
+    }
+}
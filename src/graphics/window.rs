@@ -1,7 +1,7 @@
 //! An OpenGL context and the environment around it.
 
 use std::default::Default;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use gl;
 
@@ -11,11 +11,16 @@ use glutin::GlContext;
 use super::backend::capabilities::{Capabilities, Version};
 use super::errors::*;
 
+impl_handle!(WindowHandle);
+
 /// Represents an OpenGL context and the Window or environment around it, its just
 /// simple wrappers to [glutin](https://github.com/tomaka/glutin) right now.
 pub struct Window {
     window: Arc<glutin::GlWindow>,
     capabilities: Capabilities,
+    vsync: bool,
+    /// Whether the cursor is grabbed and visible.
+    cursor: Mutex<(bool, bool)>,
 }
 
 impl Window {
@@ -101,6 +106,59 @@ impl Window {
         self.window.hidpi_factor()
     }
 
+    /// Confines the cursor to the client area of window, and keeps it from leaving
+    /// when the window is focused.
+    ///
+    /// # Platform-specific
+    ///
+    /// The cursor is also hidden when grabbed on some platforms. Has no effect on
+    /// mobile platform.
+    pub fn set_cursor_grab(&self, grab: bool) -> Result<()> {
+        let mut cursor = self.cursor.lock().unwrap();
+        self.set_cursor_state(grab, cursor.1)?;
+        cursor.0 = grab;
+        Ok(())
+    }
+
+    /// Shows or hides the cursor when it's over the window.
+    pub fn set_cursor_visible(&self, visible: bool) -> Result<()> {
+        let mut cursor = self.cursor.lock().unwrap();
+        self.set_cursor_state(cursor.0, visible)?;
+        cursor.1 = visible;
+        Ok(())
+    }
+
+    /// Moves the cursor to `position`, which is in the same coordinates of the
+    /// `MouseMoved` events relative to the top-left corner of window.
+    ///
+    /// # Platform-specific
+    ///
+    /// A `MouseMoved` event might be fired by the warping on some platforms.
+    pub fn set_cursor_position(&self, position: (f32, f32)) -> Result<()> {
+        let (x, y) = (position.0.round() as i32, position.1.round() as i32);
+        if self.window.set_cursor_position(x, y).is_err() {
+            bail!(format!("Failed to move the cursor to {:?}.", position));
+        }
+
+        Ok(())
+    }
+
+    fn set_cursor_state(&self, grab: bool, visible: bool) -> Result<()> {
+        let state = if grab {
+            glutin::CursorState::Grab
+        } else if !visible {
+            glutin::CursorState::Hide
+        } else {
+            glutin::CursorState::Normal
+        };
+
+        if let Err(err) = self.window.set_cursor_state(state) {
+            bail!(format!("Failed to set cursor state {:?}. {}", state, err));
+        }
+
+        Ok(())
+    }
+
     /// Set the context as the active context in this thread.
     #[inline]
     pub fn make_current(&self) -> Result<()> {
@@ -122,6 +180,19 @@ impl Window {
         &self.capabilities
     }
 
+    /// Returns the platform identifier of window, which is used to route the events of
+    /// secondary windows.
+    #[inline]
+    pub(crate) fn id(&self) -> glutin::WindowId {
+        self.window.id()
+    }
+
+    /// Returns true if vsync was requested when creating this window.
+    #[inline]
+    pub fn is_vsync(&self) -> bool {
+        self.vsync
+    }
+
     /// Swaps the buffers in case of double or triple buffering.
     ///
     /// **Warning**: if you enabled vsync, this function will block until the
@@ -161,6 +232,7 @@ pub struct WindowBuilder {
     size: (u32, u32),
     vsync: bool,
     multisample: u16,
+    srgb: bool,
     api: OpenGLAPI,
     profile: OpenGLProfile,
 }
@@ -171,6 +243,17 @@ impl WindowBuilder {
     }
 
     pub fn build(self, events: &glutin::EventsLoop) -> Result<Window> {
+        self.build_with(events, None)
+    }
+
+    /// Builds a secondary window whose OpenGL context shares objects with the context
+    /// of `shared`, e.g. textures, meshes and shaders. Notes that container objects like
+    /// framebuffers are never shared between contexts.
+    pub fn build_shared(self, events: &glutin::EventsLoop, shared: &Window) -> Result<Window> {
+        self.build_with(events, Some(shared))
+    }
+
+    fn build_with(self, events: &glutin::EventsLoop, shared: Option<&Window>) -> Result<Window> {
         let profile = match self.profile {
             OpenGLProfile::Core => glutin::GlProfile::Core,
             OpenGLProfile::Compatibility => glutin::GlProfile::Compatibility,
@@ -191,12 +274,19 @@ impl WindowBuilder {
             .with_dimensions(self.size.0, self.size.1)
             .with_multitouch();
 
-        let context = glutin::ContextBuilder::new()
+        let mut context = glutin::ContextBuilder::new()
             .with_multisampling(self.multisample)
+            .with_srgb(self.srgb)
+            .with_depth_buffer(24)
+            .with_stencil_buffer(8)
             .with_gl_profile(profile)
             .with_gl(api)
             .with_vsync(self.vsync);
 
+        if let Some(shared) = shared {
+            context = context.with_shared_lists(shared.window.context());
+        }
+
         let window = glutin::GlWindow::new(window, context, events)?;
 
         let capabilities = unsafe {
@@ -205,11 +295,19 @@ impl WindowBuilder {
             Capabilities::parse()?
         };
 
+        // Restores the context of primary window, which is the current one of render
+        // thread by default.
+        if let Some(shared) = shared {
+            shared.make_current()?;
+        }
+
         println!("{:#?}", capabilities);
         check_minimal_requirements(&capabilities)?;
         Ok(Window {
             window: Arc::new(window),
             capabilities: capabilities,
+            vsync: self.vsync,
+            cursor: Mutex::new((false, true)),
         })
     }
 
@@ -234,6 +332,14 @@ impl WindowBuilder {
         self
     }
 
+    /// Requests that the swapping of buffers should be synchronized with the
+    /// vertical refresh of screen.
+    #[inline]
+    pub fn with_vsync(&mut self, vsync: bool) -> &mut Self {
+        self.vsync = vsync;
+        self
+    }
+
     /// Sets the multisampling level to request. A value of 0 indicates that
     /// multisampling must not be enabled.
     #[inline]
@@ -242,6 +348,14 @@ impl WindowBuilder {
         self
     }
 
+    /// Requests a default framebuffer in sRGB space, which encodes the linear colors
+    /// written by surfaces with `SurfaceSetup::set_srgb`.
+    #[inline]
+    pub fn with_srgb(&mut self, srgb: bool) -> &mut Self {
+        self.srgb = srgb;
+        self
+    }
+
     /// Sets the desired OpenGL context profile.
     #[inline]
     pub fn with_profile(&mut self, profile: OpenGLProfile) -> &mut Self {
@@ -265,6 +379,7 @@ impl Default for WindowBuilder {
             size: (512, 512),
             vsync: false,
             multisample: 0,
+            srgb: false,
             api: OpenGLAPI::Lastest,
             profile: OpenGLProfile::Core,
         }
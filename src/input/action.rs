@@ -0,0 +1,390 @@
+//! Named actions that bind to one or more physical inputs.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use application::event::{GamepadAxis, GamepadButton, KeyboardButton, MouseButton};
+
+use super::MAX_GAMEPADS;
+use super::errors::*;
+use super::input::InputSystemShared;
+
+/// The physical input that an action could be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActionBinding {
+    Key(KeyboardButton),
+    Mouse(MouseButton),
+    /// The button of any connected gamepad.
+    Gamepad(GamepadButton),
+}
+
+/// The per-frame states of one binding.
+#[derive(Debug, Clone, Copy, Default)]
+struct BindingState {
+    down: bool,
+    press: bool,
+    release: bool,
+}
+
+impl BindingState {
+    /// Returns true if the binding is held down at the end of last frame.
+    fn was_down(&self) -> bool {
+        !self.press && (self.down || self.release)
+    }
+}
+
+/// A `ActionMap` maps named actions like `"jump"` to physical inputs, which makes
+/// it possible to rebind inputs without touching the game code.
+///
+/// ```rust,ignore
+/// let mut actions = ActionMap::new(ctx.shared::<InputSystem>().clone());
+/// actions.bind("jump", ActionBinding::Key(KeyboardButton::Space));
+/// actions.bind("jump", ActionBinding::Gamepad(GamepadButton::A));
+///
+/// if actions.just_pressed("jump") {
+///     ...
+/// }
+/// ```
+///
+/// The binding table could be saved into and loaded from a plain text, with one
+/// action per line:
+///
+/// ```text
+/// fire: Mouse(Left), Key(LControl)
+/// jump: Key(Space), Gamepad(A)
+/// ```
+pub struct ActionMap {
+    input: Arc<InputSystemShared>,
+    bindings: HashMap<String, Vec<ActionBinding>>,
+}
+
+impl ActionMap {
+    /// Creates a new and empty `ActionMap`.
+    pub fn new(input: Arc<InputSystemShared>) -> Self {
+        ActionMap {
+            input: input,
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Binds the `action` to a physical input.
+    pub fn bind<T>(&mut self, action: T, binding: ActionBinding)
+    where
+        T: Into<String>,
+    {
+        let bindings = self.bindings.entry(action.into()).or_insert_with(Vec::new);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes all the bindings of `action`.
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    /// Removes all the actions.
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// Gets the bindings of `action`.
+    pub fn bindings(&self, action: &str) -> &[ActionBinding] {
+        self.bindings
+            .get(action)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Checks if any input of `action` is currently held down.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.states(action).any(|v| v.down)
+    }
+
+    /// Checks if the `action` has been pressed during last frame. Notes that it will
+    /// not be triggered again if another input of `action` is still held down.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        let (mut press, mut was_down) = (false, false);
+        for v in self.states(action) {
+            press = press || v.press;
+            was_down = was_down || v.was_down();
+        }
+
+        press && !was_down
+    }
+
+    /// Checks if the `action` has been released during last frame, which means none
+    /// of its inputs is held down anymore.
+    pub fn just_released(&self, action: &str) -> bool {
+        let (mut release, mut down) = (false, false);
+        for v in self.states(action) {
+            release = release || v.release;
+            down = down || v.down;
+        }
+
+        release && !down
+    }
+
+    /// Replaces the binding table with `table`. The old bindings are kept if there is
+    /// any error.
+    pub fn load(&mut self, table: &str) -> Result<()> {
+        let mut bindings = HashMap::new();
+
+        for (i, line) in table.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |desc: &str| ErrorKind::MalformedBinding(i + 1, desc.into());
+
+            let mut iter = line.splitn(2, ':');
+            let action = iter.next().unwrap().trim();
+            if action.is_empty() {
+                bail!(err("action name is required"));
+            }
+
+            let mut v = Vec::new();
+            for binding in iter.next().unwrap_or("").split(',') {
+                let binding = binding.trim();
+                if !binding.is_empty() {
+                    v.push(parse(binding).ok_or_else(|| err(binding))?);
+                }
+            }
+
+            bindings.insert(action.to_owned(), v);
+        }
+
+        self.bindings = bindings;
+        Ok(())
+    }
+
+    /// Saves the binding table into plain text, the actions are sorted by name.
+    pub fn save(&self) -> String {
+        let mut actions: Vec<_> = self.bindings.iter().collect();
+        actions.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+
+        let mut table = String::new();
+        for (action, bindings) in actions {
+            let bindings: Vec<_> = bindings.iter().map(|v| format!("{:?}", v)).collect();
+            writeln!(table, "{}: {}", action, bindings.join(", ")).unwrap();
+        }
+
+        table
+    }
+
+    fn states<'a>(&'a self, action: &str) -> impl Iterator<Item = BindingState> + 'a {
+        let input = &self.input;
+        self.bindings(action).iter().map(move |v| match *v {
+            ActionBinding::Key(key) => BindingState {
+                down: input.is_key_down(key),
+                press: input.is_key_press(key),
+                release: input.is_key_release(key),
+            },
+            ActionBinding::Mouse(button) => BindingState {
+                down: input.is_mouse_down(button),
+                press: input.is_mouse_press(button),
+                release: input.is_mouse_release(button),
+            },
+            ActionBinding::Gamepad(button) => {
+                let mut state = BindingState::default();
+                for id in 0..MAX_GAMEPADS {
+                    state.down = state.down || input.gamepad_button(id, button);
+                    state.press = state.press || input.is_gamepad_button_press(id, button);
+                    state.release = state.release || input.is_gamepad_button_release(id, button);
+                }
+                state
+            }
+        })
+    }
+}
+
+fn parse(binding: &str) -> Option<ActionBinding> {
+    if !binding.ends_with(')') {
+        return None;
+    }
+
+    let mut iter = binding[..binding.len() - 1].splitn(2, '(');
+    let (kind, name) = (iter.next()?.trim(), iter.next()?.trim());
+
+    match kind {
+        "Key" => parse_key(name).map(ActionBinding::Key),
+        "Mouse" => parse_mouse(name).map(ActionBinding::Mouse),
+        "Gamepad" => parse_gamepad(name).map(ActionBinding::Gamepad),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_mouse(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        _ => {
+            if name.starts_with("Other(") && name.ends_with(')') {
+                name[6..name.len() - 1].parse().ok().map(MouseButton::Other)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+macro_rules! parse_by_names {
+    ($name: ident, $tt: ident, [$($variant: ident),*]) => (
+        match $name {
+            $(stringify!($variant) => Some($tt::$variant),)*
+            _ => None,
+        }
+    )
+}
+
+pub(super) fn parse_gamepad(name: &str) -> Option<GamepadButton> {
+    parse_by_names!(name, GamepadButton, [
+        A, B, X, Y, LeftShoulder, RightShoulder, LeftThumb, RightThumb, Back, Start,
+        Guide, DPadUp, DPadDown, DPadLeft, DPadRight
+    ])
+}
+
+pub(super) fn parse_gamepad_axis(name: &str) -> Option<GamepadAxis> {
+    parse_by_names!(name, GamepadAxis, [
+        LeftStickX, LeftStickY, RightStickX, RightStickY, LeftTrigger, RightTrigger
+    ])
+}
+
+// Defines `KEYS` along with an exhaustive match, so adding a variant to
+// `KeyboardButton` without listing it here fails to compile.
+macro_rules! key_table {
+    ($($variant: ident),*) => (
+        /// Every keyboard key with the name it is written as in binding tables, which
+        /// is the same as its `Debug` representation.
+        pub(super) const KEYS: &[(&str, KeyboardButton)] = &[
+            $((stringify!($variant), KeyboardButton::$variant)),*
+        ];
+
+        #[allow(dead_code)]
+        fn exhaustive_keys(key: KeyboardButton) {
+            match key {
+                $(KeyboardButton::$variant)|* => (),
+            }
+        }
+    )
+}
+
+key_table![
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15,
+    Snapshot, Scroll, Pause,
+    Insert, Home, Delete, End, PageDown, PageUp, Left, Up, Right, Down,
+    Back, Return, Space, Compose, Numlock,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8,
+    Numpad9,
+    AbntC1, AbntC2, Add, Apostrophe, Apps, At, Ax, Backslash, Calculator, Capital, Colon,
+    Comma, Convert, Decimal, Divide, Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl,
+    LMenu, LShift, LWin, Mail, MediaSelect, MediaStop, Minus, Multiply, Mute, MyComputer,
+    NavigateForward, NavigateBackward, NextTrack, NoConvert, NumpadComma, NumpadEnter,
+    NumpadEquals, OEM102, Period, PlayPause, Power, PrevTrack, RAlt, RBracket, RControl,
+    RMenu, RShift, RWin, Semicolon, Slash, Sleep, Stop, Subtract, Sysrq, Tab, Underline,
+    Unlabeled, VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward, WebHome,
+    WebRefresh, WebSearch, WebStop, Yen
+];
+
+pub(super) fn parse_key(name: &str) -> Option<KeyboardButton> {
+    KEYS.iter().find(|v| v.0 == name).map(|v| v.1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use application::event::InputDeviceEvent;
+    use input::{InputSetup, InputSystem};
+
+    #[test]
+    fn edges() {
+        let mut input = InputSystem::new(InputSetup::default());
+        let mut actions = ActionMap::new(input.shared());
+        actions.bind("jump", ActionBinding::Key(KeyboardButton::Space));
+        actions.bind("jump", ActionBinding::Mouse(MouseButton::Left));
+
+        input.update_with(InputDeviceEvent::KeyboardPressed {
+            key: KeyboardButton::Space,
+        });
+        assert!(actions.pressed("jump"));
+        assert!(actions.just_pressed("jump"));
+        assert!(!actions.pressed("fire"));
+
+        // Another binding is pressed while the action is already held down.
+        input.advance(1.0);
+        input.update_with(InputDeviceEvent::MousePressed {
+            button: MouseButton::Left,
+        });
+        assert!(actions.pressed("jump"));
+        assert!(!actions.just_pressed("jump"));
+
+        input.advance(1.0);
+        input.update_with(InputDeviceEvent::KeyboardReleased {
+            key: KeyboardButton::Space,
+        });
+        assert!(actions.pressed("jump"));
+        assert!(!actions.just_released("jump"));
+
+        input.advance(1.0);
+        input.update_with(InputDeviceEvent::MouseReleased {
+            button: MouseButton::Left,
+        });
+        assert!(!actions.pressed("jump"));
+        assert!(actions.just_released("jump"));
+
+        // Pressed and released during one frame.
+        input.advance(1.0);
+        input.update_with(InputDeviceEvent::KeyboardPressed {
+            key: KeyboardButton::Space,
+        });
+        input.update_with(InputDeviceEvent::KeyboardReleased {
+            key: KeyboardButton::Space,
+        });
+        assert!(actions.just_pressed("jump"));
+        assert!(actions.just_released("jump"));
+    }
+
+    #[test]
+    fn table() {
+        let input = InputSystem::new(InputSetup::default());
+        let mut actions = ActionMap::new(input.shared());
+        actions.bind("jump", ActionBinding::Key(KeyboardButton::Space));
+        actions.bind("jump", ActionBinding::Gamepad(GamepadButton::A));
+        actions.bind("fire", ActionBinding::Mouse(MouseButton::Other(4)));
+
+        let table = actions.save();
+        assert_eq!(table, "fire: Mouse(Other(4))\njump: Key(Space), Gamepad(A)\n");
+
+        actions.clear();
+        actions.load(&table).unwrap();
+        assert_eq!(actions.save(), table);
+
+        assert!(actions.load("jump: Key(Space)\nfire: Key(Unknown)").is_err());
+        assert!(actions.load(": Key(Space)").is_err());
+        assert_eq!(actions.save(), table);
+
+        actions.load("# comments\n\njump:\n").unwrap();
+        assert!(actions.bindings("jump").is_empty());
+    }
+
+    #[test]
+    fn every_key() {
+        let input = InputSystem::new(InputSetup::default());
+        let mut actions = ActionMap::new(input.shared());
+        for &(name, key) in KEYS {
+            assert_eq!(name, format!("{:?}", key));
+            actions.bind("key", ActionBinding::Key(key));
+        }
+
+        let table = actions.save();
+        actions.clear();
+        actions.load(&table).unwrap();
+        assert_eq!(actions.bindings("key").len(), KEYS.len());
+        assert_eq!(actions.save(), table);
+    }
+}
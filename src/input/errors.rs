@@ -0,0 +1,17 @@
+error_chain!{
+    types {
+        Error, ErrorKind, ResultExt, Result;
+    }
+
+    errors {
+        MalformedBinding(line: usize, desc: String) {
+            description("malformed action binding")
+            display("Malformed action binding at line {}: {}.", line, desc)
+        }
+
+        MalformedRecord(line: usize, desc: String) {
+            description("malformed input record")
+            display("Malformed input record at line {}: {}.", line, desc)
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+
+use application::event::{GamepadAxis, GamepadButton, MAX_GAMEPAD_AXES};
+
+use super::MAX_GAMEPADS;
+
+/// The setup parameters of gamepad devices.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSetup {
+    dead_zones: [f32; MAX_GAMEPAD_AXES],
+}
+
+impl Default for GamepadSetup {
+    fn default() -> Self {
+        let mut setup = GamepadSetup {
+            dead_zones: [0.15; MAX_GAMEPAD_AXES],
+        };
+
+        setup.set_dead_zone(GamepadAxis::LeftTrigger, 0.05);
+        setup.set_dead_zone(GamepadAxis::RightTrigger, 0.05);
+        setup
+    }
+}
+
+impl GamepadSetup {
+    /// Sets the dead zone of `axis`. The values whose magnitude are smaller than the
+    /// dead zone will be reported as zero, which prevents the thumbstick drifting.
+    pub fn set_dead_zone(&mut self, axis: GamepadAxis, dead_zone: f32) {
+        self.dead_zones[axis as usize] = dead_zone.max(0.0).min(0.99);
+    }
+
+    /// Gets the dead zone of `axis`.
+    pub fn dead_zone(&self, axis: GamepadAxis) -> f32 {
+        self.dead_zones[axis as usize]
+    }
+}
+
+struct Gamepad {
+    downs: HashSet<GamepadButton>,
+    presses: HashSet<GamepadButton>,
+    releases: HashSet<GamepadButton>,
+    axes: [f32; MAX_GAMEPAD_AXES],
+}
+
+impl Gamepad {
+    fn new() -> Self {
+        Gamepad {
+            downs: HashSet::new(),
+            presses: HashSet::new(),
+            releases: HashSet::new(),
+            axes: [0.0; MAX_GAMEPAD_AXES],
+        }
+    }
+}
+
+pub struct Gamepads {
+    setup: GamepadSetup,
+    slots: [Option<Gamepad>; MAX_GAMEPADS],
+    /// The device that used the slot last time, which makes the id stable when a
+    /// gamepad is reconnected.
+    history: [Option<u64>; MAX_GAMEPADS],
+    devices: HashMap<u64, usize>,
+}
+
+impl Gamepads {
+    pub fn new(setup: GamepadSetup) -> Self {
+        Gamepads {
+            setup: setup,
+            slots: [None, None, None, None],
+            history: [None; MAX_GAMEPADS],
+            devices: HashMap::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn reset(&mut self) {
+        for v in self.slots.iter_mut().filter_map(|v| v.as_mut()) {
+            v.downs.clear();
+            v.presses.clear();
+            v.releases.clear();
+            v.axes = [0.0; MAX_GAMEPAD_AXES];
+        }
+    }
+
+    #[inline(always)]
+    pub fn advance(&mut self) {
+        for v in self.slots.iter_mut().filter_map(|v| v.as_mut()) {
+            v.presses.clear();
+            v.releases.clear();
+        }
+    }
+
+    /// Assigns a stable id to the connected `device`. Returns `None` if there are
+    /// too many gamepads connected.
+    pub fn on_connected(&mut self, device: u64) -> Option<usize> {
+        if let Some(&id) = self.devices.get(&device) {
+            return Some(id);
+        }
+
+        // Prefers the slot used by this device last time, and then the slots that have
+        // never been used.
+        let free: Vec<_> = (0..MAX_GAMEPADS)
+            .filter(|&i| self.slots[i].is_none())
+            .collect();
+
+        let id = free.iter()
+            .find(|&&i| self.history[i] == Some(device))
+            .or_else(|| free.iter().find(|&&i| self.history[i].is_none()))
+            .or_else(|| free.first())
+            .cloned()?;
+
+        self.slots[id] = Some(Gamepad::new());
+        self.history[id] = Some(device);
+        self.devices.insert(device, id);
+        Some(id)
+    }
+
+    pub fn on_disconnected(&mut self, device: u64) -> Option<usize> {
+        let id = self.devices.remove(&device)?;
+        self.slots[id] = None;
+        Some(id)
+    }
+
+    pub fn on_button_pressed(&mut self, device: u64, button: GamepadButton) {
+        if let Some(v) = self.gamepad_mut(device) {
+            if v.downs.insert(button) {
+                v.presses.insert(button);
+            }
+        }
+    }
+
+    pub fn on_button_released(&mut self, device: u64, button: GamepadButton) {
+        if let Some(v) = self.gamepad_mut(device) {
+            v.downs.remove(&button);
+            v.releases.insert(button);
+        }
+    }
+
+    pub fn on_axis_moved(&mut self, device: u64, axis: GamepadAxis, value: f32) {
+        let dead_zone = self.setup.dead_zone(axis);
+        if let Some(v) = self.gamepad_mut(device) {
+            // Rescales the values outside of dead zone, so there is no sudden jump at
+            // the edge of dead zone.
+            let magnitude = value.abs().min(1.0);
+            v.axes[axis as usize] = if magnitude < dead_zone {
+                0.0
+            } else {
+                value.signum() * (magnitude - dead_zone) / (1.0 - dead_zone)
+            };
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_connected(&self, id: usize) -> bool {
+        self.gamepad(id).is_some()
+    }
+
+    #[inline(always)]
+    pub fn is_button_down(&self, id: usize, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .map(|v| v.downs.contains(&button))
+            .unwrap_or(false)
+    }
+
+    #[inline(always)]
+    pub fn is_button_press(&self, id: usize, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .map(|v| v.presses.contains(&button))
+            .unwrap_or(false)
+    }
+
+    #[inline(always)]
+    pub fn is_button_release(&self, id: usize, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .map(|v| v.releases.contains(&button))
+            .unwrap_or(false)
+    }
+
+    #[inline(always)]
+    pub fn axis(&self, id: usize, axis: GamepadAxis) -> f32 {
+        self.gamepad(id)
+            .map(|v| v.axes[axis as usize])
+            .unwrap_or(0.0)
+    }
+
+    fn gamepad(&self, id: usize) -> Option<&Gamepad> {
+        self.slots.get(id).and_then(|v| v.as_ref())
+    }
+
+    fn gamepad_mut(&mut self, device: u64) -> Option<&mut Gamepad> {
+        let id = *self.devices.get(&device)?;
+        self.slots[id].as_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hotplug() {
+        let mut gamepads = Gamepads::new(GamepadSetup::default());
+        assert_eq!(gamepads.on_connected(10), Some(0));
+        assert_eq!(gamepads.on_connected(20), Some(1));
+        assert_eq!(gamepads.on_connected(10), Some(0));
+
+        assert_eq!(gamepads.on_disconnected(10), Some(0));
+        assert!(!gamepads.is_connected(0));
+        assert_eq!(gamepads.on_connected(30), Some(2));
+        assert_eq!(gamepads.on_connected(10), Some(0));
+        assert_eq!(gamepads.on_disconnected(40), None);
+
+        gamepads.on_button_pressed(20, GamepadButton::A);
+        assert!(gamepads.is_button_down(1, GamepadButton::A));
+        assert!(gamepads.is_button_press(1, GamepadButton::A));
+        assert!(!gamepads.is_button_down(0, GamepadButton::A));
+
+        gamepads.advance();
+        assert!(gamepads.is_button_down(1, GamepadButton::A));
+        assert!(!gamepads.is_button_press(1, GamepadButton::A));
+    }
+
+    #[test]
+    fn dead_zone() {
+        let mut setup = GamepadSetup::default();
+        setup.set_dead_zone(GamepadAxis::LeftStickX, 0.2);
+
+        let mut gamepads = Gamepads::new(setup);
+        gamepads.on_connected(10);
+
+        gamepads.on_axis_moved(10, GamepadAxis::LeftStickX, 0.1);
+        assert_eq!(gamepads.axis(0, GamepadAxis::LeftStickX), 0.0);
+
+        gamepads.on_axis_moved(10, GamepadAxis::LeftStickX, -0.6);
+        assert!((gamepads.axis(0, GamepadAxis::LeftStickX) + 0.5).abs() < 1e-6);
+
+        gamepads.on_axis_moved(10, GamepadAxis::LeftStickX, 1.0);
+        assert_eq!(gamepads.axis(0, GamepadAxis::LeftStickX), 1.0);
+        assert_eq!(gamepads.axis(1, GamepadAxis::LeftStickX), 0.0);
+    }
+}
@@ -2,14 +2,29 @@ use std::sync::{Arc, RwLock};
 
 use math;
 use application::event;
-use super::{keyboard, mouse, touchpad};
+use graphics::WindowHandle;
+use super::{gamepad, keyboard, mouse, touchpad};
+use super::record::InputRecord;
 
 /// The setup parameters of all supported input devices.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InputSetup {
     pub keyboard: keyboard::KeyboardSetup,
     pub mouse: mouse::MouseSetup,
     pub touchpad: touchpad::TouchPadSetup,
+    pub gamepad: gamepad::GamepadSetup,
+}
+
+/// The source of input device events.
+enum InputMode {
+    Live,
+    Recording(InputRecord),
+    Playback {
+        record: InputRecord,
+        cursor: usize,
+        repeat: bool,
+    },
 }
 
 /// The `InputSystem` struct are used to manage all the events and corresponding
@@ -18,6 +33,11 @@ pub struct InputSystem {
     touch_emulation: bool,
     touch_emulation_button: Option<event::MouseButton>,
     shared: Arc<InputSystemShared>,
+    mode: InputMode,
+    /// The frame number counted from the start of recording or playback.
+    frame: u64,
+    /// The application events derived from the played back events.
+    events: Vec<event::ApplicationEvent>,
 }
 
 impl InputSystem {
@@ -28,6 +48,9 @@ impl InputSystem {
             shared: shared,
             touch_emulation: false,
             touch_emulation_button: None,
+            mode: InputMode::Live,
+            frame: 0,
+            events: Vec::new(),
         }
     }
 
@@ -41,6 +64,7 @@ impl InputSystem {
         self.shared.mouse.write().unwrap().reset();
         self.shared.keyboard.write().unwrap().reset();
         self.shared.touchpad.write().unwrap().reset();
+        self.shared.gamepads.write().unwrap().reset();
         self.touch_emulation_button = None;
     }
 
@@ -50,13 +74,174 @@ impl InputSystem {
         self
     }
 
-    pub(crate) fn advance(&mut self, hidpi: f32) {
+    /// Starts recording the events of input devices, the previous record will be
+    /// discarded. It stops the playback if there is one.
+    pub fn record(&mut self) {
+        self.mode = InputMode::Recording(InputRecord::new());
+        self.frame = 0;
+    }
+
+    /// Stops recording and returns the record, or none if it's not recording.
+    pub fn stop_recording(&mut self) -> Option<InputRecord> {
+        match ::std::mem::replace(&mut self.mode, InputMode::Live) {
+            InputMode::Recording(mut record) => {
+                record.extend(self.frame + 1);
+                Some(record)
+            }
+            mode => {
+                self.mode = mode;
+                None
+            }
+        }
+    }
+
+    /// Returns true if it's recording.
+    pub fn is_recording(&self) -> bool {
+        match self.mode {
+            InputMode::Recording(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Plays back the `record` in place of live input, which starts from current frame.
+    /// The states of input devices are reset, and live events are ignored completely
+    /// until the playback is finished or stopped.
+    ///
+    /// The `ApplicationEvent::InputPlaybackFinished` is emitted when the end of record
+    /// is reached. If `repeat` is true, the playback starts over again, otherwise the
+    /// live input will be resumed.
+    pub fn playback(&mut self, record: InputRecord, repeat: bool) {
+        self.mode = InputMode::Playback {
+            record: record,
+            cursor: 0,
+            repeat: repeat,
+        };
+
+        self.restart();
+    }
+
+    /// Stops the playback and resumes the live input.
+    pub fn stop_playback(&mut self) {
+        if self.is_playing_back() {
+            self.mode = InputMode::Live;
+            self.reset();
+        }
+    }
+
+    /// Returns true if it's playing back a record.
+    pub fn is_playing_back(&self) -> bool {
+        match self.mode {
+            InputMode::Playback { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Advances the input devices into next frame, and returns the application events
+    /// derived from the played back events.
+    pub(crate) fn advance(&mut self, hidpi: f32) -> Vec<event::ApplicationEvent> {
         self.shared.mouse.write().unwrap().advance(hidpi);
         self.shared.keyboard.write().unwrap().advance();
         self.shared.touchpad.write().unwrap().advance(hidpi);
+        self.shared.gamepads.write().unwrap().advance();
+        self.frame += 1;
+
+        let finished = match self.mode {
+            InputMode::Playback {
+                ref record, repeat, ..
+            } => {
+                if self.frame >= record.frames() {
+                    Some(repeat)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        match finished {
+            Some(true) => {
+                self.events.push(event::ApplicationEvent::InputPlaybackFinished);
+                self.restart();
+            }
+            Some(false) => {
+                self.events.push(event::ApplicationEvent::InputPlaybackFinished);
+                self.mode = InputMode::Live;
+                self.reset();
+            }
+            None => self.feed(),
+        }
+
+        ::std::mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Updates the internal states with input device event. The application event
+    /// that derived from the input, e.g. connecting of gamepads, will be returned.
+    /// Live events are ignored during playback.
+    pub(crate) fn update_with(
+        &mut self,
+        v: event::InputDeviceEvent,
+    ) -> Option<event::ApplicationEvent> {
+        match self.mode {
+            InputMode::Live => {}
+            InputMode::Recording(ref mut record) => record.push(self.frame, v),
+            InputMode::Playback { .. } => return None,
+        }
+
+        self.apply(v)
+    }
+
+    /// Sets the window that the following input device events come from, `None` for
+    /// the primary window.
+    pub(crate) fn set_window(&mut self, window: Option<WindowHandle>) {
+        *self.shared.window.write().unwrap() = window;
+    }
+
+    /// Notifies that the cursor has been moved to `position` by the application, so
+    /// the synthetic event of warping will not be counted as movement. It's ignored
+    /// during playback.
+    pub(crate) fn on_cursor_warped(&mut self, position: (f32, f32)) {
+        if !self.is_playing_back() {
+            self.shared.mouse.write().unwrap().on_warp(position);
+        }
+    }
+
+    /// Resets the input devices and plays back the record from the beginning.
+    fn restart(&mut self) {
+        self.reset();
+        self.frame = 0;
+
+        if let InputMode::Playback { ref mut cursor, .. } = self.mode {
+            *cursor = 0;
+        }
+
+        self.feed();
+    }
+
+    /// Applies the played back events of current frame.
+    fn feed(&mut self) {
+        let events: Vec<_> = match self.mode {
+            InputMode::Playback {
+                ref record,
+                ref mut cursor,
+                ..
+            } => {
+                let from = *cursor;
+                let events = &record.events()[from..];
+                let len = events.iter().take_while(|v| v.0 <= self.frame).count();
+                *cursor += len;
+                events[..len].iter().map(|v| v.1).collect()
+            }
+            _ => return,
+        };
+
+        for v in events {
+            if let Some(v) = self.apply(v) {
+                self.events.push(v);
+            }
+        }
     }
 
-    pub(crate) fn update_with(&mut self, v: event::InputDeviceEvent) {
+    fn apply(&mut self, v: event::InputDeviceEvent) -> Option<event::ApplicationEvent> {
         match v {
             event::InputDeviceEvent::MouseMoved { position } => {
                 if self.touch_emulation_button.is_some() {
@@ -127,7 +312,42 @@ impl InputSystem {
             event::InputDeviceEvent::Touch(touch) => {
                 self.shared.touchpad.write().unwrap().on_touch(touch);
             }
+
+            event::InputDeviceEvent::GamepadConnected { device } => {
+                let mut gamepads = self.shared.gamepads.write().unwrap();
+                return gamepads
+                    .on_connected(device)
+                    .map(event::ApplicationEvent::GamepadConnected);
+            }
+
+            event::InputDeviceEvent::GamepadDisconnected { device } => {
+                let mut gamepads = self.shared.gamepads.write().unwrap();
+                return gamepads
+                    .on_disconnected(device)
+                    .map(event::ApplicationEvent::GamepadDisconnected);
+            }
+
+            event::InputDeviceEvent::GamepadPressed { device, button } => {
+                let mut gamepads = self.shared.gamepads.write().unwrap();
+                gamepads.on_button_pressed(device, button);
+            }
+
+            event::InputDeviceEvent::GamepadReleased { device, button } => {
+                let mut gamepads = self.shared.gamepads.write().unwrap();
+                gamepads.on_button_released(device, button);
+            }
+
+            event::InputDeviceEvent::GamepadAxisMoved {
+                device,
+                axis,
+                value,
+            } => {
+                let mut gamepads = self.shared.gamepads.write().unwrap();
+                gamepads.on_axis_moved(device, axis, value);
+            }
         }
+
+        None
     }
 }
 
@@ -136,6 +356,8 @@ pub struct InputSystemShared {
     mouse: RwLock<mouse::Mouse>,
     keyboard: RwLock<keyboard::Keyboard>,
     touchpad: RwLock<touchpad::TouchPad>,
+    gamepads: RwLock<gamepad::Gamepads>,
+    window: RwLock<Option<WindowHandle>>,
 }
 
 impl InputSystemShared {
@@ -143,13 +365,23 @@ impl InputSystemShared {
         let kb = keyboard::Keyboard::new(setup.keyboard);
         let mice = mouse::Mouse::new(setup.mouse);
         let tp = touchpad::TouchPad::new(setup.touchpad);
+        let gp = gamepad::Gamepads::new(setup.gamepad);
 
         InputSystemShared {
             mouse: RwLock::new(mice),
             keyboard: RwLock::new(kb),
             touchpad: RwLock::new(tp),
+            gamepads: RwLock::new(gp),
+            window: RwLock::new(None),
         }
     }
+
+    /// Gets the window that the latest input device event comes from, `None` for the
+    /// primary window. The positions of mouse and touches are relative to it.
+    #[inline(always)]
+    pub fn window(&self) -> Option<WindowHandle> {
+        *self.window.read().unwrap()
+    }
 }
 
 impl InputSystemShared {
@@ -184,6 +416,16 @@ impl InputSystemShared {
         self.keyboard.read().unwrap().is_key_repeat(key)
     }
 
+    /// Gets the characters that have been typed during the last frame, in the order
+    /// they are received. This is different from key states: the characters are
+    /// translated with the keyboard layout and composed by the input method of
+    /// platform, e.g. `Shift+2` yields '@' or '"' depending on layout, and the
+    /// accented or CJK characters arrive as finished codepoints.
+    #[inline(always)]
+    pub fn text_input(&self) -> Vec<char> {
+        self.keyboard.read().unwrap().captured_chars().to_vec()
+    }
+
     /// Gets captured text during the last frame.
     #[inline(always)]
     pub fn text(&self) -> String {
@@ -243,7 +485,27 @@ impl InputSystemShared {
         self.mouse.read().unwrap().movement()
     }
 
+    /// Enables or disables the relative mouse mode, which grabs and hides the cursor,
+    /// and re-centers it every frame, e.g. for first-person cameras. The movement of
+    /// mouse keeps being reported by `mouse_movement` without hitting the borders of
+    /// window.
+    ///
+    /// The cursor is released automatically when the window lost focus, and grabbed
+    /// again once it gains focus if the relative mode is still enabled.
+    #[inline(always)]
+    pub fn set_relative_mouse_mode(&self, relative: bool) {
+        self.mouse.write().unwrap().set_relative(relative);
+    }
+
+    /// Returns true if the relative mouse mode is enabled.
+    #[inline(always)]
+    pub fn is_relative_mouse_mode(&self) -> bool {
+        self.mouse.read().unwrap().is_relative()
+    }
+
     /// Gets the scroll movement of mouse in pixels, usually provided by mouse wheel.
+    /// The scroll events received during last frame are accumulated, and it will be
+    /// reset to zero at the start of each frame.
     #[inline(always)]
     pub fn mouse_scroll(&self) -> math::Vector2<f32> {
         self.mouse.read().unwrap().scroll()
@@ -258,6 +520,13 @@ impl InputSystemShared {
         true
     }
 
+    /// Gets the touches of fingers during last frame, including the ones that are
+    /// lifted or cancelled during last frame.
+    #[inline(always)]
+    pub fn touches(&self) -> Vec<touchpad::Touch> {
+        self.touchpad.read().unwrap().touches()
+    }
+
     /// Checks if the `n`th finger is touched during last frame.
     #[inline(always)]
     pub fn is_finger_touched(&self, n: usize) -> bool {
@@ -288,3 +557,37 @@ impl InputSystemShared {
         self.touchpad.read().unwrap().pan()
     }
 }
+
+impl InputSystemShared {
+    /// Checks if the gamepad with `id` is connected.
+    #[inline(always)]
+    pub fn is_gamepad_connected(&self, id: usize) -> bool {
+        self.gamepads.read().unwrap().is_connected(id)
+    }
+
+    /// Checks if a gamepad button is held down.
+    #[inline(always)]
+    pub fn gamepad_button(&self, id: usize, button: event::GamepadButton) -> bool {
+        self.gamepads.read().unwrap().is_button_down(id, button)
+    }
+
+    /// Checks if a gamepad button has been pressed during last frame.
+    #[inline(always)]
+    pub fn is_gamepad_button_press(&self, id: usize, button: event::GamepadButton) -> bool {
+        self.gamepads.read().unwrap().is_button_press(id, button)
+    }
+
+    /// Checks if a gamepad button has been released during last frame.
+    #[inline(always)]
+    pub fn is_gamepad_button_release(&self, id: usize, button: event::GamepadButton) -> bool {
+        self.gamepads.read().unwrap().is_button_release(id, button)
+    }
+
+    /// Gets the value of analog axis, which is in range of [-1.0, 1.0] for sticks and
+    /// [0.0, 1.0] for triggers. Zero is returned if the value is inside the dead zone,
+    /// or the gamepad is not connected.
+    #[inline(always)]
+    pub fn gamepad_axis(&self, id: usize, axis: event::GamepadAxis) -> f32 {
+        self.gamepads.read().unwrap().axis(id, axis)
+    }
+}
@@ -4,7 +4,8 @@ use std::time::{Duration, Instant};
 use application::event;
 
 /// The setup parameters of keyboard device.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct KeyboardSetup {
     /// The maximum characters that could be captured in one frame.
     pub max_chars: usize,
@@ -96,8 +97,15 @@ impl Keyboard {
         self.releases.insert(key);
     }
 
+    /// Captures the text input. The characters have been translated with keyboard layout
+    /// and composed by the input method of platform, so only the control characters
+    /// like backspace and return, which are handled as keys, are discarded.
     #[inline(always)]
     pub fn on_char(&mut self, c: char) {
+        if c.is_control() {
+            return;
+        }
+
         if self.chars.len() < self.setup.max_chars {
             self.chars.push(c);
         }
@@ -134,3 +142,24 @@ impl Keyboard {
         &self.chars
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn text_input() {
+        let mut setup = KeyboardSetup::default();
+        setup.max_chars = 4;
+
+        let mut keyboard = Keyboard::new(setup);
+        for &c in &['@', '\u{8}', '\r', 'é', '\u{7f}', '中', '文', '!'] {
+            keyboard.on_char(c);
+        }
+
+        assert_eq!(keyboard.captured_chars(), &['@', 'é', '中', '文']);
+
+        keyboard.advance();
+        assert!(keyboard.captured_chars().is_empty());
+    }
+}
@@ -33,6 +33,9 @@
 //! ```rust,ignore
 //! // Gets captured text during the last frame.
 //! input.text();
+//!
+//! // Gets the typed characters during the last frame, e.g. to feed text fields of UI.
+//! for c in input.text_input() { ... }
 //! ```
 //!
 //! # Mouse Inputs
@@ -71,6 +74,21 @@
 //! input.mouse_scroll();
 //! ```
 //!
+//! First-person cameras usually capture the cursor, and rotate with the movement of
+//! mouse. In relative mode, the cursor is grabbed, hidden, and re-centered every frame,
+//! so the movement is not clamped by the borders of window:
+//!
+//! ```rust,ignore
+//! input.set_relative_mouse_mode(true);
+//!
+//! // Gets mouse movement in pixels since last frame.
+//! let delta = input.mouse_movement();
+//! ```
+//!
+//! Some platforms or window managers refuse to grab the cursor, the application is
+//! notified with `ApplicationEvent::CursorGrabFailed` and keeps running with a free
+//! cursor then.
+//!
 //! Mouse positions and movement are reported in pixel coordinates which makes it
 //! difficult to derive useful movement information out of it. It might changes in
 //! the future versions (dividing by the framebuffer resolution is a simple but very
@@ -99,6 +117,14 @@
 //!
 //! // Gets the position of the `n`th touched finger.
 //! input.finger_position(n);
+//!
+//! // Iterates the touches of fingers, whose ids are stable until they are lifted.
+//! for touch in input.touches() {
+//!     match touch.state {
+//!         TouchState::Start | TouchState::Move => { ... },
+//!         TouchState::End | TouchState::Cancel => { ... },
+//!     }
+//! }
 //! ```
 //!
 //! The touch support also addresses a few platform-agnostic gesture recognizers
@@ -128,23 +154,94 @@
 //! }
 //! ```
 //!
+//! # Gamepad Inputs
+//!
+//! Gamepads are identified by stable ids, which are assigned when the gamepads are
+//! connected. A reconnected gamepad gets the same id if it's still available. The
+//! `ApplicationEvent::GamepadConnected` and `ApplicationEvent::GamepadDisconnected`
+//! events will be emitted when gamepads are plugged in or out.
+//!
+//! ```rust,ignore
+//! // Checks if the `n`th gamepad is connected.
+//! input.is_gamepad_connected(n);
+//!
+//! // Checks if a gamepad button is held down.
+//! input.gamepad_button(n, GamepadButton::A);
+//!
+//! // Gets the value of analog axis, the dead zone is already applied.
+//! input.gamepad_axis(n, GamepadAxis::LeftStickX);
+//! ```
+//!
+//! The windowing system does not report game controllers, so the gamepad events are
+//! fed by the platform backend of your choice, e.g. polled from a separated thread
+//! and sent through `Context::event_sender`:
+//!
+//! ```rust,ignore
+//! let sender = ctx.event_sender();
+//! thread::spawn(move || loop {
+//!     let v = InputDeviceEvent::GamepadPressed { device: 0, button: GamepadButton::A };
+//!     sender.send(Event::InputDevice(v)).unwrap();
+//! });
+//! ```
+//!
+//! # Actions
+//!
+//! Instead of checking the physical inputs directly, it's recommended to use the
+//! named actions through `ActionMap`, which makes it possible to rebind inputs without
+//! touching the game code.
+//!
+//! ```rust,ignore
+//! actions.bind("jump", ActionBinding::Key(KeyboardButton::Space));
+//!
+//! // Checks if the action has been triggered during last frame.
+//! actions.just_pressed("jump");
+//! ```
+//!
+//! # Recording and Playback
+//!
+//! All the events of input devices could be recorded with their frame numbers, and
+//! played back later in place of live input. Combined with a fixed timestep, it gives
+//! deterministic replays which are useful to reproduce bugs.
+//!
+//! ```rust,ignore
+//! engine.input.record();
+//! let mut engine = engine.run(window)?;
+//! let record = engine.input.stop_recording().unwrap();
+//! fs::write("session.txt", record.save())?;
+//!
+//! // Live inputs are ignored completely during playback, and the
+//! // `ApplicationEvent::InputPlaybackFinished` is emitted at the end of record.
+//! let record = InputRecord::load(&fs::read_to_string("session.txt")?)?;
+//! engine.input.playback(record, false);
+//! ```
+//!
 //! # Others Inputs
 //!
 //! Somethings that nice to have, but not implemented right now:
 //!
 //! 1. Device sensor inputs;
-//! 2. GamePad inputs;
-//! 3. More touch gesture like `Pinching`.
+//! 2. More touch gesture like `Pinching`.
+
+pub mod errors;
 
 mod keyboard;
 mod mouse;
 mod touchpad;
+mod gamepad;
 mod input;
+mod action;
+mod record;
 
 pub use self::keyboard::KeyboardSetup;
+pub use self::gamepad::GamepadSetup;
 pub use self::mouse::MouseSetup;
-pub use self::touchpad::{GesturePan, GestureTap, TouchPadSetup};
+pub use self::touchpad::{GesturePan, GestureTap, Touch, TouchPadSetup};
 pub use self::input::{InputSetup, InputSystem, InputSystemShared};
+pub use self::action::{ActionBinding, ActionMap};
+pub use self::record::InputRecord;
 
 /// Maximum touches that would be tracked at sametime.
 pub const MAX_TOUCHES: usize = 4;
+
+/// Maximum gamepads that would be tracked at sametime.
+pub const MAX_GAMEPADS: usize = 4;
@@ -9,7 +9,8 @@ use application::event;
 ///
 /// Notes that the `distance` series paramters will be multiplied by HiDPI
 /// factor before recognizing processes.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MouseSetup {
     pub press_timeout: Duration,
     pub max_press_distance: f32,
@@ -34,11 +35,14 @@ pub struct Mouse {
     downs: HashSet<event::MouseButton>,
     presses: HashSet<event::MouseButton>,
     releases: HashSet<event::MouseButton>,
-    last_position: math::Vector2<f32>,
     position: math::Vector2<f32>,
+    movement: math::Vector2<f32>,
     scrol: math::Vector2<f32>,
     click_detectors: HashMap<event::MouseButton, ClickDetector>,
     setup: MouseSetup,
+    relative: bool,
+    /// The position of the last warping, and the number of frames since then.
+    warp: Option<(math::Vector2<f32>, u32)>,
 }
 
 impl Mouse {
@@ -47,11 +51,13 @@ impl Mouse {
             downs: HashSet::new(),
             presses: HashSet::new(),
             releases: HashSet::new(),
-            last_position: math::Vector2::new(0.0, 0.0),
             position: math::Vector2::new(0.0, 0.0),
+            movement: math::Vector2::new(0.0, 0.0),
             scrol: math::Vector2::new(0.0, 0.0),
             click_detectors: HashMap::new(),
             setup: setup,
+            relative: false,
+            warp: None,
         }
     }
 
@@ -60,9 +66,10 @@ impl Mouse {
         self.downs.clear();
         self.presses.clear();
         self.releases.clear();
-        self.last_position = math::Vector2::new(0.0, 0.0);
         self.position = math::Vector2::new(0.0, 0.0);
+        self.movement = math::Vector2::new(0.0, 0.0);
         self.scrol = math::Vector2::new(0.0, 0.0);
+        self.warp = None;
 
         for (_, v) in &mut self.click_detectors {
             v.reset();
@@ -74,7 +81,14 @@ impl Mouse {
         self.presses.clear();
         self.releases.clear();
         self.scrol = math::Vector2::new(0.0, 0.0);
-        self.last_position = self.position;
+        self.movement = math::Vector2::new(0.0, 0.0);
+
+        // The synthetic event of warping is received in the next frame if there is one,
+        // the filter is disarmed after that so it won't swallow real movements.
+        self.warp = match self.warp {
+            Some((position, 0)) => Some((position, 1)),
+            _ => None,
+        };
 
         for (_, v) in &mut self.click_detectors {
             v.advance(hidpi);
@@ -83,7 +97,37 @@ impl Mouse {
 
     #[inline(always)]
     pub fn on_move(&mut self, position: (f32, f32)) {
+        let position = position.into();
+
+        // Warping the cursor fires a synthetic event on some platforms, which should
+        // not be counted as movement.
+        if self.warp.map(|v| v.0) == Some(position) {
+            self.warp = None;
+            self.position = position;
+            return;
+        }
+
+        // Accumulates the movements that received during one frame.
+        self.movement += position - self.position;
+        self.position = position;
+    }
+
+    /// Moves the cursor to `position` without movement, e.g. re-centering it in
+    /// relative mode.
+    #[inline(always)]
+    pub fn on_warp(&mut self, position: (f32, f32)) {
         self.position = position.into();
+        self.warp = Some((self.position, 0));
+    }
+
+    #[inline(always)]
+    pub fn set_relative(&mut self, relative: bool) {
+        self.relative = relative;
+    }
+
+    #[inline(always)]
+    pub fn is_relative(&self) -> bool {
+        self.relative
     }
 
     #[inline(always)]
@@ -120,7 +164,8 @@ impl Mouse {
 
     #[inline(always)]
     pub fn on_wheel_scroll(&mut self, delta: (f32, f32)) {
-        self.scrol = delta.into();
+        // Accumulates the scroll events that received during one frame.
+        self.scrol += math::Vector2::from(delta);
     }
 
     #[inline(always)]
@@ -163,7 +208,7 @@ impl Mouse {
 
     #[inline(always)]
     pub fn movement(&self) -> math::Vector2<f32> {
-        self.position - self.last_position
+        self.movement
     }
 
     #[inline(always)]
@@ -254,3 +299,62 @@ impl ClickDetector {
         self.frame_clicks
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn warp() {
+        let mut mouse = Mouse::new(MouseSetup::default());
+        mouse.on_move((10.0, 10.0));
+        mouse.advance(1.0);
+        assert_eq!(mouse.movement(), math::Vector2::new(0.0, 0.0));
+
+        mouse.on_move((15.0, 8.0));
+        mouse.on_move((20.0, 6.0));
+        assert_eq!(mouse.movement(), math::Vector2::new(10.0, -4.0));
+
+        // Re-centers the cursor, and the synthetic event of warping is filtered.
+        mouse.on_warp((50.0, 50.0));
+        assert_eq!(mouse.position(), math::Vector2::new(50.0, 50.0));
+        mouse.on_move((50.0, 50.0));
+        assert_eq!(mouse.movement(), math::Vector2::new(10.0, -4.0));
+
+        mouse.on_move((52.0, 50.0));
+        assert_eq!(mouse.movement(), math::Vector2::new(12.0, -4.0));
+
+        // Only the first event at warped position is synthetic.
+        mouse.on_move((50.0, 50.0));
+        assert_eq!(mouse.movement(), math::Vector2::new(10.0, -4.0));
+
+        // The synthetic event arrives in the next frame.
+        mouse.on_warp((50.0, 50.0));
+        mouse.advance(1.0);
+        mouse.on_move((50.0, 50.0));
+        assert_eq!(mouse.movement(), math::Vector2::new(0.0, 0.0));
+
+        // The filter is disarmed if no synthetic event arrives in the next frame, so
+        // the real movements back to the center are counted.
+        mouse.on_warp((50.0, 50.0));
+        mouse.advance(1.0);
+        mouse.advance(1.0);
+        mouse.on_move((48.0, 50.0));
+        mouse.on_move((50.0, 50.0));
+        assert_eq!(mouse.movement(), math::Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn scroll() {
+        let mut mouse = Mouse::new(MouseSetup::default());
+        mouse.on_wheel_scroll((0.0, 1.0));
+        mouse.on_wheel_scroll((0.5, 2.0));
+        assert_eq!(mouse.scroll(), math::Vector2::new(0.5, 3.0));
+
+        mouse.advance(1.0);
+        assert_eq!(mouse.scroll(), math::Vector2::new(0.0, 0.0));
+
+        mouse.on_wheel_scroll((-1.0, 0.0));
+        assert_eq!(mouse.scroll(), math::Vector2::new(-1.0, 0.0));
+    }
+}
@@ -0,0 +1,336 @@
+//! Records the events of input devices with their frame numbers, which could be
+//! played back later to reproduce a session.
+
+use std::fmt::Write;
+
+use math;
+use application::event::{InputDeviceEvent, TouchEvent, TouchState};
+
+use super::errors::*;
+use super::action::{parse_gamepad, parse_gamepad_axis, parse_key, parse_mouse};
+
+/// A sequence of input device events, each of them is tagged with the frame it's
+/// received at, which is counted from the start of recording.
+///
+/// The record could be saved into and loaded from a plain text, with one event per
+/// line. The last line marks the number of recorded frames:
+///
+/// ```text
+/// 0: MouseMoved(120, 64.5)
+/// 2: KeyboardPressed(Space)
+/// 5: KeyboardReleased(Space)
+/// 6: End
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InputRecord {
+    events: Vec<(u64, InputDeviceEvent)>,
+    frames: u64,
+}
+
+impl InputRecord {
+    /// Creates a new and empty `InputRecord`.
+    pub fn new() -> Self {
+        InputRecord::default()
+    }
+
+    /// Gets the number of recorded frames.
+    #[inline]
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Gets the recorded events, which are sorted by frame.
+    #[inline]
+    pub fn events(&self) -> &[(u64, InputDeviceEvent)] {
+        &self.events
+    }
+
+    /// Gets the number of recorded events.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if there is no event recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, frame: u64, v: InputDeviceEvent) {
+        self.events.push((frame, v));
+        self.extend(frame + 1);
+    }
+
+    /// Makes sure the record lasts for at least `frames`.
+    pub(crate) fn extend(&mut self, frames: u64) {
+        self.frames = self.frames.max(frames);
+    }
+
+    /// Saves the record into plain text. Every event is kept, keys are written with
+    /// the same names as the binding tables of `ActionMap`.
+    pub fn save(&self) -> String {
+        let mut text = String::new();
+        for &(frame, ref v) in &self.events {
+            writeln!(text, "{}: {}", frame, format(v)).unwrap();
+        }
+
+        writeln!(text, "{}: End", self.frames).unwrap();
+        text
+    }
+
+    /// Loads the record from plain text.
+    pub fn load(text: &str) -> Result<Self> {
+        let mut record = InputRecord::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |desc: &str| ErrorKind::MalformedRecord(i + 1, desc.into());
+
+            let mut iter = line.splitn(2, ':');
+            let frame: u64 = iter.next()
+                .unwrap()
+                .trim()
+                .parse()
+                .map_err(|_| err("frame number is required"))?;
+
+            let last = record.events.last().map(|v| v.0).unwrap_or(0);
+            if frame < last {
+                bail!(err("events should be sorted by frame"));
+            }
+
+            let v = iter.next().unwrap_or("").trim();
+            if v == "End" {
+                record.extend(frame);
+            } else {
+                record.push(frame, parse(v).ok_or_else(|| err(v))?);
+            }
+        }
+
+        Ok(record)
+    }
+}
+
+fn format(v: &InputDeviceEvent) -> String {
+    match *v {
+        InputDeviceEvent::MouseMoved { position } => {
+            format!("MouseMoved({}, {})", position.0, position.1)
+        }
+        InputDeviceEvent::MousePressed { button } => format!("MousePressed({:?})", button),
+        InputDeviceEvent::MouseReleased { button } => format!("MouseReleased({:?})", button),
+        InputDeviceEvent::MouseWheel { delta } => format!("MouseWheel({}, {})", delta.0, delta.1),
+        // The binding table lists every `KeyboardButton` by its `Debug` name.
+        InputDeviceEvent::KeyboardPressed { key } => format!("KeyboardPressed({:?})", key),
+        InputDeviceEvent::KeyboardReleased { key } => format!("KeyboardReleased({:?})", key),
+        InputDeviceEvent::ReceivedCharacter { character } => {
+            format!("ReceivedCharacter({})", character as u32)
+        }
+        InputDeviceEvent::Touch(touch) => format!(
+            "Touch({}, {:?}, {}, {})",
+            touch.id, touch.state, touch.position.x, touch.position.y
+        ),
+        InputDeviceEvent::GamepadConnected { device } => format!("GamepadConnected({})", device),
+        InputDeviceEvent::GamepadDisconnected { device } => {
+            format!("GamepadDisconnected({})", device)
+        }
+        InputDeviceEvent::GamepadPressed { device, button } => {
+            format!("GamepadPressed({}, {:?})", device, button)
+        }
+        InputDeviceEvent::GamepadReleased { device, button } => {
+            format!("GamepadReleased({}, {:?})", device, button)
+        }
+        InputDeviceEvent::GamepadAxisMoved {
+            device,
+            axis,
+            value,
+        } => format!("GamepadAxisMoved({}, {:?}, {})", device, axis, value),
+    }
+}
+
+fn parse(v: &str) -> Option<InputDeviceEvent> {
+    if !v.ends_with(')') {
+        return None;
+    }
+
+    let mut iter = v[..v.len() - 1].splitn(2, '(');
+    let kind = iter.next()?.trim();
+    let args: Vec<_> = iter.next()?.split(',').map(|v| v.trim()).collect();
+
+    let v = match (kind, args.len()) {
+        ("MouseMoved", 2) => InputDeviceEvent::MouseMoved {
+            position: (args[0].parse().ok()?, args[1].parse().ok()?),
+        },
+        ("MousePressed", 1) => InputDeviceEvent::MousePressed {
+            button: parse_mouse(args[0])?,
+        },
+        ("MouseReleased", 1) => InputDeviceEvent::MouseReleased {
+            button: parse_mouse(args[0])?,
+        },
+        ("MouseWheel", 2) => InputDeviceEvent::MouseWheel {
+            delta: (args[0].parse().ok()?, args[1].parse().ok()?),
+        },
+        ("KeyboardPressed", 1) => InputDeviceEvent::KeyboardPressed {
+            key: parse_key(args[0])?,
+        },
+        ("KeyboardReleased", 1) => InputDeviceEvent::KeyboardReleased {
+            key: parse_key(args[0])?,
+        },
+        ("ReceivedCharacter", 1) => InputDeviceEvent::ReceivedCharacter {
+            character: ::std::char::from_u32(args[0].parse().ok()?)?,
+        },
+        ("Touch", 4) => InputDeviceEvent::Touch(TouchEvent {
+            id: args[0].parse().ok()?,
+            state: parse_touch_state(args[1])?,
+            position: math::Vector2::new(args[2].parse().ok()?, args[3].parse().ok()?),
+        }),
+        ("GamepadConnected", 1) => InputDeviceEvent::GamepadConnected {
+            device: args[0].parse().ok()?,
+        },
+        ("GamepadDisconnected", 1) => InputDeviceEvent::GamepadDisconnected {
+            device: args[0].parse().ok()?,
+        },
+        ("GamepadPressed", 2) => InputDeviceEvent::GamepadPressed {
+            device: args[0].parse().ok()?,
+            button: parse_gamepad(args[1])?,
+        },
+        ("GamepadReleased", 2) => InputDeviceEvent::GamepadReleased {
+            device: args[0].parse().ok()?,
+            button: parse_gamepad(args[1])?,
+        },
+        ("GamepadAxisMoved", 3) => InputDeviceEvent::GamepadAxisMoved {
+            device: args[0].parse().ok()?,
+            axis: parse_gamepad_axis(args[1])?,
+            value: args[2].parse().ok()?,
+        },
+        _ => return None,
+    };
+
+    Some(v)
+}
+
+fn parse_touch_state(name: &str) -> Option<TouchState> {
+    match name {
+        "Start" => Some(TouchState::Start),
+        "Move" => Some(TouchState::Move),
+        "End" => Some(TouchState::End),
+        "Cancel" => Some(TouchState::Cancel),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use application::event::{ApplicationEvent, GamepadAxis, KeyboardButton, MouseButton};
+    use input::{InputSetup, InputSystem};
+
+    #[test]
+    fn text() {
+        let mut record = InputRecord::new();
+        record.push(0, InputDeviceEvent::MouseMoved { position: (120.0, 64.5) });
+        record.push(0, InputDeviceEvent::MousePressed { button: MouseButton::Other(4) });
+        record.push(2, InputDeviceEvent::KeyboardPressed { key: KeyboardButton::Space });
+        record.push(2, InputDeviceEvent::ReceivedCharacter { character: ',' });
+        record.push(
+            3,
+            InputDeviceEvent::GamepadAxisMoved {
+                device: 7,
+                axis: GamepadAxis::LeftTrigger,
+                value: 0.25,
+            },
+        );
+        record.extend(6);
+
+        let text = record.save();
+        assert_eq!(
+            text,
+            "0: MouseMoved(120, 64.5)\n0: MousePressed(Other(4))\n2: KeyboardPressed(Space)\n\
+             2: ReceivedCharacter(44)\n3: GamepadAxisMoved(7, LeftTrigger, 0.25)\n6: End\n"
+        );
+
+        let loaded = InputRecord::load(&text).unwrap();
+        assert_eq!(loaded.frames(), 6);
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(loaded.save(), text);
+
+        assert!(InputRecord::load("2: MouseWheel(0, 1)\n1: MouseWheel(0, 1)").is_err());
+        assert!(InputRecord::load("KeyboardPressed(Space)").is_err());
+        assert!(InputRecord::load("0: KeyboardPressed(Unknown)").is_err());
+
+        let record = InputRecord::load("# comments\n\n3: Touch(0, Start, 1, 2)").unwrap();
+        assert_eq!(record.frames(), 4);
+    }
+
+    #[test]
+    fn every_key() {
+        use input::action::KEYS;
+
+        let mut record = InputRecord::new();
+        for (i, &(_, key)) in KEYS.iter().enumerate() {
+            record.push(i as u64, InputDeviceEvent::KeyboardPressed { key: key });
+            record.push(i as u64, InputDeviceEvent::KeyboardReleased { key: key });
+        }
+
+        let text = record.save();
+        assert_eq!(text.lines().count(), KEYS.len() * 2 + 1);
+
+        let loaded = InputRecord::load(&text).unwrap();
+        assert_eq!(loaded.len(), record.len());
+        for (lhs, rhs) in loaded.events().iter().zip(record.events()) {
+            assert_eq!(format(&lhs.1), format(&rhs.1));
+        }
+    }
+
+    #[test]
+    fn playback() {
+        let mut input = InputSystem::new(InputSetup::default());
+        let shared = input.shared();
+        let space = KeyboardButton::Space;
+
+        input.record();
+        input.advance(1.0);
+        input.update_with(InputDeviceEvent::KeyboardPressed { key: space });
+        input.advance(1.0);
+        input.advance(1.0);
+        input.update_with(InputDeviceEvent::KeyboardReleased { key: space });
+        let record = input.stop_recording().unwrap();
+        assert_eq!(record.frames(), 4);
+        assert_eq!(record.len(), 2);
+        assert!(input.stop_recording().is_none());
+
+        // Live events are ignored during playback.
+        input.update_with(InputDeviceEvent::KeyboardPressed { key: space });
+        input.playback(record, true);
+        assert!(!shared.is_key_down(space));
+        input.update_with(InputDeviceEvent::KeyboardPressed { key: space });
+        assert!(!shared.is_key_down(space));
+
+        for _ in 0..2 {
+            assert!(input.advance(1.0).is_empty());
+            assert!(shared.is_key_press(space));
+            assert!(input.advance(1.0).is_empty());
+            assert!(shared.is_key_down(space) && !shared.is_key_press(space));
+
+            // The record is played back again when the end is reached.
+            let events = input.advance(1.0);
+            assert!(shared.is_key_release(space));
+            assert_eq!(events.len(), 0);
+
+            let events = input.advance(1.0);
+            assert!(input.is_playing_back());
+            assert_eq!(events.len(), 1);
+            match events[0] {
+                ApplicationEvent::InputPlaybackFinished => {}
+                _ => panic!("unexpected events: {:?}", events),
+            }
+        }
+
+        input.stop_playback();
+        input.update_with(InputDeviceEvent::KeyboardPressed { key: space });
+        assert!(shared.is_key_down(space));
+    }
+}
@@ -11,7 +11,8 @@ use super::MAX_TOUCHES;
 ///
 /// Notes that the `distance` series paramters will be multiplied by HiDPI
 /// factor before recognizing processes.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TouchPadSetup {
     /// The minimum distance before a touch is recognized as panning.
     pub min_pan_distance: f32,
@@ -39,8 +40,24 @@ impl Default for TouchPadSetup {
     }
 }
 
+/// The touch point of a finger during last frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    /// The identifier of finger, which is stable from the frame it lands to the frame
+    /// it lifts. It's the smallest one that is not used by other fingers, so the ids
+    /// of lifted fingers are reused by the new ones since next frame.
+    pub id: usize,
+    /// The state of finger. A finger is `Start` in the frame it lands, and `Move` in
+    /// the following frames until it's lifted with `End`, or `Cancel` if the touch is
+    /// interrupted by the system.
+    pub state: TouchState,
+    pub position: math::Vector2<f32>,
+}
+
 pub struct TouchPad {
     record: TouchesRecord,
+    /// The touches with the identifiers assigned by platform.
+    touches: Vec<(u64, Touch)>,
 
     pan_detector: GesturePanDetector,
     pan: GesturePan,
@@ -56,6 +73,7 @@ impl TouchPad {
     pub fn new(setup: TouchPadSetup) -> Self {
         TouchPad {
             record: TouchesRecord::default(),
+            touches: Vec::new(),
 
             pan_detector: GesturePanDetector::new(setup),
             pan: GesturePan::None,
@@ -69,6 +87,11 @@ impl TouchPad {
     }
 
     pub fn advance(&mut self, hidpi: f32) {
+        self.touches.retain(|v| is_active(v.1.state));
+        for v in &mut self.touches {
+            v.1.state = TouchState::Move;
+        }
+
         self.pan = GesturePan::None;
         self.pan_detector.set_hidpi_factor(hidpi);
 
@@ -80,6 +103,14 @@ impl TouchPad {
     }
 
     pub fn reset(&mut self) {
+        // The touches are cancelled instead of being dropped silently, so users could
+        // be notified during next frame.
+        for v in &mut self.touches {
+            if is_active(v.1.state) {
+                v.1.state = TouchState::Cancel;
+            }
+        }
+
         self.record = TouchesRecord::default();
         self.pan_detector.reset();
         self.pan = GesturePan::None;
@@ -90,6 +121,7 @@ impl TouchPad {
     }
 
     pub fn on_touch(&mut self, touch: TouchEvent) {
+        self.track(touch);
         self.record.update_touch(touch);
 
         self.pan = self.pan_detector.detect(&self.record);
@@ -97,6 +129,46 @@ impl TouchPad {
         self.double_tap = self.double_tap_detector.detect(&self.record);
     }
 
+    #[inline(always)]
+    pub fn touches(&self) -> Vec<Touch> {
+        self.touches.iter().map(|v| v.1).collect()
+    }
+
+    fn track(&mut self, touch: TouchEvent) {
+        let found = self.touches
+            .iter()
+            .position(|v| v.0 == touch.id && is_active(v.1.state));
+
+        if let Some(index) = found {
+            let v = &mut self.touches[index];
+            v.1.position = touch.position;
+            // The finger landed during this frame is reported as `Start` even if it
+            // moves after that.
+            if v.1.state != TouchState::Start || touch.state != TouchState::Move {
+                v.1.state = touch.state;
+            }
+
+            return;
+        }
+
+        // Ignores the lifted fingers which landed before we start tracking.
+        if !is_active(touch.state) {
+            return;
+        }
+
+        let id = (0..)
+            .find(|&id| self.touches.iter().all(|v| v.1.id != id))
+            .unwrap();
+
+        let v = Touch {
+            id: id,
+            state: TouchState::Start,
+            position: touch.position,
+        };
+
+        self.touches.push((touch.id, v));
+    }
+
     #[inline(always)]
     pub fn is_touched(&self, index: usize) -> bool {
         self.record.position(index).is_some()
@@ -123,6 +195,10 @@ impl TouchPad {
     }
 }
 
+fn is_active(state: TouchState) -> bool {
+    state == TouchState::Start || state == TouchState::Move
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum GestureTap {
     Action {
@@ -437,3 +513,61 @@ impl TouchesRecord {
         lhs.1.state.cmp(&rhs.1.state).then(lhs.0.cmp(&rhs.0))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn touch(id: u64, state: TouchState, x: f32) -> TouchEvent {
+        TouchEvent {
+            id: id,
+            state: state,
+            position: math::Vector2::new(x, 0.0),
+        }
+    }
+
+    fn states(touchpad: &TouchPad) -> Vec<(usize, TouchState)> {
+        touchpad.touches().iter().map(|v| (v.id, v.state)).collect()
+    }
+
+    #[test]
+    fn touches() {
+        let mut touchpad = TouchPad::new(TouchPadSetup::default());
+        touchpad.on_touch(touch(0xFFFF_0001, TouchState::Start, 0.0));
+        touchpad.on_touch(touch(0xFFFF_0001, TouchState::Move, 1.0));
+        touchpad.on_touch(touch(0xFFFF_0002, TouchState::Start, 2.0));
+        assert_eq!(states(&touchpad), [(0, TouchState::Start), (1, TouchState::Start)]);
+        assert_eq!(touchpad.touches()[0].position, math::Vector2::new(1.0, 0.0));
+
+        touchpad.advance(1.0);
+        assert_eq!(states(&touchpad), [(0, TouchState::Move), (1, TouchState::Move)]);
+
+        // The id of lifted finger is not reused during the same frame.
+        touchpad.on_touch(touch(0xFFFF_0001, TouchState::End, 1.0));
+        touchpad.on_touch(touch(0xFFFF_0001, TouchState::Start, 3.0));
+        assert_eq!(
+            states(&touchpad),
+            [(0, TouchState::End), (1, TouchState::Move), (2, TouchState::Start)]
+        );
+
+        touchpad.advance(1.0);
+        touchpad.on_touch(touch(0xFFFF_0003, TouchState::Start, 4.0));
+        touchpad.on_touch(touch(0xFFFF_0004, TouchState::End, 4.0));
+        assert_eq!(
+            states(&touchpad),
+            [(1, TouchState::Move), (2, TouchState::Move), (0, TouchState::Start)]
+        );
+
+        // Cancelled touches are delivered too.
+        touchpad.advance(1.0);
+        touchpad.on_touch(touch(0xFFFF_0002, TouchState::Cancel, 2.0));
+        touchpad.reset();
+        assert_eq!(
+            states(&touchpad),
+            [(1, TouchState::Cancel), (2, TouchState::Cancel), (0, TouchState::Cancel)]
+        );
+
+        touchpad.advance(1.0);
+        assert!(touchpad.touches().is_empty());
+    }
+}
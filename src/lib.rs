@@ -27,13 +27,20 @@
 //! ```
 
 extern crate gl;
+extern crate base64;
 extern crate glutin;
 extern crate libc;
+extern crate png;
 pub extern crate cgmath as math;
 
 #[macro_use]
 extern crate error_chain;
 
+extern crate rusttype;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate two_lock_queue;
 extern crate zip;
 
@@ -47,4 +54,5 @@ pub mod graphics;
 pub mod resource;
 pub mod input;
 pub mod scene;
+pub mod text;
 pub mod prelude;
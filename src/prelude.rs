@@ -2,11 +2,11 @@ pub use math;
 pub use math::prelude::{Angle, InnerSpace, Matrix, One, SquareMatrix, Zero};
 
 pub use ecs;
-pub use ecs::{Arena, ArenaMut, Component, Entity, Fetch, FetchMut, System, View, World};
+pub use ecs::{Arena, ArenaMut, Component, Entity, Events, Fetch, FetchMut, System, View, World};
 
 pub use resource;
 pub use resource::{Location, ResourceSystem};
-pub use resource::filesystem::{DirectoryFS, ZipFS};
+pub use resource::filesystem::{DirectoryFS, HttpFS, MemoryFS, ZipFS};
 
 pub use application::{Application, Context, Engine, FrameInfo, Settings, TimeSystem};
 pub use application::{errors, event, time};
@@ -0,0 +1,137 @@
+//! A set of resources that are loading asynchronously, e.g. the assets of a level.
+
+use std::path::{Path, PathBuf};
+
+use super::errors::*;
+use super::future::ResourceFuture;
+use super::location::Location;
+
+/// `AssetBatch` tracks the `ResourceFuture`s of a set of resources, which is useful for
+/// loading screens. It never blocks, so the main loop could keep running and render the
+/// progress while the resources are streaming in on the background threads.
+///
+/// Failed resources are counted as finished, so the progress always reaches `1.0`, and
+/// the URIs of their `Location`s could be enumerated with `failures`.
+///
+/// ```rust,ignore
+/// let mut batch = resource.batch();
+/// let (mesh, future) = video.create_mesh_from_async::<Parser>(l1, MeshSetup::default())?;
+/// batch.add(l1, future);
+/// let (texture, future) = video.create_texture_from_async::<Parser>(l2, TextureSetup::default())?;
+/// batch.add(l2, future);
+///
+/// // Every frame.
+/// println!("Loading {:.0}%", batch.progress() * 100.0);
+/// if batch.is_complete() {
+///     for v in batch.failures() {
+///         println!("Failed to load {:?}.", v);
+///     }
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct AssetBatch {
+    assets: Vec<(PathBuf, ResourceFuture)>,
+}
+
+impl AssetBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        AssetBatch { assets: Vec::new() }
+    }
+
+    /// Adds a resource at `location` which is resolved with `future`.
+    pub fn add(&mut self, location: Location, future: ResourceFuture) {
+        self.assets.push((location.uri().to_owned(), future));
+    }
+
+    /// Gets the number of resources in this batch.
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// Returns true if there is no resource in this batch.
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Gets the fraction of resources that are ready or failed, in range `[0, 1]`. An
+    /// empty batch is always completed.
+    pub fn progress(&self) -> f32 {
+        if self.assets.is_empty() {
+            return 1.0;
+        }
+
+        let finished = self.assets.iter().filter(|v| v.1.is_finished()).count();
+        finished as f32 / self.assets.len() as f32
+    }
+
+    /// Returns true if all the resources are ready or failed.
+    pub fn is_complete(&self) -> bool {
+        self.assets.iter().all(|v| v.1.is_finished())
+    }
+
+    /// Gets the URIs of resources that failed to load, in the order they are added.
+    /// Resources that are still loading are not included.
+    pub fn failures(&self) -> Vec<&Path> {
+        self.assets
+            .iter()
+            .filter(|v| match v.1.poll() {
+                Some(Err(_)) => true,
+                _ => false,
+            })
+            .map(|v| v.0.as_path())
+            .collect()
+    }
+
+    /// Blocks the current thread until all the resources are ready or failed. The
+    /// first error is returned if any of them fails.
+    pub fn wait(&self) -> Result<()> {
+        let mut result = Ok(());
+        for v in &self.assets {
+            if let Err(err) = v.1.wait() {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::future::ResourcePromise;
+
+    #[test]
+    fn progress() {
+        let mut batch = AssetBatch::new();
+        assert!(batch.is_complete());
+        assert_eq!(batch.progress(), 1.0);
+
+        let l1 = Location::unique("/a.png");
+        let l2 = Location::unique("/b.obj");
+        let l3 = Location::unique("/c.obj");
+        let p2 = ResourcePromise::new();
+        let p3 = ResourcePromise::new();
+
+        batch.add(l1, ResourceFuture::ready());
+        batch.add(l2, p2.future());
+        batch.add(l3, p3.future());
+        assert_eq!(batch.len(), 3);
+        assert!(!batch.is_complete());
+        assert!(batch.failures().is_empty());
+
+        p2.resolve(Err("Broken.".into()));
+        assert!(!batch.is_complete());
+        assert_eq!(batch.failures(), [l2.uri()]);
+
+        // Failures count toward completion.
+        drop(p3);
+        assert!(batch.is_complete());
+        assert_eq!(batch.progress(), 1.0);
+        assert_eq!(batch.failures(), [l2.uri(), l3.uri()]);
+        assert!(batch.wait().is_err());
+    }
+}
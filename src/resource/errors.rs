@@ -15,5 +15,18 @@ error_chain!{
         DriveWithSameIdentFound
         DriveNotFound
         NotFound
+        HotReloadUnsupported
+        InvalidUrl(t: String) {
+            description("invalid url")
+            display("Invalid URL '{}'.", t)
+        }
+        HttpStatus(code: u16, url: String) {
+            description("unexpected http status")
+            display("Unexpected HTTP status {} of '{}'.", code, url)
+        }
+        LoadFailed(t: String) {
+            description("failed to load resource")
+            display("Failed to load resource. {}", t)
+        }
     }
 }
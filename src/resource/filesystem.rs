@@ -5,12 +5,15 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 
 use zip;
 
 use utils::HashValue;
 use super::errors::*;
 
+pub use super::http::HttpFS;
+
 /// `Filesystem` enumerates all the io operations that should be supported.
 pub trait Filesystem: Sync + Send {
     /// Return whether the path points at an existing file.
@@ -18,6 +21,12 @@ pub trait Filesystem: Sync + Send {
 
     /// Read all bytes until EOF in this source, and placing them into `buf`.
     fn load_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<()>;
+
+    /// Return the last modification time of file, which is used to detect changes
+    /// when hot reloading is enabled.
+    fn modified(&self, _: &Path) -> Result<SystemTime> {
+        bail!(ErrorKind::HotReloadUnsupported);
+    }
 }
 
 /// The driver of the virtual filesystem (VFS).
@@ -94,6 +103,21 @@ impl FilesystemDriver {
         bail!(ErrorKind::DriveNotFound);
     }
 
+    /// Return the last modification time of file.
+    pub fn modified<P>(&self, path: P) -> Result<SystemTime>
+    where
+        P: AsRef<Path>,
+    {
+        if let Some((bundle, file)) = FilesystemDriver::parse(path.as_ref().components()) {
+            let hash = HashValue::from(bundle);
+            if let Some(fs) = self.filesystems.get(&hash) {
+                return fs.modified(file);
+            }
+        }
+
+        bail!(ErrorKind::DriveNotFound);
+    }
+
     fn parse<'a>(mut cmps: Components<'a>) -> Option<(&'a str, &'a Path)> {
         while let Some(v) = cmps.next() {
             if let Component::Normal(ident) = v {
@@ -139,9 +163,70 @@ impl Filesystem for DirectoryFS {
         file.read_to_end(buf)?;
         Ok(())
     }
+
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        let meta = fs::metadata(self.wp.join(path))?;
+        Ok(meta.modified()?)
+    }
+}
+
+/// A virtual file system that keeps files in memory, which is useful to feed synthetic
+/// bytes to loaders in tests.
+///
+/// ```rust,ignore
+/// let fs = MemoryFS::new().insert("foo/mock.txt", "Hello, World!");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFS {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFS {
+    /// Create a new and empty memory filesystem.
+    pub fn new() -> Self {
+        MemoryFS {
+            files: HashMap::new(),
+        }
+    }
+
+    /// Register a virtual file at `path`, the old one will be replaced if exists.
+    pub fn insert<P, T>(mut self, path: P, bytes: T) -> Self
+    where
+        P: AsRef<Path>,
+        T: Into<Vec<u8>>,
+    {
+        self.files
+            .insert(MemoryFS::canonicalize(path.as_ref()), bytes.into());
+        self
+    }
+
+    fn canonicalize(path: &Path) -> PathBuf {
+        path.components()
+            .filter(|v| match *v {
+                Component::Normal(_) | Component::ParentDir => true,
+                _ => false,
+            })
+            .collect()
+    }
+}
+
+impl Filesystem for MemoryFS {
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(&MemoryFS::canonicalize(path))
+    }
+
+    fn load_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<()> {
+        if let Some(bytes) = self.files.get(&MemoryFS::canonicalize(path)) {
+            buf.extend_from_slice(bytes);
+            Ok(())
+        } else {
+            bail!(ErrorKind::NotFound);
+        }
+    }
 }
 
-/// A virtual file sytem that builds on a zip archive.
+/// A virtual file sytem that builds on a zip archive. Notes that hot reloading is not
+/// supported by `ZipFS`.
 pub struct ZipFS {
     archive: RwLock<zip::ZipArchive<fs::File>>,
 }
@@ -0,0 +1,168 @@
+//! The completion of resources that are loading asynchronously.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::errors::*;
+
+struct FutureState {
+    result: Mutex<Option<::std::result::Result<(), String>>>,
+    cvar: Condvar,
+}
+
+/// `ResourceFuture` resolves when a resource that is loading asynchronously becomes
+/// ready, or fails with the error of loading or parsing. It could be cloned, and
+/// waited on from any thread.
+///
+/// ```rust,ignore
+/// let (texture, future) = video.create_texture_from_async::<Parser>(location, setup)?;
+/// if let Err(err) = future.wait() {
+///     println!("{:?}", err);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ResourceFuture {
+    state: Arc<FutureState>,
+}
+
+impl ResourceFuture {
+    /// Creates a future which has been resolved successfully, e.g. for resources that
+    /// are created from memory.
+    pub fn ready() -> Self {
+        let promise = ResourcePromise::new();
+        promise.resolve(Ok(()));
+        promise.future()
+    }
+
+    /// Returns true if the resource is ready or failed.
+    pub fn is_finished(&self) -> bool {
+        self.state.result.lock().unwrap().is_some()
+    }
+
+    /// Gets the result without blocking, `None` is returned if it's still loading.
+    pub fn poll(&self) -> Option<Result<()>> {
+        let result = self.state.result.lock().unwrap();
+        result.as_ref().map(ResourceFuture::convert)
+    }
+
+    /// Blocks the current thread until the resource is ready or failed.
+    pub fn wait(&self) -> Result<()> {
+        let mut result = self.state.result.lock().unwrap();
+        while result.is_none() {
+            result = self.state.cvar.wait(result).unwrap();
+        }
+
+        ResourceFuture::convert(result.as_ref().unwrap())
+    }
+
+    /// Blocks the current thread until the resource is ready or failed, or `timeout`
+    /// elapsed. `None` is returned if it's still loading.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<()>> {
+        let deadline = Instant::now() + timeout;
+        let mut result = self.state.result.lock().unwrap();
+
+        while result.is_none() {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+
+            result = self.state.cvar.wait_timeout(result, deadline - now).unwrap().0;
+        }
+
+        result.as_ref().map(ResourceFuture::convert)
+    }
+
+    fn convert(result: &::std::result::Result<(), String>) -> Result<()> {
+        match *result {
+            Ok(_) => Ok(()),
+            Err(ref err) => bail!(ErrorKind::LoadFailed(err.clone())),
+        }
+    }
+}
+
+/// The producer side of `ResourceFuture`, which is held by the async loader of
+/// resource. The futures are resolved with an error if the promise is dropped before
+/// being resolved, e.g. the parser panics.
+pub struct ResourcePromise {
+    state: Arc<FutureState>,
+}
+
+impl ResourcePromise {
+    /// Creates a new and unresolved promise.
+    pub fn new() -> Self {
+        let state = FutureState {
+            result: Mutex::new(None),
+            cvar: Condvar::new(),
+        };
+
+        ResourcePromise {
+            state: Arc::new(state),
+        }
+    }
+
+    /// Gets a future that resolves with this promise.
+    pub fn future(&self) -> ResourceFuture {
+        ResourceFuture {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Resolves the futures, and wakes up the threads waiting on them. Only the first
+    /// result takes effect.
+    pub fn resolve(&self, result: ::std::result::Result<(), String>) {
+        // The lock might be poisoned if a waiting thread panics.
+        if let Ok(mut v) = self.state.result.lock() {
+            if v.is_none() {
+                *v = Some(result);
+                self.state.cvar.notify_all();
+            }
+        }
+    }
+}
+
+impl Default for ResourcePromise {
+    fn default() -> Self {
+        ResourcePromise::new()
+    }
+}
+
+impl Drop for ResourcePromise {
+    fn drop(&mut self) {
+        self.resolve(Err("The loader was dropped before finishing.".into()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn resolve() {
+        let promise = ResourcePromise::new();
+        let future = promise.future();
+        assert!(!future.is_finished());
+        assert!(future.poll().is_none());
+        assert!(future.wait_timeout(Duration::from_millis(1)).is_none());
+
+        let worker = future.clone();
+        let join = thread::spawn(move || worker.wait().is_ok());
+        promise.resolve(Ok(()));
+        promise.resolve(Err("Ignored.".into()));
+        assert!(join.join().unwrap());
+        assert!(future.poll().unwrap().is_ok());
+
+        assert!(ResourceFuture::ready().wait().is_ok());
+    }
+
+    #[test]
+    fn dropped() {
+        let future = {
+            let promise = ResourcePromise::new();
+            promise.future()
+        };
+
+        assert!(future.wait().is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! A virtual file system that fetches files from a remote HTTP server.
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::str;
+use std::time::Duration;
+
+use super::errors::*;
+use super::filesystem::Filesystem;
+
+/// The default timeout in seconds of connecting, reading and writing sockets.
+const DEFAULT_TIMEOUT: u64 = 30;
+
+/// Maps the files of a remote HTTP server into virtual file system. The path of file
+/// is resolved against the base URL, e.g. `/res/textures/stone.png` is fetched from
+/// `http://localhost:8080/assets/textures/stone.png` if the `HttpFS` is mounted as
+/// `res` with base URL `http://localhost:8080/assets`.
+///
+/// ```rust,ignore
+/// let fs = HttpFS::new("http://localhost:8080/assets")?.with_cache("target/cache")?;
+/// resource.mount("res", fs)?;
+/// ```
+///
+/// Files are fetched with blocking requests on the worker thread of `ResourceSystem`,
+/// so they work with `load_async` just like the local ones. Notes that hot reloading
+/// is not supported by `HttpFS`.
+///
+/// It's a minimal HTTP/1.1 client built on `TcpStream`, which is meant for streaming
+/// assets from a development server rather than talking to the internet. The limits
+/// are:
+///
+/// - Only plain `http://` is supported, there is no TLS.
+/// - Redirections are not followed, a `3xx` response fails with `HttpStatus`.
+/// - Every request opens a new connection, and the whole response is buffered in
+///   memory before it's parsed.
+/// - Bodies are requested with `Accept-Encoding: identity`. The `chunked` transfer
+///   encoding is decoded, but its trailers are ignored.
+/// - Proxies are not supported.
+///
+/// Connecting, reading and writing the sockets are all bounded by the timeout, see
+/// `HttpFS::with_timeout`.
+#[derive(Debug, Clone)]
+pub struct HttpFS {
+    host: String,
+    port: u16,
+    base: String,
+    cache: Option<PathBuf>,
+    timeout: Duration,
+}
+
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl HttpFS {
+    /// Creates a new HTTP filesystem with base URL.
+    pub fn new<T>(url: T) -> Result<Self>
+    where
+        T: AsRef<str>,
+    {
+        let url = url.as_ref();
+        let invalid = || ErrorKind::InvalidUrl(url.to_owned());
+
+        if url.starts_with("https://") {
+            bail!("HTTPS is not supported by HttpFS.");
+        }
+
+        if !url.starts_with("http://") {
+            bail!(invalid());
+        }
+
+        let rest = &url["http://".len()..];
+        let (authority, base) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+
+        let (host, port) = match authority.rfind(':') {
+            Some(i) => {
+                let port = authority[i + 1..].parse().map_err(|_| invalid())?;
+                (&authority[..i], port)
+            }
+            None => (authority, 80),
+        };
+
+        if host.is_empty() {
+            bail!(invalid());
+        }
+
+        Ok(HttpFS {
+            host: host.to_owned(),
+            port: port,
+            base: base.trim_right_matches('/').to_owned(),
+            cache: None,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+        })
+    }
+
+    /// Caches the downloaded files in local directory `path`, so the following requests
+    /// of the same file are served locally. The cached files are never revalidated, it's
+    /// up to user to clear the directory when the remote files change.
+    pub fn with_cache<P>(mut self, path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        fs::create_dir_all(&path)?;
+        self.cache = Some(path.as_ref().to_owned());
+        Ok(self)
+    }
+
+    /// Sets the timeout of connecting, reading and writing sockets. It's 30 seconds by
+    /// default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reads the bytes in `range` of file with a range request, and placing them into
+    /// `buf`. This is useful to stream parts of large files.
+    pub fn load_range(&self, path: &Path, range: Range<u64>, buf: &mut Vec<u8>) -> Result<()> {
+        if range.start >= range.end {
+            return Ok(());
+        }
+
+        if let Some(mut file) = self.cached_file(path).and_then(|v| fs::File::open(v).ok()) {
+            file.seek(SeekFrom::Start(range.start))?;
+            file.take(range.end - range.start).read_to_end(buf)?;
+            return Ok(());
+        }
+
+        let response = self.request("GET", path, Some(range.clone()))?;
+        match response.status {
+            206 => buf.extend_from_slice(&response.body),
+            // The server does not support range requests, and responds the whole file.
+            200 => {
+                let len = response.body.len() as u64;
+                let (start, end) = (range.start.min(len), range.end.min(len));
+                buf.extend_from_slice(&response.body[start as usize..end as usize]);
+            }
+            v => bail!(self.status_error(path, v)),
+        }
+
+        Ok(())
+    }
+
+    fn request(&self, method: &str, path: &Path, range: Option<Range<u64>>) -> Result<Response> {
+        let uri = self.uri(path)?;
+        let host = if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        };
+
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nAccept-Encoding: identity\r\n",
+            method, uri, host
+        );
+
+        if let Some(range) = range {
+            request.push_str(&format!("Range: bytes={}-{}\r\n", range.start, range.end - 1));
+        }
+
+        request.push_str("\r\n");
+
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(request.as_bytes())?;
+
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes)?;
+        parse_response(&bytes, method == "HEAD")
+    }
+
+    /// Connects to the first resolved address that accepts within the timeout.
+    fn connect(&self) -> Result<TcpStream> {
+        let mut last = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last = Some(err),
+            }
+        }
+
+        match last {
+            Some(err) => Err(err.into()),
+            None => bail!(format!("Failed to resolve host {:?}.", self.host)),
+        }
+    }
+
+    fn get(&self, path: &Path) -> Result<Vec<u8>> {
+        let response = self.request("GET", path, None)?;
+        if response.status != 200 {
+            bail!(self.status_error(path, response.status));
+        }
+
+        Ok(response.body)
+    }
+
+    fn status_error(&self, path: &Path, status: u16) -> Error {
+        if status == 404 {
+            return ErrorKind::NotFound.into();
+        }
+
+        let url = self.uri(path).unwrap_or_default();
+        ErrorKind::HttpStatus(status, url).into()
+    }
+
+    /// Gets the percent-encoded URI of file.
+    fn uri(&self, path: &Path) -> Result<String> {
+        let mut uri = self.base.clone();
+        for v in HttpFS::components(path)? {
+            uri.push('/');
+            uri.push_str(&percent_encode(&v));
+        }
+
+        Ok(uri)
+    }
+
+    fn cached_file(&self, path: &Path) -> Option<PathBuf> {
+        let components = HttpFS::components(path).ok()?;
+        self.cache
+            .as_ref()
+            .map(|v| components.iter().fold(v.clone(), |acc, v| acc.join(v)))
+    }
+
+    fn write_cache(file: &Path, bytes: &[u8]) -> Result<()> {
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Writes into a temporary file first, so a interrupted download will never be
+        // served as a complete one.
+        let tmp = file.with_extension("download");
+        fs::File::create(&tmp)?.write_all(bytes)?;
+        fs::rename(&tmp, file)?;
+        Ok(())
+    }
+
+    fn components(path: &Path) -> Result<Vec<String>> {
+        let mut components = Vec::new();
+        for v in path.components() {
+            match v {
+                Component::Normal(v) => {
+                    let v = v.to_str().ok_or(ErrorKind::NotFound)?;
+                    components.push(v.to_owned());
+                }
+                Component::ParentDir => bail!(ErrorKind::NotFound),
+                _ => {}
+            }
+        }
+
+        Ok(components)
+    }
+}
+
+impl Filesystem for HttpFS {
+    fn exists(&self, path: &Path) -> bool {
+        if self.cached_file(path).map(|v| v.is_file()).unwrap_or(false) {
+            return true;
+        }
+
+        self.request("HEAD", path, None)
+            .map(|v| v.status == 200)
+            .unwrap_or(false)
+    }
+
+    fn load_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<()> {
+        let cached = self.cached_file(path);
+
+        if let Some(mut file) = cached.as_ref().and_then(|v| fs::File::open(v).ok()) {
+            file.read_to_end(buf)?;
+            return Ok(());
+        }
+
+        let bytes = self.get(path)?;
+        if let Some(file) = cached {
+            // Failures of caching do not affect the loading.
+            let _ = HttpFS::write_cache(&file, &bytes);
+        }
+
+        buf.extend_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+fn parse_response(bytes: &[u8], head: bool) -> Result<Response> {
+    let end = find(bytes, b"\r\n\r\n").ok_or("Malformed HTTP response.")?;
+    let header = str::from_utf8(&bytes[..end]).map_err(|_| "Malformed HTTP response.")?;
+
+    let mut lines = header.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|v| v.split(' ').nth(1))
+        .and_then(|v| v.parse().ok())
+        .ok_or("Malformed status line of HTTP response.")?;
+
+    let mut chunked = false;
+    let mut len = None;
+    for line in lines {
+        if let Some(i) = line.find(':') {
+            let value = line[i + 1..].trim();
+            match line[..i].trim().to_lowercase().as_str() {
+                "transfer-encoding" => chunked = value.to_lowercase().contains("chunked"),
+                "content-length" => len = value.parse::<usize>().ok(),
+                _ => {}
+            }
+        }
+    }
+
+    let body = &bytes[end + 4..];
+    let body = if head {
+        Vec::new()
+    } else if chunked {
+        decode_chunked(body)?
+    } else if let Some(len) = len {
+        body.get(..len).ok_or("Truncated HTTP response.")?.to_vec()
+    } else {
+        body.to_vec()
+    };
+
+    Ok(Response {
+        status: status,
+        body: body,
+    })
+}
+
+fn decode_chunked(mut bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+
+    loop {
+        let i = find(bytes, b"\r\n").ok_or("Truncated HTTP chunk.")?;
+        let size = str::from_utf8(&bytes[..i])
+            .ok()
+            .and_then(|v| v.split(';').next())
+            .and_then(|v| usize::from_str_radix(v.trim(), 16).ok())
+            .ok_or("Malformed HTTP chunk.")?;
+
+        bytes = &bytes[i + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+
+        body.extend_from_slice(bytes.get(..size).ok_or("Truncated HTTP chunk.")?);
+        bytes = bytes.get(size + 2..).ok_or("Truncated HTTP chunk.")?;
+    }
+}
+
+fn find(bytes: &[u8], pattern: &[u8]) -> Option<usize> {
+    bytes.windows(pattern.len()).position(|v| v == pattern)
+}
+
+fn percent_encode(v: &str) -> String {
+    let mut encoded = String::with_capacity(v.len());
+    for &b in v.as_bytes() {
+        match b {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn url() {
+        let fs = HttpFS::new("http://localhost:8080/assets/").unwrap();
+        assert_eq!(fs.host, "localhost");
+        assert_eq!(fs.port, 8080);
+
+        let uri = fs.uri(Path::new("/textures/stone 1.png")).unwrap();
+        assert_eq!(uri, "/assets/textures/stone%201.png");
+        assert!(fs.uri(Path::new("../secret")).is_err());
+
+        let fs = HttpFS::new("http://example.com").unwrap();
+        assert_eq!(fs.port, 80);
+        assert_eq!(fs.uri(Path::new("a.txt")).unwrap(), "/a.txt");
+
+        assert!(HttpFS::new("https://example.com").is_err());
+        assert!(HttpFS::new("ftp://example.com").is_err());
+        assert!(HttpFS::new("http://:80").is_err());
+        assert!(HttpFS::new("http://example.com:port").is_err());
+    }
+
+    #[test]
+    fn response() {
+        let bytes = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHello, World!";
+        let response = parse_response(bytes, false).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"Hello");
+
+        let bytes = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      5\r\nHello\r\n8;ext=1\r\n, World!\r\n0\r\n\r\n";
+        let response = parse_response(bytes, false).unwrap();
+        assert_eq!(response.body, b"Hello, World!");
+
+        let bytes = b"HTTP/1.0 404 Not Found\r\nContent-Length: 5\r\n\r\n";
+        let response = parse_response(bytes, true).unwrap();
+        assert_eq!(response.status, 404);
+        assert!(response.body.is_empty());
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n", false).is_err());
+        let bytes = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nA\r\nHello";
+        assert!(parse_response(bytes, false).is_err());
+    }
+}
@@ -60,13 +60,58 @@
 //! assert!(l3 != l4);
 //! ```
 //!
+//! ## Hot Reloading
+//!
+//! Resources that loaded from a `DirectoryFS` could be reloaded automatically when their
+//! files change on disk, which is useful when iterating on shaders and textures. It's
+//! disabled by default, and could be enabled with `ResourceSystem::enable_hot_reload`.
+//!
+//! ```rust,ignore
+//! resource.enable_hot_reload(true);
+//! resource.shared().set_hot_reload_failure_callback(|path, err| {
+//!     println!("Failed to reload {:?}. {:?}", path, err);
+//! });
+//! ```
+//!
 //! ## Lifetime (TODO)
 //!
-//! ## Asynchronization (TODO)
+//! ## Asynchronization
+//!
+//! Resources created from a `Location` are loaded and parsed on the background thread,
+//! and their handles could be used immediately. The `_async` variants of creation also
+//! return a `ResourceFuture`, which resolves when the resource becomes ready or fails.
+//! A loading screen could block on a batch of resources, or poll them every frame to
+//! show the progress.
+//!
+//! ```rust,ignore
+//! let (mesh, f1) = video.create_mesh_from_async::<Parser>(l1, MeshSetup::default())?;
+//! let (texture, f2) = video.create_texture_from_async::<Parser>(l2, TextureSetup::default())?;
+//!
+//! let futures = [f1, f2];
+//! let finished = futures.iter().filter(|v| v.is_finished()).count();
+//! println!("Progress: {}/{}", finished, futures.len());
+//!
+//! resource.join_all(&futures)?;
+//! ```
+//!
+//! The futures could also be collected into an `AssetBatch`, which reports the progress
+//! and the failed resources of a loading screen without blocking the main loop.
+//!
+//! ```rust,ignore
+//! let mut batch = resource.batch();
+//! batch.add(l1, f1);
+//! batch.add(l2, f2);
+//!
+//! println!("Progress: {}", batch.progress());
+//! if batch.is_complete() {
+//!     println!("Failures: {:?}", batch.failures());
+//! }
+//! ```
 //!
 
 pub mod errors;
 pub mod filesystem;
+mod http;
 pub mod cache;
 
 mod location;
@@ -75,5 +120,12 @@ pub use self::location::Location;
 mod registery;
 pub use self::registery::Registery;
 
+mod future;
+pub use self::future::{ResourceFuture, ResourcePromise};
+
+mod batch;
+pub use self::batch::AssetBatch;
+
 mod resource;
-pub use self::resource::{ResourceAsyncLoader, ResourceSystem, ResourceSystemShared};
+pub use self::resource::{HotReloadFailureCallback, ResourceAsyncLoader, ResourceHotReloader,
+                         ResourceSystem, ResourceSystemShared};
@@ -1,10 +1,15 @@
-use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
 use std::thread;
 
 use two_lock_queue;
 
 use super::filesystem::{Filesystem, FilesystemDriver};
+use super::future::ResourceFuture;
+use super::batch::AssetBatch;
 use super::errors::*;
 
 /// The callbacks of async loader.
@@ -12,6 +17,22 @@ pub trait ResourceAsyncLoader: Send + Sync + 'static {
     fn on_finished(self, _: &Path, _: Result<&[u8]>);
 }
 
+/// The callbacks of hot reloading, which will be invoked on the watcher thread when
+/// the watched file changes.
+pub trait ResourceHotReloader: Send + Sync + 'static {
+    /// Returns false if the resource has been deleted, and the watch will be removed.
+    fn is_alive(&self) -> bool;
+
+    /// Reloads the resource with the new contents of file.
+    fn on_reload(&self, _: &Path, _: &[u8]) -> Result<()>;
+}
+
+/// The callback that will be invoked if anything goes wrong when hot reloading.
+pub type HotReloadFailureCallback = Fn(&Path, &Error) + Send + Sync;
+
+/// The interval that watcher thread polls the modification time of files.
+const HOT_RELOAD_INTERVAL: u64 = 500;
+
 /// Takes care of loading data asynchronously through pluggable filesystems.
 pub struct ResourceSystem {
     filesystems: Arc<RwLock<FilesystemDriver>>,
@@ -22,7 +43,8 @@ impl ResourceSystem {
     /// Creates a new `ResourceSystem`.
     ///
     /// Notes that this will spawn a worker thread running background to perform
-    /// io requests.
+    /// io requests. The watcher thread, which detects changes of files, is spawned
+    /// the first time hot reloading is enabled.
     pub fn new() -> Result<Self> {
         let driver = Arc::new(RwLock::new(FilesystemDriver::new()));
 
@@ -38,7 +60,8 @@ impl ResourceSystem {
                 .unwrap();
         }
 
-        let shared = ResourceSystemShared::new(driver.clone(), tx);
+        let watcher = Arc::new(HotReloadWatcher::new());
+        let shared = ResourceSystemShared::new(driver.clone(), tx, watcher);
 
         Ok(ResourceSystem {
             filesystems: driver,
@@ -70,6 +93,12 @@ impl ResourceSystem {
         self.filesystems.write().unwrap().unmount(ident);
     }
 
+    /// Enables or disables hot reloading of resources.
+    #[inline]
+    pub fn enable_hot_reload(&self, enable: bool) {
+        self.shared.enable_hot_reload(enable);
+    }
+
     fn run(chan: two_lock_queue::Receiver<ResourceTask>, driver: Arc<RwLock<FilesystemDriver>>) {
         let mut buf = Vec::new();
 
@@ -77,7 +106,10 @@ impl ResourceSystem {
             match chan.recv().unwrap() {
                 ResourceTask::Load { mut closure } => {
                     let driver = driver.read().unwrap();
-                    closure(&driver, &mut buf);
+                    // Keeps the worker alive if a parser panics, the loader is dropped
+                    // during unwinding and reports the failure by itself.
+                    let task = panic::AssertUnwindSafe(|| closure(&driver, &mut buf));
+                    let _ = panic::catch_unwind(task);
                 }
 
                 ResourceTask::Stop => return,
@@ -102,6 +134,7 @@ impl ResourceSystem {
 pub struct ResourceSystemShared {
     filesystems: Arc<RwLock<FilesystemDriver>>,
     chan: two_lock_queue::Sender<ResourceTask>,
+    watcher: Arc<HotReloadWatcher>,
 }
 
 enum ResourceTask {
@@ -115,21 +148,36 @@ impl ResourceSystemShared {
     fn new(
         filesystems: Arc<RwLock<FilesystemDriver>>,
         chan: two_lock_queue::Sender<ResourceTask>,
+        watcher: Arc<HotReloadWatcher>,
     ) -> Self {
         ResourceSystemShared {
             filesystems: filesystems,
             chan: chan,
+            watcher: watcher,
         }
     }
 
     /// Return whether the path points at an existing file.
-    pub fn exists<T, P>(&self, path: P) -> bool
+    pub fn exists<P>(&self, path: P) -> bool
     where
         P: AsRef<Path>,
     {
         self.filesystems.read().unwrap().exists(path)
     }
 
+    /// Load a file at location `path` synchronously on the calling thread.
+    pub fn load<P>(&self, path: P) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut buf = Vec::new();
+        self.filesystems
+            .read()
+            .unwrap()
+            .load_into(path.as_ref(), &mut buf)?;
+        Ok(buf)
+    }
+
     /// Load a file at location `path` asynchronously.
     ///
     /// `ResourceAsyncLoader::on_finished` will be called if task finishs or any
@@ -155,10 +203,182 @@ impl ResourceSystemShared {
             })
             .unwrap();
     }
+
+    /// Blocks the current thread until all the `futures` have been resolved. The first
+    /// error is returned if any of them fails, after all the others are finished.
+    pub fn join_all<'a, I>(&self, futures: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a ResourceFuture>,
+    {
+        let mut result = Ok(());
+        for v in futures {
+            if let Err(err) = v.wait() {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Creates an empty `AssetBatch`, which tracks the progress of a set of resources
+    /// that are loading asynchronously.
+    pub fn batch(&self) -> AssetBatch {
+        AssetBatch::new()
+    }
+
+    /// Enables or disables hot reloading of resources. Notes that only the resources
+    /// created after hot reloading is enabled will be watched.
+    pub fn enable_hot_reload(&self, enable: bool) {
+        self.watcher.enabled.store(enable, Ordering::SeqCst);
+
+        if enable && !self.watcher.spawned.swap(true, Ordering::SeqCst) {
+            let driver = self.filesystems.clone();
+            let watcher = self.watcher.clone();
+            thread::Builder::new()
+                .name("RESOURCE-WATCHER".into())
+                .spawn(move || {
+                    watcher.run(&driver);
+                })
+                .unwrap();
+        }
+    }
+
+    /// Returns true if hot reloading is enabled.
+    pub fn is_hot_reload_enabled(&self) -> bool {
+        self.watcher.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Sets the callback that will be invoked if anything goes wrong when hot
+    /// reloading, e.g. a broken edit of shader.
+    pub fn set_hot_reload_failure_callback<F>(&self, callback: F)
+    where
+        F: Fn(&Path, &Error) + Send + Sync + 'static,
+    {
+        *self.watcher.callback.write().unwrap() = Some(Arc::new(callback));
+    }
+
+    /// Watches the file at location `path`, `ResourceHotReloader::on_reload` will be
+    /// called when the file changes.
+    ///
+    /// An `HotReloadUnsupported` error is returned, and passed to the failure callback,
+    /// if the underlying filesystem does not support hot reloading.
+    pub fn watch<T, P>(&self, reloader: T, path: P) -> Result<()>
+    where
+        T: ResourceHotReloader,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let modified = self.filesystems.read().unwrap().modified(path);
+
+        match modified {
+            Ok(modified) => {
+                let watch = HotReloadWatch {
+                    path: path.to_owned(),
+                    modified: modified,
+                    reloader: Box::new(reloader),
+                };
+
+                self.watcher.watches.lock().unwrap().push(watch);
+                Ok(())
+            }
+            Err(err) => {
+                self.notify_reload_failure(path, &err);
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn notify_reload_failure(&self, path: &Path, err: &Error) {
+        self.watcher.notify(path, err);
+    }
 }
 
 impl Drop for ResourceSystemShared {
     fn drop(&mut self) {
+        self.watcher.stopped.store(true, Ordering::SeqCst);
         self.chan.send(ResourceTask::Stop).unwrap();
     }
 }
+
+struct HotReloadWatch {
+    path: PathBuf,
+    modified: SystemTime,
+    reloader: Box<ResourceHotReloader>,
+}
+
+struct HotReloadWatcher {
+    enabled: AtomicBool,
+    spawned: AtomicBool,
+    stopped: AtomicBool,
+    watches: Mutex<Vec<HotReloadWatch>>,
+    callback: RwLock<Option<Arc<HotReloadFailureCallback>>>,
+}
+
+impl HotReloadWatcher {
+    fn new() -> Self {
+        HotReloadWatcher {
+            enabled: AtomicBool::new(false),
+            spawned: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            watches: Mutex::new(Vec::new()),
+            callback: RwLock::new(None),
+        }
+    }
+
+    fn run(&self, driver: &RwLock<FilesystemDriver>) {
+        let mut buf = Vec::new();
+        let mut failures = Vec::new();
+
+        while !self.stopped.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(HOT_RELOAD_INTERVAL));
+
+            if !self.enabled.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            {
+                let mut watches = self.watches.lock().unwrap();
+                watches.retain(|v| v.reloader.is_alive());
+
+                let driver = driver.read().unwrap();
+                for v in watches.iter_mut() {
+                    let modified = match driver.modified(&v.path) {
+                        Ok(modified) => modified,
+                        // The file might be in the middle of saving, try it next time.
+                        Err(_) => continue,
+                    };
+
+                    if modified == v.modified {
+                        continue;
+                    }
+
+                    v.modified = modified;
+                    buf.clear();
+
+                    let result = driver
+                        .load_into(&v.path, &mut buf)
+                        .and_then(|_| v.reloader.on_reload(&v.path, &buf));
+
+                    if let Err(err) = result {
+                        failures.push((v.path.clone(), err));
+                    }
+                }
+            }
+
+            // The callback is invoked without holding any locks, so it could watch
+            // or load other files.
+            for (path, err) in failures.drain(..) {
+                self.notify(&path, &err);
+            }
+        }
+    }
+
+    fn notify(&self, path: &Path, err: &Error) {
+        let callback = self.callback.read().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(path, err);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Keyframe animations of skeletons.
+
+use std::sync::Arc;
+use std::time::Duration;
+use std::cmp::Ordering;
+
+use ecs;
+use math;
+use math::InnerSpace;
+
+use scene::skeleton::Skeleton;
+
+/// Values that could be interpolated between keyframes.
+pub trait Interpolate: Copy {
+    fn interpolate(&self, rhs: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for math::Vector3<f32> {
+    fn interpolate(&self, rhs: &Self, t: f32) -> Self {
+        *self + (*rhs - *self) * t
+    }
+}
+
+impl Interpolate for math::Quaternion<f32> {
+    fn interpolate(&self, rhs: &Self, t: f32) -> Self {
+        // Takes the shortest path between rotations.
+        let rhs = if self.dot(*rhs) < 0.0 { -*rhs } else { *rhs };
+        self.nlerp(rhs, t)
+    }
+}
+
+/// A track of keyframes in ascending order of time. The keyframes are not required
+/// to be evenly spaced.
+#[derive(Debug, Clone)]
+pub struct Keyframes<T> {
+    frames: Vec<(f32, T)>,
+}
+
+impl<T> Default for Keyframes<T> {
+    fn default() -> Self {
+        Keyframes { frames: Vec::new() }
+    }
+}
+
+impl<T> Keyframes<T>
+where
+    T: Interpolate,
+{
+    /// Creates a empty track.
+    pub fn new() -> Self {
+        Keyframes::default()
+    }
+
+    /// Inserts a keyframe at `time` in seconds.
+    pub fn insert(&mut self, time: f32, value: T) {
+        let i = self.upper_bound(time);
+        self.frames.insert(i, (time, value));
+    }
+
+    /// Gets the number of keyframes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if there is no keyframe in this track.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Gets the time of the last keyframe.
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map(|v| v.0).unwrap_or(0.0)
+    }
+
+    /// Samples the value at `time` by interpolating the nearest keyframes. Values are
+    /// clamped before the first keyframe and after the last one.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let i = self.upper_bound(time);
+        if i == 0 {
+            return Some(self.frames[0].1);
+        }
+
+        if i == self.frames.len() {
+            return Some(self.frames[i - 1].1);
+        }
+
+        let (t0, v0) = self.frames[i - 1];
+        let (t1, v1) = self.frames[i];
+        Some(v0.interpolate(&v1, (time - t0) / (t1 - t0)))
+    }
+
+    /// Gets the index of the first keyframe later than `time`.
+    fn upper_bound(&self, time: f32) -> usize {
+        let search = self.frames.binary_search_by(|v| {
+            if v.0 > time {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        });
+
+        match search {
+            Ok(i) | Err(i) => i,
+        }
+    }
+}
+
+/// The keyframes of a joint. Components without keyframes are left untouched when
+/// the channel is applied.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationChannel {
+    pub translations: Keyframes<math::Vector3<f32>>,
+    pub rotations: Keyframes<math::Quaternion<f32>>,
+    pub scales: Keyframes<math::Vector3<f32>>,
+}
+
+impl AnimationChannel {
+    /// Gets the time of the last keyframe in all components.
+    pub fn duration(&self) -> f32 {
+        self.translations
+            .duration()
+            .max(self.rotations.duration())
+            .max(self.scales.duration())
+    }
+}
+
+/// An animation clip, which consists of channels that animate joints of skeleton.
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    channels: Vec<(usize, AnimationChannel)>,
+    duration: f32,
+    looping: bool,
+}
+
+impl Animation {
+    /// Creates a empty animation clip.
+    pub fn new(looping: bool) -> Self {
+        Animation {
+            channels: Vec::new(),
+            duration: 0.0,
+            looping: looping,
+        }
+    }
+
+    /// Adds a channel that animates the joint at index `joint` of skeleton.
+    pub fn add_channel(&mut self, joint: usize, channel: AnimationChannel) {
+        self.duration = self.duration.max(channel.duration());
+        self.channels.push((joint, channel));
+    }
+
+    /// Gets the duration of clip in seconds.
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Returns true if the clip restarts from the beginning after it ends.
+    #[inline]
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Sets whether the clip is looping.
+    #[inline]
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Wraps `time` into the range of clip if it's looping, or clamps it otherwise.
+    pub fn normalize_time(&self, time: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+
+        if self.looping {
+            let time = time % self.duration;
+            if time < 0.0 {
+                time + self.duration
+            } else {
+                time
+            }
+        } else {
+            time.max(0.0).min(self.duration)
+        }
+    }
+
+    /// Applies the poses at `time` to the joints of `skeleton`.
+    pub fn apply(&self, time: f32, skeleton: &mut Skeleton) {
+        let time = self.normalize_time(time);
+
+        for &(joint, ref channel) in &self.channels {
+            if let Some(mut pose) = skeleton.joint(joint).map(|v| v.pose) {
+                if let Some(v) = channel.translations.sample(time) {
+                    pose.position = v;
+                }
+
+                if let Some(v) = channel.rotations.sample(time) {
+                    pose.rotation = v;
+                }
+
+                if let Some(v) = channel.scales.sample(time) {
+                    pose.scale = v;
+                }
+
+                skeleton.set_pose(joint, pose);
+            }
+        }
+    }
+}
+
+/// `Animator` plays an animation clip on the `Skeleton` of the same node. It's driven
+/// by the frame delta of `TimeSystem` with `Scene::advance_animations`.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    clip: Arc<Animation>,
+    time: f32,
+    speed: f32,
+    playing: bool,
+}
+
+/// Declare `Animator` as component with hash map storage.
+impl ecs::Component for Animator {
+    type Arena = ecs::HashMapArena<Animator>;
+}
+
+impl Animator {
+    /// Creates a new `Animator` which starts playing `clip` from the beginning.
+    pub fn new(clip: Arc<Animation>) -> Self {
+        Animator {
+            clip: clip,
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+        }
+    }
+
+    /// Gets the animation clip.
+    #[inline]
+    pub fn clip(&self) -> &Arc<Animation> {
+        &self.clip
+    }
+
+    /// Gets the current time of clip in seconds.
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Jumps to `time` of clip.
+    #[inline]
+    pub fn seek(&mut self, time: f32) {
+        self.time = self.clip.normalize_time(time);
+    }
+
+    /// Sets the playback speed, negative speed plays the clip backwards.
+    #[inline]
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    #[inline]
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    #[inline]
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    #[inline]
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Returns true if a clip that is not looping has reached its end.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        !self.clip.is_looping() && self.time >= self.clip.duration()
+    }
+
+    /// Advances the clip by `dt`, and applies the poses to `skeleton`.
+    pub fn advance(&mut self, dt: Duration, skeleton: &mut Skeleton) {
+        if self.playing {
+            let dt = dt.as_secs() as f32 + dt.subsec_nanos() as f32 / 1_000_000_000.0;
+            self.time = self.clip.normalize_time(self.time + dt * self.speed);
+        }
+
+        self.clip.apply(self.time, skeleton);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::{One, SquareMatrix};
+    use scene::skeleton::JointPose;
+
+    fn v3(x: f32) -> math::Vector3<f32> {
+        math::Vector3::new(x, 0.0, 0.0)
+    }
+
+    #[test]
+    fn keyframes() {
+        let mut track = Keyframes::new();
+        assert_eq!(track.sample(0.0), None);
+
+        // Non-uniform spacing, and inserted out of order.
+        track.insert(3.0, v3(4.0));
+        track.insert(0.0, v3(0.0));
+        track.insert(1.0, v3(2.0));
+
+        assert_eq!(track.len(), 3);
+        assert_eq!(track.duration(), 3.0);
+        assert_eq!(track.sample(-1.0), Some(v3(0.0)));
+        assert_eq!(track.sample(0.5), Some(v3(1.0)));
+        assert_eq!(track.sample(1.0), Some(v3(2.0)));
+        assert_eq!(track.sample(2.0), Some(v3(3.0)));
+        assert_eq!(track.sample(5.0), Some(v3(4.0)));
+    }
+
+    #[test]
+    fn rotations() {
+        let mut track = Keyframes::new();
+        let q = math::Quaternion::one();
+        track.insert(0.0, q);
+        track.insert(1.0, -q);
+
+        // The same rotation with opposite signs, the shortest path does not rotate.
+        let v = track.sample(0.5).unwrap();
+        assert!((v.s.abs() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn looping() {
+        let mut channel = AnimationChannel::default();
+        channel.translations.insert(0.0, v3(0.0));
+        channel.translations.insert(2.0, v3(2.0));
+
+        let mut clip = Animation::new(true);
+        clip.add_channel(0, channel);
+        assert_eq!(clip.duration(), 2.0);
+        assert_eq!(clip.normalize_time(3.0), 1.0);
+        assert_eq!(clip.normalize_time(-0.5), 1.5);
+
+        clip.set_looping(false);
+        assert_eq!(clip.normalize_time(3.0), 2.0);
+        assert_eq!(clip.normalize_time(-0.5), 0.0);
+
+        let mut skeleton = Skeleton::new();
+        let identity = math::Matrix4::identity();
+        skeleton
+            .add_joint("root", None, identity, JointPose::default())
+            .unwrap();
+
+        clip.set_looping(true);
+        let mut animator = Animator::new(Arc::new(clip));
+        animator.advance(Duration::from_millis(2500), &mut skeleton);
+        assert_eq!(animator.time(), 0.5);
+        assert_eq!(skeleton.joint(0).unwrap().pose.position, v3(0.5));
+
+        animator.pause();
+        animator.advance(Duration::from_millis(500), &mut skeleton);
+        assert_eq!(animator.time(), 0.5);
+        assert!(!animator.is_finished());
+    }
+}
@@ -0,0 +1,13 @@
+#version 100
+precision lowp float;
+attribute vec3 Position;
+attribute vec4 Color0;
+
+uniform mat4 u_MVPMatrix;
+
+varying vec4 v_Color;
+
+void main() {
+    v_Color = Color0;
+    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+#version 100
+precision lowp float;
+
+varying vec4 v_Color;
+
+void main() {
+    gl_FragColor = v_Color;
+}
\ No newline at end of file
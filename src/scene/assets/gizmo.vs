@@ -0,0 +1,15 @@
+#version 100
+precision lowp float;
+attribute vec3 Position;
+attribute vec4 Texcoord0;
+attribute vec4 Texcoord1;
+attribute vec4 Color0;
+
+varying vec4 v_Color;
+
+void main() {
+    // Lines are still lines after projection, so it's fine to interpolate the end
+    // points in clip space.
+    v_Color = Color0;
+    gl_Position = mix(Texcoord0, Texcoord1, Position.x);
+}
@@ -17,12 +17,19 @@ uniform vec3 u_EmissiveFactor;
 uniform sampler2D u_MetallicRoughnessSampler;
 uniform vec2 u_MetallicRoughnessValues;
 
+// Scene-wide render settings, fog params are (start, end, enable).
+uniform vec3 u_AmbientColor;
+uniform vec3 u_FogColor;
+uniform vec3 u_FogParams;
+uniform float u_Exposure;
+uniform int u_DebugMode;
+
 uniform vec4 u_ScaleDiffBaseMR;
 uniform vec4 u_ScaleFGDSpec;
 uniform vec4 u_ScaleIBLAmbient;
 
 in vec3 v_Position;
-in vec3 v_Normal;
+in mat3 v_TBN;
 in vec2 v_Texcoord0;
 
 out vec4 FragColor;
@@ -54,23 +61,17 @@ const float c_MinRoughness = 0.04;
 vec3 getNormal()
 {
     // Retrieve the tangent space matrix
-    vec3 pos_dx = dFdx(v_Position);
-    vec3 pos_dy = dFdy(v_Position);
-    vec3 tex_dx = dFdx(vec3(v_UV, 0.0));
-    vec3 tex_dy = dFdy(vec3(v_UV, 0.0));
-    vec3 t = (tex_dy.t * pos_dx - tex_dx.t * pos_dy) / (tex_dx.s * tex_dy.t - tex_dy.s * tex_dx.t);
-    vec3 ng = normalize(v_Normal);
-
-    t = normalize(t - ng * dot(ng, t));
-    vec3 b = normalize(cross(ng, t));
+    vec3 ng = normalize(v_TBN[2]);
+    vec3 t = normalize(v_TBN[0] - ng * dot(ng, v_TBN[0]));
+    vec3 b = normalize(v_TBN[1]);
     mat3 tbn = mat3(t, b, ng);
 
 // #ifdef HAS_NORMALMAP
-//     vec3 n = texture(u_NormalSampler, v_UV).rgb;
+//     vec3 n = texture(u_NormalSampler, v_Texcoord0).rgb;
 //     n = normalize(tbn * ((2.0 * n - 1.0) * vec3(u_NormalScale, u_NormalScale, 1.0)));
 // #else
     vec3 n = tbn[2].xyz;
-#endif
+// #endif
 
     // reverse backface normals
     // TODO!: correct/best place? -> https://github.com/KhronosGroup/glTF-WebGL-PBR/issues/51
@@ -129,7 +130,7 @@ void main()
 
     // Roughness is stored in the 'g' channel, metallic is stored in the 'b' channel.
     // This layout intentionally reserves the 'r' channel for (optional) occlusion map data
-    vec4 mrSample = texture(u_MetallicRoughnessSampler, v_UV);
+    vec4 mrSample = texture(u_MetallicRoughnessSampler, v_Texcoord0);
     perceptualRoughness = mrSample.g * perceptualRoughness;
     metallic = mrSample.b * metallic;
 
@@ -140,7 +141,7 @@ void main()
     float alphaRoughness = perceptualRoughness * perceptualRoughness;
 
     // The albedo may be defined from a base texture or a flat color
-    vec4 baseColor = texture(u_BaseColorSampler, v_UV) * u_BaseColorFactor;
+    vec4 baseColor = texture(u_BaseColorSampler, v_Texcoord0) * u_BaseColorFactor;
 
     vec3 f0 = vec3(0.04);
     vec3 diffuseColor = baseColor.rgb * (vec3(1.0) - f0);
@@ -193,9 +194,12 @@ void main()
     vec3 specContrib = F * G * D / (4.0 * NdotL * NdotV);
     vec3 color = NdotL * u_LightColor * (diffuseContrib + specContrib);
 
-    vec3 emissive = texture(u_EmissiveSampler, v_UV).rgb * u_EmissiveFactor;
+    vec3 emissive = texture(u_EmissiveSampler, v_Texcoord0).rgb * u_EmissiveFactor;
     color += emissive;
 
+    color += u_AmbientColor * diffuseColor;
+    color *= u_Exposure;
+
     // This section uses mix to override final color for reference app visualization
     // of various parameters in the lighting equation.
     color = mix(color, F, u_ScaleFGDSpec.x);
@@ -208,5 +212,25 @@ void main()
     color = mix(color, vec3(metallic), u_ScaleDiffBaseMR.z);
     color = mix(color, vec3(perceptualRoughness), u_ScaleDiffBaseMR.w);
 
+    float distance = length(v_Position);
+    if (u_FogParams.z > 0.5)
+    {
+        float fog = (distance - u_FogParams.x) / max(u_FogParams.y - u_FogParams.x, 0.0001);
+        color = mix(color, u_FogColor, clamp(fog, 0.0, 1.0));
+    }
+
+    if (u_DebugMode == 1)
+    {
+        color = n * 0.5 + 0.5;
+    }
+    else if (u_DebugMode == 2)
+    {
+        color = baseColor.rgb;
+    }
+    else if (u_DebugMode == 3)
+    {
+        color = vec3(distance / (distance + 1.0));
+    }
+
     FragColor = vec4(color, baseColor.a);
 }
\ No newline at end of file
@@ -1,23 +1,31 @@
 #version 330 core
 
-layout(location = 0) in vec4 Position;
-layout(location = 1) in vec4 Normal;
-layout(location = 2) in vec2 Texcoord0;
+layout(location = 0) in vec3 Position;
+layout(location = 1) in vec3 Normal;
+layout(location = 2) in vec4 Tangent;
+layout(location = 3) in vec2 Texcoord0;
 
 uniform mat4 u_MVPMatrix;
 uniform mat4 u_ModelViewMatrix;
 uniform mat4 u_NormalMatrix;
 
 out vec3 v_Position;
-out vec3 v_Normal;
+out mat3 v_TBN;
 out vec2 v_Texcoord0;
 
 void main()
 {
-    vec4 pos = u_ModelViewMatrix * Position;
+    vec4 pos = u_ModelViewMatrix * vec4(Position, 1.0);
     v_Position = vec3(pos.xyz) / pos.w;
-    v_Normal = normalize(vec3(u_NormalMatrix * vec4(Normal.xyz, 0.0)));
+
+    vec3 normal = normalize(vec3(u_NormalMatrix * vec4(Normal, 0.0)));
+    vec3 tangent = normalize(vec3(u_ModelViewMatrix * vec4(Tangent.xyz, 0.0)));
+    // Re-orthogonalize since the model view matrix might have non-uniform scales.
+    tangent = normalize(tangent - normal * dot(normal, tangent));
+    vec3 bitangent = cross(normal, tangent) * Tangent.w;
+    v_TBN = mat3(tangent, bitangent, normal);
+
     v_Texcoord0 = Texcoord0;
 
-    gl_Position = u_MVPMatrix * Position;
-}
\ No newline at end of file
+    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+}
@@ -1,17 +1,41 @@
 #version 100
 #define MAX_POINT_LIGHTS 4
+#define MAX_SPOT_LIGHTS 2
 
 precision lowp float;
 
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+#define SHADOW_PRECISION highp
+#else
+#define SHADOW_PRECISION mediump
+#endif
+
 varying vec3 v_EyeFragPos;
 varying vec3 v_EyeNormal;
 varying vec4 v_Color;
+varying SHADOW_PRECISION vec4 v_LightSpacePos;
 
 uniform vec3 u_DirLightEyeDir;
 uniform vec3 u_DirLightColor;
 uniform vec3 u_PointLightEyePos[MAX_POINT_LIGHTS];
 uniform vec3 u_PointLightColor[MAX_POINT_LIGHTS];
 uniform vec3 u_PointLightAttenuation[MAX_POINT_LIGHTS];
+uniform vec3 u_SpotLightEyePos[MAX_SPOT_LIGHTS];
+uniform vec3 u_SpotLightEyeDir[MAX_SPOT_LIGHTS];
+uniform vec3 u_SpotLightColor[MAX_SPOT_LIGHTS];
+uniform vec3 u_SpotLightAttenuation[MAX_SPOT_LIGHTS];
+uniform vec3 u_SpotLightCutoff[MAX_SPOT_LIGHTS];
+
+// Shadow of directional light, params are (bias, enable, unused).
+uniform sampler2D u_ShadowMap;
+uniform vec3 u_ShadowParams;
+
+// Scene-wide render settings, fog params are (start, end, enable).
+uniform vec3 u_AmbientColor;
+uniform vec3 u_FogColor;
+uniform vec3 u_FogParams;
+uniform float u_Exposure;
+uniform int u_DebugMode;
 
 // Phong materials
 uniform vec3 u_Ambient;
@@ -27,6 +51,53 @@ vec3 CalculateLight(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 reflectDir)
     return (0.2 * ambient + 0.5 * diffuse + specular);
 }
 
+vec4 ApplySettings(vec4 color, vec3 normal, vec4 unlit)
+{
+    float distance = length(v_EyeFragPos);
+
+    if (u_FogParams.z > 0.5)
+    {
+        float fog = (distance - u_FogParams.x) / max(u_FogParams.y - u_FogParams.x, 0.0001);
+        color.rgb = mix(color.rgb, u_FogColor, clamp(fog, 0.0, 1.0));
+    }
+
+    if (u_DebugMode == 1)
+    {
+        return vec4(normal * 0.5 + 0.5, 1.0);
+    }
+
+    if (u_DebugMode == 2)
+    {
+        return unlit;
+    }
+
+    if (u_DebugMode == 3)
+    {
+        return vec4(vec3(distance / (distance + 1.0)), 1.0);
+    }
+
+    return color;
+}
+
+float CalculateShadow()
+{
+    if (u_ShadowParams.y < 0.5)
+    {
+        return 0.0;
+    }
+
+    SHADOW_PRECISION vec3 coords = v_LightSpacePos.xyz / v_LightSpacePos.w * 0.5 + 0.5;
+
+    // Fragments outside the light frustum are never shadowed.
+    if (coords.z > 1.0 || coords.x < 0.0 || coords.x > 1.0 || coords.y < 0.0 || coords.y > 1.0)
+    {
+        return 0.0;
+    }
+
+    SHADOW_PRECISION float closest = texture2D(u_ShadowMap, coords.xy).r;
+    return coords.z - u_ShadowParams.x > closest ? 1.0 : 0.0;
+}
+
 void main()
 {
     vec3 normal = normalize(v_EyeNormal);
@@ -35,6 +106,7 @@ void main()
     // directional light
     vec3 reflectDir = reflect(-u_DirLightEyeDir, normal);
     vec3 result = CalculateLight(normal, viewDir, u_DirLightEyeDir, reflectDir) * u_DirLightColor;
+    result *= 1.0 - CalculateShadow();
 
     // point lights
     for(int i = 0; i < MAX_POINT_LIGHTS; i++)
@@ -51,5 +123,30 @@ void main()
         result += max(power * attenuation, vec3(0.0, 0.0, 0.0));
     }
 
-    gl_FragColor = vec4(result, 1.0) * v_Color;
+    // spot lights
+    for(int i = 0; i < MAX_SPOT_LIGHTS; i++)
+    {
+        vec3 lightDir3 = normalize(v_EyeFragPos - u_SpotLightEyePos[i]);
+        vec3 reflectDir3 = reflect(-lightDir3, normal);
+        float distance = length(u_SpotLightEyePos[i] - v_EyeFragPos);
+        float attenuation =
+            u_SpotLightAttenuation[i].x +
+            u_SpotLightAttenuation[i].y * distance +
+            u_SpotLightAttenuation[i].z * (distance * distance);
+
+        // x and y are the cosines of inner and outer cutoff, the cone has a hard edge
+        // when they are equal.
+        float theta = dot(lightDir3, normalize(u_SpotLightEyeDir[i]));
+        float inner = u_SpotLightCutoff[i].x;
+        float outer = u_SpotLightCutoff[i].y;
+        float cone = inner > outer ? smoothstep(outer, inner, theta) : step(outer, theta);
+
+        vec3 power = CalculateLight(normal, viewDir, lightDir3, reflectDir3) * u_SpotLightColor[i];
+        result += max(power * attenuation * cone, vec3(0.0, 0.0, 0.0));
+    }
+
+    result += u_AmbientColor * u_Ambient;
+    result *= u_Exposure;
+
+    gl_FragColor = ApplySettings(vec4(result, 1.0) * v_Color, normal, v_Color);
 }
\ No newline at end of file
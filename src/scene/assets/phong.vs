@@ -8,15 +8,19 @@ attribute vec4 Color0;
 uniform mat4 u_ModelViewMatrix;
 uniform mat4 u_MVPMatrix;
 uniform mat4 u_NormalMatrix;
+uniform mat4 u_ModelMatrix;
+uniform mat4 u_LightSpaceMatrix;
 
 varying vec3 v_EyeFragPos;
 varying vec3 v_EyeNormal;
 varying vec4 v_Color;
+varying vec4 v_LightSpacePos;
 
 void main() {
     gl_Position = u_MVPMatrix * vec4(Position, 1.0);
 
     v_EyeFragPos = vec3(u_ModelViewMatrix * vec4(Position, 1.0));
-    v_EyeNormal = vec3(u_NormalMatrix * vec4(Normal, 1.0));
+    v_EyeNormal = vec3(u_NormalMatrix * vec4(Normal, 0.0));
     v_Color = Color0;
+    v_LightSpacePos = u_LightSpaceMatrix * u_ModelMatrix * vec4(Position, 1.0);
 }
\ No newline at end of file
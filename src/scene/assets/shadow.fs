@@ -0,0 +1,7 @@
+#version 100
+precision lowp float;
+
+// Only the depth is written into shadow map.
+void main() {
+    gl_FragColor = vec4(1.0, 1.0, 1.0, 1.0);
+}
\ No newline at end of file
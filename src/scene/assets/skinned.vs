@@ -0,0 +1,37 @@
+precision lowp float;
+
+attribute vec3 Position;
+attribute vec3 Normal;
+attribute vec4 Color0;
+attribute vec4 Indices;
+attribute vec4 Weight;
+
+uniform mat4 u_ModelViewMatrix;
+uniform mat4 u_MVPMatrix;
+uniform mat4 u_NormalMatrix;
+uniform mat4 u_ModelMatrix;
+uniform mat4 u_LightSpaceMatrix;
+uniform mat4 u_JointMatrices[MAX_JOINTS];
+
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+varying vec4 v_Color;
+varying vec4 v_LightSpacePos;
+
+void main() {
+    mat4 skin =
+        u_JointMatrices[int(Indices.x)] * Weight.x +
+        u_JointMatrices[int(Indices.y)] * Weight.y +
+        u_JointMatrices[int(Indices.z)] * Weight.z +
+        u_JointMatrices[int(Indices.w)] * Weight.w;
+
+    vec4 position = skin * vec4(Position, 1.0);
+    vec4 normal = skin * vec4(Normal, 0.0);
+
+    gl_Position = u_MVPMatrix * position;
+
+    v_EyeFragPos = vec3(u_ModelViewMatrix * position);
+    v_EyeNormal = vec3(u_NormalMatrix * normal);
+    v_Color = Color0;
+    v_LightSpacePos = u_LightSpaceMatrix * u_ModelMatrix * position;
+}
\ No newline at end of file
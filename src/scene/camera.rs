@@ -1,24 +1,75 @@
 //! A device through which the player views the world.
 
 use math;
-use math::{Angle, Zero};
+use math::{Angle, InnerSpace, SquareMatrix, Zero};
+
+use graphics::Comparison;
+use utils::Ray;
 
 /// The projection funcs used when take primitives into camera.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Projection {
     /// Orthographic projection with orthographic-size, half vertical
     /// size of camera, in pixels as payload.
     Ortho(f32),
+    /// Orthographic projection with the left, right, bottom and top planes of view
+    /// volume, which is not necessarily centered around the camera. The near and far
+    /// planes are the clipping planes of camera.
+    OrthoOffCenter {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+    },
     /// Perspective projection with `fov`, field of view, in degree as payload.
     Perspective(math::Rad<f32>),
 }
 
+/// The corner of screen where the origin of 2D camera is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreenOrigin {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
 /// A `Camera` is a device through which the player views the world.
-#[derive(Debug, Clone, Copy)]
+///
+/// # Reverse-Z
+///
+/// The depth precision of standard projection clusters near the camera, so distant
+/// geometry of large scenes suffers z-fighting. With `set_reversed_z`, the near plane
+/// maps to depth 1.0 and the far plane to 0.0 instead, which spreads the precision of a
+/// floating-point depth buffer (`RenderTextureFormat::Depth32F`) over the distances.
+///
+/// The depth comparisons should be reversed accordingly, e.g. `GreaterOrEqual` instead
+/// of `LessOrEqual`, which is done automatically for the meshes rendered by `Scene`,
+/// and could be queried with `depth_comparison` for custom draw calls.
+///
+/// The projection maps depth into `[0, 1]` directly, so the camera should be rendered
+/// into a surface with reverse-Z, which sets up `glClipControl` and clears the depth
+/// buffer to 0.0. It requires OpenGL 4.5 or `GL_ARB_clip_control`:
+///
+/// ```rust,ignore
+/// let mut camera = Camera::perspective(math::Deg(60.0), aspect, 0.1, 100000.0);
+/// camera.set_reversed_z(true);
+///
+/// let mut setup = SurfaceSetup::default();
+/// setup.set_reversed_z(true);
+/// let surface = video.create_surface(setup)?;
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Camera {
     aspect: f32,
     clip: math::Vector2<f32>,
     projection: Projection,
+    viewport: ((f32, f32), (f32, f32)),
+    priority: i32,
+    origin: ScreenOrigin,
+    pixel_perfect: bool,
+    #[serde(default)]
+    reversed_z: bool,
 }
 
 impl Default for Camera {
@@ -27,23 +78,39 @@ impl Default for Camera {
             aspect: 1.0,
             clip: math::Vector2::new(0.1, 1000.0),
             projection: Projection::Perspective(math::Deg(60.0).into()),
+            viewport: ((0.0, 0.0), (1.0, 1.0)),
+            priority: 0,
+            origin: ScreenOrigin::BottomLeft,
+            pixel_perfect: false,
+            reversed_z: false,
         }
     }
 }
 
 impl Camera {
-    /// Creates a new camera with orthographic projection.
+    /// Creates a new camera with orthographic projection, the view volume is centered
+    /// around the camera.
     pub fn ortho(width: f32, height: f32, near: f32, far: f32) -> Camera {
         let camera = Camera {
             aspect: width / height,
             clip: math::Vector2::new(near, far),
             projection: Projection::Ortho(height * 0.5),
+            ..Default::default()
         };
 
         camera.validate();
         camera
     }
 
+    /// Creates a new 2D camera with orthographic projection, which maps one unit in
+    /// world to one pixel on screen. The origin is placed at the bottom-left corner of
+    /// screen by default.
+    pub fn ortho_2d(width: f32, height: f32) -> Camera {
+        let mut camera = Camera::ortho(width, height, -1000.0, 1000.0);
+        camera.resize((width, height));
+        camera
+    }
+
     /// Creates a new camera with perspective projection.
     pub fn perspective<T>(fovy: T, aspect: f32, near: f32, far: f32) -> Camera
     where
@@ -53,6 +120,7 @@ impl Camera {
             aspect: aspect,
             clip: math::Vector2::new(near, far),
             projection: Projection::Perspective(fovy.into()),
+            ..Default::default()
         };
 
         camera.validate();
@@ -93,21 +161,227 @@ impl Camera {
     /// Sets the projection type.
     #[inline(always)]
     pub fn set_projection(&mut self, projection: Projection) {
+        if let Projection::OrthoOffCenter {
+            left,
+            right,
+            bottom,
+            top,
+        } = projection
+        {
+            self.aspect = ((right - left) / (top - bottom)).abs();
+        }
+
         self.projection = projection;
         self.validate();
     }
 
+    /// Sets the corner of screen where the origin of 2D camera is placed. Notes that
+    /// the y axis points down if the origin is at the top, which reverses the winding
+    /// order of triangles on screen.
+    #[inline]
+    pub fn set_screen_origin(&mut self, origin: ScreenOrigin) {
+        self.origin = origin;
+        if let Projection::OrthoOffCenter {
+            left,
+            right,
+            bottom,
+            top,
+        } = self.projection
+        {
+            let size = ((right - left).abs(), (top - bottom).abs());
+            self.resize(size);
+        }
+    }
+
+    /// Gets the corner of screen where the origin of 2D camera is placed.
+    #[inline]
+    pub fn screen_origin(&self) -> ScreenOrigin {
+        self.origin
+    }
+
+    /// Enables or disables the pixel-perfect mode. The projection will be updated with
+    /// the dimensions of the viewport in pixels before rendering, so one unit always
+    /// equals to one physical pixel even if the window resizes, or on hidpi screens.
+    /// This keeps UI and sprites crisp.
+    #[inline]
+    pub fn set_pixel_perfect(&mut self, enable: bool) {
+        self.pixel_perfect = enable;
+    }
+
+    /// Returns true if the pixel-perfect mode is enabled.
+    #[inline]
+    pub fn is_pixel_perfect(&self) -> bool {
+        self.pixel_perfect
+    }
+
+    /// Resets the projection into a 2D orthographic projection of `dimensions`, with
+    /// the origin at the corner specified by `set_screen_origin`.
+    pub fn resize(&mut self, dimensions: (f32, f32)) {
+        let (w, h) = dimensions;
+        let (left, right) = match self.origin {
+            ScreenOrigin::BottomLeft | ScreenOrigin::TopLeft => (0.0, w),
+            ScreenOrigin::BottomRight | ScreenOrigin::TopRight => (-w, 0.0),
+        };
+
+        let (bottom, top) = match self.origin {
+            ScreenOrigin::BottomLeft | ScreenOrigin::BottomRight => (0.0, h),
+            ScreenOrigin::TopLeft | ScreenOrigin::TopRight => (h, 0.0),
+        };
+
+        self.set_projection(Projection::OrthoOffCenter {
+            left: left,
+            right: right,
+            bottom: bottom,
+            top: top,
+        });
+    }
+
+    /// Enables or disables the reverse-Z projection, which maps the near plane to depth
+    /// 1.0 and the far plane to 0.0. It's disabled by default.
+    ///
+    /// The camera should be rendered into a surface with `SurfaceSetup::set_reversed_z`.
+    #[inline]
+    pub fn set_reversed_z(&mut self, enable: bool) {
+        self.reversed_z = enable;
+    }
+
+    /// Returns true if the reverse-Z projection is enabled.
+    #[inline]
+    pub fn is_reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// Gets the depth comparison that pairs with the projection of this camera, where
+    /// `standard` is the one used with standard Z, e.g. `Comparison::LessOrEqual`.
+    #[inline]
+    pub fn depth_comparison(&self, standard: Comparison) -> Comparison {
+        if self.reversed_z {
+            standard.reversed()
+        } else {
+            standard
+        }
+    }
+
+    /// Sets the normalized viewport of camera, with `position` at the bottom-left
+    /// corner. The scene will be rendered into this region of surface only, which
+    /// makes split-screen or picture-in-picture possible.
+    #[inline(always)]
+    pub fn set_viewport(&mut self, position: (f32, f32), size: (f32, f32)) {
+        self.viewport = (position, size);
+    }
+
+    /// Gets the normalized viewport of camera.
+    #[inline(always)]
+    pub fn viewport(&self) -> ((f32, f32), (f32, f32)) {
+        self.viewport
+    }
+
+    /// Sets the priority of camera. When rendering multiple cameras, the ones with
+    /// greater priority are drawn later, on top of others.
+    #[inline(always)]
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
+    /// Gets the priority of camera.
+    #[inline(always)]
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Returns the matrix that maps normalized device coordinates into the viewport
+    /// of camera, or `None` if the viewport covers the whole surface.
+    pub(crate) fn viewport_matrix(&self) -> Option<math::Matrix4<f32>> {
+        let ((x, y), (w, h)) = self.viewport;
+        if (x, y, w, h) == (0.0, 0.0, 1.0, 1.0) {
+            return None;
+        }
+
+        let t = math::Vector3::new(2.0 * x + w - 1.0, 2.0 * y + h - 1.0, 0.0);
+        let s = math::Matrix4::from_nonuniform_scale(w, h, 1.0);
+        Some(math::Matrix4::from_translation(t) * s)
+    }
+
+    /// Gets the projection matrix based on projector.
+    #[deprecated(since = "0.2.0", note = "Use `Camera::projection_matrix` instead.")]
+    #[inline]
+    pub fn matrix(&self) -> math::Matrix4<f32> {
+        self.projection_matrix()
+    }
+
     /// Gets the projection matrix based on projector. The camera is aligned along the
     /// local coordinate system's positive z-axis.
-    pub fn matrix(&self) -> math::Matrix4<f32> {
-        match self.projection {
+    ///
+    /// The near and far planes are mapped to -1 and 1 of normalized device coordinates,
+    /// or 1 and 0 if the reverse-Z projection is enabled.
+    pub fn projection_matrix(&self) -> math::Matrix4<f32> {
+        let m = match self.projection {
             Projection::Ortho(vsize) => {
                 let hsize = vsize * self.aspect;
-                Camera::ortho_matrix(-hsize, hsize, -vsize, vsize, self.clip.x, self.clip.y).into()
+                Camera::ortho_matrix(-hsize, hsize, -vsize, vsize, self.clip.x, self.clip.y)
             }
+            Projection::OrthoOffCenter {
+                left,
+                right,
+                bottom,
+                top,
+            } => Camera::ortho_matrix(left, right, bottom, top, self.clip.x, self.clip.y),
             Projection::Perspective(fovy) => {
                 Camera::perspective_matrix(fovy, self.aspect, self.clip.x, self.clip.y).into()
             }
+        };
+
+        if self.reversed_z {
+            // Maps the depth from `[-1, 1]` into `[1, 0]`, which is clipped with the
+            // depth range `[0, 1]` of `glClipControl`.
+            let mut reverse = math::Matrix4::identity();
+            reverse.z.z = -0.5;
+            reverse.w.z = 0.5;
+            reverse * m
+        } else {
+            m
+        }
+    }
+
+    /// Creates a ray in world space which starts from the near plane and goes through
+    /// `point` in screen. The `point` is relative to the top-left corner of viewport,
+    /// and `view` is the world-to-view matrix of camera, which could be calculated
+    /// with `Transform::world_view_matrix`.
+    ///
+    /// Returns `None` if the view-projection matrix is not invertible, or the near and
+    /// far planes are degenerated into the same point.
+    pub fn screen_to_ray<P, V>(
+        &self,
+        view: math::Matrix4<f32>,
+        point: P,
+        viewport: V,
+    ) -> Option<Ray>
+    where
+        P: Into<math::Vector2<f32>>,
+        V: Into<math::Vector2<f32>>,
+    {
+        let point = point.into();
+        let viewport = viewport.into();
+
+        let x = point.x / viewport.x * 2.0 - 1.0;
+        let y = 1.0 - point.y / viewport.y * 2.0;
+
+        let (zn, zf) = if self.reversed_z {
+            (1.0, 0.0)
+        } else {
+            (-1.0, 1.0)
+        };
+
+        let inverse = (self.projection_matrix() * view).invert()?;
+        let near = inverse * math::Vector4::new(x, y, zn, 1.0);
+        let far = inverse * math::Vector4::new(x, y, zf, 1.0);
+
+        let near = math::Point3::from_homogeneous(near);
+        let far = math::Point3::from_homogeneous(far);
+        if (far - near).magnitude2() > 0.0 {
+            Some(Ray::new(near, far - near))
+        } else {
+            None
         }
     }
 
@@ -169,3 +443,106 @@ impl Camera {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn viewport() {
+        let mut camera = Camera::default();
+        assert!(camera.viewport_matrix().is_none());
+
+        // The right-top quarter.
+        camera.set_viewport((0.5, 0.5), (0.5, 0.5));
+        let m = camera.viewport_matrix().unwrap();
+
+        let p = m * math::Vector4::new(-1.0, -1.0, 0.5, 1.0);
+        assert_eq!(p, math::Vector4::new(0.0, 0.0, 0.5, 1.0));
+
+        let p = m * math::Vector4::new(1.0, 1.0, -0.5, 1.0);
+        assert_eq!(p, math::Vector4::new(1.0, 1.0, -0.5, 1.0));
+    }
+
+    fn project(camera: &Camera, x: f32, y: f32) -> (f32, f32) {
+        let p = camera.projection_matrix() * math::Vector4::new(x, y, 0.0, 1.0);
+        ((p.x * 1000.0).round() / 1000.0, (p.y * 1000.0).round() / 1000.0)
+    }
+
+    #[test]
+    fn ortho_2d() {
+        let mut camera = Camera::ortho_2d(200.0, 100.0);
+        assert_eq!(camera.aspect(), 2.0);
+        assert_eq!(project(&camera, 0.0, 0.0), (-1.0, -1.0));
+        assert_eq!(project(&camera, 200.0, 100.0), (1.0, 1.0));
+
+        camera.set_screen_origin(ScreenOrigin::TopLeft);
+        assert_eq!(project(&camera, 0.0, 0.0), (-1.0, 1.0));
+        assert_eq!(project(&camera, 200.0, 100.0), (1.0, -1.0));
+
+        camera.set_screen_origin(ScreenOrigin::BottomRight);
+        assert_eq!(project(&camera, 0.0, 0.0), (1.0, -1.0));
+
+        // One unit is still one pixel after resizing.
+        camera.set_screen_origin(ScreenOrigin::BottomLeft);
+        camera.resize((400.0, 100.0));
+        assert_eq!(project(&camera, 200.0, 50.0), (0.0, 0.0));
+        assert_eq!(camera.aspect(), 4.0);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn ortho() {
+        let camera = Camera::ortho(200.0, 100.0, 0.1, 100.0);
+        match camera.projection() {
+            Projection::Ortho(vsize) => assert_eq!(vsize, 50.0),
+            _ => unreachable!(),
+        }
+
+        assert_eq!(project(&camera, 0.0, 0.0), (0.0, 0.0));
+        assert_eq!(project(&camera, 100.0, 50.0), (1.0, 1.0));
+        assert_eq!(camera.matrix(), camera.projection_matrix());
+    }
+
+    fn depth(camera: &Camera, z: f32) -> f32 {
+        let p = camera.projection_matrix() * math::Vector4::new(0.0, 0.0, z, 1.0);
+        // Maps the normalized device coordinates into the default depth range, which
+        // is done by clip control with reverse-Z.
+        let depth = if camera.is_reversed_z() {
+            p.z / p.w
+        } else {
+            p.z / p.w * 0.5 + 0.5
+        };
+
+        (depth * 1000.0).round() / 1000.0
+    }
+
+    #[test]
+    fn reversed_z() {
+        let mut camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+        assert!(!camera.is_reversed_z());
+        assert_eq!(depth(&camera, 0.1), 0.0);
+        assert_eq!(depth(&camera, 100.0), 1.0);
+        assert!(depth(&camera, 1.0) < depth(&camera, 10.0));
+
+        camera.set_reversed_z(true);
+        assert_eq!(depth(&camera, 0.1), 1.0);
+        assert_eq!(depth(&camera, 100.0), 0.0);
+        assert!(depth(&camera, 1.0) > depth(&camera, 10.0));
+
+        let v = camera.depth_comparison(Comparison::LessOrEqual);
+        assert_eq!(v, Comparison::GreaterOrEqual);
+        assert_eq!(camera.depth_comparison(Comparison::Always), Comparison::Always);
+
+        // Rays still start from the near plane.
+        let view = math::Matrix4::identity();
+        let ray = camera.screen_to_ray(view, (50.0, 50.0), (100.0, 100.0)).unwrap();
+        assert!((ray.origin.z - 0.1).abs() < 1e-4);
+        assert!(ray.direction.z > 0.0);
+
+        let mut camera = Camera::ortho(2.0, 2.0, 1.0, 11.0);
+        camera.set_reversed_z(true);
+        assert_eq!(depth(&camera, 1.0), 1.0);
+        assert_eq!(depth(&camera, 11.0), 0.0);
+    }
+}
@@ -0,0 +1,294 @@
+//! Immediate mode drawing of lines, which is useful to visualize bounding boxes, rays
+//! and frustums when debugging.
+
+use std::sync::Arc;
+
+use application::Context;
+use math;
+use math::{EuclideanSpace, SquareMatrix, Transform};
+use graphics::*;
+use resource::Location;
+use utils::{Aabb3, Color, Ray};
+
+use scene::errors::*;
+use scene::factory;
+
+impl_vertex! {
+    DebugVertex {
+        position => [Position; Float; 3; false],
+        color => [Color0; UByte; 4; true],
+    }
+}
+
+/// The maximum number of lines that could be drawn with one `DebugRenderer` during a
+/// frame, which is limited by the 16-bits indices.
+pub const MAX_LINES: usize = 32768;
+
+/// A `DebugRenderer` accumulates colored line segments, and flushes them with one
+/// draw call of a dynamic `Primitive::Lines` mesh.
+///
+/// ```rust,ignore
+/// let mut debug = DebugRenderer::new(&ctx)?;
+/// debug.set_matrix(projection * view);
+///
+/// debug.aabb(&aabb, Color::red());
+/// debug.ray(&ray, 10.0, Color::green());
+/// debug.frustum(camera_projection * camera_view, Color::blue());
+/// debug.flush(surface, 0)?;
+/// ```
+///
+/// The lines are depth tested against the scene, but they never write depth.
+pub struct DebugRenderer {
+    video: Arc<GraphicsSystemShared>,
+    shader: ShaderHandle,
+    matrix: math::Matrix4<f32>,
+    verts: Vec<DebugVertex>,
+
+    mesh: Option<(usize, MeshHandle)>,
+    /// The frame index and the number of lines that have been uploaded during it.
+    cursor: (u64, usize),
+}
+
+impl DebugRenderer {
+    /// Creates a new `DebugRenderer` with the debug shader from factory.
+    pub fn new(ctx: &Context) -> Result<Self> {
+        let video = ctx.shared::<GraphicsSystem>().clone();
+        let shader = factory::shader::debug(&video)?;
+
+        Ok(DebugRenderer {
+            video: video,
+            shader: shader,
+            matrix: math::Matrix4::identity(),
+            verts: Vec::new(),
+            mesh: None,
+            cursor: (0, 0),
+        })
+    }
+
+    /// Sets the view-projection matrix of lines. It's the identity matrix by default.
+    #[inline]
+    pub fn set_matrix(&mut self, matrix: math::Matrix4<f32>) {
+        self.matrix = matrix;
+    }
+
+    /// Gets the number of lines that waiting for flushing.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.verts.len() / 2
+    }
+
+    /// Returns true if there is no line waiting for flushing.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.verts.is_empty()
+    }
+
+    /// Draws a line segment from `from` to `to`.
+    pub fn line(&mut self, from: math::Point3<f32>, to: math::Point3<f32>, color: Color) {
+        let color: [u8; 4] = color.into();
+        self.verts.push(DebugVertex::new(from.into(), color));
+        self.verts.push(DebugVertex::new(to.into(), color));
+    }
+
+    /// Draws the edges of axis-aligned bounding box.
+    pub fn aabb(&mut self, aabb: &Aabb3, color: Color) {
+        self.corners(&aabb_corners(aabb), color);
+    }
+
+    /// Draws the ray from its origin to the point at `len`.
+    pub fn ray(&mut self, ray: &Ray, len: f32, color: Color) {
+        self.line(ray.origin, ray.at(len), color);
+    }
+
+    /// Draws the edges of frustum, which is described by a view-projection `matrix`
+    /// like `Frustum::new`. Nothing is drawn if the matrix is not invertible.
+    pub fn frustum(&mut self, matrix: math::Matrix4<f32>, color: Color) {
+        if let Some(corners) = frustum_corners(matrix) {
+            self.corners(&corners, color);
+        }
+    }
+
+    /// Draws the axes of coordinate system described by `transform`, the X, Y and Z
+    /// axes are drawn in red, green and blue respectively with `len`.
+    pub fn axes(&mut self, transform: math::Matrix4<f32>, len: f32) {
+        let origin = transform.transform_point(math::Point3::origin());
+        let axes = [
+            (math::Vector3::unit_x(), Color::red()),
+            (math::Vector3::unit_y(), Color::green()),
+            (math::Vector3::unit_z(), Color::blue()),
+        ];
+
+        for &(axis, color) in &axes {
+            let to = transform.transform_point(math::Point3::from_vec(axis * len));
+            self.line(origin, to, color);
+        }
+    }
+
+    /// Submits all the lines into `surface` with `order`, and clears the renderer. It
+    /// could be flushed several times during a frame.
+    pub fn flush<T>(&mut self, surface: SurfaceHandle, order: T) -> Result<()>
+    where
+        T: Into<u64>,
+    {
+        if self.verts.is_empty() {
+            return Ok(());
+        }
+
+        let len = self.len();
+        let result = self.submit(surface, order.into(), len);
+        self.verts.clear();
+        result
+    }
+
+    fn submit(&mut self, surface: SurfaceHandle, order: u64, len: usize) -> Result<()> {
+        let (mesh, from) = self.reserve(len)?;
+
+        let offset = from * 2 * ::std::mem::size_of::<DebugVertex>();
+        let slice = DebugVertex::as_bytes(&self.verts);
+        let cmd = Command::update_vertex_buffer(mesh, offset, slice);
+        self.video.submit(surface, order, cmd)?;
+
+        let mut dc = DrawCall::new(self.shader, mesh);
+        dc.set_uniform_variable("u_MVPMatrix", self.matrix);
+        let cmd = dc.build_from(from * 2, len * 2)?;
+        self.video.submit(surface, order, cmd)?;
+        Ok(())
+    }
+
+    fn corners(&mut self, corners: &[math::Point3<f32>; 8], color: Color) {
+        // The four edges of near face, far face, and the ones between them.
+        for i in 0..4 {
+            let j = (i + 1) % 4;
+            self.line(corners[i], corners[j], color);
+            self.line(corners[i + 4], corners[j + 4], color);
+            self.line(corners[i], corners[i + 4], color);
+        }
+    }
+
+    /// Reserves room for `len` lines in the dynamic mesh. The lines of different
+    /// flushes during a frame are placed after each other, so they will not override
+    /// the others before being drawn.
+    fn reserve(&mut self, len: usize) -> Result<(MeshHandle, usize)> {
+        let frame_index = self.video.frame_index();
+        if self.cursor.0 != frame_index {
+            self.cursor = (frame_index, 0);
+        }
+
+        let required = self.cursor.1 + len;
+
+        if let Some((capacity, mesh)) = self.mesh {
+            if required <= capacity {
+                let from = self.cursor.1;
+                self.cursor.1 = required;
+                return Ok((mesh, from));
+            }
+
+            // The old mesh will be deleted after the drawing of this frame.
+            self.video.delete_mesh(mesh);
+            self.mesh = None;
+        }
+
+        if len > MAX_LINES {
+            bail!("Too many lines to draw with one DebugRenderer during a frame.");
+        }
+
+        let mut capacity = 256;
+        while capacity < required && capacity < MAX_LINES {
+            capacity *= 2;
+        }
+
+        let idxes: Vec<u16> = (0..capacity * 2).map(|v| v as u16).collect();
+
+        let mut setup = MeshSetup::default();
+        setup.hint = BufferHint::Stream;
+        setup.index_hint = Some(BufferHint::Static);
+        setup.layout = DebugVertex::layout();
+        setup.index_format = IndexFormat::U16;
+        setup.primitive = Primitive::Lines;
+        setup.num_verts = capacity * 2;
+        setup.num_idxes = capacity * 2;
+
+        let verts: Option<&[u8]> = None;
+        let ibytes = IndexFormat::as_bytes::<u16>(&idxes);
+        let mesh = self.video
+            .create_mesh(Location::unique(""), setup, verts, ibytes)?;
+
+        self.mesh = Some((capacity, mesh));
+        self.cursor.1 = len;
+        Ok((mesh, 0))
+    }
+}
+
+impl Drop for DebugRenderer {
+    fn drop(&mut self) {
+        if let Some((_, mesh)) = self.mesh.take() {
+            self.video.delete_mesh(mesh);
+        }
+
+        self.video.delete_shader(self.shader);
+    }
+}
+
+/// Gets the corners of aabb, the first four are the ones of near (min z) face.
+pub(crate) fn aabb_corners(aabb: &Aabb3) -> [math::Point3<f32>; 8] {
+    let (min, max) = (aabb.min, aabb.max);
+    [
+        math::Point3::new(min.x, min.y, min.z),
+        math::Point3::new(max.x, min.y, min.z),
+        math::Point3::new(max.x, max.y, min.z),
+        math::Point3::new(min.x, max.y, min.z),
+        math::Point3::new(min.x, min.y, max.z),
+        math::Point3::new(max.x, min.y, max.z),
+        math::Point3::new(max.x, max.y, max.z),
+        math::Point3::new(min.x, max.y, max.z),
+    ]
+}
+
+/// Gets the corners of frustum by un-projecting the corners of NDC cube, the first four
+/// are the ones of near plane.
+fn frustum_corners(matrix: math::Matrix4<f32>) -> Option<[math::Point3<f32>; 8]> {
+    let inverse = matrix.invert()?;
+
+    let mut corners = aabb_corners(&Aabb3::new(
+        math::Point3::new(-1.0, -1.0, -1.0),
+        math::Point3::new(1.0, 1.0, 1.0),
+    ));
+
+    for v in &mut corners {
+        let p = inverse * v.to_homogeneous();
+        *v = math::Point3::from_homogeneous(p);
+    }
+
+    Some(corners)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::InnerSpace;
+
+    fn assert_near(lhs: math::Point3<f32>, rhs: math::Point3<f32>) {
+        assert!((lhs - rhs).magnitude() < 1e-4, "{:?} != {:?}", lhs, rhs);
+    }
+
+    #[test]
+    fn corners() {
+        let aabb = Aabb3::new(math::Point3::new(0.0, 0.0, 0.0), math::Point3::new(1.0, 2.0, 3.0));
+        let corners = aabb_corners(&aabb);
+        assert_eq!(corners[0], aabb.min);
+        assert_eq!(corners[6], aabb.max);
+        assert!(corners.iter().all(|v| aabb.contains(*v)));
+
+        let corners = frustum_corners(math::Matrix4::identity()).unwrap();
+        assert_eq!(corners[0], math::Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(corners[6], math::Point3::new(1.0, 1.0, 1.0));
+
+        let ortho = math::ortho(-2.0, 2.0, -1.0, 1.0, 1.0, 10.0);
+        let corners = frustum_corners(ortho).unwrap();
+        assert_near(corners[0], math::Point3::new(-2.0, -1.0, -1.0));
+        assert_near(corners[6], math::Point3::new(2.0, 1.0, -10.0));
+
+        let degenerated = math::Matrix4::from_scale(0.0);
+        assert!(frustum_corners(degenerated).is_none());
+    }
+}
@@ -1,3 +1,6 @@
+use serde_json;
+
+use ecs;
 use graphics;
 use resource;
 
@@ -7,10 +10,15 @@ error_chain!{
     }
 
     links {
+        Ecs(ecs::errors::Error, ecs::errors::ErrorKind);
         Graphics(graphics::errors::Error, graphics::errors::ErrorKind);
         Resource(resource::errors::Error, resource::errors::ErrorKind);
     }
 
+    foreign_links {
+        Json(serde_json::Error);
+    }
+
     errors {
         NonTransformFound
         CanNotInverseTransform
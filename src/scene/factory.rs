@@ -1,3 +1,5 @@
+/// The built-in shaders. They are shared by location, and every call takes a reference
+/// of the shader, which should be released with `GraphicsSystemShared::delete_shader`.
 pub mod shader {
     use graphics::errors::*;
     use graphics::*;
@@ -7,16 +9,33 @@ pub mod shader {
     pub const PHONG: &str = "__Core/Scene/Shader/PHONG";
     pub const UNDEFINED: &str = "__Core/Scene/Shader/UNDEFINED";
     pub const COLOR: &str = "__Core/Scene/Shader/COLOR";
+    pub const COLOR_TRANSLUCENT: &str = "__Core/Scene/Shader/COLOR_TRANSLUCENT";
+    pub const SHADOW: &str = "__Core/Scene/Shader/SHADOW";
+    pub const SPRITE: &str = sprite::SHADER;
+    pub const SKINNED: &str = "__Core/Scene/Shader/SKINNED";
+    pub const DEBUG: &str = "__Core/Scene/Shader/DEBUG";
+    pub const GIZMO: &str = "__Core/Scene/Shader/GIZMO";
+
+    /// The uniforms of scene-wide `RenderSettings` that read by the lit shaders.
+    const SETTINGS_UNIFORMS: [(&str, UniformVariableType); 5] = [
+        ("u_AmbientColor", UniformVariableType::Vector3f),
+        ("u_FogColor", UniformVariableType::Vector3f),
+        ("u_FogParams", UniformVariableType::Vector3f),
+        ("u_Exposure", UniformVariableType::F32),
+        ("u_DebugMode", UniformVariableType::I32),
+    ];
+
+    /// The maximum number of joints that could be used by the skinned shader.
+    pub const MAX_JOINTS: usize = 64;
+    /// The uniform vectors in vertex shader reserved for variables except joints.
+    const RESERVED_UNIFORM_VECTORS: u32 = 32;
 
     pub fn pbr(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
         let location = Location::shared(0, PBR);
-        if let Some(shader) = video.lookup_shader_from(location) {
-            return Ok(shader);
-        }
-
         let attributes = AttributeLayout::build()
-            .with(Attribute::Position, 4)
-            .with(Attribute::Normal, 4)
+            .with(Attribute::Position, 3)
+            .with(Attribute::Normal, 3)
+            .with(Attribute::Tangent, 4)
             .with(Attribute::Texcoord0, 2)
             .finish();
 
@@ -36,7 +55,7 @@ pub mod shader {
             ("u_NormalMatrix", UniformVariableType::Matrix4f),
         ];
 
-        for &(field, tt) in &uvs {
+        for &(field, tt) in uvs.iter().chain(&SETTINGS_UNIFORMS) {
             setup.uniform_variables.insert(field.into(), tt);
         }
 
@@ -45,10 +64,6 @@ pub mod shader {
 
     pub fn phong(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
         let location = Location::shared(0, PHONG);
-        if let Some(shader) = video.lookup_shader_from(location) {
-            return Ok(shader);
-        }
-
         let attributes = AttributeLayout::build()
             .with(Attribute::Position, 3)
             .with(Attribute::Normal, 3)
@@ -68,29 +83,29 @@ pub mod shader {
 
         let uvs = [
             ("u_MVPMatrix", UniformVariableType::Matrix4f),
+            ("u_ModelMatrix", UniformVariableType::Matrix4f),
             ("u_ModelViewMatrix", UniformVariableType::Matrix4f),
             ("u_NormalMatrix", UniformVariableType::Matrix4f),
             ("u_DirLightEyeDir", UniformVariableType::Vector3f),
             ("u_DirLightColor", UniformVariableType::Vector3f),
-            ("u_PointLightEyePos[0]", UniformVariableType::Vector3f),
-            ("u_PointLightColor[0]", UniformVariableType::Vector3f),
-            ("u_PointLightAttenuation[0]", UniformVariableType::Vector3f),
-            ("u_PointLightEyePos[1]", UniformVariableType::Vector3f),
-            ("u_PointLightColor[1]", UniformVariableType::Vector3f),
-            ("u_PointLightAttenuation[1]", UniformVariableType::Vector3f),
-            ("u_PointLightEyePos[2]", UniformVariableType::Vector3f),
-            ("u_PointLightColor[2]", UniformVariableType::Vector3f),
-            ("u_PointLightAttenuation[2]", UniformVariableType::Vector3f),
-            ("u_PointLightEyePos[3]", UniformVariableType::Vector3f),
-            ("u_PointLightColor[3]", UniformVariableType::Vector3f),
-            ("u_PointLightAttenuation[3]", UniformVariableType::Vector3f),
+            ("u_PointLightEyePos", UniformVariableType::Vector3fArray(4)),
+            ("u_PointLightColor", UniformVariableType::Vector3fArray(4)),
+            ("u_PointLightAttenuation", UniformVariableType::Vector3fArray(4)),
+            ("u_SpotLightEyePos", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightEyeDir", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightColor", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightAttenuation", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightCutoff", UniformVariableType::Vector3fArray(2)),
+            ("u_LightSpaceMatrix", UniformVariableType::Matrix4f),
+            ("u_ShadowMap", UniformVariableType::Texture),
+            ("u_ShadowParams", UniformVariableType::Vector3f),
             ("u_Ambient", UniformVariableType::Vector3f),
             ("u_Diffuse", UniformVariableType::Vector3f),
             ("u_Specular", UniformVariableType::Vector3f),
             ("u_Shininess", UniformVariableType::F32),
         ];
 
-        for &(field, tt) in &uvs {
+        for &(field, tt) in uvs.iter().chain(&SETTINGS_UNIFORMS) {
             setup.uniform_variables.insert(field.into(), tt);
         }
 
@@ -98,17 +113,25 @@ pub mod shader {
     }
 
     pub fn color(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
-        let location = Location::shared(0, COLOR);
-        if let Some(shader) = video.lookup_shader_from(location) {
-            return Ok(shader);
-        }
+        color_with(video, COLOR, RenderState::default())
+    }
 
+    /// Creates a color shader which blends with the alpha channel of `u_Color`.
+    pub fn color_translucent(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        color_with(video, COLOR_TRANSLUCENT, RenderState::translucent())
+    }
+
+    fn color_with(
+        video: &GraphicsSystemShared,
+        name: &str,
+        mut render_state: RenderState,
+    ) -> Result<ShaderHandle> {
+        let location = Location::shared(0, name);
         let attributes = AttributeLayout::build()
             .with(Attribute::Position, 3)
             .finish();
 
-        let mut render_state = RenderState::default();
-        render_state.depth_write = true;
+        render_state.depth_write = render_state.color_blend.is_none();
         render_state.depth_test = Comparison::LessOrEqual;
         render_state.cull_face = CullFace::Back;
 
@@ -130,12 +153,162 @@ pub mod shader {
         video.create_shader(location, setup)
     }
 
-    pub fn undefined(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
-        let location = Location::shared(0, UNDEFINED);
-        if let Some(shader) = video.lookup_shader_from(location) {
-            return Ok(shader);
+    /// Creates the depth-only shader which renders objects into shadow map.
+    pub fn shadow(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        let location = Location::shared(0, SHADOW);
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let mut render_state = RenderState::default();
+        render_state.depth_write = true;
+        render_state.depth_test = Comparison::LessOrEqual;
+        render_state.cull_face = CullFace::Back;
+
+        let mut setup = ShaderSetup::default();
+        setup.render_state = render_state;
+        setup.layout = attributes;
+        setup.vs = include_str!("assets/shadow.vs").to_owned();
+        setup.fs = include_str!("assets/shadow.fs").to_owned();
+        setup
+            .uniform_variables
+            .insert("u_MVPMatrix".into(), UniformVariableType::Matrix4f);
+
+        video.create_shader(location, setup)
+    }
+
+    /// Gets the number of joints supported by the skinned shader, which is clamped to
+    /// the uniform limit of vertex shader reported by capabilities.
+    pub fn max_joints(capabilities: &Capabilities) -> usize {
+        let vectors = capabilities
+            .max_vertex_uniform_vectors
+            .saturating_sub(RESERVED_UNIFORM_VECTORS);
+
+        // Each `mat4` takes four uniform vectors.
+        ::std::cmp::min(MAX_JOINTS, (vectors / 4) as usize)
+    }
+
+    /// Creates the phong shader of skinned meshes, which deforms vertices with at most
+    /// four joints. The skinning matrices of joints are uploaded as `u_JointMatrices`.
+    pub fn skinned(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        let location = Location::shared(0, SKINNED);
+        let joints = max_joints(video.capabilities());
+        if joints == 0 {
+            bail!("Skinning is not supported due to the limit of uniform vectors.");
         }
 
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Normal, 3)
+            .with(Attribute::Color0, 4)
+            .with(Attribute::Indices, 4)
+            .with(Attribute::Weight, 4)
+            .finish();
+
+        let mut render_state = RenderState::default();
+        render_state.depth_write = true;
+        render_state.depth_test = Comparison::LessOrEqual;
+        render_state.cull_face = CullFace::Back;
+
+        let mut setup = ShaderSetup::default();
+        setup.render_state = render_state;
+        setup.layout = attributes;
+        setup.vs = format!(
+            "#version 100\n#define MAX_JOINTS {}\n{}",
+            joints,
+            include_str!("assets/skinned.vs")
+        );
+        setup.fs = include_str!("assets/phong.fs").to_owned();
+
+        let uvs = [
+            ("u_MVPMatrix", UniformVariableType::Matrix4f),
+            ("u_ModelMatrix", UniformVariableType::Matrix4f),
+            ("u_ModelViewMatrix", UniformVariableType::Matrix4f),
+            ("u_NormalMatrix", UniformVariableType::Matrix4f),
+            ("u_JointMatrices", UniformVariableType::Matrix4fArray(joints)),
+            ("u_DirLightEyeDir", UniformVariableType::Vector3f),
+            ("u_DirLightColor", UniformVariableType::Vector3f),
+            ("u_PointLightEyePos", UniformVariableType::Vector3fArray(4)),
+            ("u_PointLightColor", UniformVariableType::Vector3fArray(4)),
+            ("u_PointLightAttenuation", UniformVariableType::Vector3fArray(4)),
+            ("u_SpotLightEyePos", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightEyeDir", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightColor", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightAttenuation", UniformVariableType::Vector3fArray(2)),
+            ("u_SpotLightCutoff", UniformVariableType::Vector3fArray(2)),
+            ("u_LightSpaceMatrix", UniformVariableType::Matrix4f),
+            ("u_ShadowMap", UniformVariableType::Texture),
+            ("u_ShadowParams", UniformVariableType::Vector3f),
+            ("u_Ambient", UniformVariableType::Vector3f),
+            ("u_Diffuse", UniformVariableType::Vector3f),
+            ("u_Specular", UniformVariableType::Vector3f),
+            ("u_Shininess", UniformVariableType::F32),
+        ];
+
+        for &(field, tt) in &uvs {
+            setup.uniform_variables.insert(field.into(), tt);
+        }
+
+        video.create_shader(location, setup)
+    }
+
+    /// Creates the shader of sprites, which modulates the texture with vertex color and
+    /// blends with the alpha channel.
+    pub fn sprite(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        sprite::shader(video)
+    }
+
+    /// Creates the shader of debug lines, which draws with vertex color. The lines are
+    /// depth tested but never write depth, so they don't occlude each other.
+    pub fn debug(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        let location = Location::shared(0, DEBUG);
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Color0, 4)
+            .finish();
+
+        let mut render_state = RenderState::default();
+        render_state.depth_write = false;
+        render_state.depth_test = Comparison::LessOrEqual;
+
+        let mut setup = ShaderSetup::default();
+        setup.render_state = render_state;
+        setup.layout = attributes;
+        setup.vs = include_str!("assets/debug.vs").to_owned();
+        setup.fs = include_str!("assets/debug.fs").to_owned();
+        setup
+            .uniform_variables
+            .insert("u_MVPMatrix".into(), UniformVariableType::Matrix4f);
+
+        video.create_shader(location, setup)
+    }
+
+    /// The shader of instanced line segments, which has no uniform at all. The end
+    /// points of every instance are given in clip space with `Texcoord0` and
+    /// `Texcoord1`, and the mesh is expected to be `mesh::segment`.
+    pub fn gizmo(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        let location = Location::shared(0, GIZMO);
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Texcoord0, 4)
+            .with(Attribute::Texcoord1, 4)
+            .with(Attribute::Color0, 4)
+            .finish();
+
+        let mut render_state = RenderState::default();
+        render_state.depth_write = false;
+        render_state.depth_test = Comparison::LessOrEqual;
+
+        let mut setup = ShaderSetup::default();
+        setup.render_state = render_state;
+        setup.layout = attributes;
+        setup.vs = include_str!("assets/gizmo.vs").to_owned();
+        setup.fs = include_str!("assets/gizmo.fs").to_owned();
+        video.create_shader(location, setup)
+    }
+
+    pub fn undefined(video: &GraphicsSystemShared) -> Result<ShaderHandle> {
+        let location = Location::shared(0, UNDEFINED);
         let attributes = AttributeLayout::build()
             .with(Attribute::Position, 3)
             .finish();
@@ -164,6 +337,8 @@ pub mod shader {
 pub mod mesh {
     use graphics::errors::*;
     use graphics::*;
+    use math;
+    use math::InnerSpace;
     use resource::Location;
 
     impl_vertex! {
@@ -172,7 +347,53 @@ pub mod mesh {
             color => [Color0; UByte; 4; true],
             texcoord => [Texcoord0; Float; 2; false],
             normal => [Normal; Float; 3; false],
+            tangent => [Tangent; Float; 4; false],
+        }
+    }
+
+    impl_vertex! {
+        SkinnedVertex {
+            position => [Position; Float; 3; false],
+            color => [Color0; UByte; 4; true],
+            texcoord => [Texcoord0; Float; 2; false],
+            normal => [Normal; Float; 3; false],
+            joints => [Indices; UByte; 4; false],
+            weights => [Weight; Float; 4; false],
+        }
+    }
+
+    impl_vertex! {
+        SegmentVertex {
+            position => [Position; Float; 3; false],
+        }
+    }
+
+    pub const SEGMENT: &str = "__Core/Scene/Mesh/SEGMENT";
+
+    /// The line segment from origin to `(1, 0, 0)`, which is used to draw instanced
+    /// lines by interpolating the end points of instances with `Position.x`.
+    pub fn segment(video: &GraphicsSystemShared) -> Result<MeshHandle> {
+        let location = Location::shared(0, SEGMENT);
+        if let Some(segment) = video.lookup_mesh_from(location) {
+            return Ok(segment);
         }
+
+        let verts = [
+            SegmentVertex::new([0.0, 0.0, 0.0]),
+            SegmentVertex::new([1.0, 0.0, 0.0]),
+        ];
+
+        let idxes: [u16; 2] = [0, 1];
+
+        let mut setup = MeshSetup::default();
+        setup.layout = SegmentVertex::layout();
+        setup.primitive = Primitive::Lines;
+        setup.num_verts = verts.len();
+        setup.num_idxes = idxes.len();
+
+        let vbytes = SegmentVertex::as_bytes(&verts);
+        let ibytes = IndexFormat::as_bytes::<u16>(&idxes);
+        video.create_mesh(location, setup, vbytes, ibytes)
     }
 
     pub const CUBE: &str = "__Core/Scene/Mesh/CUBE";
@@ -206,31 +427,32 @@ pub mod mesh {
             [0.0, -1.0, 0.0],
         ];
 
-        let verts = [
-            PrimitiveVertex::new(points[0], color, texcoords[0], normals[0]),
-            PrimitiveVertex::new(points[1], color, texcoords[1], normals[0]),
-            PrimitiveVertex::new(points[2], color, texcoords[2], normals[0]),
-            PrimitiveVertex::new(points[3], color, texcoords[3], normals[0]),
-            PrimitiveVertex::new(points[1], color, texcoords[0], normals[1]),
-            PrimitiveVertex::new(points[5], color, texcoords[1], normals[1]),
-            PrimitiveVertex::new(points[6], color, texcoords[2], normals[1]),
-            PrimitiveVertex::new(points[2], color, texcoords[3], normals[1]),
-            PrimitiveVertex::new(points[5], color, texcoords[0], normals[2]),
-            PrimitiveVertex::new(points[4], color, texcoords[1], normals[2]),
-            PrimitiveVertex::new(points[7], color, texcoords[2], normals[2]),
-            PrimitiveVertex::new(points[6], color, texcoords[3], normals[2]),
-            PrimitiveVertex::new(points[4], color, texcoords[0], normals[3]),
-            PrimitiveVertex::new(points[0], color, texcoords[1], normals[3]),
-            PrimitiveVertex::new(points[3], color, texcoords[2], normals[3]),
-            PrimitiveVertex::new(points[7], color, texcoords[3], normals[3]),
-            PrimitiveVertex::new(points[3], color, texcoords[0], normals[4]),
-            PrimitiveVertex::new(points[2], color, texcoords[1], normals[4]),
-            PrimitiveVertex::new(points[6], color, texcoords[2], normals[4]),
-            PrimitiveVertex::new(points[7], color, texcoords[3], normals[4]),
-            PrimitiveVertex::new(points[4], color, texcoords[0], normals[5]),
-            PrimitiveVertex::new(points[5], color, texcoords[1], normals[5]),
-            PrimitiveVertex::new(points[1], color, texcoords[2], normals[5]),
-            PrimitiveVertex::new(points[0], color, texcoords[3], normals[5]),
+        let t = [0.0; 4];
+        let mut verts = [
+            PrimitiveVertex::new(points[0], color, texcoords[0], normals[0], t),
+            PrimitiveVertex::new(points[1], color, texcoords[1], normals[0], t),
+            PrimitiveVertex::new(points[2], color, texcoords[2], normals[0], t),
+            PrimitiveVertex::new(points[3], color, texcoords[3], normals[0], t),
+            PrimitiveVertex::new(points[1], color, texcoords[0], normals[1], t),
+            PrimitiveVertex::new(points[5], color, texcoords[1], normals[1], t),
+            PrimitiveVertex::new(points[6], color, texcoords[2], normals[1], t),
+            PrimitiveVertex::new(points[2], color, texcoords[3], normals[1], t),
+            PrimitiveVertex::new(points[5], color, texcoords[0], normals[2], t),
+            PrimitiveVertex::new(points[4], color, texcoords[1], normals[2], t),
+            PrimitiveVertex::new(points[7], color, texcoords[2], normals[2], t),
+            PrimitiveVertex::new(points[6], color, texcoords[3], normals[2], t),
+            PrimitiveVertex::new(points[4], color, texcoords[0], normals[3], t),
+            PrimitiveVertex::new(points[0], color, texcoords[1], normals[3], t),
+            PrimitiveVertex::new(points[3], color, texcoords[2], normals[3], t),
+            PrimitiveVertex::new(points[7], color, texcoords[3], normals[3], t),
+            PrimitiveVertex::new(points[3], color, texcoords[0], normals[4], t),
+            PrimitiveVertex::new(points[2], color, texcoords[1], normals[4], t),
+            PrimitiveVertex::new(points[6], color, texcoords[2], normals[4], t),
+            PrimitiveVertex::new(points[7], color, texcoords[3], normals[4], t),
+            PrimitiveVertex::new(points[4], color, texcoords[0], normals[5], t),
+            PrimitiveVertex::new(points[5], color, texcoords[1], normals[5], t),
+            PrimitiveVertex::new(points[1], color, texcoords[2], normals[5], t),
+            PrimitiveVertex::new(points[0], color, texcoords[3], normals[5], t),
         ];
 
         #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -243,6 +465,9 @@ pub mod mesh {
             20, 21, 22, 22, 23, 20,
         ];
 
+        let triangles: Vec<u32> = idxes.iter().map(|v| *v as u32).collect();
+        generate_tangents(&mut verts, &triangles);
+
         let mut setup = MeshSetup::default();
         setup.layout = PrimitiveVertex::layout();
         setup.num_verts = verts.len();
@@ -253,4 +478,136 @@ pub mod mesh {
         let ibytes = IndexFormat::as_bytes::<u16>(&idxes);
         video.create_mesh(location, setup, vbytes, ibytes)
     }
+
+    /// Generates the tangents of triangles `idxes` from the positions, normals and
+    /// texcoords of vertices, which are required by normal mapping.
+    ///
+    /// The `w` component of tangent holds the handedness of tangent space, so the
+    /// bitangent could be reconstructed with `cross(normal, tangent.xyz) * tangent.w`.
+    /// Vertices without valid texcoord gradients, e.g. the ones of triangles with
+    /// degenerate UVs, get an arbitrary tangent that is orthogonal to their normals.
+    pub fn generate_tangents(verts: &mut [PrimitiveVertex], idxes: &[u32]) {
+        let zero = math::Vector3::new(0.0, 0.0, 0.0);
+        let mut tangents = vec![zero; verts.len()];
+        let mut bitangents = vec![zero; verts.len()];
+
+        for v in idxes.chunks(3).filter(|v| v.len() == 3) {
+            let (a, b, c) = (v[0] as usize, v[1] as usize, v[2] as usize);
+            if a >= verts.len() || b >= verts.len() || c >= verts.len() {
+                continue;
+            }
+
+            let p0 = math::Vector3::from(verts[a].position);
+            let e1 = math::Vector3::from(verts[b].position) - p0;
+            let e2 = math::Vector3::from(verts[c].position) - p0;
+
+            let uv0 = math::Vector2::from(verts[a].texcoord);
+            let d1 = math::Vector2::from(verts[b].texcoord) - uv0;
+            let d2 = math::Vector2::from(verts[c].texcoord) - uv0;
+
+            let r = d1.x * d2.y - d2.x * d1.y;
+            if r.abs() <= ::std::f32::EPSILON {
+                continue;
+            }
+
+            let t = (e1 * d2.y - e2 * d1.y) / r;
+            let bt = (e2 * d1.x - e1 * d2.x) / r;
+            if !t.x.is_finite() || !t.y.is_finite() || !t.z.is_finite() {
+                continue;
+            }
+
+            for &i in &[a, b, c] {
+                tangents[i] += t;
+                bitangents[i] += bt;
+            }
+        }
+
+        for (i, v) in verts.iter_mut().enumerate() {
+            let n = math::Vector3::from(v.normal);
+            let n = if n.magnitude2() > ::std::f32::EPSILON {
+                n.normalize()
+            } else {
+                math::Vector3::unit_z()
+            };
+
+            // Gram-Schmidt orthogonalize.
+            let t = tangents[i] - n * n.dot(tangents[i]);
+            let t = if t.magnitude2() > ::std::f32::EPSILON {
+                t.normalize()
+            } else {
+                orthogonal(n)
+            };
+
+            let w = if n.cross(t).dot(bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+
+            v.tangent = [t.x, t.y, t.z, w];
+        }
+    }
+
+    /// Picks an unit vector that is orthogonal to the normalized `n`.
+    fn orthogonal(n: math::Vector3<f32>) -> math::Vector3<f32> {
+        let axis = if n.x.abs() < 0.9 {
+            math::Vector3::unit_x()
+        } else {
+            math::Vector3::unit_y()
+        };
+
+        (axis - n * n.dot(axis)).normalize()
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn vertex(position: [f32; 3], texcoord: [f32; 2]) -> PrimitiveVertex {
+            PrimitiveVertex::new(position, [255; 4], texcoord, [0.0, 0.0, 1.0], [0.0; 4])
+        }
+
+        #[test]
+        fn tangents() {
+            let mut verts = [
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 1.0]),
+            ];
+
+            generate_tangents(&mut verts, &[0, 1, 2]);
+            for v in &verts {
+                assert_eq!(v.tangent, [1.0, 0.0, 0.0, 1.0]);
+            }
+
+            // Mirrored texcoords flip the handedness.
+            verts[2].texcoord = [0.0, -1.0];
+            generate_tangents(&mut verts, &[0, 1, 2]);
+            for v in &verts {
+                assert_eq!(v.tangent, [1.0, 0.0, 0.0, -1.0]);
+            }
+        }
+
+        #[test]
+        fn degenerate() {
+            let mut verts = [
+                vertex([0.0, 0.0, 0.0], [0.5, 0.5]),
+                vertex([1.0, 0.0, 0.0], [0.5, 0.5]),
+                vertex([0.0, 1.0, 0.0], [0.5, 0.5]),
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+            ];
+
+            verts[3].normal = [0.0; 3];
+            generate_tangents(&mut verts, &[0, 1, 2, 3, 3, 3]);
+
+            for v in &verts {
+                assert!(v.tangent.iter().all(|v| v.is_finite()));
+
+                let n = math::Vector3::from(v.normal);
+                let t = math::Vector3::new(v.tangent[0], v.tangent[1], v.tangent[2]);
+                assert!((t.magnitude() - 1.0).abs() < 1e-5);
+                assert!(n.dot(t).abs() < 1e-5);
+            }
+        }
+    }
 }
@@ -0,0 +1,72 @@
+use math;
+use math::InnerSpace;
+
+/// A frustum defined by six planes, which is used to cull objects that are invisible
+/// to camera.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    /// The planes in order of left, right, bottom, top, near and far. Each plane is
+    /// represented as `(normal, distance)` with the normal points to the inside.
+    planes: [math::Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from view-projection matrix. The frustum will be in
+    /// world space, or in the local space of objects if model-view-projection matrix
+    /// is used. The depth of clip space is expected to be in `[-w, w]`.
+    pub fn new(matrix: math::Matrix4<f32>) -> Self {
+        let row = |i: usize| math::Vector4::new(matrix.x[i], matrix.y[i], matrix.z[i], matrix.w[i]);
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [
+            r3 + r0,
+            r3 - r0,
+            r3 + r1,
+            r3 - r1,
+            r3 + r2,
+            r3 - r2,
+        ];
+
+        for v in &mut planes {
+            let len = v.truncate().magnitude();
+            if len > 0.0 {
+                *v /= len;
+            }
+        }
+
+        Frustum { planes: planes }
+    }
+
+    /// Returns false if the sphere is completely outside of this frustum.
+    pub fn contains_sphere(&self, center: math::Point3<f32>, radius: f32) -> bool {
+        for v in &self.planes {
+            let distance = v.x * center.x + v.y * center.y + v.z * center.z + v.w;
+            if distance < -radius {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use scene::Camera;
+
+    #[test]
+    fn sphere() {
+        // The camera is aligned along the positive z-axis.
+        let camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+        let frustum = Frustum::new(camera.projection_matrix());
+
+        assert!(frustum.contains_sphere(math::Point3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.contains_sphere(math::Point3::new(0.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.contains_sphere(math::Point3::new(0.0, 0.0, 200.0), 1.0));
+        assert!(!frustum.contains_sphere(math::Point3::new(100.0, 0.0, 10.0), 1.0));
+
+        // Intersects with the near plane.
+        assert!(frustum.contains_sphere(math::Point3::new(0.0, 0.0, -0.5), 1.0));
+    }
+}
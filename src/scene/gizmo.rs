@@ -0,0 +1,450 @@
+//! Handles to translate, rotate and scale the selected node, and the ground grid, which
+//! are drawn as instanced line segments without any uniform variable.
+
+use std::sync::Arc;
+
+use application::Context;
+use ecs::{Arena, Entity};
+use math;
+use math::{EuclideanSpace, InnerSpace, SquareMatrix};
+use graphics::*;
+use utils::{Aabb3, Color, Ray};
+
+use scene::{Node, Transform};
+use scene::debug::aabb_corners;
+use scene::errors::*;
+use scene::factory;
+use scene::scene::{Scene, SceneNode};
+
+impl_vertex! {
+    GizmoInstance {
+        from => [Texcoord0; Float; 4; false],
+        to => [Texcoord1; Float; 4; false],
+        color => [Color0; Float; 4; false],
+    }
+}
+
+/// The half thickness of handles when picking, relative to the size of gizmo.
+const PICK_TOLERANCE: f32 = 0.08;
+/// The number of segments of rotation circles.
+const CIRCLE_SEGMENTS: usize = 48;
+
+/// The kinds of handles drawn at the selected node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// The axis of handles in the local space of selected node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    fn index(self) -> usize {
+        match self {
+            GizmoAxis::X => 0,
+            GizmoAxis::Y => 1,
+            GizmoAxis::Z => 2,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            GizmoAxis::X => Color::red(),
+            GizmoAxis::Y => Color::green(),
+            GizmoAxis::Z => Color::blue(),
+        }
+    }
+}
+
+const AXES: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+/// A `GizmoRenderer` draws the handles of selected node and the ground grid, which is
+/// the standard debug overlay of editors.
+///
+/// ```rust,ignore
+/// let mut gizmo = GizmoRenderer::new(&ctx)?;
+/// gizmo.set_camera(&scene, camera)?;
+/// gizmo.select(Some(scene.world_transform(entity)?));
+///
+/// let ray = scene.screen_to_ray(camera, cursor, dimensions)?;
+/// let axis = gizmo.pick(&ray);
+/// gizmo.set_active(axis);
+/// gizmo.draw(surface, order)?;
+/// ```
+///
+/// Every line is an instance of the segment mesh, whose end points are transformed into
+/// clip space on CPU, so the shader has no uniform. The grid is depth tested against
+/// the scene, while the handles are always drawn on top of it.
+pub struct GizmoRenderer {
+    video: Arc<GraphicsSystemShared>,
+    shader: ShaderHandle,
+    mesh: MeshHandle,
+    matrix: math::Matrix4<f32>,
+
+    mode: GizmoMode,
+    size: f32,
+    selection: Option<Transform>,
+    active: Option<GizmoAxis>,
+    grid: Option<(f32, usize)>,
+
+    grid_state: RenderState,
+    handle_state: RenderState,
+    instances: Vec<GizmoInstance>,
+}
+
+impl GizmoRenderer {
+    /// Creates a new `GizmoRenderer` with the gizmo shader and segment mesh from
+    /// factory.
+    pub fn new(ctx: &Context) -> Result<Self> {
+        let video = ctx.shared::<GraphicsSystem>().clone();
+        let shader = factory::shader::gizmo(&video)?;
+        let mesh = factory::mesh::segment(&video)?;
+
+        let mut grid_state = RenderState::default();
+        grid_state.depth_write = false;
+        grid_state.depth_test = Comparison::LessOrEqual;
+
+        let mut handle_state = RenderState::default();
+        handle_state.depth_write = false;
+        handle_state.depth_test = Comparison::Always;
+
+        Ok(GizmoRenderer {
+            video: video,
+            shader: shader,
+            mesh: mesh,
+            matrix: math::Matrix4::identity(),
+            mode: GizmoMode::Translate,
+            size: 1.0,
+            selection: None,
+            active: None,
+            grid: Some((1.0, 10)),
+            grid_state: grid_state,
+            handle_state: handle_state,
+            instances: Vec::new(),
+        })
+    }
+
+    /// Sets the view-projection matrix. It's the identity matrix by default.
+    #[inline]
+    pub fn set_matrix(&mut self, matrix: math::Matrix4<f32>) {
+        self.matrix = matrix;
+    }
+
+    /// Sets the view-projection matrix with the one of `camera` in `scene`. The depth
+    /// test of grid follows the reverse-Z projection of camera.
+    pub fn set_camera(&mut self, scene: &Scene, camera: Entity) -> Result<()> {
+        let cam = match scene.arena::<SceneNode>().get(camera) {
+            Some(&SceneNode::Camera(v)) => v,
+            _ => bail!(ErrorKind::NonCameraFound),
+        };
+
+        let tree = scene.arena::<Node>();
+        let arena = scene.arena::<Transform>();
+        let view = Transform::world_view_matrix(&tree, &arena, camera)?;
+
+        let projection = cam.projection_matrix();
+        let projection = cam.viewport_matrix()
+            .map(|m| m * projection)
+            .unwrap_or(projection);
+
+        self.matrix = projection * view;
+        self.grid_state.depth_test = cam.depth_comparison(Comparison::LessOrEqual);
+        Ok(())
+    }
+
+    /// Sets the kind of handles. It's `GizmoMode::Translate` by default.
+    #[inline]
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        self.mode = mode;
+    }
+
+    /// Gets the kind of handles.
+    #[inline]
+    pub fn mode(&self) -> GizmoMode {
+        self.mode
+    }
+
+    /// Sets the length of handles in world space. It's 1.0 by default.
+    #[inline]
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.max(::std::f32::EPSILON);
+    }
+
+    /// Selects the node with its world transform, which could be calculated with
+    /// `Scene::world_transform`. Handles are not drawn if nothing is selected.
+    #[inline]
+    pub fn select(&mut self, transform: Option<Transform>) {
+        self.selection = transform;
+    }
+
+    /// Highlights the handle of `axis`, e.g. the one under cursor or being dragged.
+    #[inline]
+    pub fn set_active(&mut self, axis: Option<GizmoAxis>) {
+        self.active = axis;
+    }
+
+    /// Sets the spacing and the number of cells from origin to the edges of ground
+    /// grid on the XZ plane. The grid is hidden if it's `None`.
+    #[inline]
+    pub fn set_grid(&mut self, grid: Option<(f32, usize)>) {
+        self.grid = grid;
+    }
+
+    /// Gets the axis of handle hit by `ray` in world space, or `None` if nothing is
+    /// selected or hit. The nearest handle wins if several of them are hit.
+    pub fn pick(&self, ray: &Ray) -> Option<GizmoAxis> {
+        let inverse = self.handle_matrix()?.invert()?;
+        pick(self.mode, &ray.transform(inverse))
+    }
+
+    /// Submits the grid and handles into `surface` with `order`. The handles are
+    /// submitted after grid, so they are drawn on top of it.
+    pub fn draw<T>(&mut self, surface: SurfaceHandle, order: T) -> Result<()>
+    where
+        T: Into<u64>,
+    {
+        let order = order.into();
+
+        if let Some((spacing, cells)) = self.grid {
+            self.instances.clear();
+            self.grid_lines(spacing, cells);
+            let state = self.grid_state;
+            self.submit(surface, order, state)?;
+        }
+
+        if let Some(matrix) = self.handle_matrix() {
+            let matrix = self.matrix * matrix;
+            self.instances.clear();
+            self.handle_lines(matrix);
+            let state = self.handle_state;
+            self.submit(surface, order, state)?;
+        }
+
+        Ok(())
+    }
+
+    fn submit(&mut self, surface: SurfaceHandle, order: u64, state: RenderState) -> Result<()> {
+        if self.instances.is_empty() {
+            return Ok(());
+        }
+
+        let mut dc = DrawCall::new(self.shader, self.mesh);
+        dc.set_render_state(state);
+
+        let layout = GizmoInstance::attributes();
+        let bytes = GizmoInstance::as_bytes(&self.instances);
+        let cmd = dc.build_instanced(MeshIndex::All, layout, bytes, self.instances.len())?;
+        self.video.submit(surface, order, cmd)?;
+        Ok(())
+    }
+
+    /// Gets the matrix from the local space of handles, where the handles are of unit
+    /// length, into world space. The scale of selected node is ignored.
+    fn handle_matrix(&self) -> Option<math::Matrix4<f32>> {
+        self.selection.map(|v| {
+            let translation = math::Matrix4::from_translation(v.position());
+            let rotation = math::Matrix4::from(v.rotation());
+            translation * rotation * math::Matrix4::from_scale(self.size)
+        })
+    }
+
+    fn grid_lines(&mut self, spacing: f32, cells: usize) {
+        let matrix = self.matrix;
+        let extent = spacing * cells as f32;
+        let cells = cells as isize;
+
+        for i in -cells..(cells + 1) {
+            let v = spacing * i as f32;
+            let (x, z) = if i == 0 {
+                (Color::red(), Color::blue())
+            } else {
+                (Color::gray(), Color::gray())
+            };
+
+            let from = math::Point3::new(-extent, 0.0, v);
+            let to = math::Point3::new(extent, 0.0, v);
+            self.line(matrix, from, to, x);
+
+            let from = math::Point3::new(v, 0.0, -extent);
+            let to = math::Point3::new(v, 0.0, extent);
+            self.line(matrix, from, to, z);
+        }
+    }
+
+    fn handle_lines(&mut self, matrix: math::Matrix4<f32>) {
+        let origin = math::Point3::origin();
+
+        for &axis in &AXES {
+            let color = if self.active == Some(axis) {
+                Color::yellow()
+            } else {
+                axis.color()
+            };
+
+            let i = axis.index();
+            let (dir, u, v) = basis(i);
+            let end = origin + dir;
+
+            match self.mode {
+                GizmoMode::Translate => {
+                    self.line(matrix, origin, end, color);
+                    // The arrow head.
+                    let base = origin + dir * 0.85;
+                    for &side in &[u, -u, v, -v] {
+                        self.line(matrix, end, base + side * 0.05, color);
+                    }
+                }
+                GizmoMode::Scale => {
+                    self.line(matrix, origin, end, color);
+                    let half = math::Vector3::new(0.05, 0.05, 0.05);
+                    let corners = aabb_corners(&Aabb3::new(end - half, end + half));
+                    for j in 0..4 {
+                        let k = (j + 1) % 4;
+                        self.line(matrix, corners[j], corners[k], color);
+                        self.line(matrix, corners[j + 4], corners[k + 4], color);
+                        self.line(matrix, corners[j], corners[j + 4], color);
+                    }
+                }
+                GizmoMode::Rotate => {
+                    // The circle around axis.
+                    let step = 2.0 * ::std::f32::consts::PI / CIRCLE_SEGMENTS as f32;
+                    for j in 0..CIRCLE_SEGMENTS {
+                        let (a, b) = (step * j as f32, step * (j + 1) as f32);
+                        let from = origin + u * a.cos() + v * a.sin();
+                        let to = origin + u * b.cos() + v * b.sin();
+                        self.line(matrix, from, to, color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn line(
+        &mut self,
+        matrix: math::Matrix4<f32>,
+        from: math::Point3<f32>,
+        to: math::Point3<f32>,
+        color: Color,
+    ) {
+        let from = matrix * from.to_homogeneous();
+        let to = matrix * to.to_homogeneous();
+        let color: [f32; 4] = color.into();
+        self.instances
+            .push(GizmoInstance::new(from.into(), to.into(), color));
+    }
+}
+
+impl Drop for GizmoRenderer {
+    fn drop(&mut self) {
+        self.video.delete_mesh(self.mesh);
+        self.video.delete_shader(self.shader);
+    }
+}
+
+/// Gets the unit vector of axis `i`, and the two unit vectors perpendicular to it.
+fn basis(i: usize) -> (math::Vector3<f32>, math::Vector3<f32>, math::Vector3<f32>) {
+    let (x, y, z) = (
+        math::Vector3::unit_x(),
+        math::Vector3::unit_y(),
+        math::Vector3::unit_z(),
+    );
+
+    match i {
+        0 => (x, y, z),
+        1 => (y, z, x),
+        _ => (z, x, y),
+    }
+}
+
+/// Picks the handle with `ray` in the local space of handles. The handles are tested
+/// as thin bounding boxes along axes, and the circles of rotation are tested as slabs
+/// with the distance to origin.
+fn pick(mode: GizmoMode, ray: &Ray) -> Option<GizmoAxis> {
+    let w = PICK_TOLERANCE;
+    let mut hit: Option<(GizmoAxis, f32)> = None;
+
+    for &axis in &AXES {
+        let i = axis.index();
+        let (dir, u, v) = basis(i);
+        let margin = (u + v) * w;
+
+        let distance = match mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let min = math::Point3::from_vec(-margin);
+                let max = math::Point3::from_vec(dir * (1.0 + w) + margin);
+                ray.intersect_aabb(&Aabb3::new(min, max))
+            }
+            GizmoMode::Rotate => {
+                let extent = (u + v) * (1.0 + w);
+                let min = math::Point3::from_vec(-dir * w - extent);
+                let max = math::Point3::from_vec(dir * w + extent);
+                ray.intersect_aabb(&Aabb3::new(min, max)).and_then(|t| {
+                    // Moves to the plane of circle if the ray crosses it.
+                    let t = if ray.direction[i] != 0.0 {
+                        t.max(-ray.origin[i] / ray.direction[i])
+                    } else {
+                        t
+                    };
+
+                    let mut p = ray.at(t).to_vec();
+                    p[i] = 0.0;
+                    if (p.magnitude() - 1.0).abs() <= w {
+                        Some(t)
+                    } else {
+                        None
+                    }
+                })
+            }
+        };
+
+        if let Some(t) = distance {
+            if hit.map(|v| t < v.1).unwrap_or(true) {
+                hit = Some((axis, t));
+            }
+        }
+    }
+
+    hit.map(|v| v.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picking() {
+        let down = math::Vector3::new(0.0, -1.0, 0.0);
+
+        let ray = Ray::new(math::Point3::new(0.5, 5.0, 0.0), down);
+        assert_eq!(pick(GizmoMode::Translate, &ray), Some(GizmoAxis::X));
+        assert_eq!(pick(GizmoMode::Scale, &ray), Some(GizmoAxis::X));
+
+        let ray = Ray::new(math::Point3::new(0.0, 5.0, 0.5), down);
+        assert_eq!(pick(GizmoMode::Translate, &ray), Some(GizmoAxis::Z));
+
+        let ray = Ray::new(math::Point3::new(0.5, 5.0, 0.5), down);
+        assert_eq!(pick(GizmoMode::Translate, &ray), None);
+
+        // Looks down the y axis, the nearest handle is picked.
+        let ray = Ray::new(math::Point3::new(0.0, 5.0, 0.0), down);
+        assert_eq!(pick(GizmoMode::Translate, &ray), Some(GizmoAxis::Y));
+
+        // The circle of y axis lies on the XZ plane.
+        let ray = Ray::new(math::Point3::new(0.7, 5.0, 0.7), down);
+        assert_eq!(pick(GizmoMode::Rotate, &ray), Some(GizmoAxis::Y));
+
+        let ray = Ray::new(math::Point3::new(0.3, 5.0, 0.3), down);
+        assert_eq!(pick(GizmoMode::Rotate, &ray), None);
+
+        // The circle of x axis lies on the YZ plane.
+        let ray = Ray::new(math::Point3::new(5.0, 0.0, 1.0), math::Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(pick(GizmoMode::Rotate, &ray), Some(GizmoAxis::X));
+    }
+}
@@ -0,0 +1,473 @@
+//! Imports the geometry of [glTF 2.0](https://github.com/KhronosGroup/glTF) models.
+//!
+//! Both the binary `.glb` and the JSON based `.gltf` containers are supported. Since
+//! the parser only has the bytes of one file, the buffers of a `.gltf` must be embedded
+//! as base64 data URIs.
+//!
+//! ```rust,ignore
+//! let location = Location::shared(0, "/std/box.glb");
+//! let mesh = video.create_mesh_from::<GltfParser>(location, MeshSetup::default())?;
+//! ```
+
+use std::str;
+
+use base64;
+use serde_json::{self, Value};
+
+use graphics::{IndexFormat, MeshData, MeshParser, Primitive};
+use scene::errors::*;
+use scene::factory::mesh::{self, PrimitiveVertex};
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+const BYTE: u32 = 5120;
+const UNSIGNED_BYTE: u32 = 5121;
+const SHORT: u32 = 5122;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// Parses glTF 2.0 models into `MeshData`, which could be used with
+/// `create_mesh_from::<GltfParser>`.
+///
+/// Every primitive of the meshes in document becomes a sub-mesh, and all of them are
+/// merged into one vertex buffer with the layout of `PrimitiveVertex`. The missing
+/// normals and texcoords are filled with zeros, and the missing colors with white.
+/// The tangents are generated with `generate_tangents` if the primitive does not
+/// have them.
+///
+/// Vertices are converted from the right-handed coordinate system of glTF into our
+/// left-handed one by flipping the z axis, and the winding of triangles is reversed
+/// to keep the front faces.
+pub struct GltfParser {}
+
+impl MeshParser for GltfParser {
+    type Error = Error;
+
+    fn parse(bytes: &[u8]) -> Result<MeshData> {
+        let (text, blob) = GltfParser::split(bytes)?;
+        let doc: Value = serde_json::from_str(text)?;
+        let buffers = GltfParser::buffers(&doc, blob)?;
+
+        let mut primitive = None;
+        let mut verts = Vec::new();
+        let mut idxes = Vec::new();
+        let mut sub_mesh_offsets = Vec::new();
+
+        for mesh in array(&doc, "meshes") {
+            let prims = mesh.get("primitives").and_then(|v| v.as_array());
+            for prim in prims.ok_or("Mesh without primitives.")? {
+                let mode = GltfParser::mode(prim)?;
+                if primitive.is_some() && primitive != Some(mode) {
+                    bail!("Primitives with different modes can not be merged into one mesh.");
+                }
+
+                primitive = Some(mode);
+                sub_mesh_offsets.push(idxes.len());
+                GltfParser::primitive(&doc, &buffers, prim, mode, &mut verts, &mut idxes)?;
+            }
+        }
+
+        let primitive = primitive.ok_or("No mesh found in glTF.")?;
+
+        let (index_format, idxes) = if verts.len() > (::std::u16::MAX as usize + 1) {
+            (IndexFormat::U32, Vec::from(IndexFormat::as_bytes(&idxes)))
+        } else {
+            let v: Vec<u16> = idxes.iter().map(|v| *v as u16).collect();
+            (IndexFormat::U16, Vec::from(IndexFormat::as_bytes(&v)))
+        };
+
+        Ok(MeshData {
+            layout: PrimitiveVertex::layout(),
+            index_format: index_format,
+            primitive: primitive,
+            num_verts: verts.len(),
+            num_idxes: idxes.len() / index_format.len(),
+            sub_mesh_offsets: sub_mesh_offsets,
+            verts: Vec::from(PrimitiveVertex::as_bytes(&verts)),
+            idxes: idxes,
+        })
+    }
+}
+
+impl GltfParser {
+    /// Splits the bytes into JSON document and the optional binary chunk.
+    fn split(bytes: &[u8]) -> Result<(&str, Option<&[u8]>)> {
+        if !bytes.starts_with(GLB_MAGIC) {
+            let text = str::from_utf8(bytes).map_err(|_| "Invalid UTF-8 glTF document.")?;
+            return Ok((text, None));
+        }
+
+        if bytes.len() < 12 || read_u32(bytes, 4) != 2 {
+            bail!("Only the binary glTF of version 2 is supported.");
+        }
+
+        let len = read_u32(bytes, 8) as usize;
+        if len > bytes.len() {
+            bail!("Truncated binary glTF.");
+        }
+
+        let mut text = None;
+        let mut blob = None;
+        let mut pos = 12;
+
+        while pos + 8 <= len {
+            let chunk_len = read_u32(bytes, pos) as usize;
+            let chunk_type = read_u32(bytes, pos + 4);
+            let data = bytes.get(pos + 8..pos + 8 + chunk_len);
+            let data = data.ok_or("Truncated chunk of binary glTF.")?;
+
+            match chunk_type {
+                GLB_CHUNK_JSON if text.is_none() => {
+                    let v = str::from_utf8(data).map_err(|_| "Invalid UTF-8 glTF document.")?;
+                    text = Some(v);
+                }
+                GLB_CHUNK_BIN if blob.is_none() => blob = Some(data),
+                _ => {}
+            }
+
+            pos += 8 + chunk_len;
+        }
+
+        Ok((text.ok_or("Binary glTF without JSON chunk.")?, blob))
+    }
+
+    fn buffers(doc: &Value, blob: Option<&[u8]>) -> Result<Vec<Vec<u8>>> {
+        let mut buffers = Vec::new();
+
+        for (i, buffer) in array(doc, "buffers").iter().enumerate() {
+            let len = field(buffer, "byteLength")?;
+
+            let data = match buffer.get("uri").and_then(|v| v.as_str()) {
+                None if i == 0 && blob.is_some() => blob.unwrap().to_vec(),
+                None => bail!(format!("Buffer {} without data.", i)),
+                Some(uri) => if uri.starts_with("data:") {
+                    let from = uri.find(";base64,").ok_or("Data URI should be base64 encoded.")?;
+                    base64::decode(&uri[from + 8..]).map_err(|_| "Invalid base64 data URI.")?
+                } else {
+                    bail!(format!(
+                        "External buffer {:?} is not supported, it should be embedded.",
+                        uri
+                    ));
+                },
+            };
+
+            if data.len() < len {
+                bail!(format!("Buffer {} is shorter than its byteLength.", i));
+            }
+
+            buffers.push(data);
+        }
+
+        Ok(buffers)
+    }
+
+    fn mode(prim: &Value) -> Result<Primitive> {
+        match prim.get("mode").and_then(as_usize).unwrap_or(4) {
+            0 => Ok(Primitive::Points),
+            1 => Ok(Primitive::Lines),
+            4 => Ok(Primitive::Triangles),
+            v => bail!(format!("Unsupported primitive mode {}.", v)),
+        }
+    }
+
+    fn primitive(
+        doc: &Value,
+        buffers: &[Vec<u8>],
+        prim: &Value,
+        mode: Primitive,
+        verts: &mut Vec<PrimitiveVertex>,
+        idxes: &mut Vec<u32>,
+    ) -> Result<()> {
+        let attributes = prim.get("attributes").ok_or("Primitive without attributes.")?;
+        let accessor = |name, types: &[u32], sizes: &[usize]| {
+            Accessor::attribute(doc, buffers, attributes, name, types, sizes)
+        };
+
+        let positions = accessor("POSITION", &[FLOAT], &[3])?;
+        let positions = positions.ok_or("Primitive without POSITION attribute.")?;
+        let normals = accessor("NORMAL", &[FLOAT], &[3])?;
+        let texcoords = accessor("TEXCOORD_0", &[FLOAT, UNSIGNED_BYTE, UNSIGNED_SHORT], &[2])?;
+        let colors = accessor("COLOR_0", &[FLOAT, UNSIGNED_BYTE, UNSIGNED_SHORT], &[3, 4])?;
+        let tangents = accessor("TANGENT", &[FLOAT], &[4])?;
+
+        for v in &[&normals, &texcoords, &colors, &tangents] {
+            if let Some(ref v) = **v {
+                if v.count != positions.count {
+                    bail!("Attributes of primitive have different number of elements.");
+                }
+            }
+        }
+
+        let base = verts.len();
+        for i in 0..positions.count {
+            let p = positions.read(i);
+            let n = normals.as_ref().map(|v| v.read(i)).unwrap_or([0.0; 4]);
+            let uv = texcoords.as_ref().map(|v| v.read(i)).unwrap_or([0.0; 4]);
+            let c = colors.as_ref().map(|v| v.read(i)).unwrap_or([1.0; 4]);
+            let t = tangents.as_ref().map(|v| v.read(i)).unwrap_or([0.0; 4]);
+
+            let color = [
+                (c[0].max(0.0).min(1.0) * 255.0).round() as u8,
+                (c[1].max(0.0).min(1.0) * 255.0).round() as u8,
+                (c[2].max(0.0).min(1.0) * 255.0).round() as u8,
+                (c[3].max(0.0).min(1.0) * 255.0).round() as u8,
+            ];
+
+            verts.push(PrimitiveVertex::new(
+                [p[0], p[1], -p[2]],
+                color,
+                [uv[0], uv[1]],
+                [n[0], n[1], -n[2]],
+                // Mirroring the z axis flips the handedness of tangent space too.
+                [t[0], t[1], -t[2], -t[3]],
+            ));
+        }
+
+        let from = idxes.len();
+        if let Some(index) = prim.get("indices") {
+            let index = as_usize(index).ok_or("Invalid accessor index.")?;
+            let accessor = Accessor::new(doc, buffers, index)?;
+            accessor.check("indices", &[UNSIGNED_BYTE, UNSIGNED_SHORT, UNSIGNED_INT], &[1])?;
+
+            for i in 0..accessor.count {
+                let v = accessor.read_index(i);
+                if v >= positions.count {
+                    bail!(format!("Index {} is out of the range of vertices.", v));
+                }
+
+                idxes.push((base + v) as u32);
+            }
+        } else {
+            idxes.extend((base..verts.len()).map(|v| v as u32));
+        }
+
+        if mode == Primitive::Triangles {
+            if (idxes.len() - from) % 3 != 0 {
+                bail!("The number of indices of triangles should be multiple of 3.");
+            }
+
+            for v in idxes[from..].chunks_mut(3) {
+                v.swap(1, 2);
+            }
+        }
+
+        if tangents.is_none() {
+            let triangles: Vec<u32> = match mode {
+                Primitive::Triangles => idxes[from..].iter().map(|v| v - base as u32).collect(),
+                _ => Vec::new(),
+            };
+
+            mesh::generate_tangents(&mut verts[base..], &triangles);
+        }
+
+        Ok(())
+    }
+}
+
+/// The typed view into a buffer.
+struct Accessor<'a> {
+    data: &'a [u8],
+    stride: usize,
+    count: usize,
+    component: u32,
+    size: usize,
+    normalized: bool,
+}
+
+impl<'a> Accessor<'a> {
+    fn new(doc: &Value, buffers: &'a [Vec<u8>], index: usize) -> Result<Accessor<'a>> {
+        let accessor = element(doc, "accessors", index)?;
+
+        if accessor.get("sparse").is_some() {
+            bail!("Sparse accessor is not supported.");
+        }
+
+        let component = field(accessor, "componentType")? as u32;
+        let count = field(accessor, "count")?;
+        let offset = accessor.get("byteOffset").and_then(as_usize);
+        let normalized = accessor.get("normalized").and_then(|v| v.as_bool());
+
+        let size = match accessor.get("type").and_then(|v| v.as_str()) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            v => bail!(format!("Unsupported accessor type {:?}.", v)),
+        };
+
+        let component_size = match component {
+            BYTE | UNSIGNED_BYTE => 1,
+            SHORT | UNSIGNED_SHORT => 2,
+            UNSIGNED_INT | FLOAT => 4,
+            v => bail!(format!("Unsupported component type {}.", v)),
+        };
+
+        let view = accessor.get("bufferView").and_then(as_usize);
+        let view = element(doc, "bufferViews", view.ok_or("Accessor without buffer view.")?)?;
+        let buffer = buffers
+            .get(field(view, "buffer")?)
+            .ok_or("Undefined buffer of buffer view.")?;
+
+        let view_offset = view.get("byteOffset").and_then(as_usize);
+        let view_len = field(view, "byteLength")?;
+        let stride = view.get("byteStride").and_then(as_usize);
+        let stride = stride.unwrap_or(component_size * size);
+
+        let from = view_offset.unwrap_or(0);
+        let data = buffer
+            .get(from..from + view_len)
+            .ok_or("Buffer view is out of the range of buffer.")?;
+
+        let from = offset.unwrap_or(0);
+        let len = if count > 0 {
+            stride * (count - 1) + component_size * size
+        } else {
+            0
+        };
+
+        let data = data.get(from..from + len)
+            .ok_or("Accessor is out of the range of buffer view.")?;
+
+        Ok(Accessor {
+            data: data,
+            stride: stride,
+            count: count,
+            component: component,
+            size: size,
+            normalized: normalized.unwrap_or(false),
+        })
+    }
+
+    /// Gets the accessor of named attribute, and checks its component type and size.
+    fn attribute(
+        doc: &Value,
+        buffers: &'a [Vec<u8>],
+        attributes: &Value,
+        name: &str,
+        types: &[u32],
+        sizes: &[usize],
+    ) -> Result<Option<Accessor<'a>>> {
+        if let Some(v) = attributes.get(name) {
+            let index = as_usize(v).ok_or("Invalid accessor index.")?;
+            let accessor = Accessor::new(doc, buffers, index)?;
+            accessor.check(name, types, sizes)?;
+            Ok(Some(accessor))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn check(&self, name: &str, types: &[u32], sizes: &[usize]) -> Result<()> {
+        // Integer attributes must be normalized, except the indices.
+        if !types.contains(&self.component)
+            || (self.component != FLOAT && name != "indices" && !self.normalized)
+        {
+            bail!(format!("Unsupported component type {} of {}.", self.component, name));
+        }
+
+        if !sizes.contains(&self.size) {
+            bail!(format!("Unsupported number of components {} of {}.", self.size, name));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the `i`-th element as floats, the integer components are normalized into
+    /// [0, 1] or [-1, 1]. Alpha is filled with 1.0 if the element has only 3 components.
+    fn read(&self, i: usize) -> [f32; 4] {
+        let mut v = [0.0, 0.0, 0.0, 1.0];
+        let data = &self.data[i * self.stride..];
+
+        for c in 0..self.size {
+            v[c] = match self.component {
+                BYTE => (data[c] as i8 as f32 / 127.0).max(-1.0),
+                UNSIGNED_BYTE => data[c] as f32 / 255.0,
+                SHORT => (read_u16(data, c * 2) as i16 as f32 / 32767.0).max(-1.0),
+                UNSIGNED_SHORT => read_u16(data, c * 2) as f32 / 65535.0,
+                UNSIGNED_INT => read_u32(data, c * 4) as f32,
+                _ => f32::from_bits(read_u32(data, c * 4)),
+            };
+        }
+
+        v
+    }
+
+    fn read_index(&self, i: usize) -> usize {
+        let data = &self.data[i * self.stride..];
+        match self.component {
+            UNSIGNED_BYTE => data[0] as usize,
+            UNSIGNED_SHORT => read_u16(data, 0) as usize,
+            _ => read_u32(data, 0) as usize,
+        }
+    }
+}
+
+/// Gets the array field `name`, which is empty if missing.
+fn array<'a>(v: &'a Value, name: &str) -> &'a [Value] {
+    v.get(name)
+        .and_then(|v| v.as_array())
+        .map(|v| v.as_slice())
+        .unwrap_or(&[])
+}
+
+fn element<'a>(doc: &'a Value, name: &str, index: usize) -> Result<&'a Value> {
+    array(doc, name)
+        .get(index)
+        .ok_or_else(|| format!("Undefined {} {}.", name, index).into())
+}
+
+fn field(v: &Value, name: &str) -> Result<usize> {
+    v.get(name)
+        .and_then(as_usize)
+        .ok_or_else(|| format!("Invalid or missing field {:?}.", name).into())
+}
+
+fn as_usize(v: &Value) -> Option<usize> {
+    v.as_u64().map(|v| v as usize)
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> u16 {
+    bytes[pos] as u16 | (bytes[pos + 1] as u16) << 8
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    bytes[pos] as u32 | (bytes[pos + 1] as u32) << 8 | (bytes[pos + 2] as u32) << 16
+        | (bytes[pos + 3] as u32) << 24
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unsupported() {
+        // Positions with unsigned short components are not allowed.
+        let text = r#"{
+            "buffers": [{"byteLength": 8, "uri": "data:application/octet-stream;base64,AAAAAAAAAAA="}],
+            "bufferViews": [{"buffer": 0, "byteLength": 8}],
+            "accessors": [{"bufferView": 0, "componentType": 5123, "count": 1, "type": "VEC3"}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}]
+        }"#;
+
+        assert!(GltfParser::parse(text.as_bytes()).is_err());
+
+        let text = text.replace("5123", "5130");
+        assert!(GltfParser::parse(text.as_bytes()).is_err());
+
+        let text = text.replace("5130", "5126").replace("\"byteLength\": 8", "\"byteLength\": 12");
+        assert!(GltfParser::parse(text.as_bytes()).is_err());
+
+        // A point with the embedded buffer of 12 bytes.
+        let text = text.replace("AAAAAAAAAAA=", "AAAAAAAAAAAAAAAA")
+            .replace("\"attributes\"", "\"mode\": 0, \"attributes\"");
+        assert!(GltfParser::parse(text.as_bytes()).is_ok());
+
+        // Malformed document and data URI.
+        assert!(GltfParser::parse(b"{\"meshes\": [1,]}").is_err());
+        let text = text.replace("AAAAAAAAAAAAAAAA", "AAAA!AAAAAAAAAAA");
+        assert!(GltfParser::parse(text.as_bytes()).is_err());
+    }
+}
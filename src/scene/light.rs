@@ -1,6 +1,7 @@
+use math;
 use utils::Color;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Light {
     /// Is this light enable.
     pub enable: bool,
@@ -13,7 +14,7 @@ pub struct Light {
 }
 
 /// Enumeration for all light sources.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum LightSource {
     /// A direcitonal light.
     Directional,
@@ -24,6 +25,19 @@ pub enum LightSource {
         /// Smoothness of the light-to-dark transition from the center to the radius.
         smoothness: f32,
     },
+    /// A spot light which shines along the forward direction of its node.
+    Spot {
+        /// Maximum raidus of the spot light's affected data.
+        radius: f32,
+        /// Smoothness of the light-to-dark transition from the center to the radius.
+        smoothness: f32,
+        /// Angle between the direction and the edge of the fully lit inner cone.
+        cutoff: math::Rad<f32>,
+        /// Angle between the direction and the edge of the outer cone, the light fades
+        /// out between the inner and outer cone. A hard-edged cone is produced if it's
+        /// not greater than `cutoff`.
+        outer_cutoff: math::Rad<f32>,
+    },
 }
 
 impl Default for Light {
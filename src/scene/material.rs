@@ -1,26 +1,37 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
-use graphics::{RenderState, ShaderHandle, ShaderState, UniformVariable, UniformVariableType};
+use graphics::{DrawCall, GraphicsSystemShared, MeshHandle, RenderState, ShaderHandle,
+               UniformVariable, UniformVariableType};
 use utils::HashValue;
 
 use scene::errors::*;
 
 impl_handle!(MaterialHandle);
 
-#[derive(Debug, Clone)]
+/// A `Material` wraps a shader with the values of its uniform variables, which are
+/// validated against the uniform variables declared by shader.
+///
+/// Cloning a material shares the shader, but copies the uniform values. The state
+/// of shader is looked up by handle, so it's always the latest one after the shader
+/// is hot reloaded.
+#[derive(Clone)]
 pub struct Material {
+    video: Arc<GraphicsSystemShared>,
     shader: ShaderHandle,
-    render_state: RenderState,
-    fields: HashMap<HashValue<str>, UniformVariableType>,
-    pub(crate) variables: HashMap<HashValue<str>, UniformVariable>,
+    render_state_override: Option<RenderState>,
+    transparent: bool,
+    variables: HashMap<HashValue<str>, UniformVariable>,
 }
 
 impl Material {
-    pub fn new(shader: ShaderHandle, state: ShaderState) -> Self {
+    pub fn new(video: Arc<GraphicsSystemShared>, shader: ShaderHandle) -> Self {
         Material {
+            video: video,
             shader: shader,
-            render_state: state.render_state,
-            fields: state.uniform_variables,
+            render_state_override: None,
+            transparent: false,
             variables: HashMap::new(),
         }
     }
@@ -30,9 +41,39 @@ impl Material {
         self.shader
     }
 
+    /// Returns the render state of this material, which is the one of shader
+    /// unless it has been overridden.
     #[inline(always)]
     pub fn render_state(&self) -> RenderState {
-        self.render_state
+        self.render_state_override.unwrap_or_else(|| {
+            self.video
+                .with_shader_state(self.shader, |v| v.render_state)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Overrides the render state of shader for meshes drawn with this material.
+    /// It could be reset to the shader's with `None`.
+    #[inline(always)]
+    pub fn set_render_state<T1>(&mut self, state: T1)
+    where
+        T1: Into<Option<RenderState>>,
+    {
+        self.render_state_override = state.into();
+    }
+
+    /// Returns true if meshes drawn with this material are transparent, which are
+    /// rendered after the opaque ones in back-to-front order.
+    #[inline(always)]
+    pub fn is_transparent(&self) -> bool {
+        self.transparent || self.render_state().color_blend.is_some()
+    }
+
+    /// Marks this material as transparent. Notes that materials with color blending
+    /// are always transparent.
+    #[inline(always)]
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
     }
 
     #[inline(always)]
@@ -40,9 +81,15 @@ impl Material {
     where
         T1: Into<HashValue<str>>,
     {
-        self.fields.contains_key(&field.into())
+        let field = field.into();
+        self.video
+            .with_shader_state(self.shader, |v| v.uniform_variables.contains_key(&field))
+            .unwrap_or(false)
     }
 
+    /// Sets the value of uniform variable, which fails with the same error as
+    /// submitting a drawcall if the `field` is not declared by shader or the type
+    /// is mismatched.
     pub fn set_uniform_variable<T1, T2>(&mut self, field: T1, variable: T2) -> Result<()>
     where
         T1: Into<HashValue<str>>,
@@ -51,14 +98,11 @@ impl Material {
         let field = field.into();
         let variable = variable.into();
 
-        if !self.fields.contains_key(&field) {
-            bail!(ErrorKind::UniformUndefined);
-        }
-
-        if let Some(&tt) = self.fields.get(&field) {
-            if tt != variable.variable_type() {
-                bail!(ErrorKind::UniformTypeInvalid);
-            }
+        match self.video.with_shader_state(self.shader, |v| {
+            v.validate_uniform_variable(field, &variable)
+        }) {
+            Some(result) => result?,
+            None => bail!("Undefined shader handle."),
         }
 
         self.variables.insert(field, variable);
@@ -70,6 +114,120 @@ impl Material {
     where
         T1: Into<HashValue<str>>,
     {
-        self.variables.get(&field.into()).map(|v| *v)
+        self.variables.get(&field.into()).cloned()
+    }
+
+    #[inline(always)]
+    pub fn uniform_variable_type<T1>(&self, field: T1) -> Option<UniformVariableType>
+    where
+        T1: Into<HashValue<str>>,
+    {
+        let field = field.into();
+        self.video
+            .with_shader_state(self.shader, |v| v.uniform_variables.get(&field).cloned())
+            .and_then(|v| v)
+    }
+
+    /// Gets the names and values of uniform variables that have been set.
+    pub(crate) fn uniform_variables(&self) -> Vec<(String, &UniformVariable)> {
+        let variables = &self.variables;
+        self.video
+            .with_shader_state(self.shader, |state| {
+                variables
+                    .iter()
+                    .filter_map(|(k, v)| {
+                        let name = state.uniform_variable_names.get(k)?;
+                        Some((name.clone(), v))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Creates a drawcall of `mesh` with the shader, uniform variables and the
+    /// overridden render state of this material.
+    pub fn build_drawcall(&self, mesh: MeshHandle) -> DrawCall {
+        let mut dc = DrawCall::new(self.shader, mesh);
+        if let Some(state) = self.render_state_override {
+            dc.set_render_state(state);
+        }
+
+        for (k, v) in &self.variables {
+            dc.set_uniform_variable(*k, v.clone());
+        }
+
+        dc
+    }
+}
+
+impl fmt::Debug for Material {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Material")
+            .field("shader", &self.shader)
+            .field("render_state_override", &self.render_state_override)
+            .field("transparent", &self.transparent)
+            .field("variables", &self.variables)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphics::{GraphicsSystem, ShaderSetup};
+    use resource::{Location, ResourceSystem};
+    use utils::Color;
+
+    fn material() -> Material {
+        let resource = ResourceSystem::new().unwrap();
+        let video = GraphicsSystem::headless(resource.shared(), (320, 240));
+
+        let mut setup = ShaderSetup::default();
+        setup.vs = "void main() {}".to_owned();
+        setup.fs = "void main() {}".to_owned();
+        setup
+            .uniform_variables
+            .insert("u_Color".into(), UniformVariableType::Vector4f);
+
+        let shared = video.shared();
+        let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+        Material::new(shared, shader)
+    }
+
+    #[test]
+    fn uniforms() {
+        let mut mat = material();
+        assert!(mat.set_uniform_variable("u_Color", Color::red()).is_ok());
+        assert!(mat.set_uniform_variable("u_Color", 1.0f32).is_err());
+        assert!(mat.set_uniform_variable("u_Undefined", 1.0f32).is_err());
+
+        let color: [f32; 4] = Color::red().into();
+        let v = mat.uniform_variable("u_Color");
+        assert_eq!(v, Some(UniformVariable::Vector4f(color)));
+    }
+
+    #[test]
+    fn clone() {
+        let mut mat = material();
+        mat.set_uniform_variable("u_Color", Color::red()).unwrap();
+
+        let mut other = mat.clone();
+        other.set_uniform_variable("u_Color", Color::white()).unwrap();
+
+        assert_eq!(mat.shader(), other.shader());
+        assert_ne!(mat.uniform_variable("u_Color"), other.uniform_variable("u_Color"));
+    }
+
+    #[test]
+    fn transparent() {
+        let mut mat = material();
+        assert!(!mat.is_transparent());
+
+        mat.set_transparent(true);
+        assert!(mat.is_transparent());
+
+        mat.set_transparent(false);
+        mat.set_render_state(RenderState::translucent());
+        assert!(mat.is_transparent());
     }
 }
@@ -2,15 +2,33 @@ pub mod errors;
 pub mod node;
 pub mod transform;
 pub mod camera;
+pub mod frustum;
 pub mod light;
 pub mod scene;
 pub mod renderer;
 pub mod factory;
 pub mod material;
+pub mod settings;
+pub mod shadow;
+pub mod gltf;
+pub mod debug;
+pub mod gizmo;
+pub mod serialize;
+pub mod skeleton;
+pub mod animation;
 
 pub use self::node::Node;
-pub use self::transform::Transform;
+pub use self::transform::{Transform, TransformCache};
 pub use self::light::{Light, LightSource};
-pub use self::camera::{Camera, Projection};
+pub use self::camera::{Camera, Projection, ScreenOrigin};
+pub use self::frustum::Frustum;
 pub use self::renderer::MeshRenderer;
 pub use self::scene::Scene;
+pub use self::gltf::GltfParser;
+pub use self::settings::{Fog, RenderDebugMode, RenderSettings};
+pub use self::shadow::ShadowSetup;
+pub use self::debug::DebugRenderer;
+pub use self::gizmo::{GizmoAxis, GizmoMode, GizmoRenderer};
+pub use self::serialize::SceneLoadReport;
+pub use self::skeleton::{JointPose, Skeleton};
+pub use self::animation::{Animation, AnimationChannel, Animator, Keyframes};
@@ -9,7 +9,7 @@ use scene::errors::*;
 /// through the arena, which can be cumbersome and comes with some runtime overhead.
 /// But it not only keeps code clean and simple, but also makes `Node` could be
 /// send or shared across threads safely. This enables e.g. parallel tree traversals.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Node {
     parent: Option<ecs::Entity>,
     next_sib: Option<ecs::Entity>,
@@ -22,6 +22,19 @@ impl ecs::Component for Node {
     type Arena = ecs::VecArena<Node>;
 }
 
+/// The relationships are remapped to the re-created entities when loading, and the
+/// ones to entities that are not saved together are dropped.
+impl ecs::SerializableComponent for Node {
+    const NAME: &'static str = "Node";
+
+    fn remap(&mut self, entities: &ecs::EntityMap) {
+        self.parent = self.parent.and_then(|v| entities.get(v));
+        self.next_sib = self.next_sib.and_then(|v| entities.get(v));
+        self.prev_sib = self.prev_sib.and_then(|v| entities.get(v));
+        self.first_child = self.first_child.and_then(|v| entities.get(v));
+    }
+}
+
 impl Default for Node {
     fn default() -> Self {
         Node {
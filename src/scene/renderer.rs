@@ -1,18 +1,71 @@
+use std::collections::HashMap;
+
+use ecs;
 use ecs::{Arena, Fetch, System, View};
 use math;
-use math::{Matrix, SquareMatrix};
-use graphics::{DrawCall, GraphicsSystemShared, MeshHandle, MeshIndex, ShaderHandle, SurfaceHandle};
-use utils::HandleObjectPool;
+use math::{Angle, InnerSpace, Matrix, SquareMatrix};
+use graphics::{DrawCall, GraphicsSystemShared, MeshHandle, MeshIndex, RenderStateOverride,
+               ShaderHandle, SurfaceHandle, TextureHandle, UniformVariableType};
+use utils::{Aabb3, HandleIndex, HandleObjectPool};
 
-use scene::{LightSource, Node, Transform};
+use scene::{Frustum, LightSource, Node, Skeleton, Transform};
 use scene::material::{Material, MaterialHandle};
 use scene::scene::SceneNode;
-
-#[derive(Debug, Copy, Clone)]
+use scene::settings::RenderSettings;
+use scene::errors::*;
+
+/// Renders the mesh with materials.
+///
+/// If `index` is `MeshIndex::All`, every sub-mesh declared by
+/// `MeshSetup::sub_mesh_offsets` is drawn with its own draw call and the material at
+/// the same position of `materials`. The last material is repeated if there are fewer
+/// materials than sub-meshes, and having more materials than sub-meshes is an error.
+/// Otherwise, the selected range is drawn with the first material.
+#[derive(Debug, Clone)]
 pub struct MeshRenderer {
     pub mesh: MeshHandle,
     pub index: MeshIndex,
-    pub material: MaterialHandle,
+    pub materials: Vec<MaterialHandle>,
+}
+
+/// The mesh is saved by its location, and the materials are described along with it,
+/// see `Scene::save_world`.
+impl ecs::Component for MeshRenderer {
+    type Arena = ecs::HashMapArena<MeshRenderer>;
+}
+
+impl ecs::SerializableComponent for MeshRenderer {
+    const NAME: &'static str = "MeshRenderer";
+}
+
+impl MeshRenderer {
+    /// Gets the indices and materials of draw calls of this renderer, with the number
+    /// of sub-meshes of mesh.
+    pub(crate) fn draws(&self, sub_meshes: usize) -> Result<Vec<(MeshIndex, MaterialHandle)>> {
+        let num = match self.index {
+            MeshIndex::All => sub_meshes.max(1),
+            _ => 1,
+        };
+
+        if self.materials.len() > num {
+            bail!(format!(
+                "There are {} materials for {} sub-meshes of mesh {:?}.",
+                self.materials.len(),
+                num,
+                self.mesh
+            ));
+        }
+
+        // Draws with the fallback material if there is no material at all.
+        let last = self.materials.last().cloned().unwrap_or_default();
+        let material = |i: usize| self.materials.get(i).cloned().unwrap_or(last);
+
+        if num > 1 {
+            Ok((0..num).map(|i| (MeshIndex::SubMesh(i), material(i))).collect())
+        } else {
+            Ok(vec![(self.index, material(0))])
+        }
+    }
 }
 
 type SceneViewData<'a> = (Fetch<'a, Node>, Fetch<'a, Transform>, Fetch<'a, SceneNode>);
@@ -31,17 +84,35 @@ pub(crate) struct RenderDataDirLight {
 pub(crate) struct RenderDataPointLight {
     /// Position in eye space.
     pub position: math::Vector3<f32>,
-    pub position_field: String,
     pub color: math::Vector3<f32>,
-    pub color_field: String,
     pub attenuation: math::Vector3<f32>,
-    pub attenuation_field: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RenderDataSpotLight {
+    /// Position in eye space.
+    pub position: math::Vector3<f32>,
+    /// Direction in eye space.
+    pub dir: math::Vector3<f32>,
+    pub color: math::Vector3<f32>,
+    pub attenuation: math::Vector3<f32>,
+    /// Cosines of the inner and outer cutoff angles.
+    pub cutoff: math::Vector3<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RenderDataShadow {
+    /// The view-projection matrix of light.
+    pub matrix: math::Matrix4<f32>,
+    pub texture: TextureHandle,
+    pub bias: f32,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct RenderData {
     pub dir: Option<RenderDataDirLight>,
     pub points: Vec<RenderDataPointLight>,
+    pub spots: Vec<RenderDataSpotLight>,
 }
 
 pub(crate) struct RenderTask<'a> {
@@ -51,92 +122,291 @@ pub(crate) struct RenderTask<'a> {
     pub surface: SurfaceHandle,
     pub view_matrix: math::Matrix4<f32>,
     pub projection_matrix: math::Matrix4<f32>,
+    /// Objects outside the frustum are culled if presented.
+    pub frustum: Option<Frustum>,
+    /// The bounding boxes of meshes, which are cached during this task, so the mesh
+    /// registry is locked once per mesh instead of once per node.
+    pub bounds: HashMap<MeshHandle, Option<Aabb3>>,
+    /// The camera uses reverse-Z projection, so the depth tests of materials are
+    /// reversed.
+    pub reversed_z: bool,
+    pub shadow: Option<RenderDataShadow>,
+    pub settings: &'a RenderSettings,
+    /// The draw calls are grouped into this layer if presented.
+    pub layer: Option<u8>,
+    pub skeletons: Fetch<'a, Skeleton>,
     pub data: RenderData,
+    /// The meshes that failed to render, e.g. with more materials than sub-meshes.
+    pub failures: Vec<String>,
 }
 
 impl<'a, 'b> System<'a> for RenderTask<'b> {
     type ViewWith = SceneViewData<'a>;
 
-    fn run(&self, view: View, data: Self::ViewWith) {
-        let vp = self.projection_matrix * self.view_matrix;
+    fn run_mut(&mut self, view: View, data: Self::ViewWith) {
         unsafe {
             for v in view {
-                if let &SceneNode::Mesh(mesh) = data.2.get_unchecked(v) {
-                    let mut mat = self.materials.get(mesh.material).unwrap_or(self.fallback);
-                    if !self.video.is_shader_alive(mat.shader()) {
-                        mat = self.fallback;
+                if let &SceneNode::Mesh(ref mesh) = data.2.get_unchecked(v) {
+                    let m = Transform::world_matrix(&data.0, &data.1, v).unwrap();
+                    if let Some(ref frustum) = self.frustum {
+                        let aabb = mesh_aabb(self.video, &mut self.bounds, mesh.mesh);
+                        if !is_visible(frustum, aabb, m) {
+                            continue;
+                        }
                     }
 
+                    // Meshes that are not ready yet are skipped.
+                    let sub_meshes = match self.video.mesh_sub_meshes(mesh.mesh) {
+                        Some(sub_meshes) => sub_meshes,
+                        None => continue,
+                    };
+
+                    let draws = match mesh.draws(sub_meshes) {
+                        Ok(draws) => draws,
+                        Err(err) => {
+                            self.failures.push(format!("{:?}: {}", v, err));
+                            continue;
+                        }
+                    };
+
                     // Generate packed draw order.
                     let p = Transform::world_position(&data.0, &data.1, v).unwrap();
                     let mut csp = self.view_matrix * math::Vector4::new(p.x, p.y, p.z, 1.0);
                     csp /= csp.w;
+                    let distance = csp.truncate().magnitude();
 
-                    let order = DrawOrder {
-                        tranlucent: mat.render_state().color_blend.is_some(),
-                        zorder: (csp.z * 1000.0) as u32,
-                        shader: mat.shader(),
-                    };
+                    for (index, material) in draws {
+                        let mut mat = self.materials.get(material).unwrap_or(self.fallback);
+                        if !self.video.is_shader_alive(mat.shader()) {
+                            mat = self.fallback;
+                        }
 
-                    // Generate draw call and fill it with build-in uniforms.
-                    let mut dc = DrawCall::new(mat.shader(), mesh.mesh);
-                    let m = Transform::world_matrix(&data.0, &data.1, v).unwrap();
-                    let mv = self.view_matrix * m;
+                        let order = DrawOrder {
+                            transparent: mat.is_transparent(),
+                            distance: distance,
+                            shader: mat.shader(),
+                            entity: v.index(),
+                        };
 
-                    for (k, v) in &mat.variables {
-                        dc.set_uniform_variable(*k, *v);
-                    }
+                        let order = Self::layered_order(self.layer, order.into());
+                        let skeleton = self.skeletons.get(v);
+                        let mut dc = self.drawcall(mat, mesh.mesh, m, skeleton);
+                        let sdc = dc.build(index).unwrap();
 
-                    if mat.has_uniform_variable("u_ModelMatrix") {
-                        dc.set_uniform_variable("u_ModelMatrix", m);
+                        // Submit.
+                        if let Err(err) = self.video.submit(self.surface, order, sdc) {
+                            self.failures.push(format!("{:?}: {}", v, err));
+                        }
                     }
+                }
+            }
+        }
+    }
+}
 
-                    if mat.has_uniform_variable("u_ModelViewMatrix") {
-                        dc.set_uniform_variable("u_ModelViewMatrix", mv);
-                    }
+impl<'a> RenderTask<'a> {
+    /// Generates the draw call of mesh with material, and fills it with build-in
+    /// uniforms. The `u_ModelMatrix`, `u_ModelViewMatrix`, `u_MVPMatrix` and
+    /// `u_NormalMatrix` are derived from the world transform of node and the active
+    /// camera, so shaders never have to compute them by themselves.
+    fn drawcall(
+        &self,
+        mat: &Material,
+        mesh: MeshHandle,
+        m: math::Matrix4<f32>,
+        skeleton: Option<&Skeleton>,
+    ) -> DrawCall {
+        let mut dc = mat.build_drawcall(mesh);
+
+        if self.reversed_z {
+            let mut v = RenderStateOverride::default();
+            v.depth_test = Some(mat.render_state().depth_test.reversed());
+            dc.set_render_state_override(v);
+        }
 
-                    if mat.has_uniform_variable("u_MVPMatrix") {
-                        dc.set_uniform_variable("u_MVPMatrix", vp * m);
-                    }
+        let mv = self.view_matrix * m;
+        let vp = self.projection_matrix * self.view_matrix;
 
-                    if mat.has_uniform_variable("u_NormalMatrix") {
-                        let n = if let Some(invert) = mv.invert() {
-                            invert.transpose()
-                        } else {
-                            mv
-                        };
+        if mat.has_uniform_variable("u_ModelMatrix") {
+            dc.set_uniform_variable("u_ModelMatrix", m);
+        }
 
-                        dc.set_uniform_variable("u_NormalMatrix", n);
-                    }
+        if mat.has_uniform_variable("u_ModelViewMatrix") {
+            dc.set_uniform_variable("u_ModelViewMatrix", mv);
+        }
 
-                    if let &Some(ref dir) = &self.data.dir {
-                        if mat.has_uniform_variable(&dir.dir_field) {
-                            dc.set_uniform_variable(&dir.dir_field, dir.dir);
-                        }
+        if mat.has_uniform_variable("u_MVPMatrix") {
+            dc.set_uniform_variable("u_MVPMatrix", vp * m);
+        }
 
-                        if mat.has_uniform_variable(&dir.color_field) {
-                            dc.set_uniform_variable(&dir.color_field, dir.color);
-                        }
-                    }
+        if mat.has_uniform_variable("u_NormalMatrix") {
+            dc.set_uniform_variable("u_NormalMatrix", normal_matrix(mv));
+        }
 
-                    for v in &self.data.points {
-                        if mat.has_uniform_variable(&v.position_field) {
-                            dc.set_uniform_variable(&v.position_field, v.position);
-                        }
+        if let &Some(ref dir) = &self.data.dir {
+            if mat.has_uniform_variable(&dir.dir_field) {
+                dc.set_uniform_variable(&dir.dir_field, dir.dir);
+            }
 
-                        if mat.has_uniform_variable(&v.color_field) {
-                            dc.set_uniform_variable(&v.color_field, v.color);
-                        }
+            if mat.has_uniform_variable(&dir.color_field) {
+                dc.set_uniform_variable(&dir.color_field, dir.color);
+            }
+        }
 
-                        if mat.has_uniform_variable(&v.attenuation_field) {
-                            dc.set_uniform_variable(&v.attenuation_field, v.attenuation);
-                        }
+        self.bind_point_lights(mat, &mut dc);
+        self.bind_spot_lights(mat, &mut dc);
+        self.bind_settings(mat, &mut dc);
+        self.bind_shadow(mat, &mut dc);
+
+        if let Some(skeleton) = skeleton {
+            Self::bind_skeleton(mat, &mut dc, skeleton);
+        }
+
+        dc
+    }
+
+    /// Packs the layer into the most significant bits of draw order, which makes sure
+    /// that the draw calls of different layers never interleave.
+    pub fn layered_order(layer: Option<u8>, order: u64) -> u64 {
+        match layer {
+            Some(layer) => ((layer as u64) << 56) | (order >> 8),
+            None => order,
+        }
+    }
+
+    fn bind_point_lights(&self, mat: &Material, dc: &mut DrawCall) {
+        let fields: [(&str, fn(&RenderDataPointLight) -> math::Vector3<f32>); 3] = [
+            ("u_PointLightEyePos", |v| v.position),
+            ("u_PointLightColor", |v| v.color),
+            ("u_PointLightAttenuation", |v| v.attenuation),
+        ];
+
+        Self::bind_light_arrays(mat, dc, &self.data.points, &fields);
+    }
+
+    fn bind_spot_lights(&self, mat: &Material, dc: &mut DrawCall) {
+        let fields: [(&str, fn(&RenderDataSpotLight) -> math::Vector3<f32>); 5] = [
+            ("u_SpotLightEyePos", |v| v.position),
+            ("u_SpotLightEyeDir", |v| v.dir),
+            ("u_SpotLightColor", |v| v.color),
+            ("u_SpotLightAttenuation", |v| v.attenuation),
+            ("u_SpotLightCutoff", |v| v.cutoff),
+        ];
+
+        Self::bind_light_arrays(mat, dc, &self.data.spots, &fields);
+    }
+
+    fn bind_light_arrays<T>(
+        mat: &Material,
+        dc: &mut DrawCall,
+        lights: &[T],
+        fields: &[(&str, fn(&T) -> math::Vector3<f32>)],
+    ) {
+        // Lights exceed the declared size of arrays are ignored.
+        for &(field, get) in fields {
+            if let Some(UniformVariableType::Vector3fArray(n)) = mat.uniform_variable_type(field) {
+                let v: Vec<[f32; 3]> = lights.iter().take(n).map(|v| get(v).into()).collect();
+                dc.set_uniform_variable(field, v);
+            }
+        }
+    }
+
+    fn bind_skeleton(mat: &Material, dc: &mut DrawCall, skeleton: &Skeleton) {
+        // Joints exceed the declared size of array are ignored.
+        let field = "u_JointMatrices";
+        if let Some(UniformVariableType::Matrix4fArray(n)) = mat.uniform_variable_type(field) {
+            let v: Vec<[[f32; 4]; 4]> = skeleton
+                .skinning_matrices()
+                .iter()
+                .take(n)
+                .map(|m| *m.as_ref())
+                .collect();
+
+            dc.set_uniform_variable(field, v);
+        }
+    }
+
+    fn bind_shadow(&self, mat: &Material, dc: &mut DrawCall) {
+        if mat.has_uniform_variable("u_ShadowParams") {
+            let params = self.shadow
+                .map(|v| [v.bias, 1.0, 0.0])
+                .unwrap_or([0.0, 0.0, 0.0]);
+            dc.set_uniform_variable("u_ShadowParams", params);
+        }
+
+        if let Some(shadow) = self.shadow {
+            if mat.has_uniform_variable("u_LightSpaceMatrix") {
+                dc.set_uniform_variable("u_LightSpaceMatrix", shadow.matrix);
+            }
+
+            if mat.has_uniform_variable("u_ShadowMap") {
+                dc.set_uniform_variable("u_ShadowMap", shadow.texture);
+            }
+        }
+    }
+
+    fn bind_settings(&self, mat: &Material, dc: &mut DrawCall) {
+        let settings = self.settings;
+
+        if mat.has_uniform_variable("u_AmbientColor") {
+            dc.set_uniform_variable("u_AmbientColor", settings.ambient.rgb());
+        }
+
+        if mat.has_uniform_variable("u_FogColor") {
+            let color = settings.fog.map(|v| v.color).unwrap_or_default();
+            dc.set_uniform_variable("u_FogColor", color.rgb());
+        }
+
+        if mat.has_uniform_variable("u_FogParams") {
+            let params = settings
+                .fog
+                .map(|v| [v.start, v.end, 1.0])
+                .unwrap_or([0.0, 0.0, 0.0]);
+            dc.set_uniform_variable("u_FogParams", params);
+        }
+
+        if mat.has_uniform_variable("u_Exposure") {
+            dc.set_uniform_variable("u_Exposure", settings.exposure);
+        }
+
+        if mat.has_uniform_variable("u_DebugMode") {
+            let mode: i32 = settings.debug.into();
+            dc.set_uniform_variable("u_DebugMode", mode);
+        }
+    }
+}
+
+/// Renders the depth of meshes into shadow map.
+pub(crate) struct ShadowTask<'a> {
+    pub video: &'a GraphicsSystemShared,
+    pub surface: SurfaceHandle,
+    pub shader: ShaderHandle,
+    /// The view-projection matrix of light.
+    pub matrix: math::Matrix4<f32>,
+    /// The cached bounding boxes of meshes, see `RenderTask::bounds`.
+    pub bounds: HashMap<MeshHandle, Option<Aabb3>>,
+}
+
+impl<'a, 'b> System<'a> for ShadowTask<'b> {
+    type ViewWith = SceneViewData<'a>;
+
+    fn run_mut(&mut self, view: View, data: Self::ViewWith) {
+        let frustum = Frustum::new(self.matrix);
+
+        unsafe {
+            for v in view {
+                if let &SceneNode::Mesh(ref mesh) = data.2.get_unchecked(v) {
+                    let m = Transform::world_matrix(&data.0, &data.1, v).unwrap();
+                    let aabb = mesh_aabb(self.video, &mut self.bounds, mesh.mesh);
+                    if !is_visible(&frustum, aabb, m) {
+                        continue;
                     }
 
-                    let sdc = dc.build(mesh.index).unwrap();
+                    let mut dc = DrawCall::new(self.shader, mesh.mesh);
+                    dc.set_uniform_variable("u_MVPMatrix", self.matrix * m);
 
-                    // Submit.
-                    self.video.submit(self.surface, order, sdc).unwrap();
+                    let sdc = dc.build(mesh.index).unwrap();
+                    self.video.submit(self.surface, 0u64, sdc).unwrap();
                 }
             }
         }
@@ -155,6 +425,7 @@ impl RenderDataCollectTask {
             data: RenderData {
                 dir: None,
                 points: Vec::new(),
+                spots: Vec::new(),
             },
         }
     }
@@ -191,18 +462,29 @@ impl<'a> System<'a> for RenderDataCollectTask {
                             let p = Transform::world_position(&data.0, &data.1, v).unwrap();
                             let vp = (self.view_matrix * p.extend(1.0)).truncate();
                             let color: [f32; 4] = light.color.into();
-                            let n = self.data.points.len();
                             self.data.points.push(RenderDataPointLight {
                                 position: vp,
-                                position_field: format!("u_PointLightEyePos[{0}]", n),
                                 color: math::Vector4::from(color).truncate(),
-                                color_field: format!("u_PointLightColor[{0}]", n),
-                                attenuation: math::Vector3::new(
-                                    1.0,
-                                    -1.0 / (radius + smoothness * radius * radius),
-                                    -smoothness / (radius + smoothness * radius * radius),
-                                ),
-                                attenuation_field: format!("u_PointLightAttenuation[{0}]", n),
+                                attenuation: attenuation(radius, smoothness),
+                            });
+                        }
+
+                        LightSource::Spot {
+                            radius,
+                            smoothness,
+                            cutoff,
+                            outer_cutoff,
+                        } => {
+                            let p = Transform::world_position(&data.0, &data.1, v).unwrap();
+                            let vp = (self.view_matrix * p.extend(1.0)).truncate();
+                            let dir = Transform::forward(&data.0, &data.1, v).unwrap();
+                            let color: [f32; 4] = light.color.into();
+                            self.data.spots.push(RenderDataSpotLight {
+                                position: vp,
+                                dir: dir_matrix * dir,
+                                color: math::Vector4::from(color).truncate(),
+                                attenuation: attenuation(radius, smoothness),
+                                cutoff: spot_cutoff(cutoff, outer_cutoff),
                             });
                         }
                     }
@@ -212,22 +494,224 @@ impl<'a> System<'a> for RenderDataCollectTask {
     }
 }
 
+/// Gets the bounding box of mesh, which is looked up only once for each mesh.
+fn mesh_aabb(
+    video: &GraphicsSystemShared,
+    bounds: &mut HashMap<MeshHandle, Option<Aabb3>>,
+    mesh: MeshHandle,
+) -> Option<Aabb3> {
+    *bounds.entry(mesh).or_insert_with(|| video.mesh_aabb(mesh))
+}
+
+/// Returns false if the bounding sphere of mesh is outside of the frustum. Meshes
+/// without bounding box are always visible.
+fn is_visible(frustum: &Frustum, aabb: Option<Aabb3>, m: math::Matrix4<f32>) -> bool {
+    if let Some(aabb) = aabb {
+        let center = math::Point3::from_homogeneous(m * aabb.center().to_homogeneous());
+        let scale = m.x
+            .truncate()
+            .magnitude()
+            .max(m.y.truncate().magnitude())
+            .max(m.z.truncate().magnitude());
+
+        frustum.contains_sphere(center, aabb.radius() * scale)
+    } else {
+        true
+    }
+}
+
+fn attenuation(radius: f32, smoothness: f32) -> math::Vector3<f32> {
+    math::Vector3::new(
+        1.0,
+        -1.0 / (radius + smoothness * radius * radius),
+        -smoothness / (radius + smoothness * radius * radius),
+    )
+}
+
+/// Packs the cosines of inner and outer cutoff angles. The outer cone is never smaller
+/// than the inner one, and the shader treats equal cosines as a hard-edged cone.
+fn spot_cutoff(cutoff: math::Rad<f32>, outer_cutoff: math::Rad<f32>) -> math::Vector3<f32> {
+    let outer_cutoff = if outer_cutoff > cutoff {
+        outer_cutoff
+    } else {
+        cutoff
+    };
+
+    math::Vector3::new(cutoff.cos(), outer_cutoff.cos(), 0.0)
+}
+
+/// The sort key of draw calls in a surface. Opaque objects are always drawn ahead of
+/// transparent ones, and sorted front-to-back to reduce overdraw, then grouped by
+/// shader. Transparent objects are sorted back-to-front by the distance to camera,
+/// which is required by blending.
+///
+/// The index of entity breaks the ties, so objects at equal distance are drawn in
+/// the same order every frame.
 #[derive(Debug, Copy, Clone)]
 struct DrawOrder {
-    tranlucent: bool,
-    zorder: u32,
+    transparent: bool,
+    distance: f32,
     shader: ShaderHandle,
+    entity: HandleIndex,
 }
 
 impl Into<u64> for DrawOrder {
     fn into(self) -> u64 {
-        let prefix = if self.tranlucent {
-            (!self.zorder)
+        // The bits of positive floats are ordered in the same way as their values, and
+        // fit in 31 bits since the sign bit is zero.
+        let distance = if self.distance > 0.0 {
+            self.distance.to_bits()
+        } else {
+            0
+        };
+
+        let (prefix, depth) = if self.transparent {
+            (1u64, !distance & 0x7FFF_FFFF)
         } else {
-            self.zorder
+            (0u64, distance)
         };
 
-        let suffix = self.shader.index();
-        ((prefix as u64) << 32) | (suffix as u64)
+        let shader = self.shader.index() as u64 & 0xFFFF;
+        let entity = self.entity as u64 & 0xFFFF;
+        (prefix << 63) | ((depth as u64) << 32) | (shader << 16) | entity
+    }
+}
+
+/// Computes the matrix that transforms normals from the object space into the eye
+/// space, which is the inverse-transpose of the upper 3x3 part of model-view matrix.
+/// Normals stay perpendicular to the surfaces even if the node is scaled non-uniformly,
+/// and the translation is dropped, so the result could be applied to both `vec4(n, 0.0)`
+/// and `vec4(n, 1.0)`.
+pub(crate) fn normal_matrix(mv: math::Matrix4<f32>) -> math::Matrix4<f32> {
+    let m = math::Matrix3::from_cols(mv.x.truncate(), mv.y.truncate(), mv.z.truncate());
+    let n = if let Some(invert) = m.invert() {
+        invert.transpose()
+    } else {
+        m
+    };
+
+    n.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::Handle;
+
+    #[test]
+    fn layers() {
+        assert_eq!(RenderTask::layered_order(None, 42), 42);
+
+        let max = RenderTask::layered_order(Some(0), ::std::u64::MAX);
+        let min = RenderTask::layered_order(Some(1), 0);
+        assert!(max < min);
+        let lhs = RenderTask::layered_order(Some(1), 256);
+        let rhs = RenderTask::layered_order(Some(1), 512);
+        assert!(lhs < rhs);
+    }
+
+    #[test]
+    fn orders() {
+        let order = |transparent, distance, shader: u32, entity| -> u64 {
+            let v = DrawOrder {
+                transparent: transparent,
+                distance: distance,
+                shader: Handle::new(shader, 1).into(),
+                entity: entity,
+            };
+
+            v.into()
+        };
+
+        // Opaque objects are ahead of transparent ones regardless of distance.
+        assert!(order(false, 1000.0, 7, 1) < order(true, 0.0, 0, 0));
+        assert!(order(false, ::std::f32::INFINITY, 7, 1) < order(true, 1000.0, 0, 0));
+
+        // Opaque objects are sorted front-to-back, and transparent ones back-to-front.
+        assert!(order(false, 1.0, 7, 1) < order(false, 1.5, 0, 0));
+        assert!(order(true, 1.5, 7, 1) < order(true, 1.0, 0, 0));
+
+        // Negative or invalid distances are clamped to zero.
+        assert_eq!(order(false, -1.0, 0, 0), order(false, 0.0, 0, 0));
+        assert_eq!(order(true, ::std::f32::NAN, 0, 0), order(true, 0.0, 0, 0));
+
+        // Ties are broken by shader and entity.
+        assert!(order(false, 1.0, 0, 1) < order(false, 1.0, 1, 0));
+        assert!(order(true, 1.0, 0, 1) < order(true, 1.0, 0, 2));
+    }
+
+    #[test]
+    fn sub_meshes() {
+        let m0: MaterialHandle = Handle::new(0, 1).into();
+        let m1: MaterialHandle = Handle::new(1, 1).into();
+        let mut v = MeshRenderer {
+            mesh: MeshHandle::default(),
+            index: MeshIndex::All,
+            materials: vec![m0, m1],
+        };
+
+        let draws = v.draws(3).unwrap();
+        assert_eq!(
+            draws,
+            [
+                (MeshIndex::SubMesh(0), m0),
+                (MeshIndex::SubMesh(1), m1),
+                (MeshIndex::SubMesh(2), m1),
+            ]
+        );
+
+        assert!(v.draws(1).is_err());
+        assert!(v.draws(0).is_err());
+
+        v.index = MeshIndex::SubMesh(1);
+        assert!(v.draws(3).is_err());
+
+        v.materials = vec![m1];
+        assert_eq!(v.draws(3).unwrap(), [(MeshIndex::SubMesh(1), m1)]);
+
+        v.index = MeshIndex::All;
+        v.materials = Vec::new();
+        let draws = v.draws(0).unwrap();
+        assert_eq!(draws, [(MeshIndex::All, MaterialHandle::default())]);
+    }
+
+    #[test]
+    fn cutoff() {
+        let v = spot_cutoff(math::Deg(15.0).into(), math::Deg(30.0).into());
+        assert!(v.x > v.y);
+
+        // Hard-edged cone.
+        let v = spot_cutoff(math::Deg(15.0).into(), math::Deg(15.0).into());
+        assert_eq!(v.x, v.y);
+
+        let v = spot_cutoff(math::Deg(30.0).into(), math::Deg(15.0).into());
+        assert_eq!(v.x, v.y);
+    }
+
+    #[test]
+    fn normal_matrix() {
+        let m = math::Matrix4::from_translation(math::Vector3::new(3.0, -2.0, 5.0))
+            * math::Matrix4::from_angle_z(math::Deg(30.0))
+            * math::Matrix4::from_nonuniform_scale(1.0, 4.0, 0.5);
+        let n = super::normal_matrix(m);
+
+        // A plane through the origin with normal (1, 1, 0), and a tangent on it.
+        let normal = math::Vector4::new(1.0, 1.0, 0.0, 0.0);
+        let tangent = math::Vector4::new(1.0, -1.0, 2.0, 0.0);
+        assert_eq!(normal.dot(tangent), 0.0);
+
+        let normal = (n * normal).truncate();
+        let tangent = (m * tangent).truncate();
+        assert!(normal.dot(tangent).abs() < 1e-4);
+
+        // Uses the rotation only would break it.
+        let r = math::Matrix4::from_angle_z(math::Deg(30.0));
+        let wrong = (r * math::Vector4::new(1.0, 1.0, 0.0, 0.0)).truncate();
+        assert!(wrong.dot(tangent).abs() > 0.1);
+
+        // The translation never affects normals.
+        let v = n * math::Vector4::new(0.0, 0.0, 1.0, 1.0);
+        assert!((v.truncate() - (n * math::Vector4::unit_z()).truncate()).magnitude() < 1e-6);
+        assert_eq!(n.w, math::Vector4::unit_w());
     }
 }
@@ -1,54 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::mem;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json;
 
 use application::Context;
-use ecs::{ArenaMut, Component, Entity, Fetch, FetchMut, System, VecArena, World};
-use graphics::{GraphicsSystem, GraphicsSystemShared, ShaderHandle, SurfaceHandle, UniformVariable};
-use utils::{HandleObjectPool, HashValue};
+use ecs::{Arena, ArenaMut, Component, Entity, Fetch, FetchMut, LoadReport, System, VecArena,
+          View, World};
+use math;
+use graphics::{Command, GraphicsSystem, GraphicsSystemShared, MeshHandle, RenderState, Scissor,
+               ShaderHandle, SurfaceHandle, TextureHandle, UniformVariable};
+use resource::{ResourceSystem, ResourceSystemShared};
+use utils::{HandleObjectPool, HashValue, Ray};
 
-use scene::{Camera, Light, MeshRenderer, Node, Transform};
+use scene::{Animator, Camera, Frustum, Light, MeshRenderer, Node, Skeleton, Transform,
+            TransformCache};
 use scene::material::{Material, MaterialHandle};
-use scene::renderer::{RenderDataCollectTask, RenderTask};
+use scene::renderer::{RenderDataCollectTask, RenderDataShadow, RenderTask, ShadowTask};
 use scene::errors::*;
 use scene::factory;
+use scene::serialize;
+use scene::serialize::{AssetLocations, MaterialData, MeshRendererAssets, MeshRendererData,
+                       NodeData, SceneData, SceneLoadReport};
+use scene::settings::RenderSettings;
+use scene::shadow::ShadowMap;
+
+/// The maximum number of cameras that could be rendered with `Scene::render_cameras`.
+pub const MAX_CAMERA_LAYERS: usize = 256;
 
 pub struct Scene {
     world: World,
     materials: HandleObjectPool<Material>,
     video: Arc<GraphicsSystemShared>,
+    resource: Arc<ResourceSystemShared>,
+    locations: AssetLocations,
     fallback: Material,
     shader: ShaderHandle,
+    settings: RenderSettings,
+    pending_settings: Option<RenderSettings>,
+    frame_index: u64,
+    frustum_culling: bool,
+    shadow: Option<ShadowMap>,
+    transforms: TransformCache,
 }
 
 impl Drop for Scene {
     fn drop(&mut self) {
         self.video.delete_shader(self.shader);
+        if let Some(shadow) = self.shadow.take() {
+            shadow.delete(&self.video);
+        }
     }
 }
 
 impl Scene {
     pub fn new(ctx: &Context) -> Result<Self> {
         let video = ctx.shared::<GraphicsSystem>().clone();
+        let resource = ctx.shared::<ResourceSystem>().clone();
 
         let mut world = World::new();
-        world.register::<Node>();
-        world.register::<Transform>();
+        world.register_serializable::<Node>();
+        world.register_serializable::<Transform>();
         world.register::<SceneNode>();
+        world.register::<Skeleton>();
+        world.register::<Animator>();
 
         let materials = HandleObjectPool::new();
 
         let shader = factory::shader::undefined(&video)?;
-        let shader_state = video.shader_state(shader).unwrap();
-        let fallback = Material::new(shader, shader_state);
+        let fallback = Material::new(video.clone(), shader);
 
         Ok(Scene {
             world: world,
             materials: materials,
             video: video,
+            resource: resource,
+            locations: AssetLocations::default(),
             shader: shader,
             fallback: fallback,
+            settings: RenderSettings::default(),
+            pending_settings: None,
+            frame_index: 0,
+            frustum_culling: true,
+            shadow: None,
+            transforms: TransformCache::new(),
         })
     }
 
+    /// Gets the `World` of scene. It's advanced once per frame when the first camera
+    /// is rendered, so `World::changed` reports the changes made since last frame.
+    #[inline(always)]
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
     #[inline(always)]
     pub fn arena<T>(&self) -> Fetch<T>
     where
@@ -99,18 +147,89 @@ impl Scene {
     pub fn delete_node(&mut self, handle: Entity) -> Result<()> {
         Node::remove_from_parent(&mut self.arena_mut::<Node>(), handle)?;
         self.world.free(handle);
+        self.transforms.remove(handle);
+        Ok(())
+    }
+
+    /// Returns an iterator of the children of node. The iterator works on a snapshot
+    /// of hierarchy, so the scene could be modified during iterating.
+    pub fn children(&self, handle: Entity) -> ::std::vec::IntoIter<Entity> {
+        let tree = self.world.arena::<Node>();
+        let children: Vec<_> = Node::children(&tree, handle).collect();
+        children.into_iter()
+    }
+
+    /// Returns an iterator of the ancestors of node, from its parent to the root.
+    pub fn ancestors(&self, handle: Entity) -> ::std::vec::IntoIter<Entity> {
+        let tree = self.world.arena::<Node>();
+        let ancestors: Vec<_> = Node::ancestors(&tree, handle).collect();
+        ancestors.into_iter()
+    }
+
+    /// Returns an iterator of the descendants of node in depth-first order.
+    pub fn descendants(&self, handle: Entity) -> ::std::vec::IntoIter<Entity> {
+        let tree = self.world.arena::<Node>();
+        let descendants: Vec<_> = Node::descendants(&tree, handle).collect();
+        descendants.into_iter()
+    }
+
+    /// Gets the transform of node in world space. It's cached and recomputed only if
+    /// the `Transform` of node or any of its ancestors changes.
+    pub fn world_transform(&mut self, handle: Entity) -> Result<Transform> {
+        self.transforms.world_transform(&self.world, handle)
+    }
+
+    /// Attaches a `Skeleton` to node. The skinning matrices of joints are uploaded to
+    /// the `u_JointMatrices` of material if the node is a skinned mesh, and the joints
+    /// beyond the declared size of array are ignored.
+    pub fn set_skeleton(&mut self, handle: Entity, skeleton: Skeleton) -> Result<()> {
+        if !self.world.is_alive(handle) {
+            bail!(ErrorKind::HandleInvalid);
+        }
+
+        self.world.add(handle, skeleton);
         Ok(())
     }
 
+    /// Attaches a `Animator` to node, which plays the animation clip on the `Skeleton`
+    /// of the same node.
+    pub fn set_animator(&mut self, handle: Entity, animator: Animator) -> Result<()> {
+        if !self.world.is_alive(handle) {
+            bail!(ErrorKind::HandleInvalid);
+        }
+
+        self.world.add(handle, animator);
+        Ok(())
+    }
+
+    /// Advances all the animators by `dt`, usually the frame delta of `TimeSystem`,
+    /// and applies the poses to their skeletons.
+    pub fn advance_animations(&mut self, dt: Duration) {
+        let mut task = AnimationTask { dt: dt };
+        task.run_mut_at(&self.world);
+    }
+
     #[inline(always)]
     pub fn create_material(&mut self, shader: ShaderHandle) -> Result<MaterialHandle> {
-        if let Some(state) = self.video.shader_state(shader) {
-            Ok(self.materials.create(Material::new(shader, state)).into())
+        if self.video.is_shader_alive(shader) {
+            let mat = Material::new(self.video.clone(), shader);
+            Ok(self.materials.create(mat).into())
         } else {
             bail!("Undefined shader handle.");
         }
     }
 
+    /// Creates a new material which shares the shader of `handle`, with a copy of
+    /// its uniform variables and render state.
+    #[inline(always)]
+    pub fn clone_material(&mut self, handle: MaterialHandle) -> Result<MaterialHandle> {
+        if let Some(mat) = self.materials.get(*handle).cloned() {
+            Ok(self.materials.create(mat).into())
+        } else {
+            bail!("Undefined material handle.");
+        }
+    }
+
     #[inline(always)]
     pub fn update_material_uniform<T1, T2>(
         &mut self,
@@ -129,6 +248,23 @@ impl Scene {
         }
     }
 
+    #[inline(always)]
+    pub fn update_material_render_state<T1>(
+        &mut self,
+        handle: MaterialHandle,
+        state: T1,
+    ) -> Result<()>
+    where
+        T1: Into<Option<RenderState>>,
+    {
+        if let Some(mat) = self.materials.get_mut(*handle) {
+            mat.set_render_state(state);
+            Ok(())
+        } else {
+            bail!("Undefined material handle.");
+        }
+    }
+
     #[inline(always)]
     pub fn delete_material(&mut self, handle: MaterialHandle) -> Result<()> {
         if self.materials.free(handle).is_none() {
@@ -138,40 +274,650 @@ impl Scene {
         Ok(())
     }
 
+    /// Sets the scene-wide render settings. The new settings will take effect
+    /// completely since next frame, so all the cameras rendered in the same frame
+    /// will always share the same settings.
+    pub fn set_render_settings(&mut self, settings: RenderSettings) -> Result<()> {
+        if let Some(ref v) = settings.shadow {
+            v.validate()?;
+        }
+
+        self.pending_settings = Some(settings);
+        Ok(())
+    }
+
+    /// Gets the render settings that currently used.
+    #[inline]
+    pub fn render_settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    /// Enables or disables the frustum culling, meshes whose bounding spheres are
+    /// completely outside the view frustum of camera will not be submitted. It's
+    /// enabled by default.
+    #[inline]
+    pub fn set_frustum_culling(&mut self, enable: bool) {
+        self.frustum_culling = enable;
+    }
+
+    /// Records the location that `mesh` is created from, which is used to refer the mesh
+    /// by `Scene::save`. The meshes resolved by `Scene::load` and the built-in meshes of
+    /// `factory` are known already.
+    pub fn set_mesh_location<P>(&mut self, mesh: MeshHandle, location: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.locations.set_mesh(mesh, location.as_ref());
+    }
+
+    /// Records the location that `texture` is created from, see `set_mesh_location`.
+    pub fn set_texture_location<P>(&mut self, texture: TextureHandle, location: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.locations.set_texture(texture, location.as_ref());
+    }
+
+    /// Records the shared location that `shader` is created with, see
+    /// `set_mesh_location`.
+    pub fn set_shader_location<P>(&mut self, shader: ShaderHandle, location: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.locations.set_shader(shader, location.as_ref());
+    }
+
+    /// Writes the nodes with their hierarchy, transforms, meshes, lights and cameras
+    /// into `writer`, see `scene::serialize` for the format. Meshes, textures and
+    /// shaders are referred by their locations, it fails if any of them is unknown.
+    pub fn save<W>(&self, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let tree = self.world.arena::<Node>();
+        let transforms = self.world.arena::<Transform>();
+        let nodes = self.world.arena::<SceneNode>();
+
+        let mut data = SceneData::default();
+        let mut materials = HashMap::new();
+
+        // Nodes are written in tree order, so the order of children is kept.
+        let roots: Vec<_> = self.world
+            .iter()
+            .filter(|&v| tree.get(v).map(|n| n.is_root()).unwrap_or(false))
+            .collect();
+
+        for root in roots {
+            for ent in Some(root).into_iter().chain(Node::descendants(&tree, root)) {
+                let mut v = NodeData {
+                    id: ent,
+                    parent: tree.get(ent).and_then(|n| n.parent()),
+                    transform: transforms.get(ent).cloned().unwrap_or_default(),
+                    mesh: None,
+                    light: None,
+                    camera: None,
+                };
+
+                match nodes.get(ent) {
+                    Some(&SceneNode::Mesh(ref mesh)) => {
+                        let mut indices = Vec::with_capacity(mesh.materials.len());
+                        for &handle in &mesh.materials {
+                            let found = materials.get(&handle).cloned();
+                            let index = match found {
+                                Some(index) => index,
+                                None => {
+                                    let index = data.materials.len();
+                                    data.materials.push(self.describe_material(handle)?);
+                                    materials.insert(handle, index);
+                                    index
+                                }
+                            };
+
+                            indices.push(index);
+                        }
+
+                        v.mesh = Some(MeshRendererData {
+                            location: self.locations.mesh(&self.video, mesh.mesh)?,
+                            index: mesh.index.into(),
+                            materials: indices,
+                        });
+                    }
+                    Some(&SceneNode::Light(light)) => v.light = Some(light),
+                    Some(&SceneNode::Camera(camera)) => v.camera = Some(camera),
+                    _ => {}
+                }
+
+                data.nodes.push(v);
+            }
+        }
+
+        serde_json::to_writer_pretty(writer, &data)?;
+        Ok(())
+    }
+
+    /// Creates the nodes described in `reader` besides the existing ones, and resolves
+    /// the assets they refer by locations, see `scene::serialize`.
+    ///
+    /// The parts that fail to load, e.g. a missing mesh file or an unknown parent, are
+    /// reported in `SceneLoadReport` instead of failing the whole loading, and the
+    /// nodes are still created without them. Nothing will be created if the description
+    /// is malformed.
+    pub fn load<R>(&mut self, reader: R) -> Result<SceneLoadReport>
+    where
+        R: Read,
+    {
+        let data: SceneData = serde_json::from_reader(reader)?;
+        let mut report = SceneLoadReport::default();
+
+        let mut materials = Vec::with_capacity(data.materials.len());
+        for (i, v) in data.materials.into_iter().enumerate() {
+            match self.load_material(v, &mut report.failures) {
+                Ok(handle) => materials.push(Some(handle)),
+                Err(err) => {
+                    let err = format!("Failed to load material {}. {}", i, err);
+                    report.failures.push((None, err));
+                    materials.push(None);
+                }
+            }
+        }
+
+        let mut created = Vec::with_capacity(data.nodes.len());
+        for v in &data.nodes {
+            let mut failure = None;
+            let node = if let Some(ref mesh) = v.mesh {
+                match self.load_mesh_renderer(mesh, &materials) {
+                    Ok(mesh) => SceneNode::Mesh(mesh),
+                    Err(err) => {
+                        failure = Some(err.to_string());
+                        SceneNode::None
+                    }
+                }
+            } else if let Some(light) = v.light {
+                SceneNode::Light(light)
+            } else if let Some(camera) = v.camera {
+                SceneNode::Camera(camera)
+            } else {
+                SceneNode::None
+            };
+
+            let ent = self.create_node(node);
+            unsafe {
+                let mut arena = self.world.arena_mut::<Transform>();
+                let transform = arena.get_unchecked_mut(ent);
+                transform.set_scale(v.transform.scale());
+                transform.set_position(v.transform.position());
+                transform.set_rotation(v.transform.rotation());
+            }
+
+            if report.entities.get(v.id).is_some() {
+                let err = format!("Node id {:?} is duplicated.", v.id);
+                report.failures.push((Some(ent), err));
+            }
+
+            if let Some(err) = failure {
+                report.failures.push((Some(ent), err));
+            }
+
+            report.entities.insert(v.id, ent);
+            created.push(ent);
+        }
+
+        // Since a child is attached before the existing children of its parent, the
+        // nodes are attached in reverse order to keep the order of children.
+        for (v, &ent) in data.nodes.iter().zip(created.iter()).rev() {
+            let parent = match v.parent {
+                Some(parent) => parent,
+                None => continue,
+            };
+
+            let result = match report.entities.get(parent) {
+                Some(parent) => Node::set_parent(&mut self.world.arena_mut::<Node>(), ent, parent),
+                None => Err(format!("Parent {:?} is not found.", parent).into()),
+            };
+
+            if let Err(err) = result {
+                report.failures.push((Some(ent), err.to_string()));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Writes the entities of `world` into `writer` with `World::save`. The meshes of
+    /// `MeshRenderer` components are referred by their locations, and the materials of
+    /// this scene they use are described along with them, see `scene::serialize`. It
+    /// fails if the location of any mesh, texture or shader is unknown.
+    pub fn save_world<W>(&self, world: &World, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        serialize::save_scoped(self, || world.save(writer))?;
+        Ok(())
+    }
+
+    /// Loads the entities written by `save_world` into `world` with `World::load`. The
+    /// meshes of `MeshRenderer` components are resolved by their locations, and their
+    /// materials are created in this scene. The renderers that shared a material get
+    /// their own copies after loading.
+    pub fn load_world<R>(&mut self, world: &mut World, reader: R) -> Result<LoadReport>
+    where
+        R: Read,
+    {
+        let report = serialize::load_scoped(self, || world.load(reader))?;
+        Ok(report)
+    }
+
+    pub(crate) fn describe_mesh_renderer(&self, v: &MeshRenderer) -> Result<MeshRendererAssets> {
+        let mut materials = Vec::with_capacity(v.materials.len());
+        for &handle in &v.materials {
+            materials.push(self.describe_material(handle)?);
+        }
+
+        Ok(MeshRendererAssets {
+            location: self.locations.mesh(&self.video, v.mesh)?,
+            index: v.index.into(),
+            materials: materials,
+        })
+    }
+
+    pub(crate) fn resolve_mesh_renderer(&mut self, v: MeshRendererAssets) -> Result<MeshRenderer> {
+        let mesh = self.locations
+            .resolve_mesh(&self.video, &self.resource, &v.location)?;
+
+        // Unlike `Scene::load`, the renderer fails as a whole if any of its materials
+        // could not be loaded.
+        let mut failures = Vec::new();
+        let mut materials = Vec::with_capacity(v.materials.len());
+        for data in v.materials {
+            match self.load_material(data, &mut failures) {
+                Ok(handle) => materials.push(handle),
+                Err(err) => {
+                    failures.push((None, err.to_string()));
+                    break;
+                }
+            }
+        }
+
+        if let Some((_, err)) = failures.into_iter().next() {
+            for handle in materials {
+                self.delete_material(handle)?;
+            }
+
+            bail!(err);
+        }
+
+        Ok(MeshRenderer {
+            mesh: mesh,
+            index: v.index.into(),
+            materials: materials,
+        })
+    }
+
+    fn describe_material(&self, handle: MaterialHandle) -> Result<MaterialData> {
+        let mat = match self.materials.get(*handle) {
+            Some(mat) => mat,
+            None => bail!("Undefined material handle."),
+        };
+
+        let mut uniforms = BTreeMap::new();
+        for (name, v) in mat.uniform_variables() {
+            uniforms.insert(name.to_owned(), self.locations.describe_uniform(v)?);
+        }
+
+        Ok(MaterialData {
+            shader: self.locations.shader(&self.video, mat.shader())?,
+            transparent: mat.is_transparent(),
+            uniforms: uniforms,
+        })
+    }
+
+    /// Creates the material described by `v`. The material falls back to the undefined
+    /// shader if its shader could not be resolved, and the uniform variables that fail
+    /// to be resolved are skipped.
+    fn load_material(
+        &mut self,
+        v: MaterialData,
+        failures: &mut Vec<(Option<Entity>, String)>,
+    ) -> Result<MaterialHandle> {
+        let shader = match self.locations.resolve_shader(&self.video, &v.shader) {
+            Ok(shader) => Some(shader),
+            Err(err) => {
+                failures.push((None, err.to_string()));
+                None
+            }
+        };
+
+        let handle = self.create_material(shader.unwrap_or(self.shader))?;
+        let mat = self.materials.get_mut(*handle).unwrap();
+        mat.set_transparent(v.transparent);
+
+        if shader.is_none() {
+            return Ok(handle);
+        }
+
+        for (name, u) in v.uniforms {
+            let result = self.locations
+                .resolve_uniform(&self.video, &self.resource, u)
+                .and_then(|u| mat.set_uniform_variable(name.as_str(), u));
+
+            if let Err(err) = result {
+                let err = format!("Failed to set uniform variable {:?}. {}", name, err);
+                failures.push((None, err));
+            }
+        }
+
+        Ok(handle)
+    }
+
+    fn load_mesh_renderer(
+        &mut self,
+        v: &MeshRendererData,
+        materials: &[Option<MaterialHandle>],
+    ) -> Result<MeshRenderer> {
+        let mut handles = Vec::with_capacity(v.materials.len());
+        for &index in &v.materials {
+            match materials.get(index) {
+                Some(&Some(material)) => handles.push(material),
+                _ => bail!(format!("Material {} is not found.", index)),
+            }
+        }
+
+        let mesh = self.locations
+            .resolve_mesh(&self.video, &self.resource, &v.location)?;
+
+        Ok(MeshRenderer {
+            mesh: mesh,
+            index: v.index.into(),
+            materials: handles,
+        })
+    }
+
+    /// Creates a ray in world space from `camera`, which goes through `point` relative
+    /// to the top-left corner of `viewport`, e.g. the mouse position from `InputSystem`
+    /// and the dimensions of window.
+    pub fn screen_to_ray<P, V>(&self, camera: Entity, point: P, viewport: V) -> Result<Ray>
+    where
+        P: Into<math::Vector2<f32>>,
+        V: Into<math::Vector2<f32>>,
+    {
+        let v = match self.world.arena::<SceneNode>().get(camera) {
+            Some(&SceneNode::Camera(v)) => v,
+            _ => bail!(ErrorKind::NonCameraFound),
+        };
+
+        let tree = self.world.arena::<Node>();
+        let arena = self.world.arena::<Transform>();
+        let view = Transform::world_view_matrix(&tree, &arena, camera)?;
+        let ray = v.screen_to_ray(view, point, viewport);
+        Ok(ray.ok_or(ErrorKind::CanNotInverseTransform)?)
+    }
+
+    /// Casts a ray in world space against the bounding boxes of meshes, and returns the
+    /// nearest `MeshRenderer` and the distance to it. Meshes without bounding box are
+    /// ignored, and the one with smaller entity id wins if distances are equal.
+    pub fn raycast(&self, ray: Ray) -> Option<(Entity, f32)> {
+        let mut task = RaycastTask {
+            video: &self.video,
+            ray: ray,
+            hit: None,
+        };
+
+        task.run_mut_at(&self.world);
+        task.hit
+    }
+
     /// Renders objects into `Surface` from `Camera`.
     pub fn render(&mut self, surface: SurfaceHandle, camera: Entity) -> Result<()> {
-        let (view, projection) = {
-            if let Some(SceneNode::Camera(v)) = self.world.get::<SceneNode>(camera) {
-                let tree = self.world.arena::<Node>();
-                let arena = self.world.arena::<Transform>();
-                let view = Transform::world_view_matrix(&tree, &arena, camera)?;
-                let projection = v.matrix();
-                (view, projection)
+        self.render_cameras(&[(surface, camera)])
+    }
+
+    /// Renders objects from a list of cameras, each into its associated `Surface` and
+    /// the viewport of camera. Cameras are drawn in ascending order of priority, and
+    /// in the order of list if priorities are equal, so later cameras draw on top of
+    /// earlier ones.
+    ///
+    /// Notes that cameras sharing a surface share its depth buffer too. Overlays like
+    /// minimap should be rendered into a separate surface with greater order, which
+    /// clears the depth buffer.
+    pub fn render_cameras(&mut self, cameras: &[(SurfaceHandle, Entity)]) -> Result<()> {
+        if cameras.len() > MAX_CAMERA_LAYERS {
+            bail!("Too many cameras to render in one pass.");
+        }
+
+        let frame_index = self.video.frame_index();
+        if frame_index != self.frame_index {
+            self.frame_index = frame_index;
+            self.world.advance();
+            if let Some(settings) = self.pending_settings.take() {
+                self.apply_render_settings(settings)?;
+            }
+        }
+
+        let mut passes = Vec::with_capacity(cameras.len());
+        for &(surface, camera) in cameras {
+            if !self.world.is_alive(camera) {
+                bail!(ErrorKind::HandleInvalid);
+            }
+
+            let mut arena = self.world.arena_mut::<SceneNode>();
+            if let Some(&mut SceneNode::Camera(ref mut v)) = arena.get_mut(camera) {
+                // Keeps one unit equals to one physical pixel of the viewport if the
+                // window resizes, or moves to a screen with different hidpi factor.
+                if v.is_pixel_perfect() {
+                    let (w, h) = self.video.dimensions_in_pixels();
+                    let (_, size) = v.viewport();
+                    v.resize((w as f32 * size.0, h as f32 * size.1));
+                }
+
+                passes.push((surface, camera, *v));
             } else {
                 bail!(ErrorKind::NonCameraFound);
             }
+        }
+
+        // The sort is stable, so the order of list is kept for equal priorities.
+        passes.sort_by_key(|v| v.2.priority());
+
+        // A single camera is rendered exactly as it always was, otherwise the draw
+        // calls of each camera are grouped into a layer with its own scissor.
+        let layered = passes.len() > 1;
+        for (i, &(surface, camera, v)) in passes.iter().enumerate() {
+            let layer = if layered { Some(i as u8) } else { None };
+            self.render_camera(surface, camera, v, layer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the render settings, the shadow map is re-created if its setup changes.
+    /// Nothing is changed if it fails.
+    fn apply_render_settings(&mut self, settings: RenderSettings) -> Result<()> {
+        if settings.shadow != self.settings.shadow {
+            let shadow = match settings.shadow {
+                Some(setup) => Some(ShadowMap::new(&self.video, setup)?),
+                None => None,
+            };
+
+            if let Some(old) = mem::replace(&mut self.shadow, shadow) {
+                old.delete(&self.video);
+            }
+        }
+
+        self.settings = settings;
+        Ok(())
+    }
+
+    fn render_camera(
+        &mut self,
+        surface: SurfaceHandle,
+        camera: Entity,
+        cam: Camera,
+        layer: Option<u8>,
+    ) -> Result<()> {
+        let frame_index = self.frame_index;
+
+        let (view, projection, position) = {
+            let tree = self.world.arena::<Node>();
+            let arena = self.world.arena::<Transform>();
+            let view = Transform::world_view_matrix(&tree, &arena, camera)?;
+            let position = Transform::world_position(&tree, &arena, camera)?;
+            (view, cam.projection_matrix(), position)
         };
 
         let mut task = RenderDataCollectTask::new(view);
         task.run_mut_at(&self.world);
 
-        let task = RenderTask {
+        // The shadow map is rendered only once per frame, with the first camera.
+        let mut shadow = None;
+        if let (Some(v), Some(dir)) = (self.shadow.as_mut(), task.data.dir.as_ref()) {
+            let matrix = match v.matrix {
+                Some((index, matrix)) if index == frame_index => matrix,
+                _ => {
+                    let matrix = v.matrix(position, dir.forward);
+                    v.matrix = Some((frame_index, matrix));
+
+                    let mut task = ShadowTask {
+                        video: &self.video,
+                        surface: v.surface,
+                        shader: v.shader,
+                        matrix: matrix,
+                        bounds: HashMap::new(),
+                    };
+                    task.run_mut_at(&self.world);
+                    matrix
+                }
+            };
+
+            shadow = Some(RenderDataShadow {
+                matrix: matrix,
+                texture: v.texture,
+                bias: v.setup.bias,
+            });
+        }
+
+        let frustum = if self.frustum_culling {
+            // The planes are extracted from the standard projection, whose depth of
+            // clip space is in `[-w, w]`.
+            let mut standard = cam;
+            standard.set_reversed_z(false);
+            Some(Frustum::new(standard.projection_matrix() * view))
+        } else {
+            None
+        };
+
+        // Maps the projection into the viewport of camera, and clips everything outside
+        // of it with scissor. The scissor is normalized to the viewport of surface, and
+        // submitted with the smallest order of layer, so it's always applied before the
+        // draw calls of this camera.
+        let viewport = cam.viewport_matrix();
+        let scissored = layer.is_some() || viewport.is_some();
+        if scissored {
+            let scissor = match viewport {
+                Some(_) => {
+                    let (position, size) = cam.viewport();
+                    Scissor::Normalized(position, size)
+                }
+                None => Scissor::Disable,
+            };
+
+            let order = RenderTask::layered_order(layer, 0);
+            self.video.submit(surface, order, Command::set_scissor(scissor))?;
+        }
+
+        let mut task = RenderTask {
             video: &self.video,
             materials: &self.materials,
             surface: surface,
             fallback: &self.fallback,
             view_matrix: view,
-            projection_matrix: projection,
+            projection_matrix: viewport.map(|m| m * projection).unwrap_or(projection),
+            frustum: frustum,
+            bounds: HashMap::new(),
+            reversed_z: cam.is_reversed_z(),
+            shadow: shadow,
+            settings: &self.settings,
+            layer: layer,
+            skeletons: self.world.arena::<Skeleton>(),
             data: task.data,
+            failures: Vec::new(),
         };
-        task.run_at(&self.world);
+        task.run_mut_at(&self.world);
+
+        if !task.failures.is_empty() {
+            bail!(task.failures.join("\n"));
+        }
+
+        // Disables the scissor after this pass with the largest order of layer, so the
+        // following commands of surface are not clipped by the viewport of camera.
+        if scissored {
+            let order = RenderTask::layered_order(layer, ::std::u64::MAX);
+            let cmd = Command::set_scissor(Scissor::Disable);
+            self.video.submit(surface, order, cmd)?;
+        }
 
         Ok(())
     }
 }
 
+struct AnimationTask {
+    dt: Duration,
+}
+
+impl<'a> System<'a> for AnimationTask {
+    type ViewWith = (FetchMut<'a, Animator>, FetchMut<'a, Skeleton>);
+
+    fn run_mut(&mut self, view: View, mut data: Self::ViewWith) {
+        unsafe {
+            for v in view {
+                let skeleton = data.1.get_unchecked_mut(v);
+                data.0.get_unchecked_mut(v).advance(self.dt, skeleton);
+            }
+        }
+    }
+}
+
+struct RaycastTask<'a> {
+    video: &'a GraphicsSystemShared,
+    ray: Ray,
+    hit: Option<(Entity, f32)>,
+}
+
+impl<'a, 'b> System<'a> for RaycastTask<'b> {
+    type ViewWith = (Fetch<'a, Node>, Fetch<'a, Transform>, Fetch<'a, SceneNode>);
+
+    fn run_mut(&mut self, view: View, data: Self::ViewWith) {
+        unsafe {
+            for v in view {
+                if let &SceneNode::Mesh(ref mesh) = data.2.get_unchecked(v) {
+                    let aabb = match self.video.mesh_aabb(mesh.mesh) {
+                        Some(aabb) => aabb,
+                        None => continue,
+                    };
+
+                    // Intersects in local space, the distance is preserved since the
+                    // direction of transformed ray is not normalized.
+                    let m = match Transform::inverse_world_matrix(&data.0, &data.1, v) {
+                        Ok(m) => m,
+                        Err(_) => continue,
+                    };
+
+                    if let Some(t) = self.ray.transform(m).intersect_aabb(&aabb) {
+                        let nearer = match self.hit {
+                            Some((e, d)) => t < d || (t == d && v < e),
+                            None => true,
+                        };
+
+                        if nearer {
+                            self.hit = Some((v, t));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum SceneNode {
     None,
     Light(Light),
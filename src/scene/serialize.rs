@@ -0,0 +1,444 @@
+//! The human-editable description of scene, which is written by `Scene::save` and read
+//! by `Scene::load`.
+//!
+//! The scene is described as JSON document, where nodes are listed in tree order with
+//! the references to their parents, and the meshes, textures and shaders are referred
+//! by the paths of their `Location`s:
+//!
+//! ```json
+//! {
+//!   "materials": [
+//!     {
+//!       "shader": "__Core/Scene/Shader/PHONG",
+//!       "transparent": false,
+//!       "uniforms": { "u_Diffuse": { "Vector3f": [1.0, 0.5, 0.5] } }
+//!     }
+//!   ],
+//!   "nodes": [
+//!     { "id": { "index": 0, "version": 1 }, "transform": { ... }, "camera": { ... } },
+//!     {
+//!       "id": { "index": 1, "version": 1 },
+//!       "parent": { "index": 0, "version": 1 },
+//!       "transform": { ... },
+//!       "mesh": { "location": "/res/box.glb", "index": "All", "materials": [0] }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! The built-in meshes and shaders of `factory` are referred by their shared locations,
+//! e.g. `__Core/Scene/Mesh/CUBE`.
+//!
+//! `MeshRenderer` is a `SerializableComponent` as well, so it could be saved with other
+//! components of a `World` by `Scene::save_world`. Since materials belong to the scene,
+//! they are described along with every renderer instead of being shared:
+//!
+//! ```json
+//! {
+//!   "location": "/res/box.glb",
+//!   "index": "All",
+//!   "materials": [{ "shader": "__Core/Scene/Shader/PHONG", "uniforms": { ... } }]
+//! }
+//! ```
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error as DeError;
+use serde::ser::Error as SerError;
+
+use ecs::{Entity, EntityMap};
+use graphics;
+use graphics::{GraphicsSystemShared, MeshHandle, MeshIndex, MeshSetup, ShaderHandle,
+               TextureContainerParser, TextureHandle, TextureSetup, UniformVariable};
+use resource::{Location, ResourceSystemShared};
+
+use scene::{Camera, Light, MeshRenderer, Scene, Transform};
+use scene::errors::*;
+use scene::factory::{mesh, shader};
+use scene::gltf::GltfParser;
+
+/// The result of `Scene::load`.
+#[derive(Debug, Clone, Default)]
+pub struct SceneLoadReport {
+    /// The mappings from the node ids in description to the created nodes.
+    pub entities: EntityMap,
+    /// The parts of description that failed to load, with the created node they belong
+    /// to if any. The node is still created without the failed part, e.g. a node whose
+    /// mesh file is missing becomes an empty node.
+    pub failures: Vec<(Option<Entity>, String)>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SceneData {
+    #[serde(default)]
+    pub materials: Vec<MaterialData>,
+    #[serde(default)]
+    pub nodes: Vec<NodeData>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct NodeData {
+    pub id: Entity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Entity>,
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshRendererData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<Light>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<Camera>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MeshRendererData {
+    pub location: PathBuf,
+    #[serde(default)]
+    pub index: MeshIndexData,
+    /// The indices of materials of sub-meshes in `SceneData::materials`.
+    #[serde(default)]
+    pub materials: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum MeshIndexData {
+    All,
+    SubMesh(usize),
+    Ptr(usize, usize),
+}
+
+impl Default for MeshIndexData {
+    fn default() -> Self {
+        MeshIndexData::All
+    }
+}
+
+impl From<MeshIndex> for MeshIndexData {
+    fn from(v: MeshIndex) -> Self {
+        match v {
+            MeshIndex::All => MeshIndexData::All,
+            MeshIndex::SubMesh(i) => MeshIndexData::SubMesh(i),
+            MeshIndex::Ptr(from, len) => MeshIndexData::Ptr(from, len),
+        }
+    }
+}
+
+impl Into<MeshIndex> for MeshIndexData {
+    fn into(self) -> MeshIndex {
+        match self {
+            MeshIndexData::All => MeshIndex::All,
+            MeshIndexData::SubMesh(i) => MeshIndex::SubMesh(i),
+            MeshIndexData::Ptr(from, len) => MeshIndex::Ptr(from, len),
+        }
+    }
+}
+
+/// Notes that the overridden render state of material is not described.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MaterialData {
+    pub shader: PathBuf,
+    #[serde(default)]
+    pub transparent: bool,
+    #[serde(default)]
+    pub uniforms: BTreeMap<String, UniformData>,
+}
+
+/// The value of uniform variable, where textures are referred by their locations.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum UniformData {
+    Texture(PathBuf),
+    TextureCube(PathBuf),
+    Texture2DArray(PathBuf),
+    I32(i32),
+    F32(f32),
+    Vector2f([f32; 2]),
+    Vector3f([f32; 3]),
+    Vector4f([f32; 4]),
+    Matrix2f([[f32; 2]; 2], bool),
+    Matrix3f([[f32; 3]; 3], bool),
+    Matrix4f([[f32; 4]; 4], bool),
+    Vector3fArray(Vec<[f32; 3]>),
+    Matrix4fArray(Vec<[[f32; 4]; 4]>),
+}
+
+type Builtin<T> = (&'static str, fn(&GraphicsSystemShared) -> graphics::errors::Result<T>);
+
+const BUILTIN_MESHES: [Builtin<MeshHandle>; 2] =
+    [(mesh::CUBE, mesh::cube), (mesh::SEGMENT, mesh::segment)];
+
+const BUILTIN_SHADERS: [Builtin<ShaderHandle>; 10] = [
+    (shader::PBR, shader::pbr),
+    (shader::PHONG, shader::phong),
+    (shader::UNDEFINED, shader::undefined),
+    (shader::COLOR, shader::color),
+    (shader::COLOR_TRANSLUCENT, shader::color_translucent),
+    (shader::SHADOW, shader::shadow),
+    (shader::SPRITE, shader::sprite),
+    (shader::SKINNED, shader::skinned),
+    (shader::DEBUG, shader::debug),
+    (shader::GIZMO, shader::gizmo),
+];
+
+/// The locations of assets referenced by scene, which are used to describe the
+/// references when saving, and to resolve them when loading.
+#[derive(Debug, Default)]
+pub(crate) struct AssetLocations {
+    meshes: HashMap<MeshHandle, PathBuf>,
+    textures: HashMap<TextureHandle, PathBuf>,
+    shaders: HashMap<ShaderHandle, PathBuf>,
+}
+
+impl AssetLocations {
+    pub fn set_mesh(&mut self, handle: MeshHandle, path: &Path) {
+        self.meshes.insert(handle, path.to_owned());
+    }
+
+    pub fn set_texture(&mut self, handle: TextureHandle, path: &Path) {
+        self.textures.insert(handle, path.to_owned());
+    }
+
+    pub fn set_shader(&mut self, handle: ShaderHandle, path: &Path) {
+        self.shaders.insert(handle, path.to_owned());
+    }
+
+    pub fn mesh(&self, video: &GraphicsSystemShared, handle: MeshHandle) -> Result<PathBuf> {
+        if let Some(path) = self.meshes.get(&handle) {
+            return Ok(path.clone());
+        }
+
+        for &(name, _) in &BUILTIN_MESHES {
+            if video.lookup_mesh_from(Location::shared(0, name)) == Some(handle) {
+                return Ok(name.into());
+            }
+        }
+
+        bail!(format!("The location of mesh {:?} is unknown.", handle));
+    }
+
+    pub fn texture(&self, handle: TextureHandle) -> Result<PathBuf> {
+        match self.textures.get(&handle) {
+            Some(path) => Ok(path.clone()),
+            None => bail!(format!("The location of texture {:?} is unknown.", handle)),
+        }
+    }
+
+    pub fn shader(&self, video: &GraphicsSystemShared, handle: ShaderHandle) -> Result<PathBuf> {
+        if let Some(path) = self.shaders.get(&handle) {
+            return Ok(path.clone());
+        }
+
+        for &(name, _) in &BUILTIN_SHADERS {
+            if video.lookup_shader_from(Location::shared(0, name)) == Some(handle) {
+                return Ok(name.into());
+            }
+        }
+
+        bail!(format!("The location of shader {:?} is unknown.", handle));
+    }
+
+    /// Resolves the mesh at `path`. The mesh which has been created with the shared
+    /// location is reused, otherwise it's loaded with `GltfParser`.
+    pub fn resolve_mesh(
+        &mut self,
+        video: &GraphicsSystemShared,
+        resource: &ResourceSystemShared,
+        path: &Path,
+    ) -> Result<MeshHandle> {
+        let location = Location::shared(0, path);
+        let handle = if let Some(handle) = video.lookup_mesh_from(location) {
+            handle
+        } else if let Some(&(_, create)) = BUILTIN_MESHES.iter().find(|v| path == Path::new(v.0)) {
+            create(video)?
+        } else if resource.exists(path) {
+            video.create_mesh_from::<GltfParser>(location, MeshSetup::default())?
+        } else {
+            bail!(format!("Mesh file {:?} is not found.", path));
+        };
+
+        self.set_mesh(handle, path);
+        Ok(handle)
+    }
+
+    /// Resolves the texture at `path`. The texture which has been created with the
+    /// shared location is reused, otherwise it's loaded with `TextureContainerParser`.
+    pub fn resolve_texture(
+        &mut self,
+        video: &GraphicsSystemShared,
+        resource: &ResourceSystemShared,
+        path: &Path,
+    ) -> Result<TextureHandle> {
+        let location = Location::shared(0, path);
+        let handle = if let Some(handle) = video.lookup_texture_from(location) {
+            handle
+        } else if resource.exists(path) {
+            let setup = TextureSetup::default();
+            video.create_texture_from::<TextureContainerParser>(location, setup)?
+        } else {
+            bail!(format!("Texture file {:?} is not found.", path));
+        };
+
+        self.set_texture(handle, path);
+        Ok(handle)
+    }
+
+    /// Resolves the shader at `path`. Since the setup of shader could not be described
+    /// by its location, only the built-in shaders and the ones that have been created
+    /// with the shared location could be resolved.
+    pub fn resolve_shader(&mut self, video: &GraphicsSystemShared, path: &Path) -> Result<ShaderHandle> {
+        let location = Location::shared(0, path);
+        let handle = if let Some(handle) = video.lookup_shader_from(location) {
+            handle
+        } else if let Some(&(_, create)) = BUILTIN_SHADERS.iter().find(|v| path == Path::new(v.0)) {
+            create(video)?
+        } else {
+            bail!(format!(
+                "Shader {:?} is not found, it should be created before loading scene.",
+                path
+            ));
+        };
+
+        self.set_shader(handle, path);
+        Ok(handle)
+    }
+
+    pub fn describe_uniform(&self, v: &UniformVariable) -> Result<UniformData> {
+        let data = match *v {
+            UniformVariable::Texture(t) => UniformData::Texture(self.texture(t)?),
+            UniformVariable::TextureCube(t) => UniformData::TextureCube(self.texture(t)?),
+            UniformVariable::Texture2DArray(t) => UniformData::Texture2DArray(self.texture(t)?),
+            UniformVariable::I32(v) => UniformData::I32(v),
+            UniformVariable::F32(v) => UniformData::F32(v),
+            UniformVariable::Vector2f(v) => UniformData::Vector2f(v),
+            UniformVariable::Vector3f(v) => UniformData::Vector3f(v),
+            UniformVariable::Vector4f(v) => UniformData::Vector4f(v),
+            UniformVariable::Matrix2f(v, t) => UniformData::Matrix2f(v, t),
+            UniformVariable::Matrix3f(v, t) => UniformData::Matrix3f(v, t),
+            UniformVariable::Matrix4f(v, t) => UniformData::Matrix4f(v, t),
+            UniformVariable::Vector3fArray(ref v) => UniformData::Vector3fArray(v.clone()),
+            UniformVariable::Matrix4fArray(ref v) => UniformData::Matrix4fArray(v.clone()),
+        };
+
+        Ok(data)
+    }
+
+    pub fn resolve_uniform(
+        &mut self,
+        video: &GraphicsSystemShared,
+        resource: &ResourceSystemShared,
+        v: UniformData,
+    ) -> Result<UniformVariable> {
+        let variable = match v {
+            UniformData::Texture(p) => {
+                UniformVariable::Texture(self.resolve_texture(video, resource, &p)?)
+            }
+            UniformData::TextureCube(p) => {
+                UniformVariable::TextureCube(self.resolve_texture(video, resource, &p)?)
+            }
+            UniformData::Texture2DArray(p) => {
+                UniformVariable::Texture2DArray(self.resolve_texture(video, resource, &p)?)
+            }
+            UniformData::I32(v) => UniformVariable::I32(v),
+            UniformData::F32(v) => UniformVariable::F32(v),
+            UniformData::Vector2f(v) => UniformVariable::Vector2f(v),
+            UniformData::Vector3f(v) => UniformVariable::Vector3f(v),
+            UniformData::Vector4f(v) => UniformVariable::Vector4f(v),
+            UniformData::Matrix2f(v, t) => UniformVariable::Matrix2f(v, t),
+            UniformData::Matrix3f(v, t) => UniformVariable::Matrix3f(v, t),
+            UniformData::Matrix4f(v, t) => UniformVariable::Matrix4f(v, t),
+            UniformData::Vector3fArray(v) => UniformVariable::Vector3fArray(v),
+            UniformData::Matrix4fArray(v) => UniformVariable::Matrix4fArray(v),
+        };
+
+        Ok(variable)
+    }
+}
+
+/// The `MeshRenderer` component written by `Scene::save_world`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MeshRendererAssets {
+    pub location: PathBuf,
+    #[serde(default)]
+    pub index: MeshIndexData,
+    #[serde(default)]
+    pub materials: Vec<MaterialData>,
+}
+
+/// The scene that `MeshRenderer` components are described with, or resolved into,
+/// during `Scene::save_world` and `Scene::load_world`.
+#[derive(Clone, Copy)]
+enum Scope {
+    Save(*const Scene),
+    Load(*mut Scene),
+}
+
+thread_local!(static SCOPE: Cell<Option<Scope>> = Cell::new(None));
+
+/// Restores the outer scope when dropped, even if the serialization panics.
+struct ScopeGuard(Option<Scope>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let outer = self.0;
+        SCOPE.with(|v| v.set(outer));
+    }
+}
+
+fn scoped<F, R>(scope: Scope, func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _guard = ScopeGuard(SCOPE.with(|v| v.replace(Some(scope))));
+    func()
+}
+
+/// Runs `func` with `scene`, which describes the `MeshRenderer` components saved in it.
+pub(crate) fn save_scoped<F, R>(scene: &Scene, func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    scoped(Scope::Save(scene), func)
+}
+
+/// Runs `func` with `scene`, which resolves the `MeshRenderer` components loaded in it.
+pub(crate) fn load_scoped<F, R>(scene: &mut Scene, func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    scoped(Scope::Load(scene), func)
+}
+
+impl Serialize for MeshRenderer {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let data: Result<MeshRendererAssets> = match SCOPE.with(|v| v.get()) {
+            // The scene outlives the scope, see `save_scoped`.
+            Some(Scope::Save(scene)) => unsafe { (*scene).describe_mesh_renderer(self) },
+            _ => Err("MeshRenderer could only be saved with `Scene::save_world`.".into()),
+        };
+
+        data.map_err(|err| S::Error::custom(err.to_string()))?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MeshRenderer {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = MeshRendererAssets::deserialize(deserializer)?;
+        let renderer: Result<MeshRenderer> = match SCOPE.with(|v| v.get()) {
+            // The scene outlives the scope, see `load_scoped`.
+            Some(Scope::Load(scene)) => unsafe { (*scene).resolve_mesh_renderer(data) },
+            _ => Err("MeshRenderer could only be loaded with `Scene::load_world`.".into()),
+        };
+
+        renderer.map_err(|err| D::Error::custom(err.to_string()))
+    }
+}
@@ -0,0 +1,77 @@
+//! Scene-wide rendering profiles.
+
+use utils::Color;
+
+use scene::shadow::ShadowSetup;
+
+/// The distance based fog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    /// Color of the fog.
+    pub color: Color,
+    /// The distance in view space where the fog starts.
+    pub start: f32,
+    /// The distance in view space where objects are fully fogged.
+    pub end: f32,
+}
+
+/// The debug visualization modes of scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderDebugMode {
+    None,
+    /// Renders surface normals in view space.
+    Normals,
+    /// Renders without any lighting.
+    Unlit,
+    /// Renders the distance to camera, which is mapped into `[0, 1)` with `d / (d + 1)`.
+    Depth,
+}
+
+impl Into<i32> for RenderDebugMode {
+    fn into(self) -> i32 {
+        match self {
+            RenderDebugMode::None => 0,
+            RenderDebugMode::Normals => 1,
+            RenderDebugMode::Unlit => 2,
+            RenderDebugMode::Depth => 3,
+        }
+    }
+}
+
+/// A bundle of scene-wide rendering choices, which makes it possible to swap
+/// profiles like "day", "night" or "debug" atomically.
+///
+/// The settings are exposed to materials with following built-in uniforms if
+/// they are declared by shader, which are read by the built-in phong and pbr shaders:
+///
+/// - `u_AmbientColor: Vector3f`;
+/// - `u_FogColor: Vector3f`;
+/// - `u_FogParams: Vector3f`, which is (start, end, enable);
+/// - `u_Exposure: F32`;
+/// - `u_DebugMode: I32`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// The ambient light of scene, it's black by default so there is no ambient term.
+    pub ambient: Color,
+    pub fog: Option<Fog>,
+    pub exposure: f32,
+    pub debug: RenderDebugMode,
+    /// The shadow casting of directional light, it's disabled by default. An extra
+    /// pass is used to render the depth of scene into shadow map before others.
+    ///
+    /// Notes that the shadow map is rendered into a surface with order 0, so surfaces
+    /// that the scene renders into should have a greater order.
+    pub shadow: Option<ShadowSetup>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            ambient: Color::black(),
+            fog: None,
+            exposure: 1.0,
+            debug: RenderDebugMode::None,
+            shadow: None,
+        }
+    }
+}
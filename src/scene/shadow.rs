@@ -0,0 +1,127 @@
+//! Shadow mapping of the directional light.
+
+use math;
+use math::InnerSpace;
+
+use graphics::{FrameBufferHandle, FrameBufferSetup, GraphicsSystemShared, RenderTextureFormat,
+               RenderTextureSetup, ShaderHandle, SurfaceHandle, SurfaceSetup, TextureHandle};
+
+use scene::Camera;
+use scene::errors::*;
+use scene::factory;
+
+/// The configurations of shadow mapping.
+///
+/// The shadow map is rendered with an orthographic projection along the direction of
+/// light, which covers a box of `size` centered at the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSetup {
+    /// The resolution of shadow map in pixels.
+    pub resolution: u16,
+    /// The depth bias that used to avoid shadow acne. Notes that a large bias makes
+    /// shadows detached from their casters, which is known as peter-panning.
+    pub bias: f32,
+    /// The width and height of the box that casts shadows in world space.
+    pub size: f32,
+    /// The depth of the box that casts shadows in world space.
+    pub depth: f32,
+}
+
+impl Default for ShadowSetup {
+    fn default() -> Self {
+        ShadowSetup {
+            resolution: 1024,
+            bias: 0.005,
+            size: 50.0,
+            depth: 100.0,
+        }
+    }
+}
+
+impl ShadowSetup {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.resolution == 0 {
+            bail!("The resolution of shadow map should be greater than zero.");
+        }
+
+        if self.bias < 0.0 || self.size <= 0.0 || self.depth <= 0.0 {
+            bail!("Invalid size or bias of shadow map.");
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct ShadowMap {
+    pub setup: ShadowSetup,
+    pub texture: TextureHandle,
+    pub framebuffer: FrameBufferHandle,
+    pub surface: SurfaceHandle,
+    pub shader: ShaderHandle,
+    /// The view-projection matrix of light and the frame it was rendered at.
+    pub matrix: Option<(u64, math::Matrix4<f32>)>,
+}
+
+impl ShadowMap {
+    pub fn new(video: &GraphicsSystemShared, setup: ShadowSetup) -> Result<Self> {
+        setup.validate()?;
+
+        let mut rt = RenderTextureSetup::default();
+        rt.format = RenderTextureFormat::Depth24;
+        rt.dimensions = (setup.resolution as u32, setup.resolution as u32);
+        let texture = video.create_render_texture(rt)?;
+
+        let mut fb = FrameBufferSetup::default();
+        fb.set_attachment(texture, None)?;
+        let framebuffer = video.create_framebuffer(fb)?;
+
+        // The shadow map should be rendered before any other surfaces.
+        let mut surface = SurfaceSetup::default();
+        surface.set_framebuffer(framebuffer);
+        surface.set_clear(None, 1.0, None);
+        surface.set_order(0);
+        let surface = video.create_surface(surface)?;
+
+        Ok(ShadowMap {
+            setup: setup,
+            texture: texture,
+            framebuffer: framebuffer,
+            surface: surface,
+            shader: factory::shader::shadow(video)?,
+            matrix: None,
+        })
+    }
+
+    /// Calculates the view-projection matrix of light with the `forward` direction,
+    /// the shadow box is centered at `center`.
+    pub fn matrix(
+        &self,
+        center: math::Vector3<f32>,
+        forward: math::Vector3<f32>,
+    ) -> math::Matrix4<f32> {
+        let forward = forward.normalize();
+        let up = if forward.y.abs() > 0.99 {
+            math::Vector3::unit_z()
+        } else {
+            math::Vector3::unit_y()
+        };
+
+        // The rotation from world space into light space, which makes the `forward`
+        // aligned with positive z-axis.
+        let eye = center - forward * self.setup.depth * 0.5;
+        let ir = math::Matrix4::from(math::Matrix3::look_at(forward, up));
+        let it = math::Matrix4::from_translation(-eye);
+        let view = ir * it;
+
+        let s = self.setup.size;
+        let projection = Camera::ortho(s, s, 0.0, self.setup.depth).projection_matrix();
+        projection * view
+    }
+
+    pub fn delete(&self, video: &GraphicsSystemShared) {
+        video.delete_surface(self.surface);
+        video.delete_framebuffer(self.framebuffer);
+        video.delete_texture(self.texture);
+        video.delete_shader(self.shader);
+    }
+}
@@ -0,0 +1,187 @@
+//! Hierarchies of joints that deform skinned meshes.
+
+use ecs;
+use math;
+use math::One;
+
+use scene::errors::*;
+
+/// The transform of a joint relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointPose {
+    pub position: math::Vector3<f32>,
+    pub rotation: math::Quaternion<f32>,
+    pub scale: math::Vector3<f32>,
+}
+
+impl Default for JointPose {
+    fn default() -> Self {
+        JointPose {
+            position: math::Vector3::new(0.0, 0.0, 0.0),
+            rotation: math::Quaternion::one(),
+            scale: math::Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl JointPose {
+    /// Gets the matrix that transforms from the space of joint into its parent's.
+    pub fn matrix(&self) -> math::Matrix4<f32> {
+        let t = math::Matrix4::from_translation(self.position);
+        let r = math::Matrix4::from(self.rotation);
+        let s = math::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z);
+        t * r * s
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// The matrix that transforms vertices from the space of mesh into the space of
+    /// joint in bind pose.
+    pub inverse_bind_matrix: math::Matrix4<f32>,
+    pub pose: JointPose,
+}
+
+/// `Skeleton` is a hierarchy of joints. The vertices of skinned mesh are bound to at
+/// most four joints with weights, and follow the joints when their poses change.
+///
+/// Joints are stored in a flat list, and parents always come before their children,
+/// so the skinning matrices could be computed in one pass.
+#[derive(Debug, Clone, Default)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+}
+
+/// Declare `Skeleton` as component with hash map storage, since most of the nodes
+/// are not skinned.
+impl ecs::Component for Skeleton {
+    type Arena = ecs::HashMapArena<Skeleton>;
+}
+
+impl Skeleton {
+    /// Creates a empty skeleton.
+    pub fn new() -> Self {
+        Skeleton { joints: Vec::new() }
+    }
+
+    /// Adds a joint and returns its index. The parent of joint should be added before.
+    pub fn add_joint<T>(
+        &mut self,
+        name: T,
+        parent: Option<usize>,
+        inverse_bind_matrix: math::Matrix4<f32>,
+        pose: JointPose,
+    ) -> Result<usize>
+    where
+        T: Into<String>,
+    {
+        if let Some(parent) = parent {
+            if parent >= self.joints.len() {
+                bail!("The parent of joint should be added before it.");
+            }
+        }
+
+        self.joints.push(Joint {
+            name: name.into(),
+            parent: parent,
+            inverse_bind_matrix: inverse_bind_matrix,
+            pose: pose,
+        });
+
+        Ok(self.joints.len() - 1)
+    }
+
+    /// Gets the number of joints.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.joints.len()
+    }
+
+    /// Returns true if there is no joint in skeleton.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.joints.is_empty()
+    }
+
+    /// Gets the joint at `index`.
+    #[inline]
+    pub fn joint(&self, index: usize) -> Option<&Joint> {
+        self.joints.get(index)
+    }
+
+    /// Finds the index of joint with `name`.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|v| v.name == name)
+    }
+
+    /// Sets the current pose of joint at `index`.
+    #[inline]
+    pub fn set_pose(&mut self, index: usize, pose: JointPose) {
+        if let Some(joint) = self.joints.get_mut(index) {
+            joint.pose = pose;
+        }
+    }
+
+    /// Computes the skinning matrices of joints, which transform vertices from bind
+    /// pose into the current pose in the space of mesh.
+    pub fn skinning_matrices(&self) -> Vec<math::Matrix4<f32>> {
+        let mut matrices: Vec<math::Matrix4<f32>> = Vec::with_capacity(self.joints.len());
+
+        for joint in &self.joints {
+            let local = joint.pose.matrix();
+            let m = match joint.parent {
+                Some(parent) => matrices[parent] * local,
+                None => local,
+            };
+
+            matrices.push(m);
+        }
+
+        for (m, joint) in matrices.iter_mut().zip(self.joints.iter()) {
+            *m = *m * joint.inverse_bind_matrix;
+        }
+
+        matrices
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::SquareMatrix;
+
+    fn translation(x: f32, y: f32, z: f32) -> JointPose {
+        let mut pose = JointPose::default();
+        pose.position = math::Vector3::new(x, y, z);
+        pose
+    }
+
+    #[test]
+    fn hierarchy() {
+        let mut skeleton = Skeleton::new();
+        let inv = math::Matrix4::from_translation(math::Vector3::new(0.0, -1.0, 0.0));
+
+        let root = skeleton
+            .add_joint("root", None, math::Matrix4::identity(), JointPose::default())
+            .unwrap();
+        let arm = skeleton
+            .add_joint("arm", Some(root), inv, translation(0.0, 1.0, 0.0))
+            .unwrap();
+
+        assert!(skeleton.add_joint("hand", Some(5), inv, JointPose::default()).is_err());
+        assert_eq!(skeleton.find("arm"), Some(arm));
+        assert_eq!(skeleton.len(), 2);
+
+        // Nothing moves in bind pose.
+        let matrices = skeleton.skinning_matrices();
+        assert_eq!(matrices[arm], math::Matrix4::identity());
+
+        // Children follow their parents.
+        skeleton.set_pose(root, translation(2.0, 0.0, 0.0));
+        let matrices = skeleton.skinning_matrices();
+        let v = matrices[arm] * math::Vector4::new(0.0, 1.0, 0.0, 1.0);
+        assert_eq!(v, math::Vector4::new(2.0, 1.0, 0.0, 1.0));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use ecs;
 use math;
 use math::Transform as _Transform;
@@ -8,7 +10,7 @@ use scene::errors::*;
 
 /// `Transform` is used to store and manipulate the postiion, rotation and scale
 /// of the object. We use a left handed, y-up world coordinate system.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Transform {
     decomposed: math::Decomposed<math::Vector3<f32>, math::Quaternion<f32>>,
 }
@@ -18,6 +20,10 @@ impl ecs::Component for Transform {
     type Arena = ecs::VecArena<Transform>;
 }
 
+impl ecs::SerializableComponent for Transform {
+    const NAME: &'static str = "Transform";
+}
+
 impl Default for Transform {
     fn default() -> Self {
         Transform {
@@ -344,7 +350,9 @@ impl Transform {
 
         if let Some(inverse) = relative.inverse_transform() {
             unsafe {
-                arena.get_unchecked_mut(handle).decomposed = inverse.concat(&decomposed);
+                let transform = arena.get_unchecked_mut(handle);
+                transform.decomposed = inverse.concat(&decomposed);
+                transform.touch();
             }
             Ok(())
         } else {
@@ -458,3 +466,116 @@ impl Transform {
         Transform::transform_direction(tree, arena, handle, math::Vector3::new(1.0, 0.0, 0.0))
     }
 }
+
+/// Caches the world transforms of nodes.
+///
+/// The world transform of a node is composed lazily from the cached world transform of
+/// its parent. It's recomputed only if the `Transform` of the node or any of its
+/// ancestors has changed, or the node has been attached to another parent, so reading
+/// it repeatedly is cheap.
+///
+/// The changes are detected with the ticks of `World`. Since a tick lasts for a whole
+/// frame, the nodes changed during current frame are recomputed on every read until
+/// `World::advance` is called.
+#[derive(Debug, Default)]
+pub struct TransformCache {
+    entries: HashMap<ecs::Entity, TransformCacheEntry>,
+    updates: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TransformCacheEntry {
+    /// The tick of world when this entry is computed.
+    tick: u32,
+    /// The parent and the stamp of its entry when this entry is computed.
+    parent: Option<(ecs::Entity, u64)>,
+    stamp: u64,
+    decomposed: math::Decomposed<math::Vector3<f32>, math::Quaternion<f32>>,
+}
+
+impl TransformCache {
+    /// Creates a empty cache.
+    pub fn new() -> Self {
+        TransformCache::default()
+    }
+
+    /// Gets the transform of node in world space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena of `Node` or `Transform` is currently borrowed mutably.
+    pub fn world_transform(
+        &mut self,
+        world: &ecs::World,
+        handle: ecs::Entity,
+    ) -> Result<Transform> {
+        let tree = world.arena::<Node>();
+        let arena = world.arena::<Transform>();
+        let v = self.entry(world, &tree, &arena, handle)?;
+        Ok(Transform {
+            decomposed: v.decomposed,
+        })
+    }
+
+    /// Removes the cached world transform of node.
+    #[inline]
+    pub fn remove(&mut self, handle: ecs::Entity) {
+        self.entries.remove(&handle);
+    }
+
+    /// Gets the number of world transforms that have been recomputed.
+    #[inline]
+    pub fn updates(&self) -> u64 {
+        self.updates
+    }
+
+    fn entry<T1, T2>(
+        &mut self,
+        world: &ecs::World,
+        tree: &T1,
+        arena: &T2,
+        handle: ecs::Entity,
+    ) -> Result<TransformCacheEntry>
+    where
+        T1: ecs::Arena<Node>,
+        T2: ecs::Arena<Transform>,
+    {
+        let transform = match arena.get(handle) {
+            Some(transform) => *transform,
+            None => bail!(ErrorKind::NonTransformFound),
+        };
+
+        // Makes sure that the parent is up to date first, the changes of ancestors are
+        // propagated through the stamps of their entries.
+        let parent = match tree.get(handle).and_then(|v| v.parent()) {
+            Some(parent) if arena.get(parent).is_some() => {
+                Some((parent, self.entry(world, tree, arena, parent)?))
+            }
+            _ => None,
+        };
+
+        let stamps = parent.map(|(e, v)| (e, v.stamp));
+        if let Some(&v) = self.entries.get(&handle) {
+            let modified = world.modified::<Transform>(handle).unwrap_or(world.tick());
+            if modified < v.tick && v.parent == stamps {
+                return Ok(v);
+            }
+        }
+
+        let decomposed = match parent {
+            Some((_, v)) => v.decomposed.concat(&transform.decomposed),
+            None => transform.decomposed,
+        };
+
+        self.updates += 1;
+        let v = TransformCacheEntry {
+            tick: world.tick(),
+            parent: stamps,
+            stamp: self.updates,
+            decomposed: decomposed,
+        };
+
+        self.entries.insert(handle, v);
+        Ok(v)
+    }
+}
@@ -0,0 +1,82 @@
+//! Packing of glyph bitmaps into a texture atlas.
+
+/// The empty pixels between glyphs, which prevents bleeding with linear filtering.
+const PADDING: u32 = 1;
+
+/// A shelf packer of rectangles. Rectangles are placed left-to-right into rows,
+/// the height of a row is determined by the tallest rectangle in it.
+#[derive(Debug, Clone)]
+pub struct GlyphAtlas {
+    dimensions: (u32, u32),
+    /// The position of next rectangle in the current row.
+    cursor: (u32, u32),
+    row_height: u32,
+}
+
+impl GlyphAtlas {
+    /// Creates a new and empty atlas with `dimensions`.
+    pub fn new(dimensions: (u32, u32)) -> Self {
+        GlyphAtlas {
+            dimensions: dimensions,
+            cursor: (PADDING, PADDING),
+            row_height: 0,
+        }
+    }
+
+    /// Gets the dimensions of this atlas.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// Finds room for a rectangle with `size`, and returns its top-left position.
+    /// `None` is returned if the atlas runs out of room.
+    pub fn pack(&mut self, size: (u32, u32)) -> Option<(u32, u32)> {
+        let (w, h) = size;
+        if w + PADDING * 2 > self.dimensions.0 {
+            return None;
+        }
+
+        // Starts a new row if there is no room left in current one.
+        if self.cursor.0 + w + PADDING > self.dimensions.0 {
+            self.cursor = (PADDING, self.cursor.1 + self.row_height + PADDING);
+            self.row_height = 0;
+        }
+
+        if self.cursor.1 + h + PADDING > self.dimensions.1 {
+            return None;
+        }
+
+        let position = self.cursor;
+        self.cursor.0 += w + PADDING;
+        self.row_height = self.row_height.max(h);
+        Some(position)
+    }
+
+    /// Removes all the rectangles, and resets the dimensions.
+    pub fn reset(&mut self, dimensions: (u32, u32)) {
+        *self = GlyphAtlas::new(dimensions);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack() {
+        let mut atlas = GlyphAtlas::new((16, 16));
+        assert_eq!(atlas.pack((4, 4)), Some((1, 1)));
+        assert_eq!(atlas.pack((4, 6)), Some((6, 1)));
+        assert_eq!(atlas.pack((4, 2)), Some((11, 1)));
+
+        // Breaks into next row.
+        assert_eq!(atlas.pack((4, 4)), Some((1, 8)));
+        assert_eq!(atlas.pack((8, 8)), None);
+        assert_eq!(atlas.pack((20, 1)), None);
+
+        atlas.reset((32, 32));
+        assert_eq!(atlas.dimensions(), (32, 32));
+        assert_eq!(atlas.pack((8, 8)), Some((1, 1)));
+    }
+}
@@ -0,0 +1,16 @@
+use graphics;
+
+error_chain!{
+    types {
+        Error, ErrorKind, ResultExt, Result;
+    }
+
+    links {
+        Graphics(graphics::errors::Error, graphics::errors::ErrorKind);
+    }
+
+    errors {
+        FontInvalid
+        AtlasOverflow
+    }
+}
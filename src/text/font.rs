@@ -0,0 +1,274 @@
+//! TrueType fonts that rasterize glyphs into a texture atlas on demand.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rusttype;
+
+use application::Context;
+use graphics::{GraphicsSystem, GraphicsSystemShared, SpriteBatch, SurfaceHandle, TextureHandle,
+               TextureSetup};
+use resource::Location;
+use math;
+use utils::{Color, Rect};
+
+use super::atlas::GlyphAtlas;
+use super::errors::*;
+use super::layout::{GlyphMetrics, TextLayout, TextStyle};
+
+/// The initial dimensions of the glyph atlas.
+pub const INITIAL_ATLAS_SIZE: u32 = 256;
+/// The maximum dimensions of the glyph atlas. The atlas is cleared and repacked with
+/// glyphs in use if it runs out of room at this size.
+pub const MAX_ATLAS_SIZE: u32 = 4096;
+
+#[derive(Debug, Copy, Clone)]
+struct Glyph {
+    /// The position and size of bitmap in atlas, it's `None` for glyphs without any
+    /// visible pixels, e.g. whitespaces.
+    rect: Option<((u32, u32), (u32, u32))>,
+    /// The offset of the top-left corner of bitmap from the origin on baseline.
+    offset: (i32, i32),
+}
+
+/// A TrueType (or OpenType) font, which rasterizes glyphs of different sizes into a
+/// atlas texture lazily, and draws text as textured quads with `SpriteBatch`.
+///
+/// ```rust,ignore
+/// let mut font = Font::new(&ctx, include_bytes!("DejaVuSans.ttf").to_vec())?;
+/// font.set_matrix(Camera::ortho_2d(width, height).projection_matrix());
+///
+/// // Centers the label in screen.
+/// let rect = font.measure_text("Hello, World!", 32.0);
+/// let position = ((width - rect.width() as f32) * 0.5, (height + rect.height() as f32) * 0.5);
+/// font.draw_text(surface, 0, "Hello, World!", position, 32.0, Color::white())?;
+/// ```
+///
+/// The text is drawn in a coordinate system with y-axis pointing up, `position` is the
+/// top-left corner of the first line. Newlines break the text into lines, and kerning
+/// between characters are applied.
+pub struct Font {
+    font: rusttype::Font<'static>,
+    video: Arc<GraphicsSystemShared>,
+    batch: SpriteBatch,
+
+    atlas: GlyphAtlas,
+    texture: TextureHandle,
+    glyphs: HashMap<(char, u32), Glyph>,
+}
+
+impl Font {
+    /// Creates a new `Font` from the bytes of TrueType file.
+    pub fn new(ctx: &Context, bytes: Vec<u8>) -> Result<Self> {
+        let font = match rusttype::Font::from_bytes(bytes) {
+            Ok(font) => font,
+            Err(_) => bail!(ErrorKind::FontInvalid),
+        };
+
+        let video = ctx.shared::<GraphicsSystem>().clone();
+        let batch = SpriteBatch::new(video.clone())?;
+
+        let dimensions = (INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE);
+        let texture = Font::create_atlas_texture(&video, dimensions)?;
+
+        Ok(Font {
+            font: font,
+            video: video,
+            batch: batch,
+            atlas: GlyphAtlas::new(dimensions),
+            texture: texture,
+            glyphs: HashMap::new(),
+        })
+    }
+
+    /// Sets the view-projection matrix of text, e.g. the projection matrix of a 2D
+    /// camera.
+    #[inline]
+    pub fn set_matrix(&mut self, matrix: math::Matrix4<f32>) {
+        self.batch.set_matrix(matrix);
+    }
+
+    /// Gets the atlas texture of glyphs. Notes that the handle will be changed after
+    /// the atlas grows.
+    #[inline]
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Measures the bounding rect of text with font size `scale` in pixels. The `min`
+    /// corner of the rect is always at origin.
+    pub fn measure_text(&self, text: &str, scale: f32) -> Rect {
+        let (w, h) = self.layout(text, scale, Color::white()).measure(self);
+        Rect::new(
+            math::Point2::new(0, 0),
+            math::Point2::new(w.ceil() as i32, h.ceil() as i32),
+        )
+    }
+
+    /// Draws text with font size `scale` in pixels into `surface`.
+    pub fn draw_text<T>(
+        &mut self,
+        surface: SurfaceHandle,
+        order: T,
+        text: &str,
+        position: (f32, f32),
+        scale: f32,
+        color: Color,
+    ) -> Result<()>
+    where
+        T: Into<u64>,
+    {
+        let output = self.layout(text, scale, color).draw(self);
+        let keys: Vec<_> = output
+            .glyphs
+            .iter()
+            .map(|v| (v.character, v.size.to_bits()))
+            .collect();
+
+        self.cache(&keys)?;
+
+        let (aw, ah) = self.atlas.dimensions();
+        let (aw, ah) = (aw as f32, ah as f32);
+        let ascent = self.font.v_metrics(rusttype::Scale::uniform(scale)).ascent;
+
+        for (v, key) in output.glyphs.iter().zip(keys.iter()) {
+            let glyph = self.glyphs[key];
+            if let Some(((x, y), (w, h))) = glyph.rect {
+                let (w, h) = (w as f32, h as f32);
+                let left = position.0 + v.position.0 + glyph.offset.0 as f32;
+                let top = position.1 - (v.position.1 + ascent + glyph.offset.1 as f32);
+
+                let dst = ((left, top - h), (w, h));
+                // The rows of bitmap are stored from top to bottom in atlas.
+                let uv = ((x as f32 / aw, (y as f32 + h) / ah), (w / aw, -h / ah));
+                self.batch.draw(self.texture, dst, uv, v.color);
+            }
+        }
+
+        self.batch.flush(surface, order)?;
+        Ok(())
+    }
+
+    fn layout(&self, text: &str, scale: f32, color: Color) -> TextLayout {
+        let mut style = TextStyle::default();
+        style.size = scale;
+        style.color = color;
+
+        let mut layout = TextLayout::new(None);
+        layout.push(text, style);
+        layout
+    }
+
+    /// Makes sure that all the glyphs are rasterized into atlas. The atlas grows if it
+    /// runs out of room, or gets cleared and repacked once it reaches the maximum size.
+    fn cache(&mut self, keys: &[(char, u32)]) -> Result<()> {
+        let mut repacked = false;
+
+        loop {
+            if self.rasterize(keys)? {
+                return Ok(());
+            }
+
+            let (w, h) = self.atlas.dimensions();
+            let dimensions = if w < MAX_ATLAS_SIZE || h < MAX_ATLAS_SIZE {
+                ((w * 2).min(MAX_ATLAS_SIZE), (h * 2).min(MAX_ATLAS_SIZE))
+            } else if !repacked {
+                repacked = true;
+                (w, h)
+            } else {
+                bail!(ErrorKind::AtlasOverflow);
+            };
+
+            // The old texture will be deleted after the drawing of this frame, so
+            // text drawn before keeps valid.
+            let texture = Font::create_atlas_texture(&self.video, dimensions)?;
+            self.video.delete_texture(self.texture);
+            self.texture = texture;
+            self.atlas.reset(dimensions);
+            self.glyphs.clear();
+        }
+    }
+
+    /// Rasterizes glyphs which are not in the atlas yet, returns false if the atlas
+    /// runs out of room.
+    fn rasterize(&mut self, keys: &[(char, u32)]) -> Result<bool> {
+        for &(c, bits) in keys {
+            if self.glyphs.contains_key(&(c, bits)) {
+                continue;
+            }
+
+            let scale = rusttype::Scale::uniform(f32::from_bits(bits));
+            let glyph = self.font
+                .glyph(c)
+                .scaled(scale)
+                .positioned(rusttype::point(0.0, 0.0));
+
+            let mut v = Glyph {
+                rect: None,
+                offset: (0, 0),
+            };
+
+            if let Some(bb) = glyph.pixel_bounding_box() {
+                let (w, h) = (bb.width() as u32, bb.height() as u32);
+                let (x, y) = match self.atlas.pack((w, h)) {
+                    Some(position) => position,
+                    None => return Ok(false),
+                };
+
+                let mut bytes = vec![0; (w * h * 4) as usize];
+                glyph.draw(|gx, gy, coverage| {
+                    let i = ((gy * w + gx) * 4) as usize;
+                    bytes[i..i + 4].copy_from_slice(&[255, 255, 255, (coverage * 255.0) as u8]);
+                });
+
+                let rect = Rect::new(
+                    math::Point2::new(x as i32, y as i32),
+                    math::Point2::new((x + w) as i32, (y + h) as i32),
+                );
+
+                self.video.update_texture(self.texture, rect, &bytes)?;
+                v.rect = Some(((x, y), (w, h)));
+                v.offset = (bb.min.x, bb.min.y);
+            }
+
+            self.glyphs.insert((c, bits), v);
+        }
+
+        Ok(true)
+    }
+
+    fn create_atlas_texture(
+        video: &GraphicsSystemShared,
+        dimensions: (u32, u32),
+    ) -> Result<TextureHandle> {
+        let mut setup = TextureSetup::default();
+        setup.dimensions = dimensions;
+
+        // Clears the atlas, the padding between glyphs might be sampled.
+        let bytes = vec![0; (dimensions.0 * dimensions.1 * 4) as usize];
+        let texture = video.create_texture(Location::unique(""), setup, &bytes[..])?;
+        Ok(texture)
+    }
+}
+
+impl GlyphMetrics for Font {
+    fn advance(&self, c: char, size: f32) -> f32 {
+        let scale = rusttype::Scale::uniform(size);
+        self.font.glyph(c).scaled(scale).h_metrics().advance_width
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        let v = self.font.v_metrics(rusttype::Scale::uniform(size));
+        v.ascent - v.descent + v.line_gap
+    }
+
+    fn kerning(&self, lhs: char, rhs: char, size: f32) -> f32 {
+        self.font.pair_kerning(rusttype::Scale::uniform(size), lhs, rhs)
+    }
+}
+
+impl Drop for Font {
+    fn drop(&mut self) {
+        self.video.delete_texture(self.texture);
+    }
+}
@@ -0,0 +1,460 @@
+//! Line wrapping, alignment and styled runs.
+
+use utils::Color;
+
+/// The metrics of glyphs that used to layout text. It's usually implemented by fonts.
+pub trait GlyphMetrics {
+    /// Gets the horizontal advance of character with specified size.
+    fn advance(&self, c: char, size: f32) -> f32;
+
+    /// Gets the height of a line with specified size.
+    fn line_height(&self, size: f32) -> f32;
+
+    /// Gets the kerning adjustment between two characters with specified size.
+    fn kerning(&self, _: char, _: char, _: f32) -> f32 {
+        0.0
+    }
+}
+
+/// The horizontal alignment of lines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl Default for TextAlign {
+    fn default() -> Self {
+        TextAlign::Left
+    }
+}
+
+/// The direction of characters in a run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Default for TextDirection {
+    fn default() -> Self {
+        TextDirection::LeftToRight
+    }
+}
+
+/// The style of a run of text.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextStyle {
+    pub color: Color,
+    pub size: f32,
+    pub direction: TextDirection,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            color: Color::white(),
+            size: 16.0,
+            direction: TextDirection::LeftToRight,
+        }
+    }
+}
+
+/// A positioned glyph, the `position` is the top-left corner of its cell in
+/// the layout space, with y-axis pointing down.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphQuad {
+    pub character: char,
+    pub position: (f32, f32),
+    pub advance: f32,
+    pub size: f32,
+    pub color: Color,
+}
+
+/// The outputs of `TextLayout`.
+#[derive(Debug, Clone, Default)]
+pub struct TextLayoutOutput {
+    /// The positioned glyphs in visual order. Whitespaces are not included.
+    pub glyphs: Vec<GlyphQuad>,
+    /// The number of lines after wrapping.
+    pub lines: usize,
+    /// The bounding size of all the lines.
+    pub dimensions: (f32, f32),
+}
+
+/// `TextLayout` arranges styled runs of text into lines with a optional width
+/// constraint.
+///
+/// Lines are broken at whitespaces if possible, words that are wider than the
+/// constraint will be broken at characters. The bidirectional support is basic:
+/// the characters of runs with `TextDirection::RightToLeft` are reversed in visual
+/// order inside every line, while the order of lines and runs are unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct TextLayout {
+    width: Option<f32>,
+    align: TextAlign,
+    runs: Vec<(String, TextStyle)>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Item {
+    character: char,
+    style: usize,
+    advance: f32,
+}
+
+impl TextLayout {
+    /// Creates a new and empty `TextLayout` with optional width constraint.
+    pub fn new<T>(width: T) -> Self
+    where
+        T: Into<Option<f32>>,
+    {
+        TextLayout {
+            width: width.into(),
+            align: TextAlign::Left,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Sets the width constraint.
+    #[inline]
+    pub fn set_width<T>(&mut self, width: T)
+    where
+        T: Into<Option<f32>>,
+    {
+        self.width = width.into();
+    }
+
+    /// Sets the horizontal alignment of lines.
+    #[inline]
+    pub fn set_align(&mut self, align: TextAlign) {
+        self.align = align;
+    }
+
+    /// Appends a run of text with specified style.
+    pub fn push<T>(&mut self, text: T, style: TextStyle)
+    where
+        T: Into<String>,
+    {
+        self.runs.push((text.into(), style));
+    }
+
+    /// Removes all the runs.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.runs.clear();
+    }
+
+    /// Measures the bounding size of text after layout.
+    pub fn measure<M>(&self, metrics: &M) -> (f32, f32)
+    where
+        M: GlyphMetrics,
+    {
+        self.draw(metrics).dimensions
+    }
+
+    /// Layouts the text into positioned glyphs.
+    pub fn draw<M>(&self, metrics: &M) -> TextLayoutOutput
+    where
+        M: GlyphMetrics,
+    {
+        let mut output = TextLayoutOutput::default();
+        let lines = self.break_lines(metrics);
+
+        let mut y = 0.0;
+        for line in &lines {
+            let (width, height) = self.line_dimensions(metrics, line);
+
+            let offset = match (self.align, self.width) {
+                (TextAlign::Left, _) | (_, None) => 0.0,
+                (TextAlign::Center, Some(w)) => (w - width) * 0.5,
+                (TextAlign::Right, Some(w)) => w - width,
+            };
+
+            let mut x = offset;
+            for v in Self::visual_order(&self.runs, line) {
+                let style = &self.runs[v.style].1;
+                if !v.character.is_whitespace() {
+                    output.glyphs.push(GlyphQuad {
+                        character: v.character,
+                        position: (x, y),
+                        advance: v.advance,
+                        size: style.size,
+                        color: style.color,
+                    });
+                }
+
+                x += v.advance;
+            }
+
+            output.dimensions.0 = output.dimensions.0.max(width);
+            y += height;
+        }
+
+        output.lines = lines.len();
+        output.dimensions.1 = y;
+        output
+    }
+
+    fn break_lines<M>(&self, metrics: &M) -> Vec<Vec<Item>>
+    where
+        M: GlyphMetrics,
+    {
+        let mut lines = Vec::new();
+        let mut line: Vec<Item> = Vec::new();
+        let mut line_width = 0.0;
+        let mut word: Vec<Item> = Vec::new();
+        let mut word_width = 0.0;
+        let mut last: Option<char> = None;
+
+        let limit = self.width.unwrap_or(::std::f32::INFINITY);
+
+        for (i, &(ref text, style)) in self.runs.iter().enumerate() {
+            for c in text.chars() {
+                if c == '\n' {
+                    line.extend(word.drain(..));
+                    lines.push(::std::mem::replace(&mut line, Vec::new()));
+                    line_width = 0.0;
+                    word_width = 0.0;
+                    last = None;
+                    continue;
+                }
+
+                // The kerning adjusts the advance of previous character, so this one
+                // is moved closer to (or farther from) it.
+                if let Some(prev) = last {
+                    let kerning = metrics.kerning(prev, c, style.size);
+                    if let Some(v) = word.last_mut() {
+                        v.advance += kerning;
+                        word_width += kerning;
+                    } else if let Some(v) = line.last_mut() {
+                        v.advance += kerning;
+                        line_width += kerning;
+                    }
+                }
+
+                let advance = metrics.advance(c, style.size);
+                last = Some(c);
+
+                let item = Item {
+                    character: c,
+                    style: i,
+                    advance: advance,
+                };
+
+                if c.is_whitespace() {
+                    // Commits the pending word, and wraps it into next line if it
+                    // does not fit.
+                    if !line.is_empty() && line_width + word_width > limit {
+                        Self::trim_end(&mut line);
+                        lines.push(::std::mem::replace(&mut line, Vec::new()));
+                        line_width = 0.0;
+                    }
+
+                    line.extend(word.drain(..));
+                    line.push(item);
+                    line_width += word_width + advance;
+                    word_width = 0.0;
+                    continue;
+                }
+
+                // Breaks the word at characters if its wider than constraint.
+                if word_width + advance > limit && !word.is_empty() {
+                    if !line.is_empty() {
+                        Self::trim_end(&mut line);
+                        lines.push(::std::mem::replace(&mut line, Vec::new()));
+                    }
+
+                    lines.push(::std::mem::replace(&mut word, Vec::new()));
+                    line_width = 0.0;
+                    word_width = 0.0;
+                }
+
+                word.push(item);
+                word_width += advance;
+            }
+        }
+
+        if !line.is_empty() && line_width + word_width > limit {
+            Self::trim_end(&mut line);
+            lines.push(::std::mem::replace(&mut line, Vec::new()));
+        }
+
+        line.extend(word.drain(..));
+        if !line.is_empty() || lines.is_empty() || last.is_none() {
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    fn trim_end(line: &mut Vec<Item>) {
+        while line.last().map(|v| v.character.is_whitespace()) == Some(true) {
+            line.pop();
+        }
+    }
+
+    fn line_dimensions<M>(&self, metrics: &M, line: &[Item]) -> (f32, f32)
+    where
+        M: GlyphMetrics,
+    {
+        let mut width = 0.0;
+        let mut height: f32 = 0.0;
+
+        for v in line {
+            width += v.advance;
+            height = height.max(metrics.line_height(self.runs[v.style].1.size));
+        }
+
+        if line.is_empty() {
+            let size = self.runs.last().map(|v| v.1.size).unwrap_or(0.0);
+            height = metrics.line_height(size);
+        }
+
+        (width, height)
+    }
+
+    fn visual_order(runs: &[(String, TextStyle)], line: &[Item]) -> Vec<Item> {
+        let mut items = Vec::with_capacity(line.len());
+        let mut rtl = Vec::new();
+
+        for v in line {
+            if runs[v.style].1.direction == TextDirection::RightToLeft {
+                rtl.push(*v);
+            } else {
+                items.extend(rtl.drain(..).rev());
+                items.push(*v);
+            }
+        }
+
+        items.extend(rtl.drain(..).rev());
+        items
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Monospace;
+
+    impl GlyphMetrics for Monospace {
+        fn advance(&self, _: char, size: f32) -> f32 {
+            size * 0.5
+        }
+
+        fn line_height(&self, size: f32) -> f32 {
+            size
+        }
+    }
+
+    fn chars(output: &TextLayoutOutput) -> String {
+        output.glyphs.iter().map(|v| v.character).collect()
+    }
+
+    #[test]
+    fn wrap() {
+        let mut layout = TextLayout::new(100.0);
+        layout.push("hello world foo", TextStyle::default());
+
+        let output = layout.draw(&Monospace);
+        assert_eq!(output.lines, 2);
+        assert_eq!(output.dimensions, (88.0, 32.0));
+        assert_eq!(output.glyphs[10].character, 'f');
+        assert_eq!(output.glyphs[10].position, (0.0, 16.0));
+    }
+
+    #[test]
+    fn newline() {
+        let mut layout = TextLayout::new(None);
+        layout.push("a\nbc", TextStyle::default());
+
+        let output = layout.draw(&Monospace);
+        assert_eq!(output.lines, 2);
+        assert_eq!(output.dimensions, (16.0, 32.0));
+    }
+
+    #[test]
+    fn align() {
+        let mut layout = TextLayout::new(100.0);
+        layout.set_align(TextAlign::Right);
+        layout.push("ab", TextStyle::default());
+
+        let output = layout.draw(&Monospace);
+        assert_eq!(output.glyphs[0].position, (84.0, 0.0));
+
+        layout.set_align(TextAlign::Center);
+        let output = layout.draw(&Monospace);
+        assert_eq!(output.glyphs[0].position, (42.0, 0.0));
+    }
+
+    #[test]
+    fn spans() {
+        let mut layout = TextLayout::new(None);
+        layout.push("a", TextStyle::default());
+
+        let mut style = TextStyle::default();
+        style.size = 32.0;
+        style.color = Color::red();
+        layout.push("b", style);
+
+        let output = layout.draw(&Monospace);
+        assert_eq!(output.dimensions, (24.0, 32.0));
+        assert_eq!(output.glyphs[1].position, (8.0, 0.0));
+        assert_eq!(output.glyphs[1].color, Color::red());
+    }
+
+    #[test]
+    fn bidi() {
+        let mut layout = TextLayout::new(None);
+        layout.push("ab", TextStyle::default());
+
+        let mut style = TextStyle::default();
+        style.direction = TextDirection::RightToLeft;
+        layout.push("cde", style);
+
+        assert_eq!(chars(&layout.draw(&Monospace)), "abedc");
+    }
+
+    struct Kerning;
+
+    impl GlyphMetrics for Kerning {
+        fn advance(&self, _: char, size: f32) -> f32 {
+            size * 0.5
+        }
+
+        fn line_height(&self, size: f32) -> f32 {
+            size
+        }
+
+        fn kerning(&self, lhs: char, rhs: char, _: f32) -> f32 {
+            if lhs == 'A' && rhs == 'V' {
+                -2.0
+            } else {
+                0.0
+            }
+        }
+    }
+
+    #[test]
+    fn kerning() {
+        let mut layout = TextLayout::new(None);
+        layout.push("AVA", TextStyle::default());
+
+        let output = layout.draw(&Kerning);
+        assert_eq!(output.glyphs[1].position, (6.0, 0.0));
+        assert_eq!(output.glyphs[2].position, (14.0, 0.0));
+        assert_eq!(output.dimensions, (22.0, 16.0));
+    }
+
+    #[test]
+    fn long_word() {
+        let mut layout = TextLayout::new(24.0);
+        layout.push("abcdefg", TextStyle::default());
+
+        let output = layout.draw(&Monospace);
+        assert_eq!(output.lines, 3);
+        assert_eq!(chars(&output), "abcdefg");
+    }
+}
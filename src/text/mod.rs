@@ -0,0 +1,46 @@
+//! Text layout and rendering.
+//!
+//! # Layout
+//!
+//! `TextLayout` arranges styled runs of text into positioned glyphs, which could be
+//! consumed by any glyph renderer later. It handles line wrapping to a width
+//! constraint, horizontal alignment, inline color/size spans and basic bidirectional
+//! text.
+//!
+//! ```rust,ignore
+//! let mut layout = TextLayout::new(256.0);
+//! layout.set_align(TextAlign::Center);
+//! layout.push("Hello, ", TextStyle::default());
+//! layout.push("World!", TextStyle { color: Color::red(), ..TextStyle::default() });
+//!
+//! // Gets the bounding size of text.
+//! let (width, height) = layout.measure(&font);
+//!
+//! // Gets the positioned glyphs.
+//! for glyph in layout.draw(&font).glyphs { ... }
+//! ```
+//!
+//! The glyph metrics are provided by implementations of `GlyphMetrics`.
+//!
+//! # Font
+//!
+//! `Font` loads a TrueType font, and draws text as textured quads through
+//! `SpriteBatch`. Glyphs are rasterized into a atlas texture the first time they are
+//! used with a specific size, the atlas grows if it runs out of room, and gets
+//! repacked with glyphs in use once it reaches `MAX_ATLAS_SIZE`.
+//!
+//! ```rust,ignore
+//! let mut font = Font::new(&ctx, bytes)?;
+//! font.set_matrix(Camera::ortho_2d(width, height).projection_matrix());
+//!
+//! font.draw_text(surface, 0, "Score: 100", (8.0, height - 8.0), 24.0, Color::white())?;
+//! ```
+
+pub mod errors;
+pub mod layout;
+pub mod atlas;
+pub mod font;
+
+pub use self::font::Font;
+pub use self::layout::{GlyphMetrics, GlyphQuad, TextAlign, TextDirection, TextLayout,
+                       TextLayoutOutput, TextStyle};
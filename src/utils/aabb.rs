@@ -0,0 +1,78 @@
+use math;
+use math::{EuclideanSpace, InnerSpace};
+
+/// An axis aligned bounding box, with the minimum corner at `min`, and maximum corner
+/// at `max`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb3 {
+    pub min: math::Point3<f32>,
+    pub max: math::Point3<f32>,
+}
+
+impl Aabb3 {
+    #[inline]
+    pub fn new(min: math::Point3<f32>, max: math::Point3<f32>) -> Self {
+        Aabb3 { min: min, max: max }
+    }
+
+    /// Creates the smallest box which contains all the `points`. Returns `None` if
+    /// there is no point at all.
+    pub fn from_points<I, P>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<math::Point3<f32>>,
+    {
+        let mut iter = points.into_iter();
+        let first = iter.next()?.into();
+        let mut aabb = Aabb3::new(first, first);
+
+        for v in iter {
+            aabb = aabb.grow(v.into());
+        }
+
+        Some(aabb)
+    }
+
+    /// Returns a box which is extended to contain `p`.
+    #[inline]
+    pub fn grow(&self, p: math::Point3<f32>) -> Self {
+        Aabb3 {
+            min: math::Point3::new(
+                self.min.x.min(p.x),
+                self.min.y.min(p.y),
+                self.min.z.min(p.z),
+            ),
+            max: math::Point3::new(
+                self.max.x.max(p.x),
+                self.max.y.max(p.y),
+                self.max.z.max(p.z),
+            ),
+        }
+    }
+
+    #[inline]
+    pub fn center(&self) -> math::Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    #[inline]
+    pub fn dim(&self) -> math::Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// The radius of the bounding sphere which shares the center with this box.
+    #[inline]
+    pub fn radius(&self) -> f32 {
+        self.dim().magnitude() * 0.5
+    }
+
+    #[inline]
+    pub fn contains<P>(&self, p: P) -> bool
+    where
+        P: Into<math::Point3<f32>>,
+    {
+        let p = p.into();
+        p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
+            && p.z >= self.min.z && p.z <= self.max.z
+    }
+}
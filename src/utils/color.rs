@@ -1,6 +1,9 @@
+use std::error;
+use std::fmt;
+
 /// A RGBA `Color`. Each color component is a floating point value
 /// with a range from 0 to 1.
-#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Color(pub f32, pub f32, pub f32, pub f32);
 
 impl Into<u32> for Color {
@@ -93,6 +96,132 @@ impl Color {
     pub fn rgb(&self) -> [f32; 3] {
         [self.0, self.1, self.2]
     }
+
+    /// Parses `Color` from hex codes in form of `#rrggbb` or `#rrggbbaa`, the leading
+    /// `#` is optional. The alpha is 1.0 if it's not specified.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseColorError> {
+        let digits = if hex.starts_with('#') { &hex[1..] } else { hex };
+
+        if (digits.len() != 6 && digits.len() != 8)
+            || !digits.chars().all(|v| v.is_digit(16))
+        {
+            return Err(ParseColorError {
+                hex: hex.to_owned(),
+            });
+        }
+
+        let component = |i: usize| {
+            let v = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+            f32::from(v) / 255.0
+        };
+
+        let alpha = if digits.len() == 8 { component(3) } else { 1.0 };
+        Ok(Color(component(0), component(1), component(2), alpha))
+    }
+
+    /// Returns the hex codes in form of `#rrggbbaa`.
+    pub fn to_hex(&self) -> String {
+        let v = self.clip();
+        let component = |v: f32| (v * 255.0).round() as u8;
+
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            component(v.0),
+            component(v.1),
+            component(v.2),
+            component(v.3)
+        )
+    }
+
+    /// Creates opaque `Color` from hue in degrees, saturation and value in range of
+    /// [0.0, 1.0].
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let h = ((h % 360.0) + 360.0) % 360.0 / 60.0;
+        let s = clamp(s, 0.0, 1.0);
+        let v = clamp(v, 0.0, 1.0);
+
+        let c = v * s;
+        let x = c * (1.0 - ((h % 2.0) - 1.0).abs());
+        let m = v - c;
+
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        Color(r + m, g + m, b + m, 1.0)
+    }
+
+    /// Returns the hue in degrees of range [0.0, 360.0), saturation and value of RGB
+    /// components. The hue of grays is zero.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let v = self.clip();
+        let max = v.0.max(v.1).max(v.2);
+        let min = v.0.min(v.1).min(v.2);
+        let delta = max - min;
+
+        let h = if delta <= 0.0 {
+            0.0
+        } else if max == v.0 {
+            60.0 * (((v.1 - v.2) / delta) % 6.0)
+        } else if max == v.1 {
+            60.0 * ((v.2 - v.0) / delta + 2.0)
+        } else {
+            60.0 * ((v.0 - v.1) / delta + 4.0)
+        };
+
+        let h = if h < 0.0 { h + 360.0 } else { h };
+        let s = if max <= 0.0 { 0.0 } else { delta / max };
+        (h, s, max)
+    }
+
+    /// Converts the RGB components from sRGB into linear space, which should be used
+    /// for lighting and blending. The alpha is kept unchanged.
+    pub fn to_linear(&self) -> Color {
+        Color(
+            srgb_to_linear(self.0),
+            srgb_to_linear(self.1),
+            srgb_to_linear(self.2),
+            self.3,
+        )
+    }
+
+    /// Creates `Color` in sRGB space from the linear RGB components. The alpha is kept
+    /// unchanged.
+    pub fn from_linear(linear: Color) -> Self {
+        Color(
+            linear_to_srgb(linear.0),
+            linear_to_srgb(linear.1),
+            linear_to_srgb(linear.2),
+            linear.3,
+        )
+    }
+}
+
+/// An error which can be returned when parsing hex codes of `Color`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError {
+    hex: String,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid hex color '{}', expects #rrggbb or #rrggbbaa.",
+            self.hex
+        )
+    }
+}
+
+impl error::Error for ParseColorError {
+    fn description(&self) -> &str {
+        "invalid hex color"
+    }
 }
 
 impl Color {
@@ -137,6 +266,22 @@ impl Color {
     }
 }
 
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 fn clamp(v: f32, min: f32, max: f32) -> f32 {
     let mut v = v;
 
@@ -150,3 +295,82 @@ fn clamp(v: f32, min: f32, max: f32) -> f32 {
 
     v
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn approx(lhs: Color, rhs: Color) -> bool {
+        let (lhs, rhs): ([f32; 4], [f32; 4]) = (lhs.into(), rhs.into());
+        lhs.iter().zip(rhs.iter()).all(|(a, b)| (a - b).abs() < 1e-3)
+    }
+
+    #[test]
+    fn hex() {
+        let table = [
+            ("#ffffff", Color::white(), "#ffffffff"),
+            ("000000", Color::black(), "#000000ff"),
+            ("#FF0000", Color::red(), "#ff0000ff"),
+            ("#00ff0080", Color(0.0, 1.0, 0.0, 128.0 / 255.0), "#00ff0080"),
+            ("00000000", Color::transparent(), "#00000000"),
+        ];
+
+        for &(hex, color, formatted) in &table {
+            let v = Color::from_hex(hex).unwrap();
+            assert!(approx(v, color), "{} => {:?}", hex, v);
+            assert_eq!(v.to_hex(), formatted);
+            assert_eq!(Color::from_hex(formatted).unwrap(), v);
+        }
+
+        for &hex in &["", "#", "#fff", "#fffffff", "#fffffffff", "#gg0000", "##ffffff", "#ff 000"] {
+            assert!(Color::from_hex(hex).is_err(), "{}", hex);
+        }
+
+        assert!(Color::from_hex("#ff€00").is_err());
+    }
+
+    #[test]
+    fn hsv() {
+        let table = [
+            ((0.0, 0.0, 1.0), Color::white()),
+            ((0.0, 0.0, 0.0), Color::black()),
+            ((0.0, 0.0, 0.5), Color::gray()),
+            ((0.0, 1.0, 1.0), Color::red()),
+            ((120.0, 1.0, 1.0), Color::green()),
+            ((240.0, 1.0, 1.0), Color::blue()),
+            ((60.0, 1.0, 1.0), Color::yellow()),
+            ((180.0, 1.0, 1.0), Color::cyan()),
+            ((300.0, 1.0, 1.0), Color::magenta()),
+            ((30.0, 0.5, 0.8), Color(0.8, 0.6, 0.4, 1.0)),
+        ];
+
+        for &((h, s, v), color) in &table {
+            assert!(approx(Color::from_hsv(h, s, v), color), "{:?}", (h, s, v));
+
+            let (h2, s2, v2) = color.to_hsv();
+            assert!(approx(Color::from_hsv(h2, s2, v2), color), "{:?}", color);
+            assert!((h - h2).abs() < 1e-3 && (s - s2).abs() < 1e-3 && (v - v2).abs() < 1e-3);
+        }
+
+        assert!(approx(Color::from_hsv(-120.0, 1.0, 1.0), Color::blue()));
+        assert!(approx(Color::from_hsv(480.0, 1.0, 1.0), Color::green()));
+    }
+
+    #[test]
+    fn linear() {
+        let table = [
+            (0.0, 0.0),
+            (1.0, 1.0),
+            (0.5, 0.214_041),
+            (0.04045, 0.003_130_8),
+            (0.8, 0.603_827),
+        ];
+
+        for &(srgb, linear) in &table {
+            let color = Color(srgb, srgb, srgb, 0.5);
+            let v = color.to_linear();
+            assert!(approx(v, Color(linear, linear, linear, 0.5)), "{:?}", v);
+            assert!(approx(Color::from_linear(v), color));
+        }
+    }
+}
@@ -5,21 +5,56 @@ use std::borrow::Borrow;
 
 use utils;
 
-/// Where we store all the intermediate bytes.
+/// Where we store all the intermediate bytes. It also tracks the high-water mark of
+/// its length, which survives clearing.
 #[derive(Debug, Clone)]
-pub struct DataBuffer(Vec<u8>, HashMap<u64, DataBufferPtr<str>>);
+pub struct DataBuffer(Vec<u8>, HashMap<u64, DataBufferPtr<str>>, usize);
 
 impl DataBuffer {
     /// Creates a new task buffer with specified capacity.
     pub fn with_capacity(capacity: usize) -> Self {
-        DataBuffer(Vec::with_capacity(capacity), HashMap::new())
+        DataBuffer(Vec::with_capacity(capacity), HashMap::new(), 0)
     }
 
     pub fn clear(&mut self) {
+        self.2 = self.peak();
         self.0.clear();
         self.1.clear();
     }
 
+    /// Gets the number of bytes in the buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Gets the number of bytes the buffer can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Gets the maximum length the buffer has ever reached.
+    #[inline]
+    pub fn peak(&self) -> usize {
+        ::std::cmp::max(self.2, self.0.len())
+    }
+
+    /// Grows the buffer to hold at least `capacity` bytes in total without
+    /// reallocating. It does nothing if the capacity is already sufficient.
+    pub fn reserve(&mut self, capacity: usize) {
+        let len = self.0.len();
+        if capacity > self.0.capacity() {
+            self.0.reserve_exact(capacity - len);
+        }
+    }
+
     pub fn extend<T>(&mut self, value: &T) -> DataBufferPtr<T>
     where
         T: Copy,
@@ -35,6 +70,42 @@ impl DataBuffer {
         }
     }
 
+    /// Appends the value to the buffer, with its offset rounded up to `align` bytes,
+    /// e.g. 16 bytes for the `vec4` and `mat4` in std140 layout of uniform blocks. The
+    /// padding bytes are zeros.
+    ///
+    /// Notes that the offset is relative to the start of buffer, which is what matters
+    /// when the data is uploaded to GPU.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn extend_aligned<T>(&mut self, value: &T, align: usize) -> DataBufferPtr<T>
+    where
+        T: Copy,
+    {
+        self.align(align);
+        self.extend(value)
+    }
+
+    /// Clones and appends all elements in a slice to the buffer, with its offset
+    /// rounded up to `align` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn extend_from_slice_aligned<T>(
+        &mut self,
+        slice: &[T],
+        align: usize,
+    ) -> DataBufferPtr<[T]>
+    where
+        T: Copy,
+    {
+        self.align(align);
+        self.extend_from_slice(slice)
+    }
+
     /// Clones and appends all elements in a slice to the buffer.
     pub fn extend_from_slice<T>(&mut self, slice: &[T]) -> DataBufferPtr<[T]>
     where
@@ -107,6 +178,15 @@ impl DataBuffer {
     {
         &self.0[slice.position as usize..(slice.position + slice.size) as usize]
     }
+
+    /// Pads the buffer with zeros until its length is a multiple of `align`.
+    fn align(&mut self, align: usize) {
+        assert!(align.is_power_of_two(), "Alignment must be a power of two.");
+
+        let len = self.0.len();
+        let aligned = (len + align - 1) & !(align - 1);
+        self.0.resize(aligned, 0);
+    }
 }
 
 /// A view into our `DataBuffer`, indicates where the object `T` stored.
@@ -120,6 +200,23 @@ where
     _phantom: PhantomData<T>,
 }
 
+impl<T> DataBufferPtr<T>
+where
+    T: ?Sized,
+{
+    /// Gets the offset in bytes from the start of buffer.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.position as usize
+    }
+
+    /// Gets the size in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.size as usize
+    }
+}
+
 impl<T> Clone for DataBufferPtr<T>
 where
     T: ?Sized,
@@ -174,4 +271,59 @@ mod test {
         let slice_text = buffer.extend_from_str(text);
         assert_eq!(text, buffer.as_str(slice_text));
     }
+
+    #[test]
+    fn aligned() {
+        let mut buffer = DataBuffer::with_capacity(128);
+
+        let byte = buffer.extend(&7u8);
+        assert_eq!(byte.offset(), 0);
+
+        let mut matrix = [[0.0f32; 4]; 4];
+        for i in 0..4 {
+            matrix[i][i] = i as f32 + 1.0;
+        }
+
+        let slice_matrix = buffer.extend_aligned(&matrix, 16);
+        assert_eq!(slice_matrix.offset() % 16, 0);
+        assert_eq!(slice_matrix.offset(), 16);
+        assert_eq!(slice_matrix.len(), 64);
+        assert_eq!(*buffer.as_ref(slice_matrix), matrix);
+        assert_eq!(*buffer.as_ref(byte), 7);
+
+        // The padding bytes are zeros.
+        let padding = buffer.extend(&1u8);
+        let arr = buffer.extend_from_slice_aligned(&[1.0f32, 2.0, 3.0, 4.0], 16);
+        assert_eq!(padding.offset(), 80);
+        assert_eq!(arr.offset(), 96);
+        assert_eq!(buffer.as_slice(arr), &[1.0, 2.0, 3.0, 4.0]);
+        assert!(buffer.0[81..96].iter().all(|v| *v == 0));
+
+        // Aligned offsets are kept.
+        let v = buffer.extend_aligned(&[0.0f32; 4], 16);
+        assert_eq!(v.offset(), 112);
+    }
+
+    #[test]
+    fn peak() {
+        let mut buffer = DataBuffer::with_capacity(0);
+        buffer.extend_from_slice(&[0u8; 100]);
+        assert_eq!(buffer.len(), 100);
+        assert_eq!(buffer.peak(), 100);
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.peak(), 100);
+
+        buffer.extend_from_slice(&[0u8; 20]);
+        assert_eq!(buffer.peak(), 100);
+
+        buffer.reserve(256);
+        assert!(buffer.capacity() >= 256);
+        assert_eq!(buffer.len(), 20);
+
+        let capacity = buffer.capacity();
+        buffer.reserve(16);
+        assert_eq!(buffer.capacity(), capacity);
+    }
 }
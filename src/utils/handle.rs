@@ -10,7 +10,8 @@ pub type HandleIndex = u32;
 /// is recycled when an `Handle` is freed to save address. However, this
 /// means that you could end up with two different `Handle` with identical
 /// indices. We solve this by introducing `version`.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+         Deserialize)]
 pub struct Handle {
     index: HandleIndex,
     version: HandleIndex,
@@ -12,6 +12,8 @@ pub mod data_buf;
 mod finally;
 mod color;
 mod rect;
+mod aabb;
+mod ray;
 
 pub use self::handle::{Handle, HandleIndex};
 pub use self::handle_pool::{HandleIter, HandlePool};
@@ -22,4 +24,6 @@ pub use self::hash_value::HashValue;
 pub use self::variant::{VariantChar, VariantStr};
 pub use self::data_buf::{DataBuffer, DataBufferPtr};
 pub use self::rect::*;
+pub use self::aabb::*;
+pub use self::ray::*;
 pub use self::color::*;
@@ -0,0 +1,124 @@
+use math;
+use math::{EuclideanSpace, InnerSpace};
+
+use super::Aabb3;
+
+/// A half-line which starts from `origin` and extends infinitely along `direction`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: math::Point3<f32>,
+    pub direction: math::Vector3<f32>,
+}
+
+impl Ray {
+    /// Creates a new ray, the `direction` will be normalized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `direction` is zero, which has no direction to normalize.
+    #[inline]
+    pub fn new<P, D>(origin: P, direction: D) -> Self
+    where
+        P: Into<math::Point3<f32>>,
+        D: Into<math::Vector3<f32>>,
+    {
+        let direction = direction.into();
+        assert!(
+            direction.magnitude2() > 0.0,
+            "the direction of ray must be non-zero."
+        );
+
+        Ray {
+            origin: origin.into(),
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Gets the point at distance `t` along the ray.
+    #[inline]
+    pub fn at(&self, t: f32) -> math::Point3<f32> {
+        self.origin + self.direction * t
+    }
+
+    /// Transforms the ray with matrix `m`. The direction is NOT normalized after
+    /// transforming, so the distances along the transformed ray stay the same as
+    /// the original one.
+    pub fn transform(&self, m: math::Matrix4<f32>) -> Self {
+        let origin = m * self.origin.to_homogeneous();
+        let direction = m * self.direction.extend(0.0);
+
+        Ray {
+            origin: math::Point3::from_homogeneous(origin),
+            direction: direction.truncate(),
+        }
+    }
+
+    /// Returns the distance to the nearest intersection point with `aabb`. If the
+    /// origin is inside the box, zero is returned.
+    pub fn intersect_aabb(&self, aabb: &Aabb3) -> Option<f32> {
+        let mut tmin = 0.0f32;
+        let mut tmax = ::std::f32::INFINITY;
+
+        let origin = self.origin.to_vec();
+        for i in 0..3 {
+            if self.direction[i] == 0.0 {
+                // The ray is parallel to the slab.
+                if origin[i] < aabb.min[i] || origin[i] > aabb.max[i] {
+                    return None;
+                }
+            } else {
+                let inv = 1.0 / self.direction[i];
+                let t1 = (aabb.min[i] - origin[i]) * inv;
+                let t2 = (aabb.max[i] - origin[i]) * inv;
+
+                tmin = tmin.max(t1.min(t2));
+                tmax = tmax.min(t1.max(t2));
+
+                if tmin > tmax {
+                    return None;
+                }
+            }
+        }
+
+        Some(tmin)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aabb() {
+        let aabb = Aabb3::new([-1.0, -1.0, -1.0].into(), [1.0, 1.0, 1.0].into());
+
+        let ray = Ray::new([0.0, 0.0, -5.0], [0.0, 0.0, 2.0]);
+        assert_eq!(ray.intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(ray.at(4.0), [0.0, 0.0, -1.0].into());
+
+        let ray = Ray::new([0.0, 0.0, -5.0], [0.0, 0.0, -1.0]);
+        assert_eq!(ray.intersect_aabb(&aabb), None);
+
+        let ray = Ray::new([0.0, 2.0, -5.0], [0.0, 0.0, 1.0]);
+        assert_eq!(ray.intersect_aabb(&aabb), None);
+
+        let ray = Ray::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(ray.intersect_aabb(&aabb), Some(0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_direction() {
+        Ray::new([0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn transform() {
+        let m = math::Matrix4::from_translation([0.0, 0.0, 5.0].into())
+            * math::Matrix4::from_scale(2.0);
+        let ray = Ray::new([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]).transform(m);
+
+        assert_eq!(ray.origin, [0.0, 0.0, 5.0].into());
+        assert_eq!(ray.direction, [0.0, 0.0, 2.0].into());
+    }
+}
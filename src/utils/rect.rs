@@ -1,7 +1,7 @@
 use math;
 
 /// A rectangle, with top-left corner at `min`, and bottom-right corner at `max`.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Rect {
     pub min: math::Point2<i32>,
     pub max: math::Point2<i32>,
@@ -51,4 +51,194 @@ impl Rect {
         let p = p.into();
         p.x >= self.min.x && p.x < self.max.x && p.y >= self.min.y && p.y < self.max.y
     }
+
+    /// Returns true if the rect has no area.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width() <= 0 || self.height() <= 0
+    }
+
+    /// Returns the area of rect, which is zero if the rect is empty.
+    #[inline]
+    pub fn area(&self) -> i32 {
+        if self.is_empty() {
+            0
+        } else {
+            self.size()
+        }
+    }
+
+    /// Returns the center point, which is rounded towards the top-left corner.
+    #[inline]
+    pub fn center(&self) -> math::Point2<i32> {
+        math::Point2::new(
+            self.min.x + self.width() / 2,
+            self.min.y + self.height() / 2,
+        )
+    }
+
+    /// Returns true if the point is inside the rect. The bottom-right edges are
+    /// exclusive.
+    #[inline]
+    pub fn contains_point<P>(&self, p: P) -> bool
+    where
+        P: Into<math::Point2<i32>>,
+    {
+        self.contains(p)
+    }
+
+    /// Returns true if the `rhs` is completely inside the rect.
+    #[inline]
+    pub fn contains_rect(&self, rhs: Self) -> bool {
+        rhs.min.x >= self.min.x && rhs.min.y >= self.min.y && rhs.max.x <= self.max.x
+            && rhs.max.y <= self.max.y
+    }
+
+    /// Returns true if the two rects overlap with non-zero area.
+    #[inline]
+    pub fn intersects(&self, rhs: Self) -> bool {
+        !self.overlap(rhs).is_empty()
+    }
+
+    /// Returns the overlapped area of two rects, or `None` if they are disjoint.
+    #[inline]
+    pub fn intersection(&self, rhs: Self) -> Option<Self> {
+        let v = self.overlap(rhs);
+        if v.is_empty() {
+            None
+        } else {
+            Some(v)
+        }
+    }
+
+    /// Returns the smallest rect that contains both rects. An empty rect is treated
+    /// as the identity.
+    pub fn union(&self, rhs: Self) -> Self {
+        use std::cmp;
+
+        if rhs.is_empty() {
+            return *self;
+        }
+
+        if self.is_empty() {
+            return rhs;
+        }
+
+        Rect {
+            min: math::Point2::new(
+                cmp::min(self.min.x, rhs.min.x),
+                cmp::min(self.min.y, rhs.min.y),
+            ),
+            max: math::Point2::new(
+                cmp::max(self.max.x, rhs.max.x),
+                cmp::max(self.max.y, rhs.max.y),
+            ),
+        }
+    }
+
+    /// Clamps the corners of rect into `bounds`, the result is empty if they are
+    /// disjoint.
+    pub fn clamp_to(&self, bounds: Self) -> Self {
+        let clamp = |v: i32, min: i32, max: i32| v.max(min).min(max);
+
+        Rect {
+            min: math::Point2::new(
+                clamp(self.min.x, bounds.min.x, bounds.max.x),
+                clamp(self.min.y, bounds.min.y, bounds.max.y),
+            ),
+            max: math::Point2::new(
+                clamp(self.max.x, bounds.min.x, bounds.max.x),
+                clamp(self.max.y, bounds.min.y, bounds.max.y),
+            ),
+        }
+    }
+
+    /// Shrinks every edge by `margin`. The rect collapses to its center if the
+    /// margin is larger than half of its size.
+    pub fn inset(&self, margin: i32) -> Self {
+        let center = self.center();
+        let shrink = |min: i32, max: i32, center: i32| {
+            if max - min >= margin * 2 {
+                (min + margin, max - margin)
+            } else {
+                (center, center)
+            }
+        };
+
+        let (x0, x1) = shrink(self.min.x, self.max.x, center.x);
+        let (y0, y1) = shrink(self.min.y, self.max.y, center.y);
+        Rect::new(math::Point2::new(x0, y0), math::Point2::new(x1, y1))
+    }
+
+    /// Grows every edge by `margin`.
+    pub fn expand(&self, margin: i32) -> Self {
+        Rect::new(
+            math::Point2::new(self.min.x - margin, self.min.y - margin),
+            math::Point2::new(self.max.x + margin, self.max.y + margin),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rect(x0: i32, y0: i32, x1: i32, y1: i32) -> Rect {
+        Rect::new(math::Point2::new(x0, y0), math::Point2::new(x1, y1))
+    }
+
+    #[test]
+    fn intersection() {
+        let a = rect(0, 0, 10, 10);
+        let b = rect(5, 5, 15, 15);
+
+        assert!(a.intersects(b));
+        assert_eq!(a.intersection(b), Some(rect(5, 5, 10, 10)));
+
+        // The edges are exclusive, so touching rects are disjoint.
+        let c = rect(10, 0, 20, 10);
+        assert!(!a.intersects(c));
+        assert_eq!(a.intersection(c), None);
+        assert_eq!(a.intersection(rect(20, 20, 30, 30)), None);
+    }
+
+    #[test]
+    fn union() {
+        let a = rect(0, 0, 10, 10);
+        let b = rect(5, -5, 15, 5);
+        assert_eq!(a.union(b), rect(0, -5, 15, 10));
+
+        let empty = rect(100, 100, 100, 120);
+        assert!(empty.is_empty());
+        assert_eq!(a.union(empty), a);
+        assert_eq!(empty.union(a), a);
+    }
+
+    #[test]
+    fn containment() {
+        let a = rect(0, 0, 10, 10);
+        assert!(a.contains_point(math::Point2::new(0, 0)));
+        assert!(a.contains_point(math::Point2::new(9, 9)));
+        assert!(!a.contains_point(math::Point2::new(10, 5)));
+
+        assert!(a.contains_rect(a));
+        assert!(a.contains_rect(rect(2, 2, 8, 8)));
+        assert!(!a.contains_rect(rect(2, 2, 11, 8)));
+
+        assert_eq!(rect(-5, 5, 20, 8).clamp_to(a), rect(0, 5, 10, 8));
+        assert!(rect(20, 20, 30, 30).clamp_to(a).is_empty());
+    }
+
+    #[test]
+    fn metrics() {
+        let a = rect(0, 0, 10, 6);
+        assert_eq!(a.area(), 60);
+        assert_eq!(rect(10, 0, 0, 6).area(), 0);
+        assert_eq!(a.center(), math::Point2::new(5, 3));
+
+        assert_eq!(a.inset(2), rect(2, 2, 8, 4));
+        assert_eq!(a.inset(4), rect(4, 3, 6, 3));
+        assert_eq!(a.expand(2), rect(-2, -2, 12, 8));
+        assert_eq!(a.inset(-2), a.expand(2));
+    }
 }
@@ -0,0 +1,109 @@
+extern crate crayon;
+
+use std::sync::{Arc, Mutex};
+
+use crayon::prelude::*;
+
+struct WindowEvents {
+    events: Arc<Mutex<Vec<event::WindowEvent>>>,
+}
+
+impl Application for WindowEvents {
+    fn on_window_event(&mut self, ctx: &Context, v: event::WindowEvent) -> errors::Result<()> {
+        self.events.lock().unwrap().push(v);
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[test]
+fn window_events() {
+    let mut settings = Settings::default();
+    settings.window.width = 320;
+    settings.window.height = 240;
+
+    let mut engine = Engine::new_headless(settings).unwrap();
+    for &v in &[
+        event::ApplicationEvent::LostFocus,
+        event::ApplicationEvent::Resized(0, 0),
+        event::ApplicationEvent::Resized(0, 0),
+        event::ApplicationEvent::Resized(320, 240),
+        event::ApplicationEvent::GainFocus,
+        event::ApplicationEvent::Resized(640, 480),
+    ] {
+        engine.events_loop.push(event::Event::Application(v));
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let application = WindowEvents {
+        events: events.clone(),
+    };
+
+    engine.run(application).unwrap();
+
+    use crayon::application::event::WindowEvent::*;
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            WindowFocused(false),
+            WindowMinimized,
+            WindowResized(320, 240),
+            WindowFocused(true),
+            WindowResized(640, 480),
+        ]
+    );
+}
+
+struct SecondaryWindowEvents {
+    closed: Arc<Mutex<Vec<(graphics::WindowHandle, Option<graphics::WindowHandle>)>>>,
+}
+
+impl Application for SecondaryWindowEvents {
+    fn on_receive_event(&mut self, ctx: &Context, v: event::ApplicationEvent) -> errors::Result<()> {
+        match v {
+            event::ApplicationEvent::WindowClosed(handle) => {
+                let input = ctx.shared::<InputSystem>().window();
+                self.closed.lock().unwrap().push((handle, input));
+                ctx.shutdown();
+            }
+            event::ApplicationEvent::Closed => panic!("the primary window is closed."),
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn secondary_window_events() {
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    assert!(
+        engine
+            .create_window(graphics::WindowBuilder::new())
+            .is_err()
+    );
+
+    let window = graphics::WindowHandle::default();
+    let mut setup = graphics::SurfaceSetup::default();
+    setup.set_window(window);
+    assert!(engine.graphics.shared().create_surface(setup).is_err());
+
+    // Closing a secondary window does not stop the application.
+    for &v in &[
+        event::Event::Application(event::ApplicationEvent::LostFocus),
+        event::Event::InputDevice(event::InputDeviceEvent::MouseMoved {
+            position: (10.0, 10.0),
+        }),
+        event::Event::Application(event::ApplicationEvent::Closed),
+    ] {
+        engine.events_loop.push_from(window, v);
+    }
+
+    let closed = Arc::new(Mutex::new(Vec::new()));
+    let application = SecondaryWindowEvents {
+        closed: closed.clone(),
+    };
+
+    engine.run(application).unwrap();
+    assert_eq!(*closed.lock().unwrap(), [(window, Some(window))]);
+}
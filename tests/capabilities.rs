@@ -0,0 +1,26 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+
+// Requires a real OpenGL context, run it with `cargo test -- --ignored` on a
+// machine with display.
+#[test]
+#[ignore]
+pub fn capabilities() {
+    let engine = Engine::new().unwrap();
+    let video = engine.graphics.shared();
+    let caps = video.capabilities();
+
+    assert!(!caps.version_string.is_empty());
+    assert!(caps.max_texture_size > 0);
+    assert!(caps.max_combined_texture_image_units > 0);
+    assert!(caps.max_vertex_attributes > 0);
+    assert!(caps.max_vertex_uniform_vectors > 0);
+    assert!(caps.max_fragment_uniform_vectors > 0);
+    assert_eq!(caps.support_multisample(), caps.max_samples > 0);
+    assert_eq!(caps.support_texture_array(), caps.max_array_texture_layers > 0);
+    assert_eq!(
+        caps.support_compute_shader(),
+        caps.max_shader_storage_buffer_bindings > 0
+    );
+}
@@ -0,0 +1,11 @@
+//! The setups shared by integration tests.
+
+use crayon::prelude::*;
+
+/// Creates a headless `GraphicsSystem` of 320x240, with the `ResourceSystem` it
+/// loads from. The `ResourceSystem` should be kept alive during the test.
+pub fn headless() -> (ResourceSystem, GraphicsSystem) {
+    let resource = ResourceSystem::new().unwrap();
+    let video = GraphicsSystem::headless(resource.shared(), (320, 240));
+    (resource, video)
+}
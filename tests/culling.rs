@@ -0,0 +1,92 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+use crayon::graphics::{Attribute, BufferHint, MeshSetup, SurfaceSetup, VertexFormat,
+                       VertexLayout};
+use crayon::scene::factory;
+
+#[test]
+pub fn behind_camera() {
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+    let mesh = factory::mesh::cube(&video).unwrap();
+    let shader = factory::shader::phong(&video).unwrap();
+    let material = scene.create_material(shader).unwrap();
+
+    let cube = scene.create_node(MeshRenderer {
+        mesh: mesh,
+        index: MeshIndex::All,
+        materials: vec![material],
+    });
+
+    // The camera at origin looks along the positive z-axis, so the cube is behind it.
+    let camera = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+    unsafe {
+        let mut transforms = scene.arena_mut::<Transform>();
+        transforms.get_unchecked_mut(cube).set_position([0.0, 0.0, -10.0]);
+    }
+
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    assert_eq!(engine.graphics.advance().unwrap().drawcall, 0);
+
+    scene.set_frustum_culling(false);
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    assert_eq!(engine.graphics.advance().unwrap().drawcall, 1);
+}
+
+fn as_bytes(verts: &[[f32; 3]]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for v in verts.iter().flat_map(|v| v.iter()) {
+        let bits = v.to_bits();
+        bytes.extend_from_slice(&[
+            bits as u8,
+            (bits >> 8) as u8,
+            (bits >> 16) as u8,
+            (bits >> 24) as u8,
+        ]);
+    }
+
+    bytes
+}
+
+#[test]
+pub fn update_vertex_buffer() {
+    let engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+
+    let mut setup = MeshSetup::default();
+    setup.hint = BufferHint::Dynamic;
+    setup.layout = VertexLayout::build()
+        .with(Attribute::Position, VertexFormat::Float, 3, false)
+        .finish();
+    setup.num_verts = 3;
+    setup.num_idxes = 3;
+
+    let verts = as_bytes(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    let empty: Option<&[u8]> = None;
+    let mesh = video
+        .create_mesh(Location::unique(""), setup, &verts[..], empty)
+        .unwrap();
+
+    let aabb = video.mesh_aabb(mesh).unwrap();
+    assert_eq!(aabb.max, math::Point3::new(1.0, 1.0, 0.0));
+
+    // Replaces the whole vertex buffer.
+    let verts = as_bytes(&[[0.0, 0.0, -10.0], [1.0, 0.0, -10.0], [0.0, 1.0, -10.0]]);
+    video.update_vertex_buffer(mesh, 0, &verts).unwrap();
+    let aabb = video.mesh_aabb(mesh).unwrap();
+    assert_eq!(aabb.min, math::Point3::new(0.0, 0.0, -10.0));
+    assert_eq!(aabb.max, math::Point3::new(1.0, 1.0, -10.0));
+
+    // Updates the last vertex only.
+    let verts = as_bytes(&[[2.0, 2.0, 2.0]]);
+    video.update_vertex_buffer(mesh, 24, &verts).unwrap();
+    let aabb = video.mesh_aabb(mesh).unwrap();
+    assert_eq!(aabb.min, math::Point3::new(0.0, 0.0, -10.0));
+    assert_eq!(aabb.max, math::Point3::new(2.0, 2.0, 2.0));
+}
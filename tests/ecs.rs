@@ -301,3 +301,137 @@ fn system() {
 
     assert!(!ecs::system::validate(&world, &[&inc, &dec]));
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct MovedEvent {
+    entity: Entity,
+    x: u32,
+}
+
+struct MoveSystem<'b> {
+    events: &'b Events,
+}
+
+impl<'a, 'b> System<'a> for MoveSystem<'b> {
+    type ViewWith = FetchMut<'a, Position>;
+
+    fn run(&self, view: View, mut arena: Self::ViewWith) {
+        unsafe {
+            for v in view {
+                let position = arena.get_unchecked_mut(v);
+                position.x += 1;
+
+                self.events.send(MovedEvent {
+                    entity: v,
+                    x: position.x,
+                });
+            }
+        }
+    }
+}
+
+struct ListenSystem<'b> {
+    events: &'b Events,
+    received: Vec<MovedEvent>,
+}
+
+impl<'a, 'b> System<'a> for ListenSystem<'b> {
+    type ViewWith = ();
+
+    fn run_mut(&mut self, _: View, _: Self::ViewWith) {
+        self.received.extend(self.events.read::<MovedEvent>());
+    }
+}
+
+#[test]
+fn events() {
+    let mut world = World::new();
+    world.register::<Position>();
+
+    let e1 = world.build().with_default::<Position>().finish();
+    let e2 = world.build().with_default::<Position>().finish();
+
+    let events = Events::new();
+    let producer = MoveSystem { events: &events };
+    let mut consumers = [
+        ListenSystem {
+            events: &events,
+            received: Vec::new(),
+        },
+        ListenSystem {
+            events: &events,
+            received: Vec::new(),
+        },
+    ];
+
+    for _ in 0..3 {
+        events.advance();
+        producer.run_at(&world);
+
+        for v in &mut consumers {
+            v.run_mut_at(&world);
+        }
+    }
+
+    // The events sent during the last frame have not been readable yet.
+    events.advance();
+    for v in &mut consumers {
+        v.run_mut_at(&world);
+    }
+
+    for v in &consumers {
+        let mut received = v.received.clone();
+        received.sort_by_key(|v| (v.x, v.entity));
+
+        let mut expected = Vec::new();
+        for x in 1..4 {
+            expected.push(MovedEvent { entity: e1, x: x });
+            expected.push(MovedEvent { entity: e2, x: x });
+        }
+
+        expected.sort_by_key(|v| (v.x, v.entity));
+        assert_eq!(received, expected);
+    }
+}
+
+#[test]
+fn change_detection() {
+    let mut world = World::new();
+    world.register::<Position>();
+
+    let e1 = world.build().with_default::<Position>().finish();
+    let e2 = world.build().with_default::<Position>().finish();
+    let e3 = world.create();
+
+    assert_eq!(world.added::<Position>().collect::<Vec<_>>(), [e1, e2]);
+    assert_eq!(world.changed::<Position>().collect::<Vec<_>>(), [e1, e2]);
+
+    world.advance();
+    let tick = world.tick();
+    assert_eq!(world.changed::<Position>().count(), 0);
+
+    // Reading does not mark the components as modified.
+    {
+        let arena = world.arena_mut::<Position>();
+        assert_eq!(arena.get(e1).unwrap().x, 0);
+    }
+
+    assert_eq!(world.changed::<Position>().count(), 0);
+
+    world.arena_mut::<Position>().get_mut(e2).unwrap().x = 1;
+    world.add(e3, Position { x: 2, y: 2 });
+    assert_eq!(world.added::<Position>().collect::<Vec<_>>(), [e3]);
+    assert_eq!(world.changed::<Position>().collect::<Vec<_>>(), [e2, e3]);
+
+    // The component untouched for a frame is not reported.
+    world.advance();
+    assert_eq!(world.changed::<Position>().count(), 0);
+    assert_eq!(world.changed_since::<Position>(tick).collect::<Vec<_>>(), [e2, e3]);
+
+    world.remove::<Position>(e2);
+    world.free(e1);
+    assert_eq!(world.removed::<Position>().collect::<Vec<_>>(), [e2, e1]);
+
+    world.advance();
+    assert_eq!(world.removed::<Position>().count(), 0);
+}
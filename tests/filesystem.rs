@@ -1,5 +1,11 @@
 extern crate crayon;
 
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::thread;
+
 use crayon::resource::filesystem::*;
 
 #[test]
@@ -55,3 +61,116 @@ fn zip() {
     fs.load_into("foo/mock.prefab".as_ref(), &mut buf).unwrap();
     assert_eq!(String::from_utf8(buf).unwrap(), "mock");
 }
+
+#[test]
+fn modified() {
+    let fs = DirectoryFS::new("tests/assets").unwrap();
+    assert!(fs.modified("mock.txt".as_ref()).is_ok());
+    assert!(fs.modified("_invalid_path_".as_ref()).is_err());
+
+    let fs = ZipFS::new("tests/assets/mock.zip").unwrap();
+    match *fs.modified("foo/mock.prefab".as_ref()).unwrap_err().kind() {
+        crayon::resource::errors::ErrorKind::HotReloadUnsupported => {}
+        ref v => panic!("unexpected error {:?}", v),
+    }
+}
+
+#[test]
+fn memory() {
+    let fs = MemoryFS::new()
+        .insert("mock.txt", "Hello, World!")
+        .insert("/foo/./mock.prefab", vec![1, 2, 3]);
+
+    assert!(fs.exists("mock.txt".as_ref()));
+    assert!(fs.exists("foo/mock.prefab".as_ref()));
+    assert!(!fs.exists("foo".as_ref()));
+
+    let mut buf = Vec::new();
+    fs.load_into("mock.txt".as_ref(), &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "Hello, World!");
+
+    let mut buf = Vec::new();
+    fs.load_into("foo/mock.prefab".as_ref(), &mut buf).unwrap();
+    assert_eq!(buf, vec![1, 2, 3]);
+
+    let err = fs.load_into("_invalid_path_".as_ref(), &mut buf).unwrap_err();
+    match *err.kind() {
+        crayon::resource::errors::ErrorKind::NotFound => {}
+        ref v => panic!("unexpected error {:?}", v),
+    }
+
+    let mut driver = FilesystemDriver::new();
+    driver.mount("res", fs).unwrap();
+    assert!(driver.exists("/res/foo/mock.prefab"));
+
+    let mut buf = Vec::new();
+    driver.load_into("/res/mock.txt", &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "Hello, World!");
+}
+
+/// Serves `n` requests with a minimal HTTP server, and returns its address.
+fn serve(n: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().take(n) {
+            let mut stream = stream.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..len]);
+            }
+
+            let request = String::from_utf8(request).unwrap();
+            let response: &[u8] = if request.starts_with("GET /assets/mock.txt ") {
+                if request.contains("Range: bytes=7-11") {
+                    b"HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\n\r\nWorld"
+                } else {
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      5\r\nHello\r\n8\r\n, World!\r\n0\r\n\r\n"
+                }
+            } else {
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+            };
+
+            stream.write_all(response).unwrap();
+        }
+    });
+
+    format!("http://{}/assets", addr)
+}
+
+#[test]
+fn http() {
+    let cache = ::std::env::temp_dir().join("crayon_http_cache");
+    let _ = fs::remove_dir_all(&cache);
+
+    let fs = HttpFS::new(serve(3)).unwrap().with_cache(&cache).unwrap();
+    let mut driver = FilesystemDriver::new();
+    driver.mount("res", fs.clone()).unwrap();
+
+    let mut buf = Vec::new();
+    assert!(driver.load_into("/res/missing.txt", &mut buf).is_err());
+
+    let mut range = Vec::new();
+    fs.load_range(Path::new("mock.txt"), 7..12, &mut range).unwrap();
+    assert_eq!(range, b"World");
+
+    driver.load_into("/res/mock.txt", &mut buf).unwrap();
+    assert_eq!(buf, b"Hello, World!");
+    assert!(cache.join("mock.txt").is_file());
+
+    // The second request is served by the cache without reaching the server.
+    buf.clear();
+    driver.load_into("/res/mock.txt", &mut buf).unwrap();
+    assert_eq!(buf, b"Hello, World!");
+
+    let mut range = Vec::new();
+    fs.load_range(Path::new("mock.txt"), 0..5, &mut range).unwrap();
+    assert_eq!(range, b"Hello");
+    assert!(driver.exists("/res/mock.txt"));
+
+    fs::remove_dir_all(&cache).unwrap();
+}
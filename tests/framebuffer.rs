@@ -0,0 +1,167 @@
+extern crate crayon;
+
+mod common;
+
+use crayon::prelude::*;
+use crayon::graphics::*;
+use crayon::scene::factory;
+
+const VS: &'static str = "
+#version 100
+attribute vec3 Position;
+
+void main() {
+    gl_Position = vec4(Position, 1.0);
+}";
+
+const FS: &'static str = "
+#version 100
+#extension GL_EXT_draw_buffers : enable
+precision lowp float;
+
+void main() {
+    gl_FragData[0] = vec4(1.0, 0.0, 0.0, 1.0);
+    gl_FragData[1] = vec4(0.0, 1.0, 0.0, 1.0);
+}";
+
+fn render_target(video: &GraphicsSystemShared) -> TextureHandle {
+    let mut setup = RenderTextureSetup::default();
+    setup.format = RenderTextureFormat::RGBA8;
+    setup.dimensions = (64, 64);
+    video.create_render_texture(setup).unwrap()
+}
+
+fn shader(video: &GraphicsSystemShared, outputs: usize) -> ShaderHandle {
+    let mut setup = ShaderSetup::default();
+    setup.layout = AttributeLayoutBuilder::new()
+        .with(Attribute::Position, 3)
+        .finish();
+    setup.outputs = outputs;
+    setup.vs = VS.to_owned();
+    setup.fs = FS.to_owned();
+    video.create_shader(Location::unique(""), setup).unwrap()
+}
+
+// Requires a real OpenGL context, run it with `cargo test -- --ignored` on a
+// machine with display.
+#[test]
+#[ignore]
+pub fn multiple_render_targets() {
+    let mut engine = Engine::new().unwrap();
+    let video = engine.graphics.shared();
+    if video.capabilities().max_color_attachments < 2 {
+        return;
+    }
+
+    let mut setup = FrameBufferSetup::default();
+    setup.set_attachment(render_target(&video), 0).unwrap();
+    setup.set_attachment(render_target(&video), 1).unwrap();
+    let fbo = video.create_framebuffer(setup).unwrap();
+
+    let mut setup = SurfaceSetup::default();
+    setup.set_framebuffer(fbo);
+    let surface = video.create_surface(setup).unwrap();
+    let mesh = factory::mesh::cube(&video).unwrap();
+
+    // The framebuffer is complete, otherwise it fails to bind the surface.
+    let mrt = shader(&video, 2);
+    let mut dc = DrawCall::new(mrt, mesh);
+    video.submit(surface, 0u64, dc.build(MeshIndex::All).unwrap()).unwrap();
+    engine.graphics.swap_frames();
+    assert_eq!(engine.graphics.advance().unwrap().drawcall, 1);
+
+    // Mismatches between the outputs of shader and the color attachments.
+    let mismatched = shader(&video, 3);
+    let mut dc = DrawCall::new(mismatched, mesh);
+    video.submit(surface, 0u64, dc.build(MeshIndex::All).unwrap()).unwrap();
+    engine.graphics.swap_frames();
+    assert!(engine.graphics.advance().is_err());
+}
+
+#[test]
+fn shader_outputs() {
+    let (_resource, video) = common::headless();
+    let video = video.shared();
+
+    let mut setup = ShaderSetup::default();
+    assert_eq!(setup.outputs, 1);
+    setup.vs = VS.to_owned();
+    setup.fs = FS.to_owned();
+
+    setup.outputs = 0;
+    assert!(video.create_shader(Location::unique(""), setup.clone()).is_err());
+
+    setup.outputs = video.capabilities().max_color_attachments as usize + 1;
+    assert!(video.create_shader(Location::unique(""), setup.clone()).is_err());
+
+    setup.outputs = 2;
+    assert!(video.create_shader(Location::unique(""), setup).is_ok());
+}
+
+#[test]
+fn depth_render_textures() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+
+    let mut setup = RenderTextureSetup::default();
+    setup.format = RenderTextureFormat::Depth24;
+    setup.dimensions = (256, 256);
+    let texture = shared.create_render_texture(setup).unwrap();
+
+    // A depth-only framebuffer, which is used by shadow maps.
+    let mut setup = FrameBufferSetup::default();
+    setup.set_attachment(texture, None).unwrap();
+    let fbo = shared.create_framebuffer(setup).unwrap();
+
+    let mut setup = SurfaceSetup::default();
+    setup.set_framebuffer(fbo);
+    setup.set_clear(None, 1.0, None);
+    let surface = shared.create_surface(setup).unwrap();
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_textures, 1);
+    assert_eq!(info.alive_frame_buffers, 1);
+
+    let mut capabilities = Capabilities::headless();
+    capabilities.version = Version::ES(2, 0);
+    assert!(!capabilities.support_render_texture_format(RenderTextureFormat::Depth24));
+    assert!(capabilities.support_render_texture_format(RenderTextureFormat::RGBA8));
+
+    capabilities.extensions.gl_oes_depth_texture = true;
+    assert!(capabilities.support_render_texture_format(RenderTextureFormat::Depth24));
+    assert!(!capabilities.support_render_texture_format(RenderTextureFormat::Depth24Stencil8));
+    assert!(!capabilities.support_render_texture_format(RenderTextureFormat::Depth32));
+    assert!(!capabilities.support_render_texture_format(RenderTextureFormat::Depth32F));
+
+    // Floating-point depth is available since ES 3.0.
+    capabilities.version = Version::ES(3, 0);
+    assert!(capabilities.support_render_texture_format(RenderTextureFormat::Depth32F));
+
+    shared.delete_surface(surface);
+    shared.delete_framebuffer(fbo);
+    shared.delete_texture(texture);
+}
+
+#[test]
+fn multisampled_surfaces() {
+    let (_resource, video) = common::headless();
+    let shared = video.shared();
+
+    let mut setup = SurfaceSetup::default();
+    setup.set_samples(4);
+    let surface = shared.create_surface(setup).unwrap();
+
+    // The contents of render target are not loaded into the multisampled framebuffer.
+    setup.set_clear_color(None);
+    assert!(shared.create_surface(setup).is_err());
+    setup.set_clear(Color::black(), None, None);
+    assert!(shared.create_surface(setup).is_err());
+
+    // Preserving the contents is fine without multisampling.
+    setup.set_samples(1);
+    let preserved = shared.create_surface(setup).unwrap();
+
+    shared.delete_surface(surface);
+    shared.delete_surface(preserved);
+}
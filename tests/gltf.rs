@@ -0,0 +1,63 @@
+extern crate crayon;
+
+use crayon::graphics::*;
+use crayon::scene::GltfParser;
+
+fn read_f32s(data: &MeshData, vert: usize, attribute: Attribute, n: usize) -> Vec<f32> {
+    let stride = data.layout.stride() as usize;
+    let offset = data.layout.offset(attribute).unwrap() as usize;
+    let from = vert * stride + offset;
+
+    (0..n)
+        .map(|i| {
+            let v = &data.verts[from + i * 4..];
+            let bits = (v[0] as u32) | (v[1] as u32) << 8 | (v[2] as u32) << 16
+                | (v[3] as u32) << 24;
+            f32::from_bits(bits)
+        })
+        .collect()
+}
+
+fn read_color(data: &MeshData, vert: usize) -> Vec<u8> {
+    let stride = data.layout.stride() as usize;
+    let offset = data.layout.offset(Attribute::Color0).unwrap() as usize;
+    let from = vert * stride + offset;
+    data.verts[from..from + 4].to_vec()
+}
+
+#[test]
+fn glb() {
+    // A triangle with interleaved positions and normals, and a quad with positions only.
+    let data = GltfParser::parse(include_bytes!("assets/mesh.glb")).unwrap();
+
+    assert_eq!(data.primitive, Primitive::Triangles);
+    assert_eq!(data.index_format, IndexFormat::U16);
+    assert_eq!(data.num_verts, 7);
+    assert_eq!(data.num_idxes, 9);
+    assert_eq!(data.sub_mesh_offsets, vec![0, 3]);
+    assert_eq!(data.verts.len(), 7 * data.layout.stride() as usize);
+
+    let idxes: Vec<u16> = data.idxes
+        .chunks(2)
+        .map(|v| (v[0] as u16) | (v[1] as u16) << 8)
+        .collect();
+    assert_eq!(idxes, vec![0, 2, 1, 3, 5, 4, 5, 3, 6]);
+
+    // The z axis is flipped into left-handed coordinates.
+    assert_eq!(read_f32s(&data, 1, Attribute::Position, 3), vec![1.0, 0.0, -1.0]);
+    assert_eq!(read_f32s(&data, 1, Attribute::Normal, 3), vec![0.0, 0.0, -1.0]);
+    assert_eq!(read_f32s(&data, 1, Attribute::Texcoord0, 2), vec![1.0, 0.0]);
+    assert_eq!(read_color(&data, 1), vec![0, 255, 0, 255]);
+
+    // Missing attributes are filled with defaults.
+    assert_eq!(read_f32s(&data, 3, Attribute::Position, 3), vec![-1.0, -1.0, 0.0]);
+    assert_eq!(read_f32s(&data, 3, Attribute::Normal, 3), vec![0.0, 0.0, 0.0]);
+    assert_eq!(read_color(&data, 3), vec![255, 255, 255, 255]);
+}
+
+#[test]
+fn invalid() {
+    assert!(GltfParser::parse(b"glTF").is_err());
+    assert!(GltfParser::parse(b"{}").is_err());
+    assert!(GltfParser::parse(b"{\"meshes\": [{\"primitives\": [{\"attributes\": {}}]}]}").is_err());
+}
@@ -0,0 +1,985 @@
+extern crate crayon;
+
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crayon::prelude::*;
+use crayon::graphics::{Attribute, AttributeLayout, BufferHint, Capabilities, Command,
+                       ComputeShaderSetup, Dispatch, DrawCall, IndexFormat, MeshData, MeshIndex,
+                       MeshParser, MeshSetup, OwnedHandle, Primitive, RenderTextureFormat,
+                       RenderTextureSetup, ShaderSetup, StorageBufferSetup, SurfaceSetup,
+                       TextureData, TextureFormat, TextureParser, TextureSetup, UniformBufferSetup,
+                       Version, VertexFormat, VertexLayout};
+use crayon::graphics::errors::{Error, ErrorKind};
+
+#[test]
+fn headless() {
+    let (_resource, mut video) = common::headless();
+    assert!(video.is_headless());
+
+    let shared = video.shared();
+    assert!(shared.is_headless());
+    assert_eq!(shared.dimensions(), (320, 240));
+
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = TextureSetup::default();
+    setup.dimensions = (2, 2);
+    let bytes = [0u8; 16];
+    let texture = shared
+        .create_texture(Location::unique(""), setup, &bytes[..])
+        .unwrap();
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_surfaces, 1);
+    assert_eq!(info.alive_textures, 1);
+    assert_eq!(info.drawcall, 0);
+
+    let mut setup = RenderTextureSetup::default();
+    setup.dimensions = (2, 2);
+    let rt = shared.create_render_texture(setup).unwrap();
+
+    let rect = Rect::new(math::Point2::new(0, 0), math::Point2::new(2, 2));
+    match shared.read_render_texture(rt, rect) {
+        Err(err) => match *err.kind() {
+            ErrorKind::Headless => {}
+            _ => panic!("unexpected error: {}", err),
+        },
+        Ok(_) => panic!("reading pixels back should fail in headless mode."),
+    }
+
+    match shared.capture_frame("screenshot.png") {
+        Err(err) => match *err.kind() {
+            ErrorKind::Headless => {}
+            _ => panic!("unexpected error: {}", err),
+        },
+        Ok(_) => panic!("capturing frame should fail in headless mode."),
+    }
+
+    shared.delete_surface(surface);
+    shared.delete_texture(texture);
+    shared.delete_texture(rt);
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_surfaces, 0);
+    assert_eq!(info.alive_textures, 0);
+}
+
+#[test]
+fn read_render_texture_validation() {
+    let (_resource, video) = common::headless();
+    let shared = video.shared();
+
+    let mut setup = TextureSetup::default();
+    setup.dimensions = (2, 2);
+    let bytes = [0u8; 16];
+    let texture = shared
+        .create_texture(Location::unique(""), setup, &bytes[..])
+        .unwrap();
+
+    let mut setup = RenderTextureSetup::default();
+    setup.dimensions = (4, 4);
+    let color = shared.create_render_texture(setup).unwrap();
+    setup.format = RenderTextureFormat::Depth24;
+    let depth = shared.create_render_texture(setup).unwrap();
+
+    let read = |texture, min: (i32, i32), max: (i32, i32)| {
+        let rect = Rect::new(math::Point2::new(min.0, min.1), math::Point2::new(max.0, max.1));
+        match shared.read_render_texture(texture, rect) {
+            Err(err) => match *err.kind() {
+                ErrorKind::Headless => None,
+                _ => Some(format!("{}", err)),
+            },
+            Ok(_) => panic!("reading pixels back should fail in headless mode."),
+        }
+    };
+
+    // Valid requests pass the validation, and fail only because of headless mode.
+    assert_eq!(read(color, (0, 0), (4, 4)), None);
+    assert_eq!(read(color, (2, 2), (8, 8)), None);
+
+    let err = read(texture, (0, 0), (2, 2)).unwrap();
+    assert!(err.contains("Only render textures"), "{}", err);
+    let err = read(depth, (0, 0), (4, 4)).unwrap();
+    assert!(err.contains("Depth24"), "{}", err);
+    let err = read(color, (1, 1), (1, 3)).unwrap();
+    assert!(err.contains("empty or out of the bounds"), "{}", err);
+    let err = read(color, (4, 0), (6, 4)).unwrap();
+    assert!(err.contains("empty or out of the bounds"), "{}", err);
+
+    shared.delete_texture(texture);
+    shared.delete_texture(color);
+    shared.delete_texture(depth);
+}
+
+#[test]
+fn static_mesh_updates() {
+    let (_resource, video) = common::headless();
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 3;
+    assert_eq!(setup.hint, BufferHint::Static);
+
+    let empty: Option<&[u8]> = None;
+    let mesh = shared
+        .create_mesh(Location::unique(""), setup.clone(), empty, empty)
+        .unwrap();
+
+    let bytes = [0u8; 6];
+    let is_static = |result: Result<(), Error>| match result {
+        Err(err) => match *err.kind() {
+            ErrorKind::InvalidUpdateStaticResource => true,
+            _ => false,
+        },
+        Ok(_) => false,
+    };
+
+    assert!(is_static(shared.update_index_buffer(mesh, 0, &bytes)));
+    let cmd = Command::update_index_buffer(mesh, 0, &bytes);
+    assert!(is_static(shared.submit(surface, 0, cmd)));
+
+    // The index buffer follows the hint of vertex buffer if not specified.
+    setup.hint = BufferHint::Dynamic;
+    let dynamic = shared
+        .create_mesh(Location::unique(""), setup.clone(), empty, empty)
+        .unwrap();
+    assert!(shared.update_index_buffer(dynamic, 0, &bytes).is_ok());
+
+    setup.index_hint = Some(BufferHint::Static);
+    let mixed = shared
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+    assert!(shared.update_vertex_buffer(mixed, 0, &[]).is_ok());
+    assert!(is_static(shared.update_index_buffer(mixed, 0, &bytes)));
+}
+
+#[test]
+fn batch_deletion() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 3;
+
+    let meshes: Vec<_> = (0..10_000)
+        .map(|_| {
+            shared
+                .create_mesh(Location::unique(""), setup.clone(), empty, empty)
+                .unwrap()
+        })
+        .collect();
+
+    // Shared meshes are kept alive until the last reference is released.
+    let location = Location::shared(0, "/batch");
+    let retained = shared.create_mesh(location, setup.clone(), empty, empty).unwrap();
+    assert_eq!(shared.create_mesh(location, setup, empty, empty).unwrap(), retained);
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_meshes, 10_001);
+
+    shared.delete_meshes(&meshes);
+    shared.delete_meshes(&[retained]);
+    assert_eq!(shared.lookup_mesh_from(location), Some(retained));
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_meshes, 1);
+
+    // Deleting the freed handles again is a no-op, while the shared mesh releases its
+    // last reference and gets freed.
+    shared.delete_meshes(&meshes);
+    shared.delete_meshes(&[retained]);
+    assert_eq!(shared.lookup_mesh_from(location), None);
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_meshes, 0);
+}
+
+#[test]
+fn batch_deletion_matches_loop() {
+    use crayon::graphics::MeshHandle;
+
+    let run = |batch: bool| {
+        let (_resource, mut video) = common::headless();
+        let shared = video.shared();
+
+        let empty: Option<&[u8]> = None;
+        let mut setup = MeshSetup::default();
+        setup.num_idxes = 3;
+
+        // Unique meshes, and shared meshes which are referenced twice.
+        let locations: Vec<_> = (0..10).map(|i| format!("/shared/{}", i)).collect();
+        let mut meshes: Vec<_> = (0..100)
+            .map(|_| {
+                shared
+                    .create_mesh(Location::unique(""), setup.clone(), empty, empty)
+                    .unwrap()
+            })
+            .collect();
+
+        for v in &locations {
+            let location = Location::shared(0, v);
+            let mesh = shared.create_mesh(location, setup.clone(), empty, empty).unwrap();
+            shared.create_mesh(location, setup.clone(), empty, empty).unwrap();
+            meshes.push(mesh);
+        }
+
+        let delete = |handles: &[MeshHandle]| {
+            if batch {
+                shared.delete_meshes(handles);
+            } else {
+                for &v in handles {
+                    shared.delete_mesh(v);
+                }
+            }
+        };
+
+        // Records the number of alive meshes and the lookups of shared meshes.
+        let mut reports = Vec::new();
+        for _ in 0..2 {
+            delete(&meshes);
+            video.swap_frames();
+            let alive = video.advance().unwrap().alive_meshes;
+            let lookups: Vec<_> = locations
+                .iter()
+                .map(|v| shared.lookup_mesh_from(Location::shared(0, v)))
+                .collect();
+            reports.push((alive, lookups));
+        }
+
+        reports
+    };
+
+    let reports = run(false);
+    assert_eq!(reports[0].0, 10);
+    assert!(reports[0].1.iter().all(|v| v.is_some()));
+    assert_eq!(reports[1].0, 0);
+    assert!(reports[1].1.iter().all(|v| v.is_none()));
+
+    assert_eq!(run(true), reports);
+}
+
+#[test]
+fn reversed_z_surfaces() {
+    let (_resource, video) = common::headless();
+    let shared = video.shared();
+
+    // Clip control is not available in the headless context of OpenGL 3.3.
+    let mut capabilities = shared.capabilities().clone();
+    assert!(!capabilities.support_clip_control());
+
+    let mut setup = SurfaceSetup::default();
+    setup.set_reversed_z(true);
+    assert!(shared.create_surface(setup).is_err());
+
+    setup.set_reversed_z(false);
+    assert!(shared.create_surface(setup).is_ok());
+
+    capabilities.extensions.gl_arb_clip_control = true;
+    assert!(capabilities.support_clip_control());
+
+    capabilities.extensions.gl_arb_clip_control = false;
+    capabilities.version = Version::GL(4, 5);
+    assert!(capabilities.support_clip_control());
+}
+
+#[test]
+fn texture_arrays() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    assert!(shared.capabilities().support_texture_array());
+
+    let mut setup = TextureSetup::default();
+    setup.dimensions = (2, 2);
+
+    // Layers are stored contiguously, every one of them takes 2x2 RGBA8 pixels.
+    let bytes = [0u8; 48];
+    assert!(
+        shared
+            .create_texture_array(Location::unique(""), setup, 3, &bytes[..40])
+            .is_err()
+    );
+    assert!(
+        shared
+            .create_texture_array(Location::unique(""), setup, 0, None)
+            .is_err()
+    );
+
+    let texture = shared
+        .create_texture_array(Location::unique(""), setup, 3, &bytes[..])
+        .unwrap();
+
+    let rect = Rect::new(math::Point2::new(0, 0), math::Point2::new(1, 1));
+    shared.update_texture_layer(texture, 2, rect, &[255; 4]).unwrap();
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_textures, 1);
+
+    shared.delete_texture(texture);
+}
+
+#[test]
+fn uniform_buffers() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    // A `mat4` and a `vec3` in std140 layout, the `vec3` is padded to 16 bytes.
+    let empty: Option<&[u8]> = None;
+    let mut setup = UniformBufferSetup::default();
+    setup.size = 80;
+    let ubo = shared.create_uniform_buffer(setup, empty).unwrap();
+    assert!(shared.update_uniform_buffer(ubo, 64, &[0; 16]).is_ok());
+    assert!(shared.update_uniform_buffer(ubo, 72, &[0; 16]).is_err());
+    assert!(shared.create_uniform_buffer(setup, &[0u8; 16][..]).is_err());
+
+    setup.size = 64;
+    setup.hint = BufferHint::Static;
+    let small = shared.create_uniform_buffer(setup, &[0u8; 64][..]).unwrap();
+    match shared.update_uniform_buffer(small, 0, &[0; 16]) {
+        Err(err) => match *err.kind() {
+            ErrorKind::InvalidUpdateStaticResource => {}
+            _ => panic!("unexpected error: {}", err),
+        },
+        Ok(_) => panic!("updates of static uniform buffer should fail."),
+    }
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    setup.uniform_blocks.insert("Camera".to_owned(), 80);
+    let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 3;
+    let mesh = shared
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    // Every uniform block of shader should be bound.
+    let mut dc = DrawCall::new(shader, mesh);
+    assert!(shared.submit(surface, 0, dc.build(MeshIndex::All).unwrap()).is_err());
+
+    dc.set_uniform_buffer("Camera", small);
+    assert!(shared.submit(surface, 0, dc.build(MeshIndex::All).unwrap()).is_err());
+
+    dc.set_uniform_buffer("Camera", ubo);
+    assert!(shared.submit(surface, 0, dc.build(MeshIndex::All).unwrap()).is_ok());
+
+    dc.set_uniform_buffer("Light", ubo);
+    assert!(shared.submit(surface, 0, dc.build(MeshIndex::All).unwrap()).is_err());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_uniform_buffers, 2);
+    assert_eq!(info.drawcall, 1);
+
+    shared.delete_uniform_buffer(ubo);
+    shared.delete_uniform_buffer(small);
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_uniform_buffers, 0);
+}
+
+#[test]
+fn shader_preprocessor() {
+    let (resource, video) = common::headless();
+    let fs = MemoryFS::new()
+        .insert("shaders/lighting.glsl", "#include \"math.glsl\"\nvec3 lighting();")
+        .insert("shaders/math.glsl", "float saturate(float v);")
+        .insert("shaders/circular.glsl", "#include \"/res/shaders/circular.glsl\"");
+    resource.mount("res", fs).unwrap();
+
+    let shared = video.shared();
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "#version 330\n#include \"/res/shaders/lighting.glsl\"\nvoid main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    setup.defines.push(("NUM_LIGHTS".to_owned(), "4".to_owned()));
+
+    let expanded = shared.preprocess_shader(&setup).unwrap();
+    assert_eq!(
+        expanded.vs,
+        "#version 330\n#define NUM_LIGHTS 4\nfloat saturate(float v);\nvec3 lighting();\n\
+         void main() {}\n"
+    );
+    assert_eq!(expanded.fs, "#define NUM_LIGHTS 4\nvoid main() {}\n");
+    assert!(expanded.defines.is_empty());
+    assert!(shared.create_shader(Location::unique(""), setup.clone()).is_ok());
+
+    setup.vs = "#include \"/res/shaders/circular.glsl\"".to_owned();
+    let err = shared.create_shader(Location::unique(""), setup).unwrap_err();
+    assert!(err.to_string().contains("Circular #include"), "{}", err);
+}
+
+#[test]
+fn compute_shaders() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    // Compute shaders require GL 4.3 or ES 3.1, which are not provided by headless.
+    assert!(!shared.capabilities().support_compute_shader());
+
+    let mut setup = ComputeShaderSetup::default();
+    setup.storage_blocks.push("Particles".to_owned());
+    setup.cs = "void main() {}".to_owned();
+    assert!(
+        shared
+            .create_compute_shader(Location::unique(""), setup)
+            .is_err()
+    );
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = StorageBufferSetup::default();
+    setup.size = 64;
+    assert!(shared.create_storage_buffer(setup, empty).is_err());
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    setup.storage_blocks.push("Particles".to_owned());
+    assert!(shared.create_shader(Location::unique(""), setup).is_err());
+
+    // Empty work groups are rejected, and so are dispatches on devices without compute
+    // shaders.
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+
+    let mut dispatch = Dispatch::new(shader);
+    assert!(dispatch.build((0, 1, 1)).is_err());
+    assert!(shared.submit(surface, 0, dispatch.build((1, 1, 1)).unwrap()).is_err());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_storage_buffers, 0);
+
+    let mut capabilities = Capabilities::headless();
+    capabilities.version = Version::GL(4, 3);
+    assert!(capabilities.support_compute_shader());
+    capabilities.version = Version::ES(3, 0);
+    assert!(!capabilities.support_compute_shader());
+    capabilities.version = Version::ES(3, 1);
+    assert!(capabilities.support_compute_shader());
+
+    shared.delete_shader(shader);
+}
+
+struct MockParser {}
+
+impl TextureParser for MockParser {
+    type Error = Error;
+
+    fn parse(bytes: &[u8]) -> Result<TextureData, Error> {
+        match bytes {
+            b"ok" => Ok(TextureData {
+                format: TextureFormat::U8U8U8U8,
+                dimensions: (2, 2),
+                levels: 1,
+                data: vec![0; 16],
+            }),
+            b"panic" => panic!("The parser panics."),
+            _ => Err("Broken texture.".into()),
+        }
+    }
+}
+
+#[test]
+fn async_loading() {
+    let (resource, video) = common::headless();
+    let fs = MemoryFS::new()
+        .insert("ok", "ok")
+        .insert("broken", "broken")
+        .insert("panic", "panic");
+    resource.mount("res", fs).unwrap();
+
+    let shared = video.shared();
+
+    let load = |path: &str| {
+        let location = Location::shared(0, path);
+        shared
+            .create_texture_from_async::<MockParser>(location, TextureSetup::default())
+            .unwrap()
+            .1
+    };
+
+    let ok = load("/res/ok");
+    let broken = load("/res/broken");
+    let panicked = load("/res/panic");
+    let missing = load("/res/missing");
+
+    let timeout = Duration::from_secs(5);
+    assert!(ok.wait_timeout(timeout).unwrap().is_ok());
+    assert!(broken.wait_timeout(timeout).unwrap().is_err());
+    assert!(panicked.wait_timeout(timeout).unwrap().is_err());
+    assert!(missing.wait_timeout(timeout).unwrap().is_err());
+
+    // The worker keeps running after a parser panics.
+    let again = load("/res/ok");
+    assert!(again.is_finished());
+    let late = load("/res/late");
+    assert!(late.wait_timeout(timeout).unwrap().is_err());
+
+    assert!(resource.shared().join_all(&[ok.clone(), again]).is_ok());
+    assert!(resource.shared().join_all(&[ok, broken]).is_err());
+}
+
+#[test]
+fn owned_handles() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+
+    let mut setup = TextureSetup::default();
+    setup.dimensions = (2, 2);
+    let location = Location::shared(0, "/owned");
+    let empty: Option<&[u8]> = None;
+    let create = || {
+        let texture = shared.create_texture(location, setup, empty).unwrap();
+        OwnedHandle::new(shared.clone(), texture)
+    };
+
+    // Creating with the same location twice holds two references.
+    let t1 = create();
+    let t2 = create();
+    assert_eq!(t1.handle(), t2.handle());
+
+    let t3 = shared
+        .lookup_texture_from(location)
+        .and_then(|v| OwnedHandle::retain(shared.clone(), v))
+        .unwrap();
+
+    let t4 = t3.clone();
+    drop(t1);
+    drop(t3);
+    assert!(shared.lookup_texture_from(location).is_some());
+
+    drop(t2);
+    assert!(shared.lookup_texture_from(location).is_some());
+
+    let handle = t4.handle();
+    drop(t4);
+    assert!(shared.lookup_texture_from(location).is_none());
+    assert!(OwnedHandle::retain(shared.clone(), handle).is_none());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_textures, 0);
+
+    // The manual deletion still works.
+    let texture = shared.create_texture(location, setup, empty).unwrap();
+    shared.delete_texture(texture);
+    assert!(shared.lookup_texture_from(location).is_none());
+}
+
+#[test]
+fn shared_sprite_shader() {
+    use crayon::graphics::SpriteBatch;
+
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = TextureSetup::default();
+    setup.dimensions = (2, 2);
+    let empty: Option<&[u8]> = None;
+    let texture = video
+        .create_texture(Location::unique(""), setup, empty)
+        .unwrap();
+
+    // Both batches use the same built-in sprite shader.
+    let lhs = SpriteBatch::new(video.clone()).unwrap();
+    let mut rhs = SpriteBatch::new(video.clone()).unwrap();
+    drop(lhs);
+
+    engine.graphics.swap_frames();
+    engine.graphics.advance().unwrap();
+
+    rhs.draw(texture, ((0.0, 0.0), (1.0, 1.0)), ((0.0, 0.0), (1.0, 1.0)), Color::white());
+    rhs.flush(surface, 0u64).unwrap();
+
+    engine.graphics.swap_frames();
+    let info = engine.graphics.advance().unwrap();
+    assert_eq!(info.drawcall, 1);
+    assert_eq!(info.errors, 0);
+}
+
+#[test]
+#[cfg(feature = "leak-detection")]
+fn leak_detection() {
+    use crayon::graphics::ResourceKind;
+
+    let (_resource, video) = common::headless();
+    let shared = video.shared();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 3;
+    let m1 = shared
+        .create_mesh(Location::unique(""), setup.clone(), empty, empty)
+        .unwrap();
+    let m2 = shared
+        .create_mesh(Location::unique("/leaked"), setup, empty, empty)
+        .unwrap();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    shared.delete_mesh(m1);
+
+    let leaks = shared.report_leaks();
+    let handles: Vec<_> = leaks.iter().map(|v| (v.kind, v.handle)).collect();
+    assert_eq!(
+        handles,
+        [
+            (ResourceKind::Surface, surface.into()),
+            (ResourceKind::Mesh, m2.into()),
+        ]
+    );
+    assert_eq!(leaks[0].label, "create_surface");
+    assert_eq!(leaks[1].label, "/leaked (create_mesh)");
+
+    shared.delete_mesh(m2);
+    shared.delete_surface(surface);
+    assert!(shared.report_leaks().is_empty());
+}
+
+#[test]
+#[cfg(not(feature = "leak-detection"))]
+fn leak_detection_disabled() {
+    let (_resource, video) = common::headless();
+    let shared = video.shared();
+
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+    assert!(shared.report_leaks().is_empty());
+    shared.delete_surface(surface);
+}
+
+#[test]
+fn texture_atlas() {
+    use crayon::graphics::TextureAtlas;
+
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+
+    let mut setup = TextureSetup::default();
+    setup.dimensions = (32, 32);
+    let mut atlas = TextureAtlas::new(shared.clone(), setup).unwrap();
+
+    let pixels = [0u8; 15 * 15 * 4];
+    let regions: Vec<_> = (0..4)
+        .map(|_| atlas.insert((15, 15), &pixels).unwrap())
+        .collect();
+
+    // None of the uv rects overlap.
+    for (i, lhs) in regions.iter().enumerate() {
+        let ((x0, y0), (x1, y1)) = lhs.uv;
+        assert!(x0 >= 0.0 && y0 >= 0.0 && x1 <= 1.0 && y1 <= 1.0);
+
+        for rhs in &regions[i + 1..] {
+            let ((u0, v0), (u1, v1)) = rhs.uv;
+            assert!(x1 <= u0 || u1 <= x0 || y1 <= v0 || v1 <= y0);
+        }
+    }
+
+    // The atlas is full, and the existing regions are never overwritten.
+    assert!(atlas.insert((15, 15), &pixels).is_err());
+    assert!(atlas.insert((2, 2), &pixels[0..16]).is_err());
+    assert!(atlas.insert((1, 1), &pixels[0..3]).is_err());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_textures, 1);
+
+    drop(atlas);
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.alive_textures, 0);
+}
+
+#[test]
+fn frame_capture() {
+    use crayon::graphics::{UniformVariable, UniformVariableType};
+
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    let tt = UniformVariableType::Matrix4f;
+    setup.uniform_variables.insert("u_MVPMatrix".to_owned(), tt);
+    let tt = UniformVariableType::F32;
+    setup.uniform_variables.insert("u_Alpha".to_owned(), tt);
+    let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 3;
+    let mesh = shared
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    let rx = shared.capture_next_frame();
+    let index = shared.frame_index();
+
+    let mut dc = DrawCall::new(shader, mesh);
+    dc.set_uniform_variable("u_MVPMatrix", math::Matrix4::from_scale(0.0f32));
+    dc.set_uniform_variable("u_Alpha", 0.5f32);
+    shared.submit(surface, 7, dc.build(MeshIndex::All).unwrap()).unwrap();
+
+    // Nothing is sent until the frame is dispatched.
+    assert!(rx.try_recv().is_err());
+
+    video.swap_frames();
+    video.advance().unwrap();
+
+    let capture = rx.try_recv().unwrap();
+    assert_eq!(capture.frame_index, index);
+    assert_eq!(capture.pre.len(), 3);
+    assert_eq!(capture.tasks.len(), 1);
+    assert_eq!(capture.tasks[0].surface, surface);
+    assert_eq!(capture.tasks[0].order, 7);
+
+    let zeroed: [[f32; 4]; 4] = math::Matrix4::from_scale(0.0f32).into();
+    assert_eq!(
+        capture.tasks[0].uniforms,
+        [
+            ("u_Alpha".to_owned(), UniformVariable::F32(0.5)),
+            ("u_MVPMatrix".to_owned(), UniformVariable::Matrix4f(zeroed, false)),
+        ]
+    );
+
+    let dump = format!("{}", capture);
+    assert!(dump.contains("DrawCall("));
+    assert!(dump.contains("u_MVPMatrix = Matrix4f("));
+
+    // The capture is only taken once.
+    video.swap_frames();
+    video.advance().unwrap();
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn mesh_index_range() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_idxes = 6;
+    setup.sub_mesh_offsets = vec![0, 3];
+    let mesh = shared
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    let dc = DrawCall::new(shader, mesh);
+    let submit = |index| shared.submit(surface, 0, dc.build(index).unwrap());
+
+    assert!(submit(MeshIndex::All).is_ok());
+    assert!(submit(MeshIndex::Ptr(3, 3)).is_ok());
+    assert!(submit(MeshIndex::SubMesh(1)).is_ok());
+
+    match submit(MeshIndex::Ptr(3, 4)) {
+        Err(err) => {
+            let message = format!("{}", err);
+            assert!(message.contains("Ptr(3, 4)"), "{}", message);
+            assert!(message.contains("out of bounds of 6 indices"), "{}", message);
+        }
+        Ok(_) => panic!("out-of-range MeshIndex should be rejected."),
+    }
+
+    assert!(submit(MeshIndex::SubMesh(2)).is_err());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.drawcall, 3);
+    assert_eq!(info.errors, 0);
+}
+
+static PENDING_RELEASED: AtomicBool = AtomicBool::new(false);
+
+struct PendingParser {}
+
+impl MeshParser for PendingParser {
+    type Error = Error;
+
+    fn parse(bytes: &[u8]) -> Result<MeshData, Error> {
+        // Holds the mesh in `NotReady` until the test releases it.
+        while !PENDING_RELEASED.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        match bytes {
+            b"ok" => Ok(MeshData {
+                layout: VertexLayout::default(),
+                index_format: IndexFormat::U16,
+                primitive: Primitive::Triangles,
+                num_verts: 3,
+                num_idxes: 3,
+                sub_mesh_offsets: vec![0],
+                verts: Vec::new(),
+                idxes: vec![0; 6],
+            }),
+            _ => Err("Broken mesh.".into()),
+        }
+    }
+}
+
+#[test]
+fn mesh_loading_skipped() {
+    let (resource, mut video) = common::headless();
+    let fs = MemoryFS::new().insert("ok", "ok").insert("broken", "broken");
+    resource.mount("res", fs).unwrap();
+
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+
+    let load = |path: &str| {
+        let location = Location::shared(0, path);
+        shared
+            .create_mesh_from_async::<PendingParser>(location, MeshSetup::default())
+            .unwrap()
+    };
+
+    let (ok, ok_future) = load("/res/ok");
+    let (broken, broken_future) = load("/res/broken");
+    let submit = |mesh, index| {
+        let dc = DrawCall::new(shader, mesh);
+        shared.submit(surface, 0, dc.build(index).unwrap())
+    };
+
+    // Draw calls of meshes that are still loading are skipped, even if their
+    // `MeshIndex` could not be validated yet.
+    assert!(!ok_future.is_finished());
+    assert!(submit(ok, MeshIndex::All).is_ok());
+    assert!(submit(ok, MeshIndex::Ptr(0, 100)).is_ok());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.drawcall, 0);
+    assert_eq!(info.errors, 0);
+
+    PENDING_RELEASED.store(true, Ordering::SeqCst);
+    let timeout = Duration::from_secs(5);
+    assert!(ok_future.wait_timeout(timeout).unwrap().is_ok());
+    assert!(broken_future.wait_timeout(timeout).unwrap().is_err());
+
+    // Draw calls of meshes that failed to load are skipped too, the failure is
+    // reported by the future.
+    assert!(submit(ok, MeshIndex::All).is_ok());
+    assert!(submit(ok, MeshIndex::Ptr(0, 100)).is_err());
+    assert!(submit(broken, MeshIndex::All).is_ok());
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.drawcall, 1);
+    assert_eq!(info.errors, 0);
+}
+
+#[test]
+fn instanced_attribute_collisions() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    let surface = shared.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut setup = ShaderSetup::default();
+    setup.vs = "void main() {}".to_owned();
+    setup.fs = "void main() {}".to_owned();
+    setup.layout = AttributeLayout::build()
+        .with(Attribute::Position, 3)
+        .with(Attribute::Color0, 4)
+        .with(Attribute::Texcoord1, 4)
+        .finish();
+    let shader = shared.create_shader(Location::unique(""), setup).unwrap();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.layout = VertexLayout::build()
+        .with(Attribute::Position, VertexFormat::Float, 3, false)
+        .with(Attribute::Color0, VertexFormat::Float, 4, false)
+        .finish();
+    setup.num_verts = 3;
+    setup.num_idxes = 3;
+    let mesh = shared
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    let data = [0u8; 32];
+    let mut dc = DrawCall::new(shader, mesh);
+
+    // Instance attributes must not shadow the attributes of vertex buffer.
+    {
+        let layout = AttributeLayout::build().with(Attribute::Color0, 4).finish();
+        let task = dc.build_instanced(MeshIndex::All, layout, &data, 2).unwrap();
+        match shared.submit(surface, 0, task) {
+            Err(err) => assert!(format!("{}", err).contains("Color0"), "{}", err),
+            Ok(_) => panic!("colliding instance attribute should be rejected."),
+        }
+    }
+
+    {
+        let layout = AttributeLayout::build().with(Attribute::Texcoord1, 4).finish();
+        let task = dc.build_instanced(MeshIndex::All, layout, &data, 2).unwrap();
+        assert!(shared.submit(surface, 0, task).is_ok());
+    }
+
+    video.swap_frames();
+    let info = video.advance().unwrap();
+    assert_eq!(info.errors, 0);
+
+    // Instanced draw calls are rejected on devices without instancing.
+    assert!(shared.capabilities().support_instancing());
+    let mut capabilities = Capabilities::headless();
+    capabilities.version = Version::ES(2, 0);
+    assert!(!capabilities.support_instancing());
+    capabilities.version = Version::GL(2, 1);
+    assert!(!capabilities.support_instancing());
+    capabilities.extensions.gl_arb_draw_instanced = true;
+    capabilities.extensions.gl_arb_instanced_arrays = true;
+    assert!(capabilities.support_instancing());
+}
+
+#[test]
+fn fences() {
+    let (_resource, mut video) = common::headless();
+    let shared = video.shared();
+    assert!(shared.capabilities().support_sync());
+
+    let fence = shared.insert_fence().unwrap();
+    assert!(!shared.is_fence_signaled(fence));
+    assert!(!shared.wait_fence(fence, Duration::from_millis(10)));
+
+    // The waiting thread is woken up once the frame has been dispatched.
+    let waiter = {
+        let shared = shared.clone();
+        std::thread::spawn(move || shared.wait_fence(fence, Duration::from_secs(10)))
+    };
+
+    video.swap_frames();
+    video.advance().unwrap();
+    assert!(waiter.join().unwrap());
+    assert!(shared.is_fence_signaled(fence));
+}
@@ -0,0 +1,104 @@
+extern crate crayon;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crayon::resource::*;
+use crayon::resource::filesystem::{DirectoryFS, ZipFS};
+
+struct Reloader(Mutex<Sender<String>>);
+
+impl ResourceHotReloader for Reloader {
+    fn is_alive(&self) -> bool {
+        true
+    }
+
+    fn on_reload(&self, _: &Path, bytes: &[u8]) -> errors::Result<()> {
+        let text = String::from_utf8_lossy(bytes).into_owned();
+        if text == "broken" {
+            return Err("broken".into());
+        }
+
+        self.0.lock().unwrap().send(text).unwrap();
+        Ok(())
+    }
+}
+
+fn write(path: &Path, text: &str) {
+    let mut file = fs::File::create(path).unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+/// Rewrites the file until `rx` receives something, as the modification time might
+/// not change on file systems with coarse timestamps.
+fn write_until<T>(path: &Path, text: &str, rx: &Receiver<T>) -> T {
+    let timeout = Duration::from_secs(10);
+    let ts = Instant::now();
+
+    loop {
+        write(path, text);
+        if let Ok(v) = rx.recv_timeout(Duration::from_millis(100)) {
+            return v;
+        }
+
+        assert!(ts.elapsed() < timeout, "Timeout when reloading {:?}.", path);
+    }
+}
+
+#[test]
+fn reload() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+    let name = format!("crayon_hot_reload_{}_{}", process::id(), nanos);
+    let dir: PathBuf = ::std::env::temp_dir().join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("mock.txt");
+    write(&file, "Hello");
+
+    let sys = ResourceSystem::new().unwrap();
+    sys.mount("res", DirectoryFS::new(&dir).unwrap()).unwrap();
+    sys.enable_hot_reload(true);
+
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    sys.shared().set_hot_reload_failure_callback(move |path, _| {
+        tx.lock().unwrap().send(path.to_owned()).unwrap();
+    });
+
+    let (reloaded_tx, reloaded_rx) = mpsc::channel();
+    let reloader = Reloader(Mutex::new(reloaded_tx));
+    sys.shared().watch(reloader, "/res/mock.txt").unwrap();
+
+    assert_eq!(write_until(&file, "World", &reloaded_rx), "World");
+
+    let path = write_until(&file, "broken", &rx);
+    assert_eq!(path, Path::new("/res/mock.txt"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unsupported() {
+    let sys = ResourceSystem::new().unwrap();
+    sys.mount("res", ZipFS::new("tests/assets/mock.zip").unwrap())
+        .unwrap();
+    sys.enable_hot_reload(true);
+
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    sys.shared().set_hot_reload_failure_callback(move |path, _| {
+        tx.lock().unwrap().send(path.to_owned()).unwrap();
+    });
+
+    let (reloaded_tx, _) = mpsc::channel();
+    let reloader = Reloader(Mutex::new(reloaded_tx));
+    assert!(sys.shared().watch(reloader, "/res/foo/mock.prefab").is_err());
+    assert_eq!(rx.try_recv().unwrap(), Path::new("/res/foo/mock.prefab"));
+}
@@ -0,0 +1,74 @@
+extern crate crayon;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crayon::prelude::*;
+
+struct GamepadEvents {
+    connected: Arc<Mutex<Vec<usize>>>,
+    pressed: Arc<Mutex<bool>>,
+    start: std::time::Instant,
+}
+
+impl Application for GamepadEvents {
+    fn on_receive_event(&mut self, ctx: &Context, v: event::ApplicationEvent) -> errors::Result<()> {
+        if let event::ApplicationEvent::GamepadConnected(id) = v {
+            self.connected.lock().unwrap().push(id);
+
+            // Feeds the button from another thread at runtime, like a gamepad backend.
+            let sender = ctx.event_sender();
+            std::thread::spawn(move || {
+                let v = event::InputDeviceEvent::GamepadPressed {
+                    device: 7,
+                    button: event::GamepadButton::A,
+                };
+
+                sender.send(event::Event::InputDevice(v)).unwrap();
+            });
+        }
+
+        Ok(())
+    }
+
+    fn on_post_update(&mut self, ctx: &Context, _: &FrameInfo) -> errors::Result<()> {
+        let input = ctx.shared::<InputSystem>();
+        let connected = self.connected.lock().unwrap().clone();
+        for id in connected {
+            if input.gamepad_button(id, event::GamepadButton::A) {
+                *self.pressed.lock().unwrap() = true;
+                ctx.shutdown();
+            }
+        }
+
+        if self.start.elapsed() > Duration::from_secs(5) {
+            ctx.shutdown();
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn gamepad_events() {
+    let engine = Engine::new_headless(Settings::default()).unwrap();
+
+    let sender = engine.context().event_sender();
+    std::thread::spawn(move || {
+        let v = event::InputDeviceEvent::GamepadConnected { device: 7 };
+        sender.send(event::Event::InputDevice(v)).unwrap();
+    }).join()
+        .unwrap();
+
+    let connected = Arc::new(Mutex::new(Vec::new()));
+    let pressed = Arc::new(Mutex::new(false));
+    let application = GamepadEvents {
+        connected: connected.clone(),
+        pressed: pressed.clone(),
+        start: std::time::Instant::now(),
+    };
+
+    engine.run(application).unwrap();
+    assert_eq!(connected.lock().unwrap().len(), 1);
+    assert!(*pressed.lock().unwrap());
+}
@@ -0,0 +1,462 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+use crayon::graphics::{MeshIndex, MeshSetup, SurfaceSetup};
+use crayon::scene::factory;
+use crayon::scene::scene::SceneNode;
+
+#[test]
+fn scene_serialization() {
+    let engine = Engine::new_headless(Settings::default()).unwrap();
+    let fs = MemoryFS::new().insert("box.glb", "glTF");
+    engine.resource.mount("res", fs).unwrap();
+    let video = engine.graphics.shared();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let cube = factory::mesh::cube(&video).unwrap();
+    let shader = factory::shader::phong(&video).unwrap();
+    let material = scene.create_material(shader).unwrap();
+    scene
+        .update_material_uniform(material, "u_Diffuse", [1.0, 0.5, 0.5])
+        .unwrap();
+
+    let root = scene.create_node(());
+    let camera = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+    let mesh = scene.create_node(MeshRenderer {
+        mesh: cube,
+        index: MeshIndex::SubMesh(0),
+        materials: vec![material],
+    });
+
+    {
+        let mut tree = scene.arena_mut::<Node>();
+        Node::set_parent(&mut tree, mesh, root).unwrap();
+        Node::set_parent(&mut tree, camera, root).unwrap();
+    }
+
+    unsafe {
+        let mut transforms = scene.arena_mut::<Transform>();
+        transforms.get_unchecked_mut(mesh).set_position([1.0, 2.0, 3.0]);
+    }
+
+    let mut bytes = Vec::new();
+    scene.save(&mut bytes).unwrap();
+
+    let mut loaded = Scene::new(engine.context()).unwrap();
+    let report = loaded.load(&bytes[..]).unwrap();
+    assert!(report.failures.is_empty(), "{:?}", report.failures);
+    assert_eq!(report.entities.len(), 3);
+
+    let root = report.entities.get(root).unwrap();
+    let camera = report.entities.get(camera).unwrap();
+    let mesh = report.entities.get(mesh).unwrap();
+    assert_eq!(loaded.children(root).collect::<Vec<_>>(), [camera, mesh]);
+
+    unsafe {
+        let transforms = loaded.arena::<Transform>();
+        let position = transforms.get_unchecked(mesh).position();
+        assert_eq!(position, math::Vector3::new(1.0, 2.0, 3.0));
+    }
+
+    match loaded.arena::<SceneNode>().get(mesh) {
+        Some(&SceneNode::Mesh(ref v)) => {
+            assert_eq!(v.mesh, cube);
+            assert_eq!(v.index, MeshIndex::SubMesh(0));
+            assert_eq!(v.materials.len(), 1);
+        }
+        _ => panic!("the mesh is not loaded."),
+    }
+
+    match loaded.arena::<SceneNode>().get(camera) {
+        Some(&SceneNode::Camera(_)) => {}
+        _ => panic!("the camera is not loaded."),
+    }
+
+    // The nodes are still created if the assets they refer fail to load.
+    let json = r#"{
+        "materials": [
+            { "shader": "__Core/Scene/Shader/PHONG" },
+            { "shader": "/res/missing.shader" }
+        ],
+        "nodes": [
+            { "id": { "index": 0, "version": 1 } },
+            {
+                "id": { "index": 1, "version": 1 },
+                "parent": { "index": 0, "version": 1 },
+                "mesh": { "location": "/res/box.glb", "materials": [0] }
+            },
+            {
+                "id": { "index": 2, "version": 1 },
+                "parent": { "index": 0, "version": 1 },
+                "mesh": { "location": "/res/missing.glb", "materials": [0] }
+            },
+            {
+                "id": { "index": 3, "version": 1 },
+                "parent": { "index": 7, "version": 1 },
+                "mesh": { "location": "__Core/Scene/Mesh/CUBE", "materials": [1] }
+            }
+        ]
+    }"#;
+
+    let mut partial = Scene::new(engine.context()).unwrap();
+    let report = partial.load(json.as_bytes()).unwrap();
+    assert_eq!(report.entities.len(), 4);
+    assert_eq!(report.failures.len(), 3, "{:?}", report.failures);
+
+    let failed: Vec<_> = report.failures.iter().map(|v| v.0).collect();
+    let e2 = report.entities.get(Entity::new(2, 1));
+    let e3 = report.entities.get(Entity::new(3, 1));
+    assert_eq!(failed, [None, e2, e3]);
+
+    let e1 = report.entities.get(Entity::new(1, 1)).unwrap();
+    match partial.arena::<SceneNode>().get(e1) {
+        Some(&SceneNode::Mesh(_)) => {}
+        _ => panic!("the mesh is not loaded."),
+    }
+
+    // The loaded file is written with its location.
+    let mut bytes = Vec::new();
+    partial.save(&mut bytes).unwrap();
+    assert!(String::from_utf8(bytes).unwrap().contains("/res/box.glb"));
+
+    assert!(partial.load(&b"{ \"nodes\": 0 }"[..]).is_err());
+}
+
+#[test]
+fn mesh_renderer_serialization() {
+    let engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let cube = factory::mesh::cube(&video).unwrap();
+    let shader = factory::shader::phong(&video).unwrap();
+    let material = scene.create_material(shader).unwrap();
+    scene
+        .update_material_uniform(material, "u_Diffuse", [1.0, 0.5, 0.5])
+        .unwrap();
+
+    let mut world = World::new();
+    world.register_serializable::<Transform>();
+    world.register_serializable::<MeshRenderer>();
+
+    let ent = world
+        .build()
+        .with(Transform::default())
+        .with(MeshRenderer {
+            mesh: cube,
+            index: MeshIndex::SubMesh(0),
+            materials: vec![material],
+        })
+        .finish();
+
+    // The handles could not be described without the scene they belong to.
+    let mut bytes = Vec::new();
+    assert!(world.save(&mut bytes).is_err());
+
+    let mut bytes = Vec::new();
+    scene.save_world(&world, &mut bytes).unwrap();
+
+    let mut loaded = World::new();
+    loaded.register_serializable::<Transform>();
+    loaded.register_serializable::<MeshRenderer>();
+    assert!(loaded.load(&bytes[..]).is_err());
+    assert_eq!(loaded.len(), 0);
+
+    let report = scene.load_world(&mut loaded, &bytes[..]).unwrap();
+    assert!(report.skipped.is_empty());
+
+    let ent = report.entities.get(ent).unwrap();
+    let renderer = loaded.arena::<MeshRenderer>().get(ent).cloned().unwrap();
+    assert_eq!(renderer.mesh, cube);
+    assert_eq!(renderer.index, MeshIndex::SubMesh(0));
+    assert_eq!(renderer.materials.len(), 1);
+    assert!(renderer.materials[0] != material);
+
+    // The loaded world could be saved again with the re-created material.
+    let mut again = Vec::new();
+    scene.save_world(&loaded, &mut again).unwrap();
+    let text = String::from_utf8(again).unwrap();
+    assert!(text.contains("__Core/Scene/Mesh/CUBE"), "{}", text);
+    assert!(text.contains("u_Diffuse"), "{}", text);
+
+    // Nothing is created if the mesh of renderer is missing.
+    let json = r#"{ "entities": [
+        {
+            "id": { "index": 0, "version": 1 },
+            "components": { "MeshRenderer": { "location": "/res/missing.glb" } }
+        }
+    ] }"#;
+
+    let mut partial = World::new();
+    partial.register_serializable::<MeshRenderer>();
+    assert!(scene.load_world(&mut partial, json.as_bytes()).is_err());
+    assert_eq!(partial.len(), 0);
+}
+
+#[test]
+fn sub_mesh_materials() {
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+
+    // A mesh with two groups of triangles.
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_verts = 6;
+    setup.num_idxes = 6;
+    setup.sub_mesh_offsets = vec![0, 3];
+    let mesh = video
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let phong = factory::shader::phong(&video).unwrap();
+    let color = factory::shader::color(&video).unwrap();
+    assert_ne!(phong, color);
+
+    let m0 = scene.create_material(phong).unwrap();
+    let m1 = scene.create_material(color).unwrap();
+    let camera = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+    let node = scene.create_node(MeshRenderer {
+        mesh: mesh,
+        index: MeshIndex::All,
+        materials: vec![m0, m1],
+    });
+
+    // One draw call per sub-mesh.
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    assert_eq!(engine.graphics.advance().unwrap().drawcall, 2);
+
+    // The last material is repeated.
+    let v = MeshRenderer {
+        mesh: mesh,
+        index: MeshIndex::All,
+        materials: vec![m0],
+    };
+    scene.update_node(node, v).unwrap();
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    assert_eq!(engine.graphics.advance().unwrap().drawcall, 2);
+
+    // A selected sub-mesh is drawn with the first material only.
+    let v = MeshRenderer {
+        mesh: mesh,
+        index: MeshIndex::SubMesh(1),
+        materials: vec![m1],
+    };
+    scene.update_node(node, v).unwrap();
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    assert_eq!(engine.graphics.advance().unwrap().drawcall, 1);
+
+    // More materials than sub-meshes is an error.
+    let v = MeshRenderer {
+        mesh: mesh,
+        index: MeshIndex::All,
+        materials: vec![m0, m1, m0],
+    };
+    scene.update_node(node, v).unwrap();
+    assert!(scene.render(surface, camera).is_err());
+}
+
+#[test]
+fn camera_viewport_scissor() {
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_verts = 3;
+    setup.num_idxes = 3;
+    let mesh = video
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let phong = factory::shader::phong(&video).unwrap();
+    let material = scene.create_material(phong).unwrap();
+    scene.create_node(MeshRenderer {
+        mesh: mesh,
+        index: MeshIndex::All,
+        materials: vec![material],
+    });
+
+    let mut camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    camera.set_viewport((0.5, 0.0), (0.5, 1.0));
+    let camera = scene.create_node(camera);
+
+    let rx = video.capture_next_frame();
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    engine.graphics.advance().unwrap();
+
+    // The scissor is relative to the surface, and disabled after the pass.
+    let capture = rx.try_recv().unwrap();
+    let tasks: Vec<_> = capture.tasks.iter().map(|v| &v.description).collect();
+    assert_eq!(tasks.len(), 3);
+    assert_eq!(tasks[0], "UpdateSurface(Normalized((0.5, 0.0), (0.5, 1.0)))");
+    assert!(tasks[1].starts_with("DrawCall("));
+    assert_eq!(tasks[2], "UpdateSurface(Disable)");
+}
+
+#[test]
+fn scene_change_ticks() {
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let camera = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+    let node = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+    assert_eq!(scene.world().changed::<Transform>().count(), 2);
+
+    scene.render(surface, camera).unwrap();
+    let tick = scene.world().tick();
+
+    // The world is advanced once per frame, no matter how many cameras are rendered.
+    for _ in 0..2 {
+        engine.graphics.swap_frames();
+        engine.graphics.advance().unwrap();
+        scene.render_cameras(&[(surface, camera), (surface, node)]).unwrap();
+        scene.render(surface, camera).unwrap();
+    }
+
+    assert_eq!(scene.world().tick(), tick + 2);
+    assert_eq!(scene.world().changed::<Transform>().count(), 0);
+
+    scene
+        .arena_mut::<Transform>()
+        .get_mut(node)
+        .unwrap()
+        .set_position([1.0, 0.0, 0.0]);
+    assert_eq!(scene.world().changed::<Transform>().collect::<Vec<_>>(), [node]);
+    let v = scene.world_transform(node).unwrap();
+    assert_eq!(v.position(), math::Vector3::new(1.0, 0.0, 0.0));
+
+    scene.delete_node(node).unwrap();
+    assert_eq!(scene.world().removed::<Transform>().collect::<Vec<_>>(), [node]);
+
+    engine.graphics.swap_frames();
+    engine.graphics.advance().unwrap();
+    scene.render(surface, camera).unwrap();
+    assert_eq!(scene.world().changed::<Transform>().count(), 0);
+    assert_eq!(scene.world().removed::<Transform>().count(), 0);
+}
+
+#[test]
+fn render_settings_uniforms() {
+    use crayon::graphics::UniformVariable;
+    use crayon::scene::{Fog, RenderDebugMode, RenderSettings};
+
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+
+    let empty: Option<&[u8]> = None;
+    let mut setup = MeshSetup::default();
+    setup.num_verts = 3;
+    setup.num_idxes = 3;
+    let mesh = video
+        .create_mesh(Location::unique(""), setup, empty, empty)
+        .unwrap();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let phong = factory::shader::phong(&video).unwrap();
+    let pbr = factory::shader::pbr(&video).unwrap();
+    let camera = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+
+    for &shader in &[phong, pbr] {
+        let material = scene.create_material(shader).unwrap();
+        scene.create_node(MeshRenderer {
+            mesh: mesh,
+            index: MeshIndex::All,
+            materials: vec![material],
+        });
+    }
+
+    let mut settings = RenderSettings::default();
+    settings.ambient = Color(0.5, 0.25, 0.0, 1.0);
+    settings.fog = Some(Fog {
+        color: Color::white(),
+        start: 1.0,
+        end: 10.0,
+    });
+    settings.exposure = 2.0;
+    settings.debug = RenderDebugMode::Normals;
+    scene.set_render_settings(settings).unwrap();
+
+    // The settings take effect since next frame.
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    engine.graphics.advance().unwrap();
+
+    let rx = video.capture_next_frame();
+    scene.render(surface, camera).unwrap();
+    engine.graphics.swap_frames();
+    engine.graphics.advance().unwrap();
+
+    let capture = rx.try_recv().unwrap();
+    assert_eq!(capture.tasks.len(), 2);
+
+    for task in &capture.tasks {
+        let uniform = |name: &str| {
+            task.uniforms
+                .iter()
+                .find(|v| v.0 == name)
+                .map(|v| v.1.clone())
+        };
+
+        let v = UniformVariable::Vector3f([0.5, 0.25, 0.0]);
+        assert_eq!(uniform("u_AmbientColor"), Some(v));
+        let v = UniformVariable::Vector3f([1.0, 1.0, 1.0]);
+        assert_eq!(uniform("u_FogColor"), Some(v));
+        let v = UniformVariable::Vector3f([1.0, 10.0, 1.0]);
+        assert_eq!(uniform("u_FogParams"), Some(v));
+        assert_eq!(uniform("u_Exposure"), Some(UniformVariable::F32(2.0)));
+        assert_eq!(uniform("u_DebugMode"), Some(UniformVariable::I32(1)));
+    }
+}
+
+#[test]
+fn render_settings_shadows() {
+    use crayon::scene::{RenderSettings, ShadowSetup};
+
+    let mut engine = Engine::new_headless(Settings::default()).unwrap();
+    let video = engine.graphics.shared();
+    let surface = video.create_surface(SurfaceSetup::default()).unwrap();
+
+    let mut scene = Scene::new(engine.context()).unwrap();
+    let camera = scene.create_node(Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0));
+
+    // There is no ambient term or shadow by default.
+    let mut settings = RenderSettings::default();
+    assert_eq!(settings.ambient, Color::black());
+    assert_eq!(settings.shadow, None);
+
+    let mut setup = ShadowSetup::default();
+    setup.resolution = 0;
+    settings.shadow = Some(setup);
+    assert!(scene.set_render_settings(settings).is_err());
+
+    settings.shadow = Some(ShadowSetup::default());
+    scene.set_render_settings(settings).unwrap();
+    assert_eq!(scene.render_settings().shadow, None);
+
+    // The shadow map is created along with the other settings since next frame.
+    let mut alive_textures = |scene: &mut Scene| {
+        engine.graphics.swap_frames();
+        engine.graphics.advance().unwrap();
+        scene.render(surface, camera).unwrap();
+        engine.graphics.swap_frames();
+        engine.graphics.advance().unwrap().alive_textures
+    };
+
+    assert_eq!(alive_textures(&mut scene), 1);
+    assert_eq!(scene.render_settings().shadow, Some(ShadowSetup::default()));
+
+    settings.shadow = None;
+    scene.set_render_settings(settings).unwrap();
+    assert_eq!(alive_textures(&mut scene), 0);
+}
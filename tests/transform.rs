@@ -3,6 +3,7 @@ extern crate approx;
 extern crate crayon;
 
 use crayon::prelude::*;
+use crayon::scene::TransformCache;
 
 pub fn build(world: &mut World) -> Entity {
     world
@@ -92,3 +93,118 @@ fn look_at() {
     let pos = Transform::transform_point(&tree, &arena, e1, v).unwrap();
     assert!(ulps_eq!(pos, math::Vector3::new(0.0, 0.0, -4.0)));
 }
+
+#[test]
+fn world_transform_cache() {
+    let mut world = World::new();
+    world.register::<Node>();
+    world.register::<Transform>();
+
+    let root = build(&mut world);
+    let mid = build(&mut world);
+    let leaf = build(&mut world);
+
+    {
+        let mut tree = world.arena_mut::<Node>();
+        let mut arena = world.arena_mut::<Transform>();
+        Node::set_parent(&mut tree, mid, Some(root)).unwrap();
+        Node::set_parent(&mut tree, leaf, Some(mid)).unwrap();
+        // root <- mid <- leaf
+
+        arena.get_mut(mid).unwrap().set_position([0.0, 1.0, 0.0]);
+        arena.get_mut(leaf).unwrap().set_position([0.0, 0.0, 1.0]);
+    }
+
+    // The nodes changed during current frame are recomputed on every read.
+    let mut cache = TransformCache::new();
+    let v = cache.world_transform(&world, leaf).unwrap();
+    assert_eq!(v.position(), math::Vector3::new(0.0, 1.0, 1.0));
+    assert_eq!(cache.updates(), 3);
+    cache.world_transform(&world, leaf).unwrap();
+    assert_eq!(cache.updates(), 6);
+
+    // Reading again in the following frames does not recompute anything.
+    world.advance();
+    cache.world_transform(&world, leaf).unwrap();
+    assert_eq!(cache.updates(), 9);
+
+    world.advance();
+    for _ in 0..3 {
+        let v = cache.world_transform(&world, leaf).unwrap();
+        assert_eq!(v.position(), math::Vector3::new(0.0, 1.0, 1.0));
+    }
+    assert_eq!(cache.updates(), 9);
+
+    // Moving the root invalidates all its descendants.
+    world
+        .arena_mut::<Transform>()
+        .get_mut(root)
+        .unwrap()
+        .set_position([2.0, 0.0, 0.0]);
+    world.advance();
+
+    for _ in 0..3 {
+        let v = cache.world_transform(&world, leaf).unwrap();
+        assert_eq!(v.position(), math::Vector3::new(2.0, 1.0, 1.0));
+    }
+    assert_eq!(cache.updates(), 12);
+
+    // The cached ancestors are reused.
+    let v = cache.world_transform(&world, mid).unwrap();
+    assert_eq!(v.position(), math::Vector3::new(2.0, 1.0, 0.0));
+    assert_eq!(cache.updates(), 12);
+
+    // Attaching to another parent invalidates the node too.
+    Node::set_parent(&mut world.arena_mut::<Node>(), leaf, Some(root)).unwrap();
+    let v = cache.world_transform(&world, leaf).unwrap();
+    assert_eq!(v.position(), math::Vector3::new(2.0, 0.0, 1.0));
+    assert_eq!(cache.updates(), 13);
+}
+
+#[test]
+pub fn serialization() {
+    let mut world = World::new();
+    world.register_serializable::<Node>();
+    world.register_serializable::<Transform>();
+
+    let e1 = build(&mut world);
+    let e2 = build(&mut world);
+    let e3 = build(&mut world);
+
+    {
+        let mut tree = world.arena_mut::<Node>();
+        Node::set_parent(&mut tree, e2, Some(e1)).unwrap();
+        Node::set_parent(&mut tree, e3, Some(e1)).unwrap();
+
+        let mut arena = world.arena_mut::<Transform>();
+        arena.get_mut(e2).unwrap().set_position([1.0, 2.0, 3.0]);
+        arena.get_mut(e3).unwrap().set_scale(2.0);
+    }
+
+    let mut bytes = Vec::new();
+    world.save(&mut bytes).unwrap();
+
+    let mut loaded = World::new();
+    loaded.register_serializable::<Node>();
+    loaded.register_serializable::<Transform>();
+    build(&mut loaded);
+
+    let report = loaded.load(&bytes[..]).unwrap();
+    assert!(report.skipped.is_empty());
+
+    let n1 = report.entities.get(e1).unwrap();
+    let n2 = report.entities.get(e2).unwrap();
+    let n3 = report.entities.get(e3).unwrap();
+
+    let tree = loaded.arena::<Node>();
+    assert!(tree.get(n1).unwrap().is_root());
+    assert_eq!(tree.get(n2).unwrap().parent(), Some(n1));
+    assert_eq!(tree.get(n3).unwrap().parent(), Some(n1));
+    assert!(Node::is_ancestor(&tree, n2, n1));
+    assert!(Node::is_ancestor(&tree, n3, n1));
+
+    let arena = loaded.arena::<Transform>();
+    let position = arena.get(n2).unwrap().position();
+    assert_eq!(position, math::Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(arena.get(n3).unwrap().scale(), 2.0);
+}